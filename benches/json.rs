@@ -0,0 +1,74 @@
+//! Cost of extracting a `Json<T>` from a representative 2 KB ingestion payload.
+//!
+//! Run it with and without the `simd-json` feature to compare the two parsers:
+//!
+//! ```text
+//! cargo bench --bench json
+//! cargo bench --bench json --features simd-json
+//! ```
+#![allow(missing_docs)] // `criterion_group!` generates undocumented functions.
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use futures_lite::future::block_on;
+use serde::Deserialize;
+use skyzen::{extract::Extractor, header, utils::Json, Body, Request};
+
+#[derive(Deserialize)]
+#[allow(dead_code)]
+struct Batch {
+    source: String,
+    sent_at: u64,
+    events: Vec<Event>,
+}
+
+#[derive(Deserialize)]
+#[allow(dead_code)]
+struct Event {
+    user_id: u64,
+    session: String,
+    kind: String,
+    path: String,
+    duration_ms: f64,
+    tags: Vec<String>,
+}
+
+fn payload() -> Vec<u8> {
+    let events = (0..12)
+        .map(|i| {
+            format!(
+                r#"{{"user_id":{},"session":"4f1c2a9e-{i:04}","kind":"page_view","path":"/products/{i}/reviews","duration_ms":{}.5,"tags":["web","eu-west","beta"]}}"#,
+                1_000_000 + i,
+                120 + i * 7,
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+    format!(r#"{{"source":"web-frontend","sent_at":1718000000,"events":[{events}]}}"#).into_bytes()
+}
+
+fn extract(payload: &[u8]) -> Json<Batch> {
+    let mut request = Request::new(Body::from_bytes(payload.to_vec()));
+    request.headers_mut().insert(
+        header::CONTENT_TYPE,
+        header::HeaderValue::from_static("application/json"),
+    );
+    block_on(Json::<Batch>::extract(&mut request)).unwrap()
+}
+
+fn json_extract(c: &mut Criterion) {
+    let payload = payload();
+    assert!((1800..2300).contains(&payload.len()), "{}", payload.len());
+
+    let name = if cfg!(feature = "simd-json") {
+        "extract_2kb/simd-json"
+    } else {
+        "extract_2kb/serde_json"
+    };
+    let mut group = c.benchmark_group("json");
+    group.throughput(Throughput::Bytes(payload.len() as u64));
+    group.bench_function(name, |b| b.iter(|| extract(&payload)));
+    group.finish();
+}
+
+criterion_group!(benches, json_extract);
+criterion_main!(benches);
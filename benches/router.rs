@@ -0,0 +1,76 @@
+//! Per-request cost of dispatching through a router: a plaintext route, a route with a path
+//! parameter, and a route wrapped in three middleware.
+#![allow(missing_docs)] // `criterion_group!` generates undocumented functions.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use futures_lite::future::block_on;
+use skyzen::{
+    routing::{CreateRouteNode, IntoRouteNode, Params, Route, Router},
+    utils::State,
+    Body, Request, Result,
+};
+
+/// Counts heap allocations so the benchmark can report allocations per request.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+fn router() -> Router {
+    Route::new((
+        "/plaintext".at(|| async { Result::Ok("Hello, World!") }),
+        "/hello/{name}"
+            .at(|params: Params| async move { Result::Ok(params.get("name")?.len().to_string()) }),
+        Route::new(("/ping".at(|| async { Result::Ok("pong") }),))
+            .middleware(State(1_u8))
+            .middleware(State(2_u16))
+            .middleware(State(3_u32))
+            .into_route_node(),
+    ))
+    .build()
+}
+
+fn dispatch(router: &Router, path: &'static str) {
+    let mut request = Request::new(Body::empty());
+    *request.uri_mut() = http::Uri::from_static(path);
+    let response = block_on(router.go(request)).unwrap();
+    assert!(response.status().is_success());
+}
+
+fn router_dispatch(c: &mut Criterion) {
+    let router = router();
+
+    for (name, path) in [
+        ("router/plaintext", "/plaintext"),
+        ("router/path_param", "/hello/world"),
+        ("router/three_middleware", "/ping"),
+    ] {
+        let before = ALLOCATIONS.load(Ordering::Relaxed);
+        dispatch(&router, path);
+        let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+        println!("{name}: {allocations} allocations per request");
+
+        c.bench_function(name, |b| b.iter(|| dispatch(&router, path)));
+    }
+}
+
+criterion_group!(benches, router_dispatch);
+criterion_main!(benches);
@@ -0,0 +1,46 @@
+//! Throughput of the SSE responder, with and without joining ready events into one chunk.
+#![allow(missing_docs)] // `criterion_group!` generates undocumented functions.
+
+use std::convert::Infallible;
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use futures_lite::{future::block_on, stream, StreamExt};
+use skyzen::{
+    responder::{sse::Event, Sse},
+    Body, Request, Responder, Response,
+};
+
+const EVENTS: usize = 10_000;
+
+fn drain(sse: Sse) -> usize {
+    let request = Request::new(Body::empty());
+    let mut response = Response::new(Body::empty());
+    sse.respond_to(&request, &mut response).unwrap();
+    block_on(async {
+        let mut body = response.into_body();
+        let mut bytes = 0;
+        while let Some(chunk) = body.next().await {
+            bytes += chunk.unwrap().len();
+        }
+        bytes
+    })
+}
+
+fn events() -> impl stream::Stream<Item = Result<Event, Infallible>> + Send + Sync + 'static {
+    stream::iter(0..EVENTS).map(|i| Ok(Event::data(format!("{{\"tick\":{i}}}"))))
+}
+
+fn sse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("sse");
+    group.throughput(Throughput::Elements(EVENTS as u64));
+    group.bench_function("chunk_per_event", |b| {
+        b.iter(|| drain(Sse::from_stream_batched(events(), 0)));
+    });
+    group.bench_function("batched", |b| {
+        b.iter(|| drain(Sse::from_stream(events())));
+    });
+    group.finish();
+}
+
+criterion_group!(benches, sse);
+criterion_main!(benches);
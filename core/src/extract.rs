@@ -1,16 +1,22 @@
 use core::mem;
-use core::{convert::Infallible, future::Future};
+use core::{
+    convert::Infallible,
+    fmt,
+    future::Future,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
 
 #[cfg(feature = "openapi")]
-use crate::openapi::{ExtractorSchema, SchemaRef};
-use alloc::boxed::Box;
+use crate::openapi::{ExtractorSchema, ParameterLocation, SchemaRef};
 #[cfg(feature = "openapi")]
 use alloc::collections::BTreeMap;
-use http_kit::error::BoxHttpError;
+use alloc::{string::String, vec::Vec};
 use http_kit::{
-    http_error,
-    utils::{ByteStr, Bytes},
-    Body, HttpError, Method, Request, StatusCode, Uri,
+    header,
+    middleware::MiddlewareError,
+    utils::{ByteStr, Bytes, Stream, StreamExt},
+    Body, BodyError, Endpoint, HttpError, Method, Middleware, Request, Response, StatusCode, Uri,
 };
 
 /// Extract a object from request,always is the header,body value,etc.
@@ -63,7 +69,17 @@ macro_rules! impl_tuple_extractor {
                 }
             }
 
-            impl <$($ty: Extractor),*>core::error::Error for TupleExtractorError<$($ty),*> {}
+            impl <$($ty: Extractor),*>core::error::Error for TupleExtractorError<$($ty),*> {
+                // The failing extractor's own error, so that it can be downcast.
+                #[allow(unused_variables)]
+                fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+                    match self {
+                        $(TupleExtractorError::$ty(e) => Some(e),)*
+                        #[allow(unreachable_patterns)]
+                        _ => unreachable!(),
+                    }
+                }
+            }
 
             impl <$($ty: Extractor),*>http_kit::HttpError for TupleExtractorError<$($ty),*> {
                 fn status(&self) -> http_kit::StatusCode {
@@ -106,13 +122,142 @@ macro_rules! impl_tuple_extractor {
 
 tuples!(impl_tuple_extractor);
 
-http_error!(pub InvalidBody, StatusCode::BAD_REQUEST, "Failed to read request body");
+/// Maximum size, in bytes, of the request body accepted by the raw body extractors
+/// ([`Bytes`], [`ByteStr`], [`String`] and [`Body`]).
+///
+/// Install it as middleware; it stores itself in the request extensions. Bodies that announce or
+/// turn out to be larger are rejected with `413 Payload Too Large`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BodyLimit(pub usize);
+
+impl Middleware for BodyLimit {
+    type Error = Infallible;
+    async fn handle<N: Endpoint>(
+        &mut self,
+        request: &mut Request,
+        mut next: N,
+    ) -> Result<Response, MiddlewareError<N::Error, Self::Error>> {
+        request.extensions_mut().insert(*self);
+        next.respond(request)
+            .await
+            .map_err(MiddlewareError::Endpoint)
+    }
+}
+
+/// An error occurred while reading the raw request body.
+#[derive(Debug)]
+pub enum BodyReadError {
+    /// The body was already taken by another extractor.
+    Consumed,
+    /// The body is larger than the configured [`BodyLimit`].
+    TooLarge(usize),
+    /// The body is not valid UTF-8.
+    InvalidUtf8,
+    /// Reading the body failed.
+    Read(BodyError),
+}
+
+impl fmt::Display for BodyReadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Consumed => f.write_str("Request body was already consumed"),
+            Self::TooLarge(limit) => write!(f, "Request body exceeds the limit of {limit} bytes"),
+            Self::InvalidUtf8 => f.write_str("Request body is not valid UTF-8"),
+            Self::Read(_) => f.write_str("Failed to read request body"),
+        }
+    }
+}
+
+impl core::error::Error for BodyReadError {}
+
+impl HttpError for BodyReadError {
+    fn status(&self) -> StatusCode {
+        match self {
+            Self::Consumed => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::TooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            Self::InvalidUtf8 | Self::Read(_) => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+/// Take the body out of the request, leaving a frozen body behind so that a second read fails
+/// instead of silently seeing an empty payload.
+fn take_body(request: &mut Request) -> Result<(Body, Option<usize>), BodyReadError> {
+    let limit = request.extensions().get::<BodyLimit>().map(|limit| limit.0);
+    if let Some(limit) = limit {
+        let announced = request
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok()?.parse::<usize>().ok());
+        if announced.is_some_and(|len| len > limit) {
+            return Err(BodyReadError::TooLarge(limit));
+        }
+    }
+
+    let body = mem::replace(request.body_mut(), Body::frozen());
+    if body.is_frozen() {
+        return Err(BodyReadError::Consumed);
+    }
+    if let (Some(limit), Some(len)) = (limit, body.len()) {
+        if len > limit {
+            return Err(BodyReadError::TooLarge(limit));
+        }
+    }
+    Ok((body, limit))
+}
+
+async fn read_body(request: &mut Request) -> Result<Bytes, BodyReadError> {
+    let (mut body, limit) = take_body(request)?;
+    let Some(limit) = limit else {
+        return body.into_bytes().await.map_err(BodyReadError::Read);
+    };
+
+    let mut buf = Vec::new();
+    while let Some(chunk) = body.next().await {
+        let chunk = chunk.map_err(BodyReadError::Read)?;
+        if buf.len() + chunk.len() > limit {
+            return Err(BodyReadError::TooLarge(limit));
+        }
+        buf.extend_from_slice(&chunk);
+    }
+    Ok(buf.into())
+}
+
+/// Stream adapter failing once more than `remaining` bytes went through.
+struct LimitedBody {
+    body: Body,
+    remaining: usize,
+}
+
+impl Stream for LimitedBody {
+    type Item = Result<Bytes, BodyError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        let chunk = match ready!(Pin::new(&mut this.body).poll_next(cx)) {
+            Some(Ok(chunk)) => chunk,
+            other => return Poll::Ready(other),
+        };
+        let Some(remaining) = this.remaining.checked_sub(chunk.len()) else {
+            let error = std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "request body exceeds the size limit",
+            );
+            return Poll::Ready(Some(Err(error.into())));
+        };
+        this.remaining = remaining;
+        Poll::Ready(Some(Ok(chunk)))
+    }
+}
 
+/// Buffers the whole request body.
+///
+/// The body can be consumed only once per request: any later body extractor fails with
+/// [`BodyReadError::Consumed`].
 impl Extractor for Bytes {
-    type Error = InvalidBody;
+    type Error = BodyReadError;
     async fn extract(request: &mut Request) -> Result<Self, Self::Error> {
-        let body = mem::replace(request.body_mut(), Body::empty());
-        body.into_bytes().await.map_err(|_| InvalidBody::new())
+        read_body(request).await
     }
 
     #[cfg(feature = "openapi")]
@@ -120,15 +265,20 @@ impl Extractor for Bytes {
         Some(ExtractorSchema {
             content_type: Some("application/octet-stream"),
             schema: None,
+            location: ParameterLocation::Body,
+            required: true,
+            extra_content_types: &[],
         })
     }
 }
 
+/// Buffers the whole request body and validates it as UTF-8.
+///
+/// The body can be consumed only once per request.
 impl Extractor for ByteStr {
-    type Error = InvalidBody;
+    type Error = BodyReadError;
     async fn extract(request: &mut Request) -> Result<Self, Self::Error> {
-        let body = mem::replace(request.body_mut(), Body::empty());
-        body.into_string().await.map_err(|_| InvalidBody::new())
+        Self::from_utf8(read_body(request).await?).map_err(|_| BodyReadError::InvalidUtf8)
     }
 
     #[cfg(feature = "openapi")]
@@ -136,14 +286,46 @@ impl Extractor for ByteStr {
         Some(ExtractorSchema {
             content_type: Some("text/plain; charset=utf-8"),
             schema: None,
+            location: ParameterLocation::Body,
+            required: true,
+            extra_content_types: &[],
         })
     }
 }
 
+/// Buffers the whole request body and validates it as UTF-8.
+///
+/// The body can be consumed only once per request.
+impl Extractor for String {
+    type Error = BodyReadError;
+    async fn extract(request: &mut Request) -> Result<Self, Self::Error> {
+        Self::from_utf8(Vec::from(read_body(request).await?))
+            .map_err(|_| BodyReadError::InvalidUtf8)
+    }
+
+    #[cfg(feature = "openapi")]
+    fn openapi() -> Option<ExtractorSchema> {
+        Some(ExtractorSchema {
+            content_type: Some("text/plain; charset=utf-8"),
+            schema: None,
+            location: ParameterLocation::Body,
+            required: true,
+            extra_content_types: &[],
+        })
+    }
+}
+
+/// Takes ownership of the request body for streaming consumption inside the handler.
+///
+/// The body can be consumed only once per request. Under a [`BodyLimit`], the returned body
+/// yields an error as soon as more than the limit has been read.
 impl Extractor for Body {
-    type Error = Infallible;
+    type Error = BodyReadError;
     async fn extract(request: &mut Request) -> Result<Self, Self::Error> {
-        Ok(mem::replace(request.body_mut(), Self::empty()))
+        match take_body(request)? {
+            (body, None) => Ok(body),
+            (body, Some(remaining)) => Ok(Self::from_stream(LimitedBody { body, remaining })),
+        }
     }
 
     #[cfg(feature = "openapi")]
@@ -151,6 +333,9 @@ impl Extractor for Body {
         Some(ExtractorSchema {
             content_type: Some("application/octet-stream"),
             schema: None,
+            location: ParameterLocation::Body,
+            required: true,
+            extra_content_types: &[],
         })
     }
 }
@@ -169,6 +354,7 @@ impl Extractor for Method {
     }
 }
 
+/// Returns `None` instead of failing the request when `T` cannot be extracted.
 impl<T: Extractor> Extractor for Option<T> {
     type Error = Infallible;
     async fn extract(request: &mut Request) -> Result<Self, Self::Error> {
@@ -177,7 +363,10 @@ impl<T: Extractor> Extractor for Option<T> {
 
     #[cfg(feature = "openapi")]
     fn openapi() -> Option<ExtractorSchema> {
-        T::openapi()
+        T::openapi().map(|schema| ExtractorSchema {
+            required: false,
+            ..schema
+        })
     }
 
     #[cfg(feature = "openapi")]
@@ -186,13 +375,11 @@ impl<T: Extractor> Extractor for Option<T> {
     }
 }
 
-// Let's erase the error for Result<T,E>, otherwise user have to deal with double error types.
-impl<T: Extractor> Extractor for Result<T, BoxHttpError> {
+/// Hands the extraction error of `T` to the handler so it can recover on its own terms.
+impl<T: Extractor> Extractor for Result<T, T::Error> {
     type Error = Infallible;
     async fn extract(request: &mut Request) -> Result<Self, Self::Error> {
-        Ok(T::extract(request)
-            .await
-            .map_err(|e| Box::new(e) as BoxHttpError))
+        Ok(T::extract(request).await)
     }
 
     #[cfg(feature = "openapi")]
@@ -205,3 +392,84 @@ impl<T: Extractor> Extractor for Result<T, BoxHttpError> {
         T::register_openapi_schemas(defs);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{BodyLimit, BodyReadError, Extractor};
+    use alloc::{string::String, vec, vec::Vec};
+    use http_kit::{
+        utils::{future::block_on, stream, Bytes, StreamExt},
+        Body, BodyError, HttpError, Request, StatusCode,
+    };
+
+    fn chunked(chunks: Vec<&'static [u8]>) -> Body {
+        Body::from_stream(stream::iter(chunks.into_iter().map(Ok::<_, BodyError>)))
+    }
+
+    fn request(body: Body, limit: Option<usize>) -> Request {
+        let mut request = Request::new(body);
+        if let Some(limit) = limit {
+            request.extensions_mut().insert(BodyLimit(limit));
+        }
+        request
+    }
+
+    #[test]
+    fn buffers_raw_bodies_once() {
+        block_on(async {
+            let mut req = request(chunked(vec![b"sig", b"ned"]), None);
+            assert_eq!(Bytes::extract(&mut req).await.unwrap(), "signed");
+            assert!(matches!(
+                Bytes::extract(&mut req).await,
+                Err(BodyReadError::Consumed)
+            ));
+
+            let mut req = request(Body::from_bytes("héllo"), None);
+            assert_eq!(String::extract(&mut req).await.unwrap(), "héllo");
+
+            let mut req = request(Body::from_bytes(vec![0xff, 0xfe]), None);
+            let error = String::extract(&mut req).await.unwrap_err();
+            assert_eq!(error.status(), StatusCode::BAD_REQUEST);
+        });
+    }
+
+    #[test]
+    fn enforces_the_body_limit() {
+        block_on(async {
+            let mut req = request(Body::from_bytes("12345"), Some(5));
+            assert_eq!(Bytes::extract(&mut req).await.unwrap(), "12345");
+
+            let mut req = request(Body::from_bytes("123456"), Some(5));
+            let error = String::extract(&mut req).await.unwrap_err();
+            assert_eq!(error.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+            let mut req = request(chunked(vec![b"123", b"456"]), Some(5));
+            assert!(matches!(
+                Bytes::extract(&mut req).await,
+                Err(BodyReadError::TooLarge(5))
+            ));
+
+            let mut req = request(chunked(vec![b"1"]), Some(5));
+            req.headers_mut()
+                .insert(http_kit::header::CONTENT_LENGTH, "64".parse().unwrap());
+            assert!(matches!(
+                Body::extract(&mut req).await,
+                Err(BodyReadError::TooLarge(5))
+            ));
+        });
+    }
+
+    #[test]
+    fn limits_streamed_bodies() {
+        block_on(async {
+            let mut req = request(chunked(vec![b"123", b"456"]), Some(5));
+            let mut body = Body::extract(&mut req).await.unwrap();
+            assert_eq!(body.next().await.unwrap().unwrap(), "123");
+            assert!(body.next().await.unwrap().is_err());
+
+            let mut req = request(chunked(vec![b"123", b"45"]), Some(5));
+            let body = Body::extract(&mut req).await.unwrap();
+            assert_eq!(body.into_bytes().await.unwrap(), "12345");
+        });
+    }
+}
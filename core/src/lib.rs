@@ -10,11 +10,11 @@ extern crate std;
 mod macros;
 
 mod extract;
-pub use extract::Extractor;
+pub use extract::{BodyLimit, BodyReadError, Extractor};
 mod responder;
 pub use responder::Responder;
 mod server;
-pub use server::Server;
+pub use server::{HttpConfig, Server};
 #[cfg(feature = "openapi")]
 pub mod openapi;
 
@@ -30,49 +30,84 @@ pub mod error {
     // Since `error[E0119]`, we have to wrap `http-kit`'s `Error` here.
     pub use http_kit::error::{BoxHttpError, HttpError};
 
-    use http_kit::{Error as HttpKitError, StatusCode};
+    use http_kit::{
+        header::{HeaderMap, HeaderName, HeaderValue},
+        Error as HttpKitError, StatusCode,
+    };
 
     /// A concrete error type for HTTP operations.
-    pub struct Error(http_kit::error::Error);
+    pub struct Error {
+        inner: HttpKitError,
+        headers: HeaderMap,
+    }
 
     impl Debug for Error {
         fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-            Debug::fmt(&self.0, f)
+            Debug::fmt(&self.inner, f)
         }
     }
 
     impl Display for Error {
         fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-            Display::fmt(&self.0, f)
+            Display::fmt(&self.inner, f)
         }
     }
 
     impl Error {
         /// Create a new error from any standard error type.
         pub fn new(e: impl Into<eyre::Report>) -> Self {
-            Self(HttpKitError::new(e))
+            HttpKitError::new(e).into()
         }
 
         /// Create a new error with a custom message.
         pub fn msg(msg: impl Display + Send + Sync + Debug + 'static) -> Self {
-            Self(HttpKitError::msg(msg))
+            HttpKitError::msg(msg).into()
         }
 
         /// Consume the error and return the inner `eyre::Report`.
         pub fn into_inner(self) -> eyre::Report {
-            self.0.into_inner()
+            self.inner.into_inner()
         }
 
         /// Convert this error into a boxed HTTP error trait object.
+        ///
+        /// Headers added with [`with_header`](Self::with_header) are kept, see [`ErrorHeaders`].
         #[must_use]
         pub fn into_boxed_http_error(self) -> BoxHttpError {
-            self.0.into_boxed_http_error()
+            let error = self.inner.into_boxed_http_error();
+            if self.headers.is_empty() {
+                error
+            } else {
+                Box::new(WithHeaders {
+                    error,
+                    headers: self.headers,
+                })
+            }
         }
 
         /// Set the HTTP status code for this error.
         #[must_use]
         pub fn set_status(self, status: StatusCode) -> Self {
-            Self(self.0.set_status(status))
+            Self {
+                inner: self.inner.set_status(status),
+                headers: self.headers,
+            }
+        }
+
+        /// Add a header to the error response, such as `WWW-Authenticate` for a `401` or
+        /// `Retry-After` for a `429`.
+        ///
+        /// Adding a header twice sends both values.
+        #[must_use]
+        pub fn with_header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+            self.headers.append(name, value);
+            self
+        }
+    }
+
+    impl ErrorHeaders for Error {
+        fn headers(&self) -> Option<HeaderMap> {
+            (!self.headers.is_empty()).then(|| self.headers.clone())
         }
     }
 
@@ -81,7 +116,90 @@ pub mod error {
         T: Into<HttpKitError>,
     {
         fn from(value: T) -> Self {
-            Self(value.into())
+            Self {
+                inner: value.into(),
+                headers: HeaderMap::new(),
+            }
+        }
+    }
+
+    /// Response headers that belong to an error, such as `WWW-Authenticate` for a `401`.
+    ///
+    /// [`HttpError`] only exposes a status, so headers travel in a wrapper created by
+    /// [`into_boxed_with_headers`](Self::into_boxed_with_headers) or
+    /// [`Error::into_boxed_http_error`]. The implementation for `dyn HttpError` finds that wrapper
+    /// behind boxes and error sources, which is how the router copies the headers onto the
+    /// error response. Middleware attached to routes passes errors through as they are; an
+    /// http-kit `MiddlewareError` would hide the wrapper, since its `source` skips the error it
+    /// holds.
+    pub trait ErrorHeaders {
+        /// Headers to send with the error response, `None` if there are none.
+        fn headers(&self) -> Option<HeaderMap> {
+            None
+        }
+
+        /// Box this error, keeping its [`headers`](Self::headers).
+        fn into_boxed_with_headers(self) -> BoxHttpError
+        where
+            Self: HttpError + Sized,
+        {
+            match self.headers() {
+                Some(headers) => with_headers(self, headers),
+                None => Box::new(self),
+            }
+        }
+    }
+
+    /// Box `error` together with `headers` to send with its response, see [`ErrorHeaders`].
+    pub fn with_headers(error: impl HttpError, headers: HeaderMap) -> BoxHttpError {
+        Box::new(WithHeaders {
+            error: Box::new(error),
+            headers,
+        })
+    }
+
+    impl ErrorHeaders for dyn HttpError {
+        fn headers(&self) -> Option<HeaderMap> {
+            let mut error: &(dyn core::error::Error + 'static) = self;
+            loop {
+                if let Some(with_headers) = error.downcast_ref::<WithHeaders>() {
+                    return Some(with_headers.headers.clone());
+                }
+                error = match error.downcast_ref::<BoxHttpError>() {
+                    Some(boxed) => &**boxed,
+                    None => error.source()?,
+                };
+            }
+        }
+    }
+
+    /// A boxed error together with the headers of its response.
+    struct WithHeaders {
+        error: BoxHttpError,
+        headers: HeaderMap,
+    }
+
+    impl Debug for WithHeaders {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            Debug::fmt(&self.error, f)
+        }
+    }
+
+    impl Display for WithHeaders {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            Display::fmt(&self.error, f)
+        }
+    }
+
+    impl core::error::Error for WithHeaders {
+        fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+            (*self.error).source()
+        }
+    }
+
+    impl HttpError for WithHeaders {
+        fn status(&self) -> StatusCode {
+            self.error.status()
         }
     }
 
@@ -16,6 +16,7 @@ macro_rules! tuples {
         $macro!(T0, T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12);
         $macro!(T0, T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12, T13);
         $macro!(T0, T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12, T13, T14);
+        $macro!(T0, T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12, T13, T14, T15);
     };
 }
 
@@ -41,6 +42,7 @@ macro_rules! impl_base_responder {
                         description: None,
                         schema: None,
                         content_type: Some("application/octet-stream"),
+                        example: None,
                     }])
                 }
             }
@@ -70,6 +72,7 @@ macro_rules! impl_base_utf8_responder {
                         description: None,
                         schema: Some(crate::openapi::plain_string_schema()),
                         content_type: Some("text/plain; charset=utf-8"),
+                        example: None,
                     }])
                 }
             }
@@ -19,6 +19,29 @@ pub struct ExtractorSchema {
     pub content_type: Option<&'static str>,
     /// JSON schema describing the extractor payload.
     pub schema: Option<SchemaRef>,
+    /// Part of the request the extractor reads.
+    pub location: ParameterLocation,
+    /// Whether the request must carry the parameter; `false` for optional extractors.
+    pub required: bool,
+    /// Further content types accepted for the same payload, documented next to `content_type`.
+    pub extra_content_types: &'static [&'static str],
+}
+
+/// Part of the request an extractor argument is read from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ParameterLocation {
+    /// The request body, aggregated per content type.
+    #[default]
+    Body,
+    /// A segment captured by the route template, such as `{id}` in `/users/{id}`.
+    ///
+    /// One path parameter is documented per template segment, typed from the matching schema
+    /// property when the extractor provides an object schema.
+    Path,
+    /// The query string, documented as one query parameter per schema property.
+    Query,
+    /// A request header, documented as a header parameter with this name.
+    Header(&'static str),
 }
 
 /// Schema information captured for a responder.
@@ -32,6 +55,8 @@ pub struct ResponseSchema {
     pub schema: Option<SchemaRef>,
     /// Content type returned by the responder, if known.
     pub content_type: Option<&'static str>,
+    /// Example payload, parsed as JSON when possible and used verbatim otherwise.
+    pub example: Option<&'static str>,
 }
 
 impl fmt::Debug for ExtractorSchema {
@@ -39,6 +64,9 @@ impl fmt::Debug for ExtractorSchema {
         f.debug_struct("ExtractorSchema")
             .field("content_type", &self.content_type)
             .field("has_schema", &self.schema.is_some())
+            .field("location", &self.location)
+            .field("required", &self.required)
+            .field("extra_content_types", &self.extra_content_types)
             .finish()
     }
 }
@@ -50,6 +78,7 @@ impl fmt::Debug for ResponseSchema {
             .field("description", &self.description)
             .field("content_type", &self.content_type)
             .field("has_schema", &self.schema.is_some())
+            .field("example", &self.example)
             .finish()
     }
 }
@@ -146,6 +146,7 @@ impl Responder for Response {
             description: None,
             schema: None,
             content_type: None,
+            example: None,
         }])
     }
 }
@@ -172,6 +173,7 @@ impl<T: Responder, E: HttpError> Responder for core::result::Result<T, E> {
             description: None,
             schema: None,
             content_type: None,
+            example: None,
         });
         if schemas.is_empty() {
             None
@@ -230,6 +232,7 @@ impl Responder for HeaderMap {
             description: None,
             schema: None,
             content_type: None,
+            example: None,
         }])
     }
 }
@@ -249,6 +252,7 @@ impl Responder for (HeaderName, HeaderValue) {
             description: None,
             schema: None,
             content_type: None,
+            example: None,
         }])
     }
 }
@@ -1,4 +1,4 @@
-use core::{error::Error, future::Future};
+use core::{error::Error, future::Future, time::Duration};
 use executor_core::Executor;
 use http_kit::{
     utils::{AsyncRead, AsyncWrite, Stream},
@@ -22,3 +22,47 @@ pub trait Server {
         C: Unpin + Send + AsyncRead + AsyncWrite + 'static,
         E: Error;
 }
+
+/// Connection-level HTTP/1 and HTTP/2 tuning shared by the server backends.
+///
+/// Every field defaults to `None`, which leaves the backend's own default in place.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HttpConfig {
+    /// Whether HTTP/1 connections are kept alive between requests (hyper default: `true`).
+    pub http1_keep_alive: Option<bool>,
+    /// How long a client may take to send a complete HTTP/1 request head.
+    ///
+    /// Connections that exceed it are closed. Unset means no timeout.
+    pub http1_header_read_timeout: Option<Duration>,
+    /// Maximum size of the HTTP/1 read buffer, which bounds the request head size.
+    ///
+    /// hyper requires at least 8192 bytes and panics on smaller values.
+    pub http1_max_buf_size: Option<usize>,
+    /// Maximum number of headers accepted in an HTTP/1 request (hyper default: 100).
+    pub http1_max_headers: Option<usize>,
+    /// Maximum number of concurrent HTTP/2 streams per connection.
+    pub http2_max_concurrent_streams: Option<u32>,
+    /// Initial HTTP/2 stream-level flow control window, in bytes.
+    pub http2_initial_stream_window_size: Option<u32>,
+    /// Initial HTTP/2 connection-level flow control window, in bytes.
+    pub http2_initial_connection_window_size: Option<u32>,
+    /// Whether to use adaptive HTTP/2 flow control based on the measured bandwidth-delay product.
+    pub http2_adaptive_window: Option<bool>,
+}
+
+impl HttpConfig {
+    /// A configuration that keeps every backend default.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            http1_keep_alive: None,
+            http1_header_read_timeout: None,
+            http1_max_buf_size: None,
+            http1_max_headers: None,
+            http2_max_concurrent_streams: None,
+            http2_initial_stream_window_size: None,
+            http2_initial_connection_window_size: None,
+            http2_adaptive_window: None,
+        }
+    }
+}
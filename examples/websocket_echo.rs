@@ -32,6 +32,9 @@ async fn websocket_echo(upgrade: WebSocketUpgrade) -> impl Responder {
                 _ => {}
             }
         }
+        if let Some(frame) = socket.close_reason() {
+            println!("Client closed with {}: {}", frame.code, frame.reason);
+        }
     })
 }
 
@@ -0,0 +1,70 @@
+//! Applies [`HttpConfig`] to hyper's connection builders.
+
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use hyper::server::conn::{http1::Builder as Http1Builder, http2::Builder as Http2Builder};
+use skyzen_core::HttpConfig;
+
+/// Apply the HTTP/1 options that are set in `config`.
+///
+/// Shared with skyzen's built-in runtime, so both servers honor [`HttpConfig`] the same way.
+pub fn configure_http1(builder: &mut Http1Builder, config: &HttpConfig) {
+    if let Some(keep_alive) = config.http1_keep_alive {
+        builder.keep_alive(keep_alive);
+    }
+    // hyper only enforces the header timeout once a timer is installed, so leave it out otherwise.
+    if let Some(timeout) = config.http1_header_read_timeout {
+        builder.timer(AsyncIoTimer).header_read_timeout(timeout);
+    }
+    if let Some(max) = config.http1_max_buf_size {
+        builder.max_buf_size(max);
+    }
+    if let Some(max) = config.http1_max_headers {
+        builder.max_headers(max);
+    }
+}
+
+/// Apply the HTTP/2 options that are set in `config`.
+pub fn configure_http2<E>(builder: &mut Http2Builder<E>, config: &HttpConfig) {
+    if let Some(max) = config.http2_max_concurrent_streams {
+        builder.max_concurrent_streams(max);
+    }
+    if let Some(size) = config.http2_initial_stream_window_size {
+        builder.initial_stream_window_size(size);
+    }
+    if let Some(size) = config.http2_initial_connection_window_size {
+        builder.initial_connection_window_size(size);
+    }
+    if let Some(enabled) = config.http2_adaptive_window {
+        builder.adaptive_window(enabled);
+    }
+}
+
+/// Runtime-agnostic [`hyper::rt::Timer`] driven by the `async-io` reactor.
+#[derive(Debug, Clone, Copy)]
+struct AsyncIoTimer;
+
+impl hyper::rt::Timer for AsyncIoTimer {
+    fn sleep(&self, duration: Duration) -> Pin<Box<dyn hyper::rt::Sleep>> {
+        Box::pin(AsyncIoSleep(async_io::Timer::after(duration)))
+    }
+
+    fn sleep_until(&self, deadline: Instant) -> Pin<Box<dyn hyper::rt::Sleep>> {
+        Box::pin(AsyncIoSleep(async_io::Timer::at(deadline)))
+    }
+}
+
+struct AsyncIoSleep(async_io::Timer);
+
+impl Future for AsyncIoSleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        Pin::new(&mut self.0).poll(cx).map(drop)
+    }
+}
+
+impl hyper::rt::Sleep for AsyncIoSleep {}
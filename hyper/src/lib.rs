@@ -6,20 +6,38 @@ use core::future::Future;
 use executor_core::{AnyExecutor, Executor, Task};
 use http_kit::utils::{AsyncRead, AsyncReadExt, AsyncWrite, Stream, StreamExt};
 use hyper::server::conn::{http1::Builder as Http1Builder, http2::Builder as Http2Builder};
-use skyzen_core::{Endpoint, Server};
+use skyzen_core::{Endpoint, HttpConfig, Server};
 use std::pin::Pin;
 use std::ptr;
 use std::sync::Arc;
 use std::task::{Context, Poll};
 use tracing::error;
 
+mod config;
 mod service;
+pub use config::{configure_http1, configure_http2};
 pub use service::IntoService;
 
-/// Hyper-based [`Server`] implementation.
+/// Hyper-based [`Server`] implementation using hyper's default connection options.
+///
+/// Use [`Hyper::with_config`] to tune HTTP/1 and HTTP/2 connections.
 #[derive(Debug, Default, Clone, Copy)]
 pub struct Hyper;
 
+impl Hyper {
+    /// Create a backend that applies `config` to every HTTP/1 and HTTP/2 connection.
+    #[must_use]
+    pub const fn with_config(config: HttpConfig) -> ConfiguredHyper {
+        ConfiguredHyper { config }
+    }
+}
+
+/// A [`Hyper`] backend with connection options, created by [`Hyper::with_config`].
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ConfiguredHyper {
+    config: HttpConfig,
+}
+
 struct ExecutorWrapper<E>(Arc<E>);
 
 impl<E> ExecutorWrapper<E> {
@@ -158,6 +176,23 @@ impl<C: AsyncWrite + Unpin> AsyncWrite for Prefixed<C> {
 }
 
 impl Server for Hyper {
+    async fn serve<C, E>(
+        self,
+        executor: impl executor_core::Executor + 'static,
+        error_handler: impl Fn(E) + Send + Sync + 'static,
+        connections: impl Stream<Item = Result<C, E>> + Unpin + Send + 'static,
+        endpoint: impl Endpoint + Sync + Clone + 'static,
+    ) where
+        C: Unpin + Send + AsyncRead + AsyncWrite + 'static,
+        E: std::error::Error,
+    {
+        Self::with_config(HttpConfig::new())
+            .serve(executor, error_handler, connections, endpoint)
+            .await;
+    }
+}
+
+impl Server for ConfiguredHyper {
     async fn serve<C, E>(
         self,
         executor: impl executor_core::Executor + 'static,
@@ -173,6 +208,7 @@ impl Server for Hyper {
         let executor = Arc::new(executor);
         let hyper_executor = ExecutorWrapper::new(executor.clone());
         let shared_executor: Arc<AnyExecutor> = Arc::new(AnyExecutor::new(executor.clone()));
+        let config = self.config;
         while let Some(connection) = connections.next().await {
             match connection {
                 Ok(connection) => {
@@ -191,7 +227,8 @@ impl Server for Hyper {
                             };
 
                         if is_h2 {
-                            let builder = Http2Builder::new(hyper_executor);
+                            let mut builder = Http2Builder::new(hyper_executor);
+                            configure_http2(&mut builder, &config);
                             let service = IntoService::new(endpoint, shared_executor);
                             if let Err(error) = builder
                                 .serve_connection(ConnectionWrapper(connection), service)
@@ -200,7 +237,8 @@ impl Server for Hyper {
                                 error!("Failed to serve Hyper h2 connection: {error}");
                             }
                         } else {
-                            let builder = Http1Builder::new();
+                            let mut builder = Http1Builder::new();
+                            configure_http1(&mut builder, &config);
                             let service = IntoService::new(endpoint, shared_executor);
                             if let Err(error) = builder
                                 .serve_connection(ConnectionWrapper(connection), service)
@@ -243,8 +281,9 @@ where
 
 #[cfg(test)]
 mod tests {
-    use super::sniff_protocol;
+    use super::{sniff_protocol, Hyper};
     use http_kit::utils::{AsyncRead, AsyncReadExt, AsyncWrite};
+    use skyzen_core::{HttpConfig, Server};
     use std::collections::VecDeque;
     use std::io;
     use std::pin::Pin;
@@ -324,9 +363,22 @@ mod tests {
         out
     }
 
+    #[test]
+    fn unit_and_configured_backends_are_servers() {
+        const fn assert_server<S: Server>(_: &S) {}
+        assert_server(&Hyper);
+        let mut config = HttpConfig::new();
+        config.http2_max_concurrent_streams = Some(64);
+        assert_server(&Hyper::with_config(config));
+    }
+
     #[tokio::test]
     async fn detects_split_h2_preface() {
-        let chunks = vec![PREFACE[..4].to_vec(), PREFACE[4..9].to_vec(), PREFACE[9..].to_vec()];
+        let chunks = vec![
+            PREFACE[..4].to_vec(),
+            PREFACE[4..9].to_vec(),
+            PREFACE[9..].to_vec(),
+        ];
         let stream = ChunkedStream::new(chunks);
 
         let (_prefixed, is_h2) = sniff_protocol(stream, PREFACE).await.unwrap();
@@ -336,7 +388,11 @@ mod tests {
     #[tokio::test]
     async fn preserves_bytes_on_mismatch() {
         let payload = b"GET / HTTP/1.1\r\n\r\n".to_vec();
-        let chunks = vec![payload[..2].to_vec(), payload[2..8].to_vec(), payload[8..].to_vec()];
+        let chunks = vec![
+            payload[..2].to_vec(),
+            payload[2..8].to_vec(),
+            payload[8..].to_vec(),
+        ];
         let stream = ChunkedStream::new(chunks);
 
         let (prefixed, is_h2) = sniff_protocol(stream, PREFACE).await.unwrap();
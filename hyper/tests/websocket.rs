@@ -4,7 +4,10 @@ use async_tungstenite::{
     client_async,
     tokio::TokioAdapter,
     tungstenite::{
-        client::IntoClientRequest, handshake::client::Response as ClientResponse, Message,
+        client::IntoClientRequest,
+        handshake::client::Response as ClientResponse,
+        protocol::{frame::coding::CloseCode, CloseFrame},
+        Message,
     },
     WebSocketStream,
 };
@@ -13,12 +16,14 @@ use futures_util::StreamExt;
 use hyper::header::SEC_WEBSOCKET_PROTOCOL;
 use hyper::server::conn::http1;
 use skyzen::{
-    routing::{CreateRouteNode, Route},
-    websocket::WebSocketUpgrade,
+    routing::{CreateRouteNode, Params, Route},
+    utils::State,
+    websocket::{Broadcast, WebSocketUpgrade},
 };
 use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context, Poll};
+use std::time::Duration;
 use tokio::io::duplex;
 
 type Error = Box<dyn std::any::Any + Send>;
@@ -348,3 +353,220 @@ async fn websocket_binary_convenience_methods() {
     handle.abort();
     let _ = handle.await;
 }
+
+#[tokio::test]
+async fn websocket_pings_quiet_connections() {
+    let (mut client, _, handle) = spawn_router(
+        Route::new(("/ping".at(|upgrade: WebSocketUpgrade| async move {
+            upgrade
+                .ping_interval(Duration::from_millis(50))
+                .on_upgrade(|mut socket| async move { while socket.next().await.is_some() {} })
+        }),)),
+        "ws://localhost/ping",
+    )
+    .await;
+
+    let frame = tokio::time::timeout(Duration::from_secs(2), client.next())
+        .await
+        .expect("no ping within timeout")
+        .expect("missing frame")
+        .expect("websocket frame");
+    assert!(matches!(frame, Message::Ping(_)), "{frame:?}");
+
+    let _ = client.close(None).await;
+    handle.abort();
+    let _ = handle.await;
+}
+
+#[tokio::test]
+async fn websocket_closes_idle_connections() {
+    let (mut client, _, handle) = spawn_router(
+        Route::new(("/idle".at(|upgrade: WebSocketUpgrade| async move {
+            upgrade
+                .idle_timeout(Duration::from_millis(100))
+                .on_upgrade(|mut socket| async move { while socket.next().await.is_some() {} })
+        }),)),
+        "ws://localhost/idle",
+    )
+    .await;
+
+    let frame = tokio::time::timeout(Duration::from_secs(2), client.next())
+        .await
+        .expect("connection was not closed")
+        .expect("missing frame")
+        .expect("websocket frame");
+    let Message::Close(Some(close)) = frame else {
+        panic!("expected a close frame, got {frame:?}");
+    };
+    assert_eq!(close.code, CloseCode::Away);
+
+    handle.abort();
+    let _ = handle.await;
+}
+
+#[tokio::test]
+async fn websocket_pongs_keep_the_connection_alive() {
+    let (mut client, _, handle) = spawn_router(
+        Route::new(("/alive".at(|upgrade: WebSocketUpgrade| async move {
+            upgrade
+                .ping_interval(Duration::from_millis(30))
+                .idle_timeout(Duration::from_millis(150))
+                .on_upgrade(|mut socket| async move {
+                    while let Some(Ok(message)) = socket.next().await {
+                        if let Some(text) = message.into_text() {
+                            let _ = socket.send_text(text).await;
+                        }
+                    }
+                })
+        }),)),
+        "ws://localhost/alive",
+    )
+    .await;
+
+    // Reading lets the client answer every ping with a pong.
+    let deadline = tokio::time::Instant::now() + Duration::from_millis(400);
+    while let Ok(frame) = tokio::time::timeout_at(deadline, client.next()).await {
+        let frame = frame.expect("missing frame").expect("websocket frame");
+        assert!(matches!(frame, Message::Ping(_)), "{frame:?}");
+    }
+
+    client
+        .send(Message::text("still here"))
+        .await
+        .expect("send message");
+    let reply = loop {
+        let frame = client.next().await.expect("missing reply").unwrap();
+        if let Message::Text(text) = frame {
+            break text;
+        }
+    };
+    assert_eq!(reply, "still here");
+
+    let _ = client.close(None).await;
+    handle.abort();
+    let _ = handle.await;
+}
+
+#[tokio::test]
+async fn websocket_exposes_the_peer_close_frame() {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    let tx = Arc::new(std::sync::Mutex::new(Some(tx)));
+    let (mut client, _, handle) = spawn_router(
+        Route::new(("/close".ws(move |mut socket| {
+            let tx = tx.lock().unwrap().take();
+            async move {
+                while let Some(Ok(_)) = socket.next().await {}
+                if let Some(tx) = tx {
+                    let _ = tx.send(socket.close_reason());
+                }
+            }
+        }),)),
+        "ws://localhost/close",
+    )
+    .await;
+
+    client
+        .close(Some(CloseFrame {
+            code: CloseCode::from(4000),
+            reason: "client shutdown".into(),
+        }))
+        .await
+        .expect("send close frame");
+
+    let frame = tokio::time::timeout(Duration::from_secs(2), rx)
+        .await
+        .expect("handler did not finish")
+        .expect("handler dropped the channel")
+        .expect("missing close frame");
+    assert_eq!(frame.code, 4000);
+    assert_eq!(frame.reason, "client shutdown");
+
+    handle.abort();
+    let _ = handle.await;
+}
+
+#[tokio::test]
+async fn websocket_broadcast_relays_between_peers() {
+    let hub = Broadcast::new(16);
+    let route = || {
+        let hub = hub.clone();
+        Route::new(("/chat".ws(move |socket| hub.clone().handle(socket)),))
+    };
+    let (mut alice, _, alice_handle) = spawn_router(route(), "ws://localhost/chat").await;
+    let (mut bob, _, bob_handle) = spawn_router(route(), "ws://localhost/chat").await;
+
+    // Both peers are subscribed once the hub sees them.
+    while hub.receiver_count() < 2 {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+
+    alice
+        .send(Message::text("hi bob"))
+        .await
+        .expect("send message");
+    let received = bob.next().await.expect("missing frame").unwrap();
+    assert_eq!(received.into_text().unwrap(), "hi bob");
+    let echoed = alice.next().await.expect("missing frame").unwrap();
+    assert_eq!(echoed.into_text().unwrap(), "hi bob");
+
+    bob.send(Message::text("hi alice"))
+        .await
+        .expect("send message");
+    let received = alice.next().await.expect("missing frame").unwrap();
+    assert_eq!(received.into_text().unwrap(), "hi alice");
+
+    let _ = alice.close(None).await;
+    let _ = bob.close(None).await;
+    alice_handle.abort();
+    bob_handle.abort();
+}
+
+#[tokio::test]
+async fn websocket_handlers_receive_extractors() {
+    let (mut client, _, handle) = spawn_router(
+        Route::new((
+            "/rooms/{id}".ws_with(|params: Params, mut socket| async move {
+                let id = params.get("id").unwrap_or("unknown").to_owned();
+                let _ = socket.send_text(format!("joined {id}")).await;
+            }),
+        )),
+        "ws://localhost/rooms/42",
+    )
+    .await;
+
+    let first = client
+        .next()
+        .await
+        .expect("missing first frame")
+        .expect("websocket frame");
+    assert_eq!(first.into_text().unwrap(), "joined 42");
+
+    let _ = client.close(None).await;
+    handle.abort();
+    let _ = handle.await;
+}
+
+#[tokio::test]
+async fn websocket_extractor_failures_reject_the_upgrade() {
+    let router =
+        Route::new(("/private".ws_with(|_: State<String>, _socket| async move {}),)).build();
+    let (client_stream, server_stream) = duplex(1024);
+    let handle = tokio::spawn(async move {
+        let service = skyzen_hyper::IntoService::new(router, create_executor());
+        let _ = http1::Builder::new()
+            .serve_connection(TokioIo(server_stream), service)
+            .with_upgrades()
+            .await;
+    });
+
+    let error = client_async("ws://localhost/private", TokioAdapter::new(client_stream))
+        .await
+        .expect_err("upgrade should be rejected");
+    let async_tungstenite::tungstenite::Error::Http(response) = error else {
+        panic!("expected an HTTP error, got {error:?}");
+    };
+    assert_eq!(response.status(), 500);
+
+    handle.abort();
+    let _ = handle.await;
+}
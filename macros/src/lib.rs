@@ -1,5 +1,7 @@
 //! Procedural macros for the Skyzen framework.
 
+use std::hash::{DefaultHasher, Hash, Hasher};
+
 use proc_macro::TokenStream;
 use quote::{format_ident, quote};
 use syn::{
@@ -7,9 +9,9 @@ use syn::{
     parse_macro_input, parse_quote,
     punctuated::Punctuated,
     spanned::Spanned,
-    Attribute, Data, DeriveInput, Error, Expr, ExprLit, Fields, FnArg, Item, ItemEnum, ItemFn,
-    ItemStruct, Lit, LitInt, LitStr, Meta, MetaNameValue, PatType, ReturnType, Token, Type,
-    Variant,
+    Attribute, Data, DeriveInput, Error, Expr, ExprLit, Fields, FnArg, GenericArgument, Item,
+    ItemEnum, ItemFn, ItemStruct, Lit, LitInt, LitStr, Meta, MetaNameValue, PatType, PathArguments,
+    ReturnType, Token, Type, Variant,
 };
 
 /// Attribute macro that boots a Skyzen Endpoint on native or wasm runtimes.
@@ -41,29 +43,59 @@ pub fn main(attr: TokenStream, item: TokenStream) -> TokenStream {
     };
     let wasm_factory = native_factory.clone();
 
+    let launch = options.launch_call(&native_factory);
+
     let init_logging = if options.default_logger {
-        quote! { ::skyzen::runtime::native::init_logging(); }
+        quote! { ::skyzen::runtime::native::init_logging_with(&__skyzen_config); }
     } else {
         quote! {}
     };
 
+    // Invalid environment variables and CLI flags are logged and skipped unless `config = strict`;
+    // a configuration file the user named must load.
+    let load_config = if options.strict_config {
+        quote! {
+            let __skyzen_config = match ::skyzen::runtime::native::Config::load(::std::env::args()) {
+                ::core::result::Result::Ok(config) => config,
+                ::core::result::Result::Err(error) => {
+                    ::std::eprintln!("error: {error}");
+                    ::std::process::exit(2);
+                }
+            };
+            #init_logging
+        }
+    } else {
+        quote! {
+            let (__skyzen_config, __skyzen_skipped) =
+                match ::skyzen::runtime::native::Config::load_lenient(::std::env::args()) {
+                    ::core::result::Result::Ok(loaded) => loaded,
+                    ::core::result::Result::Err(error) => {
+                        ::std::eprintln!("error: {error}");
+                        ::std::process::exit(2);
+                    }
+                };
+            #init_logging
+            ::skyzen::runtime::native::warn_skipped_settings(&__skyzen_skipped);
+        }
+    };
+
     let output = quote! {
         #function
 
         #[cfg(not(target_arch = "wasm32"))]
         fn main() {
-            #init_logging
-            ::skyzen::runtime::native::apply_cli_overrides(::std::env::args());
-            ::skyzen::runtime::native::launch(|| #native_factory);
+            #load_config
+            #launch
         }
 
+        /// Workers `fetch` entry point generated by `#[skyzen::main]`.
         #[cfg(target_arch = "wasm32")]
         #[wasm_bindgen::prelude::wasm_bindgen]
         pub async fn fetch(
             request: ::skyzen::runtime::wasm::Request,
             env: ::skyzen::runtime::wasm::Env,
             ctx: ::skyzen::runtime::wasm::ExecutionContext,
-        ) -> Result<::skyzen::runtime::wasm::Response, wasm_bindgen::JsValue> {
+        ) -> ::core::result::Result<::skyzen::runtime::wasm::Response, wasm_bindgen::JsValue> {
             ::skyzen::runtime::wasm::launch(|| #wasm_factory, request, env, ctx).await
         }
     };
@@ -71,23 +103,77 @@ pub fn main(attr: TokenStream, item: TokenStream) -> TokenStream {
     output.into()
 }
 
-/// Annotate handlers that should appear in generated `OpenAPI` documentation.
+/// Attribute macro that exports a Cloudflare Workers cron trigger handler.
+///
+/// The function must be `async` and take a `ScheduledEvent` and a `WorkerEnv`, returning
+/// `Result<(), E>` with `E: Display`. On wasm targets it is exported as `scheduled` next to the
+/// `fetch` export of `#[skyzen::main]`; errors are logged and reject the returned promise. On
+/// native targets the function is compiled out.
 #[proc_macro_attribute]
-pub fn openapi(attr: TokenStream, item: TokenStream) -> TokenStream {
-    let args =
-        parse_macro_input!(attr with Punctuated::<MetaNameValue, Token![,]>::parse_terminated);
-    if !args.is_empty() {
+pub fn scheduled(attr: TokenStream, item: TokenStream) -> TokenStream {
+    if !attr.is_empty() {
+        return Error::new(
+            proc_macro2::TokenStream::from(attr).span(),
+            "#[skyzen::scheduled] does not take arguments",
+        )
+        .to_compile_error()
+        .into();
+    }
+
+    let mut function = parse_macro_input!(item as ItemFn);
+    if function.sig.asyncness.is_none() {
         return Error::new_spanned(
-            quote! { #args },
-            "#[skyzen::openapi] does not take arguments; remove them",
+            function.sig.fn_token,
+            "#[skyzen::scheduled] functions must be async",
         )
         .to_compile_error()
         .into();
     }
 
+    let ident = if function.sig.ident == "scheduled" {
+        let unique = format_ident!("__skyzen_entry_scheduled");
+        function.sig.ident = unique.clone();
+        unique
+    } else {
+        function.sig.ident.clone()
+    };
+
+    let output = quote! {
+        #[cfg(target_arch = "wasm32")]
+        #function
+
+        /// Workers `scheduled` entry point generated by `#[skyzen::scheduled]`.
+        #[cfg(target_arch = "wasm32")]
+        #[wasm_bindgen::prelude::wasm_bindgen]
+        pub async fn scheduled(
+            event: wasm_bindgen::JsValue,
+            env: ::skyzen::runtime::wasm::Env,
+            ctx: ::skyzen::runtime::wasm::ExecutionContext,
+        ) -> ::core::result::Result<(), wasm_bindgen::JsValue> {
+            ::skyzen::runtime::wasm::scheduled(#ident, event, env, ctx).await
+        }
+    };
+
+    output.into()
+}
+
+/// Annotate handlers that should appear in generated `OpenAPI` documentation.
+///
+/// Accepts `tag = "..."` (repeatable), `summary = "..."`, `operation_id = "..."`,
+/// `security = "..."` and `deprecated`, e.g.
+/// `#[skyzen::openapi(tag = "users", operation_id = "listUsers")]`. `security` names the security
+/// scheme required by the operation; `security = ""` marks it public.
+#[proc_macro_attribute]
+pub fn openapi(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(attr with Punctuated::<Meta, Token![,]>::parse_terminated);
+    let options = match OpenApiOptions::from_args(&args) {
+        Ok(options) => options,
+        Err(error) => return error.to_compile_error().into(),
+    };
+
     let item = parse_macro_input!(item as Item);
     match item {
-        Item::Fn(function) => match expand_openapi_fn(function) {
+        Item::Fn(function) => match expand_openapi_fn(function, &options) {
             Ok(tokens) => tokens,
             Err(error) => error.to_compile_error().into(),
         },
@@ -98,6 +184,20 @@ pub fn openapi(attr: TokenStream, item: TokenStream) -> TokenStream {
 }
 
 /// Error helper that implements `Display`, `Error`, and `HttpError`.
+///
+/// Messages may interpolate fields like `format!`: `{0}` for tuple fields and `{name}` for named
+/// ones, with optional format specs such as `{0:?}`. Use `{{`/`}}` for literal braces.
+///
+/// Fields marked `#[from]` or `#[source]` are returned from `Error::source()`.
+///
+/// Also implements `skyzen::openapi::ErrorResponses`. `schema = Type` documents the response body
+/// (on the item or per variant via `#[error("...", schema = Type)]`) and `example = "..."` attaches
+/// an example payload.
+///
+/// `header("name" = "value")`, on the item or per variant, adds a header to the error response,
+/// such as `header("www-authenticate" = "Bearer")` on a `401`. Such errors implement
+/// `skyzen::ErrorHeaders` and convert into a `skyzen::BoxHttpError` that keeps the headers, so
+/// handlers return them as `Result<T, BoxHttpError>`.
 #[proc_macro_attribute]
 pub fn error(attr: TokenStream, item: TokenStream) -> TokenStream {
     let args = parse_macro_input!(attr as ErrorArgs);
@@ -109,6 +209,10 @@ pub fn error(attr: TokenStream, item: TokenStream) -> TokenStream {
 }
 
 /// Derive helper that maps enum variants to HTTP status codes.
+///
+/// Annotate variants (or a struct) with `#[status = 404]`; unannotated ones map to `500`.
+/// `#[status(transparent)]` on a single-field tuple variant or struct forwards the status of the
+/// wrapped `HttpError`.
 #[proc_macro_derive(HttpError, attributes(status))]
 pub fn derive_http_error(item: TokenStream) -> TokenStream {
     let input = parse_macro_input!(item as DeriveInput);
@@ -118,14 +222,179 @@ pub fn derive_http_error(item: TokenStream) -> TokenStream {
     }
 }
 
+/// Derive `FromRef` for every field of a composite application state.
+///
+/// Fields marked `#[from_ref(skip)]` are left out.
+#[proc_macro_derive(FromRef, attributes(from_ref))]
+pub fn derive_from_ref(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+    match expand_from_ref(&input) {
+        Ok(tokens) => tokens,
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+fn expand_from_ref(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let ident = &input.ident;
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &input.generics,
+            "FromRef cannot be derived for generic structs",
+        ));
+    }
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new(
+            ident.span(),
+            "FromRef can only be derived for structs",
+        ));
+    };
+
+    let mut impls = Vec::new();
+    let mut inserts = Vec::new();
+    for (index, field) in data.fields.iter().enumerate() {
+        if from_ref_skipped(&field.attrs)? {
+            continue;
+        }
+        let ty = &field.ty;
+        let member: syn::Member = field
+            .ident
+            .clone()
+            .map_or_else(|| syn::Index::from(index).into(), syn::Member::Named);
+        impls.push(quote! {
+            impl ::skyzen::utils::state::FromRef<#ident> for #ty {
+                fn from_ref(input: &#ident) -> Self {
+                    ::core::clone::Clone::clone(&input.#member)
+                }
+            }
+        });
+        inserts.push(quote! {
+            extensions.insert(::skyzen::utils::State(
+                <#ty as ::skyzen::utils::state::FromRef<Self>>::from_ref(self),
+            ));
+        });
+    }
+
+    Ok(quote! {
+        #(#impls)*
+
+        impl ::skyzen::utils::state::Substates for #ident {
+            fn insert_substates(&self, extensions: &mut ::skyzen::Extensions) {
+                #(#inserts)*
+            }
+        }
+    }
+    .into())
+}
+
+fn from_ref_skipped(attrs: &[Attribute]) -> syn::Result<bool> {
+    let mut skip = false;
+    for attr in attrs {
+        if attr.path().is_ident("from_ref") {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("skip") {
+                    skip = true;
+                    Ok(())
+                } else {
+                    Err(meta.error("unsupported #[from_ref] argument, expected `skip`"))
+                }
+            })?;
+        }
+    }
+    Ok(skip)
+}
+
+#[derive(Default)]
+struct OpenApiOptions {
+    tags: Vec<LitStr>,
+    summary: Option<LitStr>,
+    operation_id: Option<LitStr>,
+    security: Option<LitStr>,
+    deprecated: bool,
+}
+
+impl OpenApiOptions {
+    const SUPPORTED: &'static str =
+        "supported options are `tag = \"...\"`, `summary = \"...\"`, `operation_id = \"...\"`, `security = \"...\"` and `deprecated`";
+
+    fn from_args(args: &Punctuated<Meta, Token![,]>) -> syn::Result<Self> {
+        let mut options = Self::default();
+        for meta in args {
+            let path = meta.path();
+            if path.is_ident("deprecated") {
+                if !matches!(meta, Meta::Path(_)) {
+                    return Err(Error::new_spanned(
+                        meta,
+                        "`deprecated` does not take a value",
+                    ));
+                }
+                options.deprecated = true;
+                continue;
+            }
+
+            let slot = if path.is_ident("tag") {
+                None
+            } else if path.is_ident("summary") {
+                Some(&mut options.summary)
+            } else if path.is_ident("operation_id") {
+                Some(&mut options.operation_id)
+            } else if path.is_ident("security") {
+                Some(&mut options.security)
+            } else {
+                return Err(Error::new_spanned(
+                    path,
+                    format!("unknown #[skyzen::openapi] option; {}", Self::SUPPORTED),
+                ));
+            };
+
+            let value = match meta {
+                Meta::NameValue(MetaNameValue {
+                    value:
+                        Expr::Lit(ExprLit {
+                            lit: Lit::Str(value),
+                            ..
+                        }),
+                    ..
+                }) => value.clone(),
+                Meta::NameValue(MetaNameValue { value, .. }) => {
+                    return Err(Error::new_spanned(value, "expected string literal"));
+                }
+                other => {
+                    return Err(Error::new_spanned(other, "expected `key = \"value\"`"));
+                }
+            };
+
+            match slot {
+                None => options.tags.push(value),
+                Some(slot) if slot.is_some() => {
+                    return Err(Error::new_spanned(
+                        path,
+                        "duplicate #[skyzen::openapi] option",
+                    ));
+                }
+                Some(slot) => *slot = Some(value),
+            }
+        }
+        Ok(options)
+    }
+}
+
+fn optional_lit(value: Option<&LitStr>) -> proc_macro2::TokenStream {
+    value.map_or_else(|| quote! { None }, |value| quote! { Some(#value) })
+}
+
 #[allow(clippy::too_many_lines)]
-fn expand_openapi_fn(mut function: ItemFn) -> syn::Result<TokenStream> {
+fn expand_openapi_fn(mut function: ItemFn, options: &OpenApiOptions) -> syn::Result<TokenStream> {
     let fn_ident = &function.sig.ident;
 
-    let deprecated = function
-        .attrs
-        .iter()
-        .any(|attr| attr.path().is_ident("deprecated"));
+    let deprecated = options.deprecated
+        || function
+            .attrs
+            .iter()
+            .any(|attr| attr.path().is_ident("deprecated"));
+    let tags = &options.tags;
+    let summary = optional_lit(options.summary.as_ref());
+    let operation_id = optional_lit(options.operation_id.as_ref());
+    let security = optional_lit(options.security.as_ref());
 
     let doc = doc_string(&function.attrs);
     let doc_tokens = doc.as_deref().map_or_else(
@@ -194,14 +463,11 @@ fn expand_openapi_fn(mut function: ItemFn) -> syn::Result<TokenStream> {
     let response_schema_fn =
         quote! { Some(::skyzen::openapi::responder_schemas_of::<#response_ty>) };
 
+    let item_suffix = generated_item_suffix(fn_ident);
     let mut schema_collector_idents = Vec::new();
     let mut schema_collector_defs = Vec::new();
     for (idx, ty) in parameter_types.iter().enumerate() {
-        let ident = format_ident!(
-            "__SKYZEN_OPENAPI_SCHEMAS_{}_{}",
-            fn_ident.to_string().to_uppercase(),
-            idx
-        );
+        let ident = format_ident!("__SKYZEN_OPENAPI_SCHEMAS_{}_{}", item_suffix, idx);
         schema_collector_idents.push(ident.clone());
         schema_collector_defs.push(quote! {
             fn #ident(schemas: &mut ::std::collections::BTreeMap<String, ::skyzen::openapi::SchemaRef>) {
@@ -210,10 +476,7 @@ fn expand_openapi_fn(mut function: ItemFn) -> syn::Result<TokenStream> {
         });
     }
 
-    let response_collector_ident = format_ident!(
-        "__SKYZEN_OPENAPI_SCHEMAS_{}_RESP",
-        fn_ident.to_string().to_uppercase()
-    );
+    let response_collector_ident = format_ident!("__SKYZEN_OPENAPI_SCHEMAS_{}_RESP", item_suffix);
     schema_collector_idents.push(response_collector_ident.clone());
     schema_collector_defs.push(quote! {
         fn #response_collector_ident(
@@ -223,6 +486,32 @@ fn expand_openapi_fn(mut function: ItemFn) -> syn::Result<TokenStream> {
         }
     });
 
+    let errors_fn = result_error_type(&response_ty).map_or_else(
+        || quote! { None },
+        |error_ty| {
+            let errors_ident = format_ident!("__SKYZEN_OPENAPI_ERRORS_{}", item_suffix);
+            let errors_collector_ident =
+                format_ident!("__SKYZEN_OPENAPI_SCHEMAS_{}_ERRORS", item_suffix);
+            schema_collector_idents.push(errors_collector_ident.clone());
+            schema_collector_defs.push(quote! {
+                fn #errors_ident() -> Option<Vec<::skyzen::openapi::ResponseSchema>> {
+                    #[allow(unused_imports)]
+                    use ::skyzen::openapi::{DescribedError as _, UndescribedError as _};
+                    (&::skyzen::openapi::ErrorProbe::<#error_ty>::new()).error_responses()
+                }
+
+                fn #errors_collector_ident(
+                    schemas: &mut ::std::collections::BTreeMap<String, ::skyzen::openapi::SchemaRef>
+                ) {
+                    #[allow(unused_imports)]
+                    use ::skyzen::openapi::{DescribedError as _, UndescribedError as _};
+                    (&::skyzen::openapi::ErrorProbe::<#error_ty>::new()).register_error_schemas(schemas);
+                }
+            });
+            quote! { Some(#errors_ident) }
+        },
+    );
+
     let schema_collectors = if schema_collector_idents.is_empty() {
         quote! { &[] }
     } else {
@@ -237,10 +526,7 @@ fn expand_openapi_fn(mut function: ItemFn) -> syn::Result<TokenStream> {
 
     let type_name_literal = quote! { concat!(module_path!(), "::", stringify!(#fn_ident)) };
     let operation_name_literal = quote! { #type_name_literal };
-    let spec_ident = format_ident!(
-        "__SKYZEN_OPENAPI_SPEC_{}",
-        fn_ident.to_string().to_uppercase()
-    );
+    let spec_ident = format_ident!("__SKYZEN_OPENAPI_SPEC_{}", item_suffix);
 
     Ok(quote! {
         #function
@@ -260,15 +546,61 @@ fn expand_openapi_fn(mut function: ItemFn) -> syn::Result<TokenStream> {
             operation_name: #operation_name_literal,
             docs: #doc_tokens,
             deprecated: #deprecated,
+            tags: &[#(#tags),*],
+            summary: #summary,
+            operation_id: #operation_id,
+            security: #security,
             parameters: #schema_array,
             parameter_names: #parameter_names_array,
             response: #response_schema_fn,
+            errors: #errors_fn,
             schemas: #schema_collectors,
         };
     }
     .into())
 }
 
+/// Suffix of the items generated next to the handler `fn_ident`.
+///
+/// Besides the uppercased name, it holds a hash of the name and of where the handler is written,
+/// so that handlers whose names only differ in case, or that are expanded into the same scope by
+/// another macro, never share an item name.
+fn generated_item_suffix(fn_ident: &syn::Ident) -> String {
+    let span = fn_ident.span().unwrap();
+    let mut hasher = DefaultHasher::new();
+    fn_ident.to_string().hash(&mut hasher);
+    span.file().hash(&mut hasher);
+    span.line().hash(&mut hasher);
+    span.column().hash(&mut hasher);
+    format!(
+        "{}_{:016X}",
+        fn_ident.to_string().to_uppercase(),
+        hasher.finish()
+    )
+}
+
+/// Error type `E` of a `Result<T, E>` return type; `skyzen::Result<T>` names none.
+fn result_error_type(ty: &Type) -> Option<&Type> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+    if segment.ident != "Result" {
+        return None;
+    }
+    let PathArguments::AngleBracketed(arguments) = &segment.arguments else {
+        return None;
+    };
+    arguments
+        .args
+        .iter()
+        .filter_map(|argument| match argument {
+            GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        })
+        .nth(1)
+}
+
 struct ParameterMeta {
     ty: Type,
     name: Option<syn::Ident>,
@@ -313,7 +645,8 @@ fn expand_error(args: ErrorArgs, item: Item) -> syn::Result<TokenStream> {
 }
 
 #[allow(clippy::needless_pass_by_value)]
-fn expand_error_struct(args: ErrorArgs, item_struct: ItemStruct) -> syn::Result<TokenStream> {
+fn expand_error_struct(args: ErrorArgs, mut item_struct: ItemStruct) -> syn::Result<TokenStream> {
+    let source = take_source_field(&mut item_struct.fields)?;
     let ident = &item_struct.ident;
     let generics = &item_struct.generics;
     let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
@@ -329,17 +662,44 @@ fn expand_error_struct(args: ErrorArgs, item_struct: ItemStruct) -> syn::Result<
         .status
         .unwrap_or_else(|| parse_quote!(::skyzen::StatusCode::INTERNAL_SERVER_ERROR));
 
+    let (display_fields, display) = display_message(&message, &item_struct.fields)?;
+    let display_pattern = quote! { Self #display_fields };
+    let source_body = source.map(|member| {
+        quote! {
+            fn source(&self) -> ::core::option::Option<&(dyn ::core::error::Error + 'static)> {
+                ::core::option::Option::Some(&self.#member as &(dyn ::core::error::Error + 'static))
+            }
+        }
+    });
+
+    let response = error_response_schema(
+        &status,
+        &message,
+        args.schema.as_ref(),
+        args.example.as_ref(),
+    );
+    let error_responses =
+        error_responses_impl(ident, generics, &[response], args.schema.iter().collect());
+    let error_headers = error_headers_impl(ident, generics, &[(quote! { _ }, args.headers)]);
+
     Ok(quote! {
         #[derive(::core::fmt::Debug)]
         #item_struct
 
+        #error_responses
+
+        #error_headers
+
         impl #impl_generics ::core::fmt::Display for #ident #ty_generics #where_clause {
             fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
-                f.write_str(#message)
+                let #display_pattern = self;
+                #display
             }
         }
 
-        impl #impl_generics ::core::error::Error for #ident #ty_generics #where_clause {}
+        impl #impl_generics ::core::error::Error for #ident #ty_generics #where_clause {
+            #source_body
+        }
 
         impl #impl_generics ::skyzen::HttpError for #ident #ty_generics #where_clause {
             fn status(&self) -> ::skyzen::StatusCode {
@@ -350,18 +710,29 @@ fn expand_error_struct(args: ErrorArgs, item_struct: ItemStruct) -> syn::Result<
     .into())
 }
 
+#[allow(clippy::too_many_lines)]
 fn expand_error_enum(args: ErrorArgs, mut item_enum: ItemEnum) -> syn::Result<TokenStream> {
     let ident = &item_enum.ident;
     let generics = &item_enum.generics;
     let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
 
-    let ErrorArgs { status, .. } = args;
+    let ErrorArgs {
+        status,
+        schema: default_schema,
+        example: default_example,
+        headers: default_headers,
+        ..
+    } = args;
     let default_status =
         status.unwrap_or_else(|| parse_quote!(::skyzen::StatusCode::INTERNAL_SERVER_ERROR));
 
     let mut display_arms = Vec::new();
+    let mut source_arms = Vec::new();
     let mut status_arms = Vec::new();
+    let mut header_arms = Vec::new();
     let mut from_impls = Vec::new();
+    let mut responses = Vec::new();
+    let mut schema_types: Vec<Type> = default_schema.iter().cloned().collect();
     let mut cleaned_variants = Punctuated::new();
 
     for variant in item_enum.variants {
@@ -371,7 +742,11 @@ fn expand_error_enum(args: ErrorArgs, mut item_enum: ItemEnum) -> syn::Result<To
             VariantMeta {
                 message,
                 status,
+                schema,
+                example,
+                headers,
                 from,
+                source,
             },
         ) = parse_variant(variant)?;
 
@@ -389,16 +764,45 @@ fn expand_error_enum(args: ErrorArgs, mut item_enum: ItemEnum) -> syn::Result<To
                 quote! { Self::#ident { .. } }
             }
         };
+        let (display_fields, display) = display_message(&message, &variant.fields)?;
 
         let status_expr = status.unwrap_or_else(|| default_status.clone());
 
+        let schema = schema.or_else(|| default_schema.clone());
+        responses.push(error_response_schema(
+            &status_expr,
+            &message,
+            schema.as_ref(),
+            example.as_ref().or(default_example.as_ref()),
+        ));
+        if let Some(schema) = schema {
+            schema_types.push(schema);
+        }
+
         display_arms.push(quote! {
-            #pattern => f.write_str(#message)
+            Self::#variant_ident #display_fields => #display
         });
 
+        source_arms.push(source.map_or_else(
+            || quote! { Self::#variant_ident { .. } => ::core::option::Option::None },
+            |member| {
+                quote! {
+                    Self::#variant_ident { #member: __skyzen_source, .. } => {
+                        ::core::option::Option::Some(
+                            __skyzen_source as &(dyn ::core::error::Error + 'static),
+                        )
+                    }
+                }
+            },
+        ));
+
         status_arms.push(quote! {
             #pattern => #status_expr
         });
+        header_arms.push((
+            pattern,
+            default_headers.iter().cloned().chain(headers).collect(),
+        ));
 
         if let Some(from_info) = from {
             let binding = format_ident!("__skyzen_from");
@@ -425,10 +829,18 @@ fn expand_error_enum(args: ErrorArgs, mut item_enum: ItemEnum) -> syn::Result<To
 
     item_enum.variants = cleaned_variants;
 
+    let error_responses =
+        error_responses_impl(ident, generics, &responses, schema_types.iter().collect());
+    let error_headers = error_headers_impl(ident, generics, &header_arms);
+
     Ok(quote! {
         #[derive(::core::fmt::Debug)]
         #item_enum
 
+        #error_responses
+
+        #error_headers
+
         impl #impl_generics ::core::fmt::Display for #ident #ty_generics #where_clause {
             fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
                 match self {
@@ -437,7 +849,13 @@ fn expand_error_enum(args: ErrorArgs, mut item_enum: ItemEnum) -> syn::Result<To
             }
         }
 
-        impl #impl_generics ::core::error::Error for #ident #ty_generics #where_clause {}
+        impl #impl_generics ::core::error::Error for #ident #ty_generics #where_clause {
+            fn source(&self) -> ::core::option::Option<&(dyn ::core::error::Error + 'static)> {
+                match self {
+                    #(#source_arms),*
+                }
+            }
+        }
 
         impl #impl_generics ::skyzen::HttpError for #ident #ty_generics #where_clause {
             fn status(&self) -> ::skyzen::StatusCode {
@@ -452,62 +870,335 @@ fn expand_error_enum(args: ErrorArgs, mut item_enum: ItemEnum) -> syn::Result<To
     .into())
 }
 
+/// Field pattern and `Display` body for an error message.
+///
+/// Messages without braces are written verbatim. Otherwise `{0}`/`{name}` placeholders are bound to
+/// the tuple or named fields they reference and the message is rendered with `write!`.
+fn display_message(
+    message: &LitStr,
+    fields: &Fields,
+) -> syn::Result<(proc_macro2::TokenStream, proc_macro2::TokenStream)> {
+    let rest = match fields {
+        Fields::Unit => quote! {},
+        Fields::Unnamed(_) => quote! { (..) },
+        Fields::Named(_) => quote! { { .. } },
+    };
+    let value = message.value();
+    if !value.contains(['{', '}']) {
+        return Ok((rest, quote! { f.write_str(#message) }));
+    }
+
+    let (template, placeholders) = parse_message_template(message)?;
+    let mut bindings: Vec<syn::Ident> = Vec::new();
+    for placeholder in &placeholders {
+        let binding = match (placeholder.parse::<usize>(), fields) {
+            (Ok(index), Fields::Unnamed(unnamed)) if index < unnamed.unnamed.len() => {
+                format_ident!("__skyzen_field{index}")
+            }
+            (Ok(index), _) => {
+                return Err(Error::new(
+                    message.span(),
+                    format!("error message references field `{index}`, which does not exist"),
+                ));
+            }
+            (Err(_), Fields::Named(named))
+                if named
+                    .named
+                    .iter()
+                    .any(|field| field.ident.as_ref().is_some_and(|id| id == placeholder)) =>
+            {
+                format_ident!("{placeholder}")
+            }
+            (Err(_), _) => {
+                return Err(Error::new(
+                    message.span(),
+                    format!("error message references field `{placeholder}`, which does not exist"),
+                ));
+            }
+        };
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    let pattern = match fields {
+        Fields::Unit => rest,
+        Fields::Unnamed(unnamed) => {
+            let elements = (0..unnamed.unnamed.len()).map(|index| {
+                let binding = format_ident!("__skyzen_field{index}");
+                if bindings.contains(&binding) {
+                    quote! { #binding }
+                } else {
+                    quote! { _ }
+                }
+            });
+            quote! { ( #(#elements),* ) }
+        }
+        Fields::Named(_) => quote! { { #(#bindings,)* .. } },
+    };
+    let template = LitStr::new(&template, message.span());
+    Ok((
+        pattern,
+        quote! { ::core::write!(f, #template, #(#bindings = #bindings),*) },
+    ))
+}
+
+/// Rewrites positional placeholders in `message` to named ones and lists every referenced field.
+fn parse_message_template(message: &LitStr) -> syn::Result<(String, Vec<String>)> {
+    let value = message.value();
+    let mut template = String::with_capacity(value.len());
+    let mut placeholders = Vec::new();
+    let mut chars = value.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                template.push_str("{{");
+            }
+            '{' => {
+                let mut placeholder = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(ch) => placeholder.push(ch),
+                        None => {
+                            return Err(Error::new(
+                                message.span(),
+                                "unterminated `{` in error message; use `{{` for a literal brace",
+                            ));
+                        }
+                    }
+                }
+                let (field, spec) = placeholder
+                    .split_once(':')
+                    .map_or((placeholder.as_str(), None), |(field, spec)| {
+                        (field, Some(spec))
+                    });
+                let field = field.trim();
+                if field.is_empty() {
+                    return Err(Error::new(
+                        message.span(),
+                        "error message placeholders must name a field, e.g. `{0}` or `{name}`",
+                    ));
+                }
+                template.push('{');
+                if field.bytes().all(|byte| byte.is_ascii_digit()) {
+                    template.push_str("__skyzen_field");
+                }
+                template.push_str(field);
+                if let Some(spec) = spec {
+                    template.push(':');
+                    template.push_str(spec);
+                }
+                template.push('}');
+                placeholders.push(field.to_owned());
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                template.push_str("}}");
+            }
+            '}' => {
+                return Err(Error::new(
+                    message.span(),
+                    "unmatched `}` in error message; use `}}` for a literal brace",
+                ));
+            }
+            ch => template.push(ch),
+        }
+    }
+
+    Ok((template, placeholders))
+}
+
+/// `ResponseSchema` literal documenting one response rendered by an error type.
+fn error_response_schema(
+    status: &Expr,
+    message: &LitStr,
+    schema: Option<&Type>,
+    example: Option<&LitStr>,
+) -> proc_macro2::TokenStream {
+    let schema = schema.map_or_else(
+        || quote! { None },
+        |ty| quote! { ::skyzen::openapi::schema_of::<#ty>() },
+    );
+    let example = optional_lit(example);
+    // Respanned so lints on format-like literals don't fire on the user's message template.
+    let description = LitStr::new(&message.value(), proc_macro2::Span::call_site());
+    quote! {
+        ::skyzen::openapi::ResponseSchema {
+            status: Some(#status),
+            description: Some(#description),
+            schema: #schema,
+            content_type: None,
+            example: #example,
+        }
+    }
+}
+
+/// `ErrorResponses` impl plus a const assertion that every `schema = Type` implements `ToSchema`.
+fn error_responses_impl(
+    ident: &syn::Ident,
+    generics: &syn::Generics,
+    responses: &[proc_macro2::TokenStream],
+    mut schema_types: Vec<&Type>,
+) -> proc_macro2::TokenStream {
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let mut seen = Vec::new();
+    schema_types.retain(|ty| {
+        let key = quote!(#ty).to_string();
+        let fresh = !seen.contains(&key);
+        seen.push(key);
+        fresh
+    });
+
+    quote! {
+        const _: fn() = || {
+            #(let _ = ::skyzen::openapi::schema_of::<#schema_types>;)*
+        };
+
+        impl #impl_generics ::skyzen::openapi::ErrorResponses for #ident #ty_generics #where_clause {
+            fn openapi_responses() -> ::std::vec::Vec<::skyzen::openapi::ResponseSchema> {
+                ::std::vec![#(#responses),*]
+            }
+
+            fn register_openapi_schemas(
+                defs: &mut ::std::collections::BTreeMap<::std::string::String, ::skyzen::openapi::SchemaRef>,
+            ) {
+                #(::skyzen::openapi::register_schema_for::<#schema_types>(defs);)*
+                let _ = defs;
+            }
+        }
+    }
+}
+
 fn expand_http_error(input: DeriveInput) -> syn::Result<TokenStream> {
     let ident = input.ident;
     let generics = input.generics;
     let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
 
-    let variants = match input.data {
-        Data::Enum(data) => data.variants,
-        _ => {
+    let body = match input.data {
+        Data::Enum(data) => {
+            let mut arms = Vec::new();
+            for variant in data.variants {
+                let variant_ident = &variant.ident;
+                let arm = match status_attr(&variant.attrs)? {
+                    Some(StatusAttr::Transparent(attr)) => {
+                        let field = transparent_field(&variant.fields, &attr)?;
+                        let status = transparent_status(field, &quote! { inner });
+                        quote! { Self::#variant_ident(inner) => #status }
+                    }
+                    status => {
+                        let pattern = match &variant.fields {
+                            Fields::Unit => quote! { Self::#variant_ident },
+                            Fields::Unnamed(_) => quote! { Self::#variant_ident ( .. ) },
+                            Fields::Named(_) => quote! { Self::#variant_ident { .. } },
+                        };
+                        let status_expr = constant_status(status);
+                        quote! { #pattern => #status_expr }
+                    }
+                };
+                arms.push(arm);
+            }
+            quote! {
+                match self {
+                    #(#arms),*
+                }
+            }
+        }
+        Data::Struct(data) => match status_attr(&input.attrs)? {
+            Some(StatusAttr::Transparent(attr)) => {
+                let field = transparent_field(&data.fields, &attr)?;
+                transparent_status(field, &quote! { &self.0 })
+            }
+            status => {
+                let status_expr = constant_status(status);
+                quote! { #status_expr }
+            }
+        },
+        Data::Union(_) => {
             return Err(Error::new(
                 ident.span(),
-                "HttpError can only be derived for enums",
+                "HttpError cannot be derived for unions",
             ))
         }
     };
 
-    let mut arms = Vec::new();
-    for variant in variants {
-        let variant_ident = &variant.ident;
-        let pattern = match &variant.fields {
-            Fields::Unit => quote! { Self::#variant_ident },
-            Fields::Unnamed(_) => quote! { Self::#variant_ident ( .. ) },
-            Fields::Named(_) => quote! { Self::#variant_ident { .. } },
-        };
-        let status_expr = variant_status_expr(&variant)?;
-        arms.push(quote! { #pattern => #status_expr });
-    }
-
     Ok(quote! {
         impl #impl_generics ::skyzen::HttpError for #ident #ty_generics #where_clause {
             fn status(&self) -> ::skyzen::StatusCode {
-                match self {
-                    #(#arms),*
-                }
+                #body
             }
         }
     }
     .into())
 }
 
-fn variant_status_expr(variant: &Variant) -> syn::Result<Expr> {
-    let mut expr = None;
-    for attr in &variant.attrs {
-        if attr.path().is_ident("status") {
-            if expr.is_some() {
-                return Err(Error::new(attr.span(), "duplicate `status` attribute"));
-            }
+/// Status declared by a `#[status = ...]` or `#[status(transparent)]` attribute.
+enum StatusAttr {
+    Expr(Expr),
+    Transparent(Attribute),
+}
 
-            let value = match &attr.meta {
-                Meta::NameValue(meta) => meta.value.clone(),
-                _ => return Err(Error::new_spanned(attr, "expected #[status = <expr>]")),
-            };
-            expr = Some(normalize_status_expr(&value)?);
+fn status_attr(attrs: &[Attribute]) -> syn::Result<Option<StatusAttr>> {
+    let mut status = None;
+    for attr in attrs {
+        if !attr.path().is_ident("status") {
+            continue;
+        }
+        if status.is_some() {
+            return Err(Error::new_spanned(attr, "duplicate `status` attribute"));
         }
+
+        status = Some(match &attr.meta {
+            Meta::NameValue(meta) => StatusAttr::Expr(normalize_status_expr(&meta.value)?),
+            Meta::List(list) => {
+                let mode: syn::Ident = list.parse_args()?;
+                if mode != "transparent" {
+                    return Err(Error::new_spanned(
+                        mode,
+                        "expected #[status = <expr>] or #[status(transparent)]",
+                    ));
+                }
+                StatusAttr::Transparent(attr.clone())
+            }
+            Meta::Path(_) => {
+                return Err(Error::new_spanned(
+                    attr,
+                    "expected #[status = <expr>] or #[status(transparent)]",
+                ))
+            }
+        });
+    }
+    Ok(status)
+}
+
+fn constant_status(status: Option<StatusAttr>) -> Expr {
+    match status {
+        Some(StatusAttr::Expr(expr)) => expr,
+        _ => parse_quote!(::skyzen::StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+/// The single tuple field whose status a `#[status(transparent)]` item forwards.
+fn transparent_field<'a>(fields: &'a Fields, attr: &Attribute) -> syn::Result<&'a syn::Field> {
+    match fields {
+        Fields::Unnamed(unnamed) if unnamed.unnamed.len() == 1 => Ok(&unnamed.unnamed[0]),
+        _ => Err(Error::new_spanned(
+            attr,
+            "#[status(transparent)] requires exactly one unnamed field",
+        )),
     }
+}
 
-    Ok(expr.unwrap_or_else(|| parse_quote!(::skyzen::StatusCode::INTERNAL_SERVER_ERROR)))
+fn transparent_status(
+    field: &syn::Field,
+    inner: &proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let ty = &field.ty;
+    quote::quote_spanned! {ty.span()=>
+        <#ty as ::skyzen::HttpError>::status(#inner)
+    }
 }
 
 fn normalize_status_expr(expr: &Expr) -> syn::Result<Expr> {
@@ -537,6 +1228,9 @@ fn normalize_status_lit(lit: &LitInt) -> syn::Result<Expr> {
 struct ErrorArgs {
     status: Option<Expr>,
     message: Option<LitStr>,
+    schema: Option<Type>,
+    example: Option<LitStr>,
+    headers: Vec<(LitStr, LitStr)>,
 }
 
 impl Parse for ErrorArgs {
@@ -544,6 +1238,13 @@ impl Parse for ErrorArgs {
         let mut args = Self::default();
         while !input.is_empty() {
             let key: syn::Ident = input.parse()?;
+            if key == "header" {
+                args.headers.push(parse_header_arg(input)?);
+                if input.peek(Token![,]) {
+                    let _: Token![,] = input.parse()?;
+                }
+                continue;
+            }
             input.parse::<Token![=]>()?;
             match key.to_string().as_str() {
                 "status" => {
@@ -559,6 +1260,18 @@ impl Parse for ErrorArgs {
                     }
                     args.message = Some(input.parse()?);
                 }
+                "schema" => {
+                    if args.schema.is_some() {
+                        return Err(Error::new(key.span(), "duplicate `schema` argument"));
+                    }
+                    args.schema = Some(input.parse()?);
+                }
+                "example" => {
+                    if args.example.is_some() {
+                        return Err(Error::new(key.span(), "duplicate `example` argument"));
+                    }
+                    args.example = Some(input.parse()?);
+                }
                 other => {
                     return Err(Error::new(
                         key.span(),
@@ -579,7 +1292,11 @@ impl Parse for ErrorArgs {
 struct VariantMeta {
     message: LitStr,
     status: Option<Expr>,
+    schema: Option<Type>,
+    example: Option<LitStr>,
+    headers: Vec<(LitStr, LitStr)>,
     from: Option<VariantFrom>,
+    source: Option<syn::Member>,
 }
 
 struct VariantFrom {
@@ -614,6 +1331,15 @@ fn parse_variant(mut variant: Variant) -> syn::Result<(Variant, VariantMeta)> {
         )
     })?;
     meta.from = extract_variant_from(&mut variant.fields)?;
+    let explicit_source = take_source_field(&mut variant.fields)?;
+    meta.source = meta
+        .from
+        .as_ref()
+        .map(|from| match &from.style {
+            VariantFromStyle::Unnamed => syn::Member::Unnamed(0.into()),
+            VariantFromStyle::Named(ident) => syn::Member::Named(ident.clone()),
+        })
+        .or(explicit_source);
 
     variant.attrs = other_attrs;
     Ok((variant, meta))
@@ -623,6 +1349,9 @@ fn parse_variant_error_attr(attr: &Attribute) -> syn::Result<VariantMeta> {
     attr.parse_args_with(|input: ParseStream<'_>| {
         let mut message: Option<LitStr> = None;
         let mut status = None;
+        let mut schema: Option<Type> = None;
+        let mut example: Option<LitStr> = None;
+        let mut headers = Vec::new();
 
         while !input.is_empty() {
             if input.peek(Lit) {
@@ -643,6 +1372,14 @@ fn parse_variant_error_attr(attr: &Attribute) -> syn::Result<VariantMeta> {
                 }
             } else {
                 let key: syn::Ident = input.parse()?;
+                if key == "header" {
+                    headers.push(parse_header_arg(input)?);
+                    if input.peek(Token![,]) {
+                        input.parse::<Token![,]>()?;
+                        continue;
+                    }
+                    break;
+                }
                 input.parse::<Token![=]>()?;
                 match key.to_string().as_str() {
                     "status" => {
@@ -652,6 +1389,18 @@ fn parse_variant_error_attr(attr: &Attribute) -> syn::Result<VariantMeta> {
                         let value: Expr = input.parse()?;
                         status = Some(normalize_status_expr(&value)?);
                     }
+                    "schema" => {
+                        if schema.is_some() {
+                            return Err(Error::new(key.span(), "duplicate `schema` argument"));
+                        }
+                        schema = Some(input.parse()?);
+                    }
+                    "example" => {
+                        if example.is_some() {
+                            return Err(Error::new(key.span(), "duplicate `example` argument"));
+                        }
+                        example = Some(input.parse()?);
+                    }
                     other => {
                         return Err(Error::new(
                             key.span(),
@@ -685,11 +1434,80 @@ fn parse_variant_error_attr(attr: &Attribute) -> syn::Result<VariantMeta> {
         Ok(VariantMeta {
             message,
             status,
+            schema,
+            example,
+            headers,
             from: None,
+            source: None,
         })
     })
 }
 
+/// The `("name" = "value")` of a `header(...)` argument, with the name lowercased.
+fn parse_header_arg(input: ParseStream<'_>) -> syn::Result<(LitStr, LitStr)> {
+    let content;
+    syn::parenthesized!(content in input);
+    let name: LitStr = content.parse()?;
+    content.parse::<Token![=]>()?;
+    let value: LitStr = content.parse()?;
+    if !content.is_empty() {
+        return Err(content.error("expected `header(\"name\" = \"value\")`"));
+    }
+    let lowercase = LitStr::new(&name.value().to_ascii_lowercase(), name.span());
+    Ok((lowercase, value))
+}
+
+/// The `ErrorHeaders` impl for headers declared with `header(...)`, and a conversion into
+/// `BoxHttpError` that keeps them. Nothing is generated if no headers were declared.
+///
+/// Each arm pairs a pattern with its headers, which are checked at compile time.
+fn error_headers_impl(
+    ident: &syn::Ident,
+    generics: &syn::Generics,
+    arms: &[(proc_macro2::TokenStream, Vec<(LitStr, LitStr)>)],
+) -> proc_macro2::TokenStream {
+    if arms.iter().all(|(_, headers)| headers.is_empty()) {
+        return quote! {};
+    }
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let arms = arms.iter().map(|(pattern, headers)| {
+        if headers.is_empty() {
+            return quote! { #pattern => ::core::option::Option::None };
+        }
+        let (names, values): (Vec<_>, Vec<_>) = headers.iter().cloned().unzip();
+        quote! {
+            #pattern => {
+                let mut headers = ::skyzen::header::HeaderMap::new();
+                #(
+                    headers.append(
+                        const { ::skyzen::header::HeaderName::from_static(#names) },
+                        const { ::skyzen::header::HeaderValue::from_static(#values) },
+                    );
+                )*
+                ::core::option::Option::Some(headers)
+            }
+        }
+    });
+
+    quote! {
+        impl #impl_generics ::skyzen::ErrorHeaders for #ident #ty_generics #where_clause {
+            fn headers(&self) -> ::core::option::Option<::skyzen::header::HeaderMap> {
+                match self {
+                    #(#arms),*
+                }
+            }
+        }
+
+        impl #impl_generics ::core::convert::From<#ident #ty_generics> for ::skyzen::BoxHttpError
+            #where_clause
+        {
+            fn from(error: #ident #ty_generics) -> Self {
+                ::skyzen::ErrorHeaders::into_boxed_with_headers(error)
+            }
+        }
+    }
+}
+
 fn extract_variant_from(fields: &mut Fields) -> syn::Result<Option<VariantFrom>> {
     match fields {
         Fields::Unit => Ok(None),
@@ -697,7 +1515,7 @@ fn extract_variant_from(fields: &mut Fields) -> syn::Result<Option<VariantFrom>>
             let count = unnamed.unnamed.len();
             let mut info = None;
             for field in &mut unnamed.unnamed {
-                if take_from_attr(&mut field.attrs)? {
+                if take_marker_attr(&mut field.attrs, "from")? {
                     if info.is_some() {
                         return Err(Error::new(field.ty.span(), "duplicate #[from] attribute"));
                     }
@@ -719,7 +1537,7 @@ fn extract_variant_from(fields: &mut Fields) -> syn::Result<Option<VariantFrom>>
             let count = named.named.len();
             let mut info = None;
             for field in &mut named.named {
-                if take_from_attr(&mut field.attrs)? {
+                if take_marker_attr(&mut field.attrs, "from")? {
                     if info.is_some() {
                         return Err(Error::new(field.ty.span(), "duplicate #[from] attribute"));
                     }
@@ -743,16 +1561,42 @@ fn extract_variant_from(fields: &mut Fields) -> syn::Result<Option<VariantFrom>>
     }
 }
 
-fn take_from_attr(attrs: &mut Vec<Attribute>) -> syn::Result<bool> {
+/// The field marked `#[source]`, which `Error::source()` returns.
+fn take_source_field(fields: &mut Fields) -> syn::Result<Option<syn::Member>> {
+    let mut source = None;
+    for (index, field) in fields.iter_mut().enumerate() {
+        if take_marker_attr(&mut field.attrs, "source")? {
+            if source.is_some() {
+                return Err(Error::new(field.ty.span(), "duplicate #[source] attribute"));
+            }
+            source = Some(
+                field
+                    .ident
+                    .clone()
+                    .map_or_else(|| syn::Member::Unnamed(index.into()), syn::Member::Named),
+            );
+        }
+    }
+    Ok(source)
+}
+
+/// Strips an argument-less `#[name]` marker from `attrs`, reporting whether it was present.
+fn take_marker_attr(attrs: &mut Vec<Attribute>, name: &str) -> syn::Result<bool> {
     let mut found = false;
     let mut retained = Vec::new();
     for attr in attrs.drain(..) {
-        if attr.path().is_ident("from") {
+        if attr.path().is_ident(name) {
             if !matches!(attr.meta, Meta::Path(_)) {
-                return Err(Error::new_spanned(attr, "#[from] does not take arguments"));
+                return Err(Error::new_spanned(
+                    attr,
+                    format!("#[{name}] does not take arguments"),
+                ));
             }
             if found {
-                return Err(Error::new(attr.span(), "duplicate #[from] attribute"));
+                return Err(Error::new(
+                    attr.span(),
+                    format!("duplicate #[{name}] attribute"),
+                ));
             }
             found = true;
         } else {
@@ -789,34 +1633,95 @@ fn doc_string(attrs: &[Attribute]) -> Option<String> {
 
 struct MainOptions {
     default_logger: bool,
+    strict_config: bool,
+    worker_threads: Option<LitInt>,
+    blocking_threads: Option<LitInt>,
 }
 
 impl MainOptions {
+    const SUPPORTED: &'static str = "supported options are `default_logger = true|false`, `config = strict`, `worker_threads = N` and `blocking_threads = N`";
+
     fn from_args(args: &Punctuated<MetaNameValue, Token![,]>) -> syn::Result<Self> {
         let mut options = Self {
             default_logger: true,
+            strict_config: false,
+            worker_threads: None,
+            blocking_threads: None,
         };
 
         for meta in args {
-            if !meta.path.is_ident("default_logger") {
+            if meta.path.is_ident("default_logger") {
+                options.default_logger = match &meta.value {
+                    Expr::Lit(ExprLit {
+                        lit: Lit::Bool(bool_lit),
+                        ..
+                    }) => bool_lit.value,
+                    other => {
+                        return Err(Error::new_spanned(other, "expected boolean literal"));
+                    }
+                };
+            } else if meta.path.is_ident("config") {
+                options.strict_config = match &meta.value {
+                    Expr::Path(path) if path.path.is_ident("strict") => true,
+                    other => return Err(Error::new_spanned(other, "expected `strict`")),
+                };
+            } else if meta.path.is_ident("worker_threads") {
+                // `0` means one thread per CPU.
+                options.worker_threads = Some(thread_count(&meta.value, 0)?);
+            } else if meta.path.is_ident("blocking_threads") {
+                options.blocking_threads = Some(thread_count(&meta.value, 1)?);
+            } else {
                 return Err(Error::new_spanned(
                     &meta.path,
-                    "unsupported option, expected `default_logger = true|false`",
+                    format!("unsupported option; {}", Self::SUPPORTED),
                 ));
             }
-
-            let value = match &meta.value {
-                Expr::Lit(ExprLit {
-                    lit: Lit::Bool(bool_lit),
-                    ..
-                }) => bool_lit.value,
-                other => {
-                    return Err(Error::new_spanned(other, "expected boolean literal"));
-                }
-            };
-            options.default_logger = value;
         }
 
         Ok(options)
     }
+
+    /// Call to `launch_with_config` with the loaded configuration and the configured threads.
+    fn launch_call(&self, factory: &proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+        let worker_threads = self
+            .worker_threads
+            .as_ref()
+            .map(|threads| quote! { .worker_threads(#threads) });
+        let blocking_threads = self
+            .blocking_threads
+            .as_ref()
+            .map(|threads| quote! { .blocking_threads(#threads) });
+        quote! {
+            ::skyzen::runtime::native::launch_with_config(
+                ::skyzen::runtime::native::RuntimeOptions::new()
+                    #worker_threads
+                    #blocking_threads,
+                __skyzen_config,
+                || #factory,
+            );
+        }
+    }
+}
+
+/// Parse a thread count of at least `min` from an integer literal without a suffix.
+fn thread_count(value: &Expr, min: usize) -> syn::Result<LitInt> {
+    let Expr::Lit(ExprLit {
+        lit: Lit::Int(lit), ..
+    }) = value
+    else {
+        return Err(Error::new_spanned(value, "expected an integer literal"));
+    };
+    if !lit.suffix().is_empty() {
+        return Err(Error::new(lit.span(), "thread counts take no type suffix"));
+    }
+    let count = lit
+        .base10_parse::<usize>()
+        .map_err(|_| Error::new(lit.span(), "thread count is too large"))?;
+    if count < min {
+        return Err(Error::new(
+            lit.span(),
+            format!("thread count must be at least {min}"),
+        ));
+    }
+    Ok(lit.clone())
 }
@@ -0,0 +1,221 @@
+//! Guards that check OAuth scopes carried by the authenticated claims.
+
+use std::{fmt, marker::PhantomData};
+
+use http::StatusCode;
+
+use crate::{extract::Extractor, utils::State, Request};
+
+/// Claims that grant scopes or permissions.
+pub trait HasScopes {
+    /// Whether `scope` is granted.
+    fn has_scope(&self, scope: &str) -> bool;
+}
+
+/// Claims carrying an OAuth `scope` string of space-delimited scopes.
+///
+/// Implementing it provides [`HasScopes`]:
+///
+/// ```
+/// use skyzen::auth::ScopeClaims;
+///
+/// #[derive(Clone)]
+/// struct Claims {
+///     sub: String,
+///     scope: String,
+/// }
+///
+/// impl ScopeClaims for Claims {
+///     fn scope(&self) -> &str {
+///         &self.scope
+///     }
+/// }
+/// ```
+pub trait ScopeClaims {
+    /// The space-delimited scopes, such as `"orders:read orders:write"`.
+    fn scope(&self) -> &str;
+}
+
+impl<T: ScopeClaims> HasScopes for T {
+    fn has_scope(&self, scope: &str) -> bool {
+        self.scope().split_ascii_whitespace().any(|s| s == scope)
+    }
+}
+
+/// Names the scope a [`RequireScope`] guard demands.
+pub trait RequiredScope {
+    /// The scope, such as `"orders:write"`.
+    const SCOPE: &'static str;
+}
+
+/// Extract the claims `T` stored by
+/// [`AuthMiddleware`](crate::middleware::auth::AuthMiddleware), rejecting with `403` unless they
+/// grant the scope named by `S`.
+///
+/// ```
+/// use skyzen::{
+///     auth::{RequireScope, RequiredScope, ScopeClaims},
+///     middleware::auth::{AuthMiddleware, StaticApiKeys, ApiKeyAuthenticator},
+///     routing::{CreateRouteNode, Route},
+///     Result,
+/// };
+///
+/// #[derive(Clone)]
+/// struct Claims {
+///     scope: String,
+/// }
+///
+/// impl ScopeClaims for Claims {
+///     fn scope(&self) -> &str {
+///         &self.scope
+///     }
+/// }
+///
+/// struct OrdersWrite;
+///
+/// impl RequiredScope for OrdersWrite {
+///     const SCOPE: &'static str = "orders:write";
+/// }
+///
+/// async fn create_order(RequireScope(claims, ..): RequireScope<Claims, OrdersWrite>) -> Result<&'static str> {
+///     Ok("created")
+/// }
+///
+/// let keys = StaticApiKeys::new().with_key("key", Claims { scope: "orders:write".into() });
+/// let route = Route::new(("/orders".post(create_order),))
+///     .middleware(AuthMiddleware::new(ApiKeyAuthenticator::new(keys)));
+/// ```
+pub struct RequireScope<T, S>(pub T, pub PhantomData<S>);
+
+impl<T: fmt::Debug, S> fmt::Debug for RequireScope<T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("RequireScope").field(&self.0).finish()
+    }
+}
+
+impl<T, S> RequireScope<T, S> {
+    /// The guarded claims.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T, S> Extractor for RequireScope<T, S>
+where
+    T: HasScopes + Send + Sync + Clone + 'static,
+    S: RequiredScope + Send + Sync + 'static,
+{
+    type Error = ScopeError;
+    async fn extract(request: &mut Request) -> Result<Self, Self::Error> {
+        let State(claims) = request
+            .extensions()
+            .get::<State<T>>()
+            .cloned()
+            .ok_or(ScopeError::Unauthenticated)?;
+        if claims.has_scope(S::SCOPE) {
+            Ok(Self(claims, PhantomData))
+        } else {
+            Err(ScopeError::MissingScope(S::SCOPE))
+        }
+    }
+}
+
+/// An error occurred while checking the scopes of a request.
+#[skyzen::error]
+pub enum ScopeError {
+    /// No claims were stored, so the request was not authenticated.
+    #[error("Authentication required", status = StatusCode::UNAUTHORIZED)]
+    Unauthenticated,
+    /// The claims do not grant the required scope.
+    #[error("Missing scope `{0}`", status = StatusCode::FORBIDDEN)]
+    MissingScope(&'static str),
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::{RequireScope, RequiredScope, ScopeClaims};
+    use crate::{
+        middleware::auth::{ApiKeyAuthenticator, AuthMiddleware, StaticApiKeys},
+        routing::{CreateRouteNode, Route, Router},
+        Body, Endpoint, Method, Request, Result, StatusCode,
+    };
+
+    #[derive(Debug, Clone)]
+    struct Claims {
+        sub: &'static str,
+        scope: &'static str,
+    }
+
+    impl ScopeClaims for Claims {
+        fn scope(&self) -> &str {
+            self.scope
+        }
+    }
+
+    struct OrdersWrite;
+
+    impl RequiredScope for OrdersWrite {
+        const SCOPE: &'static str = "orders:write";
+    }
+
+    async fn create_order(
+        RequireScope(claims, ..): RequireScope<Claims, OrdersWrite>,
+    ) -> Result<String> {
+        Ok(format!("created by {}", claims.sub))
+    }
+
+    fn router() -> Router {
+        let keys = StaticApiKeys::new()
+            .with_key(
+                "writer",
+                Claims {
+                    sub: "alice",
+                    scope: "orders:read orders:write",
+                },
+            )
+            .with_key(
+                "reader",
+                Claims {
+                    sub: "bob",
+                    scope: "orders:read orders:writer",
+                },
+            );
+        Route::new(("/orders".post(create_order),))
+            .middleware(AuthMiddleware::new(ApiKeyAuthenticator::new(keys)))
+            .build()
+    }
+
+    fn request(key: &'static str) -> Request {
+        let mut request = Request::new(Body::empty());
+        *request.uri_mut() = "/orders".parse().unwrap();
+        *request.method_mut() = Method::POST;
+        request
+            .headers_mut()
+            .insert("x-api-key", key.parse().unwrap());
+        request
+    }
+
+    #[tokio::test]
+    async fn allows_claims_with_scope() {
+        let mut req = request("writer");
+        let response = router().respond(&mut req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().into_string().await.unwrap();
+        assert_eq!(body, "created by alice");
+    }
+
+    #[tokio::test]
+    async fn rejects_claims_without_scope() {
+        let mut req = request("reader");
+        let error = router().go(req).await.unwrap_err();
+        assert_eq!(error.status(), StatusCode::FORBIDDEN);
+        assert!(error.to_string().ends_with("Missing scope `orders:write`"));
+
+        req = Request::new(Body::empty());
+        *req.uri_mut() = "/orders".parse().unwrap();
+        *req.method_mut() = Method::POST;
+        let route = Route::new(("/orders".post(create_order),)).build();
+        let error = route.go(req).await.unwrap_err();
+        assert_eq!(error.status(), StatusCode::UNAUTHORIZED);
+    }
+}
@@ -0,0 +1,7 @@
+//! Authorization guards for authenticated users.
+//!
+//! [`AuthMiddleware`](crate::middleware::auth::AuthMiddleware) authenticates a request and stores
+//! its claims; the extractors here then decide whether those claims may reach a handler.
+
+pub mod guard;
+pub use guard::{HasScopes, RequireScope, RequiredScope, ScopeClaims, ScopeError};
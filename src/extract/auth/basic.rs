@@ -0,0 +1,141 @@
+//! Extract HTTP Basic credentials.
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use http::StatusCode;
+
+use crate::{extract::Extractor, header, Request};
+
+/// Credentials from an `Authorization: Basic <base64(username:password)>` header.
+///
+/// The scheme is matched case-insensitively and both parts must be valid UTF-8. The extractor
+/// only rejects with `401`; to also send a `WWW-Authenticate: Basic realm=...` challenge, protect
+/// the routes with [`BasicAuthenticator`](crate::middleware::auth::BasicAuthenticator).
+#[derive(Clone, PartialEq, Eq)]
+pub struct BasicAuth {
+    username: String,
+    password: String,
+}
+
+impl std::fmt::Debug for BasicAuth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BasicAuth")
+            .field("username", &self.username)
+            .finish_non_exhaustive()
+    }
+}
+
+impl BasicAuth {
+    /// The user name before the first `:`.
+    #[must_use]
+    pub fn username(&self) -> &str {
+        &self.username
+    }
+
+    /// The password after the first `:`, which may itself contain colons.
+    #[must_use]
+    pub fn password(&self) -> &str {
+        &self.password
+    }
+
+    pub(crate) fn resolve(request: &Request) -> Result<Self, BasicAuthError> {
+        let value = request
+            .headers()
+            .get(header::AUTHORIZATION)
+            .ok_or(BasicAuthError::Missing)?;
+        let value = value.to_str().map_err(|_| BasicAuthError::InvalidUtf8)?;
+        let (scheme, encoded) = value.split_once(' ').ok_or(BasicAuthError::Missing)?;
+        if !scheme.eq_ignore_ascii_case("basic") {
+            return Err(BasicAuthError::Missing);
+        }
+        let decoded = STANDARD
+            .decode(encoded.trim())
+            .map_err(|_| BasicAuthError::InvalidBase64)?;
+        let decoded = String::from_utf8(decoded).map_err(|_| BasicAuthError::InvalidUtf8)?;
+        let (username, password) = decoded
+            .split_once(':')
+            .ok_or(BasicAuthError::MissingColon)?;
+        Ok(Self {
+            username: username.to_owned(),
+            password: password.to_owned(),
+        })
+    }
+}
+
+impl Extractor for BasicAuth {
+    type Error = BasicAuthError;
+    async fn extract(request: &mut Request) -> Result<Self, Self::Error> {
+        Self::resolve(request)
+    }
+}
+
+/// An error occurred while authenticating with HTTP Basic credentials.
+#[skyzen::error(status = StatusCode::UNAUTHORIZED)]
+pub enum BasicAuthError {
+    /// The request carries no `Authorization: Basic` header.
+    #[error("Missing basic credentials")]
+    Missing,
+    /// The credentials are not valid base64.
+    #[error("Invalid base64 in basic credentials")]
+    InvalidBase64,
+    /// The decoded credentials have no `:` separating user name and password.
+    #[error("Basic credentials must be `username:password`")]
+    MissingColon,
+    /// The header or the decoded credentials are not valid UTF-8.
+    #[error("Invalid UTF-8 in basic credentials")]
+    InvalidUtf8,
+    /// The user name or password is wrong.
+    #[error("Invalid user name or password")]
+    InvalidCredentials,
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::{BasicAuth, BasicAuthError};
+    use crate::{Body, Request};
+    use http_kit::header::HeaderValue;
+    use skyzen_core::Extractor;
+
+    fn request(authorization: &[u8]) -> Request {
+        let mut request = Request::new(Body::empty());
+        request.headers_mut().insert(
+            "authorization",
+            HeaderValue::from_bytes(authorization).unwrap(),
+        );
+        request
+    }
+
+    #[tokio::test]
+    async fn decodes_credentials() {
+        // "aladdin:open:sesame"
+        let mut req = request(b"basic YWxhZGRpbjpvcGVuOnNlc2FtZQ==");
+        let auth = BasicAuth::extract(&mut req).await.unwrap();
+        assert_eq!(auth.username(), "aladdin");
+        assert_eq!(auth.password(), "open:sesame");
+        assert!(!format!("{auth:?}").contains("sesame"));
+    }
+
+    #[tokio::test]
+    async fn rejects_malformed_credentials() {
+        let cases: [(&[u8], &str); 5] = [
+            (b"Bearer abc", "Missing basic credentials"),
+            (b"Basic !!!", "Invalid base64 in basic credentials"),
+            // "nocolon"
+            (
+                b"Basic bm9jb2xvbg==",
+                "Basic credentials must be `username:password`",
+            ),
+            // [0xff, b':', b'x']
+            (b"Basic /zp4", "Invalid UTF-8 in basic credentials"),
+            (b"Basic \xff", "Invalid UTF-8 in basic credentials"),
+        ];
+        for (header, expected) in cases {
+            let mut req = request(header);
+            let error = BasicAuth::extract(&mut req).await.unwrap_err();
+            assert_eq!(error.to_string(), expected);
+        }
+
+        let mut req = Request::new(Body::empty());
+        let error = BasicAuth::extract(&mut req).await.unwrap_err();
+        assert!(matches!(error, BasicAuthError::Missing));
+    }
+}
@@ -0,0 +1,283 @@
+//! Extract an RFC 6750 bearer token.
+
+use std::convert::Infallible;
+
+use http::StatusCode;
+use http_kit::{middleware::MiddlewareError, Endpoint, Middleware, Response};
+
+use crate::{
+    extract::Extractor,
+    header,
+    utils::{percent::percent_decode, State},
+    Request,
+};
+
+/// The bearer token presented by the client, such as the `abc` in `Authorization: Bearer abc`.
+///
+/// The scheme is matched case-insensitively and the token must use the RFC 6750 `token68`
+/// charset. By default only the `Authorization` header is consulted; install a
+/// [`BearerTokenSource`] to also accept a query parameter or a cookie.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BearerToken(pub String);
+
+impl_deref!(BearerToken, String);
+
+impl BearerToken {
+    fn resolve(request: &Request) -> Result<Self, BearerTokenError> {
+        let source = request
+            .extensions()
+            .get::<BearerTokenSource>()
+            .or_else(|| {
+                request
+                    .extensions()
+                    .get::<State<BearerTokenSource>>()
+                    .map(|state| &state.0)
+            })
+            .cloned()
+            .unwrap_or_default();
+
+        let token = source
+            .header_token(request)?
+            .or_else(|| source.query_token(request))
+            .or_else(|| source.cookie_token(request))
+            .ok_or(BearerTokenError::Missing)?;
+        if is_token68(&token) {
+            Ok(Self(token))
+        } else {
+            Err(BearerTokenError::Invalid)
+        }
+    }
+}
+
+impl Extractor for BearerToken {
+    type Error = BearerTokenError;
+    async fn extract(request: &mut Request) -> Result<Self, Self::Error> {
+        Self::resolve(request)
+    }
+}
+
+/// Where [`BearerToken`] looks for the token.
+///
+/// Install it as a middleware (or wrap it in [`State`]) and it is stored in the request extensions
+/// for [`BearerToken`] to read. Sources are tried in order: header, query parameter, cookie.
+///
+/// Browsers cannot set headers on `WebSocket` connections, so such clients usually pass the token
+/// as a query parameter instead:
+///
+/// ```
+/// use skyzen::extract::BearerTokenSource;
+///
+/// let source = BearerTokenSource::new().query("access_token");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BearerTokenSource {
+    header: bool,
+    query: Option<String>,
+    cookie: Option<String>,
+}
+
+impl Default for BearerTokenSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BearerTokenSource {
+    /// Read the token from the `Authorization` header only.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            header: true,
+            query: None,
+            cookie: None,
+        }
+    }
+
+    /// Also accept the token from the query parameter `name`.
+    #[must_use]
+    pub fn query(mut self, name: impl Into<String>) -> Self {
+        self.query = Some(name.into());
+        self
+    }
+
+    /// Also accept the token from the cookie `name`.
+    #[must_use]
+    pub fn cookie(mut self, name: impl Into<String>) -> Self {
+        self.cookie = Some(name.into());
+        self
+    }
+
+    /// Stop reading the `Authorization` header.
+    #[must_use]
+    pub const fn without_header(mut self) -> Self {
+        self.header = false;
+        self
+    }
+
+    fn header_token(&self, request: &Request) -> Result<Option<String>, BearerTokenError> {
+        if !self.header {
+            return Ok(None);
+        }
+        let Some(value) = request.headers().get(header::AUTHORIZATION) else {
+            return Ok(None);
+        };
+        let value = value.to_str().map_err(|_| BearerTokenError::Invalid)?;
+        let Some((scheme, token)) = value.split_once(' ') else {
+            return Ok(None);
+        };
+        if !scheme.eq_ignore_ascii_case("bearer") {
+            return Ok(None);
+        }
+        Ok(Some(token.trim_start_matches(' ').to_owned()))
+    }
+
+    fn query_token(&self, request: &Request) -> Option<String> {
+        let name = self.query.as_deref()?;
+        request.uri().query()?.split('&').find_map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (key == name).then(|| percent_decode(value)).flatten()
+        })
+    }
+
+    fn cookie_token(&self, request: &Request) -> Option<String> {
+        let name = self.cookie.as_deref()?;
+        request
+            .headers()
+            .get_all(header::COOKIE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(cookie::Cookie::split_parse_encoded)
+            .filter_map(Result::ok)
+            .find(|cookie| cookie.name() == name)
+            .map(|cookie| cookie.value().to_owned())
+    }
+}
+
+impl Middleware for BearerTokenSource {
+    type Error = Infallible;
+    async fn handle<N: Endpoint>(
+        &mut self,
+        request: &mut Request,
+        mut next: N,
+    ) -> Result<Response, MiddlewareError<N::Error, Self::Error>> {
+        request.extensions_mut().insert(self.clone());
+        next.respond(request)
+            .await
+            .map_err(MiddlewareError::Endpoint)
+    }
+}
+
+/// An error occurred while extracting a [`BearerToken`].
+#[skyzen::error(status = StatusCode::UNAUTHORIZED)]
+pub enum BearerTokenError {
+    /// None of the configured sources carries a bearer token.
+    #[error("Missing bearer token")]
+    Missing,
+    /// The token is not valid `token68`.
+    #[error("invalid_request: malformed bearer token")]
+    Invalid,
+}
+
+/// `1*( ALPHA / DIGIT / "-" / "." / "_" / "~" / "+" / "/" ) *"="`
+fn is_token68(token: &str) -> bool {
+    let body = token.trim_end_matches('=');
+    !body.is_empty()
+        && body.bytes().all(|b| {
+            b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~' | b'+' | b'/')
+        })
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::{BearerToken, BearerTokenError, BearerTokenSource};
+    use crate::{utils::State, Body, Request};
+    use http_kit::header::HeaderValue;
+    use skyzen_core::Extractor;
+
+    fn request(uri: &str, headers: &[(&'static str, &'static str)]) -> Request {
+        let mut request = Request::new(Body::empty());
+        *request.uri_mut() = uri.parse().unwrap();
+        for (name, value) in headers {
+            request
+                .headers_mut()
+                .append(*name, HeaderValue::from_static(value));
+        }
+        request
+    }
+
+    #[tokio::test]
+    async fn matches_scheme_case_insensitively() {
+        for value in ["Bearer abc.DEF-1", "bearer abc.DEF-1", "BEARER  abc.DEF-1"] {
+            let mut req = request("/", &[("authorization", value)]);
+            let token = BearerToken::extract(&mut req).await.unwrap();
+            assert_eq!(token.as_str(), "abc.DEF-1");
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_tokens_outside_token68() {
+        for value in [
+            "Bearer a b",
+            "Bearer ",
+            "Bearer =abc",
+            "Bearer ab=c",
+            "Bearer a\"b",
+        ] {
+            let mut req = request("/", &[("authorization", value)]);
+            let error = BearerToken::extract(&mut req).await.unwrap_err();
+            assert!(matches!(error, BearerTokenError::Invalid), "{value}");
+        }
+        let mut req = request("/", &[("authorization", "Bearer abc==")]);
+        assert_eq!(BearerToken::extract(&mut req).await.unwrap().0, "abc==");
+    }
+
+    #[tokio::test]
+    async fn header_only_by_default() {
+        let mut req = request(
+            "/?access_token=abc",
+            &[
+                ("authorization", "Basic dXNlcjpwdw=="),
+                ("cookie", "token=abc"),
+            ],
+        );
+        let error = BearerToken::extract(&mut req).await.unwrap_err();
+        assert!(matches!(error, BearerTokenError::Missing));
+    }
+
+    #[tokio::test]
+    async fn reads_configured_query_and_cookie() {
+        let source = BearerTokenSource::new()
+            .query("access_token")
+            .cookie("token");
+
+        let mut req = request("/ws?room=1&access_token=a%2Bb%3D%3D", &[]);
+        req.extensions_mut().insert(source.clone());
+        assert_eq!(BearerToken::extract(&mut req).await.unwrap().0, "a+b==");
+
+        let mut req = request("/", &[("cookie", "theme=dark; token=from-cookie")]);
+        req.extensions_mut().insert(State(source.clone()));
+        assert_eq!(
+            BearerToken::extract(&mut req).await.unwrap().0,
+            "from-cookie"
+        );
+
+        let mut req = request(
+            "/?access_token=from-query",
+            &[("authorization", "Bearer from-header")],
+        );
+        req.extensions_mut().insert(source);
+        assert_eq!(
+            BearerToken::extract(&mut req).await.unwrap().0,
+            "from-header"
+        );
+    }
+
+    #[tokio::test]
+    async fn can_disable_header() {
+        let mut req = request("/", &[("authorization", "Bearer abc")]);
+        req.extensions_mut()
+            .insert(BearerTokenSource::new().without_header().cookie("token"));
+        let error = BearerToken::extract(&mut req).await.unwrap_err();
+        assert!(matches!(error, BearerTokenError::Missing));
+    }
+}
@@ -0,0 +1,7 @@
+//! Extract credentials presented by the client.
+
+pub mod basic;
+pub use basic::{BasicAuth, BasicAuthError};
+
+pub mod bearer;
+pub use bearer::{BearerToken, BearerTokenError, BearerTokenSource};
@@ -0,0 +1,176 @@
+//! Run work after the response has been sent.
+//!
+//! [`BackgroundTasks`] spawns futures that are not awaited by the handler, such as flushing
+//! analytics or writing audit logs, so the client is not kept waiting for them:
+//!
+//! ```
+//! use skyzen::extract::BackgroundTasks;
+//!
+//! async fn handler(tasks: BackgroundTasks) -> skyzen::Result<&'static str> {
+//!     tasks.spawn(async {
+//!         // Report the request to an analytics service.
+//!     });
+//!     Ok("accepted")
+//! }
+//! ```
+//!
+//! On Cloudflare Workers each task is registered with `ctx.waitUntil`, which keeps the Worker
+//! alive until it settles. On native targets tasks run on the executor the server was started
+//! with, falling back to the global `executor-core` executor.
+//!
+//! Tasks start when they are spawned and may finish before or after the response is sent; no
+//! order is guaranteed between tasks, or between a task and the response. Panics and failures
+//! inside a task are not reported to the client.
+
+use std::{convert::Infallible, future::Future};
+
+#[cfg(not(target_arch = "wasm32"))]
+use executor_core::{AnyExecutor, DefaultExecutor, Executor};
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::Arc;
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::{JsCast, JsValue};
+
+use crate::{extract::Extractor, Request};
+
+/// Spawns work that may outlive the response, see the [module docs](self).
+#[derive(Clone)]
+pub struct BackgroundTasks {
+    #[cfg(not(target_arch = "wasm32"))]
+    executor: Arc<AnyExecutor>,
+    /// The Workers `ExecutionContext`, or `undefined` outside of `runtime::wasm::launch`.
+    #[cfg(target_arch = "wasm32")]
+    ctx: JsValue,
+}
+
+// SAFETY: WASM is single-threaded, so Send/Sync is safe for JsValue wrappers.
+#[cfg(target_arch = "wasm32")]
+unsafe impl Send for BackgroundTasks {}
+#[cfg(target_arch = "wasm32")]
+unsafe impl Sync for BackgroundTasks {}
+
+impl std::fmt::Debug for BackgroundTasks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BackgroundTasks").finish_non_exhaustive()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl BackgroundTasks {
+    /// Run `task` in the background without waiting for it.
+    pub fn spawn<F>(&self, task: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        self.executor.spawn(task).detach();
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl BackgroundTasks {
+    /// Wrap the `ctx` object passed to the Worker's `fetch` handler.
+    pub(crate) const fn from_context(ctx: JsValue) -> Self {
+        Self { ctx }
+    }
+
+    /// Run `task` in the background without waiting for it.
+    ///
+    /// The task is registered with `ctx.waitUntil`. Without an execution context it is spawned
+    /// on the current thread, and the runtime may stop before it finishes.
+    pub fn spawn<F>(&self, task: F)
+    where
+        F: Future<Output = ()> + 'static,
+    {
+        let wait_until = js_sys::Reflect::get(&self.ctx, &JsValue::from_str("waitUntil"))
+            .ok()
+            .and_then(|function| function.dyn_into::<js_sys::Function>().ok());
+        let Some(wait_until) = wait_until else {
+            wasm_bindgen_futures::spawn_local(task);
+            return;
+        };
+
+        let promise = wasm_bindgen_futures::future_to_promise(async move {
+            task.await;
+            Ok(JsValue::UNDEFINED)
+        });
+        if let Err(error) = wait_until.call1(&self.ctx, &promise) {
+            tracing::warn!("ctx.waitUntil failed: {error:?}");
+        }
+    }
+}
+
+impl Extractor for BackgroundTasks {
+    type Error = Infallible;
+
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn extract(request: &mut Request) -> Result<Self, Self::Error> {
+        let executor = request
+            .extensions()
+            .get::<Arc<AnyExecutor>>()
+            .cloned()
+            .unwrap_or_else(|| Arc::new(AnyExecutor::new(DefaultExecutor)));
+        Ok(Self { executor })
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    async fn extract(request: &mut Request) -> Result<Self, Self::Error> {
+        Ok(request
+            .extensions()
+            .get::<Self>()
+            .cloned()
+            .unwrap_or_else(|| Self::from_context(JsValue::UNDEFINED)))
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use std::sync::Arc;
+
+    use executor_core::{tokio::TokioGlobal, AnyExecutor};
+
+    use super::BackgroundTasks;
+    use crate::{extract::Extractor, Body, Request};
+
+    #[tokio::test]
+    async fn spawns_on_the_request_executor() {
+        let mut request = Request::new(Body::empty());
+        request
+            .extensions_mut()
+            .insert(Arc::new(AnyExecutor::new(TokioGlobal)));
+        let tasks = BackgroundTasks::extract(&mut request).await.unwrap();
+
+        let (sender, receiver) = async_channel::bounded(1);
+        tasks.spawn(async move {
+            sender.send("done").await.unwrap();
+        });
+        assert_eq!(receiver.recv().await.unwrap(), "done");
+    }
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+
+    use wasm_bindgen_futures::JsFuture;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::BackgroundTasks;
+
+    #[wasm_bindgen_test]
+    async fn registers_tasks_with_wait_until() {
+        let ctx = js_sys::Object::new();
+        let wait_until = js_sys::Function::new_with_args("promise", "this.pending = promise");
+        js_sys::Reflect::set(&ctx, &"waitUntil".into(), &wait_until).unwrap();
+        let tasks = BackgroundTasks::from_context(ctx.clone().into());
+
+        let ran = Rc::new(Cell::new(false));
+        let flag = Rc::clone(&ran);
+        tasks.spawn(async move { flag.set(true) });
+
+        let pending: js_sys::Promise = js_sys::Reflect::get(&ctx, &"pending".into())
+            .unwrap()
+            .into();
+        JsFuture::from(pending).await.unwrap();
+        assert!(ran.get());
+    }
+}
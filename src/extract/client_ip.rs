@@ -1,15 +1,18 @@
 //! Look up the IP address of client.
 
 use std::{
-    net::{AddrParseError, IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    convert::Infallible,
+    net::{AddrParseError, IpAddr, Ipv6Addr, SocketAddr},
     str::{FromStr, Utf8Error},
 };
 
-use http::StatusCode;
-use http_kit::http_error;
+use http::{HeaderMap, StatusCode};
+use http_kit::{http_error, middleware::MiddlewareError, Endpoint, Middleware, Response};
 
 use crate::{extract::Extractor, header, header::HeaderName, Request};
 
+const X_FORWARDED_FOR: HeaderName = HeaderName::from_static("x-forwarded-for");
+
 http_error!(/// Raised when the connection metadata does not expose the remote address.
 pub MissingRemoteAddr,
 StatusCode::INTERNAL_SERVER_ERROR, 
@@ -37,6 +40,9 @@ impl Extractor for PeerAddr {
         crate::openapi::schema_of::<Self>().map(|schema| crate::openapi::ExtractorSchema {
             content_type: None,
             schema: Some(schema),
+            location: crate::openapi::ParameterLocation::Body,
+            required: true,
+            extra_content_types: &[],
         })
     }
 
@@ -48,13 +54,22 @@ impl Extractor for PeerAddr {
     }
 }
 
+/// Client address reported by a PROXY protocol header.
+///
+/// The native runtime stores it in the request extensions when PROXY protocol parsing is enabled
+/// on the server builder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProxiedAddr(pub SocketAddr);
+
 /// Extract the IP address of the client.
 ///
 /// This extractor will check for the presence of the `Forwarded` or `X-Forwarded-For` header, ensuring it works even when behind a proxy.
-/// The order of determination is as follows: `Forwarded` / `X-Forwarded-For` / the peer address of the client.
+/// The order of determination is as follows: [`ProxiedAddr`] / `Forwarded` / `X-Forwarded-For` / the peer address of the client.
+/// Which of those headers are believed is decided by the [`ClientIpConfig`] in the request extensions:
+/// trusted proxies are only believed through the [`ForwardedHeader`] they write.
 /// # Warning
-/// This extractor carries the risk of spoofing because the client can send a fake `Forwarded` or `X-Forwarded-For` header.
-/// If you're working on something like rate limiting, consider using the `PeerAddr` extractor.
+/// Without a [`ClientIpConfig`] this extractor carries the risk of spoofing because the client can send a fake `Forwarded` or `X-Forwarded-For` header.
+/// If you're working on something like rate limiting, install [`ClientIpConfig::TrustNone`] or list your proxies with [`ClientIpConfig::TrustProxies`].
 #[derive(Debug, Clone)]
 pub struct ClientIp(pub IpAddr);
 
@@ -62,32 +77,21 @@ impl_deref!(PeerAddr, SocketAddr);
 
 impl_deref!(ClientIp, IpAddr);
 
+impl_deref!(ProxiedAddr, SocketAddr);
+
 impl Extractor for ClientIp {
     type Error = ClientIpError;
     async fn extract(request: &mut Request) -> Result<Self, Self::Error> {
-        if let Some(v) = request.headers().get(header::FORWARDED) {
-            if let Some(addr) = parse_forwarded(v.as_bytes())? {
-                return Ok(Self(addr));
-            }
-        }
-
-        if let Some(v) = request
-            .headers()
-            .get(HeaderName::from_static("x-forwarded-for"))
-        {
-            if let Some(addr) = parse_x_forwarded_for(v.as_bytes())? {
-                return Ok(Self(addr));
-            }
+        if let Some(addr) = request.extensions().get::<ProxiedAddr>() {
+            return Ok(Self(addr.ip()));
         }
 
-        Ok(Self(
-            request
-                .extensions()
-                .get::<PeerAddr>()
-                .ok_or(ClientIpError::MissingRemoteAddr)?
-                .0
-                .ip(),
-        ))
+        let peer = request.extensions().get::<PeerAddr>().map(|peer| peer.ip());
+        let config = request
+            .extensions()
+            .get::<ClientIpConfig>()
+            .unwrap_or(&ClientIpConfig::TrustAll);
+        config.resolve(request.headers(), peer).map(Self)
 
         // It's unnecessary to consume the extension.
     }
@@ -97,6 +101,9 @@ impl Extractor for ClientIp {
         crate::openapi::schema_of::<Self>().map(|schema| crate::openapi::ExtractorSchema {
             content_type: None,
             schema: Some(schema),
+            location: crate::openapi::ParameterLocation::Body,
+            required: true,
+            extra_content_types: &[],
         })
     }
 
@@ -108,6 +115,281 @@ impl Extractor for ClientIp {
     }
 }
 
+/// Decides which forwarding headers [`ClientIp`] believes.
+///
+/// Install it as a middleware, the same way as [`State`](crate::utils::State), and it is stored in
+/// the request extensions for [`ClientIp`] to read.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum ClientIpConfig {
+    /// Believe `Forwarded` / `X-Forwarded-For` from any peer and use the leftmost entry.
+    ///
+    /// This is what happens when no config is installed. Any client can spoof it.
+    #[default]
+    TrustAll,
+    /// Ignore forwarding headers and always use the peer address.
+    TrustNone,
+    /// Believe `header` only when the peer is one of `proxies`.
+    ///
+    /// The chain is walked from the right, skipping trusted proxies; the first untrusted address
+    /// is the client. The other forwarding header is ignored, since proxies pass it on untouched
+    /// from the client.
+    TrustProxies {
+        /// The addresses of the proxies.
+        proxies: Vec<IpCidr>,
+        /// The header the proxies write.
+        header: ForwardedHeader,
+    },
+    /// Assume exactly `hops` proxies, all writing `header`, sit in front of the server and use
+    /// the address the outermost one saw. With `hops: 0` this behaves like
+    /// [`ClientIpConfig::TrustNone`].
+    TrustHops {
+        /// The number of proxies.
+        hops: usize,
+        /// The header the proxies write.
+        header: ForwardedHeader,
+    },
+}
+
+/// The forwarding header a trusted proxy writes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ForwardedHeader {
+    /// `X-Forwarded-For`, with `X-Forwarded-Host` and `X-Forwarded-Proto`, as written by nginx
+    /// and most load balancers.
+    #[default]
+    XForwarded,
+    /// The standard `Forwarded` header of RFC 7239.
+    Forwarded,
+}
+
+impl ClientIpConfig {
+    /// Trust the proxies in `ranges`, given as CIDR blocks (`10.0.0.0/8`) or single addresses,
+    /// through the `X-Forwarded-*` headers; see [`header`](Self::header) for `Forwarded`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any range is not valid CIDR notation.
+    pub fn trust_proxies<'a>(
+        ranges: impl IntoIterator<Item = &'a str>,
+    ) -> Result<Self, InvalidCidr> {
+        ranges
+            .into_iter()
+            .map(str::parse)
+            .collect::<Result<_, _>>()
+            .map(|proxies| Self::TrustProxies {
+                proxies,
+                header: ForwardedHeader::default(),
+            })
+    }
+
+    /// Trust `hops` proxies through the `X-Forwarded-*` headers; see [`header`](Self::header)
+    /// for `Forwarded`.
+    #[must_use]
+    pub const fn trust_hops(hops: usize) -> Self {
+        Self::TrustHops {
+            hops,
+            header: ForwardedHeader::XForwarded,
+        }
+    }
+
+    /// Read the addresses of trusted proxies from `header` instead.
+    ///
+    /// [`ClientIpConfig::TrustAll`] and [`ClientIpConfig::TrustNone`] are left unchanged.
+    #[must_use]
+    pub const fn header(mut self, header: ForwardedHeader) -> Self {
+        if let Self::TrustProxies { header: slot, .. } | Self::TrustHops { header: slot, .. } =
+            &mut self
+        {
+            *slot = header;
+        }
+        self
+    }
+
+    fn resolve(&self, headers: &HeaderMap, peer: Option<IpAddr>) -> Result<IpAddr, ClientIpError> {
+        let peer = peer.ok_or(ClientIpError::MissingRemoteAddr);
+        match self {
+            Self::TrustAll => {
+                for v in headers.get_all(header::FORWARDED) {
+                    if let Some(addr) = parse_forwarded(v.as_bytes())? {
+                        return Ok(addr);
+                    }
+                }
+                for v in headers.get_all(X_FORWARDED_FOR) {
+                    if let Some(addr) = parse_x_forwarded_for(v.as_bytes())? {
+                        return Ok(addr);
+                    }
+                }
+                peer
+            }
+            Self::TrustNone | Self::TrustHops { hops: 0, .. } => peer,
+            Self::TrustProxies { proxies, header } => {
+                let trusted = |ip: IpAddr| proxies.iter().any(|range| range.contains(ip));
+                let mut client = peer?;
+                if !trusted(client) {
+                    return Ok(client);
+                }
+                for node in forwarded_nodes(headers, *header)?.into_iter().rev() {
+                    client = parse_node(node)?;
+                    if !trusted(client) {
+                        break;
+                    }
+                }
+                Ok(client)
+            }
+            Self::TrustHops { hops, header } => {
+                let peer = peer?;
+                // A chain shorter than `hops` did not pass through every proxy, so none of its
+                // entries can be told apart from ones the client wrote.
+                forwarded_nodes(headers, *header)?
+                    .iter()
+                    .rev()
+                    .nth(hops - 1)
+                    .map_or(Ok(peer), |node| parse_node(node))
+            }
+        }
+    }
+}
+
+/// Which entry of a forwarding header may be believed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TrustedEntry {
+    /// The leftmost entry, as written by the first proxy (or the client).
+    First,
+    /// The rightmost entry, as written by the proxy that connected to us.
+    Last,
+}
+
+impl TrustedEntry {
+    pub(crate) const fn pick<T: Copy>(self, entries: &[T]) -> Option<T> {
+        match self {
+            Self::First => entries.first().copied(),
+            Self::Last => entries.last().copied(),
+        }
+    }
+}
+
+/// Which forwarding headers, such as `X-Forwarded-Host`, may be believed, and which of their
+/// entries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct TrustedForwarding {
+    pub(crate) entry: TrustedEntry,
+    /// The only header family believed, or `None` for both with `Forwarded` first.
+    pub(crate) header: Option<ForwardedHeader>,
+}
+
+impl TrustedForwarding {
+    pub(crate) fn reads(self, header: ForwardedHeader) -> bool {
+        self.header.is_none_or(|trusted| trusted == header)
+    }
+}
+
+impl ClientIpConfig {
+    /// Which forwarding headers may be believed for `request`, if any.
+    pub(crate) fn trusted_forwarding(request: &Request) -> Option<TrustedForwarding> {
+        let peer = request.extensions().get::<PeerAddr>().map(|peer| peer.ip());
+        let config = request
+            .extensions()
+            .get::<Self>()
+            .unwrap_or(&Self::TrustAll);
+        let last = |header| TrustedForwarding {
+            entry: TrustedEntry::Last,
+            header: Some(header),
+        };
+        match config {
+            Self::TrustAll => Some(TrustedForwarding {
+                entry: TrustedEntry::First,
+                header: None,
+            }),
+            Self::TrustNone | Self::TrustHops { hops: 0, .. } => None,
+            Self::TrustProxies { proxies, header } => peer
+                .filter(|peer| proxies.iter().any(|range| range.contains(*peer)))
+                .map(|_| last(*header)),
+            Self::TrustHops { header, .. } => Some(last(*header)),
+        }
+    }
+}
+
+impl Middleware for ClientIpConfig {
+    type Error = Infallible;
+    async fn handle<N: Endpoint>(
+        &mut self,
+        request: &mut Request,
+        mut next: N,
+    ) -> Result<Response, MiddlewareError<N::Error, Self::Error>> {
+        request.extensions_mut().insert(self.clone());
+        next.respond(request)
+            .await
+            .map_err(MiddlewareError::Endpoint)
+    }
+}
+
+/// An IPv4 or IPv6 address range in CIDR notation, such as `10.0.0.0/8` or `fd00::/8`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpCidr {
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl IpCidr {
+    /// Create a range from a network address and prefix length.
+    ///
+    /// Returns `None` if `prefix` is longer than the address (32 bits for IPv4, 128 for IPv6).
+    #[must_use]
+    pub const fn new(network: IpAddr, prefix: u8) -> Option<Self> {
+        let max = if network.is_ipv4() { 32 } else { 128 };
+        if prefix > max {
+            return None;
+        }
+        Some(Self { network, prefix })
+    }
+
+    /// Whether `ip` falls inside this range. IPv4-mapped IPv6 addresses match IPv4 ranges.
+    #[must_use]
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        let (network, ip, bits) = match (self.network, ip.to_canonical()) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => (
+                u128::from(u32::from(network)),
+                u128::from(u32::from(ip)),
+                32,
+            ),
+            (IpAddr::V6(network), IpAddr::V6(ip)) => (u128::from(network), u128::from(ip), 128),
+            _ => return false,
+        };
+        (network ^ ip)
+            .checked_shr(bits - u32::from(self.prefix))
+            .unwrap_or(0)
+            == 0
+    }
+}
+
+impl FromStr for IpCidr {
+    type Err = InvalidCidr;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (network, prefix) = s
+            .split_once('/')
+            .map_or((s, None), |(network, prefix)| (network, Some(prefix)));
+        let network: IpAddr = network.parse()?;
+        let prefix = match prefix {
+            Some(prefix) => prefix.parse().map_err(|_| InvalidCidr::Prefix)?,
+            None if network.is_ipv4() => 32,
+            None => 128,
+        };
+        Self::new(network, prefix).ok_or(InvalidCidr::Prefix)
+    }
+}
+
+/// An error occurred while parsing an [`IpCidr`].
+#[skyzen::error(status = StatusCode::INTERNAL_SERVER_ERROR)]
+pub enum InvalidCidr {
+    /// The network address is not a valid IP address.
+    #[error("Invalid network address")]
+    Addr(#[from] AddrParseError),
+    /// The prefix length is not a number or is too long for the address family.
+    #[error("Invalid prefix length")]
+    Prefix,
+}
+
 /// An error occurred while extracting the client's IP.
 
 #[skyzen::error(status = StatusCode::BAD_REQUEST)]
@@ -126,44 +408,85 @@ pub enum ClientIpError {
     MissingRemoteAddr,
 }
 
-fn parse_forwarded(v: &[u8]) -> Result<Option<IpAddr>, ClientIpError> {
-    for v in v.split(|b| *b == b';') {
-        for v in v.split(|b| *b == b',') {
-            let (mut key, mut value) =
-                split_once(v, b'=').ok_or(ClientIpError::InvalidForwardedHeader)?;
-            trim(&mut key);
+/// Node identifiers from `source`, ordered from the client towards the server.
+fn forwarded_nodes(
+    headers: &HeaderMap,
+    source: ForwardedHeader,
+) -> Result<Vec<&[u8]>, ClientIpError> {
+    let mut nodes = Vec::new();
+    match source {
+        ForwardedHeader::Forwarded => {
+            for v in headers.get_all(header::FORWARDED) {
+                nodes.extend(forwarded_param(v.as_bytes(), b"for")?);
+            }
+        }
+        ForwardedHeader::XForwarded => {
+            for v in headers.get_all(X_FORWARDED_FOR) {
+                nodes.extend(list_values(v.as_bytes()));
+            }
+        }
+    }
+    Ok(nodes)
+}
 
-            if !key.eq_ignore_ascii_case(b"for") {
+/// The value of parameter `name` (such as `for` or `host`) in every element of an RFC 7239
+/// `Forwarded` header.
+pub(crate) fn forwarded_param<'a>(
+    v: &'a [u8],
+    name: &[u8],
+) -> Result<Vec<&'a [u8]>, ClientIpError> {
+    let mut nodes = Vec::new();
+    for element in v.split(|b| *b == b',') {
+        for mut pair in element.split(|b| *b == b';') {
+            trim(&mut pair);
+            if pair.is_empty() {
                 continue;
             }
-
-            trim(&mut value);
-
-            if strip_once(&mut value, b"\"") {
-                if let Some(value) = get_ipv6_str(value) {
-                    return Ok(Some(
-                        Ipv6Addr::from_str(std::str::from_utf8(value)?)?.into(),
-                    ));
-                }
-                return Ok(Some(
-                    Ipv4Addr::from_str(std::str::from_utf8(value)?)?.into(),
-                ));
+            let (mut key, mut value) =
+                split_once(pair, b'=').ok_or(ClientIpError::InvalidForwardedHeader)?;
+            trim(&mut key);
+            if key.eq_ignore_ascii_case(name) {
+                trim(&mut value);
+                nodes.push(value);
             }
-            return Ok(Some(
-                Ipv4Addr::from_str(std::str::from_utf8(value)?)?.into(),
-            ));
         }
     }
-    Ok(None)
+    Ok(nodes)
 }
 
-fn parse_x_forwarded_for(v: &[u8]) -> Result<Option<IpAddr>, ClientIpError> {
-    if let Some(mut v) = v.split(|v| *v == b',').next() {
-        trim(&mut v);
-        Ok(Some(IpAddr::from_str(std::str::from_utf8(v)?)?))
-    } else {
-        Ok(None)
+/// Entries of a comma-separated header such as `X-Forwarded-For` or `X-Forwarded-Host`.
+pub(crate) fn list_values(v: &[u8]) -> impl DoubleEndedIterator<Item = &[u8]> {
+    v.split(|b| *b == b',')
+        .map(|mut node| {
+            trim(&mut node);
+            node
+        })
+        .filter(|node| !node.is_empty())
+}
+
+/// Parse a node identifier: a bare or quoted IP address, optionally with a port.
+fn parse_node(mut node: &[u8]) -> Result<IpAddr, ClientIpError> {
+    strip_once(&mut node, b"\"");
+    if let Some(v6) = get_ipv6_str(node) {
+        return Ok(Ipv6Addr::from_str(std::str::from_utf8(v6)?)?.into());
     }
+    let node = std::str::from_utf8(node)?;
+    IpAddr::from_str(node).or_else(|error| {
+        SocketAddr::from_str(node)
+            .map(|addr| addr.ip())
+            .map_err(|_| error.into())
+    })
+}
+
+fn parse_forwarded(v: &[u8]) -> Result<Option<IpAddr>, ClientIpError> {
+    forwarded_param(v, b"for")?
+        .first()
+        .map(|node| parse_node(node))
+        .transpose()
+}
+
+fn parse_x_forwarded_for(v: &[u8]) -> Result<Option<IpAddr>, ClientIpError> {
+    list_values(v).next().map(parse_node).transpose()
 }
 
 fn split_once(s: &[u8], pat: u8) -> Option<(&[u8], &[u8])> {
@@ -215,11 +538,14 @@ fn get_ipv6_str(s: &[u8]) -> Option<&[u8]> {
     None
 }
 
-#[cfg(test)]
+#[cfg(all(test, not(target_arch = "wasm32")))]
 mod tests {
     use std::{net::IpAddr, str::FromStr};
 
-    use super::{parse_forwarded, parse_x_forwarded_for, ClientIp, ClientIpError};
+    use super::{
+        parse_forwarded, parse_x_forwarded_for, ClientIp, ClientIpConfig, ClientIpError,
+        ForwardedHeader, IpCidr, PeerAddr, ProxiedAddr,
+    };
     use crate::{Body, Method, Request};
     use http_kit::header::HeaderValue;
     use skyzen_core::Extractor;
@@ -266,10 +592,9 @@ mod tests {
     async fn rejects_invalid_forwarded_header() {
         let mut request = Request::new(Body::empty());
         *request.method_mut() = Method::GET;
-        request.headers_mut().insert(
-            crate::header::FORWARDED,
-            HeaderValue::from_static("for"),
-        );
+        request
+            .headers_mut()
+            .insert(crate::header::FORWARDED, HeaderValue::from_static("for"));
 
         let error = ClientIp::extract(&mut request).await.unwrap_err();
         assert!(matches!(error, ClientIpError::InvalidForwardedHeader));
@@ -287,4 +612,185 @@ mod tests {
         let error = ClientIp::extract(&mut request).await.unwrap_err();
         assert!(matches!(error, ClientIpError::AddrParseError(_)));
     }
+
+    #[tokio::test]
+    async fn prefers_proxy_protocol_address() {
+        let mut request = Request::new(Body::empty());
+        request.headers_mut().insert(
+            crate::header::FORWARDED,
+            HeaderValue::from_static("for=192.0.2.43"),
+        );
+        request
+            .extensions_mut()
+            .insert(ProxiedAddr("198.51.100.7:4711".parse().unwrap()));
+
+        let ClientIp(addr) = ClientIp::extract(&mut request).await.unwrap();
+        assert_eq!(addr, IpAddr::from([198, 51, 100, 7]));
+    }
+
+    fn request_from(peer: &str, headers: &[(&'static str, &'static str)]) -> Request {
+        let mut request = Request::new(Body::empty());
+        for (name, value) in headers {
+            request
+                .headers_mut()
+                .append(*name, HeaderValue::from_static(value));
+        }
+        request
+            .extensions_mut()
+            .insert(PeerAddr(peer.parse().unwrap()));
+        request
+    }
+
+    async fn client_ip(
+        config: ClientIpConfig,
+        mut request: Request,
+    ) -> Result<IpAddr, ClientIpError> {
+        request.extensions_mut().insert(config);
+        ClientIp::extract(&mut request).await.map(|ClientIp(ip)| ip)
+    }
+
+    #[test]
+    fn parses_cidr_ranges() {
+        let range: IpCidr = "10.0.0.0/8".parse().unwrap();
+        assert!(range.contains(IpAddr::from([10, 1, 2, 3])));
+        assert!(!range.contains(IpAddr::from([11, 0, 0, 1])));
+        assert!(range.contains(IpAddr::from_str("::ffff:10.0.0.1").unwrap()));
+
+        let range: IpCidr = "2001:db8::/32".parse().unwrap();
+        assert!(range.contains(IpAddr::from_str("2001:db8:cafe::17").unwrap()));
+        assert!(!range.contains(IpAddr::from_str("2001:db9::1").unwrap()));
+        assert!(!range.contains(IpAddr::from([10, 0, 0, 1])));
+
+        let single: IpCidr = "192.0.2.1".parse().unwrap();
+        assert!(single.contains(IpAddr::from([192, 0, 2, 1])));
+        assert!(!single.contains(IpAddr::from([192, 0, 2, 2])));
+        assert!("0.0.0.0/0"
+            .parse::<IpCidr>()
+            .unwrap()
+            .contains(IpAddr::from([8, 8, 8, 8])));
+        assert!("::/0"
+            .parse::<IpCidr>()
+            .unwrap()
+            .contains(IpAddr::from_str("2001:db8::1").unwrap()));
+
+        assert!("10.0.0.0/33".parse::<IpCidr>().is_err());
+        assert!("10.0.0/8".parse::<IpCidr>().is_err());
+    }
+
+    #[tokio::test]
+    async fn ignores_spoofed_headers_from_untrusted_peers() {
+        let headers = [
+            ("x-forwarded-for", "1.1.1.1"),
+            ("forwarded", "for=\"[2001:db8::1]\""),
+        ];
+        let peer = IpAddr::from([203, 0, 113, 5]);
+
+        let ip = client_ip(
+            ClientIpConfig::TrustNone,
+            request_from("203.0.113.5:4000", &headers),
+        )
+        .await
+        .unwrap();
+        assert_eq!(ip, peer);
+
+        let config = ClientIpConfig::trust_proxies(["10.0.0.0/8"]).unwrap();
+        let ip = client_ip(config, request_from("203.0.113.5:4000", &headers))
+            .await
+            .unwrap();
+        assert_eq!(ip, peer);
+    }
+
+    #[tokio::test]
+    async fn walks_the_chain_from_a_trusted_proxy() {
+        let config = ClientIpConfig::trust_proxies(["10.0.0.0/8", "fd00::/8"]).unwrap();
+
+        // The leftmost entry was supplied by the client and must not win.
+        let request = request_from(
+            "10.0.0.1:443",
+            &[("x-forwarded-for", "1.1.1.1, 198.51.100.7, 10.0.0.2")],
+        );
+        let ip = client_ip(config.clone(), request).await.unwrap();
+        assert_eq!(ip, IpAddr::from([198, 51, 100, 7]));
+
+        let config = config.header(ForwardedHeader::Forwarded);
+        let request = request_from(
+            "10.0.0.1:443",
+            &[
+                ("forwarded", "for=1.1.1.1, for=\"[2001:db8:cafe::17]:4711\""),
+                ("forwarded", "for=\"[fd00::2]\";proto=https"),
+            ],
+        );
+        let ip = client_ip(config, request).await.unwrap();
+        assert_eq!(ip, IpAddr::from_str("2001:db8:cafe::17").unwrap());
+    }
+
+    #[tokio::test]
+    async fn only_reads_the_header_the_proxies_write() {
+        // The proxy appends to `X-Forwarded-For` and passes the client's `Forwarded` through.
+        let headers = [
+            ("forwarded", "for=1.1.1.1"),
+            ("x-forwarded-for", "198.51.100.7"),
+        ];
+        let config = ClientIpConfig::trust_proxies(["10.0.0.0/8"]).unwrap();
+        let ip = client_ip(config.clone(), request_from("10.0.0.1:443", &headers))
+            .await
+            .unwrap();
+        assert_eq!(ip, IpAddr::from([198, 51, 100, 7]));
+
+        let ip = client_ip(
+            ClientIpConfig::trust_hops(1),
+            request_from("10.0.0.1:443", &headers),
+        )
+        .await
+        .unwrap();
+        assert_eq!(ip, IpAddr::from([198, 51, 100, 7]));
+
+        // And the other way round for proxies writing `Forwarded`.
+        let headers = [
+            ("x-forwarded-for", "1.1.1.1"),
+            ("forwarded", "for=198.51.100.7"),
+        ];
+        let config = config.header(ForwardedHeader::Forwarded);
+        let ip = client_ip(config, request_from("10.0.0.1:443", &headers))
+            .await
+            .unwrap();
+        assert_eq!(ip, IpAddr::from([198, 51, 100, 7]));
+    }
+
+    #[tokio::test]
+    async fn trusts_a_fixed_number_of_hops() {
+        let headers = [("x-forwarded-for", "1.1.1.1, 198.51.100.7, 10.0.0.2")];
+
+        let ip = client_ip(
+            ClientIpConfig::trust_hops(0),
+            request_from("10.0.0.1:443", &headers),
+        )
+        .await
+        .unwrap();
+        assert_eq!(ip, IpAddr::from([10, 0, 0, 1]));
+
+        let ip = client_ip(
+            ClientIpConfig::trust_hops(2),
+            request_from("10.0.0.1:443", &headers),
+        )
+        .await
+        .unwrap();
+        assert_eq!(ip, IpAddr::from([198, 51, 100, 7]));
+
+        let ip = client_ip(
+            ClientIpConfig::trust_hops(9),
+            request_from("10.0.0.1:443", &headers),
+        )
+        .await
+        .unwrap();
+        assert_eq!(ip, IpAddr::from([10, 0, 0, 1]));
+    }
+
+    #[tokio::test]
+    async fn trusted_modes_need_the_peer_address() {
+        let mut request = Request::new(Body::empty());
+        request.extensions_mut().insert(ClientIpConfig::TrustNone);
+        let error = ClientIp::extract(&mut request).await.unwrap_err();
+        assert!(matches!(error, ClientIpError::MissingRemoteAddr));
+    }
 }
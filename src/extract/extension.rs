@@ -0,0 +1,118 @@
+//! Share per-request values between middleware and handlers through extensions.
+
+use core::{fmt, marker::PhantomData};
+use std::convert::Infallible;
+
+use crate::{extract::Extractor, responder::Responder, HttpError, Request, Response, StatusCode};
+
+/// A value stored in the request or response extensions.
+///
+/// As an extractor, it clones the `T` a middleware inserted into the request extensions, such as
+/// authentication claims or a trace context. As a responder, it inserts `T` into the response
+/// extensions so that outer middleware can read it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Extension<T>(pub T);
+
+impl_deref!(Extension);
+
+impl<T: Clone + Send + Sync + 'static> Extractor for Extension<T> {
+    type Error = ExtensionNotFound<T>;
+    async fn extract(request: &mut Request) -> Result<Self, Self::Error> {
+        request
+            .extensions()
+            .get::<T>()
+            .cloned()
+            .map(Self)
+            .ok_or(ExtensionNotFound(PhantomData))
+    }
+}
+
+impl<T: Clone + Send + Sync + 'static> Responder for Extension<T> {
+    type Error = Infallible;
+    fn respond_to(self, _request: &Request, response: &mut Response) -> Result<(), Self::Error> {
+        response.extensions_mut().insert(self.0);
+        Ok(())
+    }
+}
+
+/// The request extensions do not contain a `T`.
+pub struct ExtensionNotFound<T>(PhantomData<fn() -> T>);
+
+impl<T> ExtensionNotFound<T> {
+    /// Name of the missing extension type.
+    #[must_use]
+    pub fn type_name(&self) -> &'static str {
+        core::any::type_name::<T>()
+    }
+}
+
+impl<T> fmt::Debug for ExtensionNotFound<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ExtensionNotFound")
+            .field(&self.type_name())
+            .finish()
+    }
+}
+
+impl<T> fmt::Display for ExtensionNotFound<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Extension `{}` not found", self.type_name())
+    }
+}
+
+impl<T> core::error::Error for ExtensionNotFound<T> {}
+
+impl<T: 'static> HttpError for ExtensionNotFound<T> {
+    fn status(&self) -> StatusCode {
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::Extension;
+    use crate::{
+        responder::Responder,
+        routing::{CreateRouteNode, Route},
+        Body, HttpError, Method, Request, Response, Result, StatusCode,
+    };
+    use skyzen_core::Extractor;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Claims {
+        user: &'static str,
+    }
+
+    #[tokio::test]
+    async fn extracts_values_inserted_by_middleware() {
+        let mut request = Request::new(Body::empty());
+        request.extensions_mut().insert(Claims { user: "lexo" });
+        let Extension(claims) = Extension::<Claims>::extract(&mut request).await.unwrap();
+        assert_eq!(claims.user, "lexo");
+
+        let error = Extension::<u32>::extract(&mut request).await.unwrap_err();
+        assert_eq!(error.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(error.to_string(), "Extension `u32` not found");
+    }
+
+    #[tokio::test]
+    async fn pushes_values_into_response_extensions() {
+        let router = Route::new((
+            "/".at(|| async { Result::Ok(("done", Extension(Claims { user: "lexo" }))) }),
+        ))
+        .build();
+        let mut request = Request::new(Body::empty());
+        *request.method_mut() = Method::GET;
+        *request.uri_mut() = "/".parse().unwrap();
+        let response = router.go(request).await.unwrap();
+        assert_eq!(
+            response.extensions().get::<Claims>(),
+            Some(&Claims { user: "lexo" })
+        );
+
+        let request = Request::new(Body::empty());
+        let mut response = Response::new(Body::empty());
+        Extension(7_u8).respond_to(&request, &mut response).unwrap();
+        assert_eq!(response.extensions().get::<u8>(), Some(&7));
+    }
+}
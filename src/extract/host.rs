@@ -0,0 +1,357 @@
+//! Resolve the host and URL the client used to reach the server.
+
+use std::str::FromStr;
+
+use http::{
+    uri::{Authority, Scheme},
+    HeaderMap, StatusCode, Uri,
+};
+
+use crate::{
+    extract::{
+        client_ip::{forwarded_param, list_values},
+        ClientIpConfig, Extractor, ForwardedHeader,
+    },
+    header::{self, HeaderName},
+    Request,
+};
+
+const X_FORWARDED_HOST: HeaderName = HeaderName::from_static("x-forwarded-host");
+const X_FORWARDED_PROTO: HeaderName = HeaderName::from_static("x-forwarded-proto");
+
+/// Extract the effective host of the request, such as `example.com` or `example.com:8080`.
+///
+/// The order of determination is as follows: the `Forwarded` header's `host=` parameter /
+/// `X-Forwarded-Host` / `Host` / the URI authority. The forwarding headers are gated by the
+/// [`ClientIpConfig`] in the request extensions, exactly like [`ClientIp`](super::ClientIp):
+/// with trusted proxies, only their [`ForwardedHeader`] is read and the value written by the
+/// nearest proxy is used.
+///
+/// HTTP/2 requests carry the host in the `:authority` pseudo-header rather than `Host`. Hyper
+/// places it on the request URI, so it is picked up by the URI authority fallback.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Host(pub String);
+
+impl_deref!(Host, String);
+
+impl Host {
+    /// The host without its port. IPv6 literals keep their brackets.
+    #[must_use]
+    pub fn hostname(&self) -> &str {
+        self.split_port().0
+    }
+
+    /// The explicit port, if the host carries one.
+    #[must_use]
+    pub fn port(&self) -> Option<u16> {
+        self.split_port().1.and_then(|port| port.parse().ok())
+    }
+
+    fn split_port(&self) -> (&str, Option<&str>) {
+        let host = self.0.as_str();
+        let port_start = host
+            .rfind(':')
+            .filter(|&index| !host[index..].contains(']'));
+        port_start.map_or((host, None), |index| {
+            (&host[..index], Some(&host[index + 1..]))
+        })
+    }
+
+    fn resolve(request: &Request) -> Result<Self, HostError> {
+        let headers = request.headers();
+        if let Some(trusted) = ClientIpConfig::trusted_forwarding(request) {
+            if trusted.reads(ForwardedHeader::Forwarded) {
+                let mut forwarded = Vec::new();
+                for v in headers.get_all(header::FORWARDED) {
+                    forwarded.extend(
+                        forwarded_param(v.as_bytes(), b"host").map_err(|_| HostError::Invalid)?,
+                    );
+                }
+                if let Some(host) = trusted.entry.pick(&forwarded) {
+                    return parse_host(unquote(host));
+                }
+            }
+            if trusted.reads(ForwardedHeader::XForwarded) {
+                if let Some(host) = trusted.entry.pick(&header_list(headers, &X_FORWARDED_HOST)) {
+                    return parse_host(host);
+                }
+            }
+        }
+
+        if let Some(host) = headers.get(header::HOST) {
+            return parse_host(host.as_bytes());
+        }
+        request
+            .uri()
+            .authority()
+            .map(|authority| Self(authority.as_str().to_owned()))
+            .ok_or(HostError::Missing)
+    }
+}
+
+impl Extractor for Host {
+    type Error = HostError;
+    async fn extract(request: &mut Request) -> Result<Self, Self::Error> {
+        Self::resolve(request)
+    }
+}
+
+/// An error occurred while resolving the request host.
+#[skyzen::error(status = StatusCode::BAD_REQUEST)]
+pub enum HostError {
+    /// Neither a forwarding header, the `Host` header nor the URI names a host.
+    #[error("Missing host")]
+    Missing,
+    /// The host is not a valid `host[:port]` authority.
+    #[error("Invalid host")]
+    Invalid,
+}
+
+/// Reconstruct the URL a client used to reach the server, as seen from outside any proxies.
+pub trait RequestUriExt {
+    /// The scheme the client used.
+    ///
+    /// Uses the `Forwarded` header's `proto=` parameter or `X-Forwarded-Proto` when the
+    /// [`ClientIpConfig`] trusts them, as for [`Host`], then the URI scheme, and finally `http`.
+    fn external_scheme(&self) -> Scheme;
+
+    /// The absolute URI of the request: [`external_scheme`](Self::external_scheme), [`Host`]
+    /// and the request path and query.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the host is missing or invalid.
+    fn external_uri(&self) -> Result<Uri, HostError>;
+}
+
+impl RequestUriExt for Request {
+    fn external_scheme(&self) -> Scheme {
+        let headers = self.headers();
+        let forwarded = ClientIpConfig::trusted_forwarding(self).and_then(|trusted| {
+            let mut protos = Vec::new();
+            if trusted.reads(ForwardedHeader::Forwarded) {
+                for v in headers.get_all(header::FORWARDED) {
+                    protos.extend(forwarded_param(v.as_bytes(), b"proto").ok()?);
+                }
+            }
+            trusted.entry.pick(&protos).map(unquote).or_else(|| {
+                trusted
+                    .reads(ForwardedHeader::XForwarded)
+                    .then(|| {
+                        trusted
+                            .entry
+                            .pick(&header_list(headers, &X_FORWARDED_PROTO))
+                    })
+                    .flatten()
+            })
+        });
+        forwarded
+            .and_then(|proto| Scheme::try_from(proto).ok())
+            .or_else(|| self.uri().scheme().cloned())
+            .unwrap_or(Scheme::HTTP)
+    }
+
+    fn external_uri(&self) -> Result<Uri, HostError> {
+        let Host(host) = Host::resolve(self)?;
+        let path_and_query = self
+            .uri()
+            .path_and_query()
+            .map_or("/", |path_and_query| path_and_query.as_str());
+        Uri::builder()
+            .scheme(self.external_scheme())
+            .authority(host)
+            .path_and_query(path_and_query)
+            .build()
+            .map_err(|_| HostError::Invalid)
+    }
+}
+
+fn header_list<'a>(headers: &'a HeaderMap, name: &HeaderName) -> Vec<&'a [u8]> {
+    headers
+        .get_all(name)
+        .iter()
+        .flat_map(|v| list_values(v.as_bytes()))
+        .collect()
+}
+
+fn unquote(value: &[u8]) -> &[u8] {
+    value
+        .strip_prefix(b"\"")
+        .and_then(|value| value.strip_suffix(b"\""))
+        .unwrap_or(value)
+}
+
+/// Validate a `host[:port]` authority, rejecting user info.
+fn parse_host(value: &[u8]) -> Result<Host, HostError> {
+    let value = std::str::from_utf8(value).map_err(|_| HostError::Invalid)?;
+    let authority = Authority::from_str(value.trim()).map_err(|_| HostError::Invalid)?;
+    if authority.as_str().contains('@') || authority.host().is_empty() {
+        return Err(HostError::Invalid);
+    }
+    Ok(Host(authority.as_str().to_owned()))
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::{Host, HostError, RequestUriExt};
+    use crate::extract::{ClientIpConfig, ForwardedHeader, PeerAddr};
+    use crate::{Body, Request};
+    use http::{uri::Scheme, Version};
+    use http_kit::header::HeaderValue;
+    use skyzen_core::Extractor;
+
+    fn request(uri: &str, headers: &[(&'static str, &'static str)]) -> Request {
+        let mut request = Request::new(Body::empty());
+        *request.uri_mut() = uri.parse().unwrap();
+        for (name, value) in headers {
+            request
+                .headers_mut()
+                .append(*name, HeaderValue::from_static(value));
+        }
+        request
+    }
+
+    #[tokio::test]
+    async fn resolves_hosts_in_order() {
+        let mut req = request(
+            "/",
+            &[
+                ("forwarded", "for=192.0.2.1;host=\"public.example\""),
+                ("x-forwarded-host", "proxy.example"),
+                ("host", "internal:8080"),
+            ],
+        );
+        assert_eq!(Host::extract(&mut req).await.unwrap().0, "public.example");
+
+        let mut req = request(
+            "/",
+            &[("x-forwarded-host", "proxy.example"), ("host", "internal")],
+        );
+        assert_eq!(Host::extract(&mut req).await.unwrap().0, "proxy.example");
+
+        let mut req = request("/", &[("host", "internal:8080")]);
+        let host = Host::extract(&mut req).await.unwrap();
+        assert_eq!(host.0, "internal:8080");
+        assert_eq!(host.hostname(), "internal");
+        assert_eq!(host.port(), Some(8080));
+
+        let host = Host("[::1]".to_owned());
+        assert_eq!((host.hostname(), host.port()), ("[::1]", None));
+    }
+
+    #[tokio::test]
+    async fn gates_forwarding_headers_on_trust() {
+        let headers = [
+            ("x-forwarded-host", "evil.example"),
+            ("host", "real.example"),
+        ];
+
+        let mut req = request("/", &headers);
+        req.extensions_mut().insert(ClientIpConfig::TrustNone);
+        assert_eq!(Host::extract(&mut req).await.unwrap().0, "real.example");
+
+        let config = ClientIpConfig::trust_proxies(["10.0.0.0/8"]).unwrap();
+        let mut req = request("/", &headers);
+        req.extensions_mut().insert(config.clone());
+        req.extensions_mut()
+            .insert(PeerAddr("203.0.113.5:1234".parse().unwrap()));
+        assert_eq!(Host::extract(&mut req).await.unwrap().0, "real.example");
+
+        let mut req = request(
+            "/",
+            &[
+                ("x-forwarded-host", "spoofed.example, public.example"),
+                ("host", "real.example"),
+            ],
+        );
+        req.extensions_mut().insert(config);
+        req.extensions_mut()
+            .insert(PeerAddr("10.0.0.1:1234".parse().unwrap()));
+        assert_eq!(Host::extract(&mut req).await.unwrap().0, "public.example");
+    }
+
+    #[tokio::test]
+    async fn only_reads_the_header_the_proxies_write() {
+        let trusted = |config: ClientIpConfig, headers: &[(&'static str, &'static str)]| {
+            let mut req = request("/reset", headers);
+            req.extensions_mut().insert(config);
+            req.extensions_mut()
+                .insert(PeerAddr("10.0.0.1:1234".parse().unwrap()));
+            req
+        };
+        let config = ClientIpConfig::trust_proxies(["10.0.0.0/8"]).unwrap();
+
+        // The proxy sets `X-Forwarded-*` and passes the client's `Forwarded` through.
+        let headers = [
+            ("forwarded", "host=evil.example;proto=http"),
+            ("x-forwarded-host", "app.example"),
+            ("x-forwarded-proto", "https"),
+            ("host", "internal"),
+        ];
+        let mut req = trusted(config.clone(), &headers);
+        assert_eq!(Host::extract(&mut req).await.unwrap().0, "app.example");
+        assert_eq!(req.external_uri().unwrap(), "https://app.example/reset");
+
+        let mut req = trusted(ClientIpConfig::trust_hops(1), &headers);
+        assert_eq!(Host::extract(&mut req).await.unwrap().0, "app.example");
+
+        let headers = [
+            ("x-forwarded-host", "evil.example"),
+            ("x-forwarded-proto", "http"),
+            ("forwarded", "host=app.example;proto=https"),
+            ("host", "internal"),
+        ];
+        let mut req = trusted(config.header(ForwardedHeader::Forwarded), &headers);
+        assert_eq!(Host::extract(&mut req).await.unwrap().0, "app.example");
+        assert_eq!(req.external_uri().unwrap(), "https://app.example/reset");
+    }
+
+    #[tokio::test]
+    async fn uses_the_http2_authority() {
+        // Hyper exposes `:authority` as the URI authority and may omit `Host` entirely.
+        let mut req = request("https://example.com:8443/path", &[]);
+        *req.version_mut() = Version::HTTP_2;
+        let host = Host::extract(&mut req).await.unwrap();
+        assert_eq!(host.0, "example.com:8443");
+    }
+
+    #[tokio::test]
+    async fn rejects_missing_and_invalid_hosts() {
+        let mut req = request("/", &[]);
+        *req.version_mut() = Version::HTTP_11;
+        assert!(matches!(
+            Host::extract(&mut req).await,
+            Err(HostError::Missing)
+        ));
+
+        for invalid in ["user@example.com", "exa mple.com", ""] {
+            let mut req = request("/", &[]);
+            req.headers_mut()
+                .insert("host", HeaderValue::from_str(invalid).unwrap());
+            assert!(
+                matches!(Host::extract(&mut req).await, Err(HostError::Invalid)),
+                "{invalid}"
+            );
+        }
+    }
+
+    #[test]
+    fn reconstructs_external_uris() {
+        let req = request(
+            "/reset?token=abc",
+            &[
+                ("x-forwarded-proto", "https"),
+                ("x-forwarded-host", "app.example"),
+                ("host", "127.0.0.1:3000"),
+            ],
+        );
+        assert_eq!(req.external_scheme(), Scheme::HTTPS);
+        assert_eq!(
+            req.external_uri().unwrap(),
+            "https://app.example/reset?token=abc"
+        );
+
+        let req = request("/", &[("host", "127.0.0.1:3000")]);
+        assert_eq!(req.external_uri().unwrap(), "http://127.0.0.1:3000/");
+    }
+}
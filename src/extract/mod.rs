@@ -1,9 +1,34 @@
-pub use skyzen_core::Extractor;
+pub use skyzen_core::{BodyLimit, BodyReadError, Extractor};
 
 #[cfg(feature = "form")]
 mod query;
 #[cfg(feature = "form")]
-pub use query::Query;
+pub use query::{Query, QueryConfig, QueryError, RawQuery};
+
+pub mod extension;
+pub use extension::{Extension, ExtensionNotFound};
+
+pub mod rejection;
+pub use rejection::{Rejection, RejectionHandler};
 
 pub mod client_ip;
-pub use client_ip::{ClientIp, PeerAddr};
+pub use client_ip::{ClientIp, ClientIpConfig, ForwardedHeader, IpCidr, PeerAddr, ProxiedAddr};
+
+pub mod auth;
+pub use auth::{BasicAuth, BasicAuthError, BearerToken, BearerTokenError, BearerTokenSource};
+
+pub mod host;
+pub use host::{Host, RequestUriExt};
+
+pub mod background;
+pub use background::BackgroundTasks;
+
+#[cfg(target_arch = "wasm32")]
+pub mod worker_env;
+#[cfg(target_arch = "wasm32")]
+pub use worker_env::{KvNamespace, WorkerEnv, WorkerEnvError};
+
+#[cfg(feature = "typed-header")]
+pub mod typed_header;
+#[cfg(feature = "typed-header")]
+pub use typed_header::{TypedHeader, TypedHeaderError};
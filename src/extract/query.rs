@@ -1,31 +1,173 @@
+use core::fmt;
+use std::convert::Infallible;
+
 use crate::{extract::Extractor, Request, StatusCode};
 
-use http_kit::http_error;
-use serde::de::DeserializeOwned;
-use serde_urlencoded::from_str;
+use http_kit::{middleware::MiddlewareError, Endpoint, HttpError, Middleware, Response};
+use serde::{de::DeserializeOwned, Deserializer};
 
 /// Parse query from Uri.
+///
+/// Repeated keys deserialize into sequences, so `?tag=a&tag=b` fills a `Vec<String>` field.
+/// Install a [`QueryConfig`] with [`brackets`](QueryConfig::brackets) enabled to parse
+/// `filter[name]=x` style nesting instead.
 #[derive(Debug, Clone)]
 pub struct Query<T>(pub T);
 
 impl_deref!(Query);
 
-http_error!(
-    /// An error occurred while parsing the query string.
-    pub QueryError, StatusCode::BAD_REQUEST, "Failed to parse query string");
+/// Configure how [`Query`] parses the query string.
+///
+/// Add it as a middleware to apply it to every route below. Without it, the flat syntax with
+/// repeated keys is used.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueryConfig {
+    brackets: bool,
+}
+
+impl QueryConfig {
+    /// Create the default configuration.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { brackets: false }
+    }
+
+    /// Parse bracketed keys such as `filter[name]=x` or `tag[]=a` into nested values.
+    ///
+    /// Repeated keys without brackets are rejected in this mode.
+    #[must_use]
+    pub const fn brackets(mut self, enabled: bool) -> Self {
+        self.brackets = enabled;
+        self
+    }
+}
+
+impl Middleware for QueryConfig {
+    type Error = Infallible;
+    async fn handle<N: Endpoint>(
+        &mut self,
+        request: &mut Request,
+        mut next: N,
+    ) -> Result<Response, MiddlewareError<N::Error, Self::Error>> {
+        request.extensions_mut().insert(*self);
+        next.respond(request)
+            .await
+            .map_err(MiddlewareError::Endpoint)
+    }
+}
+
+/// An error occurred while parsing the query string.
+#[derive(Debug)]
+pub struct QueryError {
+    query: String,
+    field: Option<String>,
+    message: String,
+}
+
+impl QueryError {
+    /// The query string that failed to parse.
+    #[must_use]
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    /// Path of the field that failed to parse, such as `page` or `filter.name`.
+    #[must_use]
+    pub fn field(&self) -> Option<&str> {
+        self.field.as_deref()
+    }
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Failed to parse query string `{}`", self.query)?;
+        if let Some(field) = &self.field {
+            write!(f, " at field `{field}`")?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+impl core::error::Error for QueryError {}
+
+impl HttpError for QueryError {
+    fn status(&self) -> StatusCode {
+        StatusCode::BAD_REQUEST
+    }
+}
+
+fn parse_query<T: DeserializeOwned>(query: &str, config: QueryConfig) -> Result<T, QueryError> {
+    let error = |field: Option<String>, message: String| QueryError {
+        query: query.to_owned(),
+        field,
+        message,
+    };
+    if let Some(index) = invalid_percent_encoding(query) {
+        return Err(error(
+            None,
+            format!("invalid percent-encoding at byte {index}"),
+        ));
+    }
+
+    if config.brackets {
+        let parser = serde_qs::Config::new(5, false);
+        let deserializer = serde_qs::Deserializer::with_config(&parser, query.as_bytes())
+            .map_err(|e| error(None, e.to_string()))?;
+        deserialize(query, deserializer)
+    } else {
+        deserialize(
+            query,
+            serde_html_form::Deserializer::from_bytes(query.as_bytes()),
+        )
+    }
+}
+
+fn deserialize<'de, T: DeserializeOwned, D: Deserializer<'de>>(
+    query: &str,
+    deserializer: D,
+) -> Result<T, QueryError> {
+    serde_path_to_error::deserialize(deserializer).map_err(|e| {
+        let field = e.path().to_string();
+        QueryError {
+            query: query.to_owned(),
+            field: (field != ".").then_some(field),
+            message: e.into_inner().to_string(),
+        }
+    })
+}
+
+/// Position of the first `%` not followed by two hex digits.
+fn invalid_percent_encoding(query: &str) -> Option<usize> {
+    let bytes = query.as_bytes();
+    bytes.iter().enumerate().find_map(|(index, &byte)| {
+        let valid = byte != b'%'
+            || bytes
+                .get(index + 1..index + 3)
+                .is_some_and(|hex| hex.iter().all(u8::is_ascii_hexdigit));
+        (!valid).then_some(index)
+    })
+}
 
 impl<T: Send + Sync + DeserializeOwned + 'static> Extractor for Query<T> {
     type Error = QueryError;
     async fn extract(request: &mut Request) -> Result<Self, Self::Error> {
+        let config = request
+            .extensions()
+            .get::<QueryConfig>()
+            .copied()
+            .unwrap_or_default();
         let data = request.uri().query().unwrap_or_default();
-        Ok(Self(from_str(data).map_err(|_| QueryError::new())?))
+        parse_query(data, config).map(Self)
     }
 
     #[cfg(feature = "openapi")]
     fn openapi() -> Option<crate::openapi::ExtractorSchema> {
         Some(crate::openapi::ExtractorSchema {
-            content_type: Some("application/x-www-form-urlencoded"),
+            content_type: None,
             schema: None,
+            location: crate::openapi::ParameterLocation::Query,
+            required: true,
+            extra_content_types: &[],
         })
     }
 
@@ -36,10 +178,34 @@ impl<T: Send + Sync + DeserializeOwned + 'static> Extractor for Query<T> {
     }
 }
 
-#[cfg(test)]
+/// The untouched query string, without the leading `?`.
+///
+/// Use it when the query does not fit a [`Query`] deserialization.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RawQuery(pub Option<String>);
+
+impl RawQuery {
+    /// Borrow the query string.
+    #[must_use]
+    pub fn as_str(&self) -> Option<&str> {
+        self.0.as_deref()
+    }
+}
+
+impl Extractor for RawQuery {
+    type Error = Infallible;
+    async fn extract(request: &mut Request) -> Result<Self, Self::Error> {
+        Ok(Self(request.uri().query().map(str::to_owned)))
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
 mod tests {
-    use super::Query;
-    use crate::{Body, Method, StatusCode};
+    use super::{Query, QueryConfig, QueryError, RawQuery};
+    use crate::{
+        routing::{CreateRouteNode, Route},
+        Body, Method, Result, StatusCode,
+    };
     use http_kit::HttpError;
     use serde::Deserialize;
     use skyzen_core::Extractor;
@@ -69,6 +235,123 @@ mod tests {
         let error = Query::<Search>::extract(&mut request).await.unwrap_err();
         assert_eq!(error.status(), StatusCode::BAD_REQUEST);
     }
+
+    #[derive(Debug, Deserialize)]
+    struct Filters {
+        tag: String,
+    }
+
+    #[tokio::test]
+    async fn optional_query_is_none_without_a_query_string() {
+        async fn list(filters: Option<Query<Filters>>) -> Result<String> {
+            Ok(filters.map_or_else(|| "all".to_owned(), |Query(filters)| filters.tag))
+        }
+
+        let router = Route::new(("/items".at(list),)).build();
+        for (uri, expected) in [
+            ("http://localhost/items", "all"),
+            ("http://localhost/items?tag=rust", "rust"),
+        ] {
+            let response = router.clone().go(request(uri)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = response.into_body().into_string().await.unwrap();
+            assert_eq!(body, expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn result_query_hands_the_error_to_the_handler() {
+        let mut request = request("http://localhost/search?q=rust&page=two");
+        let result = core::result::Result::<Query<Search>, QueryError>::extract(&mut request)
+            .await
+            .unwrap();
+        assert_eq!(result.unwrap_err().status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Tags {
+        tag: Vec<String>,
+        page: Option<u8>,
+    }
+
+    #[tokio::test]
+    async fn collects_repeated_keys_into_a_vec() {
+        let mut req = request("http://localhost/items?tag=a&tag=b%20c");
+        let Query(tags) = Query::<Tags>::extract(&mut req).await.unwrap();
+        assert_eq!(
+            tags,
+            Tags {
+                tag: vec!["a".into(), "b c".into()],
+                page: None
+            }
+        );
+
+        let mut req = request("http://localhost/items?tag=a&page=3");
+        let Query(tags) = Query::<Tags>::extract(&mut req).await.unwrap();
+        assert_eq!((tags.tag.len(), tags.page), (1, Some(3)));
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Nested {
+        filter: Filters,
+        tag: Vec<String>,
+    }
+
+    #[tokio::test]
+    async fn parses_bracketed_keys_when_enabled() {
+        async fn list(Query(nested): Query<Nested>) -> Result<String> {
+            Ok(format!("{} {}", nested.filter.tag, nested.tag.join(",")))
+        }
+
+        let uri = "http://localhost/items?filter[tag]=rust&tag[]=a&tag[]=b";
+        let router = Route::new(("/items".at(list),))
+            .middleware(QueryConfig::new().brackets(true))
+            .build();
+        let response = router.go(request(uri)).await.unwrap();
+        let body = response.into_body().into_string().await.unwrap();
+        assert_eq!(body, "rust a,b");
+
+        let router = Route::new(("/items".at(list),)).build();
+        let error = router.go(request(uri)).await.unwrap_err();
+        assert_eq!(error.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn names_the_query_and_failing_field() {
+        let mut request = request("http://localhost/search?q=rust&page=two");
+        let error = Query::<Search>::extract(&mut request).await.unwrap_err();
+        assert_eq!(error.query(), "q=rust&page=two");
+        assert_eq!(error.field(), Some("page"));
+        assert!(error
+            .to_string()
+            .contains("`q=rust&page=two` at field `page`"));
+    }
+
+    #[tokio::test]
+    async fn rejects_malformed_percent_encoding() {
+        let mut request = request("http://localhost/items?tag=%zz");
+        let error = Query::<Tags>::extract(&mut request).await.unwrap_err();
+        assert_eq!(error.status(), StatusCode::BAD_REQUEST);
+        assert!(error.to_string().contains("percent-encoding"));
+    }
+
+    #[tokio::test]
+    async fn raw_query_is_untouched() {
+        let mut req = request("http://localhost/items?tag=%zz&x");
+        let raw = RawQuery::extract(&mut req).await.unwrap();
+        assert_eq!(raw.as_str(), Some("tag=%zz&x"));
+
+        let mut req = request("http://localhost/items");
+        assert_eq!(RawQuery::extract(&mut req).await.unwrap().as_str(), None);
+    }
+
+    #[cfg(feature = "openapi")]
+    #[test]
+    fn optional_query_is_not_required() {
+        assert!(Query::<Filters>::openapi().unwrap().required);
+        assert!(!Option::<Query<Filters>>::openapi().unwrap().required);
+    }
+
     fn request(uri: &str) -> http_kit::Request {
         let mut request = http_kit::Request::new(Body::empty());
         *request.uri_mut() = uri.parse().expect("invalid uri");
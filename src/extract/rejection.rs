@@ -0,0 +1,245 @@
+//! Reshape the responses of failed extractors.
+//!
+//! When an extractor such as [`Json`](crate::utils::Json) fails, the handler answers with the
+//! extractor's error. A [`RejectionHandler`] installed as middleware gets the first say instead,
+//! with access to the concrete error type:
+//!
+//! ```
+//! use skyzen::{
+//!     extract::RejectionHandler,
+//!     middleware::WithMiddleware,
+//!     routing::{CreateRouteNode, Route},
+//!     utils::{error::JsonContentTypeError, Json},
+//!     Body, Response, Result,
+//! };
+//!
+//! async fn create(Json(user): Json<serde_json::Value>) -> Result<Json<serde_json::Value>> {
+//!     Ok(Json(user))
+//! }
+//!
+//! let router = Route::new(("/users".post(create),)).build();
+//! let app = WithMiddleware::new(
+//!     router,
+//!     RejectionHandler::new(|rejection, _request| {
+//!         let error = rejection.downcast_ref::<JsonContentTypeError>()?;
+//!         let mut response = Response::new(Body::from(format!("bad user: {error}")));
+//!         *response.status_mut() = rejection.status();
+//!         Some(response)
+//!     }),
+//! );
+//! ```
+//!
+//! Only extractor failures are handed over; errors returned by the handler itself, and those of
+//! extractors the rejection handler declines with `None`, are answered as usual.
+
+use std::{convert::Infallible, error::Error, fmt, sync::Arc};
+
+use http_kit::{middleware::MiddlewareError, Endpoint, HttpError, Middleware};
+
+use crate::{Request, Response, StatusCode};
+
+/// A failed extraction, as seen by a [`RejectionHandler`].
+#[derive(Debug, Clone, Copy)]
+pub struct Rejection<'a> {
+    error: &'a (dyn Error + 'static),
+    status: StatusCode,
+}
+
+impl<'a> Rejection<'a> {
+    /// The rejection of the failing extractor's `error`.
+    ///
+    /// Errors of the argument tuples of handlers are unwrapped to the error of the extractor that
+    /// failed.
+    #[must_use]
+    pub fn new(error: &'a (dyn HttpError + 'static)) -> Self {
+        let status = error.status();
+        let error: &'a (dyn Error + 'static) = error;
+        Self {
+            error: error.source().unwrap_or(error),
+            status,
+        }
+    }
+
+    /// The status the response would have had.
+    #[must_use]
+    pub const fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// The error of the extractor that failed.
+    #[must_use]
+    pub const fn error(&self) -> &'a (dyn Error + 'static) {
+        self.error
+    }
+
+    /// The error of the extractor that failed, if it is an `E`.
+    #[must_use]
+    pub fn downcast_ref<E: Error + 'static>(&self) -> Option<&'a E> {
+        self.error.downcast_ref()
+    }
+}
+
+impl fmt::Display for Rejection<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self.error, f)
+    }
+}
+
+type Render = dyn Fn(&Rejection<'_>, &Request) -> Option<Response> + Send + Sync;
+
+/// Turns extractor failures into responses, see the [module docs](self).
+///
+/// Install it as middleware; it stores itself in the request extensions for the handlers behind
+/// it.
+#[derive(Clone)]
+pub struct RejectionHandler {
+    render: Arc<Render>,
+}
+
+impl fmt::Debug for RejectionHandler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RejectionHandler").finish_non_exhaustive()
+    }
+}
+
+impl RejectionHandler {
+    /// Answer failed extractions with the response of `render`, or as usual when it returns
+    /// `None`.
+    pub fn new(
+        render: impl Fn(&Rejection<'_>, &Request) -> Option<Response> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            render: Arc::new(render),
+        }
+    }
+
+    /// Answer every failed extraction with a JSON body such as
+    /// `{"status":415,"error":"Expected content type `application/json`"}`.
+    #[cfg(feature = "json")]
+    #[must_use]
+    pub fn json() -> Self {
+        Self::new(|rejection, _request| {
+            let body = serde_json::json!({
+                "status": rejection.status().as_u16(),
+                "error": rejection.to_string(),
+            });
+            let mut response = Response::new(http_kit::Body::from(body.to_string()));
+            *response.status_mut() = rejection.status();
+            response.headers_mut().insert(
+                crate::header::CONTENT_TYPE,
+                crate::header::HeaderValue::from_static("application/json"),
+            );
+            Some(response)
+        })
+    }
+
+    /// The response for `rejection`, if this handler reshapes it.
+    #[must_use]
+    pub fn render(&self, rejection: &Rejection<'_>, request: &Request) -> Option<Response> {
+        (self.render)(rejection, request)
+    }
+}
+
+impl Middleware for RejectionHandler {
+    type Error = Infallible;
+
+    async fn handle<N: Endpoint>(
+        &mut self,
+        request: &mut Request,
+        mut next: N,
+    ) -> Result<Response, MiddlewareError<N::Error, Self::Error>> {
+        request.extensions_mut().insert(self.clone());
+        next.respond(request)
+            .await
+            .map_err(MiddlewareError::Endpoint)
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::RejectionHandler;
+    use crate::{
+        header::{HeaderValue, CONTENT_TYPE},
+        middleware::WithMiddleware,
+        routing::{CreateRouteNode, Params, Route},
+        utils::{error::JsonContentTypeError, Json},
+        Body, Endpoint, Method, Request, Response, Result, StatusCode,
+    };
+
+    fn app(rejections: RejectionHandler) -> impl Endpoint {
+        let router = Route::new((
+            "/users".post(|Json(user): Json<serde_json::Value>| async move {
+                Result::Ok(user.to_string())
+            }),
+            "/users/{id}".at(|params: Params| async move {
+                params.parse::<u64>("id").map(|id| id.to_string())
+            }),
+        ))
+        .build();
+        WithMiddleware::new(router, rejections)
+    }
+
+    async fn respond(app: &mut impl Endpoint, mut request: Request) -> (StatusCode, String) {
+        let response = app.respond(&mut request).await.ok().unwrap();
+        let status = response.status();
+        let body = response.into_body().into_string().await.unwrap();
+        (status, body.to_string())
+    }
+
+    fn post_json(body: &'static str) -> Request {
+        let mut request = Request::new(Body::from(body));
+        *request.method_mut() = Method::POST;
+        *request.uri_mut() = "/users".parse().unwrap();
+        request
+            .headers_mut()
+            .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        request
+    }
+
+    #[tokio::test]
+    async fn reshapes_failed_json_extractions_only() {
+        let mut app = app(RejectionHandler::new(|rejection, _request| {
+            let error = rejection.downcast_ref::<JsonContentTypeError>()?;
+            let message = match error {
+                JsonContentTypeError::Syntax(_) => "malformed",
+                _ => "other",
+            };
+            let mut response = Response::new(Body::from(message));
+            *response.status_mut() = rejection.status();
+            Some(response)
+        }));
+
+        assert_eq!(
+            respond(&mut app, post_json("{")).await,
+            (StatusCode::BAD_REQUEST, "malformed".to_owned())
+        );
+        assert_eq!(
+            respond(&mut app, post_json("{}")).await,
+            (StatusCode::OK, "{}".to_owned())
+        );
+
+        // `Params::parse` fails inside the handler, which the rejection handler leaves alone.
+        let mut request = Request::new(Body::empty());
+        *request.uri_mut() = "/users/abc".parse().unwrap();
+        assert_eq!(
+            respond(&mut app, request).await,
+            (StatusCode::BAD_REQUEST, String::new())
+        );
+    }
+
+    #[tokio::test]
+    async fn renders_json_envelopes() {
+        let mut app = app(RejectionHandler::json());
+        let mut request = post_json("{}");
+        request.headers_mut().remove(CONTENT_TYPE);
+        let (status, body) = respond(&mut app, request).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&body).unwrap(),
+            serde_json::json!({
+                "status": 400,
+                "error": "Expected content type `application/json`",
+            })
+        );
+    }
+}
@@ -0,0 +1,151 @@
+//! Decode request headers into the typed representations of the [`headers`] crate.
+
+use core::fmt;
+
+use headers::{Header, HeaderMapExt};
+
+use crate::{extract::Extractor, header::HeaderName, HttpError, Request, StatusCode};
+
+/// Extract a typed header, such as `TypedHeader<headers::UserAgent>` or
+/// `TypedHeader<headers::Authorization<headers::authorization::Bearer>>`.
+///
+/// Extraction fails with a `400 Bad Request` naming the header when it is missing or malformed.
+/// Wrap it in an [`Option`] for headers the client may omit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TypedHeader<T>(pub T);
+
+impl_deref!(TypedHeader);
+
+impl<T: Header + Send + Sync + 'static> Extractor for TypedHeader<T> {
+    type Error = TypedHeaderError;
+    async fn extract(request: &mut Request) -> Result<Self, Self::Error> {
+        match request.headers().typed_try_get::<T>() {
+            Ok(Some(header)) => Ok(Self(header)),
+            Ok(None) => Err(TypedHeaderError::Missing(T::name())),
+            Err(_) => Err(TypedHeaderError::Invalid(T::name())),
+        }
+    }
+
+    #[cfg(feature = "openapi")]
+    fn openapi() -> Option<crate::openapi::ExtractorSchema> {
+        Some(crate::openapi::ExtractorSchema {
+            content_type: None,
+            schema: Some(skyzen_core::openapi::plain_string_schema()),
+            location: crate::openapi::ParameterLocation::Header(T::name().as_str()),
+            required: true,
+            extra_content_types: &[],
+        })
+    }
+}
+
+/// An error occurred while extracting a [`TypedHeader`].
+#[derive(Debug)]
+pub enum TypedHeaderError {
+    /// The request does not carry the header.
+    Missing(&'static HeaderName),
+    /// The header value could not be decoded.
+    Invalid(&'static HeaderName),
+}
+
+impl TypedHeaderError {
+    /// Name of the header that failed to extract.
+    #[must_use]
+    pub const fn name(&self) -> &'static HeaderName {
+        match self {
+            Self::Missing(name) | Self::Invalid(name) => name,
+        }
+    }
+}
+
+impl fmt::Display for TypedHeaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Missing(name) => write!(f, "Missing header `{name}`"),
+            Self::Invalid(name) => write!(f, "Invalid header `{name}`"),
+        }
+    }
+}
+
+impl core::error::Error for TypedHeaderError {}
+
+impl HttpError for TypedHeaderError {
+    fn status(&self) -> StatusCode {
+        StatusCode::BAD_REQUEST
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::{TypedHeader, TypedHeaderError};
+    use crate::{Body, HttpError, Request, StatusCode};
+    use headers::{authorization::Bearer, Authorization, UserAgent};
+    use http_kit::header::HeaderValue;
+    use skyzen_core::Extractor;
+
+    fn request(headers: &[(&'static str, &'static str)]) -> Request {
+        let mut request = Request::new(Body::empty());
+        for (name, value) in headers {
+            request
+                .headers_mut()
+                .append(*name, HeaderValue::from_static(value));
+        }
+        request
+    }
+
+    #[tokio::test]
+    async fn decodes_typed_headers() {
+        let mut req = request(&[
+            ("user-agent", "curl/8.0"),
+            ("authorization", "Bearer secret-token"),
+        ]);
+        let TypedHeader(agent) = TypedHeader::<UserAgent>::extract(&mut req).await.unwrap();
+        assert_eq!(agent.as_str(), "curl/8.0");
+        let TypedHeader(auth) = TypedHeader::<Authorization<Bearer>>::extract(&mut req)
+            .await
+            .unwrap();
+        assert_eq!(auth.token(), "secret-token");
+    }
+
+    #[tokio::test]
+    async fn names_missing_and_malformed_headers() {
+        let mut req = request(&[]);
+        let error = TypedHeader::<UserAgent>::extract(&mut req)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, TypedHeaderError::Missing(_)));
+        assert_eq!(error.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(error.to_string(), "Missing header `user-agent`");
+
+        let mut req = request(&[("authorization", "Basic")]);
+        let error = TypedHeader::<Authorization<Bearer>>::extract(&mut req)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, TypedHeaderError::Invalid(_)));
+        assert_eq!(error.to_string(), "Invalid header `authorization`");
+    }
+
+    #[tokio::test]
+    async fn optional_headers_do_not_fail() {
+        let mut req = request(&[]);
+        let header = Option::<TypedHeader<UserAgent>>::extract(&mut req)
+            .await
+            .unwrap();
+        assert!(header.is_none());
+
+        let mut req = request(&[("user-agent", "curl/8.0")]);
+        let header = Option::<TypedHeader<UserAgent>>::extract(&mut req)
+            .await
+            .unwrap();
+        assert_eq!(header.unwrap().as_str(), "curl/8.0");
+    }
+
+    #[cfg(feature = "openapi")]
+    #[test]
+    fn documents_a_header_parameter() {
+        let schema = TypedHeader::<UserAgent>::openapi().unwrap();
+        assert_eq!(
+            schema.location,
+            crate::openapi::ParameterLocation::Header("user-agent")
+        );
+    }
+}
@@ -0,0 +1,320 @@
+//! Reach Cloudflare Workers bindings from handlers.
+//!
+//! [`runtime::wasm::launch`](crate::runtime::wasm::launch) stores the Workers `Env` in the
+//! request extensions, and [`WorkerEnv`] extracts it:
+//!
+//! ```ignore
+//! use skyzen::extract::WorkerEnv;
+//!
+//! async fn handler(env: WorkerEnv) -> skyzen::Result<String> {
+//!     let token = env.secret("API_TOKEN")?;
+//!     let greeting = env.kv("CONFIG")?.get("greeting").await?;
+//!     Ok(greeting.unwrap_or(token))
+//! }
+//! ```
+
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+
+use crate::{extract::Extractor, HttpError, Request, StatusCode};
+
+/// The Workers environment of the current request, holding its KV namespaces, secrets,
+/// variables and other bindings.
+#[derive(Clone)]
+pub struct WorkerEnv(JsValue);
+
+// SAFETY: WASM is single-threaded, so Send/Sync is safe for JsValue wrappers.
+unsafe impl Send for WorkerEnv {}
+unsafe impl Sync for WorkerEnv {}
+
+impl fmt::Debug for WorkerEnv {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WorkerEnv").finish_non_exhaustive()
+    }
+}
+
+impl WorkerEnv {
+    /// Wrap the `env` object passed to the Worker's `fetch` handler.
+    #[must_use]
+    pub const fn new(env: JsValue) -> Self {
+        Self(env)
+    }
+
+    /// Look up a binding of any kind, such as a D1 database or a service binding.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`WorkerEnvError::Missing`] if the environment has no binding called `name`.
+    pub fn binding(&self, name: &str) -> Result<JsValue, WorkerEnvError> {
+        let value = js_sys::Reflect::get(&self.0, &JsValue::from_str(name))
+            .map_err(|error| WorkerEnvError::js(name, &error))?;
+        if value.is_undefined() {
+            return Err(WorkerEnvError::Missing(name.to_owned()));
+        }
+        Ok(value)
+    }
+
+    /// Read the secret or plain-text variable called `name`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the binding is missing or is not a string.
+    pub fn secret(&self, name: &str) -> Result<String, WorkerEnvError> {
+        self.binding(name)?
+            .as_string()
+            .ok_or_else(|| WorkerEnvError::wrong_type(name, "a secret or text variable"))
+    }
+
+    /// Open the KV namespace bound as `name`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the binding is missing or is not a KV namespace.
+    pub fn kv(&self, name: &str) -> Result<KvNamespace, WorkerEnvError> {
+        let namespace = self.binding(name)?;
+        if !namespace.is_object() {
+            return Err(WorkerEnvError::wrong_type(name, "a KV namespace"));
+        }
+        Ok(KvNamespace {
+            name: name.to_owned(),
+            namespace,
+        })
+    }
+}
+
+impl Extractor for WorkerEnv {
+    type Error = WorkerEnvError;
+    async fn extract(request: &mut Request) -> Result<Self, Self::Error> {
+        request
+            .extensions()
+            .get::<Self>()
+            .cloned()
+            .ok_or(WorkerEnvError::Unavailable)
+    }
+}
+
+/// A Workers KV namespace.
+#[derive(Clone)]
+pub struct KvNamespace {
+    name: String,
+    namespace: JsValue,
+}
+
+// SAFETY: WASM is single-threaded, so Send/Sync is safe for JsValue wrappers.
+unsafe impl Send for KvNamespace {}
+unsafe impl Sync for KvNamespace {}
+
+impl fmt::Debug for KvNamespace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KvNamespace")
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
+
+impl KvNamespace {
+    /// Read `key` as text, returning `None` if it does not exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the KV call fails.
+    pub async fn get(&self, key: &str) -> Result<Option<String>, WorkerEnvError> {
+        let call = self.call("get", &[JsValue::from_str(key)])?;
+        Ok(call.await?.as_string())
+    }
+
+    /// Store `value` under `key`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the KV call fails.
+    pub async fn put(&self, key: &str, value: &str) -> Result<(), WorkerEnvError> {
+        let call = self.call("put", &[JsValue::from_str(key), JsValue::from_str(value)])?;
+        call.await.map(drop)
+    }
+
+    /// Remove `key`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the KV call fails.
+    pub async fn delete(&self, key: &str) -> Result<(), WorkerEnvError> {
+        let call = self.call("delete", &[JsValue::from_str(key)])?;
+        call.await.map(drop)
+    }
+
+    /// Call the promise-returning method `method` on the namespace.
+    fn call(&self, method: &str, args: &[JsValue]) -> Result<KvFuture, WorkerEnvError> {
+        let js = |error: JsValue| WorkerEnvError::js(&self.name, &error);
+        let function = js_sys::Reflect::get(&self.namespace, &JsValue::from_str(method))
+            .map_err(js)?
+            .dyn_into::<js_sys::Function>()
+            .map_err(|_| WorkerEnvError::wrong_type(&self.name, "a KV namespace"))?;
+        let args = args.iter().collect::<js_sys::Array>();
+        let promise = js_sys::Reflect::apply(&function, &self.namespace, &args).map_err(js)?;
+        let promise = js_sys::Promise::resolve(&promise);
+        Ok(KvFuture {
+            name: self.name.clone(),
+            future: JsFuture::from(promise),
+        })
+    }
+}
+
+/// A pending KV call, settling into [`WorkerEnvError::Js`] if the promise rejects.
+struct KvFuture {
+    name: String,
+    future: JsFuture,
+}
+
+// SAFETY: WASM is single-threaded, so the `Rc` inside `JsFuture` is never shared across threads.
+#[allow(clippy::non_send_fields_in_send_ty)]
+unsafe impl Send for KvFuture {}
+
+impl Future for KvFuture {
+    type Output = Result<JsValue, WorkerEnvError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        Pin::new(&mut this.future)
+            .poll(cx)
+            .map_err(|error| WorkerEnvError::js(&this.name, &error))
+    }
+}
+
+/// Errors raised while reading Workers bindings. All of them are server errors (500).
+#[derive(Debug, Clone)]
+pub enum WorkerEnvError {
+    /// The request was not dispatched by `runtime::wasm::launch`, so no environment is attached.
+    Unavailable,
+    /// The environment has no binding with this name.
+    Missing(String),
+    /// The binding exists but has a different type.
+    WrongType {
+        /// Binding name.
+        name: String,
+        /// What the binding was expected to be.
+        expected: &'static str,
+    },
+    /// A JavaScript call on the binding threw or rejected.
+    Js {
+        /// Binding name.
+        name: String,
+        /// The JavaScript error message.
+        message: String,
+    },
+}
+
+impl WorkerEnvError {
+    fn wrong_type(name: &str, expected: &'static str) -> Self {
+        Self::WrongType {
+            name: name.to_owned(),
+            expected,
+        }
+    }
+
+    fn js(name: &str, error: &JsValue) -> Self {
+        let message = error
+            .dyn_ref::<js_sys::Error>()
+            .map(|error| String::from(error.message()))
+            .or_else(|| error.as_string())
+            .unwrap_or_else(|| format!("{error:?}"));
+        Self::Js {
+            name: name.to_owned(),
+            message,
+        }
+    }
+}
+
+impl fmt::Display for WorkerEnvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unavailable => {
+                f.write_str("Workers environment is not available for this request")
+            }
+            Self::Missing(name) => write!(f, "Workers binding `{name}` is not configured"),
+            Self::WrongType { name, expected } => {
+                write!(f, "Workers binding `{name}` is not {expected}")
+            }
+            Self::Js { name, message } => {
+                write!(f, "Workers binding `{name}` failed: {message}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for WorkerEnvError {}
+
+impl HttpError for WorkerEnvError {
+    fn status(&self) -> StatusCode {
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wasm_bindgen::JsValue;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::{WorkerEnv, WorkerEnvError};
+    use crate::{extract::Extractor, Body, Request};
+
+    fn env() -> WorkerEnv {
+        let env = js_sys::Object::new();
+        js_sys::Reflect::set(&env, &"API_TOKEN".into(), &"s3cret".into()).unwrap();
+        js_sys::Reflect::set(&env, &"COUNT".into(), &JsValue::from_f64(3.0)).unwrap();
+
+        let kv = js_sys::Object::new();
+        let get = js_sys::Function::new_with_args(
+            "key",
+            "return Promise.resolve(key === 'greeting' ? 'hello' : null)",
+        );
+        js_sys::Reflect::set(&kv, &"get".into(), &get).unwrap();
+        js_sys::Reflect::set(&env, &"CONFIG".into(), &kv).unwrap();
+        WorkerEnv::new(env.into())
+    }
+
+    #[wasm_bindgen_test]
+    fn reads_secrets() {
+        let env = env();
+        assert_eq!(env.secret("API_TOKEN").unwrap(), "s3cret");
+        assert!(matches!(
+            env.secret("MISSING"),
+            Err(WorkerEnvError::Missing(name)) if name == "MISSING"
+        ));
+        assert!(matches!(
+            env.secret("COUNT"),
+            Err(WorkerEnvError::WrongType { .. })
+        ));
+    }
+
+    #[wasm_bindgen_test]
+    async fn reads_kv_values() {
+        let kv = env().kv("CONFIG").unwrap();
+        assert_eq!(kv.get("greeting").await.unwrap().as_deref(), Some("hello"));
+        assert_eq!(kv.get("other").await.unwrap(), None);
+        assert!(matches!(
+            kv.put("greeting", "hi").await,
+            Err(WorkerEnvError::WrongType { .. })
+        ));
+    }
+
+    #[wasm_bindgen_test]
+    async fn extracts_from_request_extensions() {
+        let mut request = Request::new(Body::empty());
+        assert!(matches!(
+            WorkerEnv::extract(&mut request).await,
+            Err(WorkerEnvError::Unavailable)
+        ));
+
+        request.extensions_mut().insert(env());
+        let env = WorkerEnv::extract(&mut request).await.unwrap();
+        assert_eq!(env.secret("API_TOKEN").unwrap(), "s3cret");
+    }
+}
@@ -6,6 +6,7 @@
 //! }
 //! ```
 
+use crate::extract::{Rejection, RejectionHandler};
 use core::{future::Future, marker::PhantomData};
 use http_kit::{Endpoint, Request, Response};
 use skyzen_core::{Extractor, Responder};
@@ -46,10 +47,27 @@ impl<E: Extractor, R: Responder> http_kit::HttpError for HandlerError<E, R> {
     }
 }
 
-impl<E: Extractor, R: Responder> core::error::Error for HandlerError<E, R> {}
+impl<E: Extractor, R: Responder> core::error::Error for HandlerError<E, R> {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Self::ExtractorError(e) => Some(e),
+            Self::ResponderError(e) => Some(e),
+        }
+    }
+}
 
 /// An HTTP handler.
 /// This trait is a wrapper trait for `Fn` types. You will rarely use this type directly.
+///
+/// Async functions and closures taking up to 16 [`Extractor`]s and returning a [`Responder`] are
+/// handlers.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` is not a valid skyzen handler",
+    label = "not a handler",
+    note = "handlers are async functions or closures taking up to 16 arguments, each of which implements `Extractor`",
+    note = "the output of the returned future has to implement `Responder`, such as `skyzen::Result<T>` for a responder `T`",
+    note = "closures have to be `Clone + Send + Sync + 'static`, so captured state has to be `Clone` (wrap it in an `Arc` if needed)"
+)]
 pub trait Handler<T: Extractor, R: Responder>: Send + Sync + Clone + 'static {
     /// Handle the request and make a response.
     fn call_handler(
@@ -123,6 +141,15 @@ impl<H: Handler<T, R> + Send + Sync, T: Extractor + Send + Sync, R: Responder +
 {
     type Error = HandlerError<T, R>;
     async fn respond(&mut self, request: &mut Request) -> Result<Response, Self::Error> {
-        self.handler.call_handler(request).await
+        match self.handler.call_handler(request).await {
+            Err(HandlerError::ExtractorError(error)) => {
+                let rejected = request
+                    .extensions()
+                    .get::<RejectionHandler>()
+                    .and_then(|handler| handler.render(&Rejection::new(&error), request));
+                rejected.ok_or(HandlerError::ExtractorError(error))
+            }
+            result => result,
+        }
     }
 }
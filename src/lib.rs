@@ -23,7 +23,7 @@ pub mod utils;
 pub mod runtime;
 
 /// Attribute & derive macros exported by Skyzen.
-pub use skyzen_macros::{error, main, openapi, HttpError};
+pub use skyzen_macros::{error, main, openapi, scheduled, FromRef, HttpError};
 
 /// Static asset helpers for building file servers.
 #[cfg(not(target_arch = "wasm32"))]
@@ -33,13 +33,13 @@ pub use static_files::StaticDir;
 
 #[doc(inline)]
 pub use http_kit::{
-    header, Body, BodyError, Endpoint, HttpError, Method, Middleware, Request, Response,
-    StatusCode, Uri,
+    header, Body, BodyError, Endpoint, Extensions, HttpError, Method, Middleware, Request,
+    Response, StatusCode, Uri,
 };
 #[doc(inline)]
 pub use routing::{CreateRouteNode, Route};
 pub use skyzen_core::error::*;
-pub use skyzen_core::Server;
+pub use skyzen_core::{HttpConfig, Server};
 
 /// Hyper-based server backend.
 #[cfg(all(feature = "hyper", not(target_arch = "wasm32")))]
@@ -50,6 +50,18 @@ pub use openapi::{IgnoreOpenApi, OpenApi, OpenApiOperation};
 
 pub use utoipa::{PartialSchema, ToSchema};
 
+/// UUIDs, documented in `OpenAPI` as strings with the `uuid` format.
+#[cfg(feature = "uuid")]
+pub use uuid;
+
+/// Date and time types, documented in `OpenAPI` as `date` and `date-time` strings.
+#[cfg(feature = "chrono")]
+pub use chrono;
+
+/// Typed HTTP headers used with [`extract::TypedHeader`].
+#[cfg(feature = "typed-header")]
+pub use headers;
+
 /// Extract strong-typed object from your request.
 pub mod extract;
 
@@ -58,6 +70,14 @@ pub use responder::Responder;
 
 pub mod middleware;
 
+pub mod auth;
+
+#[cfg(all(feature = "tower", not(target_arch = "wasm32")))]
+pub mod tower;
+
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test;
+
 #[cfg(feature = "ws")]
 pub mod websocket;
 #[cfg(feature = "ws")]
@@ -0,0 +1,460 @@
+//! Structured access logs.
+//!
+//! [`AccessLogMiddleware`] emits one tracing event per request, with the fields `method`, `path`,
+//! `route`, `status`, `latency_ms`, `response_size`, `client_ip` and `request_id`. Wrap the built
+//! router so that requests matching no route are logged too:
+//!
+//! ```
+//! use skyzen::{
+//!     middleware::{AccessLogMiddleware, QueryRule, StatusClass, WithMiddleware},
+//!     routing::{CreateRouteNode, Route},
+//!     Result,
+//! };
+//! use tracing::Level;
+//!
+//! let router = Route::new(("/search".at(|| async { Result::Ok("results") }),)).build();
+//! let app = WithMiddleware::new(
+//!     router,
+//!     AccessLogMiddleware::new()
+//!         .query_param("q", QueryRule::Keep)
+//!         .query_param("user", QueryRule::Hash)
+//!         .level(StatusClass::ClientError, Level::INFO),
+//! );
+//! ```
+//!
+//! Query strings are rewritten before they are logged: by default every value is replaced with
+//! `[redacted]` and only parameter names are kept. [`AccessLogMiddleware::query_param`] and
+//! [`AccessLogMiddleware::default_query_rule`] choose per parameter whether the value is kept,
+//! redacted, or replaced by a hash, which lets requests with the same value be correlated without
+//! logging it. The hash is not cryptographic and must not be relied on to hide guessable values.
+//!
+//! While the middleware is installed, the runtime's own `request received` and `request completed`
+//! lines are skipped, so requests are not logged twice.
+
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    fmt::{self, Write},
+    net::IpAddr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use http_kit::{middleware::MiddlewareError, Endpoint, HttpError, Middleware};
+use tracing::{field, Level};
+
+use crate::{
+    extract::{ClientIp, Extractor},
+    header::{self, HeaderName},
+    routing::MatchedPath,
+    Method, Request, Response, StatusCode,
+};
+
+const X_REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Placed in the request extensions by [`AccessLogMiddleware`] so the runtime skips its own
+/// per-request log lines.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct AccessLogged;
+
+impl AccessLogged {
+    pub(crate) fn is_set(request: &Request) -> bool {
+        request.extensions().get::<Self>().is_some()
+    }
+}
+
+/// What to log in place of a query parameter's value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryRule {
+    /// Log the value unchanged.
+    Keep,
+    /// Replace the value with `[redacted]`.
+    Redact,
+    /// Replace the value with a 64-bit FNV-1a hash in hex.
+    Hash,
+    /// Leave the parameter out entirely.
+    Drop,
+}
+
+/// The class of a response status, used to pick the level of its access log event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatusClass {
+    /// `1xx` responses.
+    Informational,
+    /// `2xx` responses.
+    Success,
+    /// `3xx` responses.
+    Redirection,
+    /// `4xx` responses.
+    ClientError,
+    /// `5xx` responses.
+    ServerError,
+}
+
+impl StatusClass {
+    /// The class of `status`.
+    #[must_use]
+    pub const fn of(status: StatusCode) -> Self {
+        match status.as_u16() / 100 {
+            1 => Self::Informational,
+            2 => Self::Success,
+            3 => Self::Redirection,
+            4 => Self::ClientError,
+            _ => Self::ServerError,
+        }
+    }
+
+    const fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// The fields of one access log event, passed to a
+/// [custom formatter](AccessLogMiddleware::formatter).
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct AccessLogRecord<'a> {
+    /// Request method.
+    pub method: &'a Method,
+    /// Request path, followed by the query string after redaction.
+    pub path: &'a str,
+    /// The pattern of the route that handled the request, if any.
+    pub route: Option<&'a str>,
+    /// Response status.
+    pub status: StatusCode,
+    /// Time until the response was produced, not including streaming its body.
+    pub latency: Duration,
+    /// Response body length, when known up front.
+    pub response_size: Option<u64>,
+    /// Client address as reported by [`ClientIp`].
+    pub client_ip: Option<IpAddr>,
+    /// The `X-Request-Id` of the request, or of the response if the request had none.
+    pub request_id: Option<&'a str>,
+}
+
+type Formatter = dyn Fn(&AccessLogRecord<'_>) -> String + Send + Sync;
+
+/// Logs every request as a structured tracing event, see the [module docs](self).
+#[derive(Clone)]
+pub struct AccessLogMiddleware {
+    levels: [Level; 5],
+    default_query_rule: QueryRule,
+    query_rules: Arc<HashMap<String, QueryRule>>,
+    formatter: Option<Arc<Formatter>>,
+}
+
+impl fmt::Debug for AccessLogMiddleware {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AccessLogMiddleware")
+            .field("levels", &self.levels)
+            .field("default_query_rule", &self.default_query_rule)
+            .field("query_rules", &self.query_rules)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for AccessLogMiddleware {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AccessLogMiddleware {
+    /// Log `4xx` responses at `WARN`, `5xx` responses at `ERROR` and everything else at `INFO`,
+    /// redacting every query value.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            levels: [
+                Level::INFO,
+                Level::INFO,
+                Level::INFO,
+                Level::WARN,
+                Level::ERROR,
+            ],
+            default_query_rule: QueryRule::Redact,
+            query_rules: Arc::default(),
+            formatter: None,
+        }
+    }
+
+    /// Log responses of `class` at `level`.
+    #[must_use]
+    pub const fn level(mut self, class: StatusClass, level: Level) -> Self {
+        self.levels[class.index()] = level;
+        self
+    }
+
+    /// Apply `rule` to query parameters without a rule of their own.
+    #[must_use]
+    pub const fn default_query_rule(mut self, rule: QueryRule) -> Self {
+        self.default_query_rule = rule;
+        self
+    }
+
+    /// Apply `rule` to the query parameter called `name`.
+    #[must_use]
+    pub fn query_param(mut self, name: impl Into<String>, rule: QueryRule) -> Self {
+        Arc::make_mut(&mut self.query_rules).insert(name.into(), rule);
+        self
+    }
+
+    /// Render the event message with `formatter` instead of `request completed`.
+    ///
+    /// The structured fields are recorded either way.
+    #[must_use]
+    pub fn formatter<F>(mut self, formatter: F) -> Self
+    where
+        F: Fn(&AccessLogRecord<'_>) -> String + Send + Sync + 'static,
+    {
+        self.formatter = Some(Arc::new(formatter));
+        self
+    }
+
+    /// The path and query of `request`, with the query rewritten by the configured rules.
+    fn redacted_path(&self, request: &Request) -> String {
+        let uri = request.uri();
+        let mut path = uri.path().to_owned();
+        let Some(query) = uri.query() else {
+            return path;
+        };
+
+        let mut separator = '?';
+        for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+            let (name, value) = match pair.split_once('=') {
+                Some((name, value)) => (name, Some(value)),
+                None => (pair, None),
+            };
+            let rule = self
+                .query_rules
+                .get(name)
+                .copied()
+                .unwrap_or(self.default_query_rule);
+            if rule == QueryRule::Drop {
+                continue;
+            }
+            path.push(separator);
+            separator = '&';
+            path.push_str(name);
+            let Some(value) = value else {
+                continue;
+            };
+            path.push('=');
+            match rule {
+                QueryRule::Keep => path.push_str(value),
+                QueryRule::Redact => path.push_str("[redacted]"),
+                QueryRule::Hash => {
+                    let _ = write!(path, "{:016x}", fnv1a(value.as_bytes()));
+                }
+                QueryRule::Drop => unreachable!(),
+            }
+        }
+        path
+    }
+
+    fn log(&self, record: &AccessLogRecord<'_>) {
+        let message = self.formatter.as_ref().map_or_else(
+            || String::from("request completed"),
+            |formatter| formatter(record),
+        );
+        macro_rules! emit {
+            ($level:expr) => {
+                tracing::event!(
+                    $level,
+                    method = record.method.as_str(),
+                    path = record.path,
+                    route = record.route,
+                    status = record.status.as_u16(),
+                    latency_ms = record.latency.as_secs_f64() * 1000.0,
+                    response_size = record.response_size,
+                    client_ip = record.client_ip.map(field::display),
+                    request_id = record.request_id,
+                    "{message}"
+                )
+            };
+        }
+        match self.levels[StatusClass::of(record.status).index()] {
+            Level::TRACE => emit!(Level::TRACE),
+            Level::DEBUG => emit!(Level::DEBUG),
+            Level::INFO => emit!(Level::INFO),
+            Level::WARN => emit!(Level::WARN),
+            Level::ERROR => emit!(Level::ERROR),
+        }
+    }
+}
+
+impl Middleware for AccessLogMiddleware {
+    type Error = Infallible;
+
+    async fn handle<N: Endpoint>(
+        &mut self,
+        request: &mut Request,
+        mut next: N,
+    ) -> Result<Response, MiddlewareError<N::Error, Self::Error>> {
+        request.extensions_mut().insert(AccessLogged);
+        let started = Instant::now();
+        let method = request.method().clone();
+        let path = self.redacted_path(request);
+        let client_ip = ClientIp::extract(request).await.ok().map(|ClientIp(ip)| ip);
+
+        let result = next.respond(request).await;
+        let latency = started.elapsed();
+
+        let (status, response_size, response_id) = match &result {
+            Ok(response) => (
+                response.status(),
+                response_size(response),
+                response.headers().get(X_REQUEST_ID),
+            ),
+            Err(error) => (error.status(), None, None),
+        };
+        let request_id = request
+            .headers()
+            .get(X_REQUEST_ID)
+            .or(response_id)
+            .and_then(|id| id.to_str().ok());
+        self.log(&AccessLogRecord {
+            method: &method,
+            path: &path,
+            route: request
+                .extensions()
+                .get::<MatchedPath>()
+                .map(MatchedPath::as_str),
+            status,
+            latency,
+            response_size,
+            client_ip,
+            request_id,
+        });
+
+        result.map_err(MiddlewareError::Endpoint)
+    }
+}
+
+fn response_size(response: &Response) -> Option<u64> {
+    response
+        .body()
+        .len()
+        .and_then(|len| u64::try_from(len).ok())
+        .or_else(|| {
+            response
+                .headers()
+                .get(header::CONTENT_LENGTH)?
+                .to_str()
+                .ok()?
+                .parse()
+                .ok()
+        })
+}
+
+/// 64-bit FNV-1a, chosen because it is stable across processes and builds.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io,
+        sync::{Arc, Mutex},
+    };
+
+    use tracing::Level;
+
+    use super::{AccessLogMiddleware, QueryRule, StatusClass};
+    use crate::{
+        middleware::WithMiddleware,
+        routing::{CreateRouteNode, Route},
+        Body, Endpoint, Request, Result,
+    };
+
+    fn request(uri: &str) -> Request {
+        let mut request = Request::new(Body::empty());
+        *request.uri_mut() = uri.parse().unwrap();
+        request
+    }
+
+    #[test]
+    fn rewrites_query_strings() {
+        let log = AccessLogMiddleware::new()
+            .query_param("page", QueryRule::Keep)
+            .query_param("user", QueryRule::Hash)
+            .query_param("debug", QueryRule::Drop);
+        assert_eq!(
+            log.redacted_path(&request("/search?q=secret&page=2&user=ada&debug=1&flag")),
+            "/search?q=[redacted]&page=2&user=e70bc0190530e13b&flag"
+        );
+        assert_eq!(log.redacted_path(&request("/search?debug=1")), "/search");
+
+        let keep_all = AccessLogMiddleware::new().default_query_rule(QueryRule::Keep);
+        assert_eq!(keep_all.redacted_path(&request("/a?b=c")), "/a?b=c");
+    }
+
+    #[derive(Clone, Default)]
+    struct Captured(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for Captured {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn logs_one_event_per_request() {
+        let captured = Captured::default();
+        let writer = captured.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_ansi(false)
+            .with_max_level(Level::DEBUG)
+            .with_writer(move || writer.clone())
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let router = Route::new(("/users/{id}".at(|| async { Result::Ok("ada") }),)).build();
+        let mut app = WithMiddleware::new(
+            router,
+            AccessLogMiddleware::new()
+                .level(StatusClass::ClientError, Level::DEBUG)
+                .formatter(|record| format!("{} {}", record.method, record.status.as_u16())),
+        );
+
+        let mut hit = request("/users/7?token=abc");
+        hit.headers_mut()
+            .insert("x-request-id", "req-1".parse().unwrap());
+        app.respond(&mut hit).await.unwrap();
+        app.respond(&mut request("/missing")).await.unwrap();
+
+        let output = String::from_utf8(captured.0.lock().unwrap().clone()).unwrap();
+        assert!(!output.contains("request received"), "{output}");
+        let lines = output
+            .lines()
+            .filter(|line| line.contains("GET "))
+            .collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2, "{output}");
+        for expected in [
+            " INFO ",
+            "GET 200",
+            "path=\"/users/7?token=[redacted]\"",
+            "route=\"/users/{id}\"",
+            "status=200",
+            "response_size=3",
+            "request_id=\"req-1\"",
+        ] {
+            assert!(
+                lines[0].contains(expected),
+                "missing `{expected}` in {}",
+                lines[0]
+            );
+        }
+        assert!(lines[1].contains("DEBUG"), "{}", lines[1]);
+        assert!(lines[1].contains("GET 404"), "{}", lines[1]);
+        assert!(!lines[1].contains("route="), "{}", lines[1]);
+    }
+}
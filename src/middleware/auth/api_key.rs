@@ -0,0 +1,309 @@
+//! Authenticate requests with a static API key.
+
+use std::future::Future;
+
+use http::StatusCode;
+use http_kit::{
+    error::BoxHttpError,
+    header::{HeaderName, HeaderValue},
+    HttpError, Request,
+};
+
+use super::Authenticator;
+use crate::utils::percent::percent_decode;
+
+const X_API_KEY: HeaderName = HeaderName::from_static("x-api-key");
+
+/// Authenticate requests by the API key they carry, `X-Api-Key` by default.
+///
+/// The key is handed to an [`ApiKeyValidator`]: an async closure such as
+/// `|key: &str| lookup(key.to_owned())` for keys stored in a database, or [`StaticApiKeys`] for a
+/// fixed set. Requests without a key, or with a key rejected by `401`, receive a
+/// `WWW-Authenticate: ApiKey ...` challenge naming where the key is expected.
+///
+/// ```
+/// use skyzen::middleware::auth::{ApiKeyAuthenticator, AuthMiddleware, StaticApiKeys};
+///
+/// let keys = StaticApiKeys::new().with_key("s3cr3t", "billing-service");
+/// let auth = AuthMiddleware::new(ApiKeyAuthenticator::new(keys));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ApiKeyAuthenticator<V> {
+    location: ApiKeyLocation,
+    validator: V,
+}
+
+/// Where [`ApiKeyAuthenticator`] reads the key from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ApiKeyLocation {
+    /// A request header.
+    Header(HeaderName),
+    /// A query parameter.
+    Query(String),
+}
+
+impl ApiKeyLocation {
+    fn key(&self, request: &Request) -> Option<String> {
+        match self {
+            Self::Header(name) => request
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_owned),
+            Self::Query(name) => request.uri().query()?.split('&').find_map(|pair| {
+                let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+                (key == name).then(|| percent_decode(value)).flatten()
+            }),
+        }
+        .filter(|key| !key.is_empty())
+    }
+}
+
+impl<V> ApiKeyAuthenticator<V> {
+    /// Validate the `X-Api-Key` header with `validator`.
+    pub const fn new(validator: V) -> Self {
+        Self {
+            location: ApiKeyLocation::Header(X_API_KEY),
+            validator,
+        }
+    }
+
+    /// Read the key from the header `name` instead.
+    #[must_use]
+    pub fn header(mut self, name: HeaderName) -> Self {
+        self.location = ApiKeyLocation::Header(name);
+        self
+    }
+
+    /// Read the key from the query parameter `name` instead.
+    #[must_use]
+    pub fn query(mut self, name: impl Into<String>) -> Self {
+        self.location = ApiKeyLocation::Query(name.into());
+        self
+    }
+}
+
+impl<V> Authenticator for ApiKeyAuthenticator<V>
+where
+    V: ApiKeyValidator + Sync,
+{
+    type User = V::Claims;
+    type Error = BoxHttpError;
+
+    fn authenticate(
+        &self,
+        req: &Request,
+    ) -> impl Future<Output = Result<Self::User, Self::Error>> + Send {
+        let key = self.location.key(req);
+        async move {
+            let key = key.ok_or_else(|| Box::new(ApiKeyError::Missing) as BoxHttpError)?;
+            self.validator
+                .validate(&key)
+                .await
+                .map_err(|error| Box::new(error) as BoxHttpError)
+        }
+    }
+
+    fn has_credentials(&self, req: &Request) -> bool {
+        self.location.key(req).is_some()
+    }
+
+    fn challenge(&self) -> Option<HeaderValue> {
+        let challenge = match &self.location {
+            ApiKeyLocation::Header(name) => format!("ApiKey header=\"{name}\""),
+            ApiKeyLocation::Query(name) => format!("ApiKey query=\"{name}\""),
+        };
+        HeaderValue::try_from(challenge).ok()
+    }
+}
+
+/// Check an API key and resolve the claims of its owner.
+pub trait ApiKeyValidator {
+    /// What a valid key resolves to, stored as the authenticated user.
+    type Claims;
+    /// The error returned for rejected keys or failed lookups.
+    type Error: HttpError;
+
+    /// Validate `key`.
+    fn validate(&self, key: &str)
+        -> impl Future<Output = Result<Self::Claims, Self::Error>> + Send;
+}
+
+impl<F, Fut, C, E> ApiKeyValidator for F
+where
+    F: Fn(&str) -> Fut,
+    Fut: Future<Output = Result<C, E>> + Send,
+    E: HttpError,
+{
+    type Claims = C;
+    type Error = E;
+
+    fn validate(&self, key: &str) -> impl Future<Output = Result<C, E>> + Send {
+        self(key)
+    }
+}
+
+/// A fixed set of API keys, compared in constant time.
+#[derive(Debug, Clone)]
+pub struct StaticApiKeys<C> {
+    keys: Vec<(String, C)>,
+}
+
+impl<C> Default for StaticApiKeys<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C> StaticApiKeys<C> {
+    /// An empty key set that rejects every key.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { keys: Vec::new() }
+    }
+
+    /// Accept `key`, authenticating its bearer as `claims`.
+    #[must_use]
+    pub fn with_key(mut self, key: impl Into<String>, claims: C) -> Self {
+        self.keys.push((key.into(), claims));
+        self
+    }
+}
+
+impl<C: Clone + Send + Sync> ApiKeyValidator for StaticApiKeys<C> {
+    type Claims = C;
+    type Error = ApiKeyError;
+
+    fn validate(&self, key: &str) -> impl Future<Output = Result<C, ApiKeyError>> + Send {
+        // Every key is compared so the time taken does not reveal which one matched.
+        let mut matched = None;
+        for (candidate, claims) in &self.keys {
+            if constant_time_eq(candidate.as_bytes(), key.as_bytes()) {
+                matched = Some(claims);
+            }
+        }
+        let result = matched.cloned().ok_or(ApiKeyError::Invalid);
+        async move { result }
+    }
+}
+
+/// An error occurred while authenticating an API key.
+#[skyzen::error(status = StatusCode::UNAUTHORIZED)]
+pub enum ApiKeyError {
+    /// The request does not carry an API key.
+    #[error("Missing API key")]
+    Missing,
+    /// The API key is not recognised.
+    #[error("Invalid API key")]
+    Invalid,
+}
+
+/// Compare `a` and `b` without short-circuiting on the first differing byte.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::{constant_time_eq, ApiKeyAuthenticator, ApiKeyError, StaticApiKeys};
+    use crate::{
+        header::{HeaderName, WWW_AUTHENTICATE},
+        middleware::auth::AuthMiddleware,
+        routing::{CreateRouteNode, Route},
+        utils::State,
+        Body, Endpoint, Method, Request, Result, StatusCode,
+    };
+
+    fn request(uri: &str, headers: &[(&'static str, &'static str)]) -> Request {
+        let mut request = Request::new(Body::empty());
+        *request.uri_mut() = uri.parse().unwrap();
+        *request.method_mut() = Method::GET;
+        for (name, value) in headers {
+            request.headers_mut().append(*name, value.parse().unwrap());
+        }
+        request
+    }
+
+    async fn whoami(State(service): State<&'static str>) -> Result<String> {
+        Ok(format!("hello {service}"))
+    }
+
+    fn keys() -> StaticApiKeys<&'static str> {
+        StaticApiKeys::new()
+            .with_key("key-billing", "billing")
+            .with_key("key-search", "search")
+    }
+
+    #[tokio::test]
+    async fn accepts_known_header_keys() {
+        let mut router = Route::new(("/me".at(whoami),))
+            .middleware(AuthMiddleware::new(ApiKeyAuthenticator::new(keys())))
+            .build();
+
+        let mut req = request("/me", &[("x-api-key", "key-search")]);
+        let response = router.respond(&mut req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().into_string().await.unwrap();
+        assert_eq!(body, "hello search");
+    }
+
+    #[tokio::test]
+    async fn challenges_missing_and_unknown_keys() {
+        let mut router = Route::new(("/me".at(whoami),))
+            .middleware(AuthMiddleware::new(
+                ApiKeyAuthenticator::new(keys()).header(HeaderName::from_static("x-service-key")),
+            ))
+            .build();
+
+        for headers in [&[][..], &[("x-service-key", "key-nope")][..]] {
+            let mut req = request("/me", headers);
+            let response = router.respond(&mut req).await.unwrap();
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+            assert_eq!(
+                response.headers()[WWW_AUTHENTICATE],
+                "ApiKey header=\"x-service-key\""
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn validates_query_keys_with_async_closure() {
+        let validator = |key: &str| {
+            let known = key == "from-db";
+            async move {
+                if known {
+                    Ok("reporting")
+                } else {
+                    Err(ApiKeyError::Invalid)
+                }
+            }
+        };
+        let mut router = Route::new(("/me".at(whoami),))
+            .middleware(AuthMiddleware::new(
+                ApiKeyAuthenticator::new(validator).query("api_key"),
+            ))
+            .build();
+
+        let mut req = request("/me?api_key=from-db", &[]);
+        let response = router.respond(&mut req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let mut req = request("/me?api_key=other", &[]);
+        let response = router.respond(&mut req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            response.headers()[WWW_AUTHENTICATE],
+            "ApiKey query=\"api_key\""
+        );
+    }
+
+    #[test]
+    fn compares_in_constant_time() {
+        assert!(constant_time_eq(b"secret", b"secret"));
+        assert!(!constant_time_eq(b"secret", b"secreT"));
+        assert!(!constant_time_eq(b"secret", b"secret!"));
+    }
+}
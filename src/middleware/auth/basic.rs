@@ -0,0 +1,248 @@
+//! Authenticate requests with HTTP Basic credentials.
+
+use std::{collections::HashMap, future::Future};
+
+use http_kit::{error::BoxHttpError, header::HeaderValue, HttpError, Request};
+
+use super::{api_key::constant_time_eq, Authenticator};
+use crate::extract::{BasicAuth, BasicAuthError};
+
+/// Authenticate requests by their [`BasicAuth`] credentials.
+///
+/// Verification is delegated to a [`BasicValidator`]: an async closure taking the user name and
+/// password, or [`StaticCredentials`] for a fixed set of users. Failures with `401` receive a
+/// `WWW-Authenticate: Basic realm="..."` challenge so browsers prompt for credentials.
+///
+/// ```
+/// use skyzen::middleware::auth::{AuthMiddleware, BasicAuthenticator, StaticCredentials};
+///
+/// let users = StaticCredentials::new().with_user("admin", "hunter2", "admin");
+/// let auth = AuthMiddleware::new(BasicAuthenticator::new(users).realm("ops"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct BasicAuthenticator<V> {
+    realm: String,
+    validator: V,
+}
+
+impl<V> BasicAuthenticator<V> {
+    /// Verify credentials with `validator` in the `Restricted` realm.
+    pub fn new(validator: V) -> Self {
+        Self {
+            realm: "Restricted".to_owned(),
+            validator,
+        }
+    }
+
+    /// Name the protection space shown to the user.
+    #[must_use]
+    pub fn realm(mut self, realm: impl Into<String>) -> Self {
+        self.realm = realm.into();
+        self
+    }
+}
+
+impl<V> Authenticator for BasicAuthenticator<V>
+where
+    V: BasicValidator + Sync,
+{
+    type User = V::User;
+    type Error = BoxHttpError;
+
+    fn authenticate(
+        &self,
+        req: &Request,
+    ) -> impl Future<Output = Result<Self::User, Self::Error>> + Send {
+        let credentials = BasicAuth::resolve(req);
+        async move {
+            let credentials = credentials.map_err(|error| Box::new(error) as BoxHttpError)?;
+            self.validator
+                .verify(credentials.username(), credentials.password())
+                .await
+                .map_err(|error| Box::new(error) as BoxHttpError)
+        }
+    }
+
+    fn has_credentials(&self, req: &Request) -> bool {
+        !matches!(BasicAuth::resolve(req), Err(BasicAuthError::Missing))
+    }
+
+    fn challenge(&self) -> Option<HeaderValue> {
+        let realm = self.realm.replace('\\', "\\\\").replace('"', "\\\"");
+        HeaderValue::try_from(format!("Basic realm=\"{realm}\", charset=\"UTF-8\"")).ok()
+    }
+}
+
+/// Verify a user name and password.
+pub trait BasicValidator {
+    /// The authenticated user, stored as the request's user.
+    type User;
+    /// The error returned for wrong credentials or failed lookups.
+    type Error: HttpError;
+
+    /// Verify `password` for `username`.
+    fn verify(
+        &self,
+        username: &str,
+        password: &str,
+    ) -> impl Future<Output = Result<Self::User, Self::Error>> + Send;
+}
+
+impl<F, Fut, U, E> BasicValidator for F
+where
+    F: Fn(&str, &str) -> Fut,
+    Fut: Future<Output = Result<U, E>> + Send,
+    E: HttpError,
+{
+    type User = U;
+    type Error = E;
+
+    fn verify(&self, username: &str, password: &str) -> impl Future<Output = Result<U, E>> + Send {
+        self(username, password)
+    }
+}
+
+/// A fixed set of users whose passwords are compared in constant time.
+#[derive(Debug, Clone)]
+pub struct StaticCredentials<U> {
+    users: HashMap<String, (String, U)>,
+}
+
+impl<U> Default for StaticCredentials<U> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<U> StaticCredentials<U> {
+    /// An empty set that rejects every request.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            users: HashMap::new(),
+        }
+    }
+
+    /// Accept `username` with `password`, authenticating it as `user`.
+    #[must_use]
+    pub fn with_user(
+        mut self,
+        username: impl Into<String>,
+        password: impl Into<String>,
+        user: U,
+    ) -> Self {
+        self.users.insert(username.into(), (password.into(), user));
+        self
+    }
+}
+
+impl<U: Clone + Send + Sync> BasicValidator for StaticCredentials<U> {
+    type User = U;
+    type Error = BasicAuthError;
+
+    fn verify(
+        &self,
+        username: &str,
+        password: &str,
+    ) -> impl Future<Output = Result<U, BasicAuthError>> + Send {
+        let result = self
+            .users
+            .get(username)
+            .filter(|(expected, _)| constant_time_eq(expected.as_bytes(), password.as_bytes()))
+            .map(|(_, user)| user.clone())
+            .ok_or(BasicAuthError::InvalidCredentials);
+        async move { result }
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::{BasicAuthenticator, StaticCredentials};
+    use crate::{
+        extract::BasicAuthError,
+        header::WWW_AUTHENTICATE,
+        middleware::auth::AuthMiddleware,
+        routing::{CreateRouteNode, Route},
+        utils::State,
+        Body, Endpoint, Method, Request, Result, StatusCode,
+    };
+
+    fn request(authorization: Option<&'static str>) -> Request {
+        let mut request = Request::new(Body::empty());
+        *request.uri_mut() = "/admin".parse().unwrap();
+        *request.method_mut() = Method::GET;
+        if let Some(value) = authorization {
+            request
+                .headers_mut()
+                .insert("authorization", value.parse().unwrap());
+        }
+        request
+    }
+
+    async fn admin(State(user): State<String>) -> Result<String> {
+        Ok(format!("welcome {user}"))
+    }
+
+    #[tokio::test]
+    async fn protects_routes_with_static_credentials() {
+        let users = StaticCredentials::new().with_user("alice", "wonderland", "alice".to_owned());
+        let mut router = Route::new(("/admin".at(admin),))
+            .middleware(AuthMiddleware::new(
+                BasicAuthenticator::new(users).realm("back \"office\""),
+            ))
+            .build();
+
+        // "alice:wonderland"
+        let mut req = request(Some("Basic YWxpY2U6d29uZGVybGFuZA=="));
+        let response = router.respond(&mut req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().into_string().await.unwrap();
+        assert_eq!(body, "welcome alice");
+
+        // Missing header, wrong password ("alice:rabbit"), malformed base64, missing colon.
+        for authorization in [
+            None,
+            Some("Basic YWxpY2U6cmFiYml0"),
+            Some("Basic %%%"),
+            Some("Basic YWxpY2U="),
+        ] {
+            let mut req = request(authorization);
+            let response = router.respond(&mut req).await.unwrap();
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+            assert_eq!(
+                response.headers()[WWW_AUTHENTICATE],
+                r#"Basic realm="back \"office\"", charset="UTF-8""#
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn delegates_to_async_callback() {
+        let verify = |username: &str, password: &str| {
+            let result = if password == "letmein" {
+                Ok(username.to_uppercase())
+            } else {
+                Err(BasicAuthError::InvalidCredentials)
+            };
+            async move { result }
+        };
+        let mut router = Route::new(("/admin".at(admin),))
+            .middleware(AuthMiddleware::new(BasicAuthenticator::new(verify)))
+            .build();
+
+        // "bob:letmein"
+        let mut req = request(Some("Basic Ym9iOmxldG1laW4="));
+        let response = router.respond(&mut req).await.unwrap();
+        let body = response.into_body().into_string().await.unwrap();
+        assert_eq!(body, "welcome BOB");
+
+        // "bob:nope"
+        let mut req = request(Some("Basic Ym9iOm5vcGU="));
+        let response = router.respond(&mut req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            response.headers()[WWW_AUTHENTICATE],
+            r#"Basic realm="Restricted", charset="UTF-8""#
+        );
+    }
+}
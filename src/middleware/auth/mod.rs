@@ -0,0 +1,308 @@
+//! Authentication middleware.
+
+use std::future::Future;
+
+use http_kit::{
+    header::{HeaderMap, HeaderValue, WWW_AUTHENTICATE},
+    middleware::MiddlewareError,
+    Endpoint, HttpError, Middleware, Request, Response, StatusCode,
+};
+use skyzen_core::error::{with_headers, BoxHttpError};
+
+use crate::utils::State;
+
+mod api_key;
+pub(crate) use api_key::constant_time_eq;
+pub use api_key::{
+    ApiKeyAuthenticator, ApiKeyError, ApiKeyLocation, ApiKeyValidator, StaticApiKeys,
+};
+
+mod basic;
+pub use basic::{BasicAuthenticator, BasicValidator, StaticCredentials};
+
+/// Trait for authenticating users from requests.
+pub trait Authenticator {
+    /// The type of user returned upon successful authentication.
+    type User;
+    /// The error type returned when authentication fails.
+    type Error;
+
+    /// Authenticate a user from the given request.
+    fn authenticate(
+        &self,
+        req: &Request,
+    ) -> impl Future<Output = Result<Self::User, Self::Error>> + Send;
+
+    /// `WWW-Authenticate` challenge sent along with `401 Unauthorized` failures.
+    fn challenge(&self) -> Option<HeaderValue> {
+        None
+    }
+
+    /// Whether the request carries credentials for this authenticator at all.
+    ///
+    /// [`AuthMiddleware::optional`] lets requests without credentials through anonymously, while
+    /// requests with invalid ones are still rejected.
+    fn has_credentials(&self, req: &Request) -> bool {
+        let _ = req;
+        true
+    }
+}
+
+/// Middleware for authenticating requests.
+///
+/// The authenticated user is stored as a [`State`]. When authentication fails with
+/// `401 Unauthorized` and the authenticator provides a [`challenge`](Authenticator::challenge),
+/// the error carries that `WWW-Authenticate` header, which the router adds to the error response
+/// it renders, see [`Router::on_error`](crate::routing::Router::on_error).
+///
+/// ```
+/// use skyzen::middleware::auth::{ApiKeyAuthenticator, AuthMiddleware, StaticApiKeys};
+///
+/// let keys = StaticApiKeys::new().with_key("s3cr3t", "reader");
+/// // Anonymous requests pass without claims; `/health` is never authenticated.
+/// let auth = AuthMiddleware::new(ApiKeyAuthenticator::new(keys))
+///     .optional()
+///     .skip_paths(["/health"]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct AuthMiddleware<A: Authenticator> {
+    authenticator: A,
+    optional: bool,
+    skip_paths: Vec<String>,
+}
+
+impl<A: Authenticator> AuthMiddleware<A> {
+    /// Create a new authentication middleware.
+    pub const fn new(authenticator: A) -> Self {
+        Self {
+            authenticator,
+            optional: false,
+            skip_paths: Vec::new(),
+        }
+    }
+
+    /// Let requests without credentials through without storing a user.
+    ///
+    /// Handlers take `Option<State<User>>` to personalize when a user is present. Requests with
+    /// invalid credentials are still rejected.
+    #[must_use]
+    pub const fn optional(mut self) -> Self {
+        self.optional = true;
+        self
+    }
+
+    /// Never authenticate requests to these paths.
+    ///
+    /// Paths match exactly, or by prefix when they end with `/*` (`/public/*` matches
+    /// `/public/logo.png`).
+    #[must_use]
+    pub fn skip_paths(mut self, paths: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.skip_paths.extend(paths.into_iter().map(Into::into));
+        self
+    }
+
+    fn skips(&self, path: &str) -> bool {
+        self.skip_paths.iter().any(|skip| {
+            skip.strip_suffix('*').map_or(skip == path, |prefix| {
+                path.starts_with(prefix) || path == prefix.trim_end_matches('/')
+            })
+        })
+    }
+}
+
+impl<A> Middleware for AuthMiddleware<A>
+where
+    A: Authenticator + Send + Sync + Clone + 'static,
+    A::User: Send + Sync + Clone + 'static,
+    A::Error: HttpError,
+{
+    type Error = BoxHttpError;
+
+    async fn handle<N: Endpoint>(
+        &mut self,
+        request: &mut Request,
+        mut next: N,
+    ) -> Result<Response, MiddlewareError<N::Error, Self::Error>> {
+        if self.skips(request.uri().path())
+            || (self.optional && !self.authenticator.has_credentials(request))
+        {
+            return next
+                .respond(request)
+                .await
+                .map_err(MiddlewareError::Endpoint);
+        }
+
+        match self.authenticator.authenticate(request).await {
+            Ok(user) => {
+                request.extensions_mut().insert(State(user));
+                next.respond(request)
+                    .await
+                    .map_err(MiddlewareError::Endpoint)
+            }
+            Err(err) => Err(MiddlewareError::Middleware(
+                match self.authenticator.challenge() {
+                    Some(challenge) if err.status() == StatusCode::UNAUTHORIZED => {
+                        let mut headers = HeaderMap::new();
+                        headers.insert(WWW_AUTHENTICATE, challenge);
+                        with_headers(err, headers)
+                    }
+                    _ => Box::new(err),
+                },
+            )),
+        }
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use super::{AuthMiddleware, Authenticator};
+    use crate::{
+        header,
+        routing::{CreateRouteNode, Route, Router},
+        utils::State,
+        Body, Endpoint, Method, Request, Response, Result, StatusCode,
+    };
+    use http_kit::header::HeaderValue;
+
+    http_kit::http_error!(InvalidToken, StatusCode::UNAUTHORIZED, "Invalid token");
+
+    #[derive(Clone, Default)]
+    struct TokenAuthenticator {
+        calls: Arc<AtomicUsize>,
+    }
+
+    impl Authenticator for TokenAuthenticator {
+        type User = String;
+        type Error = InvalidToken;
+
+        async fn authenticate(&self, req: &Request) -> std::result::Result<String, InvalidToken> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            match req.headers().get(header::AUTHORIZATION) {
+                Some(value) if value == "Bearer alice" => Ok("alice".to_owned()),
+                _ => Err(InvalidToken::new()),
+            }
+        }
+
+        fn has_credentials(&self, req: &Request) -> bool {
+            req.headers().contains_key(header::AUTHORIZATION)
+        }
+
+        fn challenge(&self) -> Option<HeaderValue> {
+            Some(HeaderValue::from_static("Bearer"))
+        }
+    }
+
+    async fn articles(user: Option<State<String>>) -> Result<String> {
+        Ok(user.map_or_else(
+            || "articles for everyone".to_owned(),
+            |State(user)| format!("articles for {user}"),
+        ))
+    }
+
+    fn router(auth: AuthMiddleware<TokenAuthenticator>) -> Router {
+        Route::new(("/articles".at(articles), "/health".at(articles)))
+            .middleware(auth)
+            .build()
+    }
+
+    fn request(path: &str, token: Option<&'static str>) -> Request {
+        let mut request = Request::new(Body::empty());
+        *request.uri_mut() = path.parse().unwrap();
+        *request.method_mut() = Method::GET;
+        if let Some(token) = token {
+            request
+                .headers_mut()
+                .insert(header::AUTHORIZATION, token.parse().unwrap());
+        }
+        request
+    }
+
+    async fn body(router: &mut Router, request: &mut Request) -> (StatusCode, String) {
+        let response = router.respond(request).await.unwrap();
+        let status = response.status();
+        (
+            status,
+            response
+                .into_body()
+                .into_string()
+                .await
+                .unwrap()
+                .to_string(),
+        )
+    }
+
+    #[tokio::test]
+    async fn optional_mode_allows_anonymous_requests() {
+        let mut router = router(AuthMiddleware::new(TokenAuthenticator::default()).optional());
+
+        let (status, text) = body(&mut router, &mut request("/articles", None)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(text, "articles for everyone");
+
+        let (_, text) = body(&mut router, &mut request("/articles", Some("Bearer alice"))).await;
+        assert_eq!(text, "articles for alice");
+
+        let (status, _) = body(
+            &mut router,
+            &mut request("/articles", Some("Bearer mallory")),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn required_mode_rejects_anonymous_requests() {
+        let mut router = router(AuthMiddleware::new(TokenAuthenticator::default()));
+        let (status, _) = body(&mut router, &mut request("/articles", None)).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn skipped_paths_never_authenticate() {
+        let authenticator = TokenAuthenticator::default();
+        let calls = authenticator.calls.clone();
+        let mut router = router(AuthMiddleware::new(authenticator).skip_paths(["/health"]));
+
+        let (status, _) = body(&mut router, &mut request("/health", Some("Bearer bad"))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+
+        let (status, _) = body(&mut router, &mut request("/articles", None)).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn renders_challenges_with_the_error_renderer() {
+        let mut router = router(AuthMiddleware::new(TokenAuthenticator::default())).on_error(
+            |error, _request| {
+                let mut response = Response::new(Body::from(format!("{{\"error\":\"{error}\"}}")));
+                *response.status_mut() = error.status();
+                response
+            },
+        );
+
+        let mut req = request("/articles", Some("Bearer mallory"));
+        let response = router.respond(&mut req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(response.headers()[header::WWW_AUTHENTICATE], "Bearer");
+        let body = response.into_body().into_string().await.unwrap();
+        assert_eq!(body, r#"{"error":"Invalid token"}"#);
+    }
+
+    #[test]
+    fn matches_skip_path_prefixes() {
+        let auth = AuthMiddleware::new(TokenAuthenticator::default())
+            .skip_paths(["/metrics", "/public/*"]);
+        assert!(auth.skips("/metrics"));
+        assert!(!auth.skips("/metrics/extra"));
+        assert!(auth.skips("/public/logo.png"));
+        assert!(auth.skips("/public"));
+        assert!(!auth.skips("/publications"));
+    }
+}
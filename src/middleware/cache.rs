@@ -0,0 +1,943 @@
+//! Response caching.
+//!
+//! [`CacheMiddleware`] stores successful `GET` responses and answers repeated requests from its
+//! store without running the handler again, which suits expensive read-only endpoints such as
+//! dashboard aggregates:
+//!
+//! ```
+//! use std::time::Duration;
+//!
+//! use skyzen::{
+//!     middleware::{CacheMiddleware, MemoryStore, WithMiddleware},
+//!     routing::{CreateRouteNode, Route},
+//!     Result,
+//! };
+//!
+//! async fn stats() -> Result<&'static str> {
+//!     // Aggregate the last 30 days...
+//!     Ok("{\"signups\":42}")
+//! }
+//!
+//! let router = Route::new(("/stats".at(stats),)).build();
+//! let cache = CacheMiddleware::with_store(MemoryStore::new(256))
+//!     .ttl(Duration::from_secs(30))
+//!     .vary(["accept-language"]);
+//! let app = WithMiddleware::new(router, cache);
+//! ```
+//!
+//! Responses are keyed by method, path and query, plus the request headers named with
+//! [`vary`](CacheMiddleware::vary). Answers from the store carry an `Age` header with the seconds
+//! since they were stored and `X-Cache: HIT`; answers from the handler carry `X-Cache: MISS`.
+//!
+//! Handlers keep a response out of the cache with `Cache-Control: no-store`, `no-cache` or
+//! `private`, and override the [`ttl`](CacheMiddleware::ttl) with `max-age` or `s-maxage`.
+//! Responses that set cookies, or whose body is larger than
+//! [`max_body_size`](CacheMiddleware::max_body_size), are never stored. Responses without a known
+//! length, such as streamed bodies, are only stored with
+//! [`store_streams`](CacheMiddleware::store_streams), and server-sent events never are. A response
+//! whose `Vary` header names a request header the cache does not vary by, or is `*`, is not
+//! stored either, since its key would not tell its variants apart. Nor are responses to
+//! requests with an `Authorization` header, unless they are marked `public` or `s-maxage`, or
+//! the cache varies by `authorization`; other per-user responses should be marked `private`.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    future::{ready, Future},
+    mem,
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant, SystemTime},
+};
+
+use futures_util::{stream, StreamExt};
+use http_kit::{http_error, middleware::MiddlewareError, utils::Bytes, Endpoint, Middleware};
+
+use crate::{
+    header::{
+        HeaderMap, HeaderName, HeaderValue, AGE, AUTHORIZATION, CACHE_CONTROL, CONTENT_TYPE,
+        SET_COOKIE, VARY,
+    },
+    Body, BodyError, Method, Request, Response, StatusCode,
+};
+
+const X_CACHE: HeaderName = HeaderName::from_static("x-cache");
+const DEFAULT_TTL: Duration = Duration::from_mins(1);
+const DEFAULT_MAX_BODY_SIZE: usize = 1024 * 1024;
+const DEFAULT_MAX_ENTRIES: usize = 1024;
+
+http_error!(
+    /// The body of a response could not be read while buffering it for the cache.
+    pub CacheError,
+    StatusCode::INTERNAL_SERVER_ERROR,
+    "Failed to buffer the response for caching"
+);
+
+/// A response held by a [`CacheStore`].
+#[derive(Debug, Clone)]
+pub struct CachedResponse {
+    /// The status of the response.
+    pub status: StatusCode,
+    /// The headers of the response.
+    pub headers: HeaderMap,
+    /// The whole body of the response.
+    pub body: Bytes,
+    /// When the response was stored, from which the `Age` of hits is computed.
+    pub stored_at: SystemTime,
+}
+
+/// Where a [`CacheMiddleware`] keeps its responses.
+///
+/// Clones of the middleware share their store. Stores deal with their own failures, such as an
+/// unreachable Redis server: a lookup that fails is a miss, and an insertion that fails is
+/// dropped.
+pub trait CacheStore: Send + Sync {
+    /// The response stored under `key`, unless it expired.
+    fn get(&self, key: &str) -> impl Future<Output = Option<CachedResponse>> + Send;
+
+    /// Store `response` under `key` for `ttl`, replacing any response stored before.
+    fn insert(
+        &self,
+        key: String,
+        response: CachedResponse,
+        ttl: Duration,
+    ) -> impl Future<Output = ()> + Send;
+}
+
+/// An in-memory [`CacheStore`] evicting the least recently used response once full.
+///
+/// Clones share the same entries.
+#[derive(Debug, Clone)]
+pub struct MemoryStore {
+    entries: Arc<Mutex<Lru>>,
+}
+
+impl Default for MemoryStore {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_ENTRIES)
+    }
+}
+
+impl MemoryStore {
+    /// A store holding up to `max_entries` responses.
+    #[must_use]
+    pub fn new(max_entries: usize) -> Self {
+        Self {
+            entries: Arc::new(Mutex::new(Lru {
+                max_entries,
+                ..Lru::default()
+            })),
+        }
+    }
+
+    /// The number of stored responses, including expired ones not yet evicted.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    /// Whether no response is stored.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Lru> {
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl CacheStore for MemoryStore {
+    fn get(&self, key: &str) -> impl Future<Output = Option<CachedResponse>> + Send {
+        ready(self.lock().get(key))
+    }
+
+    fn insert(
+        &self,
+        key: String,
+        response: CachedResponse,
+        ttl: Duration,
+    ) -> impl Future<Output = ()> + Send {
+        self.lock().insert(key, response, ttl);
+        ready(())
+    }
+}
+
+#[derive(Debug, Default)]
+struct Lru {
+    max_entries: usize,
+    entries: HashMap<String, Entry>,
+    /// Keys by their last use, least recent first.
+    recency: BTreeMap<u64, String>,
+    clock: u64,
+}
+
+#[derive(Debug)]
+struct Entry {
+    response: CachedResponse,
+    /// `None` for lifetimes too long to represent.
+    expires: Option<Instant>,
+    used: u64,
+}
+
+impl Lru {
+    fn get(&mut self, key: &str) -> Option<CachedResponse> {
+        let entry = self.entries.get_mut(key)?;
+        self.recency.remove(&entry.used);
+        if entry
+            .expires
+            .is_some_and(|expires| expires <= Instant::now())
+        {
+            self.entries.remove(key);
+            return None;
+        }
+        self.clock += 1;
+        entry.used = self.clock;
+        self.recency.insert(self.clock, key.to_owned());
+        Some(entry.response.clone())
+    }
+
+    fn insert(&mut self, key: String, response: CachedResponse, ttl: Duration) {
+        if self.max_entries == 0 {
+            return;
+        }
+        self.clock += 1;
+        let entry = Entry {
+            response,
+            expires: Instant::now().checked_add(ttl),
+            used: self.clock,
+        };
+        if let Some(previous) = self.entries.insert(key.clone(), entry) {
+            self.recency.remove(&previous.used);
+        }
+        self.recency.insert(self.clock, key);
+        while self.entries.len() > self.max_entries {
+            let Some((_, oldest)) = self.recency.pop_first() else {
+                break;
+            };
+            self.entries.remove(&oldest);
+        }
+    }
+}
+
+type KeyFn = dyn Fn(&Request) -> Option<String> + Send + Sync;
+
+/// Caches successful `GET` responses, see the [module docs](self).
+#[derive(Clone)]
+pub struct CacheMiddleware<S = MemoryStore> {
+    store: S,
+    ttl: Duration,
+    max_body_size: usize,
+    store_streams: bool,
+    key: Arc<KeyFn>,
+    vary: Vec<HeaderName>,
+}
+
+impl<S> fmt::Debug for CacheMiddleware<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CacheMiddleware")
+            .field("ttl", &self.ttl)
+            .field("max_body_size", &self.max_body_size)
+            .field("store_streams", &self.store_streams)
+            .field("vary", &self.vary)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for CacheMiddleware {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CacheMiddleware {
+    /// Caches up to 1024 responses in memory for 60 seconds each.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_store(MemoryStore::default())
+    }
+}
+
+impl<S: CacheStore> CacheMiddleware<S> {
+    /// Caches responses in `store` for 60 seconds each.
+    pub fn with_store(store: S) -> Self {
+        Self {
+            store,
+            ttl: DEFAULT_TTL,
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+            store_streams: false,
+            key: Arc::new(|request: &Request| {
+                let target = request
+                    .uri()
+                    .path_and_query()
+                    .map_or("/", |target| target.as_str());
+                Some(format!("{} {target}", request.method()))
+            }),
+            vary: Vec::new(),
+        }
+    }
+
+    /// How long responses are kept when they do not set `max-age` or `s-maxage`.
+    #[must_use]
+    pub const fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Responses with a larger body, in bytes, are passed through without being stored.
+    ///
+    /// Bodies are buffered in full before being stored; the default is 1 MiB.
+    #[must_use]
+    pub const fn max_body_size(mut self, max_body_size: usize) -> Self {
+        self.max_body_size = max_body_size;
+        self
+    }
+
+    /// Whether responses whose length is not known up front are stored, off by default.
+    ///
+    /// Such bodies are read until they end or exceed [`max_body_size`](Self::max_body_size),
+    /// which holds up long-polling responses; `text/event-stream` responses are never buffered.
+    #[must_use]
+    pub const fn store_streams(mut self, store_streams: bool) -> Self {
+        self.store_streams = store_streams;
+        self
+    }
+
+    /// Replaces how requests are keyed, `"GET /path?query"` by default.
+    ///
+    /// Requests for which `key` returns `None` bypass the cache. The values of the
+    /// [`vary`](Self::vary) headers are appended to the key.
+    #[must_use]
+    pub fn key(mut self, key: impl Fn(&Request) -> Option<String> + Send + Sync + 'static) -> Self {
+        self.key = Arc::new(key);
+        self
+    }
+
+    /// Keys responses by the values of the request headers `names` as well, such as
+    /// `accept-language` for localized responses.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a name is not a valid header name.
+    #[must_use]
+    pub fn vary<I>(mut self, names: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        self.vary.extend(names.into_iter().map(|name| {
+            HeaderName::try_from(name.as_ref())
+                .unwrap_or_else(|_| panic!("invalid header name `{}`", name.as_ref()))
+        }));
+        self
+    }
+
+    fn cache_key(&self, request: &Request) -> Option<String> {
+        let mut key = (self.key)(request)?;
+        for name in &self.vary {
+            key.push('\n');
+            key.push_str(name.as_str());
+            key.push(':');
+            for value in &request.headers().get_all(name) {
+                key.push_str(&String::from_utf8_lossy(value.as_bytes()));
+                key.push(',');
+            }
+        }
+        Some(key)
+    }
+
+    /// How long `response` may be stored, or `None` if it must not be.
+    fn storable_for(&self, request: &Request, response: &Response) -> Option<Duration> {
+        let status = response.status();
+        if !status.is_success() || status == StatusCode::PARTIAL_CONTENT {
+            return None;
+        }
+        if response.headers().contains_key(SET_COOKIE) {
+            return None;
+        }
+        if response.body().len().is_none()
+            && (!self.store_streams || is_event_stream(response.headers()))
+        {
+            return None;
+        }
+        if !self.covers_vary(response.headers()) {
+            return None;
+        }
+
+        let mut max_age = None;
+        let mut shared_max_age = None;
+        let mut public = false;
+        let directives = response
+            .headers()
+            .get_all(CACHE_CONTROL)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','));
+        for directive in directives {
+            let (name, value) = directive
+                .split_once('=')
+                .map_or((directive, None), |(name, value)| (name, Some(value)));
+            let seconds = || value?.trim().trim_matches('"').parse().ok();
+            match name.trim().to_ascii_lowercase().as_str() {
+                "no-store" | "no-cache" | "private" => return None,
+                "public" => public = true,
+                "max-age" => max_age = seconds(),
+                "s-maxage" => shared_max_age = seconds(),
+                _ => {}
+            }
+        }
+
+        let authorized =
+            request.headers().contains_key(AUTHORIZATION) && !self.vary.contains(&AUTHORIZATION);
+        if authorized && !public && shared_max_age.is_none() {
+            return None;
+        }
+        let ttl = shared_max_age
+            .or(max_age)
+            .map_or(self.ttl, Duration::from_secs);
+        (!ttl.is_zero()).then_some(ttl)
+    }
+
+    /// Whether every request header named by the `Vary` header of a response is part of the key.
+    fn covers_vary(&self, headers: &HeaderMap) -> bool {
+        headers
+            .get_all(VARY)
+            .iter()
+            .flat_map(|value| value.to_str().unwrap_or("*").split(','))
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .all(|name| HeaderName::try_from(name).is_ok_and(|name| self.vary.contains(&name)))
+    }
+}
+
+fn is_event_stream(headers: &HeaderMap) -> bool {
+    headers.get(CONTENT_TYPE).is_some_and(|content_type| {
+        content_type
+            .to_str()
+            .unwrap_or_default()
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .eq_ignore_ascii_case("text/event-stream")
+    })
+}
+
+impl<S: CacheStore> Middleware for CacheMiddleware<S> {
+    type Error = CacheError;
+
+    async fn handle<N: Endpoint>(
+        &mut self,
+        request: &mut Request,
+        mut next: N,
+    ) -> Result<Response, MiddlewareError<N::Error, Self::Error>> {
+        let key = if request.method() == Method::GET {
+            self.cache_key(request)
+        } else {
+            None
+        };
+        let Some(key) = key else {
+            return next
+                .respond(request)
+                .await
+                .map_err(MiddlewareError::Endpoint);
+        };
+
+        if let Some(cached) = self.store.get(&key).await {
+            return Ok(hit(cached));
+        }
+
+        let mut response = next
+            .respond(request)
+            .await
+            .map_err(MiddlewareError::Endpoint)?;
+        if let Some(ttl) = self.storable_for(request, &response) {
+            let body = buffer_body(&mut response, self.max_body_size)
+                .await
+                .map_err(|_| MiddlewareError::Middleware(CacheError::new()))?;
+            if let Some(body) = body {
+                let cached = CachedResponse {
+                    status: response.status(),
+                    headers: response.headers().clone(),
+                    body,
+                    stored_at: SystemTime::now(),
+                };
+                self.store.insert(key, cached, ttl).await;
+            }
+        }
+        response
+            .headers_mut()
+            .insert(X_CACHE, HeaderValue::from_static("MISS"));
+        Ok(response)
+    }
+}
+
+fn hit(cached: CachedResponse) -> Response {
+    let age = SystemTime::now()
+        .duration_since(cached.stored_at)
+        .unwrap_or_default()
+        .as_secs();
+    let mut response = Response::new(Body::from_bytes(cached.body));
+    *response.status_mut() = cached.status;
+    *response.headers_mut() = cached.headers;
+    let headers = response.headers_mut();
+    headers.insert(AGE, HeaderValue::from(age));
+    headers.insert(X_CACHE, HeaderValue::from_static("HIT"));
+    response
+}
+
+/// Buffer the body of `response` if it has at most `limit` bytes.
+///
+/// Larger bodies are put back as they were, with the part read so far in front.
+async fn buffer_body(response: &mut Response, limit: usize) -> Result<Option<Bytes>, BodyError> {
+    if response.body().len().is_some_and(|len| len > limit) {
+        return Ok(None);
+    }
+    let mut body = mem::take(response.body_mut());
+    let mut buffered = Vec::new();
+    while let Some(chunk) = body.next().await {
+        buffered.extend_from_slice(&chunk?);
+        if buffered.len() > limit {
+            let read = stream::once(ready(Ok::<_, BodyError>(Bytes::from(buffered))));
+            *response.body_mut() = Body::from_stream(read.chain(body));
+            return Ok(None);
+        }
+    }
+    let buffered = Bytes::from(buffered);
+    *response.body_mut() = Body::from_bytes(buffered.clone());
+    Ok(Some(buffered))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        convert::Infallible,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::{Duration, SystemTime},
+    };
+
+    use http_kit::{utils::Bytes, Endpoint, Middleware};
+
+    use super::{CacheMiddleware, CacheStore, CachedResponse, MemoryStore};
+    use crate::{
+        header::{
+            HeaderMap, HeaderValue, ACCEPT_LANGUAGE, AGE, AUTHORIZATION, CACHE_CONTROL,
+            CONTENT_TYPE, SET_COOKIE, VARY,
+        },
+        Body, Method, Request, Response, StatusCode,
+    };
+
+    /// Answers with the request target, counting its calls; `respond` shapes the response.
+    #[derive(Clone)]
+    struct Origin {
+        calls: Arc<AtomicUsize>,
+        respond: fn(&Request, &mut Response),
+    }
+
+    impl Origin {
+        fn new(respond: fn(&Request, &mut Response)) -> Self {
+            Self {
+                calls: Arc::default(),
+                respond,
+            }
+        }
+
+        fn calls(&self) -> usize {
+            self.calls.load(Ordering::SeqCst)
+        }
+    }
+
+    impl Endpoint for Origin {
+        type Error = Infallible;
+
+        async fn respond(&mut self, request: &mut Request) -> Result<Response, Self::Error> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            let mut response = Response::new(Body::from(request.uri().to_string()));
+            (self.respond)(request, &mut response);
+            Ok(response)
+        }
+    }
+
+    fn request(uri: &str) -> Request {
+        let mut request = Request::new(Body::empty());
+        *request.uri_mut() = uri.parse().unwrap();
+        request
+    }
+
+    /// The `X-Cache` header and body of the response to `request`.
+    async fn fetch(
+        cache: &mut CacheMiddleware<impl CacheStore>,
+        origin: &mut Origin,
+        mut request: Request,
+    ) -> (Option<String>, String) {
+        let response = cache.handle(&mut request, &mut *origin).await.unwrap();
+        let x_cache = response
+            .headers()
+            .get("x-cache")
+            .map(|value| value.to_str().unwrap().to_owned());
+        let body = response.into_body().into_string().await.unwrap();
+        (x_cache, body.to_string())
+    }
+
+    fn hit(body: &str) -> (Option<String>, String) {
+        (Some("HIT".to_owned()), body.to_owned())
+    }
+
+    fn miss(body: &str) -> (Option<String>, String) {
+        (Some("MISS".to_owned()), body.to_owned())
+    }
+
+    #[tokio::test]
+    async fn serves_repeated_gets_from_the_store() {
+        let mut cache = CacheMiddleware::new();
+        let mut origin = Origin::new(|_, _| {});
+
+        assert_eq!(
+            fetch(&mut cache, &mut origin, request("/a?x=1")).await,
+            miss("/a?x=1")
+        );
+        let mut again = request("/a?x=1");
+        let response = cache.handle(&mut again, &mut origin).await.unwrap();
+        assert_eq!(response.headers().get(AGE).unwrap(), "0");
+        assert_eq!(
+            response
+                .into_body()
+                .into_string()
+                .await
+                .unwrap()
+                .to_string(),
+            "/a?x=1"
+        );
+        assert_eq!(
+            fetch(&mut cache, &mut origin, request("/a?x=2")).await,
+            miss("/a?x=2")
+        );
+        assert_eq!(origin.calls(), 2);
+
+        let mut post = request("/a?x=1");
+        *post.method_mut() = Method::POST;
+        assert_eq!(
+            fetch(&mut cache, &mut origin, post).await,
+            (None, "/a?x=1".to_owned())
+        );
+        assert_eq!(origin.calls(), 3);
+    }
+
+    #[tokio::test]
+    async fn honors_cache_control_of_responses() {
+        let mut cache = CacheMiddleware::new().ttl(Duration::ZERO);
+        let mut origin = Origin::new(|request, response| {
+            let headers = response.headers_mut();
+            match request.uri().path() {
+                "/no-store" => headers.insert(CACHE_CONTROL, HeaderValue::from_static("no-store")),
+                "/private" => headers.insert(
+                    CACHE_CONTROL,
+                    HeaderValue::from_static("max-age=60, Private"),
+                ),
+                "/cookie" => headers.insert(SET_COOKIE, HeaderValue::from_static("session=1")),
+                "/max-age" => headers.insert(CACHE_CONTROL, HeaderValue::from_static("max-age=60")),
+                _ => None,
+            };
+        });
+
+        for path in ["/no-store", "/private", "/cookie", "/default"] {
+            assert_eq!(
+                fetch(&mut cache, &mut origin, request(path)).await,
+                miss(path)
+            );
+            assert_eq!(
+                fetch(&mut cache, &mut origin, request(path)).await,
+                miss(path)
+            );
+        }
+        assert_eq!(origin.calls(), 8);
+
+        // `max-age` overrides the zero TTL of the middleware.
+        assert_eq!(
+            fetch(&mut cache, &mut origin, request("/max-age")).await,
+            miss("/max-age")
+        );
+        assert_eq!(
+            fetch(&mut cache, &mut origin, request("/max-age")).await,
+            hit("/max-age")
+        );
+    }
+
+    #[tokio::test]
+    async fn only_shares_authorized_responses_marked_public() {
+        let authorized = |path: &str, token: &'static str| {
+            let mut request = request(path);
+            request
+                .headers_mut()
+                .insert(AUTHORIZATION, HeaderValue::from_static(token));
+            request
+        };
+        let mut cache = CacheMiddleware::new();
+        let mut origin = Origin::new(|request, response| {
+            if request.uri().path() == "/public" {
+                response
+                    .headers_mut()
+                    .insert(CACHE_CONTROL, HeaderValue::from_static("public"));
+            }
+        });
+
+        assert_eq!(
+            fetch(&mut cache, &mut origin, authorized("/me", "a")).await,
+            miss("/me")
+        );
+        assert_eq!(
+            fetch(&mut cache, &mut origin, authorized("/me", "b")).await,
+            miss("/me")
+        );
+        assert_eq!(
+            fetch(&mut cache, &mut origin, authorized("/public", "a")).await,
+            miss("/public")
+        );
+        assert_eq!(
+            fetch(&mut cache, &mut origin, authorized("/public", "b")).await,
+            hit("/public")
+        );
+
+        let mut cache = CacheMiddleware::new().vary(["authorization"]);
+        assert_eq!(
+            fetch(&mut cache, &mut origin, authorized("/me", "a")).await,
+            miss("/me")
+        );
+        assert_eq!(
+            fetch(&mut cache, &mut origin, authorized("/me", "a")).await,
+            hit("/me")
+        );
+        assert_eq!(
+            fetch(&mut cache, &mut origin, authorized("/me", "b")).await,
+            miss("/me")
+        );
+    }
+
+    #[tokio::test]
+    async fn varies_by_selected_headers() {
+        let language = |value: &'static str| {
+            let mut request = request("/greeting");
+            request
+                .headers_mut()
+                .insert(ACCEPT_LANGUAGE, HeaderValue::from_static(value));
+            request
+        };
+        let mut cache = CacheMiddleware::new().vary(["Accept-Language"]);
+        let mut origin = Origin::new(|_, _| {});
+
+        assert_eq!(
+            fetch(&mut cache, &mut origin, language("en")).await,
+            miss("/greeting")
+        );
+        assert_eq!(
+            fetch(&mut cache, &mut origin, language("fr")).await,
+            miss("/greeting")
+        );
+        assert_eq!(
+            fetch(&mut cache, &mut origin, language("en")).await,
+            hit("/greeting")
+        );
+        assert_eq!(
+            fetch(&mut cache, &mut origin, request("/greeting")).await,
+            miss("/greeting")
+        );
+
+        let mut cache = CacheMiddleware::new().key(|request| {
+            (request.uri().path() != "/live").then(|| request.uri().path().to_owned())
+        });
+        assert_eq!(
+            fetch(&mut cache, &mut origin, request("/live")).await,
+            (None, "/live".to_owned())
+        );
+        assert_eq!(
+            fetch(&mut cache, &mut origin, request("/live")).await,
+            (None, "/live".to_owned())
+        );
+        assert_eq!(
+            fetch(&mut cache, &mut origin, request("/page?a")).await,
+            miss("/page?a")
+        );
+        assert_eq!(
+            fetch(&mut cache, &mut origin, request("/page?b")).await,
+            hit("/page?a")
+        );
+    }
+
+    #[tokio::test]
+    async fn passes_large_bodies_through() {
+        let mut cache = CacheMiddleware::new().max_body_size(8).store_streams(true);
+        let mut origin = Origin::new(|request, response| {
+            if request.uri().path() == "/stream" {
+                let chunks = ["/stream", "ed/", "body"].map(Ok::<_, Infallible>);
+                *response.body_mut() = Body::from_stream(futures_util::stream::iter(chunks));
+            }
+        });
+
+        for path in ["/a-long-path", "/stream"] {
+            let body = if path == "/stream" {
+                "/streamed/body"
+            } else {
+                path
+            };
+            assert_eq!(
+                fetch(&mut cache, &mut origin, request(path)).await,
+                miss(body)
+            );
+            assert_eq!(
+                fetch(&mut cache, &mut origin, request(path)).await,
+                miss(body)
+            );
+        }
+        assert_eq!(
+            fetch(&mut cache, &mut origin, request("/short")).await,
+            miss("/short")
+        );
+        assert_eq!(
+            fetch(&mut cache, &mut origin, request("/short")).await,
+            hit("/short")
+        );
+    }
+
+    #[tokio::test]
+    async fn stores_streams_only_when_asked() {
+        let mut origin = Origin::new(|request, response| {
+            let chunks = [request.uri().path().to_owned()].map(Ok::<_, Infallible>);
+            *response.body_mut() = Body::from_stream(futures_util::stream::iter(chunks));
+            if request.uri().path() == "/events" {
+                response
+                    .headers_mut()
+                    .insert(CONTENT_TYPE, HeaderValue::from_static("text/event-stream"));
+            }
+        });
+
+        let mut cache = CacheMiddleware::new();
+        for _ in 0..2 {
+            assert_eq!(
+                fetch(&mut cache, &mut origin, request("/poll")).await,
+                miss("/poll")
+            );
+        }
+
+        let mut cache = CacheMiddleware::new().store_streams(true);
+        assert_eq!(
+            fetch(&mut cache, &mut origin, request("/poll")).await,
+            miss("/poll")
+        );
+        assert_eq!(
+            fetch(&mut cache, &mut origin, request("/poll")).await,
+            hit("/poll")
+        );
+        for _ in 0..2 {
+            assert_eq!(
+                fetch(&mut cache, &mut origin, request("/events")).await,
+                miss("/events")
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn skips_responses_varying_by_unkeyed_headers() {
+        let mut origin = Origin::new(|request, response| {
+            let vary = if request.uri().path() == "/any" {
+                "*"
+            } else {
+                "Accept-Encoding"
+            };
+            response
+                .headers_mut()
+                .insert(VARY, HeaderValue::from_static(vary));
+        });
+
+        let mut cache = CacheMiddleware::new();
+        for _ in 0..2 {
+            assert_eq!(
+                fetch(&mut cache, &mut origin, request("/page")).await,
+                miss("/page")
+            );
+        }
+
+        let mut cache = CacheMiddleware::new().vary(["accept-encoding"]);
+        assert_eq!(
+            fetch(&mut cache, &mut origin, request("/page")).await,
+            miss("/page")
+        );
+        assert_eq!(
+            fetch(&mut cache, &mut origin, request("/page")).await,
+            hit("/page")
+        );
+        for _ in 0..2 {
+            assert_eq!(
+                fetch(&mut cache, &mut origin, request("/any")).await,
+                miss("/any")
+            );
+        }
+    }
+
+    fn cached(body: &'static str) -> CachedResponse {
+        CachedResponse {
+            status: StatusCode::OK,
+            headers: HeaderMap::new(),
+            body: Bytes::from_static(body.as_bytes()),
+            stored_at: SystemTime::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn evicts_least_recently_used_and_expired_entries() {
+        let store = MemoryStore::new(2);
+        let minute = Duration::from_mins(1);
+        store.insert("a".to_owned(), cached("a"), minute).await;
+        store.insert("b".to_owned(), cached("b"), minute).await;
+        assert!(store.get("a").await.is_some());
+        store.insert("c".to_owned(), cached("c"), minute).await;
+        assert_eq!(store.len(), 2);
+        assert!(store.get("b").await.is_none());
+        assert_eq!(store.get("a").await.unwrap().body, "a");
+        assert_eq!(store.get("c").await.unwrap().body, "c");
+
+        store
+            .insert("a".to_owned(), cached("a2"), Duration::from_millis(20))
+            .await;
+        assert_eq!(store.get("a").await.unwrap().body, "a2");
+        tokio::time::sleep(Duration::from_millis(40)).await;
+        assert!(store.get("a").await.is_none());
+        assert_eq!(store.len(), 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn serves_concurrent_hits_under_a_small_capacity() {
+        let store = MemoryStore::new(2);
+        let cache = CacheMiddleware::with_store(store.clone());
+        let origin = Origin::new(|_, _| {});
+
+        let tasks = (0..32)
+            .map(|task| {
+                let mut cache = cache.clone();
+                let mut origin = origin.clone();
+                tokio::spawn(async move {
+                    for round in 0..16 {
+                        let path = format!("/{}", (task + round) % 3);
+                        let (_, body) = fetch(&mut cache, &mut origin, request(&path)).await;
+                        assert_eq!(body, path);
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        // Cycling three paths through two entries may evict every entry before it is hit, so
+        // only check that the store is still bounded and serving.
+        assert!(store.len() <= 2);
+        let mut cache = cache.clone();
+        let mut origin = origin.clone();
+        fetch(&mut cache, &mut origin, request("/0")).await;
+        assert_eq!(
+            fetch(&mut cache, &mut origin, request("/0")).await,
+            hit("/0")
+        );
+    }
+}
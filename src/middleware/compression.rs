@@ -2,8 +2,9 @@
 //!
 //! This middleware inspects the `Accept-Encoding` header and compresses responses using
 //! `gzip` or `deflate` when the client signals support for those algorithms. Compression is
-//! automatically skipped for responses that are already encoded, are too small, or when the
-//! negotiated encoding would not improve the payload size.
+//! automatically skipped for responses that are already encoded, are too small, have a content
+//! type that does not compress well (such as images and video), or when the negotiated encoding
+//! would not improve the payload size.
 
 use std::{cmp::Ordering, io::Write, mem};
 
@@ -12,16 +13,14 @@ use flate2::{
     Compression as FlateCompression,
 };
 use http::{
-    header::{ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH, TRANSFER_ENCODING, VARY},
-    HeaderMap, HeaderValue, Method, StatusCode,
-};
-use http_kit::{
-    http_error,
-    middleware::MiddlewareError,
-    Body, Middleware, Request, Response,
+    header::{ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, TRANSFER_ENCODING},
+    HeaderValue, Method, StatusCode,
 };
+use http_kit::{http_error, middleware::MiddlewareError, Body, Middleware, Request, Response};
 use smallvec::{smallvec, SmallVec};
 
+use crate::utils::{header_util, negotiation};
+
 type EncodingList = SmallVec<[CompressionEncoding; 3]>;
 
 http_error!(
@@ -32,7 +31,7 @@ http_error!(
 );
 
 /// Middleware that conditionally compresses outgoing responses.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct CompressionMiddleware {
     config: CompressionConfig,
 }
@@ -47,7 +46,7 @@ impl CompressionMiddleware {
 
     /// Updates the minimum response size that qualifies for compression.
     #[must_use]
-    pub fn minimum_size(mut self, minimum_size: usize) -> Self {
+    pub const fn minimum_size(mut self, minimum_size: usize) -> Self {
         self.config.minimum_size = minimum_size;
         self
     }
@@ -65,15 +64,46 @@ impl CompressionMiddleware {
 
     /// Sets the compression level that will be used by the selected encoder.
     #[must_use]
-    pub fn level(mut self, level: CompressionLevel) -> Self {
+    pub const fn level(mut self, level: CompressionLevel) -> Self {
         self.config.level = level;
         self
     }
 
+    /// Replaces the content types that are always compressed, such as `application/json` or
+    /// `text/*`.
+    ///
+    /// These take precedence over [`skip_types`](Self::skip_types), so the default list keeps
+    /// `image/svg+xml` compressed although `image/*` is skipped. The defaults are `text/*`,
+    /// `application/json`, `application/javascript` and `image/svg+xml`.
+    #[must_use]
+    pub fn compress_types<I, S>(mut self, content_types: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.config.compress_types = content_types.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Replaces the content types that are never compressed, such as `image/*` or `*/*`.
+    ///
+    /// The defaults are images, audio, video and common archive formats, which are already
+    /// compressed. Responses whose type is on neither list, or that have no `Content-Type`, are
+    /// compressed; skip `*/*` to only compress the [`compress_types`](Self::compress_types).
+    #[must_use]
+    pub fn skip_types<I, S>(mut self, content_types: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.config.skip_types = content_types.into_iter().map(Into::into).collect();
+        self
+    }
+
     fn negotiate_encoding(&self, request: &Request) -> Option<CompressionEncoding> {
         let mut best: Option<Candidate> = None;
         let mut position = 0usize;
-        for value in request.headers().get_all(ACCEPT_ENCODING).iter() {
+        for value in &request.headers().get_all(ACCEPT_ENCODING) {
             if let Ok(raw) = value.to_str() {
                 parse_header_value(raw, &self.config.encodings, &mut position, &mut best);
             }
@@ -96,6 +126,24 @@ impl CompressionMiddleware {
             return false;
         }
 
+        if let Some(content_type) = response.headers().get(CONTENT_TYPE) {
+            let essence = content_type
+                .to_str()
+                .unwrap_or_default()
+                .split(';')
+                .next()
+                .unwrap_or_default()
+                .trim();
+            let listed = |types: &[String]| {
+                types
+                    .iter()
+                    .any(|pattern| content_type_matches(pattern, essence))
+            };
+            if !listed(&self.config.compress_types) && listed(&self.config.skip_types) {
+                return false;
+            }
+        }
+
         !matches!(response.body().is_empty(), Some(true))
     }
 
@@ -104,8 +152,11 @@ impl CompressionMiddleware {
         response: &mut Response,
         encoding: CompressionEncoding,
     ) -> Result<(), CompressionError> {
-        let mut body = mem::take(response.body_mut());
-        let original = body.into_bytes().await.map_err(|_| CompressionError::new())?;
+        let body = mem::take(response.body_mut());
+        let original = body
+            .into_bytes()
+            .await
+            .map_err(|_| CompressionError::new())?;
 
         if original.len() < self.config.minimum_size {
             set_content_length(response, original.len())?;
@@ -128,25 +179,17 @@ impl CompressionMiddleware {
         response
             .headers_mut()
             .insert(CONTENT_ENCODING, encoding.header_value());
-        ensure_vary_accept_encoding(response.headers_mut());
+        header_util::append_vary(response.headers_mut(), "Accept-Encoding");
         Ok(())
     }
 }
 
-impl Default for CompressionMiddleware {
-    fn default() -> Self {
-        Self {
-            config: CompressionConfig::default(),
-        }
-    }
-}
-
 impl Middleware for CompressionMiddleware {
     type Error = CompressionError;
     async fn handle<N: http_kit::Endpoint>(
         &mut self,
         request: &mut Request,
-        next: N,
+        mut next: N,
     ) -> Result<Response, MiddlewareError<N::Error, Self::Error>> {
         let mut response = next
             .respond(request)
@@ -170,18 +213,55 @@ struct CompressionConfig {
     minimum_size: usize,
     encodings: EncodingList,
     level: CompressionLevel,
+    compress_types: Vec<String>,
+    skip_types: Vec<String>,
 }
 
 impl Default for CompressionConfig {
     fn default() -> Self {
+        let owned = |types: &[&str]| types.iter().map(|&ty| ty.to_owned()).collect();
         Self {
             minimum_size: 512,
             encodings: default_encodings(),
             level: CompressionLevel::default(),
+            compress_types: owned(&[
+                "text/*",
+                "application/json",
+                "application/javascript",
+                "image/svg+xml",
+            ]),
+            skip_types: owned(&[
+                "image/*",
+                "audio/*",
+                "video/*",
+                "application/zip",
+                "application/gzip",
+                "application/x-gzip",
+                "application/x-bzip2",
+                "application/x-xz",
+                "application/x-7z-compressed",
+                "application/x-rar-compressed",
+                "application/zstd",
+            ]),
         }
     }
 }
 
+/// Whether the content type `essence` matches `pattern`: an exact type, `type/*` or `*/*`.
+fn content_type_matches(pattern: &str, essence: &str) -> bool {
+    if pattern == "*/*" {
+        return true;
+    }
+    pattern.strip_suffix("/*").map_or_else(
+        || pattern.eq_ignore_ascii_case(essence),
+        |prefix| {
+            essence
+                .split_once('/')
+                .is_some_and(|(ty, _)| ty.eq_ignore_ascii_case(prefix))
+        },
+    )
+}
+
 fn default_encodings() -> EncodingList {
     smallvec![CompressionEncoding::Gzip, CompressionEncoding::Deflate]
 }
@@ -200,13 +280,7 @@ fn parse_header_value(
     position: &mut usize,
     best: &mut Option<Candidate>,
 ) {
-    for part in value.split(',') {
-        let trimmed = part.trim();
-        if trimmed.is_empty() {
-            continue;
-        }
-
-        let (token, quality) = parse_part(trimmed);
+    for (token, quality) in negotiation::entries(value) {
         if quality == 0.0 {
             continue;
         }
@@ -214,7 +288,7 @@ fn parse_header_value(
         let current_position = *position;
         *position += 1;
 
-        match token {
+        match ParsedEncoding::from_token(token) {
             ParsedEncoding::Specific(encoding) => {
                 if let Some(idx) = supported
                     .iter()
@@ -254,15 +328,14 @@ fn consider_candidate(best: &mut Option<Candidate>, candidate: Candidate) {
         None => true,
         Some(existing) => match candidate.quality.partial_cmp(&existing.quality) {
             Some(Ordering::Greater) => true,
-            Some(Ordering::Less) => false,
             Some(Ordering::Equal) => {
-                if candidate.position != existing.position {
-                    candidate.position < existing.position
-                } else {
+                if candidate.position == existing.position {
                     candidate.supported_order < existing.supported_order
+                } else {
+                    candidate.position < existing.position
                 }
             }
-            None => false,
+            Some(Ordering::Less) | None => false,
         },
     };
 
@@ -271,46 +344,6 @@ fn consider_candidate(best: &mut Option<Candidate>, candidate: Candidate) {
     }
 }
 
-fn parse_part(part: &str) -> (ParsedEncoding, f32) {
-    let mut sections = part.split(';');
-    let encoding = sections.next().unwrap_or_default().trim();
-    let mut quality = 1.0_f32;
-
-    for parameter in sections {
-        let parameter = parameter.trim();
-        if parameter.is_empty() {
-            continue;
-        }
-
-        if let Some((key, value)) = parameter.split_once('=') {
-            if key.trim().eq_ignore_ascii_case("q") {
-                if let Some(parsed) = parse_quality(value) {
-                    quality = parsed;
-                } else {
-                    quality = 0.0;
-                }
-                break;
-            }
-        }
-    }
-
-    (ParsedEncoding::from_token(encoding), quality)
-}
-
-fn parse_quality(raw: &str) -> Option<f32> {
-    let trimmed = raw.trim();
-    if trimmed.is_empty() {
-        return None;
-    }
-
-    let value = trimmed.parse::<f32>().ok()?;
-    if !(0.0..=1.0).contains(&value) {
-        return None;
-    }
-
-    Some(value)
-}
-
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum ParsedEncoding {
     Specific(CompressionEncoding),
@@ -333,43 +366,13 @@ impl ParsedEncoding {
 }
 
 fn set_content_length(response: &mut Response, len: usize) -> Result<(), CompressionError> {
-    let len_header = HeaderValue::from_str(&len.to_string())
-        .map_err(|_| CompressionError::new())?;
+    let len_header =
+        HeaderValue::from_str(&len.to_string()).map_err(|_| CompressionError::new())?;
     response.headers_mut().insert(CONTENT_LENGTH, len_header);
     response.headers_mut().remove(TRANSFER_ENCODING);
     Ok(())
 }
 
-fn ensure_vary_accept_encoding(headers: &mut HeaderMap) {
-    match headers.get_mut(VARY) {
-        Some(value) => {
-            if let Ok(existing) = value.to_str() {
-                if existing
-                    .split(',')
-                    .any(|segment| segment.trim().eq_ignore_ascii_case("accept-encoding"))
-                {
-                    return;
-                }
-
-                let mut combined = existing.trim().to_owned();
-                if !combined.is_empty() {
-                    combined.push_str(", ");
-                }
-                combined.push_str("Accept-Encoding");
-                if let Ok(updated) = HeaderValue::from_str(&combined) {
-                    *value = updated;
-                }
-                return;
-            }
-
-            *value = HeaderValue::from_static("Accept-Encoding");
-        }
-        None => {
-            headers.insert(VARY, HeaderValue::from_static("Accept-Encoding"));
-        }
-    }
-}
-
 /// Compression algorithms supported by [`CompressionMiddleware`].
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CompressionEncoding {
@@ -380,7 +383,7 @@ pub enum CompressionEncoding {
 }
 
 impl CompressionEncoding {
-    fn header_value(self) -> HeaderValue {
+    const fn header_value(self) -> HeaderValue {
         match self {
             Self::Gzip => HeaderValue::from_static("gzip"),
             Self::Deflate => HeaderValue::from_static("deflate"),
@@ -405,7 +408,7 @@ impl CompressionEncoding {
 }
 
 /// Compression strength used by [`CompressionMiddleware`].
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub enum CompressionLevel {
     /// Fast compression optimized for latency.
     Fast,
@@ -414,6 +417,7 @@ pub enum CompressionLevel {
     /// Custom compression level (0-9).
     Precise(u32),
     /// Uses the zlib default.
+    #[default]
     Default,
 }
 
@@ -428,23 +432,21 @@ impl CompressionLevel {
     }
 }
 
-impl Default for CompressionLevel {
-    fn default() -> Self {
-        Self::Default
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
     use flate2::read::{GzDecoder, ZlibDecoder};
-    use http::{header::CONTENT_ENCODING, HeaderValue};
+    use http::{
+        header::{CONTENT_ENCODING, VARY},
+        HeaderValue,
+    };
     use http_kit::Endpoint;
     use std::{convert::Infallible, io::Read};
 
     struct StaticEndpoint {
         payload: String,
         vary: Option<HeaderValue>,
+        content_type: Option<&'static str>,
     }
 
     impl StaticEndpoint {
@@ -452,6 +454,7 @@ mod tests {
             Self {
                 payload: payload.to_owned(),
                 vary: None,
+                content_type: None,
             }
         }
 
@@ -460,6 +463,11 @@ mod tests {
             self
         }
 
+        const fn with_content_type(mut self, content_type: &'static str) -> Self {
+            self.content_type = Some(content_type);
+            self
+        }
+
         fn response_body(&self) -> Body {
             Body::from_bytes(self.payload.clone())
         }
@@ -476,6 +484,11 @@ mod tests {
             if let Some(value) = self.vary.clone() {
                 response.headers_mut().insert(VARY, value);
             }
+            if let Some(content_type) = self.content_type {
+                response
+                    .headers_mut()
+                    .insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+            }
             Ok(response)
         }
     }
@@ -617,4 +630,85 @@ mod tests {
         let vary = response.headers().get(VARY).unwrap().to_str().unwrap();
         assert_eq!(vary, "Accept-Language, Accept-Encoding");
     }
+
+    async fn encoding_for(
+        middleware: &mut CompressionMiddleware,
+        content_type: &'static str,
+    ) -> Option<String> {
+        let mut request = request_with_encoding(Some("gzip"));
+        let mut endpoint =
+            StaticEndpoint::new(&"payload".repeat(80)).with_content_type(content_type);
+        let response = middleware
+            .handle(&mut request, &mut endpoint)
+            .await
+            .unwrap();
+        response
+            .headers()
+            .get(CONTENT_ENCODING)
+            .map(|value| value.to_str().unwrap().to_owned())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn filters_by_content_type() {
+        let mut middleware = CompressionMiddleware::new().minimum_size(0);
+        for content_type in [
+            "text/html; charset=utf-8",
+            "application/json",
+            "application/javascript",
+            "image/svg+xml",
+            "application/octet-stream",
+        ] {
+            assert_eq!(
+                encoding_for(&mut middleware, content_type).await.as_deref(),
+                Some("gzip"),
+                "{content_type}"
+            );
+        }
+        for content_type in ["image/png", "Video/MP4", "audio/ogg", "application/zip"] {
+            assert_eq!(
+                encoding_for(&mut middleware, content_type).await,
+                None,
+                "{content_type}"
+            );
+        }
+
+        let mut middleware = CompressionMiddleware::new()
+            .minimum_size(0)
+            .compress_types(["application/json"])
+            .skip_types(["*/*"]);
+        assert!(encoding_for(&mut middleware, "application/json")
+            .await
+            .is_some());
+        assert!(encoding_for(&mut middleware, "text/plain").await.is_none());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn leaves_skipped_streams_unbuffered() {
+        struct StreamingVideo;
+
+        impl Endpoint for StreamingVideo {
+            type Error = Infallible;
+            async fn respond(&mut self, _request: &mut Request) -> Result<Response, Self::Error> {
+                // Never yields, so buffering it would hang.
+                let stream = futures_util::stream::pending::<Result<Vec<u8>, Infallible>>();
+                let mut response = Response::new(Body::from_stream(stream));
+                response
+                    .headers_mut()
+                    .insert(CONTENT_TYPE, HeaderValue::from_static("video/mp4"));
+                Ok(response)
+            }
+        }
+
+        let mut middleware = CompressionMiddleware::new().minimum_size(0);
+        let mut request = request_with_encoding(Some("gzip"));
+        let response = tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            middleware.handle(&mut request, StreamingVideo),
+        )
+        .await
+        .expect("the body was buffered")
+        .unwrap();
+        assert!(response.headers().get(CONTENT_ENCODING).is_none());
+        assert_eq!(response.body().len(), None);
+    }
 }
@@ -0,0 +1,462 @@
+//! Cross-site request forgery protection.
+//!
+//! Browsers attach cookies to requests that other sites trigger, so a session cookie alone does
+//! not prove that a form was submitted from one of your pages. [`CsrfMiddleware`] implements the
+//! double-submit pattern: it hands each browser a random token in a cookie, and requires
+//! `POST`, `PUT`, `PATCH` and `DELETE` requests to echo it in an `X-CSRF-Token` header or a form
+//! field, which other sites cannot read. Pages embed the token through the [`CsrfToken`]
+//! extractor:
+//!
+//! ```
+//! use skyzen::{
+//!     middleware::{CsrfMiddleware, CsrfToken, WithMiddleware},
+//!     routing::{CreateRouteNode, Route},
+//!     Result,
+//! };
+//!
+//! async fn edit_profile(token: CsrfToken) -> Result<String> {
+//!     Ok(format!(
+//!         r#"<form method="post">{}<input name="bio"><button>Save</button></form>"#,
+//!         token.hidden_input()
+//!     ))
+//! }
+//!
+//! async fn save_profile() -> Result<&'static str> {
+//!     Ok("saved")
+//! }
+//!
+//! let router = Route::new(("/profile".at(edit_profile).post(save_profile),)).build();
+//! let app = WithMiddleware::new(router, CsrfMiddleware::new().secure(true));
+//! ```
+//!
+//! Scripts read the token from the cookie, which is not `HttpOnly`, and send it in the header.
+//! Requests failing the check are rejected with `403 Forbidden`.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use cookie::{Cookie, Key, SameSite};
+use http_kit::{
+    error::BoxHttpError,
+    header::{HeaderName, HeaderValue, AUTHORIZATION, COOKIE, SET_COOKIE},
+    http_error,
+    middleware::MiddlewareError,
+    utils::Bytes,
+    Body, Endpoint, Method, Middleware, Request, Response, StatusCode,
+};
+
+use super::{
+    auth::constant_time_eq,
+    method_override::{form_value, is_urlencoded_form},
+};
+use crate::extract::Extractor;
+
+const X_CSRF_TOKEN: HeaderName = HeaderName::from_static("x-csrf-token");
+
+/// A state-changing request did not carry the CSRF token of its cookie.
+#[skyzen::error(status = StatusCode::FORBIDDEN)]
+pub enum CsrfError {
+    /// The request has no token cookie, or does not echo the token.
+    #[error("Missing CSRF token")]
+    Missing,
+    /// The echoed token differs from the cookie.
+    #[error("CSRF token mismatch")]
+    Mismatch,
+}
+
+/// The CSRF token of the current request, for embedding in pages.
+///
+/// Extracting it requires a [`CsrfMiddleware`] in front of the handler.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsrfToken {
+    token: String,
+    field: String,
+}
+
+impl CsrfToken {
+    /// The token, to be sent back in the `X-CSRF-Token` header or the form field.
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.token
+    }
+
+    /// The name of the form field the middleware reads the token from.
+    #[must_use]
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    /// A hidden `<input>` carrying the token, to be placed inside forms.
+    #[must_use]
+    pub fn hidden_input(&self) -> String {
+        format!(
+            r#"<input type="hidden" name="{}" value="{}">"#,
+            self.field, self.token
+        )
+    }
+}
+
+http_error!(
+    /// The request was not handled by [`CsrfMiddleware`], so it has no [`CsrfToken`].
+    pub MissingCsrfToken,
+    StatusCode::INTERNAL_SERVER_ERROR,
+    "CsrfMiddleware is not installed"
+);
+
+impl Extractor for CsrfToken {
+    type Error = MissingCsrfToken;
+    async fn extract(request: &mut Request) -> Result<Self, Self::Error> {
+        request
+            .extensions()
+            .get::<Self>()
+            .cloned()
+            .ok_or_else(MissingCsrfToken::new)
+    }
+}
+
+/// Rejects state-changing requests that do not echo their CSRF cookie, see the
+/// [module docs](self).
+///
+/// The token is read from the `X-CSRF-Token` header, or else from the `csrf_token` field of
+/// `application/x-www-form-urlencoded` bodies; `multipart/form-data` forms have to use the
+/// header. Requests with a safe method (`GET`, `HEAD`, `OPTIONS` and `TRACE`) are never checked.
+/// Requests without a valid cookie are issued a new token in the response.
+#[derive(Debug, Clone)]
+pub struct CsrfMiddleware {
+    cookie_name: String,
+    header: HeaderName,
+    form_field: String,
+    secure: bool,
+    skip_authorized: bool,
+}
+
+impl Default for CsrfMiddleware {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CsrfMiddleware {
+    /// Keep the token in the `csrf_token` cookie.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            cookie_name: "csrf_token".to_owned(),
+            header: X_CSRF_TOKEN,
+            form_field: "csrf_token".to_owned(),
+            secure: false,
+            skip_authorized: false,
+        }
+    }
+
+    /// Keep the token in the cookie `name` instead of `csrf_token`.
+    #[must_use]
+    pub fn cookie_name(mut self, name: impl Into<String>) -> Self {
+        self.cookie_name = name.into();
+        self
+    }
+
+    /// Read the echoed token from `header` instead of `X-CSRF-Token`.
+    #[must_use]
+    pub fn header(mut self, header: HeaderName) -> Self {
+        self.header = header;
+        self
+    }
+
+    /// Read the echoed token from the form field `name` instead of `csrf_token`.
+    #[must_use]
+    pub fn form_field(mut self, name: impl Into<String>) -> Self {
+        self.form_field = name.into();
+        self
+    }
+
+    /// Mark the cookie `Secure`, so that browsers only send it over HTTPS.
+    #[must_use]
+    pub const fn secure(mut self, secure: bool) -> Self {
+        self.secure = secure;
+        self
+    }
+
+    /// Let requests with an `Authorization` header through unchecked.
+    ///
+    /// API clients authenticating with bearer tokens or API keys are not exposed to CSRF, as
+    /// browsers do not attach those on their own. Leave this off if browsers authenticate with
+    /// HTTP Basic auth, which they do attach.
+    #[must_use]
+    pub const fn skip_authorized(mut self) -> Self {
+        self.skip_authorized = true;
+        self
+    }
+
+    fn cookie_token(&self, request: &Request) -> Option<String> {
+        request
+            .headers()
+            .get_all(COOKIE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(Cookie::split_parse_encoded)
+            .filter_map(Result::ok)
+            .find(|cookie| cookie.name() == self.cookie_name)
+            .map(|cookie| cookie.value().to_owned())
+            .filter(|token| is_token(token))
+    }
+
+    async fn submitted_token(&self, request: &mut Request) -> Result<Option<String>, BoxHttpError> {
+        if let Some(value) = request.headers().get(&self.header) {
+            return Ok(value.to_str().ok().map(str::to_owned));
+        }
+        if is_urlencoded_form(request) {
+            let body = Bytes::extract(request)
+                .await
+                .map_err(|error| Box::new(error) as BoxHttpError)?;
+            *request.body_mut() = Body::from_bytes(body.clone());
+            return Ok(form_value(&body, &self.form_field));
+        }
+        Ok(None)
+    }
+
+    async fn check(&self, request: &mut Request, cookie: Option<&str>) -> Result<(), BoxHttpError> {
+        let submitted = self.submitted_token(request).await?;
+        let (Some(cookie), Some(submitted)) = (cookie, submitted) else {
+            return Err(Box::new(CsrfError::Missing));
+        };
+        if constant_time_eq(cookie.as_bytes(), submitted.as_bytes()) {
+            Ok(())
+        } else {
+            Err(Box::new(CsrfError::Mismatch))
+        }
+    }
+
+    fn set_cookie(&self, token: String) -> Option<HeaderValue> {
+        let cookie = Cookie::build((self.cookie_name.clone(), token))
+            .path("/")
+            .same_site(SameSite::Lax)
+            .secure(self.secure)
+            .build();
+        HeaderValue::try_from(cookie.encoded().to_string()).ok()
+    }
+}
+
+impl Middleware for CsrfMiddleware {
+    type Error = BoxHttpError;
+
+    async fn handle<N: Endpoint>(
+        &mut self,
+        request: &mut Request,
+        mut next: N,
+    ) -> Result<Response, MiddlewareError<N::Error, Self::Error>> {
+        let cookie = self.cookie_token(request);
+
+        let safe = matches!(
+            *request.method(),
+            Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE
+        );
+        let authorized = self.skip_authorized && request.headers().contains_key(AUTHORIZATION);
+        if !safe && !authorized {
+            self.check(request, cookie.as_deref())
+                .await
+                .map_err(MiddlewareError::Middleware)?;
+        }
+
+        let issued = cookie.is_none();
+        let token = cookie.unwrap_or_else(generate_token);
+        request.extensions_mut().insert(CsrfToken {
+            token: token.clone(),
+            field: self.form_field.clone(),
+        });
+
+        let mut response = next
+            .respond(request)
+            .await
+            .map_err(MiddlewareError::Endpoint)?;
+        if issued {
+            if let Some(value) = self.set_cookie(token) {
+                response.headers_mut().append(SET_COOKIE, value);
+            }
+        }
+        Ok(response)
+    }
+}
+
+/// A new token of 32 random bytes.
+fn generate_token() -> String {
+    // `Key::generate` draws from the operating system's cryptographically secure generator.
+    let key = Key::generate();
+    URL_SAFE_NO_PAD.encode(&key.master()[..32])
+}
+
+/// Whether a cookie value looks like a token we issued, so that it is safe to embed in pages.
+fn is_token(value: &str) -> bool {
+    !value.is_empty()
+        && value
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_')
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::{CsrfMiddleware, CsrfToken};
+    use crate::{
+        header::{HeaderValue, AUTHORIZATION, CONTENT_TYPE, COOKIE, SET_COOKIE},
+        middleware::WithMiddleware,
+        routing::{CreateRouteNode, Route},
+        utils::Form,
+        Body, Endpoint, HttpError, Method, Request, Result, StatusCode,
+    };
+
+    #[derive(serde::Deserialize)]
+    struct Comment {
+        text: String,
+    }
+
+    fn app(csrf: CsrfMiddleware) -> impl Endpoint {
+        let router = Route::new((
+            "/comments".at(|token: CsrfToken| async move { Result::Ok(token.hidden_input()) }),
+            "/comments".post(|Form(comment): Form<Comment>| async move {
+                Result::Ok(format!("posted {}", comment.text))
+            }),
+            "/comments/1".delete(|| async { Result::Ok("deleted") }),
+        ))
+        .build();
+        WithMiddleware::new(router, csrf)
+    }
+
+    fn request(method: Method, uri: &str, cookie: Option<&str>) -> Request {
+        let mut request = Request::new(Body::empty());
+        *request.method_mut() = method;
+        *request.uri_mut() = uri.parse().unwrap();
+        if let Some(token) = cookie {
+            let cookie = format!("theme=dark; csrf_token={token}");
+            request
+                .headers_mut()
+                .insert(COOKIE, HeaderValue::try_from(cookie).unwrap());
+        }
+        request
+    }
+
+    fn form(cookie: Option<&str>, body: String) -> Request {
+        let mut request = request(Method::POST, "/comments", cookie);
+        *request.body_mut() = Body::from(body);
+        request.headers_mut().insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("application/x-www-form-urlencoded"),
+        );
+        request
+    }
+
+    fn with_header(mut request: Request, token: &str) -> Request {
+        request
+            .headers_mut()
+            .insert("x-csrf-token", HeaderValue::try_from(token).unwrap());
+        request
+    }
+
+    async fn status(app: &mut impl Endpoint, mut request: Request) -> StatusCode {
+        match app.respond(&mut request).await {
+            Ok(response) => response.status(),
+            Err(error) => error.status(),
+        }
+    }
+
+    /// Fetch the comment form without a cookie, returning the issued token.
+    async fn issue_token(app: &mut impl Endpoint) -> String {
+        let mut request = request(Method::GET, "/comments", None);
+        let response = app.respond(&mut request).await.ok().unwrap();
+        let set_cookie = response.headers()[SET_COOKIE].to_str().unwrap().to_owned();
+        let token = set_cookie
+            .strip_prefix("csrf_token=")
+            .and_then(|rest| rest.split(';').next())
+            .unwrap()
+            .to_owned();
+        assert!(set_cookie.contains("SameSite=Lax"), "{set_cookie}");
+        assert!(set_cookie.contains("Path=/"), "{set_cookie}");
+        assert!(!set_cookie.contains("HttpOnly"), "{set_cookie}");
+
+        let body = response.into_body().into_string().await.unwrap();
+        assert_eq!(
+            body.to_string(),
+            format!(r#"<input type="hidden" name="csrf_token" value="{token}">"#)
+        );
+        token
+    }
+
+    #[tokio::test]
+    async fn accepts_tokens_echoed_in_headers_and_forms() {
+        let mut app = app(CsrfMiddleware::new());
+        let token = issue_token(&mut app).await;
+        assert_eq!(token.len(), 43);
+        assert_ne!(issue_token(&mut app).await, token);
+
+        let mut submission = form(Some(&token), format!("text=hi&csrf_token={token}"));
+        let response = app.respond(&mut submission).await.ok().unwrap();
+        // The token of the cookie is kept rather than reissued.
+        assert!(response.headers().get(SET_COOKIE).is_none());
+        assert_eq!(
+            response
+                .into_body()
+                .into_string()
+                .await
+                .unwrap()
+                .to_string(),
+            "posted hi"
+        );
+
+        let request = with_header(request(Method::DELETE, "/comments/1", Some(&token)), &token);
+        assert_eq!(status(&mut app, request).await, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn rejects_missing_tokens() {
+        let mut app = app(CsrfMiddleware::new());
+        let token = issue_token(&mut app).await;
+
+        for request in [
+            request(Method::DELETE, "/comments/1", None),
+            with_header(request(Method::DELETE, "/comments/1", None), &token),
+            request(Method::DELETE, "/comments/1", Some(&token)),
+            form(Some(&token), "text=hi".to_owned()),
+        ] {
+            assert_eq!(status(&mut app, request).await, StatusCode::FORBIDDEN);
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_mismatched_tokens() {
+        let mut app = app(CsrfMiddleware::new());
+        let token = issue_token(&mut app).await;
+        let other = issue_token(&mut app).await;
+
+        let mut forged = with_header(request(Method::DELETE, "/comments/1", Some(&token)), &other);
+        let error = app.respond(&mut forged).await.err().unwrap();
+        assert_eq!(error.status(), StatusCode::FORBIDDEN);
+        assert!(
+            error.to_string().ends_with("CSRF token mismatch"),
+            "{error}"
+        );
+
+        let request = form(Some(&token), format!("text=hi&csrf_token={other}"));
+        assert_eq!(status(&mut app, request).await, StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn lets_safe_methods_and_configured_api_clients_through() {
+        let mut strict = app(CsrfMiddleware::new());
+        for method in [Method::GET, Method::HEAD, Method::OPTIONS] {
+            let request = request(method.clone(), "/comments", None);
+            assert_ne!(
+                status(&mut strict, request).await,
+                StatusCode::FORBIDDEN,
+                "{method}"
+            );
+        }
+
+        let api_call = || {
+            let mut request = request(Method::DELETE, "/comments/1", None);
+            request
+                .headers_mut()
+                .insert(AUTHORIZATION, HeaderValue::from_static("Bearer abc"));
+            request
+        };
+        assert_eq!(status(&mut strict, api_call()).await, StatusCode::FORBIDDEN);
+        let mut lenient = app(CsrfMiddleware::new().skip_authorized());
+        assert_eq!(status(&mut lenient, api_call()).await, StatusCode::OK);
+    }
+}
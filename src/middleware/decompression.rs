@@ -0,0 +1,418 @@
+//! Request body decompression.
+//!
+//! Clients uploading large payloads may compress them and announce it with
+//! `Content-Encoding: gzip`. [`RequestDecompressionMiddleware`] decodes such bodies as they are
+//! read, so that extractors like [`Json`](crate::utils::Json) see the plain bytes:
+//!
+//! ```
+//! use skyzen::{
+//!     middleware::{RequestDecompressionMiddleware, WithMiddleware},
+//!     routing::{CreateRouteNode, Route},
+//!     utils::Json,
+//!     Result,
+//! };
+//!
+//! async fn import(Json(records): Json<Vec<serde_json::Value>>) -> Result<String> {
+//!     Ok(format!("imported {} records", records.len()))
+//! }
+//!
+//! let router = Route::new(("/import".post(import),)).build();
+//! let app = WithMiddleware::new(
+//!     router,
+//!     RequestDecompressionMiddleware::new().max_size(64 * 1024 * 1024),
+//! );
+//! ```
+//!
+//! `gzip` and `deflate` bodies are decoded, the same encodings [`CompressionMiddleware`] produces;
+//! requests with another encoding are rejected with `415 Unsupported Media Type`.
+//!
+//! [`CompressionMiddleware`]: super::CompressionMiddleware
+
+use std::{
+    io::{self, Write},
+    mem,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    task::{ready, Context, Poll},
+};
+
+use flate2::write::{GzDecoder, ZlibDecoder};
+use futures_util::Stream;
+use http::{
+    header::{CONTENT_ENCODING, CONTENT_LENGTH},
+    StatusCode,
+};
+use http_kit::{
+    middleware::MiddlewareError, Body, BodyError, Endpoint, Middleware, Request, Response,
+};
+
+/// The default [`max_size`](RequestDecompressionMiddleware::max_size), 16 MiB.
+const DEFAULT_MAX_SIZE: usize = 16 * 1024 * 1024;
+
+/// A compressed request body could not be decoded.
+#[skyzen::error]
+pub enum RequestDecompressionError {
+    /// The `Content-Encoding` of the request is not one the middleware decodes.
+    #[error(
+        "Unsupported content encoding `{0}`",
+        status = StatusCode::UNSUPPORTED_MEDIA_TYPE
+    )]
+    Unsupported(String),
+    /// The decoded body grew past the configured
+    /// [`max_size`](RequestDecompressionMiddleware::max_size).
+    #[error(
+        "Decompressed request body exceeds the limit of {0} bytes",
+        status = StatusCode::PAYLOAD_TOO_LARGE
+    )]
+    TooLarge(usize),
+}
+
+/// Decodes `gzip` and `deflate` request bodies, see the [module docs](self).
+///
+/// The body is decoded as it is read rather than up front, and the `Content-Encoding` and
+/// `Content-Length` headers are removed since they described the compressed payload. Requests
+/// without a `Content-Encoding`, or with `identity`, pass through unchanged.
+///
+/// Decoding stops once the body grows past [`max_size`](Self::max_size), which guards against
+/// small payloads that expand to gigabytes. The extractor reading the body then fails, and the
+/// middleware answers with `413 Payload Too Large` whatever the handler made of it.
+#[derive(Debug, Clone)]
+pub struct RequestDecompressionMiddleware {
+    max_size: usize,
+}
+
+impl Default for RequestDecompressionMiddleware {
+    fn default() -> Self {
+        Self {
+            max_size: DEFAULT_MAX_SIZE,
+        }
+    }
+}
+
+impl RequestDecompressionMiddleware {
+    /// Creates a middleware decoding bodies of up to 16 MiB.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the maximum size, in bytes, of a decoded body.
+    #[must_use]
+    pub const fn max_size(mut self, max_size: usize) -> Self {
+        self.max_size = max_size;
+        self
+    }
+}
+
+impl Middleware for RequestDecompressionMiddleware {
+    type Error = RequestDecompressionError;
+
+    async fn handle<N: Endpoint>(
+        &mut self,
+        request: &mut Request,
+        mut next: N,
+    ) -> Result<Response, MiddlewareError<N::Error, Self::Error>> {
+        let Some(decoder) = request_decoder(request).map_err(MiddlewareError::Middleware)? else {
+            return next
+                .respond(request)
+                .await
+                .map_err(MiddlewareError::Endpoint);
+        };
+
+        let exceeded = Arc::new(AtomicBool::new(false));
+        let body = mem::take(request.body_mut());
+        *request.body_mut() = Body::from_stream(DecodedBody {
+            body,
+            decoder: Some(decoder),
+            remaining: self.max_size,
+            exceeded: Arc::clone(&exceeded),
+        });
+        request.headers_mut().remove(CONTENT_ENCODING);
+        request.headers_mut().remove(CONTENT_LENGTH);
+
+        let result = next.respond(request).await;
+        if exceeded.load(Ordering::Relaxed) {
+            return Err(MiddlewareError::Middleware(
+                RequestDecompressionError::TooLarge(self.max_size),
+            ));
+        }
+        result.map_err(MiddlewareError::Endpoint)
+    }
+}
+
+/// The decoder for the `Content-Encoding` of `request`, or `None` if its body is not encoded.
+fn request_decoder(request: &Request) -> Result<Option<Decoder>, RequestDecompressionError> {
+    let mut encodings = request
+        .headers()
+        .get_all(CONTENT_ENCODING)
+        .iter()
+        .flat_map(|value| value.to_str().unwrap_or("").split(','))
+        .map(str::trim)
+        .filter(|encoding| !encoding.is_empty() && !encoding.eq_ignore_ascii_case("identity"));
+    let Some(encoding) = encodings.next() else {
+        return Ok(None);
+    };
+    if encodings.next().is_some() {
+        // Stacked encodings such as `gzip, gzip` have no legitimate use.
+        let all = request
+            .headers()
+            .get_all(CONTENT_ENCODING)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .collect::<Vec<_>>()
+            .join(", ");
+        return Err(RequestDecompressionError::Unsupported(all));
+    }
+    match encoding.to_ascii_lowercase().as_str() {
+        "gzip" | "x-gzip" => Ok(Some(Decoder::Gzip(GzDecoder::new(Vec::new())))),
+        "deflate" => Ok(Some(Decoder::Deflate(ZlibDecoder::new(Vec::new())))),
+        _ => Err(RequestDecompressionError::Unsupported(encoding.to_owned())),
+    }
+}
+
+#[derive(Debug)]
+enum Decoder {
+    Gzip(GzDecoder<Vec<u8>>),
+    Deflate(ZlibDecoder<Vec<u8>>),
+}
+
+impl Decoder {
+    fn writer(&mut self) -> &mut dyn Write {
+        match self {
+            Self::Gzip(decoder) => decoder,
+            Self::Deflate(decoder) => decoder,
+        }
+    }
+
+    fn output(&mut self) -> &mut Vec<u8> {
+        match self {
+            Self::Gzip(decoder) => decoder.get_mut(),
+            Self::Deflate(decoder) => decoder.get_mut(),
+        }
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        match self {
+            Self::Gzip(decoder) => decoder.try_finish(),
+            Self::Deflate(decoder) => decoder.try_finish(),
+        }
+    }
+}
+
+/// Stream adapter decoding `body`, failing once it decoded more than `remaining` bytes.
+struct DecodedBody {
+    body: Body,
+    /// `None` once the body is fully decoded.
+    decoder: Option<Decoder>,
+    remaining: usize,
+    exceeded: Arc<AtomicBool>,
+}
+
+impl DecodedBody {
+    /// Decode `chunk`, returning the output produced so far.
+    ///
+    /// The decoder hands its output over in pieces of a few kilobytes, so the limit is checked
+    /// long before a highly compressed chunk is expanded in full.
+    fn decode(&mut self, decoder: &mut Decoder, mut chunk: &[u8]) -> io::Result<Vec<u8>> {
+        while !chunk.is_empty() {
+            let written = decoder.writer().write(chunk)?;
+            if written == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "trailing data after the compressed request body",
+                ));
+            }
+            chunk = &chunk[written..];
+            self.check(decoder.output())?;
+        }
+        decoder.writer().flush()?;
+        self.take_output(decoder)
+    }
+
+    fn take_output(&mut self, decoder: &mut Decoder) -> io::Result<Vec<u8>> {
+        self.check(decoder.output())?;
+        let output = mem::take(decoder.output());
+        self.remaining -= output.len();
+        Ok(output)
+    }
+
+    fn check(&self, output: &[u8]) -> io::Result<()> {
+        if output.len() > self.remaining {
+            self.exceeded.store(true, Ordering::Relaxed);
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "decompressed request body exceeds the size limit",
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Stream for DecodedBody {
+    type Item = Result<Vec<u8>, BodyError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        loop {
+            let Some(mut decoder) = this.decoder.take() else {
+                return Poll::Ready(None);
+            };
+            let output = match ready!(Pin::new(&mut this.body).poll_next(cx)) {
+                Some(Ok(chunk)) => {
+                    let output = this.decode(&mut decoder, &chunk);
+                    this.decoder = Some(decoder);
+                    output
+                }
+                Some(Err(error)) => return Poll::Ready(Some(Err(error))),
+                None => decoder
+                    .finish()
+                    .and_then(|()| this.take_output(&mut decoder)),
+            };
+            match output {
+                Ok(output) if output.is_empty() => {}
+                Ok(output) => return Poll::Ready(Some(Ok(output))),
+                Err(error) => {
+                    this.decoder = None;
+                    return Poll::Ready(Some(Err(error.into())));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::{
+        write::{GzEncoder, ZlibEncoder},
+        Compression,
+    };
+    use http::{
+        header::{CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE},
+        HeaderValue,
+    };
+    use http_kit::{middleware::MiddlewareError, HttpError, Middleware};
+
+    use super::{RequestDecompressionError, RequestDecompressionMiddleware};
+    use crate::{
+        routing::{CreateRouteNode, Route, Router},
+        utils::Json,
+        Body, Endpoint, Method, Request, Result, StatusCode,
+    };
+
+    fn router() -> Router {
+        Route::new((
+            "/echo".post(|Json(value): Json<serde_json::Value>| async move {
+                Result::Ok(value.to_string())
+            }),
+        ))
+        .build()
+    }
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn deflate(data: &[u8]) -> Vec<u8> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn post(body: Vec<u8>, encoding: Option<&'static str>) -> Request {
+        let len = body.len();
+        let mut request = Request::new(Body::from_bytes(body));
+        *request.method_mut() = Method::POST;
+        *request.uri_mut() = "/echo".parse().unwrap();
+        let headers = request.headers_mut();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        headers.insert(CONTENT_LENGTH, HeaderValue::from(len));
+        if let Some(encoding) = encoding {
+            headers.insert(CONTENT_ENCODING, HeaderValue::from_static(encoding));
+        }
+        request
+    }
+
+    async fn respond(
+        middleware: &mut RequestDecompressionMiddleware,
+        mut request: Request,
+    ) -> std::result::Result<(StatusCode, String), RequestDecompressionError> {
+        let mut router = router();
+        match middleware.handle(&mut request, &mut router).await {
+            Ok(response) => {
+                let status = response.status();
+                let body = response.into_body().into_string().await.unwrap();
+                Ok((status, body.to_string()))
+            }
+            Err(MiddlewareError::Middleware(error)) => Err(error),
+            Err(MiddlewareError::Endpoint(error)) => panic!("router failed: {error}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn decodes_compressed_json_bodies() {
+        let mut middleware = RequestDecompressionMiddleware::new();
+        let json = br#"{"name":"skyzen","tags":["web","async"]}"#;
+        let expected = (StatusCode::OK, String::from_utf8(json.to_vec()).unwrap());
+
+        for request in [
+            post(gzip(json), Some("gzip")),
+            post(gzip(json), Some("X-GZIP")),
+            post(deflate(json), Some("deflate")),
+            post(json.to_vec(), Some("identity")),
+            post(json.to_vec(), None),
+        ] {
+            assert_eq!(respond(&mut middleware, request).await.unwrap(), expected);
+        }
+
+        // Without the middleware, the extractor chokes on the compressed bytes.
+        let mut request = post(gzip(json), Some("gzip"));
+        let response = router().respond(&mut request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn rejects_unsupported_encodings() {
+        let mut middleware = RequestDecompressionMiddleware::new();
+        for encoding in ["br", "gzip, gzip"] {
+            let error = respond(&mut middleware, post(b"{}".to_vec(), Some(encoding)))
+                .await
+                .unwrap_err();
+            assert_eq!(
+                error.status(),
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "{error}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn stops_decoding_past_the_limit() {
+        // A megabyte of JSON whitespace compresses to about a kilobyte.
+        let mut json = vec![b' '; 1024 * 1024];
+        json.extend_from_slice(b"{}");
+        let body = gzip(&json);
+        assert!(body.len() < 4096);
+
+        let mut middleware = RequestDecompressionMiddleware::new().max_size(64 * 1024);
+        let error = respond(&mut middleware, post(body.clone(), Some("gzip")))
+            .await
+            .unwrap_err();
+        assert!(matches!(error, RequestDecompressionError::TooLarge(65536)));
+        assert_eq!(error.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let mut middleware = RequestDecompressionMiddleware::new().max_size(json.len());
+        assert_eq!(
+            respond(&mut middleware, post(body, Some("gzip")))
+                .await
+                .unwrap(),
+            (StatusCode::OK, "{}".to_owned())
+        );
+    }
+}
@@ -0,0 +1,166 @@
+//! Early rejection of `Expect: 100-continue` requests.
+//!
+//! Clients uploading large bodies can send `Expect: 100-continue` and wait for an interim
+//! `100 Continue` response before sending the body. The native runtime only sends it once the
+//! request body is first read, so a request answered without touching its body is rejected before
+//! the client uploads anything.
+//!
+//! [`ExpectContinueMiddleware`] runs a check against the request headers of such requests before
+//! the rest of the stack sees them:
+//!
+//! ```
+//! use skyzen::{
+//!     extract::BodyReadError,
+//!     header::CONTENT_LENGTH,
+//!     middleware::{ExpectContinueMiddleware, WithMiddleware},
+//!     routing::{CreateRouteNode, Route},
+//!     Body, Request, Result,
+//! };
+//!
+//! const MAX_UPLOAD: usize = 64 * 1024 * 1024;
+//!
+//! async fn upload(body: Body) -> Result<&'static str> {
+//!     // Store the body...
+//!     # let _ = body;
+//!     Ok("stored")
+//! }
+//!
+//! let router = Route::new(("/upload".post(upload),)).build();
+//! let app = WithMiddleware::new(
+//!     router,
+//!     ExpectContinueMiddleware::new(|request: &Request| {
+//!         let length = request
+//!             .headers()
+//!             .get(CONTENT_LENGTH)
+//!             .and_then(|value| value.to_str().ok()?.parse::<usize>().ok());
+//!         match length {
+//!             Some(length) if length <= MAX_UPLOAD => Ok(()),
+//!             _ => Err(BodyReadError::TooLarge(MAX_UPLOAD)),
+//!         }
+//!     }),
+//! );
+//! ```
+
+use std::convert::Infallible;
+
+use http_kit::{
+    header::{HeaderValue, EXPECT},
+    middleware::MiddlewareError,
+    Body, Endpoint, HttpError, Middleware, Request, Response,
+};
+
+/// Rejects `Expect: 100-continue` requests from their headers, see the [module docs](self).
+///
+/// The check only runs for requests carrying `Expect: 100-continue`; other requests, and those
+/// passing the check, go on unchanged. A rejected request is answered right away with the status
+/// and message of the check's error, and its body is never read.
+#[derive(Debug, Clone)]
+pub struct ExpectContinueMiddleware<F> {
+    check: F,
+}
+
+impl<F, E> ExpectContinueMiddleware<F>
+where
+    F: Fn(&Request) -> Result<(), E> + Send,
+    E: HttpError,
+{
+    /// Reject `Expect: 100-continue` requests for which `check` fails.
+    pub const fn new(check: F) -> Self {
+        Self { check }
+    }
+}
+
+impl<F, E> Middleware for ExpectContinueMiddleware<F>
+where
+    F: Fn(&Request) -> Result<(), E> + Send,
+    E: HttpError,
+{
+    type Error = Infallible;
+
+    async fn handle<N: Endpoint>(
+        &mut self,
+        request: &mut Request,
+        mut next: N,
+    ) -> Result<Response, MiddlewareError<N::Error, Self::Error>> {
+        if expects_continue(request) {
+            if let Err(error) = (self.check)(request) {
+                // Answered here rather than propagated, so that the rejection never depends on an
+                // outer layer rendering errors.
+                let mut response = Response::new(Body::from(error.to_string()));
+                *response.status_mut() = error.status();
+                return Ok(response);
+            }
+        }
+        next.respond(request)
+            .await
+            .map_err(MiddlewareError::Endpoint)
+    }
+}
+
+fn expects_continue(request: &Request) -> bool {
+    request
+        .headers()
+        .get_all(EXPECT)
+        .iter()
+        .filter_map(|value: &HeaderValue| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|expectation| expectation.trim().eq_ignore_ascii_case("100-continue"))
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    };
+
+    use super::ExpectContinueMiddleware;
+    use crate::{
+        header::{HeaderValue, EXPECT},
+        middleware::WithMiddleware,
+        routing::{CreateRouteNode, Route},
+        Body, Endpoint, Method, Request, Result, StatusCode,
+    };
+
+    http_kit::http_error!(Forbidden, StatusCode::FORBIDDEN, "Uploads are disabled");
+
+    #[tokio::test]
+    async fn only_checks_requests_expecting_continue() {
+        let handled = Arc::new(AtomicBool::new(false));
+        let router = {
+            let handled = Arc::clone(&handled);
+            Route::new(("/upload".post(move || {
+                handled.store(true, Ordering::SeqCst);
+                async { Result::Ok("stored") }
+            }),))
+            .build()
+        };
+        let mut app = WithMiddleware::new(
+            router,
+            ExpectContinueMiddleware::new(|_: &Request| Err(Forbidden::new())),
+        );
+
+        let request = |expect: Option<&'static str>| {
+            let mut request = Request::new(Body::from("payload"));
+            *request.method_mut() = Method::POST;
+            *request.uri_mut() = "/upload".parse().unwrap();
+            if let Some(expect) = expect {
+                request
+                    .headers_mut()
+                    .insert(EXPECT, HeaderValue::from_static(expect));
+            }
+            request
+        };
+
+        let response = app
+            .respond(&mut request(Some("100-Continue")))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert!(!handled.load(Ordering::SeqCst));
+
+        let response = app.respond(&mut request(None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(handled.load(Ordering::SeqCst));
+    }
+}
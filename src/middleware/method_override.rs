@@ -0,0 +1,340 @@
+//! Method overrides for clients limited to `GET` and `POST`.
+//!
+//! HTML forms can only submit `GET` and `POST`. [`MethodOverrideMiddleware`] lets a `POST` request
+//! ask to be routed as another method through a header such as `X-HTTP-Method-Override: DELETE`,
+//! a query parameter, or a form field such as `_method=DELETE`:
+//!
+//! ```
+//! use skyzen::{
+//!     middleware::{MethodOverrideMiddleware, WithMiddleware},
+//!     routing::{CreateRouteNode, Route},
+//!     Result,
+//! };
+//!
+//! async fn delete_post() -> Result<&'static str> {
+//!     Ok("deleted")
+//! }
+//!
+//! let router = Route::new(("/posts/{id}".delete(delete_post),)).build();
+//! // `<form method="post"><input type="hidden" name="_method" value="DELETE">` now reaches
+//! // `delete_post`.
+//! let app = WithMiddleware::new(router, MethodOverrideMiddleware::new().form_field("_method"));
+//! ```
+//!
+//! The method has to be rewritten before the router picks a route, so the middleware wraps the
+//! router rather than being attached with [`Route::middleware`](crate::routing::Route::middleware).
+
+use http_kit::{
+    error::BoxHttpError,
+    header::{HeaderName, CONTENT_TYPE},
+    middleware::MiddlewareError,
+    utils::Bytes,
+    Body, Endpoint, Method, Middleware, Request, Response, StatusCode,
+};
+
+use crate::{extract::Extractor, utils::percent::percent_decode};
+
+const X_HTTP_METHOD_OVERRIDE: HeaderName = HeaderName::from_static("x-http-method-override");
+
+/// The method a request was sent with, stored in the request extensions by
+/// [`MethodOverrideMiddleware`] when it overrides the method.
+///
+/// Read it with [`Extension`](crate::extract::Extension), for example to log both methods.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OriginalMethod(pub Method);
+
+/// An overridden method is not a valid method, or not one the middleware allows.
+#[skyzen::error(status = StatusCode::BAD_REQUEST)]
+pub enum MethodOverrideError {
+    /// The override is not a valid HTTP method.
+    #[error("Invalid method override")]
+    Invalid,
+    /// The override names a method outside the allowed set.
+    #[error("Method override to `{0}` is not allowed")]
+    NotAllowed(Method),
+}
+
+/// Routes `POST` requests as the method they ask for, see the [module docs](self).
+///
+/// Overrides are read from, in order, the header (`X-HTTP-Method-Override` by default), the
+/// [query field](Self::query_field) and the [form field](Self::form_field). Only `PUT`, `PATCH` and
+/// `DELETE` are accepted unless configured with [`allow`](Self::allow); other targets are rejected
+/// with `400 Bad Request`. Requests with any method other than `POST` pass through unchanged.
+#[derive(Debug, Clone)]
+pub struct MethodOverrideMiddleware {
+    header: Option<HeaderName>,
+    query_field: Option<String>,
+    form_field: Option<String>,
+    allowed: Vec<Method>,
+}
+
+impl Default for MethodOverrideMiddleware {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MethodOverrideMiddleware {
+    /// Honour the `X-HTTP-Method-Override` header for `PUT`, `PATCH` and `DELETE`.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            header: Some(X_HTTP_METHOD_OVERRIDE),
+            query_field: None,
+            form_field: None,
+            allowed: vec![Method::PUT, Method::PATCH, Method::DELETE],
+        }
+    }
+
+    /// Read the override from `header` instead of `X-HTTP-Method-Override`.
+    #[must_use]
+    pub fn header(mut self, header: HeaderName) -> Self {
+        self.header = Some(header);
+        self
+    }
+
+    /// Ignore override headers.
+    #[must_use]
+    pub fn without_header(mut self) -> Self {
+        self.header = None;
+        self
+    }
+
+    /// Also read the override from the query parameter `name`, such as `_method`.
+    #[must_use]
+    pub fn query_field(mut self, name: impl Into<String>) -> Self {
+        self.query_field = Some(name.into());
+        self
+    }
+
+    /// Also read the override from the field `name` of `application/x-www-form-urlencoded`
+    /// bodies, such as `_method`.
+    ///
+    /// The body is buffered to find the field and put back for the handler, within the
+    /// [`BodyLimit`](crate::extract::BodyLimit) installed by outer middleware.
+    /// `multipart/form-data` bodies are not searched.
+    #[must_use]
+    pub fn form_field(mut self, name: impl Into<String>) -> Self {
+        self.form_field = Some(name.into());
+        self
+    }
+
+    /// Only accept overrides to `methods`, replacing the default `PUT`, `PATCH` and `DELETE`.
+    #[must_use]
+    pub fn allow(mut self, methods: impl IntoIterator<Item = Method>) -> Self {
+        self.allowed = methods.into_iter().collect();
+        self
+    }
+
+    async fn requested(&self, request: &mut Request) -> Result<Option<String>, BoxHttpError> {
+        if let Some(header) = &self.header {
+            if let Some(value) = request.headers().get(header) {
+                let value = value
+                    .to_str()
+                    .map_err(|_| Box::new(MethodOverrideError::Invalid) as BoxHttpError)?;
+                return Ok(Some(value.to_owned()));
+            }
+        }
+        if let Some(field) = &self.query_field {
+            let value = request
+                .uri()
+                .query()
+                .and_then(|query| form_value(query.as_bytes(), field));
+            if value.is_some() {
+                return Ok(value);
+            }
+        }
+        if let Some(field) = &self.form_field {
+            if is_urlencoded_form(request) {
+                let body = Bytes::extract(request)
+                    .await
+                    .map_err(|error| Box::new(error) as BoxHttpError)?;
+                *request.body_mut() = Body::from_bytes(body.clone());
+                return Ok(form_value(&body, field));
+            }
+        }
+        Ok(None)
+    }
+
+    fn target(&self, requested: &str) -> Result<Method, MethodOverrideError> {
+        let method = Method::from_bytes(requested.trim().to_ascii_uppercase().as_bytes())
+            .map_err(|_| MethodOverrideError::Invalid)?;
+        if self.allowed.contains(&method) {
+            Ok(method)
+        } else {
+            Err(MethodOverrideError::NotAllowed(method))
+        }
+    }
+}
+
+impl Middleware for MethodOverrideMiddleware {
+    type Error = BoxHttpError;
+
+    async fn handle<N: Endpoint>(
+        &mut self,
+        request: &mut Request,
+        mut next: N,
+    ) -> Result<Response, MiddlewareError<N::Error, Self::Error>> {
+        if request.method() == Method::POST {
+            if let Some(requested) = self
+                .requested(request)
+                .await
+                .map_err(MiddlewareError::Middleware)?
+            {
+                let method = self.target(&requested).map_err(|error| {
+                    MiddlewareError::Middleware(Box::new(error) as BoxHttpError)
+                })?;
+                let original = std::mem::replace(request.method_mut(), method);
+                request.extensions_mut().insert(OriginalMethod(original));
+            }
+        }
+        next.respond(request)
+            .await
+            .map_err(MiddlewareError::Endpoint)
+    }
+}
+
+pub(crate) fn is_urlencoded_form(request: &Request) -> bool {
+    request
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .is_some_and(|mime| {
+            mime.trim()
+                .eq_ignore_ascii_case("application/x-www-form-urlencoded")
+        })
+}
+
+/// The decoded value of the first `name` field of a urlencoded form.
+pub(crate) fn form_value(form: &[u8], name: &str) -> Option<String> {
+    let decode = |part: &[u8]| {
+        let part = std::str::from_utf8(part).ok()?.replace('+', " ");
+        percent_decode(&part)
+    };
+    form.split(|&byte| byte == b'&').find_map(|pair| {
+        let mut parts = pair.splitn(2, |&byte| byte == b'=');
+        let key = parts.next()?;
+        if decode(key)? == name {
+            decode(parts.next().unwrap_or_default())
+        } else {
+            None
+        }
+    })
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::{MethodOverrideMiddleware, OriginalMethod};
+    use crate::{
+        extract::Extension,
+        header::{HeaderValue, CONTENT_TYPE},
+        middleware::WithMiddleware,
+        routing::{CreateRouteNode, Route},
+        utils::Form,
+        Body, Endpoint, HttpError, Method, Request, Result, StatusCode,
+    };
+
+    #[derive(serde::Deserialize)]
+    struct Post {
+        title: String,
+    }
+
+    fn posts(middleware: MethodOverrideMiddleware) -> impl Endpoint {
+        let router = Route::new((
+            "/posts/1"
+                .post(|| async { Result::Ok("created".to_owned()) })
+                .put(|Form(post): Form<Post>| async move {
+                    Result::Ok(format!("updated to {}", post.title))
+                }),
+            "/posts/1".delete(|Extension(OriginalMethod(method))| async move {
+                Result::Ok(format!("deleted by {method}"))
+            }),
+        ))
+        .build();
+        WithMiddleware::new(router, middleware)
+    }
+
+    fn post(uri: &str, body: &'static str) -> Request {
+        let mut request = Request::new(Body::from(body));
+        *request.method_mut() = Method::POST;
+        *request.uri_mut() = uri.parse().unwrap();
+        request.headers_mut().insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("application/x-www-form-urlencoded"),
+        );
+        request
+    }
+
+    async fn respond(app: &mut impl Endpoint, mut request: Request) -> (StatusCode, String) {
+        let response = app.respond(&mut request).await.ok().unwrap();
+        let status = response.status();
+        let body = response.into_body().into_string().await.unwrap();
+        (status, body.to_string())
+    }
+
+    #[tokio::test]
+    async fn overrides_from_the_header() {
+        let mut app = posts(MethodOverrideMiddleware::new());
+        let mut request = post("/posts/1", "");
+        request
+            .headers_mut()
+            .insert("x-http-method-override", HeaderValue::from_static("delete"));
+        assert_eq!(
+            respond(&mut app, request).await,
+            (StatusCode::OK, "deleted by POST".to_owned())
+        );
+        assert_eq!(
+            respond(&mut app, post("/posts/1", "_method=DELETE")).await,
+            (StatusCode::OK, "created".to_owned())
+        );
+    }
+
+    #[tokio::test]
+    async fn overrides_from_form_fields_and_keeps_the_body() {
+        let mut app = posts(
+            MethodOverrideMiddleware::new()
+                .without_header()
+                .query_field("_method")
+                .form_field("_method"),
+        );
+        assert_eq!(
+            respond(&mut app, post("/posts/1", "title=Hello+there&_method=PUT")).await,
+            (StatusCode::OK, "updated to Hello there".to_owned())
+        );
+        assert_eq!(
+            respond(&mut app, post("/posts/1?_method=DELETE", "")).await,
+            (StatusCode::OK, "deleted by POST".to_owned())
+        );
+        let mut request = post("/posts/1", "title=Hello");
+        request
+            .headers_mut()
+            .insert("x-http-method-override", HeaderValue::from_static("DELETE"));
+        assert_eq!(
+            respond(&mut app, request).await,
+            (StatusCode::OK, "created".to_owned())
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_methods_outside_the_allowed_set() {
+        let mut app = posts(MethodOverrideMiddleware::new().form_field("_method"));
+        let error = app
+            .respond(&mut post("/posts/1", "_method=GET"))
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(error.status(), StatusCode::BAD_REQUEST);
+        assert!(error
+            .to_string()
+            .ends_with("Method override to `GET` is not allowed"));
+
+        let mut app = posts(MethodOverrideMiddleware::new().allow([Method::DELETE]));
+        let mut request = post("/posts/1", "");
+        request
+            .headers_mut()
+            .insert("x-http-method-override", HeaderValue::from_static("PUT"));
+        let error = app.respond(&mut request).await.err().unwrap();
+        assert_eq!(error.status(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,394 @@
+//! Prometheus metrics for HTTP traffic.
+//!
+//! [`MetricsMiddleware`] records every request it sees into an in-process registry, and
+//! [`MetricsMiddleware::metrics_route`] serves that registry in the Prometheus text format.
+//! Wrap the built router so that requests matching no route are counted too:
+//!
+//! ```
+//! use skyzen::{
+//!     middleware::{MetricsMiddleware, WithMiddleware},
+//!     routing::{CreateRouteNode, Route},
+//!     Result,
+//! };
+//!
+//! let metrics = MetricsMiddleware::new();
+//! let router = Route::new((
+//!     "/hello".at(|| async { Result::Ok("world") }),
+//!     metrics.metrics_route("/metrics"),
+//! ))
+//! .build();
+//! let app = WithMiddleware::new(router, metrics);
+//! ```
+//!
+//! Requests are labelled with their method, the [`MatchedPath`] of the route that handled them
+//! (or `unmatched`), and their status class such as `2xx`. Three metric families are exported:
+//!
+//! - `http_requests_total`, a counter of finished requests;
+//! - `http_request_duration_seconds`, a histogram of the time until the response was produced;
+//! - `http_requests_in_flight`, a gauge of requests currently being handled.
+//!
+//! Attaching the middleware with [`Route::middleware`](crate::routing::Route::middleware) instead
+//! also works, but then requests that match no route never reach it.
+
+use std::{
+    collections::BTreeMap,
+    convert::Infallible,
+    fmt::{self, Write},
+    sync::{
+        atomic::{AtomicI64, Ordering},
+        Arc, Mutex, PoisonError,
+    },
+    time::{Duration, Instant},
+};
+
+use http_kit::{middleware::MiddlewareError, Endpoint, HttpError, Method, Middleware};
+
+use crate::{
+    header,
+    routing::{CreateRouteNode, MatchedPath, RouteNode},
+    Body, Request, Response, StatusCode,
+};
+
+/// Upper bounds of the duration histogram buckets, in seconds.
+const BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// The route label of requests that matched no route.
+const UNMATCHED: &str = "unmatched";
+
+/// The content type of the Prometheus text exposition format.
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Records request counts, durations and concurrency, see the [module docs](self).
+///
+/// Clones share the same registry, so the middleware and its
+/// [`metrics_route`](Self::metrics_route) report the same numbers.
+#[derive(Debug, Clone, Default)]
+pub struct MetricsMiddleware {
+    registry: Arc<Registry>,
+}
+
+impl MetricsMiddleware {
+    /// Create a middleware with an empty registry.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A `GET` route at `path` rendering the registry in the Prometheus text format.
+    #[must_use]
+    pub fn metrics_route(&self, path: impl Into<String>) -> RouteNode {
+        path.into().endpoint(
+            Method::GET,
+            MetricsEndpoint {
+                registry: Arc::clone(&self.registry),
+            },
+        )
+    }
+
+    /// Render the registry in the Prometheus text format.
+    #[must_use]
+    pub fn render(&self) -> String {
+        self.registry.render()
+    }
+}
+
+impl Middleware for MetricsMiddleware {
+    type Error = Infallible;
+
+    async fn handle<N: Endpoint>(
+        &mut self,
+        request: &mut Request,
+        mut next: N,
+    ) -> Result<Response, MiddlewareError<N::Error, Self::Error>> {
+        let _in_flight = InFlight::enter(&self.registry);
+        let method = method_label(request.method());
+        let started = Instant::now();
+
+        let result = next.respond(request).await;
+        let status = match &result {
+            Ok(response) => response.status(),
+            Err(error) => error.status(),
+        };
+        let route = request
+            .extensions()
+            .get::<MatchedPath>()
+            .map_or(UNMATCHED, MatchedPath::as_str);
+        self.registry
+            .observe(method, route, status_class(status), started.elapsed());
+
+        result.map_err(MiddlewareError::Endpoint)
+    }
+}
+
+/// Serves the registry; built by [`MetricsMiddleware::metrics_route`].
+#[derive(Debug, Clone)]
+struct MetricsEndpoint {
+    registry: Arc<Registry>,
+}
+
+impl Endpoint for MetricsEndpoint {
+    type Error = Infallible;
+
+    async fn respond(&mut self, _request: &mut Request) -> Result<Response, Self::Error> {
+        let mut response = Response::new(Body::from(self.registry.render()));
+        response.headers_mut().insert(
+            header::CONTENT_TYPE,
+            header::HeaderValue::from_static(CONTENT_TYPE),
+        );
+        Ok(response)
+    }
+}
+
+#[derive(Debug, Default)]
+struct Registry {
+    in_flight: AtomicI64,
+    series: Mutex<BTreeMap<Labels, Histogram>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Labels {
+    method: &'static str,
+    route: String,
+    status: &'static str,
+}
+
+#[derive(Debug, Default)]
+struct Histogram {
+    /// Per-bucket counts; an observation is counted in the first bucket it fits.
+    buckets: [u64; BUCKETS.len()],
+    count: u64,
+    sum: f64,
+}
+
+impl Histogram {
+    fn record(&mut self, seconds: f64) {
+        if let Some(bucket) = BUCKETS.iter().position(|bound| seconds <= *bound) {
+            self.buckets[bucket] += 1;
+        }
+        self.count += 1;
+        self.sum += seconds;
+    }
+}
+
+impl Registry {
+    fn observe(&self, method: &'static str, route: &str, status: &'static str, elapsed: Duration) {
+        let labels = Labels {
+            method,
+            route: route.to_owned(),
+            status,
+        };
+        self.series
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(labels)
+            .or_default()
+            .record(elapsed.as_secs_f64());
+    }
+
+    fn render(&self) -> String {
+        let series = self.series.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut out = String::new();
+        // Writing into a `String` cannot fail.
+        let _ = self.write(&mut out, &series);
+        out
+    }
+
+    fn write(&self, out: &mut String, series: &BTreeMap<Labels, Histogram>) -> fmt::Result {
+        writeln!(
+            out,
+            "# HELP http_requests_in_flight Requests currently being handled."
+        )?;
+        writeln!(out, "# TYPE http_requests_in_flight gauge")?;
+        writeln!(
+            out,
+            "http_requests_in_flight {}",
+            self.in_flight.load(Ordering::Relaxed)
+        )?;
+
+        writeln!(out, "# HELP http_requests_total Requests handled.")?;
+        writeln!(out, "# TYPE http_requests_total counter")?;
+        for (labels, histogram) in series {
+            writeln!(out, "http_requests_total{{{labels}}} {}", histogram.count)?;
+        }
+
+        writeln!(
+            out,
+            "# HELP http_request_duration_seconds Time taken to produce a response."
+        )?;
+        writeln!(out, "# TYPE http_request_duration_seconds histogram")?;
+        for (labels, histogram) in series {
+            let mut cumulative = 0;
+            for (bound, count) in BUCKETS.iter().zip(histogram.buckets) {
+                cumulative += count;
+                writeln!(
+                    out,
+                    "http_request_duration_seconds_bucket{{{labels},le=\"{bound}\"}} {cumulative}"
+                )?;
+            }
+            writeln!(
+                out,
+                "http_request_duration_seconds_bucket{{{labels},le=\"+Inf\"}} {}",
+                histogram.count
+            )?;
+            writeln!(
+                out,
+                "http_request_duration_seconds_sum{{{labels}}} {}",
+                histogram.sum
+            )?;
+            writeln!(
+                out,
+                "http_request_duration_seconds_count{{{labels}}} {}",
+                histogram.count
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Labels {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "method=\"{}\",route=\"", self.method)?;
+        for c in self.route.chars() {
+            match c {
+                '\\' => f.write_str("\\\\")?,
+                '"' => f.write_str("\\\"")?,
+                '\n' => f.write_str("\\n")?,
+                c => f.write_char(c)?,
+            }
+        }
+        write!(f, "\",status=\"{}\"", self.status)
+    }
+}
+
+/// Counts a request as in flight until dropped, so cancelled requests are released too.
+struct InFlight<'a>(&'a Registry);
+
+impl<'a> InFlight<'a> {
+    fn enter(registry: &'a Registry) -> Self {
+        registry.in_flight.fetch_add(1, Ordering::Relaxed);
+        Self(registry)
+    }
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.0.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// The method label; extension methods share one label to bound the number of series.
+const fn method_label(method: &Method) -> &'static str {
+    match *method {
+        Method::GET => "GET",
+        Method::POST => "POST",
+        Method::PUT => "PUT",
+        Method::DELETE => "DELETE",
+        Method::PATCH => "PATCH",
+        Method::HEAD => "HEAD",
+        Method::OPTIONS => "OPTIONS",
+        Method::CONNECT => "CONNECT",
+        Method::TRACE => "TRACE",
+        _ => "OTHER",
+    }
+}
+
+const fn status_class(status: StatusCode) -> &'static str {
+    match status.as_u16() / 100 {
+        1 => "1xx",
+        2 => "2xx",
+        3 => "3xx",
+        4 => "4xx",
+        _ => "5xx",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MetricsMiddleware;
+    use crate::{
+        header,
+        middleware::WithMiddleware,
+        routing::{CreateRouteNode, Params, Route},
+        Body, Endpoint, Error, Method, Request, Result, StatusCode,
+    };
+
+    fn request(method: Method, path: &str) -> Request {
+        let mut request = Request::new(Body::empty());
+        *request.method_mut() = method;
+        *request.uri_mut() = path.parse().unwrap();
+        request
+    }
+
+    #[tokio::test]
+    async fn exposes_request_metrics() {
+        let metrics = MetricsMiddleware::new();
+        let router = Route::new((
+            "/users/{id}"
+                .at(|params: Params| async move { Result::Ok(params.get("id")?.to_owned()) }),
+            "/fail".post(|| async { Result::<&str>::Err(Error::msg("boom")) }),
+            metrics.metrics_route("/metrics"),
+        ))
+        .build();
+        let mut app = WithMiddleware::new(router, metrics);
+
+        for path in ["/users/1", "/users/2", "/missing"] {
+            app.respond(&mut request(Method::GET, path)).await.unwrap();
+        }
+        let response = app
+            .respond(&mut request(Method::POST, "/fail"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+        let response = app
+            .respond(&mut request(Method::GET, "/metrics"))
+            .await
+            .unwrap();
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "text/plain; version=0.0.4; charset=utf-8"
+        );
+        let body = response.into_body().into_string().await.unwrap();
+        let lines = body.as_str().lines().collect::<Vec<_>>();
+        for expected in [
+            "http_requests_in_flight 1",
+            r#"http_requests_total{method="GET",route="/users/{id}",status="2xx"} 2"#,
+            r#"http_requests_total{method="GET",route="unmatched",status="4xx"} 1"#,
+            r#"http_requests_total{method="POST",route="/fail",status="5xx"} 1"#,
+            r#"http_request_duration_seconds_bucket{method="GET",route="/users/{id}",status="2xx",le="+Inf"} 2"#,
+            r#"http_request_duration_seconds_count{method="GET",route="/users/{id}",status="2xx"} 2"#,
+        ] {
+            assert!(lines.contains(&expected), "missing `{expected}` in\n{body}");
+        }
+
+        let body = app
+            .respond(&mut request(Method::GET, "/metrics"))
+            .await
+            .unwrap()
+            .into_body()
+            .into_string()
+            .await
+            .unwrap();
+        assert!(body
+            .as_str()
+            .lines()
+            .any(|line| line
+                == r#"http_requests_total{method="GET",route="/metrics",status="2xx"} 1"#));
+    }
+
+    #[test]
+    fn escapes_label_values() {
+        let metrics = MetricsMiddleware::new();
+        metrics.registry.observe(
+            "GET",
+            "/a\"b\\c",
+            "2xx",
+            std::time::Duration::from_millis(1),
+        );
+        assert!(metrics
+            .render()
+            .contains(r#"http_requests_total{method="GET",route="/a\"b\\c",status="2xx"} 1"#));
+    }
+}
@@ -23,6 +23,45 @@
 //! ```
 mod error_handling;
 
+#[cfg(not(target_arch = "wasm32"))]
+pub mod access_log;
+#[cfg(all(feature = "metrics", not(target_arch = "wasm32")))]
+pub mod metrics;
+
 pub mod auth;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod cache;
+#[cfg(feature = "compression")]
+pub mod compression;
+pub mod csrf;
+#[cfg(feature = "compression")]
+pub mod decompression;
+pub mod expect_continue;
+pub mod method_override;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod server_timing;
+pub mod trace;
+#[cfg(not(target_arch = "wasm32"))]
+pub use access_log::{AccessLogMiddleware, AccessLogRecord, QueryRule, StatusClass};
+#[cfg(not(target_arch = "wasm32"))]
+pub use cache::{CacheError, CacheMiddleware, CacheStore, CachedResponse, MemoryStore};
+#[cfg(feature = "compression")]
+pub use compression::{
+    CompressionEncoding, CompressionError, CompressionLevel, CompressionMiddleware,
+};
+pub use csrf::{CsrfError, CsrfMiddleware, CsrfToken, MissingCsrfToken};
+#[cfg(feature = "compression")]
+pub use decompression::{RequestDecompressionError, RequestDecompressionMiddleware};
 pub use error_handling::ErrorHandlingMiddleware;
+pub use expect_continue::ExpectContinueMiddleware;
+pub use http_kit::endpoint::WithMiddleware;
 pub use http_kit::middleware::Middleware;
+pub use method_override::{MethodOverrideError, MethodOverrideMiddleware, OriginalMethod};
+#[cfg(all(feature = "metrics", not(target_arch = "wasm32")))]
+pub use metrics::MetricsMiddleware;
+#[cfg(not(target_arch = "wasm32"))]
+pub use server_timing::{MissingServerTiming, ServerTiming, ServerTimingMiddleware};
+pub use trace::{MissingTraceContext, TraceContext, TraceMiddleware};
+
+#[cfg(all(feature = "tower", not(target_arch = "wasm32")))]
+pub use crate::tower::{TowerLayer, TowerNext, TowerResponse};
@@ -0,0 +1,323 @@
+//! `Server-Timing` response headers.
+//!
+//! [`ServerTimingMiddleware`] measures how long the rest of the stack takes to produce a response
+//! and reports it as the `app` metric of a
+//! [`Server-Timing`](https://www.w3.org/TR/server-timing/) header, which browser developer tools
+//! show next to the request. Handlers add their own metrics through the [`ServerTiming`]
+//! extractor:
+//!
+//! ```
+//! use std::time::Instant;
+//!
+//! use skyzen::{
+//!     middleware::{ServerTiming, ServerTimingMiddleware, WithMiddleware},
+//!     routing::{CreateRouteNode, Route},
+//!     Result,
+//! };
+//!
+//! async fn handler(timing: ServerTiming) -> Result<&'static str> {
+//!     let started = Instant::now();
+//!     // Query the database...
+//!     timing.record_with("db", "user lookup", started.elapsed());
+//!     Ok("done")
+//! }
+//!
+//! let router = Route::new(("/".at(handler),)).build();
+//! let app = WithMiddleware::new(router, ServerTimingMiddleware::new());
+//! ```
+//!
+//! The header is written together with the response headers, so metrics recorded later, for
+//! example while a streaming body is produced, are dropped.
+
+use std::{
+    convert::Infallible,
+    fmt::{self, Write},
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+use http_kit::{http_error, middleware::MiddlewareError, Endpoint, Middleware};
+
+use crate::{
+    extract::Extractor,
+    header::{HeaderName, HeaderValue},
+    Request, Response, StatusCode,
+};
+
+const SERVER_TIMING: HeaderName = HeaderName::from_static("server-timing");
+
+/// Collects the metrics of one request; clones share the same metrics.
+#[derive(Debug, Clone, Default)]
+pub struct ServerTiming {
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    metrics: Vec<Metric>,
+    /// Set once the header has been written; later metrics are dropped.
+    sealed: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Metric {
+    name: String,
+    description: Option<String>,
+    duration: Option<Duration>,
+}
+
+impl ServerTiming {
+    /// Record the metric `name` with a duration.
+    ///
+    /// `name` must be an HTTP token such as `db` or `cache-miss`; other names are ignored.
+    pub fn record(&self, name: impl Into<String>, duration: Duration) {
+        self.push(Metric {
+            name: name.into(),
+            description: None,
+            duration: Some(duration),
+        });
+    }
+
+    /// Record the metric `name` with a human-readable description and a duration.
+    pub fn record_with(
+        &self,
+        name: impl Into<String>,
+        description: impl Into<String>,
+        duration: Duration,
+    ) {
+        self.push(Metric {
+            name: name.into(),
+            description: Some(description.into()),
+            duration: Some(duration),
+        });
+    }
+
+    fn push(&self, metric: Metric) {
+        if !is_token(&metric.name) {
+            tracing::debug!(
+                name = metric.name,
+                "ignoring invalid Server-Timing metric name"
+            );
+            return;
+        }
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        if state.sealed {
+            tracing::debug!(
+                name = metric.name,
+                "ignoring Server-Timing metric recorded after the response headers"
+            );
+        } else {
+            state.metrics.push(metric);
+        }
+    }
+
+    /// Stop collecting and render the header value.
+    fn seal(&self, total: Metric) -> String {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        state.sealed = true;
+        state.metrics.push(total);
+        render(&state.metrics)
+    }
+}
+
+http_error!(
+    /// The request was not handled by [`ServerTimingMiddleware`], so it has no [`ServerTiming`].
+    pub MissingServerTiming,
+    StatusCode::INTERNAL_SERVER_ERROR,
+    "ServerTimingMiddleware is not installed"
+);
+
+impl Extractor for ServerTiming {
+    type Error = MissingServerTiming;
+    async fn extract(request: &mut Request) -> Result<Self, Self::Error> {
+        request
+            .extensions()
+            .get::<Self>()
+            .cloned()
+            .ok_or_else(MissingServerTiming::new)
+    }
+}
+
+/// Adds a `Server-Timing` header to responses, see the [module docs](self).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ServerTimingMiddleware;
+
+impl ServerTimingMiddleware {
+    /// Report the time taken to produce each response as the `app` metric.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self
+    }
+}
+
+impl Middleware for ServerTimingMiddleware {
+    type Error = Infallible;
+
+    async fn handle<N: Endpoint>(
+        &mut self,
+        request: &mut Request,
+        mut next: N,
+    ) -> Result<Response, MiddlewareError<N::Error, Self::Error>> {
+        let timing = ServerTiming::default();
+        request.extensions_mut().insert(timing.clone());
+        let started = Instant::now();
+
+        let mut response = next
+            .respond(request)
+            .await
+            .map_err(MiddlewareError::Endpoint)?;
+
+        let value = timing.seal(Metric {
+            name: "app".to_owned(),
+            description: None,
+            duration: Some(started.elapsed()),
+        });
+        if let Ok(value) = HeaderValue::from_str(&value) {
+            response.headers_mut().append(SERVER_TIMING, value);
+        }
+        Ok(response)
+    }
+}
+
+fn render(metrics: &[Metric]) -> String {
+    let mut out = String::new();
+    for (index, metric) in metrics.iter().enumerate() {
+        if index > 0 {
+            out.push_str(", ");
+        }
+        // Writing into a `String` cannot fail.
+        let _ = write!(out, "{metric}");
+    }
+    out
+}
+
+impl fmt::Display for Metric {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name)?;
+        if let Some(description) = &self.description {
+            f.write_str(";desc=")?;
+            if !description.is_empty() && is_token(description) {
+                f.write_str(description)?;
+            } else {
+                f.write_char('"')?;
+                for c in description.chars() {
+                    match c {
+                        '"' | '\\' => write!(f, "\\{c}")?,
+                        ' ' | '\t' | '!'..='~' => f.write_char(c)?,
+                        // Header values cannot carry control characters or non-ASCII text.
+                        _ => f.write_char('?')?,
+                    }
+                }
+                f.write_char('"')?;
+            }
+        }
+        if let Some(duration) = self.duration {
+            write!(f, ";dur={}", Millis(duration))?;
+        }
+        Ok(())
+    }
+}
+
+/// A duration in milliseconds, with at most three decimals and no trailing zeros.
+struct Millis(Duration);
+
+impl fmt::Display for Millis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let micros = self.0.as_micros();
+        let (whole, fraction) = (micros / 1000, micros % 1000);
+        write!(f, "{whole}")?;
+        if fraction > 0 {
+            let fraction = format!("{fraction:03}");
+            write!(f, ".{}", fraction.trim_end_matches('0'))?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether `value` is an HTTP token (RFC 9110, section 5.6.2).
+fn is_token(value: &str) -> bool {
+    !value.is_empty()
+        && value
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&byte))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{render, Metric, ServerTiming, ServerTimingMiddleware, SERVER_TIMING};
+    use crate::{
+        header::HeaderValue,
+        middleware::WithMiddleware,
+        routing::{CreateRouteNode, Route},
+        Body, Endpoint, Request, Result,
+    };
+
+    fn metric(name: &str, description: Option<&str>, micros: Option<u64>) -> Metric {
+        Metric {
+            name: name.to_owned(),
+            description: description.map(ToOwned::to_owned),
+            duration: micros.map(Duration::from_micros),
+        }
+    }
+
+    #[test]
+    fn formats_metrics_per_spec() {
+        assert_eq!(
+            render(&[
+                metric("db", Some("user lookup"), Some(12_300)),
+                metric("cache", Some("hit"), Some(42)),
+                metric("miss", None, None),
+                metric("app", None, Some(5_000)),
+            ]),
+            r#"db;desc="user lookup";dur=12.3, cache;desc=hit;dur=0.042, miss, app;dur=5"#
+        );
+        assert_eq!(
+            render(&[metric("q", Some("say \"hi\" \\ é\n"), Some(1))]),
+            r#"q;desc="say \"hi\" \\ ??";dur=0.001"#
+        );
+        assert_eq!(render(&[metric("e", Some(""), None)]), r#"e;desc="""#);
+    }
+
+    #[test]
+    fn drops_metrics_after_the_header_is_written() {
+        let timing = ServerTiming::default();
+        timing.record("db", Duration::from_millis(2));
+        timing.record("not a token", Duration::from_millis(1));
+        let header = timing.seal(metric("app", None, Some(3_000)));
+        timing.record("late", Duration::from_millis(1));
+        assert_eq!(header, "db;dur=2, app;dur=3");
+        assert_eq!(timing.state.lock().unwrap().metrics.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn reports_handler_metrics() {
+        let router = Route::new(("/".at(|timing: ServerTiming| async move {
+            timing.record_with("db", "query", Duration::from_millis(7));
+            Result::Ok((
+                "ok",
+                (SERVER_TIMING, HeaderValue::from_static("edge;dur=1")),
+            ))
+        }),))
+        .build();
+        let mut app = WithMiddleware::new(router, ServerTimingMiddleware::new());
+
+        let mut request = Request::new(Body::empty());
+        *request.uri_mut() = "/".parse().unwrap();
+        let response = app.respond(&mut request).await.unwrap();
+        let values = response
+            .headers()
+            .get_all(SERVER_TIMING)
+            .iter()
+            .map(|value| value.to_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(values.len(), 2);
+        assert_eq!(values[0], "edge;dur=1");
+        assert!(
+            values[1].starts_with("db;desc=query;dur=7, app;dur="),
+            "{}",
+            values[1]
+        );
+    }
+}
@@ -0,0 +1,462 @@
+//! Per-request tracing spans with trace-context propagation.
+//!
+//! [`TraceMiddleware`] runs each request inside a `request` span carrying the fields `method`,
+//! `route`, `status`, `trace_id`, `span_id` and `parent_span_id`; `route` and `status` are
+//! recorded just before the span closes. The trace id is taken from an incoming W3C
+//! [`traceparent`](https://www.w3.org/TR/trace-context/) header, or optionally from
+//! [B3](https://github.com/openzipkin/b3-propagation) headers, and a new one is generated when
+//! neither is present. Every request gets a fresh span id, which is echoed in the `traceparent`
+//! response header.
+//!
+//! Handlers read the ids with the [`TraceContext`] extractor, for example to forward them to
+//! downstream services:
+//!
+//! ```
+//! use skyzen::{
+//!     middleware::{TraceContext, TraceMiddleware, WithMiddleware},
+//!     routing::{CreateRouteNode, Route},
+//!     Result,
+//! };
+//!
+//! async fn handler(trace: TraceContext) -> Result<String> {
+//!     // Send `trace.traceparent()` as the `traceparent` header of outgoing requests.
+//!     Ok(trace.traceparent())
+//! }
+//!
+//! let router = Route::new(("/".at(handler),)).build();
+//! let app = WithMiddleware::new(router, TraceMiddleware::new().b3(true));
+//! ```
+//!
+//! No OpenTelemetry dependency is needed. The spans are ordinary `tracing` spans, so they are
+//! exported by [`tracing-opentelemetry`](https://docs.rs/tracing-opentelemetry) like any other;
+//! to join the remote trace there, set the parent of [`tracing::Span::current`] from the ids in
+//! [`TraceContext`] with `OpenTelemetrySpanExt::set_parent`.
+
+use std::{convert::Infallible, fmt};
+
+use http_kit::{http_error, middleware::MiddlewareError, Endpoint, HttpError, Middleware};
+use tracing::{field, Instrument};
+
+use crate::{
+    extract::Extractor,
+    header::{HeaderMap, HeaderName, HeaderValue},
+    routing::MatchedPath,
+    Request, Response, StatusCode,
+};
+
+const TRACEPARENT: HeaderName = HeaderName::from_static("traceparent");
+const TRACESTATE: HeaderName = HeaderName::from_static("tracestate");
+const B3: HeaderName = HeaderName::from_static("b3");
+const X_B3_TRACE_ID: HeaderName = HeaderName::from_static("x-b3-traceid");
+const X_B3_SPAN_ID: HeaderName = HeaderName::from_static("x-b3-spanid");
+const X_B3_SAMPLED: HeaderName = HeaderName::from_static("x-b3-sampled");
+const X_B3_FLAGS: HeaderName = HeaderName::from_static("x-b3-flags");
+
+/// The trace a request belongs to, and the span the server handles it in.
+///
+/// Inserted into the request extensions by [`TraceMiddleware`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceContext {
+    trace_id: u128,
+    span_id: u64,
+    parent_span_id: Option<u64>,
+    sampled: bool,
+    trace_state: Option<String>,
+}
+
+impl TraceContext {
+    /// Start a new trace, sampled, with no parent.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            trace_id: random_id(),
+            span_id: random_span_id(),
+            parent_span_id: None,
+            sampled: true,
+            trace_state: None,
+        }
+    }
+
+    /// Continue the trace described by `headers`, or start a new one.
+    ///
+    /// A valid `traceparent` header wins; B3 headers are only read when `b3` is true.
+    #[must_use]
+    pub fn from_headers(headers: &HeaderMap, b3: bool) -> Self {
+        let traceparent = header_str(headers, &TRACEPARENT).and_then(parse_traceparent);
+        let trace_state = traceparent
+            .and_then(|_| header_str(headers, &TRACESTATE))
+            .map(str::to_owned);
+        let parent = traceparent.or_else(|| b3.then(|| parse_b3(headers)).flatten());
+        let Some((trace_id, parent_span_id, sampled)) = parent else {
+            return Self::new();
+        };
+
+        Self {
+            trace_id,
+            span_id: random_span_id(),
+            parent_span_id: Some(parent_span_id),
+            sampled,
+            trace_state,
+        }
+    }
+
+    /// The 128-bit trace id.
+    #[must_use]
+    pub const fn trace_id(&self) -> u128 {
+        self.trace_id
+    }
+
+    /// The id of the span handling this request.
+    #[must_use]
+    pub const fn span_id(&self) -> u64 {
+        self.span_id
+    }
+
+    /// The id of the caller's span, if the request continued an existing trace.
+    #[must_use]
+    pub const fn parent_span_id(&self) -> Option<u64> {
+        self.parent_span_id
+    }
+
+    /// Whether the caller asked for the trace to be recorded.
+    #[must_use]
+    pub const fn sampled(&self) -> bool {
+        self.sampled
+    }
+
+    /// The incoming `tracestate` header, to be forwarded unchanged.
+    #[must_use]
+    pub fn trace_state(&self) -> Option<&str> {
+        self.trace_state.as_deref()
+    }
+
+    /// The `traceparent` header value naming this request's span as the parent.
+    #[must_use]
+    pub fn traceparent(&self) -> String {
+        format!(
+            "00-{:032x}-{:016x}-{:02x}",
+            self.trace_id,
+            self.span_id,
+            u8::from(self.sampled)
+        )
+    }
+
+    /// The single-header `b3` value naming this request's span as the parent.
+    #[must_use]
+    pub fn b3(&self) -> String {
+        format!(
+            "{:032x}-{:016x}-{}",
+            self.trace_id,
+            self.span_id,
+            u8::from(self.sampled)
+        )
+    }
+}
+
+impl fmt::Display for TraceContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.traceparent())
+    }
+}
+
+impl Default for TraceContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+http_error!(
+    /// The request was not handled by [`TraceMiddleware`], so it has no [`TraceContext`].
+    pub MissingTraceContext,
+    StatusCode::INTERNAL_SERVER_ERROR,
+    "TraceMiddleware is not installed"
+);
+
+impl Extractor for TraceContext {
+    type Error = MissingTraceContext;
+    async fn extract(request: &mut Request) -> Result<Self, Self::Error> {
+        request
+            .extensions()
+            .get::<Self>()
+            .cloned()
+            .ok_or_else(MissingTraceContext::new)
+    }
+}
+
+/// Wraps requests in tracing spans and propagates trace context, see the [module docs](self).
+#[derive(Debug, Clone, Default)]
+pub struct TraceMiddleware {
+    b3: bool,
+}
+
+impl TraceMiddleware {
+    /// Read and write W3C `traceparent` headers only.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { b3: false }
+    }
+
+    /// Also accept B3 headers, single or multi-header, when `traceparent` is absent, and echo
+    /// a `b3` header on responses.
+    #[must_use]
+    pub const fn b3(mut self, enabled: bool) -> Self {
+        self.b3 = enabled;
+        self
+    }
+}
+
+impl Middleware for TraceMiddleware {
+    type Error = Infallible;
+
+    async fn handle<N: Endpoint>(
+        &mut self,
+        request: &mut Request,
+        mut next: N,
+    ) -> Result<Response, MiddlewareError<N::Error, Self::Error>> {
+        let context = TraceContext::from_headers(request.headers(), self.b3);
+        let span = tracing::info_span!(
+            "request",
+            method = request.method().as_str(),
+            route = field::Empty,
+            status = field::Empty,
+            trace_id = %format_args!("{:032x}", context.trace_id),
+            span_id = %format_args!("{:016x}", context.span_id),
+            parent_span_id = context.parent_span_id.map(|id| field::display(format!("{id:016x}"))),
+        );
+        request.extensions_mut().insert(context.clone());
+
+        let mut result = next.respond(request).instrument(span.clone()).await;
+
+        if let Some(route) = request.extensions().get::<MatchedPath>() {
+            span.record("route", route.as_str());
+        }
+        let status = match &mut result {
+            Ok(response) => {
+                let headers = response.headers_mut();
+                headers.insert(TRACEPARENT, header_value(&context.traceparent()));
+                if self.b3 {
+                    headers.insert(B3, header_value(&context.b3()));
+                }
+                response.status()
+            }
+            Err(error) => error.status(),
+        };
+        span.record("status", status.as_u16());
+
+        result.map_err(MiddlewareError::Endpoint)
+    }
+}
+
+fn header_str<'a>(headers: &'a HeaderMap, name: &HeaderName) -> Option<&'a str> {
+    headers.get(name)?.to_str().ok()
+}
+
+fn header_value(value: &str) -> HeaderValue {
+    HeaderValue::from_str(value).expect("trace headers are hex digits and dashes")
+}
+
+/// Parse `version-trace_id-parent_id-flags`, returning `(trace_id, parent_id, sampled)`.
+fn parse_traceparent(value: &str) -> Option<(u128, u64, bool)> {
+    let mut parts = value.trim().split('-');
+    let version = parts.next().filter(|part| part.len() == 2)?;
+    let trace_id = parts.next().filter(|part| part.len() == 32)?;
+    let parent_id = parts.next().filter(|part| part.len() == 16)?;
+    let flags = parts.next().filter(|part| part.len() == 2)?;
+    // Version 00 has exactly four fields; later versions may append more.
+    if version == "ff" || (version == "00" && parts.next().is_some()) {
+        return None;
+    }
+    hex(version)?;
+
+    let trace_id = hex(trace_id).filter(|id| *id != 0)?;
+    let parent_id = span_id(parent_id)?;
+    let sampled = hex(flags)? & 1 == 1;
+    Some((trace_id, parent_id, sampled))
+}
+
+/// Parse the `b3` header, falling back to the `X-B3-*` headers.
+fn parse_b3(headers: &HeaderMap) -> Option<(u128, u64, bool)> {
+    if let Some(value) = header_str(headers, &B3) {
+        let mut parts = value.trim().split('-');
+        let trace_id = b3_trace_id(parts.next()?)?;
+        let span_id = span_id(parts.next()?)?;
+        let sampled = parts.next().map_or(Some(true), b3_sampled)?;
+        return Some((trace_id, span_id, sampled));
+    }
+
+    let trace_id = b3_trace_id(header_str(headers, &X_B3_TRACE_ID)?)?;
+    let span_id = span_id(header_str(headers, &X_B3_SPAN_ID)?)?;
+    let debug = header_str(headers, &X_B3_FLAGS) == Some("1");
+    let sampled = header_str(headers, &X_B3_SAMPLED).map_or(Some(true), b3_sampled)?;
+    Some((trace_id, span_id, debug || sampled))
+}
+
+/// B3 trace ids are either 64 or 128 bits.
+fn b3_trace_id(value: &str) -> Option<u128> {
+    match value.len() {
+        16 | 32 => hex(value).filter(|id| *id != 0),
+        _ => None,
+    }
+}
+
+fn b3_sampled(value: &str) -> Option<bool> {
+    match value {
+        "1" | "d" | "true" => Some(true),
+        "0" | "false" => Some(false),
+        _ => None,
+    }
+}
+
+/// A non-zero span id of exactly 16 hex digits.
+fn span_id(value: &str) -> Option<u64> {
+    if value.len() != 16 {
+        return None;
+    }
+    hex(value)
+        .and_then(|id| u64::try_from(id).ok())
+        .filter(|id| *id != 0)
+}
+
+/// Parse up to 32 hex digits.
+fn hex(value: &str) -> Option<u128> {
+    // `from_str_radix` accepts a leading `+`, which is not valid in trace headers.
+    if value.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        u128::from_str_radix(value, 16).ok()
+    } else {
+        None
+    }
+}
+
+fn random_id() -> u128 {
+    (u128::from(random_span_id()) << 64) | u128::from(random_span_id())
+}
+
+/// A random non-zero span id.
+#[cfg(not(target_arch = "wasm32"))]
+fn random_span_id() -> u64 {
+    fastrand::u64(1..)
+}
+
+/// A random non-zero span id.
+#[cfg(target_arch = "wasm32")]
+fn random_span_id() -> u64 {
+    // `Math.random` yields at least 32 random bits per call in every engine Workers run on.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let word = || (js_sys::Math::random() * 4_294_967_296.0) as u64;
+    ((word() << 32) | word()).max(1)
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::{parse_traceparent, TraceContext, TraceMiddleware};
+    use crate::{
+        header::HeaderMap,
+        middleware::WithMiddleware,
+        routing::{CreateRouteNode, Route},
+        Body, Endpoint, Request, Result,
+    };
+
+    const TRACE_ID: u128 = 0x4bf9_2f35_77b3_4da6_a3ce_929d_0e0e_4736;
+    const PARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.parse().unwrap(), value.parse().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn parses_traceparent() {
+        assert_eq!(
+            parse_traceparent(PARENT),
+            Some((TRACE_ID, 0x00f0_67aa_0ba9_02b7, true))
+        );
+        for invalid in [
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+            "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra",
+            "00-4bf92f3577b34da6a3ce929d0e0e473g-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-+0f067aa0ba902b7-01",
+        ] {
+            assert_eq!(parse_traceparent(invalid), None, "{invalid}");
+        }
+        assert!(parse_traceparent(
+            "01-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-00-future"
+        )
+        .is_some());
+    }
+
+    #[test]
+    fn continues_incoming_traces() {
+        let context = TraceContext::from_headers(
+            &headers(&[("traceparent", PARENT), ("tracestate", "vendor=1")]),
+            false,
+        );
+        assert_eq!(context.trace_id(), TRACE_ID);
+        assert_eq!(context.parent_span_id(), Some(0x00f0_67aa_0ba9_02b7));
+        assert_ne!(context.span_id(), 0x00f0_67aa_0ba9_02b7);
+        assert_eq!(context.trace_state(), Some("vendor=1"));
+        assert!(context.sampled());
+
+        let fresh = TraceContext::from_headers(&HeaderMap::new(), false);
+        assert_eq!(fresh.parent_span_id(), None);
+        assert_ne!(fresh.trace_id(), 0);
+    }
+
+    #[test]
+    fn reads_b3_headers_when_enabled() {
+        let single = headers(&[("b3", "80f198ee56343ba864fe8b2a57d3eff7-e457b5a2e4d86bd1-0")]);
+        assert_eq!(
+            TraceContext::from_headers(&single, false).parent_span_id(),
+            None
+        );
+        let context = TraceContext::from_headers(&single, true);
+        assert_eq!(
+            context.trace_id(),
+            0x80f1_98ee_5634_3ba8_64fe_8b2a_57d3_eff7
+        );
+        assert_eq!(context.parent_span_id(), Some(0xe457_b5a2_e4d8_6bd1));
+        assert!(!context.sampled());
+
+        let multi = headers(&[
+            ("x-b3-traceid", "a3ce929d0e0e4736"),
+            ("x-b3-spanid", "00f067aa0ba902b7"),
+            ("x-b3-sampled", "1"),
+        ]);
+        let context = TraceContext::from_headers(&multi, true);
+        assert_eq!(context.trace_id(), 0xa3ce_929d_0e0e_4736);
+        assert!(context.sampled());
+    }
+
+    #[tokio::test]
+    async fn propagates_trace_context() {
+        let router = Route::new((
+            "/".at(|trace: TraceContext| async move { Result::Ok(trace.traceparent()) }),
+        ))
+        .build();
+        let mut app = WithMiddleware::new(router, TraceMiddleware::new().b3(true));
+
+        let mut request = Request::new(Body::empty());
+        *request.uri_mut() = "/".parse().unwrap();
+        request
+            .headers_mut()
+            .insert("traceparent", PARENT.parse().unwrap());
+        let response = app.respond(&mut request).await.unwrap();
+
+        let echoed = response.headers()["traceparent"]
+            .to_str()
+            .unwrap()
+            .to_owned();
+        assert!(echoed.starts_with("00-4bf92f3577b34da6a3ce929d0e0e4736-"));
+        assert!(echoed.ends_with("-01"));
+        assert_ne!(echoed, PARENT);
+        assert!(response.headers()["b3"]
+            .to_str()
+            .unwrap()
+            .starts_with("4bf92f3577b34da6a3ce929d0e0e4736-"));
+        let body = response.into_body().into_string().await.unwrap();
+        assert_eq!(body, echoed);
+    }
+}
@@ -19,6 +19,12 @@ use crate::{responder::PrettyJson, utils::Json};
 #[cfg(feature = "form")]
 use crate::utils::Form;
 
+#[cfg(feature = "cbor")]
+use crate::utils::Cbor;
+
+#[cfg(feature = "msgpack")]
+use crate::utils::MsgPack;
+
 fn string_schema(description: &'static str) -> SchemaRef {
     RefOr::T(Schema::Object(
         ObjectBuilder::new()
@@ -147,6 +153,46 @@ where
     }
 }
 
+#[cfg(feature = "cbor")]
+impl<T> utoipa::PartialSchema for Cbor<T>
+where
+    T: utoipa::ToSchema + 'static + Send + Sync,
+{
+    fn schema() -> SchemaRef {
+        <T as utoipa::PartialSchema>::schema()
+    }
+}
+
+#[cfg(feature = "cbor")]
+impl<T> utoipa::ToSchema for Cbor<T>
+where
+    T: utoipa::ToSchema + 'static + Send + Sync,
+{
+    fn schemas(schemas: &mut Vec<(String, SchemaRef)>) {
+        T::schemas(schemas);
+    }
+}
+
+#[cfg(feature = "msgpack")]
+impl<T> utoipa::PartialSchema for MsgPack<T>
+where
+    T: utoipa::ToSchema + 'static + Send + Sync,
+{
+    fn schema() -> SchemaRef {
+        <T as utoipa::PartialSchema>::schema()
+    }
+}
+
+#[cfg(feature = "msgpack")]
+impl<T> utoipa::ToSchema for MsgPack<T>
+where
+    T: utoipa::ToSchema + 'static + Send + Sync,
+{
+    fn schemas(schemas: &mut Vec<(String, SchemaRef)>) {
+        T::schemas(schemas);
+    }
+}
+
 impl<T> utoipa::PartialSchema for State<T>
 where
     T: Clone + Send + Sync + 'static,
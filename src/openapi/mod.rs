@@ -1,6 +1,47 @@
 //! OpenAPI helpers powered by `utoipa` schemas.
-
-use std::collections::BTreeMap;
+//!
+//! Handlers annotated with `#[skyzen::openapi]` are collected by [`Route::openapi`] in debug
+//! builds. Describe the published API on the returned [`OpenApi`] before serving it: set the title
+//! with [`OpenApi::with_info`], list servers with [`OpenApi::with_server`], register security
+//! schemes with [`OpenApi::add_security_scheme`] and require one everywhere with
+//! [`OpenApi::secure_all`]. A handler picks another scheme with
+//! `#[skyzen::openapi(security = "...")]`, or opts out with `security = ""`.
+//!
+//! ## Publishing a secured API
+//! ```no_run
+//! use skyzen::{
+//!     openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme},
+//!     routing::{CreateRouteNode, Route},
+//!     Result,
+//! };
+//!
+//! /// Report service health.
+//! #[skyzen::openapi(security = "")]
+//! async fn health() -> Result<&'static str> {
+//!     Ok("ok")
+//! }
+//!
+//! /// List the caller's orders.
+//! #[skyzen::openapi]
+//! async fn orders() -> Result<&'static str> {
+//!     Ok("[]")
+//! }
+//!
+//! let api = Route::new(("/health".at(health), "/orders".at(orders)));
+//! let docs = api
+//!     .openapi()
+//!     .with_info("Shop API", "1.0.0", "Orders and payments")
+//!     .with_server("https://api.example.com", "Production")
+//!     .add_security_scheme(
+//!         "bearer",
+//!         SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+//!     )
+//!     .secure_all("bearer")
+//!     .routes("/docs");
+//! let router = Route::new(("".route(api), docs)).build();
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet};
 use std::{
     fmt::{self, Debug},
     sync::Arc,
@@ -12,14 +53,23 @@ use crate::{
     routing::{IntoRouteNode, RouteNode},
     Body, Endpoint, Request, Response, Route,
 };
+use bytes::Bytes;
 use http_kit::{header, http_error, Method, StatusCode};
 use utoipa::openapi::{
     content::Content,
     info::Info,
-    path::{HttpMethod, Operation, OperationBuilder, PathItemBuilder, Paths, PathsBuilder},
+    path::{
+        HttpMethod, Operation, OperationBuilder, Parameter, ParameterBuilder, ParameterIn,
+        ParameterStyle, PathItemBuilder, Paths, PathsBuilder,
+    },
     request_body::RequestBodyBuilder,
     response::{ResponseBuilder, ResponsesBuilder},
-    schema::{ComponentsBuilder, ObjectBuilder, Schema, SchemaType, Type},
+    schema::{
+        AdditionalProperties, ComponentsBuilder, Object, ObjectBuilder, Schema, SchemaType, Type,
+    },
+    security::{SecurityRequirement, SecurityScheme},
+    server::Server,
+    tag::Tag,
     Deprecated, OpenApi as UtoipaSpec, RefOr, Required,
 };
 use utoipa_redoc::Redoc;
@@ -27,8 +77,13 @@ use utoipa_redoc::Redoc;
 /// `OpenAPI` schema reference type alias.
 pub type SchemaRef = RefOr<Schema>;
 
+/// Security scheme types accepted by [`OpenApi::add_security_scheme`].
+pub use utoipa::openapi::security;
+
 #[cfg(feature = "openapi")]
-pub use skyzen_core::openapi::{ExtractorSchema, ResponseSchema, SchemaCollector};
+pub use skyzen_core::openapi::{
+    ExtractorSchema, ParameterLocation, ResponseSchema, SchemaCollector,
+};
 
 #[cfg(not(feature = "openapi"))]
 /// Schema information captured for an extractor argument (stubbed when `openapi` is disabled).
@@ -38,6 +93,30 @@ pub struct ExtractorSchema {
     pub content_type: Option<&'static str>,
     /// JSON schema describing the extractor payload.
     pub schema: Option<SchemaRef>,
+    /// Part of the request the extractor reads.
+    pub location: ParameterLocation,
+    /// Whether the request must carry the parameter; `false` for optional extractors.
+    pub required: bool,
+    /// Further content types accepted for the same payload, documented next to `content_type`.
+    pub extra_content_types: &'static [&'static str],
+}
+
+#[cfg(not(feature = "openapi"))]
+/// Part of the request an extractor argument is read from (stubbed when `openapi` is disabled).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ParameterLocation {
+    /// The request body, aggregated per content type.
+    #[default]
+    Body,
+    /// A segment captured by the route template, such as `{id}` in `/users/{id}`.
+    ///
+    /// One path parameter is documented per template segment, typed from the matching schema
+    /// property when the extractor provides an object schema.
+    Path,
+    /// The query string, documented as one query parameter per schema property.
+    Query,
+    /// A request header, documented as a header parameter with this name.
+    Header(&'static str),
 }
 
 #[cfg(not(feature = "openapi"))]
@@ -52,6 +131,8 @@ pub struct ResponseSchema {
     pub schema: Option<SchemaRef>,
     /// Content type returned by the responder, if known.
     pub content_type: Option<&'static str>,
+    /// Example payload, parsed as JSON when possible and used verbatim otherwise.
+    pub example: Option<&'static str>,
 }
 
 #[cfg(not(feature = "openapi"))]
@@ -60,6 +141,9 @@ impl fmt::Debug for ExtractorSchema {
         f.debug_struct("ExtractorSchema")
             .field("content_type", &self.content_type)
             .field("has_schema", &self.schema.is_some())
+            .field("location", &self.location)
+            .field("required", &self.required)
+            .field("extra_content_types", &self.extra_content_types)
             .finish()
     }
 }
@@ -72,6 +156,7 @@ impl fmt::Debug for ResponseSchema {
             .field("description", &self.description)
             .field("content_type", &self.content_type)
             .field("has_schema", &self.schema.is_some())
+            .field("example", &self.example)
             .finish()
     }
 }
@@ -99,11 +184,25 @@ pub type ExtractorSchemaFn = fn() -> Option<ExtractorSchema>;
 /// Function pointer used to lazily build responder schemas.
 pub type ResponderSchemaFn = fn() -> Option<Vec<ResponseSchema>>;
 
+/// Types that can appear in the generated document, i.e. every [`ToSchema`](crate::ToSchema)
+/// type.
+///
+/// It only exists to explain a missing `ToSchema` impl in the errors raised by skyzen's macros.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` cannot be documented in OpenAPI because it does not implement `ToSchema`",
+    label = "missing `ToSchema`",
+    note = "derive `skyzen::ToSchema` for `{Self}` (and for the types of its fields)",
+    note = "`uuid::Uuid` and `chrono` types implement it once skyzen's `uuid` or `chrono` feature is enabled"
+)]
+pub trait DocumentedSchema: crate::ToSchema {}
+
+impl<T: crate::ToSchema> DocumentedSchema for T {}
+
 /// Return the schema for a `ToSchema` type.
 #[must_use]
 pub fn schema_of<T>() -> Option<SchemaRef>
 where
-    T: crate::ToSchema,
+    T: DocumentedSchema,
 {
     Some(<T as crate::PartialSchema>::schema())
 }
@@ -198,12 +297,22 @@ pub struct HandlerSpec {
     pub docs: Option<&'static str>,
     /// Deprecation flag extracted from handler attributes.
     pub deprecated: bool,
+    /// Tags grouping the operation, from `#[skyzen::openapi(tag = "...")]`.
+    pub tags: &'static [&'static str],
+    /// Summary overriding the first paragraph of the docs.
+    pub summary: Option<&'static str>,
+    /// Operation identifier overriding `operation_name`.
+    pub operation_id: Option<&'static str>,
+    /// Security scheme overriding [`OpenApi::secure_all`]; empty for a public operation.
+    pub security: Option<&'static str>,
     /// Schema generators for each extractor argument.
     pub parameters: &'static [ExtractorSchemaFn],
     /// Names of each documented extractor argument (aligned with `parameters`).
     pub parameter_names: &'static [&'static str],
     /// Schema generators for the responder type, if any.
     pub response: Option<ResponderSchemaFn>,
+    /// Responses of the handler's error type when it implements [`ErrorResponses`].
+    pub errors: Option<ResponderSchemaFn>,
     /// Schema collectors for parameters and responders, including their transitive dependencies.
     pub schemas: &'static [SchemaCollector],
 }
@@ -230,11 +339,74 @@ where
     }
 }
 
+/// Responses rendered by an error type, implemented by `#[skyzen::error]`.
+///
+/// Handlers returning `Result<T, E>` document one response per status `E` can produce instead of
+/// a generic failure.
+pub trait ErrorResponses {
+    /// Describe each response the error renders.
+    fn openapi_responses() -> Vec<ResponseSchema>;
+
+    /// Register the payload schemas referenced by [`openapi_responses`](Self::openapi_responses).
+    fn register_openapi_schemas(_defs: &mut BTreeMap<String, SchemaRef>) {}
+}
+
+/// Probe used by `#[skyzen::openapi]` to pick up [`ErrorResponses`] when the error type has it.
+#[doc(hidden)]
+#[derive(Debug)]
+pub struct ErrorProbe<E>(core::marker::PhantomData<E>);
+
+impl<E> ErrorProbe<E> {
+    #[doc(hidden)]
+    #[must_use]
+    pub const fn new() -> Self {
+        Self(core::marker::PhantomData)
+    }
+}
+
+impl<E> Default for ErrorProbe<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Resolved for error types implementing [`ErrorResponses`].
+#[doc(hidden)]
+pub trait DescribedError {
+    fn error_responses(&self) -> Option<Vec<ResponseSchema>>;
+    fn register_error_schemas(&self, defs: &mut BTreeMap<String, SchemaRef>);
+}
+
+impl<E: ErrorResponses> DescribedError for ErrorProbe<E> {
+    fn error_responses(&self) -> Option<Vec<ResponseSchema>> {
+        Some(E::openapi_responses())
+    }
+
+    fn register_error_schemas(&self, defs: &mut BTreeMap<String, SchemaRef>) {
+        E::register_openapi_schemas(defs);
+    }
+}
+
+/// Fallback for error types without [`ErrorResponses`], reached through one more auto-ref.
+#[doc(hidden)]
+pub trait UndescribedError {
+    fn error_responses(&self) -> Option<Vec<ResponseSchema>>;
+    fn register_error_schemas(&self, defs: &mut BTreeMap<String, SchemaRef>);
+}
+
+impl<E> UndescribedError for &ErrorProbe<E> {
+    fn error_responses(&self) -> Option<Vec<ResponseSchema>> {
+        None
+    }
+
+    fn register_error_schemas(&self, _defs: &mut BTreeMap<String, SchemaRef>) {}
+}
+
 /// Register a schema and its dependencies when `OpenAPI` is enabled.
 #[allow(clippy::missing_const_for_fn)]
 pub fn register_schema_for<T>(defs: &mut BTreeMap<String, SchemaRef>)
 where
-    T: crate::PartialSchema + crate::ToSchema,
+    T: DocumentedSchema,
 {
     #[cfg(all(debug_assertions, feature = "openapi"))]
     register_type::<T>(defs);
@@ -337,6 +509,10 @@ pub struct OpenApi {
     operations: Vec<OpenApiOperation>,
     #[cfg(all(debug_assertions, feature = "openapi"))]
     schemas: Vec<(String, SchemaRef)>,
+    info: Option<Info>,
+    servers: Vec<Server>,
+    security_schemes: Vec<(String, SecurityScheme)>,
+    secure_all: Option<String>,
 }
 
 impl Debug for OpenApi {
@@ -354,7 +530,7 @@ impl OpenApi {
     #[must_use]
     pub(crate) fn from_entries(entries: &[RouteOpenApiEntry]) -> Self {
         let mut schema_defs = BTreeMap::new();
-        let operations = entries
+        let mut operations = entries
             .iter()
             .map(|entry| {
                 let handler_type = entry.handler.type_name;
@@ -365,6 +541,9 @@ impl OpenApi {
                         handler_type,
                         operation_id: trim_crate(handler_type).to_owned(),
                         docs: None,
+                        summary: None,
+                        tags: Vec::new(),
+                        security: None,
                         deprecated: false,
                         parameters: Vec::new(),
                         responses: Vec::new(),
@@ -383,16 +562,31 @@ impl OpenApi {
                                 });
                             }
                         }
-                        let responses = spec
+                        let mut responses = spec
                             .response
                             .and_then(|schema| schema())
                             .unwrap_or_default();
+                        if let Some(errors) = spec.errors.and_then(|errors| errors()) {
+                            // `Result<T, E>` documents an anonymous 503 when nothing is known
+                            // about `E`; the described responses replace it.
+                            responses.retain(|response| {
+                                response.status != Some(StatusCode::SERVICE_UNAVAILABLE)
+                                    || response.description.is_some()
+                            });
+                            responses.extend(errors);
+                        }
                         OpenApiOperation {
                             path: entry.path.clone(),
                             method: entry.method.clone(),
                             handler_type,
-                            operation_id: spec.operation_name.to_owned(),
+                            operation_id: spec
+                                .operation_id
+                                .unwrap_or(spec.operation_name)
+                                .to_owned(),
                             docs,
+                            summary: spec.summary,
+                            tags: spec.tags.to_vec(),
+                            security: spec.security,
                             deprecated: spec.deprecated,
                             parameters,
                             responses,
@@ -400,11 +594,16 @@ impl OpenApi {
                     },
                 )
             })
-            .collect();
+            .collect::<Vec<_>>();
+        dedupe_operation_ids(&mut operations);
+        for operation in &operations {
+            check_path_parameters(operation);
+        }
         let schemas = schema_defs.into_iter().collect();
         Self {
             operations,
             schemas,
+            ..Self::default()
         }
     }
 
@@ -412,8 +611,8 @@ impl OpenApi {
     #[cfg(not(all(debug_assertions, feature = "openapi")))]
     #[must_use]
     #[allow(dead_code)]
-    pub(crate) const fn from_entries(_: &[()]) -> Self {
-        Self {}
+    pub(crate) fn from_entries(_: &[()]) -> Self {
+        Self::default()
     }
 
     /// Inspect the registered operations. In release builds this returns an empty slice.
@@ -430,6 +629,54 @@ impl OpenApi {
         &[]
     }
 
+    /// Set the title, version and description of the document instead of skyzen's own.
+    ///
+    /// An empty `description` is left out.
+    #[must_use]
+    pub fn with_info(
+        mut self,
+        title: impl Into<String>,
+        version: impl Into<String>,
+        description: impl Into<String>,
+    ) -> Self {
+        let description = description.into();
+        let mut info = Info::new(title.into(), version.into());
+        if !description.is_empty() {
+            info.description = Some(description);
+        }
+        self.info = Some(info);
+        self
+    }
+
+    /// Declare a server the API is reachable at. An empty `description` is left out.
+    #[must_use]
+    pub fn with_server(mut self, url: impl Into<String>, description: impl Into<String>) -> Self {
+        let description = description.into();
+        let mut server = Server::new(url);
+        if !description.is_empty() {
+            server.description = Some(description);
+        }
+        self.servers.push(server);
+        self
+    }
+
+    /// Register a security scheme under `name` in `components.securitySchemes`.
+    #[must_use]
+    pub fn add_security_scheme(mut self, name: impl Into<String>, scheme: SecurityScheme) -> Self {
+        self.security_schemes.push((name.into(), scheme));
+        self
+    }
+
+    /// Require the security scheme registered as `scheme_name` on every operation.
+    ///
+    /// Handlers override it with `#[skyzen::openapi(security = "...")]`, or opt out with
+    /// `security = ""`.
+    #[must_use]
+    pub fn secure_all(mut self, scheme_name: impl Into<String>) -> Self {
+        self.secure_all = Some(scheme_name.into());
+        self
+    }
+
     /// Indicates whether `OpenAPI` instrumentation is active.
     #[must_use]
     pub const fn is_enabled(&self) -> bool {
@@ -454,33 +701,116 @@ impl OpenApi {
         redoc_route(endpoint, mount_path.into())
     }
 
+    /// Serialize the collected spec to JSON, served as `application/json`.
+    #[must_use]
+    pub fn json(&self) -> OpenApiDocumentEndpoint {
+        if !self.is_enabled() {
+            return OpenApiDocumentEndpoint::disabled("application/json");
+        }
+
+        match self.to_utoipa_spec().to_json() {
+            Ok(json) => OpenApiDocumentEndpoint::enabled(json, "application/json"),
+            Err(error) => {
+                tracing::error!("Failed to serialize OpenAPI document to JSON: {error}");
+                OpenApiDocumentEndpoint::disabled("application/json")
+            }
+        }
+    }
+
+    /// Serialize the collected spec to YAML, served as `application/yaml`.
+    #[must_use]
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn yaml(&self) -> OpenApiDocumentEndpoint {
+        #[cfg(feature = "openapi")]
+        if self.is_enabled() {
+            match self.to_utoipa_spec().to_yaml() {
+                Ok(yaml) => return OpenApiDocumentEndpoint::enabled(yaml, "application/yaml"),
+                Err(error) => {
+                    tracing::error!("Failed to serialize OpenAPI document to YAML: {error}");
+                }
+            }
+        }
+
+        OpenApiDocumentEndpoint::disabled("application/yaml")
+    }
+
+    /// Build a [`RouteNode`] that serves the JSON document at `path`.
+    #[must_use]
+    pub fn json_route(&self, path: impl Into<String>) -> RouteNode {
+        RouteNode::new_endpoint(path, Method::GET, self.json(), None)
+    }
+
+    /// Build a [`RouteNode`] that serves the YAML document at `path`.
+    #[must_use]
+    pub fn yaml_route(&self, path: impl Into<String>) -> RouteNode {
+        RouteNode::new_endpoint(path, Method::GET, self.yaml(), None)
+    }
+
+    /// Mount Redoc at `mount`, the JSON document at `{mount}/openapi.json` and the YAML document
+    /// at `{mount}/openapi.yaml`.
+    #[must_use]
+    pub fn routes(&self, mount: impl Into<String>) -> RouteNode {
+        let redoc = self.redoc();
+        let route = Route::new((
+            RouteNode::new_endpoint("", Method::GET, redoc.clone(), None),
+            RouteNode::new_endpoint("/", Method::GET, redoc, None),
+            self.json_route("/openapi.json"),
+            self.yaml_route("/openapi.yaml"),
+        ));
+        RouteNode::new_route(mount.into(), route)
+    }
+
     /// Convert collected operations to a fully hydrated [`utoipa::openapi::OpenApi`] document.
     #[must_use]
     pub fn to_utoipa_spec(&self) -> UtoipaSpec {
+        let servers = (!self.servers.is_empty()).then(|| self.servers.clone());
         UtoipaSpec::builder()
-            .info(Self::default_info())
+            .info(self.info.clone().unwrap_or_else(Self::default_info))
+            .servers(servers)
             .paths(self.build_paths())
             .components(Some(self.build_components()))
+            .tags(self.build_tags())
             .build()
     }
 
+    fn build_tags(&self) -> Option<Vec<Tag>> {
+        let names: BTreeSet<&str> = self
+            .operations()
+            .iter()
+            .flat_map(|op| op.tags.iter().copied())
+            .collect();
+        if names.is_empty() {
+            return None;
+        }
+        Some(names.into_iter().map(Tag::new).collect())
+    }
+
     fn default_info() -> Info {
         Info::new(env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))
     }
 
     fn build_paths(&self) -> Paths {
-        self.operations()
-            .iter()
-            .fold(PathsBuilder::new(), |builder, op| {
-                if let Some(http_method) = method_to_http_method(&op.method) {
-                    let operation = build_operation(op);
-                    let path_item = PathItemBuilder::new()
-                        .operation(http_method, operation)
-                        .build();
-                    builder.path(op.path.clone(), path_item)
-                } else {
-                    builder
-                }
+        let mut by_path: BTreeMap<String, Vec<(HttpMethod, Operation)>> = BTreeMap::new();
+        for op in self.operations() {
+            if let Some(http_method) = method_to_http_method(&op.method) {
+                let operation = build_operation(op, self.secure_all.as_deref());
+                by_path
+                    .entry(openapi_path(&op.path))
+                    .or_default()
+                    .push((http_method, operation));
+            }
+        }
+
+        by_path
+            .into_iter()
+            .fold(PathsBuilder::new(), |builder, (path, operations)| {
+                let path_item = operations
+                    .into_iter()
+                    .fold(PathItemBuilder::new(), |item, (method, operation)| {
+                        item.operation(method, operation)
+                    })
+                    .build();
+                builder.path(path, path_item)
             })
             .build()
     }
@@ -490,16 +820,24 @@ impl OpenApi {
         self.schemas
             .iter()
             .cloned()
-            .fold(ComponentsBuilder::new(), |builder, (name, schema)| {
+            .fold(self.security_components(), |builder, (name, schema)| {
                 builder.schema(name, schema)
             })
             .build()
     }
 
     #[cfg(not(all(debug_assertions, feature = "openapi")))]
-    #[allow(clippy::unused_self)]
     fn build_components(&self) -> utoipa::openapi::schema::Components {
-        ComponentsBuilder::new().build()
+        self.security_components().build()
+    }
+
+    fn security_components(&self) -> ComponentsBuilder {
+        self.security_schemes
+            .iter()
+            .cloned()
+            .fold(ComponentsBuilder::new(), |builder, (name, scheme)| {
+                builder.security_scheme(name, scheme)
+            })
     }
 }
 
@@ -525,6 +863,12 @@ pub struct OpenApiOperation {
     pub operation_id: String,
     /// Documentation extracted from the handler's doc comments.
     pub docs: Option<&'static str>,
+    /// Summary set on `#[skyzen::openapi]`, used instead of the first paragraph of `docs`.
+    pub summary: Option<&'static str>,
+    /// Tags grouping the operation.
+    pub tags: Vec<&'static str>,
+    /// Security scheme set on `#[skyzen::openapi]`, overriding [`OpenApi::secure_all`].
+    pub security: Option<&'static str>,
     /// Whether the handler is deprecated.
     pub deprecated: bool,
     /// Schemas describing the extractor arguments.
@@ -541,6 +885,9 @@ impl fmt::Debug for OpenApiOperation {
             .field("handler_type", &self.handler_type)
             .field("operation_id", &self.operation_id)
             .field("docs", &self.docs)
+            .field("summary", &self.summary)
+            .field("tags", &self.tags)
+            .field("security", &self.security)
             .field("deprecated", &self.deprecated)
             .field("parameters", &self.parameters.len())
             .field("responses", &self.responses.len())
@@ -587,6 +934,45 @@ impl Endpoint for OpenApiRedocEndpoint {
     }
 }
 
+#[derive(Clone, Debug)]
+/// Endpoint that serves the serialized `OpenAPI` document.
+pub struct OpenApiDocumentEndpoint {
+    document: Option<Bytes>,
+    content_type: &'static str,
+}
+
+impl OpenApiDocumentEndpoint {
+    fn enabled(document: String, content_type: &'static str) -> Self {
+        Self {
+            document: Some(Bytes::from(document)),
+            content_type,
+        }
+    }
+
+    const fn disabled(content_type: &'static str) -> Self {
+        Self {
+            document: None,
+            content_type,
+        }
+    }
+}
+
+impl Endpoint for OpenApiDocumentEndpoint {
+    type Error = OpenApiRedocDisabledError;
+    async fn respond(&mut self, _request: &mut Request) -> Result<Response, Self::Error> {
+        let document = self
+            .document
+            .clone()
+            .ok_or_else(OpenApiRedocDisabledError::new)?;
+        let mut response = Response::new(Body::from(document));
+        response.headers_mut().insert(
+            header::CONTENT_TYPE,
+            header::HeaderValue::from_static(self.content_type),
+        );
+        Ok(response)
+    }
+}
+
 fn redoc_route(endpoint: OpenApiRedocEndpoint, mount_path: String) -> RouteNode {
     let wildcard_suffix = "/{*path}";
     let route = Route::new((
@@ -617,20 +1003,34 @@ fn method_to_http_method(method: &Method) -> Option<HttpMethod> {
     }
 }
 
-fn build_operation(op: &OpenApiOperation) -> Operation {
+fn build_operation(op: &OpenApiOperation, default_security: Option<&str>) -> Operation {
     let summary = op
-        .docs
-        .and_then(doc_summary)
+        .summary
+        .map(str::to_owned)
+        .or_else(|| op.docs.and_then(doc_summary))
         .or_else(|| Some(op.operation_id.clone()));
     let mut builder = OperationBuilder::new()
         .operation_id(Some(op.operation_id.clone()))
         .summary(summary)
         .responses(build_responses(op));
 
+    if !op.tags.is_empty() {
+        builder = builder.tags(Some(op.tags.clone()));
+    }
+
     if op.deprecated {
         builder = builder.deprecated(Some(Deprecated::True));
     }
 
+    if let Some(scheme) = op.security.or(default_security).filter(|s| !s.is_empty()) {
+        builder = builder.security(SecurityRequirement::new(scheme, Vec::<String>::new()));
+    }
+
+    let parameters = build_parameters(op);
+    if !parameters.is_empty() {
+        builder = builder.parameters(Some(parameters));
+    }
+
     if let Some(body) = build_request_body(op) {
         builder = builder.request_body(Some(body));
     }
@@ -652,16 +1052,46 @@ fn build_responses(op: &OpenApiOperation) -> utoipa::openapi::response::Response
             .build();
     }
 
-    let mut builder = ResponsesBuilder::new();
+    // Variants of an error enum often share a status; they become one response listing each
+    // description, with the first payload that has a schema or example.
+    let mut by_status: BTreeMap<StatusCode, Vec<&ResponseSchema>> = BTreeMap::new();
     for response in &op.responses {
-        let status = response.status.unwrap_or(StatusCode::OK);
-        let mut response_builder =
-            ResponseBuilder::new().description(response.description.unwrap_or("Response"));
+        by_status
+            .entry(response.status.unwrap_or(StatusCode::OK))
+            .or_default()
+            .push(response);
+    }
 
-        if let Some(schema) = &response.schema {
+    let mut builder = ResponsesBuilder::new();
+    for (status, responses) in by_status {
+        let mut descriptions = Vec::new();
+        for description in responses.iter().filter_map(|response| response.description) {
+            if !descriptions.contains(&description) {
+                descriptions.push(description);
+            }
+        }
+        let description = if descriptions.is_empty() {
+            "Response".to_owned()
+        } else {
+            descriptions.join("; ")
+        };
+        let mut response_builder = ResponseBuilder::new().description(description);
+
+        if let Some(response) = responses
+            .iter()
+            .find(|response| response.schema.is_some() || response.example.is_some())
+        {
             let content_type = response.content_type.unwrap_or("application/json");
-            response_builder =
-                response_builder.content(content_type, Content::new(Some(schema.clone())));
+            #[allow(unused_mut)]
+            let mut content = Content::new(response.schema.clone());
+            #[cfg(feature = "openapi")]
+            {
+                content.example = response.example.map(|example| {
+                    serde_json::from_str(example)
+                        .unwrap_or_else(|_| serde_json::Value::String(example.to_owned()))
+                });
+            }
+            response_builder = response_builder.content(content_type, content);
         }
 
         builder = builder.response(status.as_str(), response_builder.build());
@@ -670,10 +1100,258 @@ fn build_responses(op: &OpenApiOperation) -> utoipa::openapi::response::Response
     builder.build()
 }
 
+fn build_parameters(op: &OpenApiOperation) -> Vec<Parameter> {
+    let mut parameters = Vec::new();
+    let mut path_documented = false;
+    for param in &op.parameters {
+        let schema = &param.schema;
+        match schema.location {
+            ParameterLocation::Body => {}
+            ParameterLocation::Header(name) => parameters.push(parameter(
+                name,
+                ParameterIn::Header,
+                schema.required,
+                schema.schema.clone(),
+            )),
+            ParameterLocation::Path => {
+                if path_documented {
+                    continue;
+                }
+                path_documented = true;
+                let fields = schema.schema.as_ref().and_then(object_fields);
+                for capture in path_captures(&op.path) {
+                    let field = fields
+                        .and_then(|object| object.properties.get(capture.name))
+                        .cloned();
+                    parameters.push(path_parameter(capture, field));
+                }
+            }
+            ParameterLocation::Query => {
+                if let Some(object) = schema.schema.as_ref().and_then(object_fields) {
+                    for (name, field) in &object.properties {
+                        let required = schema.required && object.required.contains(name);
+                        parameters.push(parameter(
+                            name,
+                            ParameterIn::Query,
+                            required,
+                            Some(field.clone()),
+                        ));
+                    }
+                    continue;
+                }
+                let free_form = schema.schema.clone().unwrap_or_else(|| {
+                    RefOr::T(Schema::Object(
+                        ObjectBuilder::new()
+                            .schema_type(SchemaType::from(Type::Object))
+                            .additional_properties(Some(AdditionalProperties::FreeForm(true)))
+                            .build(),
+                    ))
+                });
+                parameters.push(
+                    ParameterBuilder::from(parameter(
+                        &param.name,
+                        ParameterIn::Query,
+                        schema.required,
+                        Some(free_form),
+                    ))
+                    .style(Some(ParameterStyle::Form))
+                    .explode(Some(true))
+                    .build(),
+                );
+            }
+        }
+    }
+    if !path_documented {
+        // `OpenAPI` requires every capture of the template to be declared, including those of a
+        // prefix the handler is mounted under, even when no extractor reads them.
+        parameters.extend(
+            path_captures(&op.path)
+                .into_iter()
+                .map(|capture| path_parameter(capture, None)),
+        );
+    }
+    parameters
+}
+
+/// Required path parameter for `capture`, a string unless an extractor documents its `schema`.
+fn path_parameter(capture: PathCapture<'_>, schema: Option<RefOr<Schema>>) -> Parameter {
+    let schema = schema.unwrap_or_else(|| {
+        RefOr::T(Schema::Object(
+            ObjectBuilder::new()
+                .schema_type(SchemaType::from(Type::String))
+                .build(),
+        ))
+    });
+    let parameter = parameter(capture.name, ParameterIn::Path, true, Some(schema));
+    if !capture.catch_all {
+        return parameter;
+    }
+    ParameterBuilder::from(parameter)
+        .description(Some(
+            "Rest of the path after the preceding segments; it may contain `/`.",
+        ))
+        .build()
+}
+
+fn parameter(
+    name: &str,
+    location: ParameterIn,
+    is_required: bool,
+    schema: Option<RefOr<Schema>>,
+) -> Parameter {
+    ParameterBuilder::new()
+        .name(name)
+        .parameter_in(location)
+        .required(required(is_required))
+        .schema(schema)
+        .build()
+}
+
+/// Inline object schema with at least one property, used to document one parameter per field.
+fn object_fields(schema: &RefOr<Schema>) -> Option<&Object> {
+    match schema {
+        RefOr::T(Schema::Object(object)) if !object.properties.is_empty() => Some(object),
+        _ => None,
+    }
+}
+
+/// Render a route template as an `OpenAPI` path: catch-all `{*path}` segments become `{path}`,
+/// documented like any other path parameter.
+fn openapi_path(path: &str) -> String {
+    if path.is_empty() {
+        return "/".to_owned();
+    }
+    path.replace("{*", "{")
+}
+
+/// A segment captured by a route template.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct PathCapture<'a> {
+    name: &'a str,
+    /// Whether it is a catch-all `{*name}`, matching the rest of the path.
+    catch_all: bool,
+}
+
+/// Segments captured by a route template, e.g. `id` for `/users/{id}`.
+///
+/// Escaped braces (`{{` and `}}`) are literal text, and an unclosed `{` ends the template.
+fn path_captures(path: &str) -> Vec<PathCapture<'_>> {
+    let mut captures = Vec::new();
+    let mut rest = path;
+    while let Some(start) = rest.find('{') {
+        let after = &rest[start + 1..];
+        if let Some(escaped) = after.strip_prefix('{') {
+            rest = escaped;
+            continue;
+        }
+        let Some(end) = after.find('}') else {
+            break;
+        };
+        let name = &after[..end];
+        captures.push(name.strip_prefix('*').map_or(
+            PathCapture {
+                name,
+                catch_all: false,
+            },
+            |name| PathCapture {
+                name,
+                catch_all: true,
+            },
+        ));
+        rest = &after[end + 1..];
+    }
+    captures
+}
+
+/// Names of the segments captured by a route template, e.g. `id` for `/users/{id}`.
+#[cfg(all(debug_assertions, feature = "openapi", not(target_arch = "wasm32")))]
+fn path_template_names(path: &str) -> Vec<&str> {
+    path_captures(path)
+        .into_iter()
+        .map(|capture| capture.name)
+        .collect()
+}
+
+/// Make every `operation_id` unique, as `OpenAPI` requires, by suffixing repeats with `_2`, `_3`
+/// and so on in route order, such as for a handler served on several routes.
+#[cfg(all(debug_assertions, feature = "openapi", not(target_arch = "wasm32")))]
+fn dedupe_operation_ids(operations: &mut [OpenApiOperation]) {
+    let mut taken: BTreeSet<String> = operations
+        .iter()
+        .map(|op| op.operation_id.clone())
+        .collect();
+    let mut seen = BTreeSet::new();
+    for op in operations {
+        if seen.insert(op.operation_id.clone()) {
+            continue;
+        }
+        let mut n = 2;
+        let renamed = loop {
+            let candidate = format!("{}_{n}", op.operation_id);
+            if !taken.contains(&candidate) {
+                break candidate;
+            }
+            n += 1;
+        };
+        tracing::warn!(
+            "Operation id `{}` of {} {} is already used by another route; renamed to `{renamed}`",
+            op.operation_id,
+            op.method,
+            op.path
+        );
+        taken.insert(renamed.clone());
+        seen.insert(renamed.clone());
+        op.operation_id = renamed;
+    }
+}
+
+/// Warn when a path extractor does not line up with the segments of its route template.
+#[cfg(all(debug_assertions, feature = "openapi", not(target_arch = "wasm32")))]
+fn check_path_parameters(op: &OpenApiOperation) {
+    let Some(param) = op
+        .parameters
+        .iter()
+        .find(|param| param.schema.location == ParameterLocation::Path)
+    else {
+        return;
+    };
+
+    let template: BTreeSet<&str> = path_template_names(&op.path).into_iter().collect();
+    if template.is_empty() {
+        tracing::warn!(
+            "`{}` extracts `{}` from the path, but route `{}` has no path parameters",
+            op.operation_id,
+            param.name,
+            op.path
+        );
+        return;
+    }
+
+    if let Some(object) = param.schema.schema.as_ref().and_then(object_fields) {
+        let fields: BTreeSet<&str> = object.properties.keys().map(String::as_str).collect();
+        if fields != template {
+            tracing::warn!(
+                "`{}` expects path parameters {:?}, but route `{}` declares {:?}",
+                op.operation_id,
+                fields,
+                op.path,
+                template
+            );
+        }
+    }
+}
+
+/// Name, schema and required flag of an extractor argument read from the body.
+type BodyParameter = (String, RefOr<Schema>, bool);
+
 fn build_request_body(op: &OpenApiOperation) -> Option<utoipa::openapi::request_body::RequestBody> {
-    let mut by_content_type: BTreeMap<&str, Vec<(String, RefOr<Schema>)>> = BTreeMap::new();
+    let mut by_content_type: BTreeMap<&str, Vec<BodyParameter>> = BTreeMap::new();
+    let mut body_required = false;
 
     for param in &op.parameters {
+        if param.schema.location != ParameterLocation::Body {
+            continue;
+        }
         let content_type = param.schema.content_type;
         if content_type.is_none() && param.schema.schema.is_none() {
             continue;
@@ -685,10 +1363,16 @@ fn build_request_body(op: &OpenApiOperation) -> Option<utoipa::openapi::request_
             .clone()
             .unwrap_or_else(|| utoipa::openapi::schema::empty().into());
         let content_type = content_type.unwrap_or("application/json");
-        by_content_type
-            .entry(content_type)
-            .or_default()
-            .push((param.name.clone(), schema));
+        body_required |= param.schema.required;
+        for content_type in
+            core::iter::once(content_type).chain(param.schema.extra_content_types.iter().copied())
+        {
+            by_content_type.entry(content_type).or_default().push((
+                param.name.clone(),
+                schema.clone(),
+                param.schema.required,
+            ));
+        }
     }
 
     if by_content_type.is_empty() {
@@ -697,7 +1381,7 @@ fn build_request_body(op: &OpenApiOperation) -> Option<utoipa::openapi::request_
 
     let mut builder = RequestBodyBuilder::new()
         .description(Some("Extractor arguments"))
-        .required(Some(Required::True));
+        .required(Some(required(body_required)));
 
     for (content_type, schemas) in by_content_type {
         let schema = aggregate_parameter_schema(&schemas);
@@ -707,17 +1391,28 @@ fn build_request_body(op: &OpenApiOperation) -> Option<utoipa::openapi::request_
     Some(builder.build())
 }
 
-fn aggregate_parameter_schema(parameters: &[(String, RefOr<Schema>)]) -> RefOr<Schema> {
+const fn required(required: bool) -> Required {
+    if required {
+        Required::True
+    } else {
+        Required::False
+    }
+}
+
+fn aggregate_parameter_schema(parameters: &[BodyParameter]) -> RefOr<Schema> {
     if parameters.len() == 1 {
         return parameters[0].1.clone();
     }
 
     let object = parameters.iter().fold(
         ObjectBuilder::new().schema_type(SchemaType::from(Type::Object)),
-        |builder, (name, schema)| {
-            builder
-                .property(name.clone(), schema.clone())
-                .required(name.clone())
+        |builder, (name, schema, required)| {
+            let builder = builder.property(name.clone(), schema.clone());
+            if *required {
+                builder.required(name.clone())
+            } else {
+                builder
+            }
         },
     );
 
@@ -743,3 +1438,505 @@ fn doc_summary(docs: &str) -> Option<String> {
         Some(paragraph.join(" "))
     }
 }
+
+#[cfg(all(
+    test,
+    debug_assertions,
+    feature = "openapi",
+    not(target_arch = "wasm32")
+))]
+mod tests {
+    use serde::Deserialize;
+    use utoipa::openapi::path::ParameterIn;
+
+    use crate::{
+        extract::Query,
+        routing::{CreateRouteNode, Params, Route},
+        Result,
+    };
+
+    /// Lists every user.
+    #[skyzen::openapi(tag = "users", summary = "List users", operation_id = "listUsers")]
+    async fn list_users() -> Result<&'static str> {
+        Ok("[]")
+    }
+
+    /// Removes stale sessions.
+    #[skyzen::openapi(tag = "admin", tag = "users", deprecated)]
+    async fn purge_sessions() -> Result<&'static str> {
+        Ok("purged")
+    }
+
+    #[test]
+    fn applies_attribute_overrides() {
+        let router =
+            Route::new(("/users".at(list_users), "/sessions".delete(purge_sessions))).build();
+        let spec = router.openapi().to_utoipa_spec();
+
+        let list = spec.paths.paths["/users"].get.as_ref().unwrap();
+        assert_eq!(list.operation_id.as_deref(), Some("listUsers"));
+        assert_eq!(list.summary.as_deref(), Some("List users"));
+        assert_eq!(list.tags.as_deref(), Some(&["users".to_owned()][..]));
+
+        let purge = spec.paths.paths["/sessions"].delete.as_ref().unwrap();
+        assert!(purge
+            .operation_id
+            .as_deref()
+            .unwrap()
+            .ends_with("purge_sessions"));
+        assert_eq!(purge.summary.as_deref(), Some("Removes stale sessions."));
+        assert!(matches!(purge.deprecated, Some(super::Deprecated::True)));
+
+        let tags: Vec<_> = spec.tags.unwrap().into_iter().map(|tag| tag.name).collect();
+        assert_eq!(tags, ["admin", "users"]);
+    }
+
+    mod accounts {
+        #[skyzen::openapi]
+        pub async fn list() -> crate::Result<&'static str> {
+            Ok("accounts")
+        }
+    }
+
+    mod orders {
+        #[skyzen::openapi]
+        pub async fn list() -> crate::Result<&'static str> {
+            Ok("orders")
+        }
+    }
+
+    #[skyzen::openapi(operation_id = "listUsers")]
+    async fn list_members() -> Result<&'static str> {
+        Ok("[]")
+    }
+
+    #[test]
+    fn keeps_operation_ids_unique() {
+        let router = Route::new((
+            "/accounts".at(accounts::list),
+            "/orders".at(orders::list),
+            "/orders/archived".at(orders::list),
+            "/users".at(list_users),
+            "/members".at(list_members),
+        ))
+        .build();
+        let spec = router.openapi().to_utoipa_spec();
+        let operation_id = |path: &str| {
+            spec.paths.paths[path]
+                .get
+                .as_ref()
+                .and_then(|op| op.operation_id.clone())
+                .unwrap()
+        };
+
+        assert_eq!(
+            operation_id("/accounts"),
+            "skyzen::openapi::tests::accounts::list"
+        );
+        assert_eq!(
+            operation_id("/orders"),
+            "skyzen::openapi::tests::orders::list"
+        );
+        assert_eq!(
+            operation_id("/orders/archived"),
+            "skyzen::openapi::tests::orders::list_2"
+        );
+        assert_eq!(operation_id("/users"), "listUsers");
+        assert_eq!(operation_id("/members"), "listUsers_2");
+    }
+
+    #[derive(Deserialize)]
+    struct Filter {
+        #[allow(dead_code)]
+        name: Option<String>,
+    }
+
+    #[skyzen::openapi]
+    async fn show_post(params: Params) -> Result<&'static str> {
+        Ok(if params.get("id").is_ok() { "post" } else { "" })
+    }
+
+    #[skyzen::openapi]
+    async fn search_posts(filter: Query<Filter>) -> Result<&'static str> {
+        let _ = filter;
+        Ok("[]")
+    }
+
+    #[test]
+    fn documents_path_and_query_parameters() {
+        let router = Route::new((
+            "/users/{user}/posts/{id}".at(show_post),
+            "/posts".at(search_posts),
+        ))
+        .build();
+        let spec = router.openapi().to_utoipa_spec();
+
+        let show = spec.paths.paths["/users/{user}/posts/{id}"]
+            .get
+            .as_ref()
+            .unwrap();
+        assert!(show.request_body.is_none());
+        let path: Vec<_> = show
+            .parameters
+            .iter()
+            .flatten()
+            .map(|param| (param.name.as_str(), param.parameter_in.clone()))
+            .collect();
+        assert!(matches!(
+            path.as_slice(),
+            [("user", ParameterIn::Path), ("id", ParameterIn::Path)]
+        ));
+
+        let search = spec.paths.paths["/posts"].get.as_ref().unwrap();
+        assert!(search.request_body.is_none());
+        let query = &search.parameters.as_ref().unwrap()[0];
+        assert_eq!(query.name, "filter");
+        assert!(matches!(query.parameter_in, ParameterIn::Query));
+    }
+
+    #[test]
+    fn reads_template_segment_names() {
+        assert_eq!(
+            super::path_template_names("/files/{dir}/{*path}"),
+            ["dir", "path"]
+        );
+        assert!(super::path_template_names("/literal/{{braces}}").is_empty());
+    }
+
+    #[test]
+    fn parses_tricky_templates() {
+        let captures = |path| {
+            super::path_captures(path)
+                .into_iter()
+                .map(|capture| (capture.name, capture.catch_all))
+                .collect::<Vec<_>>()
+        };
+        assert!(captures("").is_empty());
+        assert!(captures("/").is_empty());
+        assert_eq!(
+            captures("/api/users/{id}/posts/{post_id}"),
+            [("id", false), ("post_id", false)]
+        );
+        assert_eq!(
+            captures("/files/{name}.{ext}"),
+            [("name", false), ("ext", false)]
+        );
+        assert_eq!(captures("/static/{*rest}"), [("rest", true)]);
+        assert_eq!(captures("/{{{id}}}/{{x}}"), [("id", false)]);
+        assert_eq!(captures("/a}}b/{id}"), [("id", false)]);
+        assert_eq!(captures("/{id}/{oops"), [("id", false)]);
+    }
+
+    async fn get_user() -> Result<&'static str> {
+        Ok("user")
+    }
+
+    #[skyzen::openapi]
+    async fn download(params: Params) -> Result<&'static str> {
+        Ok(if params.get("rest").is_ok() {
+            "file"
+        } else {
+            ""
+        })
+    }
+
+    #[test]
+    fn documents_captures_no_extractor_reads() {
+        let router = Route::new((
+            "/api".route(("/users/{id}".at(get_user),)),
+            "/{tenant}".route(("/files/{*rest}".at(download),)),
+        ))
+        .build();
+        let spec = router.openapi().to_utoipa_spec();
+        let parameters = |path: &str| {
+            spec.paths.paths[path]
+                .get
+                .as_ref()
+                .unwrap()
+                .parameters
+                .clone()
+                .unwrap_or_default()
+        };
+
+        let user = parameters("/api/users/{id}");
+        assert_eq!(user.len(), 1);
+        assert_eq!(user[0].name, "id");
+        assert!(matches!(user[0].parameter_in, ParameterIn::Path));
+        assert!(matches!(user[0].required, super::Required::True));
+        assert!(matches!(
+            &user[0].schema,
+            Some(super::RefOr::T(super::Schema::Object(object)))
+                if object.schema_type == super::SchemaType::from(super::Type::String)
+        ));
+
+        let files = parameters("/{tenant}/files/{rest}");
+        let names: Vec<_> = files.iter().map(|param| param.name.as_str()).collect();
+        assert_eq!(names, ["tenant", "rest"]);
+        assert!(files[0].description.is_none());
+        assert!(files[1].description.as_deref().unwrap().contains('/'));
+    }
+
+    #[cfg(feature = "json")]
+    #[tokio::test]
+    async fn serves_machine_readable_documents() {
+        let docs = Route::new(("/users".at(list_users),))
+            .openapi()
+            .routes("/docs");
+        let router = Route::new(("/users".at(list_users), docs)).build();
+
+        let get = |path: &str| {
+            let mut request = http_kit::Request::new(crate::Body::empty());
+            *request.uri_mut() = path.parse().unwrap();
+            request
+        };
+
+        let response = router.clone().go(get("/docs/openapi.json")).await.unwrap();
+        assert_eq!(
+            response.headers()[http_kit::header::CONTENT_TYPE],
+            "application/json"
+        );
+        let body = response.into_body().into_string().await.unwrap();
+        let spec: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(spec["paths"]["/users"]["get"]["operationId"], "listUsers");
+
+        let response = router.clone().go(get("/docs/openapi.yaml")).await.unwrap();
+        assert_eq!(
+            response.headers()[http_kit::header::CONTENT_TYPE],
+            "application/yaml"
+        );
+        let body = response.into_body().into_string().await.unwrap();
+        assert!(body.contains("operationId: listUsers"));
+
+        let response = router.clone().go(get("/docs")).await.unwrap();
+        assert_eq!(
+            response.headers()[http_kit::header::CONTENT_TYPE],
+            "text/html; charset=utf-8"
+        );
+    }
+
+    #[skyzen::openapi(security = "")]
+    async fn health() -> Result<&'static str> {
+        Ok("ok")
+    }
+
+    #[skyzen::openapi(security = "api_key")]
+    async fn export_users() -> Result<&'static str> {
+        Ok("[]")
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn merges_info_servers_and_security() {
+        use utoipa::openapi::security::{
+            ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme,
+        };
+
+        let spec = Route::new((
+            "/users".at(list_users),
+            "/health".at(health),
+            "/export".at(export_users),
+        ))
+        .openapi()
+        .with_info("Shop API", "2.1.0", "Orders and payments")
+        .with_server("https://api.example.com", "")
+        .add_security_scheme(
+            "bearer",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+        )
+        .add_security_scheme(
+            "api_key",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("x-api-key"))),
+        )
+        .secure_all("bearer")
+        .to_utoipa_spec();
+
+        assert_eq!(spec.info.title, "Shop API");
+        assert_eq!(spec.info.version, "2.1.0");
+        assert_eq!(
+            spec.info.description.as_deref(),
+            Some("Orders and payments")
+        );
+        assert_eq!(
+            spec.servers.as_ref().unwrap()[0].url,
+            "https://api.example.com"
+        );
+
+        let json = serde_json::to_value(&spec).unwrap();
+        let schemes = &json["components"]["securitySchemes"];
+        assert_eq!(schemes["bearer"]["scheme"], "bearer");
+        assert_eq!(schemes["api_key"]["in"], "header");
+
+        let paths = &json["paths"];
+        assert_eq!(
+            paths["/users"]["get"]["security"],
+            serde_json::json!([{ "bearer": [] }])
+        );
+        assert_eq!(
+            paths["/export"]["get"]["security"],
+            serde_json::json!([{ "api_key": [] }])
+        );
+        assert!(paths["/health"]["get"].get("security").is_none());
+    }
+
+    #[derive(crate::ToSchema)]
+    #[allow(dead_code)]
+    struct Problem {
+        title: String,
+    }
+
+    #[skyzen::error(status = BAD_REQUEST, schema = Problem)]
+    #[allow(dead_code)]
+    enum OrderError {
+        #[error("Order not found", status = NOT_FOUND, example = r#"{"title": "missing"}"#)]
+        Missing,
+        #[error("Quantity must be positive")]
+        Quantity,
+        #[error("Unknown product")]
+        Product,
+    }
+
+    #[skyzen::openapi]
+    async fn place_order() -> core::result::Result<&'static str, OrderError> {
+        Err(OrderError::Missing)
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn documents_error_responses() {
+        let spec = Route::new(("/orders".post(place_order),))
+            .openapi()
+            .to_utoipa_spec();
+        let json = serde_json::to_value(&spec).unwrap();
+        let responses = &json["paths"]["/orders"]["post"]["responses"];
+
+        assert!(responses.get("503").is_none());
+        assert_eq!(
+            responses["400"]["description"],
+            "Quantity must be positive; Unknown product"
+        );
+        let missing = &responses["404"]["content"]["application/json"];
+        assert_eq!(missing["example"]["title"], "missing");
+        assert_eq!(missing["schema"]["required"][0], "title");
+        assert!(json["components"]["schemas"].get("Problem").is_some());
+    }
+
+    #[skyzen::openapi]
+    async fn list_items() -> Result<&'static str> {
+        Ok("[]")
+    }
+
+    #[skyzen::openapi]
+    async fn create_item() -> Result<&'static str> {
+        Ok("created")
+    }
+
+    #[skyzen::openapi]
+    async fn clear_items() -> Result<&'static str> {
+        Ok("cleared")
+    }
+
+    #[skyzen::openapi]
+    async fn serve_file(params: Params) -> Result<String> {
+        Ok(params.get("path")?.to_owned())
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn groups_methods_of_one_path() {
+        let spec = Route::new((
+            "/items".at(list_items),
+            "/items".post(create_item),
+            "/items".delete(clear_items),
+            "/files/{*path}".at(serve_file),
+        ))
+        .openapi()
+        .to_utoipa_spec();
+        let json = serde_json::to_value(&spec).unwrap();
+        let paths = json["paths"].as_object().unwrap();
+
+        let items = paths["/items"].as_object().unwrap();
+        let mut methods: Vec<_> = items.keys().map(String::as_str).collect();
+        methods.sort_unstable();
+        assert_eq!(methods, ["delete", "get", "post"]);
+
+        assert!(!paths.contains_key("/files/{*path}"));
+        let file = &paths["/files/{path}"]["get"]["parameters"][0];
+        assert_eq!(file["name"], "path");
+        assert_eq!(file["in"], "path");
+    }
+
+    #[test]
+    fn skips_ignored_routes_and_documentation() {
+        let public = Route::new(("/users".at(list_users),));
+        let docs = public.openapi().routes("/docs");
+        let router = Route::new((
+            "/users".at(list_users),
+            "/admin"
+                .route(("/sessions".delete(purge_sessions),))
+                .ignore_openapi(),
+            "/internal".route(Route::new(("/items".at(list_items),)).ignore_openapi()),
+            docs,
+        ))
+        .build();
+
+        let openapi = router.openapi();
+        assert_eq!(openapi.operations().len(), 1);
+        assert_eq!(openapi.operations()[0].path, "/users");
+    }
+
+    #[cfg(feature = "sse")]
+    #[skyzen::openapi]
+    async fn ticker() -> crate::responder::Sse {
+        crate::responder::Sse::channel().1
+    }
+
+    #[derive(crate::ToSchema)]
+    #[allow(dead_code)]
+    struct Tick {
+        price: u64,
+    }
+
+    #[cfg(feature = "sse")]
+    #[skyzen::openapi]
+    async fn typed_ticker() -> Result<crate::responder::sse::TypedSse<Tick>> {
+        Ok(crate::responder::Sse::channel().1.typed())
+    }
+
+    #[cfg(all(feature = "sse", feature = "json"))]
+    #[test]
+    fn documents_event_streams() {
+        let spec = Route::new(("/ticker".at(ticker), "/ticks".at(typed_ticker)))
+            .openapi()
+            .to_utoipa_spec();
+        let json = serde_json::to_value(&spec).unwrap();
+
+        let ticker = &json["paths"]["/ticker"]["get"]["responses"]["200"];
+        assert!(ticker["description"]
+            .as_str()
+            .unwrap()
+            .starts_with("Server-sent event stream"));
+        assert_eq!(
+            ticker["content"]["text/event-stream"]["schema"]["type"],
+            "string"
+        );
+
+        let ticks = &json["paths"]["/ticks"]["get"]["responses"]["200"]["content"];
+        assert_eq!(ticks["text/event-stream"]["schema"]["required"][0], "price");
+        assert!(json["components"]["schemas"].get("Tick").is_some());
+    }
+
+    #[cfg(all(feature = "uuid", feature = "chrono", feature = "json"))]
+    #[test]
+    fn documents_uuid_and_chrono_fields() {
+        #[derive(crate::ToSchema)]
+        #[allow(dead_code)]
+        struct Order {
+            id: crate::uuid::Uuid,
+            placed_at: crate::chrono::DateTime<crate::chrono::Utc>,
+        }
+
+        let schema = serde_json::to_value(super::schema_of::<Order>().unwrap()).unwrap();
+        assert_eq!(schema["properties"]["id"]["format"], "uuid");
+        assert_eq!(schema["properties"]["placed_at"]["format"], "date-time");
+    }
+}
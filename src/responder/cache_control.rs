@@ -0,0 +1,368 @@
+//! Caching headers around other responders.
+//!
+//! [`Cached`] and [`NoCache`] set a `Cache-Control` header assembled from typed directives, and
+//! [`WithVary`] lists the request headers a response depends on in `Vary`. They wrap any
+//! responder and leave its status and body alone:
+//!
+//! ```
+//! use std::time::Duration;
+//!
+//! use skyzen::{
+//!     header::ACCEPT_LANGUAGE,
+//!     responder::{Cached, NoCache, WithVary},
+//!     utils::Json,
+//!     Result,
+//! };
+//!
+//! async fn logo() -> Cached<&'static str> {
+//!     Cached::new("<svg/>").public().max_age(Duration::from_secs(365 * 24 * 60 * 60)).immutable()
+//! }
+//!
+//! async fn greeting() -> Result<WithVary<Cached<String>>> {
+//!     let greeting = Cached::new("Hello".to_owned()).max_age(Duration::from_secs(600));
+//!     Ok(WithVary::new(greeting, [ACCEPT_LANGUAGE]))
+//! }
+//!
+//! async fn balance() -> NoCache<Json<u64>> {
+//!     NoCache(Json(42))
+//! }
+//! ```
+
+use std::{fmt::Write, time::Duration};
+
+use http_kit::{
+    header::{HeaderName, HeaderValue, CACHE_CONTROL},
+    Request, Response,
+};
+use skyzen_core::Responder;
+
+use crate::utils::header_util;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Visibility {
+    Public,
+    Private,
+}
+
+/// Sets `Cache-Control` on the response of `T`, see the [module docs](self).
+///
+/// Directives are written in a fixed order, such as
+/// `public, max-age=60, s-maxage=300, stale-while-revalidate=30, immutable`. Durations are
+/// rounded down to whole seconds. A `Cache-Control` header set by `T` is replaced, or removed
+/// when no directive is set.
+#[derive(Debug, Clone)]
+pub struct Cached<T> {
+    inner: T,
+    visibility: Option<Visibility>,
+    max_age: Option<Duration>,
+    s_maxage: Option<Duration>,
+    stale_while_revalidate: Option<Duration>,
+    immutable: bool,
+}
+
+impl<T> Cached<T> {
+    /// Wrap `inner` without any directive yet.
+    pub const fn new(inner: T) -> Self {
+        Self {
+            inner,
+            visibility: None,
+            max_age: None,
+            s_maxage: None,
+            stale_while_revalidate: None,
+            immutable: false,
+        }
+    }
+
+    /// Let shared caches, such as CDNs, store the response, even for authorized requests.
+    ///
+    /// Replaces [`private`](Self::private).
+    #[must_use]
+    pub const fn public(mut self) -> Self {
+        self.visibility = Some(Visibility::Public);
+        self
+    }
+
+    /// Only let the browser store the response, for responses specific to a user.
+    ///
+    /// Replaces [`public`](Self::public), and leaves out [`s_maxage`](Self::s_maxage), which only
+    /// applies to shared caches.
+    #[must_use]
+    pub const fn private(mut self) -> Self {
+        self.visibility = Some(Visibility::Private);
+        self
+    }
+
+    /// How long the response stays fresh.
+    #[must_use]
+    pub const fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// How long the response stays fresh in shared caches, overriding
+    /// [`max_age`](Self::max_age) there.
+    #[must_use]
+    pub const fn s_maxage(mut self, s_maxage: Duration) -> Self {
+        self.s_maxage = Some(s_maxage);
+        self
+    }
+
+    /// How long a stale response may still be served while it is refreshed in the background.
+    #[must_use]
+    pub const fn stale_while_revalidate(mut self, window: Duration) -> Self {
+        self.stale_while_revalidate = Some(window);
+        self
+    }
+
+    /// Promise that the response never changes while fresh, so that browsers skip revalidating
+    /// it on reload, such as for assets with a content hash in their URL.
+    #[must_use]
+    pub const fn immutable(mut self) -> Self {
+        self.immutable = true;
+        self
+    }
+
+    /// The `Cache-Control` value of these directives.
+    fn header_value(&self) -> String {
+        let mut directives = Vec::new();
+        match self.visibility {
+            Some(Visibility::Public) => directives.push("public".to_owned()),
+            Some(Visibility::Private) => directives.push("private".to_owned()),
+            None => {}
+        }
+        let mut seconds = |name: &str, duration: Option<Duration>| {
+            if let Some(duration) = duration {
+                let mut directive = name.to_owned();
+                // Writing into a `String` cannot fail.
+                let _ = write!(directive, "={}", duration.as_secs());
+                directives.push(directive);
+            }
+        };
+        seconds("max-age", self.max_age);
+        if self.visibility != Some(Visibility::Private) {
+            seconds("s-maxage", self.s_maxage);
+        }
+        seconds("stale-while-revalidate", self.stale_while_revalidate);
+        if self.immutable {
+            directives.push("immutable".to_owned());
+        }
+        directives.join(", ")
+    }
+}
+
+impl<T: Responder> Responder for Cached<T> {
+    type Error = T::Error;
+
+    fn respond_to(self, request: &Request, response: &mut Response) -> Result<(), Self::Error> {
+        let value = self.header_value();
+        self.inner.respond_to(request, response)?;
+        if value.is_empty() {
+            response.headers_mut().remove(CACHE_CONTROL);
+        } else if let Ok(value) = HeaderValue::from_str(&value) {
+            response.headers_mut().insert(CACHE_CONTROL, value);
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "openapi")]
+    fn openapi() -> Option<Vec<crate::openapi::ResponseSchema>> {
+        T::openapi()
+    }
+
+    #[cfg(feature = "openapi")]
+    fn register_openapi_schemas(
+        defs: &mut std::collections::BTreeMap<String, crate::openapi::SchemaRef>,
+    ) {
+        T::register_openapi_schemas(defs);
+    }
+}
+
+/// Keeps the response of `T` out of every cache with `Cache-Control: no-store`.
+///
+/// A `Cache-Control` header set by `T` is replaced.
+#[derive(Debug, Clone)]
+pub struct NoCache<T>(pub T);
+
+impl<T: Responder> Responder for NoCache<T> {
+    type Error = T::Error;
+
+    fn respond_to(self, request: &Request, response: &mut Response) -> Result<(), Self::Error> {
+        self.0.respond_to(request, response)?;
+        response
+            .headers_mut()
+            .insert(CACHE_CONTROL, HeaderValue::from_static("no-store"));
+        Ok(())
+    }
+
+    #[cfg(feature = "openapi")]
+    fn openapi() -> Option<Vec<crate::openapi::ResponseSchema>> {
+        T::openapi()
+    }
+
+    #[cfg(feature = "openapi")]
+    fn register_openapi_schemas(
+        defs: &mut std::collections::BTreeMap<String, crate::openapi::SchemaRef>,
+    ) {
+        T::register_openapi_schemas(defs);
+    }
+}
+
+/// Adds request headers to the `Vary` header of the response of `T`.
+///
+/// Names already listed, by `T` or by middleware such as `CompressionMiddleware`, are not
+/// repeated.
+#[derive(Debug, Clone)]
+pub struct WithVary<T> {
+    inner: T,
+    names: Vec<HeaderName>,
+}
+
+impl<T> WithVary<T> {
+    /// Wrap `inner`, adding `names` to `Vary`.
+    pub fn new(inner: T, names: impl IntoIterator<Item = HeaderName>) -> Self {
+        Self {
+            inner,
+            names: names.into_iter().collect(),
+        }
+    }
+}
+
+impl<T: Responder> Responder for WithVary<T> {
+    type Error = T::Error;
+
+    fn respond_to(self, request: &Request, response: &mut Response) -> Result<(), Self::Error> {
+        self.inner.respond_to(request, response)?;
+        for name in &self.names {
+            header_util::append_vary(response.headers_mut(), name.as_str());
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "openapi")]
+    fn openapi() -> Option<Vec<crate::openapi::ResponseSchema>> {
+        T::openapi()
+    }
+
+    #[cfg(feature = "openapi")]
+    fn register_openapi_schemas(
+        defs: &mut std::collections::BTreeMap<String, crate::openapi::SchemaRef>,
+    ) {
+        T::register_openapi_schemas(defs);
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use std::time::Duration;
+
+    use http_kit::{
+        header::{HeaderValue, ACCEPT_ENCODING, ACCEPT_LANGUAGE, CACHE_CONTROL, VARY},
+        HttpError,
+    };
+    use skyzen_core::Responder;
+
+    use super::{Cached, NoCache, WithVary};
+    use crate::{Body, Request, Response, StatusCode};
+
+    /// A `201 Created` response with a body and its own `Cache-Control` and `Vary` headers.
+    struct Created;
+
+    impl Responder for Created {
+        type Error = std::convert::Infallible;
+
+        fn respond_to(
+            self,
+            _request: &Request,
+            response: &mut Response,
+        ) -> Result<(), Self::Error> {
+            *response.status_mut() = StatusCode::CREATED;
+            *response.body_mut() = Body::from("created");
+            let headers = response.headers_mut();
+            headers.insert(CACHE_CONTROL, HeaderValue::from_static("max-age=5"));
+            headers.insert(VARY, HeaderValue::from_static("Accept-Encoding"));
+            Ok(())
+        }
+    }
+
+    fn respond(responder: impl Responder) -> Response {
+        let request = Request::new(Body::empty());
+        let mut response = Response::new(Body::empty());
+        responder
+            .respond_to(&request, &mut response)
+            .unwrap_or_else(|error| panic!("{}", error.status()));
+        response
+    }
+
+    fn cache_control(responder: impl Responder) -> Option<String> {
+        respond(responder)
+            .headers()
+            .get(CACHE_CONTROL)
+            .map(|value| value.to_str().unwrap().to_owned())
+    }
+
+    #[test]
+    fn formats_directives_in_order() {
+        let minute = Duration::from_mins(1);
+        assert_eq!(
+            cache_control(
+                Cached::new("")
+                    .immutable()
+                    .stale_while_revalidate(Duration::from_millis(30_900))
+                    .s_maxage(5 * minute)
+                    .max_age(minute)
+                    .public()
+            )
+            .as_deref(),
+            Some("public, max-age=60, s-maxage=300, stale-while-revalidate=30, immutable")
+        );
+        assert_eq!(
+            cache_control(
+                Cached::new("")
+                    .public()
+                    .private()
+                    .max_age(minute)
+                    .s_maxage(minute)
+            )
+            .as_deref(),
+            Some("private, max-age=60")
+        );
+        assert_eq!(
+            cache_control(Cached::new("").max_age(Duration::ZERO)).as_deref(),
+            Some("max-age=0")
+        );
+        assert_eq!(cache_control(Cached::new(Created)), None);
+        assert_eq!(cache_control(NoCache("")).as_deref(), Some("no-store"));
+    }
+
+    #[tokio::test]
+    async fn keeps_the_status_and_body_of_the_inner_responder() {
+        let responders: [Box<dyn FnOnce() -> Response>; 3] = [
+            Box::new(|| respond(Cached::new(Created).private())),
+            Box::new(|| respond(NoCache(Created))),
+            Box::new(|| respond(WithVary::new(Created, [ACCEPT_LANGUAGE]))),
+        ];
+        for respond in responders {
+            let response = respond();
+            assert_eq!(response.status(), StatusCode::CREATED);
+            assert_eq!(
+                response
+                    .into_body()
+                    .into_string()
+                    .await
+                    .unwrap()
+                    .to_string(),
+                "created"
+            );
+        }
+    }
+
+    #[test]
+    fn appends_to_vary_without_duplicates() {
+        let response = respond(WithVary::new(
+            WithVary::new(Created, [ACCEPT_ENCODING, ACCEPT_LANGUAGE]),
+            [ACCEPT_LANGUAGE],
+        ));
+        assert_eq!(response.headers()[VARY], "Accept-Encoding, accept-language");
+        assert_eq!(response.headers()[CACHE_CONTROL], "max-age=5");
+    }
+}
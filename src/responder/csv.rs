@@ -0,0 +1,300 @@
+//! CSV responder module.
+//! It streams rows as CSV without buffering the whole export.
+
+use core::{
+    fmt::Write,
+    mem,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use std::{
+    convert::Infallible,
+    io,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use futures_core::Stream;
+use http_kit::{
+    header::{HeaderValue, CONTENT_DISPOSITION, CONTENT_TYPE},
+    utils::Bytes,
+    Body, BodyError, Request, Response,
+};
+use pin_project_lite::pin_project;
+use serde::Serialize;
+use skyzen_core::Responder;
+
+/// A responder streaming rows as `text/csv`.
+///
+/// Rows are serialized with the `csv` crate as the body is read, so the next rows are only
+/// pulled from the source stream once the client has taken the previous ones. The header row
+/// comes from the field names of the first row, and a row failing to serialize ends the body
+/// with an error.
+/// # Example
+/// ```
+/// # use skyzen::responder::Csv;
+/// # use futures_util::stream;
+/// # use serde::Serialize;
+/// #[derive(Serialize)]
+/// struct Order {
+///     id: u64,
+///     total: f64,
+/// }
+///
+/// async fn export() -> Csv {
+///     let orders = stream::iter((1..=3).map(|id| Order { id, total: 9.5 }));
+///     Csv::from_stream(orders).file_name("orders.csv")
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Csv {
+    body: Body,
+    file_name: Option<String>,
+}
+
+impl Csv {
+    /// Size up to which [`Csv::from_stream`] joins ready rows into one body chunk.
+    pub const DEFAULT_BATCH_BYTES: usize = 16 * 1024;
+
+    /// Create a CSV responder from a stream of rows.
+    ///
+    /// Rows that are ready at the same time are written as one body chunk of up to
+    /// [`DEFAULT_BATCH_BYTES`](Self::DEFAULT_BATCH_BYTES).
+    pub fn from_stream<S>(rows: S) -> Self
+    where
+        S: Stream + Send + Sync + 'static,
+        S::Item: Serialize,
+    {
+        Self {
+            body: Body::from_stream(CsvStream::new(rows, Self::DEFAULT_BATCH_BYTES)),
+            file_name: None,
+        }
+    }
+
+    /// Ask the client to save the export as `name`, through `Content-Disposition`.
+    #[must_use]
+    pub fn file_name(mut self, name: impl Into<String>) -> Self {
+        self.file_name = Some(name.into());
+        self
+    }
+}
+
+impl Responder for Csv {
+    type Error = Infallible;
+    fn respond_to(self, _request: &Request, response: &mut Response) -> Result<(), Self::Error> {
+        response.headers_mut().insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("text/csv; charset=utf-8"),
+        );
+        if let Some(disposition) = self
+            .file_name
+            .as_deref()
+            .and_then(|name| HeaderValue::from_str(&attachment(name)).ok())
+        {
+            response
+                .headers_mut()
+                .insert(CONTENT_DISPOSITION, disposition);
+        }
+        *response.body_mut() = self.body;
+        Ok(())
+    }
+}
+
+/// An `attachment` disposition for `name`, adding the RFC 6266 `filename*` form for non-ASCII
+/// names.
+fn attachment(name: &str) -> String {
+    let fallback = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_graphic() && c != '"' && c != '\\' || c == ' ' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    let mut value = format!("attachment; filename=\"{fallback}\"");
+    if !name.is_ascii() {
+        value.push_str("; filename*=UTF-8''");
+        for byte in name.bytes() {
+            if byte.is_ascii_alphanumeric() || b"!#$&+-.^_`|~".contains(&byte) {
+                value.push(char::from(byte));
+            } else {
+                // Writing into a `String` cannot fail.
+                let _ = write!(value, "%{byte:02X}");
+            }
+        }
+    }
+    value
+}
+
+pin_project! {
+    // Serializes the rows that are already available into one chunk, up to `max_bytes`.
+    struct CsvStream<S> {
+        #[pin]
+        rows: S,
+        writer: csv::Writer<Output>,
+        output: Output,
+        max_bytes: usize,
+        error: Option<csv::Error>,
+        done: bool,
+    }
+}
+
+/// The output of the CSV writer, shared so that chunks can be taken out while the writer keeps
+/// its state, such as whether the header row was written.
+#[derive(Debug, Clone, Default)]
+struct Output(Arc<Mutex<Vec<u8>>>);
+
+impl Output {
+    fn len(&self) -> usize {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner).len()
+    }
+
+    fn take(&self) -> Vec<u8> {
+        mem::take(&mut *self.0.lock().unwrap_or_else(PoisonError::into_inner))
+    }
+}
+
+impl io::Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<S> CsvStream<S> {
+    fn new(rows: S, max_bytes: usize) -> Self {
+        let output = Output::default();
+        Self {
+            rows,
+            writer: csv::Writer::from_writer(output.clone()),
+            output,
+            max_bytes,
+            error: None,
+            done: false,
+        }
+    }
+}
+
+impl<S> Stream for CsvStream<S>
+where
+    S: Stream,
+    S::Item: Serialize,
+{
+    type Item = Result<Bytes, BodyError>;
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+        if let Some(error) = this.error.take() {
+            return Poll::Ready(Some(Err(BodyError::Other(Box::new(error)))));
+        }
+        if *this.done {
+            return Poll::Ready(None);
+        }
+        loop {
+            match this.rows.as_mut().poll_next(cx) {
+                Poll::Ready(Some(row)) => {
+                    let written = this
+                        .writer
+                        .serialize(row)
+                        .and_then(|()| this.writer.flush().map_err(csv::Error::from));
+                    if let Err(error) = written {
+                        // Rows written so far are still sent before the error.
+                        *this.error = Some(error);
+                        *this.done = true;
+                        break;
+                    }
+                    if this.output.len() >= *this.max_bytes {
+                        break;
+                    }
+                }
+                Poll::Ready(None) => {
+                    *this.done = true;
+                    break;
+                }
+                Poll::Pending => break,
+            }
+        }
+
+        let chunk = this.output.take();
+        if chunk.is_empty() {
+            if let Some(error) = this.error.take() {
+                return Poll::Ready(Some(Err(BodyError::Other(Box::new(error)))));
+            }
+            // Either the rows are exhausted, or they are pending and will wake this task.
+            return if *this.done {
+                Poll::Ready(None)
+            } else {
+                Poll::Pending
+            };
+        }
+        Poll::Ready(Some(Ok(chunk.into())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::{stream, StreamExt};
+    use serde::Serialize;
+
+    use super::{attachment, Csv, CsvStream};
+
+    #[derive(Serialize)]
+    struct Row {
+        id: u32,
+        name: &'static str,
+    }
+
+    #[tokio::test]
+    async fn writes_the_header_once_and_batches_rows() {
+        let rows = || stream::iter((1..=3).map(|id| Row { id, name: "a,b" }));
+        let chunks = |max_bytes| {
+            CsvStream::new(rows(), max_bytes)
+                .map(|chunk| String::from_utf8(chunk.unwrap().to_vec()).unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            chunks(Csv::DEFAULT_BATCH_BYTES).await,
+            ["id,name\n1,\"a,b\"\n2,\"a,b\"\n3,\"a,b\"\n"]
+        );
+        assert_eq!(
+            chunks(0).await,
+            ["id,name\n1,\"a,b\"\n", "2,\"a,b\"\n", "3,\"a,b\"\n"]
+        );
+    }
+
+    #[tokio::test]
+    async fn ends_the_body_with_serialization_errors() {
+        let rows = || stream::iter([vec![1, 2], vec![3, 4], vec![5]]);
+        for max_bytes in [0, Csv::DEFAULT_BATCH_BYTES] {
+            let chunks = CsvStream::new(rows(), max_bytes).collect::<Vec<_>>().await;
+            let (last, sent) = chunks.split_last().unwrap();
+            assert!(last.is_err());
+            let sent = sent
+                .iter()
+                .flat_map(|chunk| chunk.as_ref().unwrap().to_vec())
+                .collect::<Vec<_>>();
+            assert_eq!(sent, b"1,2\n3,4\n");
+        }
+    }
+
+    #[test]
+    fn quotes_file_names() {
+        assert_eq!(
+            attachment("orders 2024.csv"),
+            "attachment; filename=\"orders 2024.csv\""
+        );
+        assert_eq!(attachment("\"x\".csv"), "attachment; filename=\"_x_.csv\"");
+        assert_eq!(
+            attachment("résumé.csv"),
+            "attachment; filename=\"r_sum_.csv\"; filename*=UTF-8''r%C3%A9sum%C3%A9.csv"
+        );
+    }
+}
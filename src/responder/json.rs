@@ -1,15 +1,26 @@
 //! Json responder module.
-//! It provides a responder serializing data as pretty-printed JSON.
+//! It provides responders serializing data as pretty-printed JSON, and streaming large JSON
+//! arrays or JSON Lines without buffering the whole payload.
 
+use core::{
+    mem,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use std::convert::Infallible;
+
+use futures_core::Stream;
 use http_kit::{
     header::{HeaderValue, CONTENT_TYPE},
-    Request, Response,
+    Body, BodyError, Request, Response,
 };
 use http_kit::{http_error, StatusCode};
+use pin_project_lite::pin_project;
 use serde::Serialize;
-use serde_json::to_vec_pretty;
 use skyzen_core::Responder;
 
+use crate::utils::json::write_json;
+
 /// A pretty JSON responder,it serialize data as a pretty-printed JSON.
 /// # Example
 /// ```
@@ -40,12 +51,86 @@ http_error!(
 
 impl<T: Send + Sync + Serialize + 'static> Responder for PrettyJson<T> {
     type Error = PrettyJsonError;
+    fn respond_to(self, request: &Request, response: &mut Response) -> Result<(), Self::Error> {
+        write_json(request, response, &self.0, true).map_err(|_| PrettyJsonError::new())
+    }
+
+    #[cfg(feature = "openapi")]
+    fn openapi() -> Option<Vec<crate::openapi::ResponseSchema>> {
+        Some(vec![crate::openapi::ResponseSchema {
+            status: None,
+            description: None,
+            schema: None,
+            content_type: Some("application/json"),
+            example: None,
+        }])
+    }
+
+    #[cfg(feature = "openapi")]
+    fn register_openapi_schemas(
+        _defs: &mut std::collections::BTreeMap<String, crate::openapi::SchemaRef>,
+    ) {
+    }
+}
+
+/// A responder streaming items as one JSON array.
+///
+/// `Json(items)` serializes the whole array before the response starts. Here items are
+/// serialized as the body is read, so the next items are only pulled from the source stream once
+/// the client has taken the previous ones, and at most about
+/// [`DEFAULT_BATCH_BYTES`](Self::DEFAULT_BATCH_BYTES) of JSON is held at a time. An item failing
+/// to serialize ends the body with an error instead of a truncated array.
+/// # Example
+/// ```
+/// # use skyzen::responder::JsonStream;
+/// # use futures_util::stream;
+/// # use serde::Serialize;
+/// #[derive(Serialize)]
+/// struct Order {
+///     id: u64,
+///     total: f64,
+/// }
+///
+/// async fn export() -> JsonStream {
+///     let orders = stream::iter((1..=100_000).map(|id| Order { id, total: 9.5 }));
+///     JsonStream::from_stream(orders)
+/// }
+/// ```
+#[derive(Debug)]
+pub struct JsonStream {
+    body: Body,
+}
+
+impl JsonStream {
+    /// Size up to which [`JsonStream::from_stream`] joins ready items into one body chunk.
+    pub const DEFAULT_BATCH_BYTES: usize = 16 * 1024;
+
+    /// Create a JSON array responder from a stream of items.
+    ///
+    /// Items that are ready at the same time are written as one body chunk of up to
+    /// [`DEFAULT_BATCH_BYTES`](Self::DEFAULT_BATCH_BYTES).
+    pub fn from_stream<S>(items: S) -> Self
+    where
+        S: Stream + Send + Sync + 'static,
+        S::Item: Serialize,
+    {
+        Self {
+            body: Body::from_stream(JsonFrames::new(
+                items,
+                Framing::Array,
+                Self::DEFAULT_BATCH_BYTES,
+            )),
+        }
+    }
+}
+
+impl Responder for JsonStream {
+    type Error = Infallible;
     fn respond_to(self, _request: &Request, response: &mut Response) -> Result<(), Self::Error> {
-        let payload = to_vec_pretty(&self.0).map_err(|_| PrettyJsonError::new())?;
-        *response.body_mut() = http_kit::Body::from_bytes(payload);
         response
             .headers_mut()
             .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        *response.body_mut() = self.body;
         Ok(())
     }
 
@@ -56,12 +141,281 @@ impl<T: Send + Sync + Serialize + 'static> Responder for PrettyJson<T> {
             description: None,
             schema: None,
             content_type: Some("application/json"),
+            example: None,
         }])
     }
+}
+
+/// A responder streaming items as `application/x-ndjson`, one JSON document per line.
+///
+/// Items are serialized as the body is read, in chunks of up to
+/// [`DEFAULT_BATCH_BYTES`](Self::DEFAULT_BATCH_BYTES), like [`JsonStream`]. Clients can process
+/// each line as it arrives. An item failing to serialize ends the body with an error after the
+/// lines written before it.
+/// # Example
+/// ```
+/// # use skyzen::responder::JsonLines;
+/// # use futures_util::stream;
+/// # use serde::Serialize;
+/// #[derive(Serialize)]
+/// struct Event {
+///     kind: &'static str,
+/// }
+///
+/// async fn events() -> JsonLines {
+///     JsonLines::from_stream(stream::iter([Event { kind: "created" }, Event { kind: "paid" }]))
+/// }
+/// ```
+#[derive(Debug)]
+pub struct JsonLines {
+    body: Body,
+}
+
+impl JsonLines {
+    /// Size up to which [`JsonLines::from_stream`] joins ready lines into one body chunk.
+    pub const DEFAULT_BATCH_BYTES: usize = 16 * 1024;
+
+    /// Create a JSON Lines responder from a stream of items.
+    ///
+    /// Lines that are ready at the same time are written as one body chunk of up to
+    /// [`DEFAULT_BATCH_BYTES`](Self::DEFAULT_BATCH_BYTES).
+    pub fn from_stream<S>(items: S) -> Self
+    where
+        S: Stream + Send + Sync + 'static,
+        S::Item: Serialize,
+    {
+        Self {
+            body: Body::from_stream(JsonFrames::new(
+                items,
+                Framing::Lines,
+                Self::DEFAULT_BATCH_BYTES,
+            )),
+        }
+    }
+}
+
+impl Responder for JsonLines {
+    type Error = Infallible;
+    fn respond_to(self, _request: &Request, response: &mut Response) -> Result<(), Self::Error> {
+        response.headers_mut().insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("application/x-ndjson"),
+        );
+        *response.body_mut() = self.body;
+        Ok(())
+    }
 
     #[cfg(feature = "openapi")]
-    fn register_openapi_schemas(
-        _defs: &mut std::collections::BTreeMap<String, crate::openapi::SchemaRef>,
-    ) {
+    fn openapi() -> Option<Vec<crate::openapi::ResponseSchema>> {
+        Some(vec![crate::openapi::ResponseSchema {
+            status: None,
+            description: None,
+            schema: None,
+            content_type: Some("application/x-ndjson"),
+            example: None,
+        }])
+    }
+}
+
+/// How [`JsonFrames`] joins serialized items.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Framing {
+    /// `[a,b,c]`
+    Array,
+    /// `a\nb\nc\n`
+    Lines,
+}
+
+pin_project! {
+    // Serializes the items that are already available into one chunk, up to `max_bytes`.
+    struct JsonFrames<S> {
+        #[pin]
+        items: S,
+        framing: Framing,
+        buffer: Vec<u8>,
+        max_bytes: usize,
+        started: bool,
+        error: Option<serde_json::Error>,
+        done: bool,
+    }
+}
+
+impl<S> JsonFrames<S> {
+    const fn new(items: S, framing: Framing, max_bytes: usize) -> Self {
+        Self {
+            items,
+            framing,
+            buffer: Vec::new(),
+            max_bytes,
+            started: false,
+            error: None,
+            done: false,
+        }
+    }
+}
+
+impl<S> Stream for JsonFrames<S>
+where
+    S: Stream,
+    S::Item: Serialize,
+{
+    type Item = Result<http_kit::utils::Bytes, BodyError>;
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+        if let Some(error) = this.error.take() {
+            return Poll::Ready(Some(Err(BodyError::Other(Box::new(error)))));
+        }
+        if *this.done {
+            return Poll::Ready(None);
+        }
+        loop {
+            match this.items.as_mut().poll_next(cx) {
+                Poll::Ready(Some(item)) => {
+                    let start = this.buffer.len();
+                    if *this.framing == Framing::Array {
+                        this.buffer.push(if *this.started { b',' } else { b'[' });
+                    }
+                    if let Err(error) = serde_json::to_writer(&mut *this.buffer, &item) {
+                        // Items written so far are still sent before the error.
+                        this.buffer.truncate(start);
+                        *this.error = Some(error);
+                        *this.done = true;
+                        break;
+                    }
+                    if *this.framing == Framing::Lines {
+                        this.buffer.push(b'\n');
+                    }
+                    *this.started = true;
+                    if this.buffer.len() >= *this.max_bytes {
+                        break;
+                    }
+                }
+                Poll::Ready(None) => {
+                    if *this.framing == Framing::Array {
+                        this.buffer
+                            .extend_from_slice(if *this.started { b"]" } else { b"[]" });
+                    }
+                    *this.done = true;
+                    break;
+                }
+                Poll::Pending => break,
+            }
+        }
+
+        let chunk = mem::take(this.buffer);
+        if chunk.is_empty() {
+            if let Some(error) = this.error.take() {
+                return Poll::Ready(Some(Err(BodyError::Other(Box::new(error)))));
+            }
+            // Either the items are exhausted, or they are pending and will wake this task.
+            return if *this.done {
+                Poll::Ready(None)
+            } else {
+                Poll::Pending
+            };
+        }
+        Poll::Ready(Some(Ok(chunk.into())))
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use futures_util::{stream, StreamExt};
+    use serde::Serialize;
+
+    use super::{Framing, JsonFrames, JsonStream};
+
+    #[derive(Serialize)]
+    struct Row {
+        id: u32,
+        name: &'static str,
+    }
+
+    fn rows() -> impl futures_core::Stream<Item = Row> {
+        stream::iter((1..=3).map(|id| Row { id, name: "a\"b" }))
+    }
+
+    async fn chunks<S>(items: S, framing: Framing, max_bytes: usize) -> Vec<String>
+    where
+        S: futures_core::Stream,
+        S::Item: Serialize,
+    {
+        JsonFrames::new(items, framing, max_bytes)
+            .map(|chunk| String::from_utf8(chunk.unwrap().to_vec()).unwrap())
+            .collect()
+            .await
+    }
+
+    #[tokio::test]
+    async fn streams_a_json_array_in_batches() {
+        assert_eq!(
+            chunks(rows(), Framing::Array, JsonStream::DEFAULT_BATCH_BYTES).await,
+            [r#"[{"id":1,"name":"a\"b"},{"id":2,"name":"a\"b"},{"id":3,"name":"a\"b"}]"#]
+        );
+        assert_eq!(
+            chunks(rows(), Framing::Array, 0).await,
+            [
+                r#"[{"id":1,"name":"a\"b"}"#,
+                r#",{"id":2,"name":"a\"b"}"#,
+                r#",{"id":3,"name":"a\"b"}"#,
+                "]"
+            ]
+        );
+        assert_eq!(
+            chunks(stream::iter(Vec::<u8>::new()), Framing::Array, 0).await,
+            ["[]"]
+        );
+    }
+
+    #[tokio::test]
+    async fn streams_one_line_per_item() {
+        assert_eq!(
+            chunks(rows(), Framing::Lines, JsonStream::DEFAULT_BATCH_BYTES).await,
+            [concat!(
+                r#"{"id":1,"name":"a\"b"}"#,
+                "\n",
+                r#"{"id":2,"name":"a\"b"}"#,
+                "\n",
+                r#"{"id":3,"name":"a\"b"}"#,
+                "\n"
+            )]
+        );
+        assert!(chunks(stream::iter(Vec::<u8>::new()), Framing::Lines, 0)
+            .await
+            .is_empty());
+    }
+
+    /// A number that fails to serialize when missing.
+    struct Item(Option<u32>);
+
+    impl Serialize for Item {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let number = self
+                .0
+                .ok_or_else(|| serde::ser::Error::custom("missing number"))?;
+            serializer.serialize_u32(number)
+        }
+    }
+
+    #[tokio::test]
+    async fn ends_the_body_with_serialization_errors() {
+        let items = || stream::iter([Item(Some(1)), Item(Some(2)), Item(None), Item(Some(4))]);
+        for (framing, expected) in [
+            (Framing::Array, &b"[1,2"[..]),
+            (Framing::Lines, &b"1\n2\n"[..]),
+        ] {
+            for max_bytes in [0, JsonStream::DEFAULT_BATCH_BYTES] {
+                let chunks = JsonFrames::new(items(), framing, max_bytes)
+                    .collect::<Vec<_>>()
+                    .await;
+                let (last, sent) = chunks.split_last().unwrap();
+                assert!(last.is_err());
+                let sent = sent
+                    .iter()
+                    .flat_map(|chunk| chunk.as_ref().unwrap().to_vec())
+                    .collect::<Vec<_>>();
+                assert_eq!(sent, expected);
+            }
+        }
     }
 }
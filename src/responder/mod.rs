@@ -29,6 +29,9 @@
 //!
 pub use skyzen_core::Responder;
 
+pub mod cache_control;
+pub use cache_control::{Cached, NoCache, WithVary};
+
 #[cfg(feature = "sse")]
 pub mod sse;
 #[cfg(feature = "sse")]
@@ -37,4 +40,14 @@ pub use sse::Sse;
 #[cfg(feature = "json")]
 pub mod json;
 #[cfg(feature = "json")]
-pub use json::PrettyJson;
+pub use json::{JsonLines, JsonStream, PrettyJson};
+
+#[cfg(feature = "json")]
+pub mod negotiate;
+#[cfg(feature = "json")]
+pub use negotiate::{Negotiate, NegotiateError};
+
+#[cfg(feature = "csv")]
+pub mod csv;
+#[cfg(feature = "csv")]
+pub use csv::Csv;
@@ -0,0 +1,293 @@
+//! Content negotiation responder.
+//! It serializes a value into the representation the client prefers according to `Accept`.
+
+use core::fmt;
+
+use http_kit::{
+    header::{HeaderValue, ACCEPT, CONTENT_TYPE},
+    http_error, Request, Response, StatusCode,
+};
+use serde::Serialize;
+use skyzen_core::Responder;
+
+use crate::utils::{header_util, negotiation};
+
+type Render<T> = Box<dyn FnOnce(&T) -> Result<Vec<u8>, NegotiateError> + Send + Sync>;
+
+/// A responder choosing between representations of a value with the `Accept` header.
+///
+/// JSON is always offered, and comes first: it is picked when the request has no `Accept`
+/// header or ranks several offers equally. HTML is offered through [`html`](Self::html), and CSV
+/// through `csv` for lists with the `csv` feature. The response carries `Vary: Accept`, and a
+/// request accepting none of the offers gets `406 Not Acceptable` with the offered types listed in
+/// a plain text body.
+/// # Example
+/// ```
+/// # use skyzen::responder::Negotiate;
+/// # use serde::Serialize;
+/// #[derive(Serialize)]
+/// struct User {
+///     name: String,
+/// }
+///
+/// async fn handler() -> Negotiate<User> {
+///     Negotiate::new(User { name: "Lexo".into() })
+///         .html(|user| format!("<h1>{}</h1>", user.name))
+/// }
+/// ```
+pub struct Negotiate<T: Send + Sync + 'static> {
+    value: T,
+    offers: Vec<(&'static str, Render<T>)>,
+}
+
+impl<T: Send + Sync + Serialize + 'static> Negotiate<T> {
+    /// Offer `value` as JSON.
+    #[must_use]
+    pub fn new(value: T) -> Self {
+        Self {
+            value,
+            offers: Vec::new(),
+        }
+        .offer("application/json", |value| {
+            serde_json::to_vec(value).map_err(|_| NegotiateError::new())
+        })
+    }
+
+    /// Also offer `value` as `text/html`, rendered by `render`.
+    #[must_use]
+    pub fn html(self, render: impl FnOnce(&T) -> String + Send + Sync + 'static) -> Self {
+        self.offer("text/html; charset=utf-8", |value| Ok(render(value).into()))
+    }
+
+    fn offer(
+        mut self,
+        content_type: &'static str,
+        render: impl FnOnce(&T) -> Result<Vec<u8>, NegotiateError> + Send + Sync + 'static,
+    ) -> Self {
+        self.offers.push((content_type, Box::new(render)));
+        self
+    }
+}
+
+#[cfg(feature = "csv")]
+impl<T: Send + Sync + Serialize + 'static> Negotiate<Vec<T>> {
+    /// Also offer the list as `text/csv`, with one record per item and a header row taken from
+    /// the field names.
+    #[must_use]
+    pub fn csv(self) -> Self {
+        self.offer("text/csv; charset=utf-8", |items| {
+            let mut writer = csv::Writer::from_writer(Vec::new());
+            for item in items {
+                writer.serialize(item).map_err(|_| NegotiateError::new())?;
+            }
+            writer.into_inner().map_err(|_| NegotiateError::new())
+        })
+    }
+}
+
+impl<T: Send + Sync + 'static> fmt::Debug for Negotiate<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Negotiate")
+            .field(
+                "offers",
+                &self.offers.iter().map(|(ty, _)| ty).collect::<Vec<_>>(),
+            )
+            .finish_non_exhaustive()
+    }
+}
+
+http_error!(
+    /// An error occurred when serializing the chosen representation.
+    pub NegotiateError, StatusCode::INTERNAL_SERVER_ERROR, "Failed to encode negotiated response");
+
+impl<T: Send + Sync + 'static> Responder for Negotiate<T> {
+    type Error = NegotiateError;
+    fn respond_to(self, request: &Request, response: &mut Response) -> Result<(), Self::Error> {
+        header_util::append_vary(response.headers_mut(), "Accept");
+        let types = self.offers.iter().map(|(ty, _)| essence(ty));
+        let Some(chosen) = choose(request, types) else {
+            let offered = self
+                .offers
+                .iter()
+                .map(|(ty, _)| essence(ty))
+                .collect::<Vec<_>>();
+            *response.status_mut() = StatusCode::NOT_ACCEPTABLE;
+            *response.body_mut() = http_kit::Body::from(format!(
+                "Not acceptable, available representations: {}",
+                offered.join(", ")
+            ));
+            response.headers_mut().insert(
+                CONTENT_TYPE,
+                HeaderValue::from_static("text/plain; charset=utf-8"),
+            );
+            return Ok(());
+        };
+
+        let Self { value, mut offers } = self;
+        let (content_type, render) = offers.swap_remove(chosen);
+        *response.body_mut() = http_kit::Body::from_bytes(render(&value)?);
+        response
+            .headers_mut()
+            .insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+        Ok(())
+    }
+
+    #[cfg(feature = "openapi")]
+    fn openapi() -> Option<Vec<crate::openapi::ResponseSchema>> {
+        Some(vec![crate::openapi::ResponseSchema {
+            status: None,
+            description: None,
+            schema: None,
+            content_type: Some("application/json"),
+            example: None,
+        }])
+    }
+
+    #[cfg(feature = "openapi")]
+    fn register_openapi_schemas(
+        _defs: &mut std::collections::BTreeMap<String, crate::openapi::SchemaRef>,
+    ) {
+    }
+}
+
+fn essence(content_type: &str) -> &str {
+    content_type.split(';').next().unwrap_or_default().trim()
+}
+
+/// The index of the offered media type with the highest quality in `Accept`, the earliest one on
+/// ties. Each offer takes the quality of the most specific range matching it.
+fn choose<'a>(request: &Request, offers: impl Iterator<Item = &'a str>) -> Option<usize> {
+    let ranges = request
+        .headers()
+        .get_all(ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(negotiation::entries)
+        .collect::<Vec<_>>();
+    if ranges.is_empty() {
+        return Some(0);
+    }
+
+    let mut best: Option<(usize, f32)> = None;
+    for (index, offer) in offers.enumerate() {
+        let quality = ranges
+            .iter()
+            .filter_map(|(range, quality)| specificity(range, offer).map(|s| (s, *quality)))
+            .max_by_key(|(specificity, _)| *specificity)
+            .map_or(0.0, |(_, quality)| quality);
+        if quality > 0.0 && best.is_none_or(|(_, best)| quality > best) {
+            best = Some((index, quality));
+        }
+    }
+    best.map(|(index, _)| index)
+}
+
+/// How specifically a media range such as `text/*` matches `offer`, if it matches at all.
+fn specificity(range: &str, offer: &str) -> Option<u8> {
+    if range == "*/*" {
+        return Some(0);
+    }
+    let (range_type, range_subtype) = range.split_once('/')?;
+    let (offer_type, offer_subtype) = offer.split_once('/')?;
+    if !range_type.eq_ignore_ascii_case(offer_type) {
+        return None;
+    }
+    if range_subtype == "*" {
+        Some(1)
+    } else {
+        range_subtype
+            .eq_ignore_ascii_case(offer_subtype)
+            .then_some(2)
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::Negotiate;
+    use crate::{
+        header::{HeaderValue, ACCEPT, CONTENT_TYPE, VARY},
+        routing::{CreateRouteNode, Route, Router},
+        Body, Endpoint, Request, Response, Result, StatusCode,
+    };
+    use serde::Serialize;
+
+    #[derive(Serialize)]
+    struct User {
+        id: u32,
+        name: &'static str,
+    }
+
+    fn router() -> Router {
+        Route::new(("/users".at(|| async {
+            let users = vec![
+                User {
+                    id: 1,
+                    name: "Lexo",
+                },
+                User { id: 2, name: "Ada" },
+            ];
+            let negotiate =
+                Negotiate::new(users).html(|users| format!("<p>{} users</p>", users.len()));
+            #[cfg(feature = "csv")]
+            let negotiate = negotiate.csv();
+            Result::Ok(negotiate)
+        }),))
+        .build()
+    }
+
+    async fn get(accept: Option<&'static str>) -> Response {
+        let mut request = Request::new(Body::empty());
+        *request.uri_mut() = "/users".parse().unwrap();
+        if let Some(accept) = accept {
+            request
+                .headers_mut()
+                .insert(ACCEPT, HeaderValue::from_static(accept));
+        }
+        router().respond(&mut request).await.unwrap()
+    }
+
+    async fn body(response: Response) -> String {
+        let body = response.into_body().into_string().await.unwrap();
+        body.as_str().to_owned()
+    }
+
+    #[tokio::test]
+    async fn prefers_the_highest_quality() {
+        let response = get(Some("text/html, */*;q=0.1")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[CONTENT_TYPE], "text/html; charset=utf-8");
+        assert_eq!(response.headers()[VARY], "Accept");
+        assert_eq!(body(response).await, "<p>2 users</p>");
+
+        let response = get(Some("text/html;q=0.5, application/*")).await;
+        assert_eq!(response.headers()[CONTENT_TYPE], "application/json");
+        assert_eq!(
+            body(response).await,
+            r#"[{"id":1,"name":"Lexo"},{"id":2,"name":"Ada"}]"#
+        );
+    }
+
+    #[tokio::test]
+    async fn defaults_to_json() {
+        for accept in [None, Some("*/*")] {
+            let response = get(accept).await;
+            assert_eq!(response.headers()[CONTENT_TYPE], "application/json");
+        }
+    }
+
+    #[tokio::test]
+    async fn lists_offers_when_nothing_is_acceptable() {
+        let response = get(Some("image/png, application/json;q=0")).await;
+        assert_eq!(response.status(), StatusCode::NOT_ACCEPTABLE);
+        let body = body(response).await;
+        assert!(body.contains("application/json, text/html"), "{body}");
+    }
+
+    #[cfg(feature = "csv")]
+    #[tokio::test]
+    async fn renders_lists_as_csv() {
+        let response = get(Some("text/csv")).await;
+        assert_eq!(response.headers()[CONTENT_TYPE], "text/csv; charset=utf-8");
+        assert_eq!(body(response).await, "id,name\n1,Lexo\n2,Ada\n");
+    }
+}
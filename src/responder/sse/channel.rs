@@ -1,26 +1,52 @@
 use std::{
     convert::Infallible,
+    fmt,
     pin::Pin,
     task::{ready, Context, Poll},
 };
 
-use async_channel::unbounded;
-use http_kit::{http_error, utils::Stream};
+use async_channel::bounded;
+use http_kit::{utils::Stream, HttpError, StatusCode};
 use pin_project_lite::pin_project;
 
 use super::{Event, Sse};
 
 /// Sender of SSE channel.
+///
+/// The channel buffers a bounded number of events, so `send` waits while a slow client catches
+/// up. Once the client disconnects and the response body is dropped, `send` fails with
+/// [`SendError::Disconnected`].
 /// # Warning
-/// If you don't return SSE responder in your handler.`send` method will keep await.And event stream cannot start.
+/// If you don't return SSE responder in your handler.`send` method will keep await once the buffer is full.And event stream cannot start.
 #[derive(Debug, Clone)]
 pub struct Sender {
     sender: async_channel::Sender<Event>,
 }
 
-http_error!(
-    /// An error occurred when sending an event to the SSE channel.
-    pub SendError, http_kit::StatusCode::INTERNAL_SERVER_ERROR, "Failed to send event to SSE channel");
+/// An error occurred when sending an event to the SSE channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SendError {
+    /// The SSE stream has been dropped, usually because the client disconnected.
+    Disconnected,
+}
+
+impl fmt::Display for SendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Disconnected => {
+                f.write_str("Failed to send event to SSE channel: client disconnected")
+            }
+        }
+    }
+}
+
+impl core::error::Error for SendError {}
+
+impl HttpError for SendError {
+    fn status(&self) -> StatusCode {
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+}
 
 pin_project! {
     struct Receiver{
@@ -43,26 +69,62 @@ impl Stream for Receiver {
 }
 
 impl Sender {
-    pub(crate) fn new() -> (Self, Sse) {
-        let (sender, receiver) = unbounded();
+    pub(crate) fn new(capacity: usize) -> (Self, Sse) {
+        let (sender, receiver) = bounded(capacity);
         (Self { sender }, Sse::from_stream(Receiver::new(receiver)))
     }
 
-    /// Send an event to the stream.
+    /// Send an event to the stream, waiting while the buffer is full.
     ///
     /// # Errors
     ///
-    /// Returns a [`SendError`] if the event cannot be sent to the stream, for example if the receiver has been dropped.
+    /// Returns [`SendError::Disconnected`] if the stream has been dropped.
     pub async fn send(&self, event: Event) -> Result<(), SendError> {
-        self.sender.send(event).await.map_err(|_| SendError::new())
+        self.sender
+            .send(event)
+            .await
+            .map_err(|_| SendError::Disconnected)
+    }
+
+    /// Whether the stream has been dropped, so that no further event can be sent.
+    #[must_use]
+    pub fn is_closed(&self) -> bool {
+        self.sender.is_closed()
     }
 
     /// Send an event with a data payload to the stream.
     ///
     /// # Errors
     ///
-    /// Returns a [`SendError`] if the event cannot be sent to the stream, for example if the receiver has been dropped.
+    /// Returns [`SendError::Disconnected`] if the stream has been dropped.
     pub async fn send_data(&self, data: impl AsRef<str>) -> Result<(), SendError> {
         self.send(Event::data(data)).await
     }
 }
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use std::time::Duration;
+
+    use super::SendError;
+    use crate::responder::{sse::Event, Sse};
+
+    #[tokio::test]
+    async fn send_fails_once_the_client_disconnects() {
+        let (sender, sse) = Sse::channel_with_capacity(1);
+        sender.send_data("buffered").await.unwrap();
+
+        let pending = tokio::spawn({
+            let sender = sender.clone();
+            async move { sender.send(Event::data("blocked")).await }
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(!pending.is_finished());
+
+        // Hyper drops the response body when the client goes away.
+        drop(sse);
+        assert_eq!(pending.await.unwrap(), Err(SendError::Disconnected));
+        assert!(sender.is_closed());
+        assert_eq!(sender.send_data("late").await, Err(SendError::Disconnected));
+    }
+}
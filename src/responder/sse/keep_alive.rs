@@ -0,0 +1,203 @@
+use std::{
+    borrow::Cow,
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use http_kit::{
+    utils::{Bytes, Stream},
+    BodyError,
+};
+use pin_project_lite::pin_project;
+
+use super::Event;
+
+type Sleep = Pin<Box<dyn Future<Output = ()> + Send + Sync>>;
+type Timer = Arc<dyn Fn(Duration) -> Sleep + Send + Sync>;
+
+/// Heartbeat sent on an idle SSE stream so that proxies keep the connection open.
+///
+/// When no event has been produced for `interval`, a comment line (`:ka` by default) is written.
+/// Comments are ignored by `EventSource` clients.
+#[derive(Clone)]
+pub struct KeepAlive {
+    interval: Duration,
+    text: Cow<'static, str>,
+    timer: Timer,
+}
+
+impl KeepAlive {
+    /// Send a heartbeat after `interval` without events.
+    #[must_use]
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            text: Cow::Borrowed("ka"),
+            timer: Arc::new(default_sleep),
+        }
+    }
+
+    /// Set the text of the heartbeat comment.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the text includes a newline.
+    #[must_use]
+    pub fn text(mut self, text: impl Into<Cow<'static, str>>) -> Self {
+        let text = text.into();
+        assert!(
+            !super::has_newline(text.as_bytes()),
+            "SSE keep-alive text cannot include newline"
+        );
+        self.text = text;
+        self
+    }
+
+    /// Use a custom timer, such as the sleep function of the runtime in use.
+    #[must_use]
+    pub fn timer<F, Fut>(mut self, timer: F) -> Self
+    where
+        F: Fn(Duration) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + Sync + 'static,
+    {
+        self.timer = Arc::new(move |duration| Box::pin(timer(duration)));
+        self
+    }
+
+    fn sleep(&self) -> Sleep {
+        (self.timer)(self.interval)
+    }
+}
+
+impl fmt::Debug for KeepAlive {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeepAlive")
+            .field("interval", &self.interval)
+            .field("text", &self.text)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn default_sleep(duration: Duration) -> Sleep {
+    Box::pin(async move {
+        async_io::Timer::after(duration).await;
+    })
+}
+
+#[cfg(target_arch = "wasm32")]
+fn default_sleep(duration: Duration) -> Sleep {
+    use wasm_bindgen::JsCast;
+
+    struct SendFuture(wasm_bindgen_futures::JsFuture);
+
+    // SAFETY: WASM runs on a single thread.
+    #[allow(clippy::non_send_fields_in_send_ty)]
+    unsafe impl Send for SendFuture {}
+    unsafe impl Sync for SendFuture {}
+
+    impl Future for SendFuture {
+        type Output = ();
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+            Pin::new(&mut self.0).poll(cx).map(|_| ())
+        }
+    }
+
+    let millis = i32::try_from(duration.as_millis()).unwrap_or(i32::MAX);
+    let promise = js_sys::Promise::new(&mut |resolve, _reject| {
+        let global = js_sys::global();
+        if let Ok(set_timeout) = js_sys::Reflect::get(&global, &"setTimeout".into())
+            .and_then(JsCast::dyn_into::<js_sys::Function>)
+        {
+            let _ = set_timeout.call2(&global, &resolve, &millis.into());
+        }
+    });
+    Box::pin(SendFuture(wasm_bindgen_futures::JsFuture::from(promise)))
+}
+
+pin_project! {
+    pub(super) struct KeepAliveStream<S> {
+        #[pin]
+        stream: S,
+        keep_alive: KeepAlive,
+        comment: Bytes,
+        sleep: Sleep,
+    }
+}
+
+impl<S> KeepAliveStream<S> {
+    pub(super) fn new(stream: S, keep_alive: KeepAlive) -> Self {
+        let comment = Event::comment(&keep_alive.text).finalize();
+        let sleep = keep_alive.sleep();
+        Self {
+            stream,
+            keep_alive,
+            comment,
+            sleep,
+        }
+    }
+}
+
+impl<S> Stream for KeepAliveStream<S>
+where
+    S: Stream<Item = Result<Bytes, BodyError>>,
+{
+    type Item = Result<Bytes, BodyError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        if let Poll::Ready(item) = this.stream.poll_next(cx) {
+            *this.sleep = this.keep_alive.sleep();
+            return Poll::Ready(item);
+        }
+        if this.sleep.as_mut().poll(cx).is_ready() {
+            *this.sleep = this.keep_alive.sleep();
+            return Poll::Ready(Some(Ok(this.comment.clone())));
+        }
+        Poll::Pending
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use std::{convert::Infallible, time::Duration};
+
+    use futures_util::{stream, StreamExt};
+
+    use super::KeepAlive;
+    use crate::responder::{sse::Event, Sse};
+
+    #[tokio::test(start_paused = true)]
+    async fn interleaves_heartbeats_between_idle_events() {
+        let events =
+            stream::iter([Event::data("first"), Event::data("second")]).then(|event| async move {
+                if event.buffer.ends_with(b"second\n") {
+                    tokio::time::sleep(Duration::from_secs(50)).await;
+                }
+                Ok::<_, Infallible>(event)
+            });
+        let keep_alive = KeepAlive::new(Duration::from_secs(15))
+            .text("ping")
+            .timer(tokio::time::sleep);
+        let mut body = Sse::from_stream(events).keep_alive_with(keep_alive).stream;
+
+        let mut chunks = Vec::new();
+        while let Some(chunk) = body.next().await {
+            chunks.push(String::from_utf8(chunk.unwrap().to_vec()).unwrap());
+        }
+        assert_eq!(
+            chunks,
+            [
+                "data:first\n\n",
+                ":ping\n\n",
+                ":ping\n\n",
+                ":ping\n\n",
+                "data:second\n\n"
+            ]
+        );
+    }
+}
@@ -24,21 +24,95 @@
 //!     sse
 //! }
 //! ```
+//!
+//! # Reconnection
+//! Give each event an id. When the connection drops, the browser reconnects with the last id it
+//! received in the `Last-Event-ID` header, which [`LastEventId`] extracts so that the handler can
+//! resume right after it.
+//! ```
+//! use futures_util::stream::iter;
+//! use skyzen::{
+//!     header::HeaderValue,
+//!     responder::{sse::{Event, EventError, LastEventId}, Sse},
+//!     routing::{CreateRouteNode, Route},
+//!     Body, Method, Request, Result,
+//! };
+//!
+//! const FEED: [&str; 3] = ["one", "two", "three"];
+//!
+//! async fn feed(LastEventId(last): LastEventId) -> Result<Sse> {
+//!     let start = last.and_then(|id| id.parse::<usize>().ok()).map_or(0, |id| id + 1);
+//!     let events = FEED
+//!         .iter()
+//!         .enumerate()
+//!         .skip(start)
+//!         .map(|(id, data)| Event::builder().id(id.to_string()).data(data))
+//!         .collect::<std::result::Result<Vec<_>, EventError>>()?;
+//!     Ok(Sse::from_stream(iter(events.into_iter().map(Ok::<_, EventError>))))
+//! }
+//!
+//! # futures_lite::future::block_on(async {
+//! let router = Route::new(("/feed".at(feed),)).build();
+//! let mut request = Request::new(Body::empty());
+//! *request.method_mut() = Method::GET;
+//! *request.uri_mut() = "/feed".parse().unwrap();
+//! request.headers_mut().insert("last-event-id", HeaderValue::from_static("0"));
+//! let body = router.go(request).await.unwrap().into_body().into_string().await.unwrap();
+//! assert_eq!(body, "id:1\ndata:two\n\nid:2\ndata:three\n\n");
+//! # });
+//! ```
+//!
+//! # `OpenAPI`
+//! Routes returning [`Sse`] are documented as `text/event-stream` responses. To describe the
+//! payload of the events, return [`Sse::typed`] instead:
+//! ```
+//! use skyzen::{responder::{sse::TypedSse, Sse}, ToSchema};
+//!
+//! #[derive(ToSchema)]
+//! struct Price {
+//!     symbol: String,
+//!     cents: u64,
+//! }
+//!
+//! async fn prices() -> TypedSse<Price> {
+//!     let (sender, sse) = Sse::channel();
+//!     sender.send_data(r#"{"symbol":"ACME","cents":1250}"#);
+//!     sse.typed()
+//! }
+//! ```
+//!
+//! # Keep-alive
+//! Proxies tend to close connections that stay silent for too long. [`Sse::keep_alive`] writes
+//! a comment whenever no event has been produced within the interval.
+//! ```
+//! use skyzen::responder::Sse;
+//! use std::time::Duration;
+//! async fn handler() -> Sse{
+//!     let(sender,sse) = Sse::channel_with_keep_alive(Duration::from_secs(15));
+//!     sender.send_data("Hello!");
+//!     sse
+//! }
+//! ```
 mod channel;
 pub use channel::{SendError, Sender};
+mod keep_alive;
+pub use keep_alive::KeepAlive;
+use keep_alive::KeepAliveStream;
 
+use bytes::{BufMut, Bytes, BytesMut};
+use core::fmt;
 use futures_util::TryStreamExt;
 use itoa::Buffer;
 
 use http_kit::{
     header::{self, HeaderValue},
     utils::Stream,
-    Body, BodyError, Request, Response,
+    Body, BodyError, HttpError, Request, Response, StatusCode,
 };
 use pin_project_lite::pin_project;
 #[cfg(feature = "json")]
 use serde::Serialize;
-use skyzen_core::Responder;
+use skyzen_core::{Extractor, Responder};
 use std::{
     convert::Infallible,
     marker::PhantomData,
@@ -50,7 +124,7 @@ use std::{
 /// A SSE event
 #[derive(Debug)]
 pub struct Event {
-    buffer: Vec<u8>,
+    buffer: BytesMut,
     has_id: bool,
     has_event_field: bool,
 }
@@ -60,18 +134,23 @@ fn has_newline(v: &[u8]) -> bool {
 }
 
 impl Event {
-    const fn empty() -> Self {
+    fn with_capacity(capacity: usize) -> Self {
         Self {
-            buffer: Vec::new(),
+            buffer: BytesMut::with_capacity(capacity),
             has_id: false,
             has_event_field: false,
         }
     }
 
+    // Room for `name: value\n` plus the blank line ending the event.
+    const fn field_len(name: &str, value: &str) -> usize {
+        name.len() + value.len() + 4
+    }
+
     /// Create an SSE event with a data payload.
     pub fn data(data: impl AsRef<str>) -> Self {
-        let mut event = Self::empty();
         let data = data.as_ref();
+        let mut event = Self::with_capacity(Self::field_len("data", data));
         event.field("data", data);
         event
     }
@@ -83,16 +162,16 @@ impl Event {
     /// Returns an error if serialization of the value to JSON fails.
     #[cfg(feature = "json")]
     pub fn json(v: impl Serialize) -> serde_json::Result<Self> {
-        let mut event = Self::empty();
+        let mut event = Self::with_capacity(64);
         event.buffer.extend_from_slice(b"data:");
-        serde_json::to_writer(&mut event.buffer, &v)?;
+        serde_json::to_writer((&mut event.buffer).writer(), &v)?;
         Ok(event)
     }
 
     /// A comment for the stream,being ignored by most of client.
     pub fn comment(message: impl AsRef<str>) -> Self {
-        let mut event = Self::empty();
         let message = message.as_ref();
+        let mut event = Self::with_capacity(Self::field_len("", message));
         event.field("", message);
         // Prevent including event and id in comment
         event.has_event_field = true;
@@ -103,7 +182,7 @@ impl Event {
     /// Tell the client the stream's reconnection time.
     #[must_use]
     pub fn retry(duration: Duration) -> Self {
-        let mut event = Self::empty();
+        let mut event = Self::with_capacity(32);
         event.field("retry", Buffer::new().format(duration.as_millis()));
         // Prevent including event and id in comment.
         event.has_event_field = true;
@@ -111,18 +190,53 @@ impl Event {
         event
     }
 
+    /// Build an event whose fields are validated before it is created.
+    #[must_use]
+    pub const fn builder() -> EventBuilder {
+        EventBuilder {
+            event: None,
+            id: None,
+        }
+    }
+
     /// Set the id of this event.
     /// The id is useful in reconnection.See [The `Last-Event-ID` header](https://html.spec.whatwg.org/multipage/server-sent-events.html#the-last-event-id-header) for more information.
     ///
+    /// # Errors
+    ///
+    /// Returns an error if the id has already been set or includes a newline.
+    pub fn try_id(mut self, id: impl AsRef<str>) -> Result<Self, EventError> {
+        if self.has_id {
+            return Err(EventError::AlreadySet("id"));
+        }
+        self.try_field("id", id.as_ref())?;
+        self.has_id = true;
+        Ok(self)
+    }
+
+    /// Set the event of this event.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the event has already been set or includes a newline.
+    pub fn try_event(mut self, event: impl AsRef<str>) -> Result<Self, EventError> {
+        if self.has_event_field {
+            return Err(EventError::AlreadySet("event"));
+        }
+        self.try_field("event", event.as_ref())?;
+        self.has_event_field = true;
+        Ok(self)
+    }
+
+    /// Set the id of this event.
+    ///
     /// # Panics
     ///
     /// Panics if the id has already been set.
+    #[deprecated(note = "use `Event::try_id` or `Event::builder` instead")]
     #[must_use]
-    pub fn id(mut self, id: impl AsRef<str>) -> Self {
-        assert!(!self.has_id, "Id has already been set");
-        let id = id.as_ref();
-        self.field("id", id);
-        self
+    pub fn id(self, id: impl AsRef<str>) -> Self {
+        self.try_id(id).unwrap_or_else(|error| panic!("{error}"))
     }
 
     /// Set the event of this event.
@@ -130,13 +244,19 @@ impl Event {
     /// # Panics
     ///
     /// Panics if the event has already been set.
+    #[deprecated(note = "use `Event::try_event` or `Event::builder` instead")]
     #[must_use]
-    pub fn event(mut self, event: impl AsRef<str>) -> Self {
-        assert!(!self.has_event_field, "Event has already been set");
-        let event = event.as_ref();
-        self.field("event", event);
-        self.has_event_field = true;
-        self
+    pub fn event(self, event: impl AsRef<str>) -> Self {
+        self.try_event(event)
+            .unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    fn try_field(&mut self, name: &'static str, value: &str) -> Result<(), EventError> {
+        if has_newline(value.as_bytes()) {
+            return Err(EventError::Newline(name));
+        }
+        self.field(name, value);
+        Ok(())
     }
 
     // Warning: the value cannot include `\r` or `\n`
@@ -153,7 +273,7 @@ impl Event {
         let value = value.as_bytes();
 
         if value.starts_with(b" ") {
-            self.buffer.push(b' ');
+            self.buffer.put_u8(b' ');
         }
 
         self.buffer.extend_from_slice(value);
@@ -161,9 +281,140 @@ impl Event {
         self.buffer.extend_from_slice(b"\n");
     }
 
-    fn finalize(mut self) -> Vec<u8> {
-        self.buffer.push(b'\n');
-        self.buffer
+    fn finalize(mut self) -> Bytes {
+        self.buffer.put_u8(b'\n');
+        self.buffer.freeze()
+    }
+}
+
+/// Builder of an [`Event`], created by [`Event::builder`].
+///
+/// ```
+/// # use skyzen::responder::sse::{Event, EventError};
+/// let event = Event::builder().event("update").id("42").data("hello")?;
+/// # Ok::<_, EventError>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct EventBuilder {
+    event: Option<String>,
+    id: Option<String>,
+}
+
+impl EventBuilder {
+    /// Set the event type.
+    #[must_use]
+    pub fn event(mut self, event: impl Into<String>) -> Self {
+        self.event = Some(event.into());
+        self
+    }
+
+    /// Set the event id.
+    #[must_use]
+    pub fn id(mut self, id: impl Into<String>) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    fn build(self, payload: usize) -> Result<Event, EventError> {
+        let capacity = payload
+            + self
+                .event
+                .as_deref()
+                .map_or(0, |name| Event::field_len("event", name))
+            + self
+                .id
+                .as_deref()
+                .map_or(0, |id| Event::field_len("id", id));
+        let mut event = Event::with_capacity(capacity);
+        if let Some(name) = self.event {
+            event = event.try_event(name)?;
+        }
+        if let Some(id) = self.id {
+            event = event.try_id(id)?;
+        }
+        Ok(event)
+    }
+
+    /// Finish with a data payload.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any field includes a newline.
+    pub fn data(self, data: impl AsRef<str>) -> Result<Event, EventError> {
+        let data = data.as_ref();
+        let mut event = self.build(Event::field_len("data", data))?;
+        event.try_field("data", data)?;
+        Ok(event)
+    }
+
+    /// Finish with a data payload in json format.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any field includes a newline or the value cannot be serialized.
+    #[cfg(feature = "json")]
+    pub fn json(self, value: impl Serialize) -> Result<Event, EventError> {
+        let mut event = self.build(64)?;
+        event.buffer.extend_from_slice(b"data:");
+        serde_json::to_writer((&mut event.buffer).writer(), &value).map_err(EventError::Json)?;
+        event.buffer.put_u8(b'\n');
+        Ok(event)
+    }
+}
+
+/// An error occurred while building an [`Event`].
+#[derive(Debug)]
+pub enum EventError {
+    /// The field has already been set.
+    AlreadySet(&'static str),
+    /// The value of the field includes a newline.
+    Newline(&'static str),
+    /// The json payload could not be serialized.
+    #[cfg(feature = "json")]
+    Json(serde_json::Error),
+}
+
+impl fmt::Display for EventError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AlreadySet(field) => write!(f, "SSE field `{field}` has already been set"),
+            Self::Newline(field) => write!(f, "SSE field `{field}` cannot include newline"),
+            #[cfg(feature = "json")]
+            Self::Json(error) => write!(f, "Failed to serialize SSE data: {error}"),
+        }
+    }
+}
+
+impl core::error::Error for EventError {}
+
+impl HttpError for EventError {
+    fn status(&self) -> StatusCode {
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+}
+
+/// The `Last-Event-ID` header sent by a reconnecting client, if any.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LastEventId(pub Option<String>);
+
+impl LastEventId {
+    /// Borrow the id of the last event received by the client.
+    #[must_use]
+    pub fn as_str(&self) -> Option<&str> {
+        self.0.as_deref()
+    }
+}
+
+impl Extractor for LastEventId {
+    type Error = Infallible;
+    async fn extract(request: &mut Request) -> Result<Self, Self::Error> {
+        Ok(Self(
+            request
+                .headers()
+                .get("last-event-id")
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_owned),
+        ))
     }
 }
 
@@ -174,18 +425,23 @@ pub struct Sse {
 }
 
 pin_project! {
+    // Joins the events that are already available into one chunk, up to `max_bytes`.
     struct IntoStream<S,E>{
         #[pin]
         stream:S,
-        _marker:PhantomData<E>
+        max_bytes:usize,
+        error:Option<E>,
+        done:bool,
     }
 }
 
 impl<S, E> IntoStream<S, E> {
-    pub const fn new(stream: S) -> Self {
+    pub const fn new(stream: S, max_bytes: usize) -> Self {
         Self {
             stream,
-            _marker: PhantomData,
+            max_bytes,
+            error: None,
+            done: false,
         }
     }
 }
@@ -195,38 +451,146 @@ where
     S: Stream<Item = Result<Event, E>>,
     E: core::error::Error + Send + Sync + 'static,
 {
-    type Item = Result<Vec<u8>, E>;
+    type Item = Result<Bytes, E>;
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        Poll::Ready(ready!(self.project().stream.poll_next(cx)))
-            .map(|result| result.map(|data| data.map(Event::finalize)))
+        let mut this = self.project();
+        if let Some(error) = this.error.take() {
+            return Poll::Ready(Some(Err(error)));
+        }
+        if *this.done {
+            return Poll::Ready(None);
+        }
+        let first = match ready!(this.stream.as_mut().poll_next(cx)) {
+            Some(Ok(event)) => event.finalize(),
+            Some(Err(error)) => return Poll::Ready(Some(Err(error))),
+            None => {
+                *this.done = true;
+                return Poll::Ready(None);
+            }
+        };
+
+        let mut batch: Option<BytesMut> = None;
+        while batch.as_ref().map_or(first.len(), BytesMut::len) < *this.max_bytes {
+            match this.stream.as_mut().poll_next(cx) {
+                Poll::Ready(Some(Ok(event))) => {
+                    let event = event.finalize();
+                    batch
+                        .get_or_insert_with(|| {
+                            let mut batch = BytesMut::with_capacity(*this.max_bytes);
+                            batch.extend_from_slice(&first);
+                            batch
+                        })
+                        .extend_from_slice(&event);
+                }
+                Poll::Ready(Some(Err(error))) => {
+                    *this.error = Some(error);
+                    break;
+                }
+                Poll::Ready(None) => {
+                    *this.done = true;
+                    break;
+                }
+                Poll::Pending => break,
+            }
+        }
+        Poll::Ready(Some(Ok(batch.map_or(first, BytesMut::freeze))))
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
-        self.stream.size_hint()
+        let (lower, upper) = self.stream.size_hint();
+        (lower.min(1), upper)
     }
 }
 
 impl Sse {
-    /// Create a paif of sender and SSE responder
+    /// Capacity of the buffer created by [`Sse::channel`].
+    pub const DEFAULT_CHANNEL_CAPACITY: usize = 32;
+
+    /// Size up to which [`Sse::from_stream`] joins ready events into one body chunk.
+    pub const DEFAULT_BATCH_BYTES: usize = 16 * 1024;
+
+    /// Create a pair of sender and SSE responder buffering up to
+    /// [`DEFAULT_CHANNEL_CAPACITY`](Self::DEFAULT_CHANNEL_CAPACITY) events.
     #[must_use]
     pub fn channel() -> (Sender, Self) {
-        Sender::new()
+        Sender::new(Self::DEFAULT_CHANNEL_CAPACITY)
+    }
+
+    /// Create a pair of sender and SSE responder buffering up to `capacity` events.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    #[must_use]
+    pub fn channel_with_capacity(capacity: usize) -> (Sender, Self) {
+        assert!(capacity > 0, "SSE channel capacity must be non-zero");
+        Sender::new(capacity)
+    }
+
+    /// Create a pair of sender and SSE responder that sends a heartbeat after `interval`
+    /// without events.
+    #[must_use]
+    pub fn channel_with_keep_alive(interval: Duration) -> (Sender, Self) {
+        let (sender, sse) = Self::channel();
+        (sender, sse.keep_alive(interval))
     }
 
     /// Create a SSE responder with a stream.
+    ///
+    /// Events that are ready at the same time are written as one body chunk of up to
+    /// [`DEFAULT_BATCH_BYTES`](Self::DEFAULT_BATCH_BYTES).
     pub fn from_stream<S, E>(stream: S) -> Self
+    where
+        S: Send + Sync + Stream<Item = Result<Event, E>> + 'static,
+        E: Send + Sync + core::error::Error + 'static,
+    {
+        Self::from_stream_batched(stream, Self::DEFAULT_BATCH_BYTES)
+    }
+
+    /// Create a SSE responder with a stream, joining ready events into body chunks of up to
+    /// `max_bytes`. With `0`, every event is a chunk of its own.
+    pub fn from_stream_batched<S, E>(stream: S, max_bytes: usize) -> Self
     where
         S: Send + Sync + Stream<Item = Result<Event, E>> + 'static,
         E: Send + Sync + core::error::Error + 'static,
     {
         Self {
-            stream: Body::from_stream(IntoStream::new(stream).map_err(|error| {
+            stream: Body::from_stream(IntoStream::new(stream, max_bytes).map_err(|error| {
                 BodyError::Other(Box::new(error)) // TODO: improve error handling, currently we just box the error
             })),
         }
     }
+
+    /// Send a `:ka` comment whenever no event has been produced within `interval`.
+    #[must_use]
+    pub fn keep_alive(self, interval: Duration) -> Self {
+        self.keep_alive_with(KeepAlive::new(interval))
+    }
+
+    /// Send heartbeats according to `keep_alive`, allowing a custom comment text or timer.
+    #[must_use]
+    pub fn keep_alive_with(self, keep_alive: KeepAlive) -> Self {
+        Self {
+            stream: Body::from_stream(KeepAliveStream::new(self.stream, keep_alive)),
+        }
+    }
+
+    /// Document the `data` of each event as `T` in the `OpenAPI` document.
+    ///
+    /// The stream itself is unchanged, sending data that matches `T` is up to the caller.
+    #[must_use]
+    pub fn typed<T>(self) -> TypedSse<T> {
+        TypedSse {
+            sse: self,
+            payload: PhantomData,
+        }
+    }
 }
 
+#[cfg(feature = "openapi")]
+const SSE_DESCRIPTION: &str =
+    "Server-sent event stream: each event is a block of `field:value` lines ended by a blank line";
+
 impl Responder for Sse {
     type Error = Infallible;
     fn respond_to(self, _request: &Request, response: &mut Response) -> Result<(), Self::Error> {
@@ -240,4 +604,122 @@ impl Responder for Sse {
         *response.body_mut() = self.stream;
         Ok(())
     }
+
+    #[cfg(feature = "openapi")]
+    fn openapi() -> Option<Vec<crate::openapi::ResponseSchema>> {
+        Some(vec![crate::openapi::ResponseSchema {
+            status: None,
+            description: Some(SSE_DESCRIPTION),
+            schema: Some(skyzen_core::openapi::plain_string_schema()),
+            content_type: Some("text/event-stream"),
+            example: None,
+        }])
+    }
+}
+
+/// SSE responder whose event data is documented as `T`, created by [`Sse::typed`].
+pub struct TypedSse<T> {
+    sse: Sse,
+    payload: PhantomData<fn() -> T>,
+}
+
+impl<T> fmt::Debug for TypedSse<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TypedSse").field("sse", &self.sse).finish()
+    }
+}
+
+impl<T: crate::openapi::DocumentedSchema + 'static> Responder for TypedSse<T> {
+    type Error = Infallible;
+    fn respond_to(self, request: &Request, response: &mut Response) -> Result<(), Self::Error> {
+        self.sse.respond_to(request, response)
+    }
+
+    #[cfg(feature = "openapi")]
+    fn openapi() -> Option<Vec<crate::openapi::ResponseSchema>> {
+        Some(vec![crate::openapi::ResponseSchema {
+            status: None,
+            description: Some(SSE_DESCRIPTION),
+            schema: crate::openapi::schema_of::<T>(),
+            content_type: Some("text/event-stream"),
+            example: None,
+        }])
+    }
+
+    #[cfg(feature = "openapi")]
+    fn register_openapi_schemas(
+        defs: &mut std::collections::BTreeMap<String, crate::openapi::SchemaRef>,
+    ) {
+        crate::openapi::register_schema_for::<T>(defs);
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use std::convert::Infallible;
+
+    use futures_util::{stream::iter, StreamExt};
+
+    use super::{Event, EventError, LastEventId, Sse};
+    use crate::{Body, Request};
+    use http_kit::header::HeaderValue;
+    use skyzen_core::Extractor;
+
+    #[test]
+    fn builder_validates_fields() {
+        let event = Event::builder().event("update").id("7").data("hi").unwrap();
+        assert_eq!(event.finalize(), &b"event:update\nid:7\ndata:hi\n\n"[..]);
+
+        let error = Event::builder().id("1\n2").data("hi").unwrap_err();
+        assert!(matches!(error, EventError::Newline("id")));
+
+        let error = Event::data("hi")
+            .try_id("1")
+            .unwrap()
+            .try_id("2")
+            .unwrap_err();
+        assert_eq!(error.to_string(), "SSE field `id` has already been set");
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn builder_serializes_json() {
+        let event = Event::builder().id("3").json([1, 2]).unwrap();
+        assert_eq!(event.finalize(), &b"id:3\ndata:[1,2]\n\n"[..]);
+    }
+
+    #[tokio::test]
+    async fn batches_ready_events_without_changing_the_bytes() {
+        let events = || iter(["a", "b", "c"].map(|data| Ok::<_, Infallible>(Event::data(data))));
+        let chunks = |sse: Sse| async move {
+            sse.stream
+                .map(|chunk| String::from_utf8(chunk.unwrap().to_vec()).unwrap())
+                .collect::<Vec<_>>()
+                .await
+        };
+
+        let batched = chunks(Sse::from_stream(events())).await;
+        assert_eq!(batched, ["data:a\n\ndata:b\n\ndata:c\n\n"]);
+
+        let single = chunks(Sse::from_stream_batched(events(), 0)).await;
+        assert_eq!(single, ["data:a\n\n", "data:b\n\n", "data:c\n\n"]);
+
+        let limited = chunks(Sse::from_stream_batched(events(), 10)).await;
+        assert_eq!(limited, ["data:a\n\ndata:b\n\n", "data:c\n\n"]);
+    }
+
+    #[tokio::test]
+    async fn extracts_last_event_id() {
+        let mut request = Request::new(Body::empty());
+        assert_eq!(
+            LastEventId::extract(&mut request).await.unwrap(),
+            LastEventId(None)
+        );
+
+        request
+            .headers_mut()
+            .insert("last-event-id", HeaderValue::from_static("41"));
+        let id = LastEventId::extract(&mut request).await.unwrap();
+        assert_eq!(id.as_str(), Some("41"));
+    }
 }
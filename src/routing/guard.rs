@@ -0,0 +1,84 @@
+use std::sync::Arc;
+
+use http_kit::{header, Request};
+
+/// Predicate deciding whether an endpoint accepts a request, see [`Route::guard`](super::Route::guard).
+pub type Guard = Arc<dyn Fn(&Request) -> bool + Send + Sync>;
+
+/// Build a guard that accepts requests whose host matches `pattern`.
+///
+/// `pattern` is either an exact host such as `api.example.com` or a wildcard such as
+/// `*.example.com`, which matches any subdomain but not `example.com` itself. Ports are ignored
+/// and matching is case-insensitive.
+pub fn host(pattern: &str) -> Guard {
+    let pattern = pattern.trim_end_matches('.').to_ascii_lowercase();
+    Arc::new(move |request| request_host(request).is_some_and(|host| host_matches(&pattern, host)))
+}
+
+fn host_matches(pattern: &str, host: &str) -> bool {
+    pattern.strip_prefix('*').map_or_else(
+        || host.eq_ignore_ascii_case(pattern),
+        |suffix| {
+            host.len() > suffix.len()
+                && host
+                    .get(host.len() - suffix.len()..)
+                    .is_some_and(|tail| tail.eq_ignore_ascii_case(suffix))
+        },
+    )
+}
+
+/// The request host without its port, taken from `Host` or, for HTTP/2, the URI authority.
+fn request_host(request: &Request) -> Option<&str> {
+    let host = request
+        .headers()
+        .get(header::HOST)
+        .and_then(|value| value.to_str().ok())
+        .or_else(|| request.uri().host())?;
+
+    let host = if host.starts_with('[') {
+        host.find(']').map_or(host, |end| &host[..=end])
+    } else {
+        host.split(':').next().unwrap_or(host)
+    };
+    Some(host.trim_end_matches('.'))
+}
+
+#[cfg(test)]
+mod tests {
+    use http_kit::{header, Body, Request};
+
+    use super::host;
+
+    fn request(host_header: &str) -> Request {
+        let mut request = Request::new(Body::empty());
+        request
+            .headers_mut()
+            .insert(header::HOST, host_header.parse().unwrap());
+        request
+    }
+
+    #[test]
+    fn matches_exact_hosts_ignoring_case_and_port() {
+        let guard = host("api.example.com");
+        assert!(guard(&request("api.example.com")));
+        assert!(guard(&request("API.Example.com:8080")));
+        assert!(!guard(&request("admin.example.com")));
+    }
+
+    #[test]
+    fn matches_wildcard_subdomains() {
+        let guard = host("*.example.com");
+        assert!(guard(&request("api.example.com")));
+        assert!(guard(&request("a.b.example.com:443")));
+        assert!(!guard(&request("example.com")));
+        assert!(!guard(&request("badexample.com")));
+    }
+
+    #[test]
+    fn falls_back_to_uri_authority() {
+        let mut request = Request::new(Body::empty());
+        *request.uri_mut() = "https://api.example.com/".parse().unwrap();
+        assert!(host("api.example.com")(&request));
+        assert!(!host("admin.example.com")(&Request::new(Body::empty())));
+    }
+}
@@ -0,0 +1,42 @@
+use std::sync::Arc;
+
+use http_kit::{http_error, Request, StatusCode};
+use skyzen_core::Extractor;
+
+/// The route pattern that matched the request, such as `/users/{id}`.
+///
+/// The router stores it in the request extensions before calling the endpoint, so router-wide
+/// middleware can read it once the endpoint returns. Unlike the request path it has a bounded
+/// set of values, which makes it suitable as a metrics or log label.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchedPath(Arc<str>);
+
+impl MatchedPath {
+    pub(crate) const fn new(pattern: Arc<str>) -> Self {
+        Self(pattern)
+    }
+
+    /// The pattern as it was registered, including any nested route prefixes.
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+http_error!(
+    /// The request was not dispatched by a [`Router`](super::Router), so no route matched it.
+    pub MissingMatchedPath,
+    StatusCode::INTERNAL_SERVER_ERROR,
+    "No route pattern was matched for this request"
+);
+
+impl Extractor for MatchedPath {
+    type Error = MissingMatchedPath;
+    async fn extract(request: &mut Request) -> Result<Self, Self::Error> {
+        request
+            .extensions()
+            .get::<Self>()
+            .cloned()
+            .ok_or_else(MissingMatchedPath::new)
+    }
+}
@@ -2,8 +2,11 @@
 //!
 //! Routes are defined by combining nodes produced by the [`CreateRouteNode`] extension. Path
 //! literals gain builder methods such as `.at(handler)` (GET), `.post(handler)`, `.put(handler)`,
-//! `.delete(handler)`, `.ws(handler)`, and `.route(children)`
-//! so you can describe the full tree declaratively. Once a tree is assembled, call [`Route::build`]
+//! `.delete(handler)`, `.head(handler)`, `.options(handler)`, `.ws(handler)`, and
+//! `.route(children)` so you can describe the full tree declaratively. `HEAD` requests without a
+//! HEAD handler are answered by the GET handler with the body left out, and `OPTIONS` requests
+//! without an OPTIONS handler with the path's methods in `Allow`, unless [`RouterConfig`] says
+//! otherwise. Once a tree is assembled, call [`Route::build`]
 //! to obtain a [`Router`] that can be mounted on a server or invoked directly from tests.
 //!
 //! ## Building routes
@@ -33,10 +36,10 @@
 //!
 //! ## Named parameters and wildcards
 //! Use `{name}` to capture a single path segment and `{*path}` to capture the rest of the path.
-//! Extract the captured values with [`Params`]:
+//! Extract the captured values with [`Params`], and [`Params::parse`] them into other types:
 //! ```no_run
 //! use skyzen::{
-//!     routing::{CreateRouteNode, Params, Route},
+//!     routing::{CreateRouteNode, Params, ParseParamError, Route},
 //!     Result,
 //! };
 //!
@@ -45,7 +48,16 @@
 //!     Ok(format!("Path: {path}"))
 //! }
 //!
-//! let route = Route::new(("/files/{*path}".at(echo),));
+//! async fn show_post(params: Params) -> std::result::Result<String, ParseParamError> {
+//!     // Answers `400 Bad Request` for `/posts/latest`.
+//!     let id: u64 = params.parse("id")?;
+//!     Ok(format!("Post #{id}"))
+//! }
+//!
+//! let route = Route::new((
+//!     "/files/{*path}".at(echo),
+//!     "/posts/{id}".at(show_post),
+//! ));
 //! ```
 //!
 //! ## Applying middleware to a route tree
@@ -99,7 +111,19 @@
 //!     }),
 //! ));
 //! ```
-//! The `.ws` builder enforces the HTTP upgrade requirements automatically.
+//! The `.ws` builder enforces the HTTP upgrade requirements automatically. Use `.ws_with` when
+//! the connection needs request data such as [`Params`]:
+//! ```no_run
+//! use skyzen::routing::{CreateRouteNode, Params, Route};
+//!
+//! let routes = Route::new((
+//!     "/rooms/{id}".ws_with(|params: Params, mut socket| async move {
+//!         if let Ok(id) = params.get("id") {
+//!             let _ = socket.send_text(format!("joined {id}")).await;
+//!         }
+//!     }),
+//! ));
+//! ```
 //!
 //! Middleware is applied from the outermost route to the innermost endpoint, so errors bubble up
 //! until they are handled.
@@ -111,24 +135,32 @@ use std::{fmt, sync::Arc};
 #[cfg(all(debug_assertions, feature = "openapi", not(target_arch = "wasm32")))]
 use crate::openapi::RouteOpenApiEntry;
 #[cfg(feature = "ws")]
-use crate::websocket::{WebSocket, WebSocketUpgrade};
+use crate::websocket::{WebSocket, WebSocketUpgrade, WebSocketUpgradeResponder};
 use crate::{handler, handler::Handler, openapi, openapi::OpenApi, Middleware};
-use http_kit::endpoint::{AnyEndpoint, WithMiddleware};
-use http_kit::{Endpoint, Method};
+use http_kit::endpoint::AnyEndpoint;
+use http_kit::{Endpoint, Method, Request};
 use skyzen_core::{Extractor, Responder};
 
 /// Type alias for dynamically dispatched endpoints stored in the routing tree.
 pub type BoxEndpoint = AnyEndpoint;
-pub(crate) type EndpointFactory = Arc<dyn Fn() -> BoxEndpoint + Send + Sync>;
 // type SharedMiddleware = Box<dyn Middleware>; // Disabled for now
 
+mod guard;
+use guard::Guard;
+
+mod shared;
+use shared::{Layered, SharedEndpoint, SharedHandle};
+
 // Export param types
 mod param;
-pub use param::Params;
+pub use param::{MissingParam, Params, ParseParamError};
+
+mod matched_path;
+pub use matched_path::{MatchedPath, MissingMatchedPath};
 
 // Export router types
 mod router;
-pub use router::{build, RouteBuildError, Router};
+pub use router::{build, DynamicRouter, RouteBuildError, Router, RouterConfig, TrailingSlash};
 
 /// Collection of route nodes anchored at a path prefix.
 #[derive(Debug)]
@@ -152,12 +184,14 @@ pub enum RouteNodeType {
     Route(Route),
     /// Terminal endpoint located at the provided path and method.
     Endpoint {
-        /// Factory producing a fresh endpoint that can be safely shared.
-        endpoint_factory: EndpointFactory,
+        /// The endpoint composed with its middleware, cloned for each request.
+        endpoint: SharedEndpoint,
         /// HTTP method matched by the node.
         method: Method,
         /// Handler metadata for `OpenAPI` export.
         openapi: Option<openapi::RouteHandlerDoc>,
+        /// Predicates the request must satisfy, see [`Route::guard`].
+        guards: Vec<Guard>,
     },
 }
 
@@ -165,9 +199,11 @@ impl fmt::Debug for RouteNodeType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::Route(route) => f.debug_tuple("Route").field(route).finish(),
-            Self::Endpoint { method, .. } => {
-                f.debug_struct("Endpoint").field("method", method).finish()
-            }
+            Self::Endpoint { method, guards, .. } => f
+                .debug_struct("Endpoint")
+                .field("method", method)
+                .field("guards", &guards.len())
+                .finish(),
         }
     }
 }
@@ -182,6 +218,30 @@ impl Route {
     }
 
     /// Attach middleware to this route and all nested endpoints.
+    ///
+    /// Middleware of nested routes sits closer to the endpoint than that of the routes containing
+    /// them, and on a single route the middleware attached last runs first. A request therefore
+    /// passes the outermost route's middleware first, so a [`State`](crate::utils::State)
+    /// attached to a nested route shadows the outer one for the endpoints under it only:
+    ///
+    /// ```
+    /// use skyzen::{
+    ///     routing::{CreateRouteNode, Route},
+    ///     utils::State,
+    ///     Result,
+    /// };
+    ///
+    /// async fn version(State(version): State<u32>) -> Result<String> {
+    ///     Ok(version.to_string())
+    /// }
+    ///
+    /// let router = Route::new((
+    ///     "/v1".route(("/version".at(version),)),
+    ///     Route::new(("/v2/version".at(version),)).middleware(State(2u32)),
+    /// ))
+    /// .middleware(State(1u32))
+    /// .build();
+    /// ```
     #[must_use]
     pub fn middleware<M>(mut self, middleware: M) -> Self
     where
@@ -201,6 +261,47 @@ impl Route {
         }
     }
 
+    /// Only match requests for `host`, such as `api.example.com` or `*.example.com`.
+    ///
+    /// Several routes may register the same path and method for different hosts. A request whose
+    /// host does not match falls through to the next candidate, and finally to `404 Not Found`.
+    /// Wildcards match any subdomain but not the bare domain; ports and case are ignored.
+    ///
+    /// ```
+    /// use skyzen::{routing::{CreateRouteNode, Route}, Result};
+    ///
+    /// let router = Route::new((
+    ///     Route::new(("/".at(|| async { Result::Ok("api") }),)).host("api.example.com"),
+    ///     Route::new(("/".at(|| async { Result::Ok("admin") }),)).host("admin.example.com"),
+    /// ))
+    /// .build();
+    /// ```
+    #[must_use]
+    pub fn host(self, host: &str) -> Self {
+        self.with_guard(&guard::host(host))
+    }
+
+    /// Only match requests for which `guard` returns `true`.
+    ///
+    /// Guards run after path matching. When a guard rejects a request, the router tries the other
+    /// endpoints registered for the same path and method instead of answering `404` right away.
+    /// Guarded endpoints are tried in registration order, before an unguarded one for the same
+    /// path and method.
+    #[must_use]
+    pub fn guard<F>(self, guard: F) -> Self
+    where
+        F: Fn(&Request) -> bool + Send + Sync + 'static,
+    {
+        self.with_guard(&(Arc::new(guard) as Guard))
+    }
+
+    fn with_guard(mut self, guard: &Guard) -> Self {
+        for node in &mut self.nodes {
+            node.apply_guard(guard);
+        }
+        self
+    }
+
     /// Build the route, panicking on error.
     ///
     /// # Panics
@@ -210,6 +311,15 @@ impl Route {
         build(self).expect("Failed to build router")
     }
 
+    /// Build the route with `config`, panicking on error.
+    ///
+    /// # Panics
+    /// Panics if the route is invalid.
+    #[must_use]
+    pub fn build_with(self, config: RouterConfig) -> Router {
+        self.build().with_config(config)
+    }
+
     /// Generate an [`OpenApi`] document describing this route tree.
     #[must_use]
     pub fn openapi(&self) -> OpenApi {
@@ -226,6 +336,18 @@ impl Route {
         }
     }
 
+    /// Leave every endpoint of this route out of the generated `OpenAPI` document.
+    ///
+    /// Handy for internal subtrees such as admin routes. The documentation routes built by
+    /// [`OpenApi`] are always left out.
+    #[must_use]
+    pub fn ignore_openapi(mut self) -> Self {
+        for node in &mut self.nodes {
+            node.clear_openapi();
+        }
+        self
+    }
+
     /// Enable the Redoc API documentation endpoint at `/api-docs`.
     #[must_use]
     pub fn enable_api_doc(mut self) -> Self {
@@ -247,18 +369,31 @@ impl RouteNode {
     where
         E: Endpoint + Clone + Send + Sync + 'static,
     {
-        let endpoint_factory: EndpointFactory =
-            Arc::new(move || AnyEndpoint::new(endpoint.clone()));
         Self {
             path: path.into(),
             node_type: RouteNodeType::Endpoint {
-                endpoint_factory,
+                endpoint: Arc::new(endpoint),
                 method,
                 openapi,
+                guards: Vec::new(),
             },
         }
     }
 
+    /// Mount `endpoint` at `path` for `method`.
+    ///
+    /// This is the escape hatch for types implementing [`Endpoint`] directly rather than going
+    /// through [`Handler`], such as a GraphQL endpoint from another crate. Middleware attached to
+    /// enclosing routes wraps these nodes like any other. No `OpenAPI` operation is generated for
+    /// them.
+    #[must_use]
+    pub fn from_endpoint<E>(path: impl Into<String>, method: Method, endpoint: E) -> Self
+    where
+        E: Endpoint + Clone + Send + Sync + 'static,
+    {
+        Self::new_endpoint(path, method, endpoint, None)
+    }
+
     /// Construct a nested route node mounted under `path`.
     #[must_use]
     pub(crate) fn new_route(path: impl Into<String>, route: Route) -> Self {
@@ -268,33 +403,66 @@ impl RouteNode {
         }
     }
 
+    /// Leave this node, and every endpoint below it, out of the generated `OpenAPI` document.
+    #[must_use]
+    pub fn ignore_openapi(mut self) -> Self {
+        self.clear_openapi();
+        self
+    }
+
+    /// Only match requests for `host`, see [`Route::host`].
+    #[must_use]
+    pub fn host(mut self, host: &str) -> Self {
+        self.apply_guard(&guard::host(host));
+        self
+    }
+
+    /// Only match requests for which `guard` returns `true`, see [`Route::guard`].
+    #[must_use]
+    pub fn guard<F>(mut self, guard: F) -> Self
+    where
+        F: Fn(&Request) -> bool + Send + Sync + 'static,
+    {
+        self.apply_guard(&(Arc::new(guard) as Guard));
+        self
+    }
+
+    fn apply_guard(&mut self, guard: &Guard) {
+        match &mut self.node_type {
+            RouteNodeType::Route(route) => {
+                for node in &mut route.nodes {
+                    node.apply_guard(guard);
+                }
+            }
+            RouteNodeType::Endpoint { guards, .. } => guards.push(Arc::clone(guard)),
+        }
+    }
+
+    fn clear_openapi(&mut self) {
+        match &mut self.node_type {
+            RouteNodeType::Route(route) => {
+                for node in &mut route.nodes {
+                    node.clear_openapi();
+                }
+            }
+            RouteNodeType::Endpoint { openapi, .. } => *openapi = None,
+        }
+    }
+
     fn apply_middleware<M>(&mut self, middleware: M)
     where
         M: Middleware + Sync + Clone + 'static,
     {
         match &mut self.node_type {
             RouteNodeType::Route(route) => route.apply_middleware(middleware),
-            RouteNodeType::Endpoint {
-                endpoint_factory, ..
-            } => {
-                let factory = Arc::clone(endpoint_factory);
-                *endpoint_factory = wrap_endpoint_factory(factory, middleware);
+            RouteNodeType::Endpoint { endpoint, .. } => {
+                let inner = SharedHandle(Arc::clone(endpoint));
+                *endpoint = Arc::new(Layered { inner, middleware });
             }
         }
     }
 }
 
-fn wrap_endpoint_factory<M>(factory: EndpointFactory, middleware: M) -> EndpointFactory
-where
-    M: Middleware + Sync + Clone + 'static,
-{
-    Arc::new(move || {
-        let endpoint = factory();
-        let middleware = middleware.clone();
-        AnyEndpoint::new(WithMiddleware::new(endpoint, middleware))
-    })
-}
-
 impl RouteNode {
     /// Attach a GET handler to the current route node.
     #[must_use]
@@ -351,7 +519,33 @@ impl RouteNode {
         self.with_handler(Method::DELETE, handler)
     }
 
+    /// Attach a HEAD handler to the current route node, answering `HEAD` requests instead of the
+    /// GET handler.
+    #[must_use]
+    pub fn head<H, T, R>(self, handler: H) -> Self
+    where
+        H: Handler<T, R>,
+        T: Extractor,
+        R: Responder,
+    {
+        self.with_handler(Method::HEAD, handler)
+    }
+
+    /// Attach an OPTIONS handler to the current route node, answering `OPTIONS` requests instead
+    /// of the generated `Allow` response.
+    #[must_use]
+    pub fn options<H, T, R>(self, handler: H) -> Self
+    where
+        H: Handler<T, R>,
+        T: Extractor,
+        R: Responder,
+    {
+        self.with_handler(Method::OPTIONS, handler)
+    }
+
     /// Attach an endpoint under the current path with an arbitrary HTTP method.
+    ///
+    /// See [`RouteNode::from_endpoint`].
     #[must_use]
     pub fn endpoint<E>(self, method: Method, endpoint: E) -> Self
     where
@@ -381,6 +575,19 @@ impl RouteNode {
         self.at(builder)
     }
 
+    /// Attach a WebSocket handler that also receives an extractor, see
+    /// [`CreateRouteNode::ws_with`].
+    #[cfg(feature = "ws")]
+    #[must_use]
+    pub fn ws_with<T, F, Fut>(self, handler: F) -> Self
+    where
+        T: Extractor,
+        F: Fn(T, WebSocket) -> Fut + Clone + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.at(ws_with_extractor(handler))
+    }
+
     fn with_handler<H, T, R>(self, method: Method, handler: H) -> Self
     where
         H: Handler<T, R>,
@@ -396,15 +603,17 @@ impl RouteNode {
         let mut nodes = match self.node_type {
             RouteNodeType::Route(route) => route.nodes,
             RouteNodeType::Endpoint {
-                endpoint_factory,
+                endpoint,
                 method,
                 openapi,
+                guards,
             } => vec![Self {
                 path: String::new(),
                 node_type: RouteNodeType::Endpoint {
-                    endpoint_factory,
+                    endpoint,
                     method,
                     openapi,
+                    guards,
                 },
             }],
         };
@@ -559,12 +768,47 @@ pub trait CreateRouteNode: Sized {
         T: Extractor,
         R: Responder;
 
+    /// Attach a HEAD handler to the path, answering `HEAD` requests instead of the GET handler.
+    fn head<H, T, R>(self, handler: H) -> RouteNode
+    where
+        H: Handler<T, R>,
+        T: Extractor,
+        R: Responder;
+
+    /// Attach an OPTIONS handler to the path, answering `OPTIONS` requests instead of the
+    /// generated `Allow` response.
+    fn options<H, T, R>(self, handler: H) -> RouteNode
+    where
+        H: Handler<T, R>,
+        T: Extractor,
+        R: Responder;
+
     /// Mount nested routes under the current path segment.
     fn route(self, routes: impl Routes) -> RouteNode;
 
     /// Attach an endpoint at the specified method and path.
     ///
-    /// Note: This is a low-level method; prefer using `.at`, `.post`, etc. for common HTTP methods.
+    /// This mounts types implementing [`Endpoint`] directly, see [`RouteNode::from_endpoint`]:
+    /// ```
+    /// use skyzen::{
+    ///     routing::{CreateRouteNode, Route},
+    ///     Body, Endpoint, Method, Request, Response,
+    /// };
+    ///
+    /// #[derive(Clone)]
+    /// struct GraphQl;
+    ///
+    /// impl Endpoint for GraphQl {
+    ///     type Error = std::convert::Infallible;
+    ///     async fn respond(&mut self, _request: &mut Request) -> Result<Response, Self::Error> {
+    ///         Ok(Response::new(Body::from("{\"data\":null}")))
+    ///     }
+    /// }
+    ///
+    /// let router = Route::new(("/graphql".endpoint(Method::POST, GraphQl),)).build();
+    /// ```
+    ///
+    /// Note: This is a low-level method; prefer using `.at`, `.post`, etc. for handlers.
     /// Especially when using `OpenAPI`, those methods will automatically generate documentation.
     fn endpoint<E>(self, method: Method, endpoint: E) -> RouteNode
     where
@@ -583,6 +827,40 @@ pub trait CreateRouteNode: Sized {
         };
         self.at(builder)
     }
+
+    /// Attach a WebSocket handler that also receives an extractor, such as [`Params`] or
+    /// [`State`](crate::utils::State).
+    ///
+    /// The extractor runs before the upgrade; if it fails, the handshake is rejected with the
+    /// extractor's error instead of `101 Switching Protocols`.
+    #[cfg(feature = "ws")]
+    fn ws_with<T, F, Fut>(self, handler: F) -> RouteNode
+    where
+        T: Extractor,
+        F: Fn(T, WebSocket) -> Fut + Clone + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.at(ws_with_extractor(handler))
+    }
+}
+
+#[cfg(feature = "ws")]
+fn ws_with_extractor<T, F, Fut>(
+    handler: F,
+) -> impl Fn(T, WebSocketUpgrade) -> core::future::Ready<WebSocketUpgradeResponder>
+       + Clone
+       + Send
+       + Sync
+       + 'static
+where
+    T: Extractor,
+    F: Fn(T, WebSocket) -> Fut + Clone + Send + Sync + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    move |extracted: T, upgrade: WebSocketUpgrade| {
+        let callback = handler.clone();
+        core::future::ready(upgrade.on_upgrade(move |socket| callback(extracted, socket)))
+    }
 }
 
 impl<P> CreateRouteNode for P
@@ -625,6 +903,24 @@ where
         endpoint_node_from_handler(self, Method::DELETE, handler)
     }
 
+    fn head<H, T, R>(self, handler: H) -> RouteNode
+    where
+        H: Handler<T, R>,
+        T: Extractor,
+        R: Responder,
+    {
+        endpoint_node_from_handler(self, Method::HEAD, handler)
+    }
+
+    fn options<H, T, R>(self, handler: H) -> RouteNode
+    where
+        H: Handler<T, R>,
+        T: Extractor,
+        R: Responder,
+    {
+        endpoint_node_from_handler(self, Method::OPTIONS, handler)
+    }
+
     fn endpoint<E>(self, method: Method, endpoint: E) -> RouteNode
     where
         E: Endpoint + Clone + Send + Sync + 'static,
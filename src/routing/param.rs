@@ -1,20 +1,41 @@
+use std::any::type_name;
 use std::convert::Infallible;
 use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
 
 use http_kit::{HttpError, Request, StatusCode};
 use skyzen_core::Extractor;
+use smallvec::SmallVec;
+
+use crate::utils::percent::percent_decode;
 
 /// Extract param defined in route.
+///
+/// Values are percent-decoded; [`Params::get_raw`] returns them as they appeared in the path.
 #[derive(Debug, Clone)]
-pub struct Params(Vec<(String, String)>);
+pub struct Params(SmallVec<[Param; 2]>);
 
-/// Error returned when attempting to read a missing route parameter.
+#[derive(Debug, Clone)]
+struct Param {
+    /// Interned once per route when the router is built.
+    name: Arc<str>,
+    value: Box<str>,
+    /// The undecoded value, kept only when decoding changed it.
+    raw: Option<Box<str>>,
+}
+
+/// Error returned when attempting to read a route parameter the route pattern does not capture.
+///
+/// This is a mistake in the handler rather than in the request, so it is an internal server
+/// error.
 #[derive(Debug, Clone)]
 pub struct MissingParam {
     name: String,
 }
 
 impl MissingParam {
+    /// The error for the parameter `name`.
     pub fn new(name: impl Into<String>) -> Self {
         Self { name: name.into() }
     }
@@ -29,18 +50,131 @@ impl fmt::Display for MissingParam {
 impl std::error::Error for MissingParam {}
 
 impl HttpError for MissingParam {
+    fn status(&self) -> StatusCode {
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+}
+
+/// Error returned by [`Params::parse`].
+#[derive(Debug, Clone)]
+pub enum ParseParamError {
+    /// The route pattern does not capture the parameter.
+    Missing(MissingParam),
+    /// The captured value is not a valid value of the requested type.
+    Invalid {
+        /// Name of the parameter.
+        name: String,
+        /// Name of the requested type.
+        type_name: &'static str,
+        /// Why parsing failed.
+        reason: String,
+    },
+}
+
+impl From<MissingParam> for ParseParamError {
+    fn from(error: MissingParam) -> Self {
+        Self::Missing(error)
+    }
+}
+
+impl fmt::Display for ParseParamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Missing(error) => error.fmt(f),
+            Self::Invalid {
+                name,
+                type_name,
+                reason,
+            } => write!(f, "Param `{name}` is not a valid `{type_name}`: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for ParseParamError {}
+
+impl HttpError for ParseParamError {
+    fn status(&self) -> StatusCode {
+        match self {
+            Self::Missing(error) => error.status(),
+            Self::Invalid { .. } => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+/// Error returned when a captured route parameter is not valid percent-encoded UTF-8.
+#[derive(Debug, Clone)]
+pub struct InvalidParam {
+    name: String,
+}
+
+impl fmt::Display for InvalidParam {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Param `{}` is not valid percent-encoded UTF-8",
+            self.name
+        )
+    }
+}
+
+impl std::error::Error for InvalidParam {}
+
+impl HttpError for InvalidParam {
     fn status(&self) -> StatusCode {
         StatusCode::BAD_REQUEST
     }
 }
 
 impl Params {
-    pub(crate) const fn new(vec: Vec<(String, String)>) -> Self {
-        Self(vec)
+    /// Percent-decode the captured `(name, raw value)` pairs, reusing the route's interned
+    /// `names`.
+    ///
+    /// The `catch_all` parameter keeps `%2F` encoded so that every `/` in its decoded value is a
+    /// real path separator. Dot segments are left alone for the handler to check.
+    pub(crate) fn decode<'a>(
+        captured: impl Iterator<Item = (&'a str, &'a str)>,
+        names: &[Arc<str>],
+        catch_all: Option<&str>,
+    ) -> Result<Self, InvalidParam> {
+        captured
+            .map(|(name, raw)| {
+                let name = names
+                    .iter()
+                    .find(|interned| ***interned == *name)
+                    .map_or_else(|| Arc::from(name), Arc::clone);
+                if !raw.contains('%') {
+                    return Ok(Param {
+                        name,
+                        value: raw.into(),
+                        raw: None,
+                    });
+                }
+                let value = if catch_all == Some(&*name) {
+                    decode_keeping_slashes(raw)
+                } else {
+                    percent_decode(raw)
+                };
+                match value {
+                    Some(value) => Ok(Param {
+                        name,
+                        value: value.into_boxed_str(),
+                        raw: Some(raw.into()),
+                    }),
+                    None => Err(InvalidParam {
+                        name: name.to_string(),
+                    }),
+                }
+            })
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.is_empty()
     }
 
     pub(crate) const fn empty() -> Self {
-        Self(Vec::new())
+        Self(SmallVec::new_const())
     }
 
     /// Get the route parameter by the name.
@@ -49,13 +183,73 @@ impl Params {
     ///
     /// Returns an error if the requested parameter is not present.
     pub fn get(&self, name: &str) -> Result<&str, MissingParam> {
+        self.find(name).map(|param| &*param.value)
+    }
+
+    /// Get the route parameter by the name, or `None` if the route does not capture it.
+    #[must_use]
+    pub fn get_opt(&self, name: &str) -> Option<&str> {
+        self.get(name).ok()
+    }
+
+    /// Parse the route parameter `name` into a `T`.
+    ///
+    /// ```
+    /// use skyzen::routing::{Params, ParseParamError};
+    ///
+    /// async fn show_post(params: Params) -> Result<String, ParseParamError> {
+    ///     let id: u64 = params.parse("id")?;
+    ///     Ok(format!("Post #{id}"))
+    /// }
+    /// ```
+    ///
+    /// Converting the error into [`skyzen::Error`](crate::Error) with `?` turns it into a
+    /// `500 Internal Server Error`; keep it in the handler's error type to answer with its status.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `400 Bad Request` error naming the parameter and `T` if the value does not
+    /// parse, and a `500 Internal Server Error` if the route does not capture `name`.
+    pub fn parse<T>(&self, name: &str) -> Result<T, ParseParamError>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        self.get(name)?
+            .parse()
+            .map_err(|error: T::Err| ParseParamError::Invalid {
+                name: name.to_owned(),
+                type_name: type_name::<T>(),
+                reason: error.to_string(),
+            })
+    }
+
+    /// Get the route parameter by the name, without percent-decoding.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the requested parameter is not present.
+    pub fn get_raw(&self, name: &str) -> Result<&str, MissingParam> {
+        self.find(name)
+            .map(|param| param.raw.as_deref().unwrap_or(&param.value))
+    }
+
+    fn find(&self, name: &str) -> Result<&Param, MissingParam> {
         self.0
             .iter()
-            .find_map(|(k, v)| if k == name { Some(v.as_str()) } else { None })
+            .find(|param| &*param.name == name)
             .ok_or_else(|| MissingParam::new(name))
     }
 }
 
+fn decode_keeping_slashes(raw: &str) -> Option<String> {
+    raw.replace("%2f", "%2F")
+        .split("%2F")
+        .map(percent_decode)
+        .collect::<Option<Vec<_>>>()
+        .map(|pieces| pieces.join("%2F"))
+}
+
 impl Extractor for Params {
     type Error = Infallible;
     async fn extract(request: &mut Request) -> Result<Self, Self::Error> {
@@ -70,6 +264,9 @@ impl Extractor for Params {
         crate::openapi::schema_of::<Self>().map(|schema| crate::openapi::ExtractorSchema {
             content_type: None,
             schema: Some(schema),
+            location: crate::openapi::ParameterLocation::Path,
+            required: true,
+            extra_content_types: &[],
         })
     }
 
@@ -80,3 +277,49 @@ impl Extractor for Params {
         crate::openapi::register_schema_for::<Self>(defs);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use http_kit::{HttpError, StatusCode};
+
+    use super::Params;
+
+    fn params(captured: &[(&str, &str)]) -> Params {
+        let names = captured
+            .iter()
+            .map(|(name, _)| Arc::from(*name))
+            .collect::<Vec<_>>();
+        Params::decode(captured.iter().copied(), &names, None).unwrap()
+    }
+
+    #[test]
+    fn parses_params_into_the_requested_type() {
+        let params = params(&[("id", "42"), ("name", "Ada%20L")]);
+        assert_eq!(params.parse::<u64>("id").unwrap(), 42);
+        assert_eq!(params.parse::<String>("name").unwrap(), "Ada L");
+        assert_eq!(params.get_opt("name"), Some("Ada L"));
+        assert_eq!(params.get_opt("missing"), None);
+    }
+
+    #[test]
+    fn blames_the_client_for_bad_values_and_the_route_for_missing_ones() {
+        let params = params(&[("id", "abc")]);
+
+        let invalid = params.parse::<u64>("id").unwrap_err();
+        assert_eq!(invalid.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            invalid.to_string(),
+            "Param `id` is not a valid `u64`: invalid digit found in string"
+        );
+
+        let missing = params.parse::<u64>("user_id").unwrap_err();
+        assert_eq!(missing.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(missing.to_string(), "Missing param `user_id`");
+        assert_eq!(
+            params.get("user_id").unwrap_err().status(),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
+}
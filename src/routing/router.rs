@@ -1,23 +1,37 @@
 use std::{
     collections::{HashMap, HashSet},
     fmt::{Debug, Formatter},
-    sync::Arc,
+    sync::{Arc, Mutex, PoisonError},
 };
 
-use super::{BoxEndpoint, EndpointFactory, Params, Route, RouteNode, RouteNodeType};
+use super::{
+    endpoint_node_from_handler, param::InvalidParam, Guard, MatchedPath, Params, Route, RouteNode,
+    RouteNodeType, Routes, SharedEndpoint,
+};
 #[cfg(all(debug_assertions, feature = "openapi"))]
 use crate::openapi::RouteOpenApiEntry;
-use crate::{openapi::OpenApi, Endpoint, Method, Request, Response, StatusCode};
+use crate::{
+    handler::Handler, header, openapi::OpenApi, Endpoint, Method, Request, Response, StatusCode,
+};
 
+use arc_swap::ArcSwap;
 use http_kit::error::BoxHttpError;
 use http_kit::http_error;
 use matchit::Match;
-use skyzen_core::Extractor;
+use skyzen_core::{error::ErrorHeaders, Extractor, Responder};
 use tracing::{error, info};
 
 // The entrance of request,composing of endpoint
+#[derive(Clone)]
 pub struct App {
-    endpoint_factory: EndpointFactory,
+    endpoint: SharedEndpoint,
+    guards: Vec<Guard>,
+    /// The full route pattern, exposed to the request as [`MatchedPath`].
+    pattern: Arc<str>,
+    /// Parameter names of the route pattern, shared by every request's [`Params`].
+    param_names: Box<[Arc<str>]>,
+    /// Name of the `{*name}` parameter, if the route ends in one.
+    catch_all: Option<String>,
     // middlewares: SmallVec<[SharedMiddleware; 5]>, // Simplified for now
 }
 
@@ -28,12 +42,30 @@ impl Debug for App {
 }
 
 impl App {
-    fn new(endpoint_factory: EndpointFactory) -> Self {
-        Self { endpoint_factory }
+    fn new(path: &str, endpoint: SharedEndpoint, guards: Vec<Guard>) -> Self {
+        Self {
+            endpoint,
+            guards,
+            pattern: Arc::from(path),
+            param_names: path
+                .split('{')
+                .skip(1)
+                .filter_map(|rest| rest.split_once('}'))
+                .map(|(name, _)| Arc::from(name.trim_start_matches('*')))
+                .collect(),
+            catch_all: path
+                .split_once("{*")
+                .and_then(|(_, rest)| rest.split_once('}'))
+                .map(|(name, _)| name.to_owned()),
+        }
+    }
+
+    fn is_guarded(&self) -> bool {
+        !self.guards.is_empty()
     }
 
-    fn endpoint(&self) -> BoxEndpoint {
-        (self.endpoint_factory)()
+    fn accepts(&self, request: &Request) -> bool {
+        self.guards.iter().all(|guard| guard(request))
     }
 }
 
@@ -56,7 +88,13 @@ impl App {
 #[derive(Clone)]
 pub struct Router {
     inner: Arc<matchit::Router<Vec<(Method, App)>>>,
+    /// The checked routes `inner` was built from, kept so that [`DynamicRouter`] can rebuild it.
+    routes: Arc<FlattenBuf>,
     already_router_enabled: bool,
+    error_renderer: Option<ErrorRenderer>,
+    trailing_slash: TrailingSlash,
+    strict_head: bool,
+    strict_options: bool,
     #[cfg(all(debug_assertions, feature = "openapi"))]
     openapi_entries: Arc<Vec<RouteOpenApiEntry>>,
 }
@@ -66,7 +104,12 @@ impl Debug for Router {
         let mut debug_struct = f.debug_struct("Router");
         debug_struct
             .field("inner", &self.inner)
-            .field("already_router_enabled", &self.already_router_enabled);
+            .field("routes", &self.routes.len())
+            .field("already_router_enabled", &self.already_router_enabled)
+            .field("error_renderer", &self.error_renderer.is_some())
+            .field("trailing_slash", &self.trailing_slash)
+            .field("strict_head", &self.strict_head)
+            .field("strict_options", &self.strict_options);
         #[cfg(all(debug_assertions, feature = "openapi"))]
         {
             debug_struct.field("openapi_entries", &self.openapi_entries.len());
@@ -75,6 +118,51 @@ impl Debug for Router {
     }
 }
 
+/// How the router treats a request path that only differs from a route by a trailing slash.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TrailingSlash {
+    /// `/users` and `/users/` are distinct routes.
+    #[default]
+    Strict,
+    /// Answer `308 Permanent Redirect` to the registered form, keeping the query string.
+    Redirect,
+    /// Serve the registered route for both forms.
+    Merge,
+}
+
+/// Options for [`Route::build_with`](crate::routing::Route::build_with).
+///
+/// ```
+/// use skyzen::routing::{CreateRouteNode, Route, RouterConfig, TrailingSlash};
+/// use skyzen::Result;
+///
+/// let router = Route::new(("/users".at(|| async { Result::Ok("users") }),)).build_with(
+///     RouterConfig {
+///         trailing_slash: TrailingSlash::Redirect,
+///         ..RouterConfig::default()
+///     },
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RouterConfig {
+    /// Trailing-slash policy, [`TrailingSlash::Strict`] by default.
+    pub trailing_slash: TrailingSlash,
+    /// Only answer `HEAD` requests with handlers registered for `HEAD`.
+    ///
+    /// By default a `HEAD` request to a path without such a handler is served by its GET handler,
+    /// and the response body is dropped unread while its headers are kept.
+    pub strict_head: bool,
+    /// Only answer `OPTIONS` requests with handlers registered for `OPTIONS`.
+    ///
+    /// By default an `OPTIONS` request to a path without such a handler is answered with
+    /// `204 No Content` and an `Allow` header listing the methods the path serves. CORS middleware
+    /// in front of the router answers preflight requests before they get here.
+    pub strict_options: bool,
+}
+
+/// Renders an error that escaped every endpoint and middleware into the final response.
+type ErrorRenderer = Arc<dyn Fn(&BoxHttpError, &Request) -> Response + Send + Sync>;
+
 http_error!(pub NotFound, StatusCode::NOT_FOUND, "Route not found.");
 
 #[derive(Debug, Clone, Copy)]
@@ -88,23 +176,30 @@ impl Endpoint for NotFoundEndpoint {
 }
 
 impl Router {
-    fn search<'app, 'path, 'temp>(
+    /// The app serving `request` at `path`, and whether it is the GET handler standing in for a
+    /// missing HEAD one.
+    fn search<'app, 'path>(
         &'app self,
         path: &'path str,
-        method: &'temp Method,
-    ) -> Option<Match<'app, 'path, &'app App>>
+        request: &Request,
+    ) -> Option<(Match<'app, 'path, &'app App>, bool)>
     where
         'app: 'path,
-        'app: 'temp,
     {
-        if let Ok(Match { value, params }) = self.inner.at(path) {
+        let Match { value, params } = self.inner.at(path).ok()?;
+        let find = |method: &Method| {
             value
                 .iter()
-                .find(|(app_method, ..)| app_method == method)
-                .map(|(.., app)| Match { value: app, params })
-        } else {
-            None
+                .find(|(app_method, app)| app_method == method && app.accepts(request))
+                .map(|(.., app)| app)
+        };
+        if let Some(app) = find(request.method()) {
+            return Some((Match { value: app, params }, false));
+        }
+        if !self.strict_head && request.method() == Method::HEAD {
+            return find(&Method::GET).map(|app| (Match { value: app, params }, true));
         }
+        None
     }
 
     async fn call(&self, request: &mut Request) -> Result<Response, BoxHttpError> {
@@ -113,24 +208,87 @@ impl Router {
         }
 
         let path = request.uri().path();
-        let method = request.method();
+        let mut matched = self.search(path, request).map(decode_params);
+        if matched.is_none() && self.trailing_slash != TrailingSlash::Strict {
+            if let Some(alternate) = toggle_trailing_slash(path) {
+                matched = self
+                    .search(&alternate, request)
+                    .filter(|(found, _)| found.value.catch_all.is_none())
+                    .map(decode_params);
+                if matched.is_some() && self.trailing_slash == TrailingSlash::Redirect {
+                    return Ok(redirect_to(alternate, request.uri().query()));
+                }
+            }
+        }
 
-        if let Some(Match { value, params }) = self.search(path, method) {
-            let params: Vec<(String, String)> = params
-                .iter()
-                .map(|(key, value)| (key.to_owned(), value.to_owned()))
-                .collect();
-            let params = Params::new(params);
-            request.extensions_mut().insert(params);
+        if let Some((app, params, head_as_get)) = matched {
+            let params = params.map_err(|error| Box::new(error) as BoxHttpError)?;
+            if !params.is_empty() {
+                request.extensions_mut().insert(params);
+            }
+            request
+                .extensions_mut()
+                .insert(MatchedPath::new(Arc::clone(&app.pattern)));
 
-            let mut endpoint = value.endpoint();
-            endpoint.respond(request).await
+            let response = app.endpoint.respond_cloned(request).await?;
+            Ok(if head_as_get {
+                strip_body(response)
+            } else {
+                response
+            })
+        } else if let Some(response) = self.allow_response(request) {
+            Ok(response)
         } else {
             let mut not_found = NotFoundEndpoint;
             not_found.respond(request).await
         }
     }
 
+    /// The generated answer to an `OPTIONS` request for a path without an OPTIONS handler.
+    fn allow_response(&self, request: &Request) -> Option<Response> {
+        if self.strict_options || request.method() != Method::OPTIONS {
+            return None;
+        }
+        let path = request.uri().path();
+        let apps = match self.inner.at(path) {
+            Ok(found) => found.value,
+            Err(_) if self.trailing_slash == TrailingSlash::Strict => return None,
+            Err(_) => {
+                let alternate = toggle_trailing_slash(path)?;
+                let apps = self.inner.at(&alternate).ok()?.value;
+                if apps.iter().any(|(_, app)| app.catch_all.is_some()) {
+                    return None;
+                }
+                apps
+            }
+        };
+
+        let mut methods: Vec<&str> = Vec::new();
+        for (method, _) in apps {
+            if !methods.contains(&method.as_str()) {
+                methods.push(method.as_str());
+            }
+        }
+        if !self.strict_head && methods.contains(&"GET") && !methods.contains(&"HEAD") {
+            methods.push("HEAD");
+        }
+        methods.push("OPTIONS");
+
+        let mut response = Response::new(http_kit::Body::empty());
+        *response.status_mut() = StatusCode::NO_CONTENT;
+        if let Ok(allow) = methods.join(", ").parse() {
+            response.headers_mut().insert(header::ALLOW, allow);
+        }
+        Some(response)
+    }
+
+    pub(crate) const fn with_config(mut self, config: RouterConfig) -> Self {
+        self.trailing_slash = config.trailing_slash;
+        self.strict_head = config.strict_head;
+        self.strict_options = config.strict_options;
+        self
+    }
+
     /// Dispatch the provided [`Request`] through the router and return the produced [`Response`].
     ///
     /// # Errors
@@ -153,6 +311,76 @@ impl Router {
         self
     }
 
+    /// Render errors that reach the router, including the `404` fallback, with `renderer`.
+    ///
+    /// By default such errors become an empty response carrying only the error's status. The
+    /// renderer receives the request so it can honour headers like `Accept`. Errors are still
+    /// logged before rendering, and headers carried by the error, see
+    /// [`ErrorHeaders`](skyzen_core::error::ErrorHeaders), are added to the rendered response
+    /// unless the renderer set them itself.
+    ///
+    /// ```
+    /// use skyzen::{header, routing::{Route, Router}, Body, Response};
+    ///
+    /// let router: Router = Route::new(()).build().on_error(|error, request| {
+    ///     let wants_json = request
+    ///         .headers()
+    ///         .get(header::ACCEPT)
+    ///         .is_some_and(|accept| accept.as_bytes().starts_with(b"application/json"));
+    ///     let body = if wants_json {
+    ///         format!(r#"{{"error":"{error}"}}"#)
+    ///     } else {
+    ///         error.to_string()
+    ///     };
+    ///     let mut response = Response::new(Body::from(body));
+    ///     *response.status_mut() = error.status();
+    ///     response
+    /// });
+    /// ```
+    #[must_use]
+    pub fn on_error<F>(mut self, renderer: F) -> Self
+    where
+        F: Fn(&BoxHttpError, &Request) -> Response + Send + Sync + 'static,
+    {
+        self.error_renderer = Some(Arc::new(renderer));
+        self
+    }
+
+    fn render_error(&self, error: &BoxHttpError, request: &Request) -> Response {
+        let status = error.status();
+        let error_name = if status.is_server_error() {
+            "Server Error"
+        } else if status.is_client_error() {
+            "Client Error"
+        } else {
+            "Error"
+        };
+        error!(
+            message = error.to_string().as_str(),
+            status = status.as_str(),
+            "{error_name}"
+        );
+
+        let mut response = self.error_renderer.as_ref().map_or_else(
+            || {
+                let mut response = Response::new(http_kit::Body::empty());
+                *response.status_mut() = status;
+                response
+            },
+            |renderer| renderer(error, request),
+        );
+        if let Some(headers) = (**error).headers() {
+            for name in headers.keys() {
+                if !response.headers().contains_key(name) {
+                    for value in headers.get_all(name) {
+                        response.headers_mut().append(name.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        response
+    }
+
     /// Build an [`OpenApi`] definition containing every route registered on this router.
     #[must_use]
     pub fn openapi(&self) -> OpenApi {
@@ -168,6 +396,58 @@ impl Router {
     }
 }
 
+fn decode_params<'app>(
+    (found, head_as_get): (Match<'app, '_, &'app App>, bool),
+) -> (&'app App, Result<Params, InvalidParam>, bool) {
+    let app = found.value;
+    let params = Params::decode(
+        found.params.iter(),
+        &app.param_names,
+        app.catch_all.as_deref(),
+    );
+    (app, params, head_as_get)
+}
+
+/// Drop the body of a GET response answering a HEAD request, without reading it.
+///
+/// A body of known length is still announced in `Content-Length`, as it would be for the GET
+/// request.
+fn strip_body(mut response: Response) -> Response {
+    let body = std::mem::replace(response.body_mut(), http_kit::Body::empty());
+    if !response.headers().contains_key(header::CONTENT_LENGTH) {
+        if let Some(len) = body.len() {
+            response
+                .headers_mut()
+                .insert(header::CONTENT_LENGTH, header::HeaderValue::from(len));
+        }
+    }
+    response
+}
+
+/// `path` with its trailing slash added or removed, or `None` for the root.
+fn toggle_trailing_slash(path: &str) -> Option<String> {
+    if path == "/" {
+        None
+    } else if let Some(stripped) = path.strip_suffix('/') {
+        Some(stripped.to_owned())
+    } else {
+        Some(format!("{path}/"))
+    }
+}
+
+fn redirect_to(mut location: String, query: Option<&str>) -> Response {
+    if let Some(query) = query {
+        location.push('?');
+        location.push_str(query);
+    }
+    let mut response = Response::new(http_kit::Body::empty());
+    *response.status_mut() = StatusCode::PERMANENT_REDIRECT;
+    if let Ok(location) = location.parse() {
+        response.headers_mut().insert(header::LOCATION, location);
+    }
+    response
+}
+
 http_error!(pub RouterNotExist, StatusCode::INTERNAL_SERVER_ERROR, "This already router does not exist. Please check whether you have enabled the already router.");
 
 impl Extractor for Router {
@@ -186,7 +466,8 @@ impl Extractor for Router {
 #[derive(Debug)]
 #[non_exhaustive]
 pub enum RouteBuildError {
-    /// The same method has been registered multiple times for the same path.
+    /// The same method has been registered multiple times for the same path without a guard
+    /// telling the handlers apart.
     RepeatedMethod {
         /// Path that already has a handler registered.
         path: String,
@@ -197,6 +478,26 @@ pub enum RouteBuildError {
     MatchitError(matchit::InsertError),
 }
 
+impl std::fmt::Display for RouteBuildError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::RepeatedMethod { path, method } => {
+                write!(f, "{method} {path} is registered more than once")
+            }
+            Self::MatchitError(error) => write!(f, "invalid route: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for RouteBuildError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::RepeatedMethod { .. } => None,
+            Self::MatchitError(error) => Some(error),
+        }
+    }
+}
+
 impl From<matchit::InsertError> for RouteBuildError {
     fn from(error: matchit::InsertError) -> Self {
         Self::MatchitError(error)
@@ -220,14 +521,15 @@ fn flatten(
                 flatten(&path, route.nodes, buf, openapi_entries);
             }
             RouteNodeType::Endpoint {
-                endpoint_factory,
+                endpoint,
                 method,
                 openapi,
+                guards,
                 // middlewares, // Disabled for now
             } => {
                 let entry = buf.entry(path.clone()).or_default();
 
-                entry.push((method.clone(), App::new(endpoint_factory)));
+                entry.push((method.clone(), App::new(&path, endpoint, guards)));
                 if let Some(openapi) = openapi {
                     openapi_entries.push(RouteOpenApiEntry::new(path, method, openapi));
                 }
@@ -246,18 +548,38 @@ fn flatten(path_prefix: &str, route: Vec<RouteNode>, buf: &mut FlattenBuf) {
                 flatten(&path, route.nodes, buf);
             }
             RouteNodeType::Endpoint {
-                endpoint_factory,
+                endpoint,
                 method,
                 openapi: _,
+                guards,
                 // middlewares, // Disabled for now
             } => {
-                let entry = buf.entry(path).or_default();
-                entry.push((method, App::new(endpoint_factory)));
+                let app = App::new(&path, endpoint, guards);
+                buf.entry(path).or_default().push((method, app));
             }
         }
     }
 }
 
+/// Reject unguarded handlers sharing a method, and order guarded handlers before the unguarded
+/// fallback so that a rejecting guard falls through to it.
+fn check_methods(
+    path: String,
+    mut value: Vec<(Method, App)>,
+) -> Result<Vec<(Method, App)>, RouteBuildError> {
+    let mut set = HashSet::new();
+    for (method, app) in &value {
+        if !app.is_guarded() && !set.insert(method) {
+            return Err(RouteBuildError::RepeatedMethod {
+                path,
+                method: method.clone(),
+            });
+        }
+    }
+    value.sort_by_key(|(_, app)| !app.is_guarded());
+    Ok(value)
+}
+
 /// Build a [`Router`] from the provided [`Route`].
 ///
 /// # Errors
@@ -290,22 +612,15 @@ fn finalize_router(
     buf: HashMap<String, Vec<(Method, App)>>,
     openapi_entries: Option<Vec<RouteOpenApiEntry>>,
 ) -> Result<Router, RouteBuildError> {
-    let mut router = matchit::Router::new();
-    for (path, value) in buf {
-        let mut set = HashSet::new();
-        for (method, ..) in &value {
-            if !set.insert(method) {
-                return Err(RouteBuildError::RepeatedMethod {
-                    path,
-                    method: method.clone(),
-                });
-            }
-        } //check route
-        router.insert(path, value)?;
-    }
+    let routes = check_routes(buf)?;
     Ok(Router {
-        inner: Arc::new(router),
+        inner: Arc::new(matcher(&routes)?),
+        routes: Arc::new(routes),
         already_router_enabled: false,
+        error_renderer: None,
+        trailing_slash: TrailingSlash::Strict,
+        strict_head: false,
+        strict_options: false,
         openapi_entries: Arc::new(openapi_entries.unwrap_or_default()),
     })
 }
@@ -315,63 +630,248 @@ fn finalize_router(
     buf: HashMap<String, Vec<(Method, App)>>,
     _openapi_entries: Option<Vec<()>>,
 ) -> Result<Router, RouteBuildError> {
-    let mut router = matchit::Router::new();
-    for (path, value) in buf {
-        let mut set = HashSet::new();
-        for (method, ..) in &value {
-            if !set.insert(method) {
-                return Err(RouteBuildError::RepeatedMethod {
-                    path,
-                    method: method.clone(),
-                });
-            }
-        } //check route
-        router.insert(path, value)?;
-    }
+    let routes = check_routes(buf)?;
     Ok(Router {
-        inner: Arc::new(router),
+        inner: Arc::new(matcher(&routes)?),
+        routes: Arc::new(routes),
         already_router_enabled: false,
+        error_renderer: None,
+        trailing_slash: TrailingSlash::Strict,
+        strict_head: false,
+        strict_options: false,
     })
 }
 
+fn check_routes(buf: FlattenBuf) -> Result<FlattenBuf, RouteBuildError> {
+    buf.into_iter()
+        .map(|(path, value)| Ok((path.clone(), check_methods(path, value)?)))
+        .collect()
+}
+
+fn matcher(routes: &FlattenBuf) -> Result<matchit::Router<Vec<(Method, App)>>, RouteBuildError> {
+    let mut matcher = matchit::Router::new();
+    for (path, value) in routes {
+        matcher.insert(path.clone(), value.clone())?;
+    }
+    Ok(matcher)
+}
+
 impl Endpoint for Router {
     type Error = BoxHttpError;
     async fn respond(&mut self, request: &mut Request) -> Result<Response, Self::Error> {
-        info!(
-            method = request.method().as_str(),
-            path = request.uri().path(),
-            "request received"
-        );
-        Ok(self.call(request).await.unwrap_or_else(|error| {
-            let mut response = Response::new(http_kit::Body::empty());
-            let status = error.status();
-            *response.status_mut() = status;
-            let error_name = if status.is_server_error() {
-                "Server Error"
-            } else if status.is_client_error() {
-                "Client Error"
-            } else {
-                "Error"
-            };
-            error!(
-                message = error.to_string().as_str(),
-                status = status.as_str(),
-                "{error_name}"
+        #[cfg(not(target_arch = "wasm32"))]
+        let logged = crate::middleware::access_log::AccessLogged::is_set(request);
+        #[cfg(target_arch = "wasm32")]
+        let logged = false;
+        if !logged {
+            info!(
+                method = request.method().as_str(),
+                path = request.uri().path(),
+                "request received"
             );
-            response
-        }))
+        }
+        match self.call(request).await {
+            Ok(response) => Ok(response),
+            Err(error) => Ok(self.render_error(&error, request)),
+        }
+    }
+}
+
+impl Router {
+    /// Turn this router into a [`DynamicRouter`], which can gain and lose routes while serving.
+    #[must_use]
+    pub fn into_dynamic(self) -> DynamicRouter {
+        DynamicRouter {
+            current: Arc::new(ArcSwap::from_pointee(self)),
+            writer: Arc::new(Mutex::new(())),
+        }
     }
 }
 
-#[cfg(test)]
+/// A [`Router`] whose routes can be added and removed at runtime, returned by
+/// [`Router::into_dynamic`].
+///
+/// Each request is served by a snapshot of the routing table taken without locking, so a route
+/// added or removed while a request is in flight only applies to later requests. Changes are
+/// validated like [`build`] does and rebuild the table, which makes them far more expensive than
+/// lookups. Clones share the same routes.
+///
+/// With [`Router::enable_programmable_router`], handlers can extract the `DynamicRouter` serving
+/// them to register routes themselves:
+///
+/// ```
+/// use skyzen::{
+///     routing::{CreateRouteNode, DynamicRouter, Route},
+///     Method, Result,
+/// };
+///
+/// async fn install(router: DynamicRouter) -> Result<&'static str> {
+///     router.add("/plugin", Method::GET, || async { Result::Ok("plugin") })?;
+///     Ok("installed")
+/// }
+///
+/// let router = Route::new(("/install".post(install),))
+///     .build()
+///     .enable_programmable_router()
+///     .into_dynamic();
+/// ```
+#[derive(Clone)]
+pub struct DynamicRouter {
+    current: Arc<ArcSwap<Router>>,
+    /// Serializes changes, so that concurrent ones do not overwrite each other.
+    writer: Arc<Mutex<()>>,
+}
+
+impl Debug for DynamicRouter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("DynamicRouter")
+            .field(&self.current.load())
+            .finish()
+    }
+}
+
+impl DynamicRouter {
+    /// Serve `handler` for `method` requests to `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`RouteBuildError`] if `path` already has an unguarded handler for `method`, or if
+    /// the path matcher rejects `path`. The routes are left unchanged in that case.
+    pub fn add<H, T, R>(
+        &self,
+        path: impl Into<String>,
+        method: Method,
+        handler: H,
+    ) -> Result<(), RouteBuildError>
+    where
+        H: Handler<T, R>,
+        T: Extractor,
+        R: Responder,
+    {
+        self.add_routes(endpoint_node_from_handler(path, method, handler))
+    }
+
+    /// Add every route of `routes`, such as a [`Route`] tree or an endpoint mounted with
+    /// [`RouteNode::from_endpoint`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`RouteBuildError`] under the same conditions as [`DynamicRouter::add`]. Either all
+    /// routes are added or none is.
+    pub fn add_routes(&self, routes: impl Routes) -> Result<(), RouteBuildError> {
+        let _writer = self.writer.lock().unwrap_or_else(PoisonError::into_inner);
+        let current = self.current.load_full();
+        let mut buf = HashMap::new();
+        #[cfg(all(debug_assertions, feature = "openapi"))]
+        let mut openapi_entries = current.openapi_entries.to_vec();
+        #[cfg(all(debug_assertions, feature = "openapi"))]
+        flatten(
+            "",
+            routes.into_route_nodes(),
+            &mut buf,
+            &mut openapi_entries,
+        );
+        #[cfg(not(all(debug_assertions, feature = "openapi")))]
+        flatten("", routes.into_route_nodes(), &mut buf);
+
+        let mut routes = FlattenBuf::clone(&current.routes);
+        for (path, added) in buf {
+            let mut value = routes.remove(&path).unwrap_or_default();
+            value.extend(added);
+            let value = check_methods(path.clone(), value)?;
+            routes.insert(path, value);
+        }
+        self.current.store(Arc::new(Router {
+            inner: Arc::new(matcher(&routes)?),
+            routes: Arc::new(routes),
+            #[cfg(all(debug_assertions, feature = "openapi"))]
+            openapi_entries: Arc::new(openapi_entries),
+            ..Router::clone(&current)
+        }));
+        Ok(())
+    }
+
+    /// Stop serving `method` requests to `path`, including guarded handlers. `path` is the route
+    /// pattern, such as `/users/{id}`.
+    ///
+    /// Returns whether any handler was removed.
+    pub fn remove(&self, path: &str, method: &Method) -> bool {
+        let _writer = self.writer.lock().unwrap_or_else(PoisonError::into_inner);
+        let current = self.current.load_full();
+        let mut routes = FlattenBuf::clone(&current.routes);
+        let Some(value) = routes.get_mut(path) else {
+            return false;
+        };
+        let before = value.len();
+        value.retain(|(app_method, _)| app_method != method);
+        if value.len() == before {
+            return false;
+        }
+        if value.is_empty() {
+            routes.remove(path);
+        }
+
+        // The remaining routes were accepted together before, so they cannot conflict now.
+        let Ok(inner) = matcher(&routes) else {
+            return false;
+        };
+        #[cfg(all(debug_assertions, feature = "openapi"))]
+        let openapi_entries = current
+            .openapi_entries
+            .iter()
+            .filter(|entry| !(entry.path == path && entry.method == method))
+            .cloned()
+            .collect();
+        self.current.store(Arc::new(Router {
+            inner: Arc::new(inner),
+            routes: Arc::new(routes),
+            #[cfg(all(debug_assertions, feature = "openapi"))]
+            openapi_entries: Arc::new(openapi_entries),
+            ..Router::clone(&current)
+        }));
+        true
+    }
+
+    /// A snapshot of the current routes, unaffected by later changes.
+    #[must_use]
+    pub fn router(&self) -> Router {
+        Router::clone(&self.current.load())
+    }
+}
+
+impl Endpoint for DynamicRouter {
+    type Error = BoxHttpError;
+    async fn respond(&mut self, request: &mut Request) -> Result<Response, Self::Error> {
+        let mut router = self.router();
+        if router.already_router_enabled {
+            request.extensions_mut().insert(self.clone());
+        }
+        router.respond(request).await
+    }
+}
+
+impl Extractor for DynamicRouter {
+    type Error = RouterNotExist;
+    async fn extract(request: &mut Request) -> Result<Self, Self::Error> {
+        request
+            .extensions()
+            .get::<Self>()
+            .cloned()
+            .ok_or(RouterNotExist::new())
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
 mod tests {
-    use super::{build, RouteBuildError};
+    use super::{build, DynamicRouter, RouteBuildError, RouterConfig, TrailingSlash};
     use crate::{
         header,
         middleware::ErrorHandlingMiddleware,
         middleware::Middleware,
-        routing::{CreateRouteNode, Params, Route},
-        Body, Error, Method, Response, Result, StatusCode,
+        routing::{CreateRouteNode, MatchedPath, Params, Route, RouteNode},
+        test::TestClient,
+        utils::State,
+        Body, BoxHttpError, Endpoint, Error, Method, Response, Result, StatusCode,
     };
 
     fn get_request(path: &str) -> http_kit::Request {
@@ -401,6 +901,98 @@ mod tests {
         assert_eq!(body, "Hello, Ada!");
     }
 
+    #[tokio::test]
+    async fn exposes_the_matched_route_pattern() {
+        let router = Route::new(("/users".route((
+            "/{id}".at(|path: MatchedPath| async move { Result::Ok(path.as_str().to_owned()) }),
+        )),))
+        .build();
+        let response = router.go(get_request("/users/42")).await.unwrap();
+        let body = response.into_body().into_string().await.unwrap();
+        assert_eq!(body, "/users/{id}");
+    }
+
+    #[tokio::test]
+    async fn accepts_handlers_with_sixteen_extractors() {
+        #[allow(clippy::too_many_arguments, clippy::similar_names)]
+        async fn wide(
+            params: Params,
+            p1: MatchedPath,
+            p2: MatchedPath,
+            p3: MatchedPath,
+            p4: MatchedPath,
+            p5: MatchedPath,
+            p6: MatchedPath,
+            p7: MatchedPath,
+            p8: MatchedPath,
+            p9: MatchedPath,
+            p10: MatchedPath,
+            p11: MatchedPath,
+            p12: MatchedPath,
+            p13: MatchedPath,
+            p14: MatchedPath,
+            p15: MatchedPath,
+        ) -> Result<String> {
+            let paths = [
+                p1, p2, p3, p4, p5, p6, p7, p8, p9, p10, p11, p12, p13, p14, p15,
+            ];
+            let id = params.get("id")?;
+            Ok(format!("{id} {}", paths.len()))
+        }
+
+        let router = Route::new(("/wide/{id}".at(wide),)).build();
+        let response = router.go(get_request("/wide/7")).await.unwrap();
+        let body = response.into_body().into_string().await.unwrap();
+        assert_eq!(body, "7 15");
+    }
+
+    fn decoding_router() -> super::Router {
+        Route::new((
+            "/hello/{name}".at(|params: Params| async move {
+                Result::Ok(format!(
+                    "{}|{}",
+                    params.get("name")?,
+                    params.get_raw("name")?
+                ))
+            }),
+            "/files/{*path}"
+                .at(|params: Params| async move { Result::Ok(params.get("path")?.to_owned()) }),
+        ))
+        .build()
+    }
+
+    #[tokio::test]
+    async fn percent_decodes_params() {
+        let router = decoding_router();
+        assert_eq!(
+            body_for(&router, get_request("/hello/J%C3%BCrgen"))
+                .await
+                .as_deref(),
+            Some("Jürgen|J%C3%BCrgen")
+        );
+        assert_eq!(
+            body_for(&router, get_request("/hello/a%2Fb"))
+                .await
+                .as_deref(),
+            Some("a/b|a%2Fb")
+        );
+        assert_eq!(
+            body_for(&router, get_request("/files/%C3%A9t%C3%A9/a%2fb/../c"))
+                .await
+                .as_deref(),
+            Some("été/a%2Fb/../c")
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_params_that_are_not_utf8() {
+        let router = decoding_router();
+        for path in ["/hello/%FF", "/hello/%E2%82", "/files/ok/%C3%28"] {
+            let error = router.clone().go(get_request(path)).await.unwrap_err();
+            assert_eq!(error.status(), StatusCode::BAD_REQUEST);
+        }
+    }
+
     #[tokio::test]
     async fn builds_routes_from_create_route_node_trait() {
         async fn greet(params: Params) -> Result<String> {
@@ -456,6 +1048,51 @@ mod tests {
         assert_eq!(header.to_str().unwrap(), "applied");
     }
 
+    #[derive(Clone)]
+    struct MethodEcho;
+
+    impl Endpoint for MethodEcho {
+        type Error = std::convert::Infallible;
+        async fn respond(
+            &mut self,
+            request: &mut http_kit::Request,
+        ) -> std::result::Result<Response, Self::Error> {
+            Ok(Response::new(Body::from(request.method().to_string())))
+        }
+    }
+
+    #[tokio::test]
+    async fn mounts_hand_written_endpoints_under_route_middleware() {
+        let route = Route::new((
+            "/graphql".endpoint(Method::POST, MethodEcho),
+            RouteNode::from_endpoint("/purge", Method::from_bytes(b"PURGE").unwrap(), MethodEcho),
+        ))
+        .middleware(HeaderMiddleware);
+        let router = build(route).unwrap();
+
+        for (path, method) in [
+            ("/graphql", Method::POST),
+            ("/purge", Method::from_bytes(b"PURGE").unwrap()),
+        ] {
+            let response = router
+                .clone()
+                .go(request_with_method(path, method.clone()))
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(response.headers()["x-middleware"], "applied");
+            let body = response.into_body().into_string().await.unwrap();
+            assert_eq!(body, method.as_str());
+        }
+
+        let error = router
+            .clone()
+            .go(get_request("/graphql"))
+            .await
+            .unwrap_err();
+        assert_eq!(error.status(), StatusCode::NOT_FOUND);
+    }
+
     #[tokio::test]
     async fn wraps_handlers_with_error_handling_middleware() {
         async fn fail() -> Result<&'static str> {
@@ -487,6 +1124,139 @@ mod tests {
         ));
     }
 
+    fn request_for_host(path: &str, host: &str) -> http_kit::Request {
+        let mut request = get_request(path);
+        request
+            .headers_mut()
+            .insert(header::HOST, host.parse().unwrap());
+        request
+    }
+
+    async fn body_for(router: &super::Router, request: http_kit::Request) -> Option<String> {
+        let response = router.clone().go(request).await.ok()?;
+        Some(
+            response
+                .into_body()
+                .into_string()
+                .await
+                .unwrap()
+                .to_string(),
+        )
+    }
+
+    #[tokio::test]
+    async fn routes_hosts_sharing_a_path() {
+        let router = build(Route::new((
+            Route::new(("/".at(|| async { Result::Ok("api") }),)).host("api.example.com"),
+            Route::new(("/".at(|| async { Result::Ok("admin") }),)).host("admin.example.com"),
+        )))
+        .unwrap();
+
+        let api = request_for_host("/", "api.example.com");
+        assert_eq!(body_for(&router, api).await.as_deref(), Some("api"));
+        let admin = request_for_host("/", "admin.example.com:8080");
+        assert_eq!(body_for(&router, admin).await.as_deref(), Some("admin"));
+
+        let error = router
+            .clone()
+            .go(request_for_host("/", "other.example.com"))
+            .await
+            .unwrap_err();
+        assert_eq!(error.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn rejected_guards_fall_through_to_other_candidates() {
+        let router = build(Route::new((
+            "/".at(|| async { Result::Ok("fallback") }),
+            "/".at(|| async { Result::Ok("beta") })
+                .guard(|request| request.headers().contains_key("x-beta")),
+            "/".at(|| async { Result::Ok("tenant") })
+                .host("*.example.com"),
+        )))
+        .unwrap();
+
+        let mut beta = request_for_host("/", "a.example.com");
+        beta.headers_mut()
+            .insert("x-beta", header::HeaderValue::from_static("1"));
+        assert_eq!(body_for(&router, beta).await.as_deref(), Some("beta"));
+
+        let tenant = request_for_host("/", "a.example.com");
+        assert_eq!(body_for(&router, tenant).await.as_deref(), Some("tenant"));
+
+        let other = request_for_host("/", "example.org");
+        assert_eq!(body_for(&router, other).await.as_deref(), Some("fallback"));
+    }
+
+    #[tokio::test]
+    async fn guarded_duplicates_do_not_conflict() {
+        let route = Route::new((
+            "/dup".at(|| async { Result::Ok("first") }).host("a.test"),
+            "/dup".at(|| async { Result::Ok("second") }).host("b.test"),
+            "/dup".at(|| async { Result::Ok("third") }),
+        ));
+        assert!(build(route).is_ok());
+    }
+
+    fn slash_router(trailing_slash: TrailingSlash) -> super::Router {
+        Route::new((
+            "/users".at(|| async { Result::Ok("users") }),
+            "/teams/".at(|| async { Result::Ok("teams") }),
+            "/files/{*path}"
+                .at(|params: Params| async move { Result::Ok(params.get("path")?.to_owned()) }),
+        ))
+        .build_with(RouterConfig {
+            trailing_slash,
+            ..RouterConfig::default()
+        })
+    }
+
+    #[tokio::test]
+    async fn strict_trailing_slash_keeps_routes_distinct() {
+        let router = slash_router(TrailingSlash::Strict);
+        assert_eq!(
+            body_for(&router, get_request("/users")).await.as_deref(),
+            Some("users")
+        );
+        assert!(body_for(&router, get_request("/users/")).await.is_none());
+        assert!(body_for(&router, get_request("/teams")).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn merges_trailing_slash_variants() {
+        let router = slash_router(TrailingSlash::Merge);
+        for (path, expected) in [
+            ("/users", "users"),
+            ("/users/", "users"),
+            ("/teams/", "teams"),
+            ("/teams", "teams"),
+            ("/files/a/", "a/"),
+        ] {
+            assert_eq!(
+                body_for(&router, get_request(path)).await.as_deref(),
+                Some(expected)
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn redirects_to_canonical_trailing_slash() {
+        let router = slash_router(TrailingSlash::Redirect);
+        for (path, location) in [("/users/?page=2", "/users?page=2"), ("/teams", "/teams/")] {
+            let response = router.clone().go(get_request(path)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::PERMANENT_REDIRECT);
+            assert_eq!(response.headers()[header::LOCATION], location);
+        }
+
+        let response = router.clone().go(get_request("/users")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            body_for(&router, get_request("/files/a/")).await.as_deref(),
+            Some("a/")
+        );
+        assert!(body_for(&router, get_request("/files/")).await.is_none());
+    }
+
     #[tokio::test]
     async fn routes_distinct_methods_on_same_path() {
         async fn list() -> Result<&'static str> {
@@ -558,8 +1328,8 @@ mod tests {
 
         let route = Route::new(("/ws".ws(|_socket| async move {}),));
         let router = build(route).unwrap();
-        let mut request = get_request("/ws");
-        {
+        let upgrade_request = || {
+            let mut request = get_request("/ws");
             let headers = request.headers_mut();
             headers.insert(
                 header::SEC_WEBSOCKET_KEY,
@@ -571,10 +1341,160 @@ mod tests {
                 header::SEC_WEBSOCKET_VERSION,
                 HeaderValue::from_static("13"),
             );
-        }
+            request
+        };
 
-        let error = router.clone().go(request).await.unwrap_err();
+        let error = router.clone().go(upgrade_request()).await.unwrap_err();
         assert_eq!(error.status(), StatusCode::UPGRADE_REQUIRED);
+
+        // A backend that supports upgrades but provides no executor gets an error, not a panic.
+        let mut request = upgrade_request();
+        request
+            .extensions_mut()
+            .insert(hyper::upgrade::on(hyper::Request::new(())));
+        let error = router.clone().go(request).await.unwrap_err();
+        assert_eq!(error.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    fn json_error_router(route: Route) -> super::Router {
+        build(route).unwrap().on_error(|error, request| {
+            let body = if request
+                .headers()
+                .get(header::ACCEPT)
+                .is_some_and(|accept| accept == "application/json")
+            {
+                format!(
+                    r#"{{"error": "{error}", "status": {}}}"#,
+                    error.status().as_u16()
+                )
+            } else {
+                error.to_string()
+            };
+            let mut response = Response::new(Body::from(body));
+            *response.status_mut() = error.status();
+            response
+        })
+    }
+
+    #[tokio::test]
+    async fn renders_endpoint_errors_with_on_error() {
+        async fn missing() -> Result<&'static str> {
+            Err(Error::msg("no such user").set_status(StatusCode::NOT_FOUND))
+        }
+
+        let mut router = json_error_router(Route::new(("/user".at(missing),)));
+        let mut request = get_request("/user");
+        request
+            .headers_mut()
+            .insert(header::ACCEPT, "application/json".parse().unwrap());
+        let response = router.respond(&mut request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body = response.into_body().into_string().await.unwrap();
+        assert_eq!(body, r#"{"error": "no such user", "status": 404}"#);
+    }
+
+    #[tokio::test]
+    async fn renders_not_found_fallback_with_on_error() {
+        let mut router = json_error_router(Route::new(()));
+        let mut request = get_request("/unknown");
+        let response = router.respond(&mut request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body = response.into_body().into_string().await.unwrap();
+        assert_eq!(body, "Route not found.");
+    }
+
+    #[tokio::test]
+    async fn renders_empty_error_response_by_default() {
+        let mut router = build(Route::new(())).unwrap();
+        let mut request = get_request("/unknown");
+        let response = router.respond(&mut request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body = response.into_body().into_string().await.unwrap();
+        assert!(body.is_empty());
+    }
+
+    #[tokio::test]
+    async fn sends_error_headers_to_the_client() {
+        #[skyzen::error(status = UNAUTHORIZED)]
+        enum LoginError {
+            #[error("login required", header("WWW-Authenticate" = "Bearer realm=\"api\""))]
+            Missing,
+            #[error("token expired", header("www-authenticate" = "Bearer error=\"invalid_token\""))]
+            Expired,
+        }
+
+        async fn profile() -> Result<&'static str> {
+            Err(Error::msg("login required")
+                .set_status(StatusCode::UNAUTHORIZED)
+                .with_header(header::WWW_AUTHENTICATE, "Basic".parse().unwrap())
+                .with_header(header::WWW_AUTHENTICATE, "Bearer".parse().unwrap()))
+        }
+
+        async fn session() -> std::result::Result<&'static str, BoxHttpError> {
+            Err(LoginError::Missing.into())
+        }
+
+        async fn token() -> std::result::Result<&'static str, BoxHttpError> {
+            Err(LoginError::Expired.into())
+        }
+
+        let router = build(Route::new((
+            "/profile".at(profile),
+            "/session".at(session),
+            "/token".at(token),
+        )))
+        .unwrap();
+        let client = TestClient::new(router.clone());
+        let response = client.get("/profile").send().await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let challenges: Vec<_> = response
+            .headers()
+            .get_all(header::WWW_AUTHENTICATE)
+            .iter()
+            .collect();
+        assert_eq!(challenges, ["Basic", "Bearer"]);
+
+        let response = client.get("/session").send().await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            response.headers()[header::WWW_AUTHENTICATE],
+            "Bearer realm=\"api\""
+        );
+
+        // A custom renderer keeps its own headers and gains the missing ones.
+        let rendered = TestClient::new(router.on_error(|error, _request| {
+            let mut response = Response::new(Body::from(error.to_string()));
+            *response.status_mut() = error.status();
+            response
+                .headers_mut()
+                .insert(header::CACHE_CONTROL, "no-store".parse().unwrap());
+            response
+        }));
+        let response = rendered.get("/token").send().await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            response.headers()[header::WWW_AUTHENTICATE],
+            "Bearer error=\"invalid_token\""
+        );
+        assert_eq!(response.headers()[header::CACHE_CONTROL], "no-store");
+        assert_eq!(response.text().await, "token expired");
+    }
+
+    #[tokio::test]
+    async fn keeps_error_headers_through_route_middleware() {
+        async fn profile() -> Result<&'static str> {
+            Err(Error::msg("login required")
+                .set_status(StatusCode::UNAUTHORIZED)
+                .with_header(header::WWW_AUTHENTICATE, "Bearer".parse().unwrap()))
+        }
+
+        let route = Route::new(("/profile".at(profile),))
+            .middleware(State(1u32))
+            .middleware(State("outer"));
+        let client = TestClient::new(build(route).unwrap());
+        let response = client.get("/profile").send().await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(response.headers()[header::WWW_AUTHENTICATE], "Bearer");
     }
 
     #[tokio::test]
@@ -585,4 +1505,234 @@ mod tests {
         let error = response.unwrap_err();
         assert_eq!(error.status(), StatusCode::NOT_FOUND);
     }
+
+    async fn dynamic_response(
+        router: &DynamicRouter,
+        request: http_kit::Request,
+    ) -> (StatusCode, String) {
+        let mut request = request;
+        let response = router.clone().respond(&mut request).await.unwrap();
+        let status = response.status();
+        let body = response.into_body().into_string().await.unwrap();
+        (status, body.to_string())
+    }
+
+    #[tokio::test]
+    async fn dynamic_router_adds_and_removes_routes_while_serving() {
+        let router = Route::new(("/users".at(|| async { Result::Ok("users") }),))
+            .build()
+            .into_dynamic();
+        assert_eq!(
+            dynamic_response(&router, get_request("/plugin")).await.0,
+            StatusCode::NOT_FOUND
+        );
+
+        router
+            .add("/plugin", Method::GET, || async { Result::Ok("plugin") })
+            .unwrap();
+        assert_eq!(
+            dynamic_response(&router, get_request("/plugin")).await,
+            (StatusCode::OK, "plugin".to_owned())
+        );
+        assert_eq!(
+            dynamic_response(&router, get_request("/users")).await,
+            (StatusCode::OK, "users".to_owned())
+        );
+
+        let repeated = router.add("/users", Method::GET, || async { Result::Ok("other") });
+        assert!(matches!(
+            repeated,
+            Err(RouteBuildError::RepeatedMethod { ref path, .. }) if path == "/users"
+        ));
+        router
+            .add("/items/{id}", Method::GET, || async { Result::Ok("item") })
+            .unwrap();
+        let conflict = router.add("/items/{name}", Method::POST, || async {
+            Result::Ok("item")
+        });
+        assert!(matches!(conflict, Err(RouteBuildError::MatchitError(_))));
+        assert_eq!(
+            dynamic_response(&router, get_request("/users")).await,
+            (StatusCode::OK, "users".to_owned())
+        );
+
+        assert!(router.remove("/plugin", &Method::GET));
+        assert!(!router.remove("/plugin", &Method::GET));
+        assert!(!router.remove("/users", &Method::POST));
+        assert_eq!(
+            dynamic_response(&router, get_request("/plugin")).await.0,
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(
+            dynamic_response(&router, get_request("/items/1")).await,
+            (StatusCode::OK, "item".to_owned())
+        );
+    }
+
+    #[tokio::test]
+    async fn handlers_register_routes_through_the_programmable_router() {
+        async fn install(router: DynamicRouter) -> Result<&'static str> {
+            router.add_routes(("/hello".at(|| async { Result::Ok("hello") }),))?;
+            Ok("installed")
+        }
+
+        let router = Route::new(("/install".post(install),))
+            .build()
+            .enable_programmable_router()
+            .into_dynamic();
+        let snapshot = router.router();
+        assert_eq!(
+            dynamic_response(&router, request_with_method("/install", Method::POST)).await,
+            (StatusCode::OK, "installed".to_owned())
+        );
+        assert_eq!(
+            dynamic_response(&router, get_request("/hello")).await,
+            (StatusCode::OK, "hello".to_owned())
+        );
+        assert!(snapshot.go(get_request("/hello")).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn head_requests_fall_back_to_get_handlers_without_the_body() {
+        use std::sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        };
+
+        let polled = Arc::new(AtomicBool::new(false));
+        let stream = {
+            let polled = Arc::clone(&polled);
+            move || {
+                let polled = Arc::clone(&polled);
+                async move {
+                    let body = Body::from_stream(futures_util::stream::once(async move {
+                        polled.store(true, Ordering::SeqCst);
+                        Ok::<_, http_kit::BodyError>(http_kit::utils::Bytes::from_static(b"data"))
+                    }));
+                    Result::Ok(Response::new(body))
+                }
+            }
+        };
+        let route = || {
+            Route::new((
+                "/page".at(|| async {
+                    Result::Ok((
+                        "hello",
+                        (header::ETAG, header::HeaderValue::from_static("\"v1\"")),
+                    ))
+                }),
+                "/stream".at(stream.clone()),
+                "/custom".at(|| async { Result::Ok("get") }).head(|| async {
+                    Result::Ok((
+                        (header::ETAG, header::HeaderValue::from_static("\"head\"")),
+                        "",
+                    ))
+                }),
+            ))
+        };
+        let router = route().build();
+
+        let response = router
+            .go(request_with_method("/page", Method::HEAD))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::ETAG], "\"v1\"");
+        assert_eq!(response.headers()[header::CONTENT_LENGTH], "5");
+        let body = response.into_body().into_bytes().await.unwrap();
+        assert!(body.is_empty());
+
+        let response = router
+            .go(request_with_method("/stream", Method::HEAD))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.into_body().into_bytes().await.unwrap().is_empty());
+        assert!(!polled.load(Ordering::SeqCst));
+
+        let response = router
+            .go(request_with_method("/custom", Method::HEAD))
+            .await
+            .unwrap();
+        assert_eq!(response.headers()[header::ETAG], "\"head\"");
+
+        let strict = route().build_with(RouterConfig {
+            strict_head: true,
+            ..RouterConfig::default()
+        });
+        let error = strict
+            .go(request_with_method("/page", Method::HEAD))
+            .await
+            .unwrap_err();
+        assert_eq!(error.status(), StatusCode::NOT_FOUND);
+        assert!(strict
+            .go(request_with_method("/custom", Method::HEAD))
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn options_requests_list_the_allowed_methods() {
+        let route = || {
+            Route::new((
+                "/items"
+                    .at(|| async { Result::Ok("list") })
+                    .post(|| async { Result::Ok("created") }),
+                "/items/{id}".delete(|| async { Result::Ok("deleted") }),
+                "/custom"
+                    .at(|| async { Result::Ok("get") })
+                    .options(|| async { Result::Ok("custom options") }),
+            ))
+        };
+        let router = route().build();
+        let allow = |response: &Response| {
+            response.headers()[header::ALLOW]
+                .to_str()
+                .unwrap()
+                .to_owned()
+        };
+
+        let response = router
+            .go(request_with_method("/items", Method::OPTIONS))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert_eq!(allow(&response), "GET, POST, HEAD, OPTIONS");
+
+        let response = router
+            .go(request_with_method("/items/7", Method::OPTIONS))
+            .await
+            .unwrap();
+        assert_eq!(allow(&response), "DELETE, OPTIONS");
+
+        let response = router
+            .go(request_with_method("/custom", Method::OPTIONS))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(!response.headers().contains_key(header::ALLOW));
+        let body = response.into_body().into_string().await.unwrap();
+        assert_eq!(body, "custom options");
+
+        let error = router
+            .go(request_with_method("/missing", Method::OPTIONS))
+            .await
+            .unwrap_err();
+        assert_eq!(error.status(), StatusCode::NOT_FOUND);
+
+        let strict = route().build_with(RouterConfig {
+            strict_options: true,
+            strict_head: true,
+            ..RouterConfig::default()
+        });
+        let error = strict
+            .go(request_with_method("/items", Method::OPTIONS))
+            .await
+            .unwrap_err();
+        assert_eq!(error.status(), StatusCode::NOT_FOUND);
+        assert!(strict
+            .go(request_with_method("/custom", Method::OPTIONS))
+            .await
+            .is_ok());
+    }
 }
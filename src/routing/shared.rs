@@ -0,0 +1,69 @@
+use std::{future::Future, pin::Pin, sync::Arc};
+
+use http_kit::{
+    error::BoxHttpError, middleware::MiddlewareError, Endpoint, Middleware, Request, Response,
+};
+
+type RespondFuture<'a> = Pin<Box<dyn Future<Output = Result<Response, BoxHttpError>> + Send + 'a>>;
+
+/// A composed endpoint, built once when the route is defined and shared by every request.
+///
+/// Each request responds through its own clone of the endpoint, which is cheap for the stateless
+/// handlers and middleware that make up most routes.
+pub type SharedEndpoint = Arc<dyn CloneEndpoint>;
+
+/// Object-safe view of an `Endpoint + Clone` that responds through a fresh clone.
+pub trait CloneEndpoint: Send + Sync {
+    fn respond_cloned<'a>(&'a self, request: &'a mut Request) -> RespondFuture<'a>;
+}
+
+impl<E> CloneEndpoint for E
+where
+    E: Endpoint + Clone + Sync,
+{
+    fn respond_cloned<'a>(&'a self, request: &'a mut Request) -> RespondFuture<'a> {
+        let mut endpoint = self.clone();
+        Box::pin(async move {
+            endpoint
+                .respond(request)
+                .await
+                .map_err(|error| Box::new(error) as BoxHttpError)
+        })
+    }
+}
+
+/// [`Endpoint`] over a [`SharedEndpoint`], so middleware can wrap an already composed endpoint.
+/// Cloning it only bumps the reference count.
+#[derive(Clone)]
+pub struct SharedHandle(pub SharedEndpoint);
+
+impl Endpoint for SharedHandle {
+    type Error = BoxHttpError;
+    async fn respond(&mut self, request: &mut Request) -> Result<Response, Self::Error> {
+        self.0.respond_cloned(request).await
+    }
+}
+
+/// A middleware attached to a route, wrapped around a [`SharedHandle`].
+///
+/// Unlike [`WithMiddleware`](http_kit::endpoint::WithMiddleware), errors of the endpoint pass
+/// through as they are rather than inside a `MiddlewareError`, whose `source` skips the error it
+/// wraps and would hide the headers attached to it.
+#[derive(Clone)]
+pub struct Layered<M> {
+    pub inner: SharedHandle,
+    pub middleware: M,
+}
+
+impl<M: Middleware> Endpoint for Layered<M> {
+    type Error = BoxHttpError;
+    async fn respond(&mut self, request: &mut Request) -> Result<Response, Self::Error> {
+        self.middleware
+            .handle(request, &mut self.inner)
+            .await
+            .map_err(|error| match error {
+                MiddlewareError::Endpoint(error) => error,
+                MiddlewareError::Middleware(error) => Box::new(error),
+            })
+    }
+}
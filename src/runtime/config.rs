@@ -0,0 +1,681 @@
+//! Layered configuration of the native server: a TOML file, then `SKYZEN_*` environment
+//! variables, then CLI flags.
+
+#[cfg(feature = "toml")]
+use std::path::Path;
+use std::{
+    fmt,
+    net::{IpAddr, SocketAddr},
+    path::PathBuf,
+    time::Duration,
+};
+
+#[cfg(feature = "toml")]
+use toml::{Table, Value};
+use tracing_subscriber::EnvFilter;
+
+use super::native::{
+    parse_addr_list, parse_max_connections, parse_shutdown_timeout, CliOverrides, DEFAULT_ADDRESS,
+    DEFAULT_SHUTDOWN_TIMEOUT,
+};
+
+const ADDRESS: &str = "an address such as `127.0.0.1:8080`";
+const ADDRESSES: &str = "an address or a list of addresses such as `127.0.0.1:8080`";
+const SECONDS: &str = "a number of seconds such as `30` or `2.5`";
+const LIMIT: &str = "a non-negative integer";
+#[cfg(feature = "toml")]
+const BOOLEAN: &str = "`true` or `false`";
+#[cfg(feature = "toml")]
+const PATH: &str = "a file path";
+const FILTER: &str = "a log filter such as `info,skyzen=debug`";
+
+/// Settings of the native server, loaded by `#[skyzen::main]`.
+///
+/// Pass them to [`launch_with_config`](super::native::launch_with_config) or
+/// [`ServerBuilder::from_config`](super::native::ServerBuilder::from_config) to serve with them.
+///
+/// [`load`](Self::load) starts from the defaults and applies a TOML file, the environment and
+/// the command line in that order, each overriding the settings the previous one set:
+///
+/// | File key | Environment variable | CLI flag |
+/// |---|---|---|
+/// | `listen` | `SKYZEN_ADDRESS` | `--listen` / `--addr` (repeatable), `--host`, `--port` |
+/// | `shutdown_timeout` | `SKYZEN_SHUTDOWN_TIMEOUT` | `--shutdown-timeout` |
+/// | `max_connections` | `SKYZEN_MAX_CONNECTIONS` | `--max-connections` |
+/// | `proxy_protocol` | `SKYZEN_PROXY_PROTOCOL` | |
+/// | `tls_cert`, `tls_key` | `SKYZEN_TLS_CERT`, `SKYZEN_TLS_KEY` | `--tls-cert`, `--tls-key` |
+/// | `log_filter` | `SKYZEN_LOG_FILTER`, or else `RUST_LOG` | `--log-filter` |
+///
+/// The file is only read when named by `--config` or `SKYZEN_CONFIG`, and requires the `toml`
+/// feature:
+///
+/// ```toml
+/// listen = ["127.0.0.1:8080", "[::1]:8080"]
+/// shutdown_timeout = 10
+/// max_connections = 1024
+/// log_filter = "info,skyzen=debug"
+/// ```
+///
+/// Without `--listen`, `--host` and `--port` replace the host or port of the addresses set so
+/// far, or of [`DEFAULT_ADDRESS`] if there are none.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Config {
+    pub(super) listen: Vec<SocketAddr>,
+    pub(super) shutdown_timeout: Duration,
+    pub(super) max_connections: usize,
+    pub(super) proxy_protocol: bool,
+    pub(super) tls_cert: Option<PathBuf>,
+    pub(super) tls_key: Option<PathBuf>,
+    pub(super) log_filter: Option<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Config {
+    /// The defaults: [`DEFAULT_ADDRESS`], a 30 second shutdown timeout, no connection limit, no
+    /// PROXY protocol, plain HTTP, and `RUST_LOG` or `info` for logging.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            listen: Vec::new(),
+            shutdown_timeout: DEFAULT_SHUTDOWN_TIMEOUT,
+            max_connections: 0,
+            proxy_protocol: false,
+            tls_cert: None,
+            tls_key: None,
+            log_filter: None,
+        }
+    }
+
+    /// Load the configuration file named by `--config` or `SKYZEN_CONFIG`, if any, then apply
+    /// `SKYZEN_*` environment variables and the CLI flags in `args`.
+    ///
+    /// `args` starts with the binary name, as [`std::env::args`] does. Unknown flags are ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error naming the offending key, variable or flag if the file cannot be read,
+    /// is not valid TOML, has an unknown key, or if any setting has an invalid value.
+    pub fn load(args: impl IntoIterator<Item = String>) -> Result<Self, ConfigError> {
+        Self::load_with_env(args, |name| std::env::var(name).ok(), &mut Err)
+    }
+
+    /// [`load`](Self::load), but invalid environment variables and CLI flags are skipped and
+    /// returned next to the configuration instead of failing, as `#[skyzen::main]` does unless
+    /// it is given `config = strict`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the configuration file cannot be read or has an invalid setting.
+    pub fn load_lenient(
+        args: impl IntoIterator<Item = String>,
+    ) -> Result<(Self, Vec<ConfigError>), ConfigError> {
+        let mut skipped = Vec::new();
+        let config = Self::load_with_env(args, |name| std::env::var(name).ok(), &mut |error| {
+            skipped.push(error);
+            Ok(())
+        })?;
+        Ok((config, skipped))
+    }
+
+    /// Read the settings of a TOML file, keeping the defaults for the keys it leaves out.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read, is not valid TOML, has an unknown key, or
+    /// has an invalid value.
+    #[cfg(feature = "toml")]
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path).map_err(|error| ConfigError::Read {
+            path: path.to_owned(),
+            error,
+        })?;
+        let table = source
+            .parse::<Table>()
+            .map_err(|error| ConfigError::Parse {
+                path: path.to_owned(),
+                message: error.to_string(),
+            })?;
+        let mut config = Self::new();
+        config.apply_file(table)?;
+        Ok(config)
+    }
+
+    /// Listen on `addr` too. Without any address, the server listens on [`DEFAULT_ADDRESS`].
+    #[must_use]
+    pub fn listen(mut self, addr: SocketAddr) -> Self {
+        self.listen.push(addr);
+        self
+    }
+
+    /// How long to wait for in-flight connections after shutdown begins.
+    #[must_use]
+    pub const fn shutdown_timeout(mut self, timeout: Duration) -> Self {
+        self.shutdown_timeout = timeout;
+        self
+    }
+
+    /// Serve at most `limit` connections at once; `0` means unlimited.
+    #[must_use]
+    pub const fn max_connections(mut self, limit: usize) -> Self {
+        self.max_connections = limit;
+        self
+    }
+
+    /// Expect every connection to start with a PROXY protocol header.
+    #[must_use]
+    pub const fn proxy_protocol(mut self, enabled: bool) -> Self {
+        self.proxy_protocol = enabled;
+        self
+    }
+
+    /// Terminate TLS using a PEM certificate chain and private key, with the `tls` feature.
+    #[must_use]
+    pub fn tls(mut self, cert_path: impl Into<PathBuf>, key_path: impl Into<PathBuf>) -> Self {
+        self.tls_cert = Some(cert_path.into());
+        self.tls_key = Some(key_path.into());
+        self
+    }
+
+    /// Log with a `tracing` filter such as `info,skyzen=debug` instead of `RUST_LOG`.
+    #[must_use]
+    pub fn log_filter(mut self, filter: impl Into<String>) -> Self {
+        self.log_filter = Some(filter.into());
+        self
+    }
+
+    /// [`load`](Self::load) with environment variables looked up through `env`, passing invalid
+    /// variables and flags to `report`, which fails the load or skips the setting.
+    fn load_with_env(
+        args: impl IntoIterator<Item = String>,
+        env: impl Fn(&str) -> Option<String>,
+        report: Report<'_>,
+    ) -> Result<Self, ConfigError> {
+        let cli = CliOverrides::parse(args);
+        let mut config = match cli.config.clone().or_else(|| env("SKYZEN_CONFIG")) {
+            #[cfg(feature = "toml")]
+            Some(path) => Self::from_file(path)?,
+            #[cfg(not(feature = "toml"))]
+            Some(path) => return Err(ConfigError::FileUnsupported { path: path.into() }),
+            None => Self::new(),
+        };
+        config.apply_env(env, report)?;
+        config.apply_cli(cli, report)?;
+        Ok(config)
+    }
+
+    #[cfg(feature = "toml")]
+    fn apply_file(&mut self, table: Table) -> Result<(), ConfigError> {
+        for (key, value) in table {
+            let invalid = |expected| ConfigError::invalid(&key, &value, expected);
+            match key.as_str() {
+                "listen" => {
+                    self.listen = match &value {
+                        Value::String(list) => parse_addrs(&key, list)?,
+                        Value::Array(items) => items
+                            .iter()
+                            .map(|item| {
+                                item.as_str()
+                                    .and_then(|addr| addr.trim().parse().ok())
+                                    .ok_or_else(|| ConfigError::invalid(&key, item, ADDRESS))
+                            })
+                            .collect::<Result<_, _>>()?,
+                        _ => return Err(invalid(ADDRESSES)),
+                    };
+                }
+                "shutdown_timeout" => {
+                    self.shutdown_timeout = match &value {
+                        Value::Integer(seconds) => u64::try_from(*seconds)
+                            .map(Duration::from_secs)
+                            .map_err(|_| invalid(SECONDS))?,
+                        Value::Float(seconds) => {
+                            Duration::try_from_secs_f64(*seconds).map_err(|_| invalid(SECONDS))?
+                        }
+                        Value::String(seconds) => parse_timeout(&key, seconds)?,
+                        _ => return Err(invalid(SECONDS)),
+                    };
+                }
+                "max_connections" => {
+                    self.max_connections = value
+                        .as_integer()
+                        .and_then(|limit| usize::try_from(limit).ok())
+                        .ok_or_else(|| invalid(LIMIT))?;
+                }
+                "proxy_protocol" => {
+                    self.proxy_protocol = value.as_bool().ok_or_else(|| invalid(BOOLEAN))?;
+                }
+                "tls_cert" => {
+                    self.tls_cert = Some(value.as_str().ok_or_else(|| invalid(PATH))?.into());
+                }
+                "tls_key" => {
+                    self.tls_key = Some(value.as_str().ok_or_else(|| invalid(PATH))?.into());
+                }
+                "log_filter" => {
+                    let filter = value.as_str().ok_or_else(|| invalid(FILTER))?;
+                    self.log_filter = Some(parse_filter(&key, filter)?);
+                }
+                _ => return Err(ConfigError::UnknownKey { key }),
+            }
+        }
+        Ok(())
+    }
+
+    fn apply_env(
+        &mut self,
+        env: impl Fn(&str) -> Option<String>,
+        report: Report<'_>,
+    ) -> Result<(), ConfigError> {
+        if let Some(value) = env("SKYZEN_ADDRESS") {
+            set(
+                &mut self.listen,
+                parse_addrs("SKYZEN_ADDRESS", &value),
+                report,
+            )?;
+        }
+        if let Some(value) = env("SKYZEN_SHUTDOWN_TIMEOUT") {
+            let timeout = parse_timeout("SKYZEN_SHUTDOWN_TIMEOUT", &value);
+            set(&mut self.shutdown_timeout, timeout, report)?;
+        }
+        if let Some(value) = env("SKYZEN_MAX_CONNECTIONS") {
+            let limit = parse_limit("SKYZEN_MAX_CONNECTIONS", &value);
+            set(&mut self.max_connections, limit, report)?;
+        }
+        if let Some(value) = env("SKYZEN_PROXY_PROTOCOL") {
+            let enabled = match value.trim() {
+                "1" | "true" | "on" => Ok(true),
+                "" | "0" | "false" | "off" => Ok(false),
+                _ => Err(ConfigError::invalid(
+                    "SKYZEN_PROXY_PROTOCOL",
+                    &value,
+                    "`1`, `true`, `on`, `0`, `false` or `off`",
+                )),
+            };
+            set(&mut self.proxy_protocol, enabled, report)?;
+        }
+        if let Some(path) = env("SKYZEN_TLS_CERT") {
+            self.tls_cert = Some(path.into());
+        }
+        if let Some(path) = env("SKYZEN_TLS_KEY") {
+            self.tls_key = Some(path.into());
+        }
+        let filter = env("SKYZEN_LOG_FILTER")
+            .map(|filter| ("SKYZEN_LOG_FILTER", filter))
+            .or_else(|| env("RUST_LOG").map(|filter| ("RUST_LOG", filter)));
+        if let Some((key, filter)) = filter {
+            set(
+                &mut self.log_filter,
+                parse_filter(key, &filter).map(Some),
+                report,
+            )?;
+        }
+        Ok(())
+    }
+
+    fn apply_cli(&mut self, cli: CliOverrides, report: Report<'_>) -> Result<(), ConfigError> {
+        if let Some(value) = cli.shutdown_timeout {
+            let timeout = parse_timeout("--shutdown-timeout", &value);
+            set(&mut self.shutdown_timeout, timeout, report)?;
+        }
+        if let Some(value) = cli.max_connections {
+            let limit = parse_limit("--max-connections", &value);
+            set(&mut self.max_connections, limit, report)?;
+        }
+        if let Some(path) = cli.tls_cert {
+            self.tls_cert = Some(path.into());
+        }
+        if let Some(path) = cli.tls_key {
+            self.tls_key = Some(path.into());
+        }
+        if let Some(filter) = cli.log_filter {
+            let filter = parse_filter("--log-filter", &filter).map(Some);
+            set(&mut self.log_filter, filter, report)?;
+        }
+
+        let mut listen = Vec::new();
+        for addr in &cli.listen {
+            match addr.trim().parse() {
+                Ok(addr) => listen.push(addr),
+                Err(_) => report(ConfigError::invalid("--listen", addr, ADDRESS))?,
+            }
+        }
+        if !listen.is_empty() {
+            self.listen = listen;
+            return Ok(());
+        }
+        let mut host = None;
+        if let Some(value) = cli.host {
+            let ip = value
+                .trim()
+                .parse::<IpAddr>()
+                .map(Some)
+                .map_err(|_| ConfigError::invalid("--host", &value, "an IP address"));
+            set(&mut host, ip, report)?;
+        }
+        let mut port = None;
+        if let Some(value) = cli.port {
+            let number = value
+                .trim()
+                .parse::<u16>()
+                .map(Some)
+                .map_err(|_| ConfigError::invalid("--port", &value, "a port number"));
+            set(&mut port, number, report)?;
+        }
+        if host.is_none() && port.is_none() {
+            return Ok(());
+        }
+        if self.listen.is_empty() {
+            self.listen.push(DEFAULT_ADDRESS);
+        }
+        for addr in &mut self.listen {
+            if let Some(ip) = host {
+                addr.set_ip(ip);
+            }
+            if let Some(port) = port {
+                addr.set_port(port);
+            }
+        }
+        self.listen.dedup();
+        Ok(())
+    }
+}
+
+/// Receives invalid environment variables and CLI flags: returning the error stops loading,
+/// returning `Ok` skips the setting.
+type Report<'a> = &'a mut dyn FnMut(ConfigError) -> Result<(), ConfigError>;
+
+/// Store `value` in `slot`, or hand its error to `report` and keep the previous value.
+fn set<T>(
+    slot: &mut T,
+    value: Result<T, ConfigError>,
+    report: Report<'_>,
+) -> Result<(), ConfigError> {
+    match value {
+        Ok(value) => {
+            *slot = value;
+            Ok(())
+        }
+        Err(error) => report(error),
+    }
+}
+
+fn parse_addrs(key: &str, value: &str) -> Result<Vec<SocketAddr>, ConfigError> {
+    parse_addr_list(value).map_err(|_| ConfigError::invalid(key, value, ADDRESSES))
+}
+
+fn parse_timeout(key: &str, value: &str) -> Result<Duration, ConfigError> {
+    parse_shutdown_timeout(value).ok_or_else(|| ConfigError::invalid(key, value, SECONDS))
+}
+
+fn parse_limit(key: &str, value: &str) -> Result<usize, ConfigError> {
+    parse_max_connections(value).ok_or_else(|| ConfigError::invalid(key, value, LIMIT))
+}
+
+fn parse_filter(key: &str, value: &str) -> Result<String, ConfigError> {
+    EnvFilter::try_new(value)
+        .map(|_| value.to_owned())
+        .map_err(|_| ConfigError::invalid(key, value, FILTER))
+}
+
+/// Errors produced while loading a [`Config`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ConfigError {
+    /// The configuration file could not be read.
+    Read {
+        /// Path of the file.
+        path: PathBuf,
+        /// Underlying I/O error.
+        error: std::io::Error,
+    },
+    /// The configuration file is not valid TOML.
+    Parse {
+        /// Path of the file.
+        path: PathBuf,
+        /// Description of the syntax error, with its location.
+        message: String,
+    },
+    /// A configuration file was named, but skyzen was built without the `toml` feature.
+    FileUnsupported {
+        /// Path of the file.
+        path: PathBuf,
+    },
+    /// The configuration file has a key that is not a setting.
+    UnknownKey {
+        /// The unknown key.
+        key: String,
+    },
+    /// A setting has an invalid value.
+    Invalid {
+        /// The file key, environment variable or CLI flag that set the value.
+        key: String,
+        /// The rejected value.
+        value: String,
+        /// What the setting accepts.
+        expected: &'static str,
+    },
+}
+
+impl ConfigError {
+    fn invalid(key: &str, value: impl fmt::Display, expected: &'static str) -> Self {
+        Self::Invalid {
+            key: key.to_owned(),
+            value: value.to_string(),
+            expected,
+        }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Read { path, error } => {
+                write!(
+                    f,
+                    "failed to read config file `{}`: {error}",
+                    path.display()
+                )
+            }
+            Self::Parse { path, message } => {
+                write!(f, "invalid config file `{}`: {message}", path.display())
+            }
+            Self::FileUnsupported { path } => write!(
+                f,
+                "cannot read config file `{}`: skyzen was built without the `toml` feature",
+                path.display()
+            ),
+            Self::UnknownKey { key } => write!(f, "unknown config key `{key}`"),
+            Self::Invalid {
+                key,
+                value,
+                expected,
+            } => write!(
+                f,
+                "invalid value `{value}` for `{key}`: expected {expected}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Read { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, io::Write, time::Duration};
+
+    use super::{Config, ConfigError, Report};
+    #[cfg(feature = "toml")]
+    use crate::runtime::native::DEFAULT_ADDRESS;
+
+    fn load(
+        file: Option<&str>,
+        env: &[(&str, &str)],
+        args: &[&str],
+    ) -> Result<Config, ConfigError> {
+        load_reporting(file, env, args, &mut Err)
+    }
+
+    fn load_reporting(
+        file: Option<&str>,
+        env: &[(&str, &str)],
+        args: &[&str],
+        report: Report<'_>,
+    ) -> Result<Config, ConfigError> {
+        let mut file_handle = tempfile::NamedTempFile::new().unwrap();
+        let mut command_line = vec!["app".to_owned()];
+        if let Some(contents) = file {
+            file_handle.write_all(contents.as_bytes()).unwrap();
+            command_line.push("--config".to_owned());
+            command_line.push(file_handle.path().display().to_string());
+        }
+        command_line.extend(args.iter().map(|&arg| arg.to_owned()));
+        let env: HashMap<_, _> = env.iter().copied().collect();
+        Config::load_with_env(
+            command_line,
+            |name| env.get(name).map(|&value| value.to_owned()),
+            report,
+        )
+    }
+
+    fn ports(config: &Config) -> Vec<u16> {
+        config
+            .listen
+            .iter()
+            .map(std::net::SocketAddr::port)
+            .collect()
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn cli_overrides_env_which_overrides_the_file() {
+        let file = "listen = \"127.0.0.1:8000\"\nshutdown_timeout = 5\n";
+        let env = [("SKYZEN_ADDRESS", "127.0.0.1:9000")];
+
+        let config = load(Some(file), &[], &[]).unwrap();
+        assert_eq!(ports(&config), [8000]);
+        let config = load(Some(file), &env, &[]).unwrap();
+        assert_eq!(ports(&config), [9000]);
+        let config = load(Some(file), &env, &["--port", "10000"]).unwrap();
+        assert_eq!(config.listen, ["127.0.0.1:10000".parse().unwrap()]);
+        // Settings a later layer leaves alone keep the earlier value.
+        assert_eq!(config.shutdown_timeout, Duration::from_secs(5));
+
+        let config = load(None, &[], &["--port=10000"]).unwrap();
+        assert_eq!(
+            config.listen,
+            [DEFAULT_ADDRESS].map(|mut addr| {
+                addr.set_port(10000);
+                addr
+            })
+        );
+        assert_eq!(load(None, &[], &[]).unwrap(), Config::new());
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn reads_every_file_setting() {
+        let file = r#"
+            listen = ["127.0.0.1:8080", "[::1]:8080"]
+            shutdown_timeout = 2.5
+            max_connections = 64
+            proxy_protocol = true
+            tls_cert = "cert.pem"
+            tls_key = "key.pem"
+            log_filter = "info,skyzen=debug"
+        "#;
+        let expected = Config::new()
+            .listen("127.0.0.1:8080".parse().unwrap())
+            .listen("[::1]:8080".parse().unwrap())
+            .shutdown_timeout(Duration::from_millis(2500))
+            .max_connections(64)
+            .proxy_protocol(true)
+            .tls("cert.pem", "key.pem")
+            .log_filter("info,skyzen=debug");
+        assert_eq!(load(Some(file), &[], &[]).unwrap(), expected);
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn errors_name_the_offending_key() {
+        let message = |result: Result<Config, ConfigError>| result.unwrap_err().to_string();
+        assert_eq!(
+            message(load(Some("max_connections = -1"), &[], &[])),
+            "invalid value `-1` for `max_connections`: expected a non-negative integer"
+        );
+        assert_eq!(
+            message(load(Some("listen = [\"127.0.0.1:80\", 80]"), &[], &[])),
+            "invalid value `80` for `listen`: expected an address such as `127.0.0.1:8080`"
+        );
+        assert_eq!(
+            message(load(Some("max_conections = 1"), &[], &[])),
+            "unknown config key `max_conections`"
+        );
+        assert!(message(load(Some("listen = "), &[], &[])).starts_with("invalid config file"));
+        assert_eq!(
+            message(load(None, &[("SKYZEN_SHUTDOWN_TIMEOUT", "soon")], &[])),
+            "invalid value `soon` for `SKYZEN_SHUTDOWN_TIMEOUT`: expected a number of seconds \
+             such as `30` or `2.5`"
+        );
+        assert_eq!(
+            message(load(None, &[], &["--port", "http"])),
+            "invalid value `http` for `--port`: expected a port number"
+        );
+        assert!(matches!(
+            Config::load_with_env(
+                ["app", "--config", "missing.toml"].map(str::to_owned),
+                |_| None,
+                &mut Err,
+            ),
+            Err(ConfigError::Read { .. })
+        ));
+    }
+
+    #[cfg(not(feature = "toml"))]
+    #[test]
+    fn config_file_requires_the_toml_feature() {
+        assert!(matches!(
+            load(Some("max_connections = 1"), &[], &[]),
+            Err(ConfigError::FileUnsupported { .. })
+        ));
+    }
+
+    #[test]
+    fn lenient_loading_skips_invalid_env_and_flags() {
+        let env = [
+            ("SKYZEN_ADDRESS", "127.0.0.1:9000"),
+            ("SKYZEN_SHUTDOWN_TIMEOUT", "soon"),
+        ];
+        let args = [
+            "--port",
+            "http",
+            "--max-connections",
+            "8",
+            "--listen",
+            "nowhere",
+        ];
+        let mut skipped = Vec::new();
+        let config = load_reporting(None, &env, &args, &mut |error| {
+            skipped.push(error.to_string());
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(ports(&config), [9000]);
+        assert_eq!(config.shutdown_timeout, Duration::from_secs(30));
+        assert_eq!(config.max_connections, 8);
+        assert_eq!(skipped.len(), 3);
+        assert!(skipped[0].contains("SKYZEN_SHUTDOWN_TIMEOUT"));
+        assert!(load(None, &env, &[]).is_err());
+    }
+}
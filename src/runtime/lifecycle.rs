@@ -0,0 +1,188 @@
+//! Startup and shutdown hooks.
+//!
+//! Hooks run code once per server rather than once per request, such as running migrations or
+//! warming caches at startup and flushing telemetry or closing pools at shutdown. Register them
+//! from the app factory of `#[skyzen::main]`:
+//!
+//! ```
+//! use skyzen::{
+//!     routing::{CreateRouteNode, Route, Router},
+//!     runtime::lifecycle::{on_shutdown, on_startup, LifecycleContext},
+//!     Result,
+//! };
+//!
+//! fn app() -> Router {
+//!     on_startup(|context: LifecycleContext| async move {
+//!         tracing::info!("warming caches for {:?}", context.addrs());
+//!         Ok::<_, std::io::Error>(())
+//!     });
+//!     on_shutdown(|_context: LifecycleContext| async { Ok::<_, std::io::Error>(()) });
+//!     Route::new(("/".at(|| async { Result::Ok("ok") }),)).build()
+//! }
+//! ```
+//!
+//! The native runtime runs startup hooks after binding its listeners but before accepting any
+//! connection, and shutdown hooks once the accept loops have stopped and in-flight connections
+//! are drained. A [`ServerBuilder`](crate::runtime::native::ServerBuilder) takes its own hooks through
+//! `on_startup` and `on_shutdown` methods.
+//!
+//! The wasm runtime has no startup or shutdown: it runs the startup hooks on the first `fetch`
+//! and ignores shutdown hooks.
+
+use std::{
+    fmt::Display,
+    future::Future,
+    mem,
+    net::SocketAddr,
+    pin::Pin,
+    sync::{Mutex, PoisonError},
+};
+
+use tracing::error;
+
+type HookFuture = Pin<Box<dyn Future<Output = Result<(), String>> + Send>>;
+type Hook = Box<dyn FnOnce(LifecycleContext) -> HookFuture + Send>;
+
+/// What startup and shutdown hooks know about the server.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LifecycleContext {
+    addrs: Vec<SocketAddr>,
+}
+
+impl LifecycleContext {
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) const fn new(addrs: Vec<SocketAddr>) -> Self {
+        Self { addrs }
+    }
+
+    /// The local addresses the server listens on, in the order they were configured.
+    ///
+    /// Empty on wasm, where the platform owns the listener.
+    #[must_use]
+    pub fn addrs(&self) -> &[SocketAddr] {
+        &self.addrs
+    }
+
+    /// The first listening address, if any.
+    #[must_use]
+    pub fn addr(&self) -> Option<SocketAddr> {
+        self.addrs.first().copied()
+    }
+}
+
+/// Startup and shutdown hooks, in registration order.
+#[derive(Default)]
+pub(crate) struct Hooks {
+    startup: Vec<Hook>,
+    shutdown: Vec<Hook>,
+}
+
+impl Hooks {
+    pub(crate) const fn new() -> Self {
+        Self {
+            startup: Vec::new(),
+            shutdown: Vec::new(),
+        }
+    }
+
+    pub(crate) fn push_startup<F, Fut, E>(&mut self, hook: F)
+    where
+        F: FnOnce(LifecycleContext) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<(), E>> + Send + 'static,
+        E: Display,
+    {
+        self.startup.push(boxed(hook));
+    }
+
+    pub(crate) fn push_shutdown<F, Fut, E>(&mut self, hook: F)
+    where
+        F: FnOnce(LifecycleContext) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<(), E>> + Send + 'static,
+        E: Display,
+    {
+        self.shutdown.push(boxed(hook));
+    }
+
+    /// Append `other`'s hooks after these.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn extend(&mut self, other: Self) {
+        self.startup.extend(other.startup);
+        self.shutdown.extend(other.shutdown);
+    }
+
+    /// Run the startup hooks in order, stopping at the first one that fails.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the failing hook, after logging it.
+    pub(crate) async fn startup(&mut self, context: &LifecycleContext) -> Result<(), String> {
+        let count = self.startup.len();
+        for (index, hook) in mem::take(&mut self.startup).into_iter().enumerate() {
+            if let Err(error) = hook(context.clone()).await {
+                error!(
+                    "Startup hook {}/{count} failed, aborting launch: {error}",
+                    index + 1
+                );
+                return Err(error);
+            }
+        }
+        Ok(())
+    }
+
+    /// Run the shutdown hooks in order. Failures are logged and do not stop later hooks.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) async fn shutdown(self, context: &LifecycleContext) {
+        let count = self.shutdown.len();
+        for (index, hook) in self.shutdown.into_iter().enumerate() {
+            if let Err(error) = hook(context.clone()).await {
+                tracing::warn!("Shutdown hook {}/{count} failed: {error}", index + 1);
+            }
+        }
+    }
+}
+
+fn boxed<F, Fut, E>(hook: F) -> Hook
+where
+    F: FnOnce(LifecycleContext) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<(), E>> + Send + 'static,
+    E: Display,
+{
+    Box::new(move |context| {
+        Box::pin(async move { hook(context).await.map_err(|error| error.to_string()) })
+    })
+}
+
+static REGISTERED: Mutex<Hooks> = Mutex::new(Hooks::new());
+
+/// Run `hook` once the runtime started by `#[skyzen::main]` is ready to serve.
+///
+/// Hooks run in registration order; an error aborts the launch, see the [module docs](self).
+pub fn on_startup<F, Fut, E>(hook: F)
+where
+    F: FnOnce(LifecycleContext) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<(), E>> + Send + 'static,
+    E: Display,
+{
+    registered().push_startup(hook);
+}
+
+/// Run `hook` once the runtime started by `#[skyzen::main]` has stopped serving.
+///
+/// Hooks run in registration order; errors are logged, see the [module docs](self).
+pub fn on_shutdown<F, Fut, E>(hook: F)
+where
+    F: FnOnce(LifecycleContext) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<(), E>> + Send + 'static,
+    E: Display,
+{
+    registered().push_shutdown(hook);
+}
+
+/// Take the hooks registered through [`on_startup`] and [`on_shutdown`] so far.
+pub(crate) fn take_registered() -> Hooks {
+    mem::take(&mut *registered())
+}
+
+fn registered() -> std::sync::MutexGuard<'static, Hooks> {
+    REGISTERED.lock().unwrap_or_else(PoisonError::into_inner)
+}
@@ -1,9 +1,22 @@
 //! Runtime utilities used by `#[skyzen::main]`.
 
-/// Native (async-executor backed) runtime utilities.
+/// Native runtime utilities, backed by `async-executor` or, with `tokio-runtime`, by Tokio.
 #[cfg(all(not(target_arch = "wasm32"), feature = "rt"))]
 pub mod native;
 
+#[cfg(all(not(target_arch = "wasm32"), feature = "rt"))]
+mod config;
+
+#[cfg(all(not(target_arch = "wasm32"), feature = "rt"))]
+mod proxy_protocol;
+
+/// Startup and shutdown hooks shared by the native and wasm runtimes.
+#[cfg(any(target_arch = "wasm32", feature = "rt"))]
+pub mod lifecycle;
+
+#[cfg(all(not(target_arch = "wasm32"), feature = "tls"))]
+mod tls;
+
 /// WebWorker/WASM runtime utilities.
 #[cfg(target_arch = "wasm32")]
 pub mod wasm;
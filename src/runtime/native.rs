@@ -1,18 +1,39 @@
 use std::{
     future::Future,
     net::{IpAddr, Ipv4Addr, SocketAddr},
-    pin::Pin,
+    pin::{pin, Pin},
     ptr,
-    sync::Arc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, OnceLock, PoisonError,
+    },
     task::{Context, Poll},
+    time::Duration,
 };
 
-use crate::Endpoint;
-use async_channel::{bounded, Receiver};
+pub use super::config::{Config, ConfigError};
+use super::{
+    lifecycle::{self, Hooks, LifecycleContext},
+    proxy_protocol,
+};
+use crate::{
+    extract::{PeerAddr, ProxiedAddr},
+    middleware::access_log::AccessLogged,
+    Endpoint, HttpConfig,
+};
+use async_channel::{bounded, Receiver, Sender};
+#[cfg(not(feature = "tokio-runtime"))]
 use async_executor::Executor as AsyncExecutor;
+use async_lock::{Semaphore, SemaphoreGuardArc};
 use async_net::TcpListener;
+#[cfg(feature = "tokio-runtime")]
+use executor_core::tokio::TokioGlobal;
 use executor_core::{try_init_global_executor, AnyExecutor, Executor as CoreExecutor, Task};
-use futures_util::{future::FutureExt, stream::MapOk, StreamExt, TryStreamExt};
+use futures_util::{
+    future::{Either, FutureExt},
+    stream::MapOk,
+    StreamExt, TryStreamExt,
+};
 use http_body_util::{BodyDataStream, StreamBody};
 use http_kit::{
     error::BoxHttpError,
@@ -24,6 +45,7 @@ use hyper::{
     server::conn::{http1, http2},
     service::Service,
 };
+use skyzen_hyper::{configure_http1, configure_http2};
 use tracing::{debug, error, info, warn};
 use tracing_log::log::LevelFilter as LogLevelFilter;
 use tracing_subscriber::EnvFilter;
@@ -55,7 +77,7 @@ where
     }
 }
 
-struct ConnectionWrapper<C>(C);
+pub(crate) struct ConnectionWrapper<C>(pub(crate) C);
 
 impl<C: Unpin + AsyncRead> hyper::rt::Read for ConnectionWrapper<C> {
     fn poll_read(
@@ -171,6 +193,13 @@ impl<C: AsyncWrite + Unpin> AsyncWrite for Prefixed<C> {
 /// # Panics
 /// If the subscriber fails to initialize.
 pub fn init_logging() {
+    init_logging_with(&Config::new());
+}
+
+/// [`init_logging`] with the log filter of `config`, falling back to `RUST_LOG` and then `info`.
+/// # Panics
+/// If the subscriber fails to initialize.
+pub fn init_logging_with(config: &Config) {
     use std::sync::Once;
 
     static INIT: Once = Once::new();
@@ -183,8 +212,14 @@ pub fn init_logging() {
             .with_max_level(LogLevelFilter::Trace)
             .init();
 
-        let env_filter = EnvFilter::try_from_default_env()
-            .or_else(|_| EnvFilter::try_new("info"))
+        let env_filter = config
+            .log_filter
+            .as_deref()
+            .map_or_else(
+                || EnvFilter::try_from_default_env().ok(),
+                |filter| EnvFilter::try_new(filter).ok(),
+            )
+            .map_or_else(|| EnvFilter::try_new("info"), Ok)
             .expect("failed to build env filter");
 
         if tracing::dispatcher::has_been_set() {
@@ -213,86 +248,111 @@ pub fn init_logging() {
     });
 }
 
-/// Apply CLI overrides such as `--addr` or `--port` to configure the listener.
-pub fn apply_cli_overrides(args: impl IntoIterator<Item = String>) {
-    let mut args = args.into_iter();
-    let _ = args.next(); // binary name
-    let mut listen = None;
-    let mut host = None;
-    let mut port = None;
-
-    while let Some(arg) = args.next() {
-        if let Some(value) = arg.strip_prefix("--listen=") {
-            listen = Some(value.to_owned());
-        } else if let Some(value) = arg.strip_prefix("--addr=") {
-            listen = Some(value.to_owned());
-        } else if let Some(value) = arg.strip_prefix("--host=") {
-            host = Some(value.to_owned());
-        } else if let Some(value) = arg.strip_prefix("--port=") {
-            port = Some(value.to_owned());
-        } else {
-            match arg.as_str() {
-                "--listen" | "--addr" => {
-                    if let Some(value) = args.next() {
-                        listen = Some(value);
-                    }
-                }
-                "--host" => {
-                    if let Some(value) = args.next() {
-                        host = Some(value);
-                    }
-                }
-                "--port" | "-p" => {
-                    if let Some(value) = args.next() {
-                        port = Some(value);
-                    }
-                }
-                _ => {}
-            }
-        }
+/// How long the server waits for in-flight connections after a shutdown signal by default.
+pub(super) const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long a new connection may take to get through the steps before HTTP by default.
+const DEFAULT_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Address `#[skyzen::main]` and [`ServerBuilder::from_env`] listen on when `SKYZEN_ADDRESS` is
+/// not set.
+pub const DEFAULT_ADDRESS: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 8080);
+
+/// Log the settings skipped by [`Config::load_lenient`], once logging is set up.
+#[doc(hidden)]
+pub fn warn_skipped_settings(skipped: &[ConfigError]) {
+    for error in skipped {
+        warn!("Ignoring {error}");
     }
+}
 
-    if let Some(addr) = listen {
-        match addr.parse::<SocketAddr>() {
-            Ok(socket) => {
-                unsafe {
-                    std::env::set_var("SKYZEN_ADDRESS", socket.to_string());
-                }
-                info!("Configured listener address via CLI: {socket}");
-            }
-            Err(error) => warn!("Ignoring invalid --listen address `{addr}`: {error}"),
+/// Settings recorded by [`apply_cli_overrides`] for the next [`launch`].
+static CLI_CONFIG: Mutex<Option<Config>> = Mutex::new(None);
+
+/// Apply CLI overrides such as `--addr`, `--port`, `--tls-cert`, `--shutdown-timeout` or
+/// `--max-connections` to the server started by the next [`launch`].
+///
+/// The flags, the environment and any `--config` file are read with [`Config::load_lenient`]:
+/// invalid values are logged and ignored, as is a configuration file that cannot be read.
+#[deprecated(
+    note = "load a `Config` and pass it to `launch_with_config` or `ServerBuilder::from_config`"
+)]
+pub fn apply_cli_overrides(args: impl IntoIterator<Item = String>) {
+    *CLI_CONFIG.lock().unwrap_or_else(PoisonError::into_inner) = Some(load_lenient(args));
+}
+
+/// [`Config::load_lenient`], logging skipped settings and falling back to the defaults when
+/// the configuration file cannot be read.
+fn load_lenient(args: impl IntoIterator<Item = String>) -> Config {
+    match Config::load_lenient(args) {
+        Ok((config, skipped)) => {
+            warn_skipped_settings(&skipped);
+            config
+        }
+        Err(error) => {
+            warn!("Ignoring configuration: {error}");
+            Config::new()
         }
-        return;
     }
+}
 
-    if host.is_none() && port.is_none() {
-        return;
-    }
+/// Raw values of the flags understood by [`Config::load`].
+#[derive(Debug, Default)]
+pub(super) struct CliOverrides {
+    pub(super) config: Option<String>,
+    pub(super) listen: Vec<String>,
+    pub(super) host: Option<String>,
+    pub(super) port: Option<String>,
+    pub(super) shutdown_timeout: Option<String>,
+    pub(super) max_connections: Option<String>,
+    pub(super) tls_cert: Option<String>,
+    pub(super) tls_key: Option<String>,
+    pub(super) log_filter: Option<String>,
+}
 
-    let mut candidate = server_addr();
-    if let Some(host) = host {
-        match host.parse::<IpAddr>() {
-            Ok(ip) => candidate.set_ip(ip),
-            Err(error) => {
-                warn!("Ignoring invalid --host `{host}`: {error}");
-                return;
+impl CliOverrides {
+    /// Parse `--flag value` and `--flag=value` pairs, skipping the binary name and unknown flags.
+    pub(super) fn parse(args: impl IntoIterator<Item = String>) -> Self {
+        let mut overrides = Self::default();
+        let mut args = args.into_iter();
+        let _ = args.next(); // binary name
+
+        while let Some(arg) = args.next() {
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => {
+                    (flag.to_owned(), Some(value.to_owned()))
+                }
+                _ => (arg, None),
+            };
+            if matches!(flag.as_str(), "--listen" | "--addr") {
+                overrides
+                    .listen
+                    .extend(inline_value.or_else(|| args.next()));
+                continue;
             }
-        }
-    }
-    if let Some(port) = port {
-        match port.parse::<u16>() {
-            Ok(value) => candidate.set_port(value),
-            Err(error) => {
-                warn!("Ignoring invalid --port `{port}`: {error}");
-                return;
+            let Some(slot) = overrides.slot(&flag) else {
+                continue;
+            };
+            if let Some(value) = inline_value.or_else(|| args.next()) {
+                *slot = Some(value);
             }
         }
+        overrides
     }
 
-    unsafe {
-        std::env::set_var("SKYZEN_ADDRESS", candidate.to_string());
+    fn slot(&mut self, flag: &str) -> Option<&mut Option<String>> {
+        match flag {
+            "--config" => Some(&mut self.config),
+            "--host" => Some(&mut self.host),
+            "--port" | "-p" => Some(&mut self.port),
+            "--shutdown-timeout" => Some(&mut self.shutdown_timeout),
+            "--max-connections" => Some(&mut self.max_connections),
+            "--tls-cert" => Some(&mut self.tls_cert),
+            "--tls-key" => Some(&mut self.tls_key),
+            "--log-filter" => Some(&mut self.log_filter),
+            _ => None,
+        }
     }
-    info!("Configured listener address via CLI: {candidate}");
 }
 
 fn shutdown_signal() -> Receiver<()> {
@@ -307,122 +367,1139 @@ fn shutdown_signal() -> Receiver<()> {
 
 /// Build the executor and serve the provided endpoint over Hyper.
 ///
+/// Listener addresses, TLS and the shutdown timeout come from the environment (see
+/// [`ServerBuilder::from_env`]), and the server drains on Ctrl+C. Hooks registered with
+/// [`on_startup`](lifecycle::on_startup) and [`on_shutdown`](lifecycle::on_shutdown), for example
+/// from `factory`, run once the listeners are bound and once the server has drained.
+///
+/// By default the app runs on an `async-executor` driven by `async_io::block_on`. With the
+/// `tokio-runtime` feature it runs on a multi-threaded Tokio runtime instead, and connection
+/// tasks, the global `executor-core` executor and the executor handed to handlers all spawn onto
+/// it, so libraries that need a Tokio reactor (sqlx, reqwest, tonic clients) work in handlers.
+///
 /// # Panics
 ///
-/// Panics if the global executor fails to initialize.
+/// Panics if the global executor or, with `tokio-runtime`, the Tokio runtime fails to initialize.
 pub fn launch<Fut, E>(factory: impl FnOnce() -> Fut)
 where
     Fut: Future<Output = E> + Send + 'static,
     E: Endpoint + Clone + Send + Sync + 'static,
 {
-    let executor = Arc::new(AsyncExecutor::new());
-    if try_init_global_executor(executor.clone()).is_err() {
-        debug!("Global executor already initialized; reusing existing instance");
+    launch_with(RuntimeOptions::new(), factory);
+}
+
+/// Thread configuration for [`launch_with`], set by `#[skyzen::main(worker_threads = ..,
+/// blocking_threads = ..)]`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RuntimeOptions {
+    worker_threads: Option<usize>,
+    blocking_threads: Option<usize>,
+}
+
+impl RuntimeOptions {
+    /// Options matching [`launch`]: a single executor thread and the default blocking pool.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            worker_threads: None,
+            blocking_threads: None,
+        }
     }
 
-    let executor_clone = Arc::clone(&executor);
-    async_io::block_on(executor.run(async move {
-        tracing::info!("Skyzen application starting up");
+    /// Run the executor on `threads` OS threads; `0` means one per CPU.
+    #[must_use]
+    pub const fn worker_threads(mut self, threads: usize) -> Self {
+        self.worker_threads = Some(threads);
+        self
+    }
 
-        let endpoint = factory().await;
-        match run_server(executor_clone, endpoint).await {
-            Ok(()) => info!("Skyzen server shut down gracefully"),
-            Err(error) => error!("Skyzen server terminated: {error}"),
+    /// Cap the pool used for blocking work (file system access and `spawn_blocking`).
+    #[must_use]
+    pub const fn blocking_threads(mut self, threads: usize) -> Self {
+        self.blocking_threads = Some(threads);
+        self
+    }
+
+    /// Number of executor threads to start, resolving `0` to the number of CPUs.
+    fn resolved_worker_threads(&self) -> Option<usize> {
+        self.worker_threads.map(|threads| match threads {
+            0 => std::thread::available_parallelism().map_or(1, std::num::NonZeroUsize::get),
+            threads => threads,
+        })
+    }
+}
+
+/// [`launch`] with explicit thread configuration.
+///
+/// Without `tokio-runtime`, `worker_threads` threads all run the same `async-executor`, the
+/// calling thread being one of them, and `blocking_threads` sets `BLOCKING_MAX_THREADS` for the
+/// `blocking` pool. With `tokio-runtime` they configure the Tokio runtime builder.
+///
+/// # Panics
+///
+/// Panics if the global executor, a worker thread or, with `tokio-runtime`, the Tokio runtime
+/// fails to initialize.
+pub fn launch_with<Fut, E>(options: RuntimeOptions, factory: impl FnOnce() -> Fut)
+where
+    Fut: Future<Output = E> + Send + 'static,
+    E: Endpoint + Clone + Send + Sync + 'static,
+{
+    launch_inner(options, None, factory);
+}
+
+/// [`launch_with`] serving with the settings of `config` instead of the environment, as
+/// `#[skyzen::main]` does with the [loaded](Config::load) configuration.
+///
+/// # Panics
+///
+/// Panics if the global executor, a worker thread or, with `tokio-runtime`, the Tokio runtime
+/// fails to initialize.
+pub fn launch_with_config<Fut, E>(
+    options: RuntimeOptions,
+    config: Config,
+    factory: impl FnOnce() -> Fut,
+) where
+    Fut: Future<Output = E> + Send + 'static,
+    E: Endpoint + Clone + Send + Sync + 'static,
+{
+    launch_inner(options, Some(config), factory);
+}
+
+fn launch_inner<Fut, E>(
+    options: RuntimeOptions,
+    config: Option<Config>,
+    factory: impl FnOnce() -> Fut,
+) where
+    Fut: Future<Output = E> + Send + 'static,
+    E: Endpoint + Clone + Send + Sync + 'static,
+{
+    #[cfg(not(feature = "tokio-runtime"))]
+    {
+        if let Some(threads) = options.blocking_threads {
+            // SAFETY: the variable is set before the executor and the `blocking` pool that reads
+            // it start, while skyzen has not spawned any thread.
+            unsafe {
+                std::env::set_var("BLOCKING_MAX_THREADS", threads.to_string());
+            }
         }
-    }));
+
+        let executor = Arc::new(AsyncExecutor::new());
+        if try_init_global_executor(executor.clone()).is_err() {
+            debug!("Global executor already initialized; reusing existing instance");
+        }
+        set_global_executor(Arc::clone(&executor));
+
+        let threads = options.resolved_worker_threads().unwrap_or(1);
+        let executor_clone = Arc::clone(&executor);
+        block_on_workers(&executor, threads, run_app(factory, executor_clone, config));
+    }
+
+    #[cfg(feature = "tokio-runtime")]
+    {
+        let runtime = tokio_runtime(options).expect("failed to build the Tokio runtime");
+        if try_init_global_executor(TokioGlobal).is_err() {
+            debug!("Global executor already initialized; reusing existing instance");
+        }
+        set_global_executor(Arc::new(TokioGlobal));
+
+        runtime.block_on(run_app(factory, Arc::new(TokioGlobal), config));
+    }
 }
 
-async fn run_server<Exec, E>(executor: Arc<Exec>, endpoint: E) -> std::io::Result<()>
+static GLOBAL_EXECUTOR: OnceLock<Arc<AnyExecutor>> = OnceLock::new();
+
+/// Register the executor used when a request carries none.
+///
+/// This is the case for WebSocket upgrades on a custom backend that does not insert an
+/// `Arc<AnyExecutor>` into the request extensions.
+///
+/// [`launch`] and [`launch_with`] register the executor they run on. Returns `false`, keeping the
+/// existing executor, if one has been registered already.
+pub fn set_global_executor<Exec>(executor: Arc<Exec>) -> bool
 where
     Exec: CoreExecutor + 'static,
+{
+    GLOBAL_EXECUTOR
+        .set(Arc::new(AnyExecutor::new(executor)))
+        .is_ok()
+}
+
+/// The executor registered with [`set_global_executor`], if any.
+#[must_use]
+pub fn global_executor() -> Option<Arc<AnyExecutor>> {
+    GLOBAL_EXECUTOR.get().cloned()
+}
+
+/// Drive `future` to completion on the calling thread while `threads - 1` extra threads run
+/// `executor` alongside it.
+#[cfg(not(feature = "tokio-runtime"))]
+fn block_on_workers<T>(
+    executor: &Arc<AsyncExecutor<'static>>,
+    threads: usize,
+    future: impl Future<Output = T>,
+) -> T {
+    let (stop_tx, stop_rx) = bounded::<()>(1);
+    let workers = (1..threads)
+        .map(|index| {
+            let executor = Arc::clone(executor);
+            let stop = stop_rx.clone();
+            std::thread::Builder::new()
+                .name(format!("skyzen-worker-{index}"))
+                .spawn(move || async_io::block_on(executor.run(stop.recv())))
+                .expect("failed to spawn an executor thread")
+        })
+        .collect::<Vec<_>>();
+
+    let output = async_io::block_on(executor.run(future));
+    drop(stop_tx);
+    for worker in workers {
+        let _ = worker.join();
+    }
+    output
+}
+
+async fn run_app<Fut, E, Exec>(
+    factory: impl FnOnce() -> Fut,
+    executor: Arc<Exec>,
+    config: Option<Config>,
+) where
+    Fut: Future<Output = E>,
     E: Endpoint + Clone + Send + Sync + 'static,
+    Exec: CoreExecutor + 'static,
 {
-    const HTTP2_PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+    tracing::info!("Skyzen application starting up");
+
+    let endpoint = factory().await;
+    let config = config.or_else(|| {
+        CLI_CONFIG
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take()
+    });
+    let mut server = config
+        .map_or_else(ServerBuilder::from_env, ServerBuilder::from_config)
+        .executor(executor)
+        .shutdown_on_ctrl_c();
+    server.hooks.extend(lifecycle::take_registered());
+    match server.serve(endpoint).await {
+        Ok(()) => info!("Skyzen server shut down gracefully"),
+        Err(error) => error!("Skyzen server terminated: {error}"),
+    }
+}
+
+/// The multi-threaded runtime [`launch`] uses under the `tokio-runtime` feature.
+#[cfg(feature = "tokio-runtime")]
+fn tokio_runtime(options: RuntimeOptions) -> std::io::Result<tokio::runtime::Runtime> {
+    let mut builder = tokio::runtime::Builder::new_multi_thread();
+    if let Some(threads) = options.resolved_worker_threads() {
+        builder.worker_threads(threads);
+    }
+    if let Some(threads) = options.blocking_threads {
+        builder.max_blocking_threads(threads);
+    }
+    builder.enable_all().build()
+}
 
-    let listener = TcpListener::bind(server_addr()).await?;
-    info!(
-        "Skyzen listening on http://{}",
-        listener.local_addr().unwrap()
-    );
+/// Executor for builders that were not given one: the current Tokio runtime under the
+/// `tokio-runtime` feature, otherwise the global `executor-core` executor.
+fn default_executor() -> Arc<AnyExecutor> {
+    #[cfg(feature = "tokio-runtime")]
+    if tokio::runtime::Handle::try_current().is_ok() {
+        return Arc::new(AnyExecutor::new(TokioGlobal));
+    }
+    Arc::new(AnyExecutor::new(executor_core::DefaultExecutor))
+}
 
-    let hyper_executor = HyperExecutor(Arc::clone(&executor));
-    let shared_executor: Arc<AnyExecutor> = Arc::new(AnyExecutor::new(Arc::clone(&executor)));
+/// HTTP versions offered on accepted connections.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum HttpProtocols {
+    /// Detect HTTP/2 by its connection preface (or ALPN under TLS), otherwise use HTTP/1.
+    #[default]
+    Auto,
+    Http1Only,
+    Http2Only,
+}
 
-    let mut incoming = listener.incoming();
-    let shutdown_rx = shutdown_signal();
-    let shutdown = shutdown_rx.recv().fuse();
-    futures_util::pin_mut!(shutdown);
+impl HttpProtocols {
+    /// ALPN identifiers to advertise during the TLS handshake.
+    #[cfg(feature = "tls")]
+    const fn alpn(self) -> &'static [&'static [u8]] {
+        match self {
+            Self::Auto => &[super::tls::ALPN_H2, super::tls::ALPN_HTTP11],
+            Self::Http1Only => &[super::tls::ALPN_HTTP11],
+            Self::Http2Only => &[super::tls::ALPN_H2],
+        }
+    }
+}
 
-    loop {
-        futures_util::select! {
-            _ = shutdown => {
-                info!("Ctrl+C received, stopping accept loop");
-                break;
+/// What stops a [`ServerBuilder`] from accepting new connections.
+enum ShutdownTrigger {
+    Never,
+    CtrlC,
+    Future(BoxFuture<()>),
+}
+
+/// Programmatic configuration for the native Hyper server.
+///
+/// Use this instead of `#[skyzen::main]` to embed skyzen into an existing binary: pick the
+/// listener addresses and executor yourself, and decide when the server should stop.
+///
+/// ```no_run
+/// # async fn run(router: skyzen::routing::Router) -> std::io::Result<()> {
+/// use skyzen::runtime::native::ServerBuilder;
+///
+/// let (stop_tx, stop_rx) = async_channel::bounded::<()>(1);
+/// ServerBuilder::new()
+///     .bind("127.0.0.1:8080".parse().unwrap())
+///     .graceful_shutdown(async move {
+///         let _ = stop_rx.recv().await;
+///     })
+///     .serve(router)
+///     .await
+/// # }
+/// ```
+pub struct ServerBuilder {
+    addrs: Vec<SocketAddr>,
+    default_addr: Option<SocketAddr>,
+    on_bound: Option<Box<dyn FnMut(SocketAddr) + Send>>,
+    hooks: Hooks,
+    executor: Option<Arc<AnyExecutor>>,
+    protocols: HttpProtocols,
+    http: HttpConfig,
+    shutdown: ShutdownTrigger,
+    shutdown_timeout: Duration,
+    handshake_timeout: Duration,
+    max_connections: usize,
+    proxy_protocol: bool,
+    #[cfg(feature = "tls")]
+    tls_cert: Option<std::path::PathBuf>,
+    #[cfg(feature = "tls")]
+    tls_key: Option<std::path::PathBuf>,
+}
+
+impl std::fmt::Debug for ServerBuilder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ServerBuilder")
+            .field("addrs", &self.addrs)
+            .field("default_addr", &self.default_addr)
+            .field("protocols", &self.protocols)
+            .field("http", &self.http)
+            .field("shutdown_timeout", &self.shutdown_timeout)
+            .field("handshake_timeout", &self.handshake_timeout)
+            .field("max_connections", &self.max_connections)
+            .field("proxy_protocol", &self.proxy_protocol)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for ServerBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ServerBuilder {
+    /// Create a builder with no listener addresses and no shutdown trigger.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            addrs: Vec::new(),
+            default_addr: None,
+            on_bound: None,
+            hooks: Hooks::new(),
+            executor: None,
+            protocols: HttpProtocols::Auto,
+            http: HttpConfig::new(),
+            shutdown: ShutdownTrigger::Never,
+            shutdown_timeout: DEFAULT_SHUTDOWN_TIMEOUT,
+            handshake_timeout: DEFAULT_HANDSHAKE_TIMEOUT,
+            max_connections: 0,
+            proxy_protocol: false,
+            #[cfg(feature = "tls")]
+            tls_cert: None,
+            #[cfg(feature = "tls")]
+            tls_key: None,
+        }
+    }
+
+    /// Create a builder configured from the environment variables listed on [`Config`],
+    /// including a `SKYZEN_CONFIG` file.
+    ///
+    /// Settings are read with [`Config::load_lenient`], so invalid values are logged and ignored.
+    /// Without `SKYZEN_ADDRESS`, the server listens on [`DEFAULT_ADDRESS`] unless another
+    /// [`default_addr`](Self::default_addr) is set.
+    #[must_use]
+    pub fn from_env() -> Self {
+        Self::from_config(load_lenient(std::iter::empty()))
+    }
+
+    /// Create a builder with the settings of `config`, such as one [loaded](Config::load) from
+    /// a file, the environment and CLI flags.
+    ///
+    /// Without listen addresses, the server listens on [`DEFAULT_ADDRESS`] unless another
+    /// [`default_addr`](Self::default_addr) is set.
+    #[must_use]
+    pub fn from_config(config: Config) -> Self {
+        let mut builder = Self::new()
+            .shutdown_timeout(config.shutdown_timeout)
+            .max_connections(config.max_connections)
+            .proxy_protocol(config.proxy_protocol);
+        builder.addrs = config.listen;
+        builder.default_addr = Some(DEFAULT_ADDRESS);
+
+        #[cfg(feature = "tls")]
+        {
+            builder.tls_cert = config.tls_cert;
+            builder.tls_key = config.tls_key;
+        }
+        #[cfg(not(feature = "tls"))]
+        if config.tls_cert.is_some() {
+            warn!("A TLS certificate is configured but skyzen was built without the `tls` feature; serving plain HTTP");
+        }
+        builder
+    }
+
+    /// Listen on `addr`. Call repeatedly to serve the same endpoint on several addresses.
+    #[must_use]
+    pub fn bind(mut self, addr: SocketAddr) -> Self {
+        self.addrs.push(addr);
+        self
+    }
+
+    /// Listen on `addr` if no address has been bound, for example through `SKYZEN_ADDRESS`.
+    ///
+    /// Port `0` picks a random free port, which is logged as a warning; use
+    /// [`on_bound`](Self::on_bound) to learn it.
+    #[must_use]
+    pub const fn default_addr(mut self, addr: SocketAddr) -> Self {
+        self.default_addr = Some(addr);
+        self
+    }
+
+    /// Call `callback` with the local address of each listener once it is bound, before any
+    /// connection is accepted.
+    ///
+    /// This reports the actual port when binding port `0`, for registering the server with
+    /// service discovery or connecting to it from tests.
+    #[must_use]
+    pub fn on_bound(mut self, callback: impl FnMut(SocketAddr) + Send + 'static) -> Self {
+        self.on_bound = Some(Box::new(callback));
+        self
+    }
+
+    /// Run `hook` after every listener is bound and before any connection is accepted.
+    ///
+    /// Startup hooks run in registration order with the bound addresses. If one fails, the error
+    /// is logged and [`serve`](Self::serve) returns it without serving; later startup hooks and
+    /// the shutdown hooks do not run.
+    #[must_use]
+    pub fn on_startup<F, Fut, Err>(mut self, hook: F) -> Self
+    where
+        F: FnOnce(LifecycleContext) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<(), Err>> + Send + 'static,
+        Err: std::fmt::Display,
+    {
+        self.hooks.push_startup(hook);
+        self
+    }
+
+    /// Run `hook` after the server stops accepting and in-flight connections are drained.
+    ///
+    /// Shutdown hooks run in registration order; failures are logged and do not stop later ones.
+    #[must_use]
+    pub fn on_shutdown<F, Fut, Err>(mut self, hook: F) -> Self
+    where
+        F: FnOnce(LifecycleContext) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<(), Err>> + Send + 'static,
+        Err: std::fmt::Display,
+    {
+        self.hooks.push_shutdown(hook);
+        self
+    }
+
+    /// Spawn connection tasks on `executor` instead of the default one.
+    ///
+    /// The default is the global `executor-core` executor or, with the `tokio-runtime` feature
+    /// and when serving from inside a Tokio runtime, `tokio::spawn`. Handlers receive the same
+    /// executor through the request extensions.
+    #[must_use]
+    pub fn executor<Exec>(mut self, executor: Arc<Exec>) -> Self
+    where
+        Exec: CoreExecutor + 'static,
+    {
+        self.executor = Some(Arc::new(AnyExecutor::new(executor)));
+        self
+    }
+
+    /// Only speak HTTP/1.x, skipping HTTP/2 detection.
+    #[must_use]
+    pub const fn http1_only(mut self) -> Self {
+        self.protocols = HttpProtocols::Http1Only;
+        self
+    }
+
+    /// Only speak HTTP/2 (prior knowledge on plain TCP, `h2` via ALPN under TLS).
+    #[must_use]
+    pub const fn http2_only(mut self) -> Self {
+        self.protocols = HttpProtocols::Http2Only;
+        self
+    }
+
+    /// Tune HTTP/1 and HTTP/2 connection options. Unset fields keep hyper's defaults.
+    #[must_use]
+    pub const fn http_config(mut self, config: HttpConfig) -> Self {
+        self.http = config;
+        self
+    }
+
+    /// Stop accepting connections and start draining once `signal` completes.
+    #[must_use]
+    pub fn graceful_shutdown(mut self, signal: impl Future<Output = ()> + Send + 'static) -> Self {
+        self.shutdown = ShutdownTrigger::Future(Box::pin(signal));
+        self
+    }
+
+    /// Shut down on Ctrl+C. A second Ctrl+C aborts connections that are still draining.
+    #[must_use]
+    pub fn shutdown_on_ctrl_c(mut self) -> Self {
+        self.shutdown = ShutdownTrigger::CtrlC;
+        self
+    }
+
+    /// How long to wait for in-flight connections after shutdown begins (30 seconds by default).
+    #[must_use]
+    pub const fn shutdown_timeout(mut self, timeout: Duration) -> Self {
+        self.shutdown_timeout = timeout;
+        self
+    }
+
+    /// How long a new connection may take to send its PROXY protocol header, complete its TLS
+    /// handshake and send its first bytes (10 seconds by default for each step).
+    ///
+    /// Connections that take longer are closed, so idle sockets cannot hold on to a
+    /// [`max_connections`](Self::max_connections) slot.
+    #[must_use]
+    pub const fn handshake_timeout(mut self, timeout: Duration) -> Self {
+        self.handshake_timeout = timeout;
+        self
+    }
+
+    /// Serve at most `limit` connections at once across all listeners; `0` (the default) means
+    /// unlimited.
+    ///
+    /// Once the limit is reached the server stops accepting until a connection finishes, leaving
+    /// new clients queued in the listen backlog instead of dropping them.
+    #[must_use]
+    pub const fn max_connections(mut self, limit: usize) -> Self {
+        self.max_connections = limit;
+        self
+    }
+
+    /// Expect every connection to start with a PROXY protocol (v1 or v2) header.
+    ///
+    /// Enable this only behind a load balancer that always sends the header: the client address
+    /// it reports is exposed as [`ProxiedAddr`] and preferred by
+    /// [`ClientIp`](crate::extract::ClientIp). Connections with a missing or malformed header are
+    /// closed.
+    #[must_use]
+    pub const fn proxy_protocol(mut self, enabled: bool) -> Self {
+        self.proxy_protocol = enabled;
+        self
+    }
+
+    /// Terminate TLS using a PEM certificate chain and private key.
+    #[cfg(feature = "tls")]
+    #[must_use]
+    pub fn tls(
+        mut self,
+        cert_path: impl Into<std::path::PathBuf>,
+        key_path: impl Into<std::path::PathBuf>,
+    ) -> Self {
+        self.tls_cert = Some(cert_path.into());
+        self.tls_key = Some(key_path.into());
+        self
+    }
+
+    /// Bind every configured address and serve `endpoint` until the shutdown trigger fires.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no address was configured, TLS material cannot be loaded, any
+    /// address fails to bind, or a [startup hook](Self::on_startup) fails.
+    pub async fn serve<E>(mut self, endpoint: E) -> std::io::Result<()>
+    where
+        E: Endpoint + Clone + Send + Sync + 'static,
+    {
+        if self.addrs.is_empty() {
+            self.addrs.extend(self.default_addr);
+        }
+        if self.addrs.is_empty() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "no listener address configured; call `ServerBuilder::bind`",
+            ));
+        }
+        let executor = self.executor.unwrap_or_else(default_executor);
+
+        #[cfg(feature = "tls")]
+        let tls = match (&self.tls_cert, &self.tls_key) {
+            (None, None) => None,
+            (Some(cert), Some(key)) => {
+                Some(super::tls::load_acceptor(cert, key, self.protocols.alpn())?)
             }
-            connection = incoming.next().fuse() => {
-                match connection {
-                    Some(Ok(stream)) => {
-                        if let Ok(peer) = stream.peer_addr() {
-                            debug!("Accepted connection from {peer}");
-                        }
-                        let endpoint = endpoint.clone();
-                        let (stream, is_h2) = match sniff_protocol(stream, HTTP2_PREFACE).await {
-                            Ok(result) => result,
+            (Some(_), None) | (None, Some(_)) => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "TLS needs both a certificate and a private key (SKYZEN_TLS_CERT / SKYZEN_TLS_KEY)",
+                ));
+            }
+        };
+        #[cfg(feature = "tls")]
+        let scheme = if tls.is_some() { "https" } else { "http" };
+        #[cfg(not(feature = "tls"))]
+        let scheme = "http";
+
+        let listeners = bind_all(&self.addrs)
+            .await?
+            .into_iter()
+            .map(|tcp| BoundListener {
+                tcp,
+                #[cfg(feature = "tls")]
+                tls: tls.clone(),
+            })
+            .collect::<Vec<_>>();
+        let mut bound = Vec::with_capacity(listeners.len());
+        for (listener, requested) in listeners.iter().zip(&self.addrs) {
+            let addr = listener.tcp.local_addr()?;
+            bound.push(addr);
+            info!("Skyzen listening on {scheme}://{addr}");
+            if requested.port() == 0 {
+                warn!(
+                    "Listening on random port {}; set SKYZEN_ADDRESS or --port for a fixed one",
+                    addr.port()
+                );
+            }
+            if let Some(on_bound) = &mut self.on_bound {
+                on_bound(addr);
+            }
+        }
+
+        let context = LifecycleContext::new(bound);
+        self.hooks
+            .startup(&context)
+            .await
+            .map_err(|error| std::io::Error::other(format!("startup hook failed: {error}")))?;
+
+        // Keeps the channel open so "never" really means never.
+        let (_never_tx, never_rx) = bounded(1);
+        let shutdown = match self.shutdown {
+            ShutdownTrigger::Never => never_rx,
+            ShutdownTrigger::CtrlC => shutdown_signal(),
+            ShutdownTrigger::Future(signal) => {
+                let (tx, rx) = bounded(1);
+                executor
+                    .spawn(async move {
+                        signal.await;
+                        let _ = tx.send(()).await;
+                    })
+                    .detach();
+                rx
+            }
+        };
+
+        let options = ServeOptions {
+            protocols: self.protocols,
+            http: self.http,
+            drain_timeout: self.shutdown_timeout,
+            handshake_timeout: self.handshake_timeout,
+            max_connections: self.max_connections,
+            proxy_protocol: self.proxy_protocol,
+        };
+        serve(executor, listeners, endpoint, shutdown, options).await?;
+        self.hooks.shutdown(&context).await;
+        Ok(())
+    }
+}
+
+/// A bound TCP listener, optionally terminating TLS on accepted connections.
+struct BoundListener {
+    tcp: TcpListener,
+    #[cfg(feature = "tls")]
+    tls: Option<futures_rustls::TlsAcceptor>,
+}
+
+impl BoundListener {
+    #[cfg(test)]
+    const fn plain(tcp: TcpListener) -> Self {
+        Self {
+            tcp,
+            #[cfg(feature = "tls")]
+            tls: None,
+        }
+    }
+}
+
+/// Connection handling options shared by every accept loop.
+#[derive(Debug, Clone)]
+struct ServeOptions {
+    protocols: HttpProtocols,
+    http: HttpConfig,
+    drain_timeout: Duration,
+    handshake_timeout: Duration,
+    max_connections: usize,
+    proxy_protocol: bool,
+}
+
+/// Accept connections on every listener until `shutdown` fires, then drain in-flight connections.
+///
+/// Connections still running after the drain timeout (or a second shutdown signal) are aborted.
+async fn serve<Exec, E>(
+    executor: Arc<Exec>,
+    listeners: Vec<BoundListener>,
+    endpoint: E,
+    shutdown: Receiver<()>,
+    options: ServeOptions,
+) -> std::io::Result<()>
+where
+    Exec: CoreExecutor + 'static,
+    E: Endpoint + Clone + Send + Sync + 'static,
+{
+    let (active_tx, active_rx) = bounded::<()>(1);
+    let (draining_tx, draining_rx) = bounded::<()>(1);
+    let (aborting_tx, aborting_rx) = bounded::<()>(1);
+
+    let state = ServeState {
+        executor: Arc::clone(&executor),
+        endpoint,
+        hyper_executor: HyperExecutor(Arc::clone(&executor)),
+        shared_executor: Arc::new(AnyExecutor::new(Arc::clone(&executor))),
+        active: active_tx.clone(),
+        draining: draining_rx,
+        aborting: aborting_rx,
+        protocols: options.protocols,
+        http: options.http,
+        handshake_timeout: options.handshake_timeout,
+        limit: (options.max_connections > 0)
+            .then(|| Arc::new(ConnectionLimit::new(options.max_connections))),
+        proxy_protocol: options.proxy_protocol,
+    };
+    let accept_loops = listeners
+        .into_iter()
+        .map(|listener| executor.spawn(state.clone().accept_loop(listener)))
+        .collect::<Vec<_>>();
+    drop(state);
+
+    let _ = shutdown.recv().await;
+    info!("Shutdown signal received, stopping accept loops");
+    draining_tx.close();
+    for accept_loop in accept_loops {
+        accept_loop.await;
+    }
+
+    drain_connections(active_tx, &active_rx, &shutdown, options.drain_timeout).await;
+    aborting_tx.close();
+    Ok(())
+}
+
+/// Shared state handed to every accept loop.
+struct ServeState<Exec, E> {
+    executor: Arc<Exec>,
+    endpoint: E,
+    hyper_executor: HyperExecutor<Exec>,
+    shared_executor: Arc<AnyExecutor>,
+    active: Sender<()>,
+    draining: Receiver<()>,
+    aborting: Receiver<()>,
+    protocols: HttpProtocols,
+    http: HttpConfig,
+    handshake_timeout: Duration,
+    limit: Option<Arc<ConnectionLimit>>,
+    proxy_protocol: bool,
+}
+
+impl<Exec, E: Clone> Clone for ServeState<Exec, E> {
+    fn clone(&self) -> Self {
+        Self {
+            executor: Arc::clone(&self.executor),
+            endpoint: self.endpoint.clone(),
+            hyper_executor: self.hyper_executor.clone(),
+            shared_executor: Arc::clone(&self.shared_executor),
+            active: self.active.clone(),
+            draining: self.draining.clone(),
+            aborting: self.aborting.clone(),
+            protocols: self.protocols,
+            http: self.http,
+            handshake_timeout: self.handshake_timeout,
+            limit: self.limit.clone(),
+            proxy_protocol: self.proxy_protocol,
+        }
+    }
+}
+
+impl<Exec, E> ServeState<Exec, E>
+where
+    Exec: CoreExecutor + 'static,
+    E: Endpoint + Clone + Send + Sync + 'static,
+{
+    /// Accept connections on `listener` until the server starts draining.
+    async fn accept_loop(self, listener: BoundListener) {
+        const HTTP2_PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+        let mut incoming = listener.tcp.incoming();
+        let draining = self.draining.recv().fuse();
+        futures_util::pin_mut!(draining);
+
+        loop {
+            let permit = match &self.limit {
+                Some(limit) => {
+                    let acquire = limit.acquire().fuse();
+                    futures_util::pin_mut!(acquire);
+                    futures_util::select! {
+                        _ = draining => break,
+                        permit = acquire => Some(permit),
+                    }
+                }
+                None => None,
+            };
+            let stream = futures_util::select! {
+                _ = draining => break,
+                connection = incoming.next().fuse() => match connection {
+                    Some(Ok(stream)) => stream,
+                    Some(Err(error)) => {
+                        error!("Accept error: {error}");
+                        continue;
+                    }
+                    None => break,
+                },
+            };
+
+            let mut connection = self.connection();
+            if let Ok(peer) = stream.peer_addr() {
+                debug!("Accepted connection from {peer}");
+                connection.addrs.peer = Some(PeerAddr(peer));
+            }
+            let protocols = self.protocols;
+            let handshake_timeout = self.handshake_timeout;
+            let proxy_protocol = self.proxy_protocol;
+            #[cfg(feature = "tls")]
+            let tls = listener.tls.clone();
+
+            self.executor
+                .spawn(async move {
+                    let _permit = permit;
+                    let stream = if proxy_protocol {
+                        match within(handshake_timeout, read_proxy_header(stream)).await {
+                            Ok((stream, source)) => {
+                                connection.addrs.proxied = source.map(ProxiedAddr);
+                                stream
+                            }
                             Err(error) => {
-                                error!("Failed to read connection preface: {error}");
-                                continue;
+                                error!("Closing connection with invalid PROXY protocol header: {error}");
+                                return;
+                            }
+                        }
+                    } else {
+                        Prefixed::new(stream, Vec::new())
+                    };
+                    #[cfg(feature = "tls")]
+                    if let Some(acceptor) = tls {
+                        match within(handshake_timeout, acceptor.accept(stream)).await {
+                            Ok(stream) => {
+                                let is_h2 =
+                                    stream.get_ref().1.alpn_protocol() == Some(super::tls::ALPN_H2);
+                                connection.serve(stream, is_h2).await;
                             }
-                        };
-
-                        if is_h2 {
-                            let service = IntoService::new(endpoint, shared_executor.clone());
-                            let hyper_executor = hyper_executor.clone();
-                            executor
-                                .spawn(async move {
-                                    let builder = http2::Builder::new(hyper_executor);
-                                    if let Err(error) = builder
-                                        .serve_connection(ConnectionWrapper(stream), service)
-                                        .await
-                                    {
-                                        error!("Hyper h2 connection error: {error}");
-                                    }
-                                })
-                                .detach();
-                        } else {
-                            let service = IntoService::new(endpoint, shared_executor.clone());
-                            executor
-                                .spawn(async move {
-                                    let builder = http1::Builder::new();
-                                    if let Err(error) = builder
-                                        .serve_connection(ConnectionWrapper(stream), service)
-                                        .with_upgrades()
-                                        .await
-                                    {
-                                        error!("Hyper h1 connection error: {error}");
-                                    }
-                                })
-                                .detach();
+                            Err(error) => warn!("TLS handshake failed: {error}"),
                         }
+                        return;
                     }
-                    Some(Err(error)) => error!("Accept error: {error}"),
-                    None => break,
-                }
+
+                    // Waiting for the first bytes bounds how long a silent client holds its slot,
+                    // whichever protocol is served.
+                    let sniffed = within(handshake_timeout, sniff_protocol(stream, HTTP2_PREFACE));
+                    match sniffed.await {
+                        Ok((stream, is_h2)) => {
+                            let is_h2 = match protocols {
+                                HttpProtocols::Http1Only => false,
+                                HttpProtocols::Http2Only => true,
+                                HttpProtocols::Auto => is_h2,
+                            };
+                            connection.serve(stream, is_h2).await;
+                        }
+                        Err(error) => error!("Failed to read connection preface: {error}"),
+                    }
+                })
+                .detach();
+        }
+    }
+
+    fn connection(&self) -> HyperConnection<Exec, E> {
+        HyperConnection {
+            endpoint: self.endpoint.clone(),
+            hyper_executor: self.hyper_executor.clone(),
+            shared_executor: Arc::clone(&self.shared_executor),
+            http: self.http,
+            addrs: ConnectionAddrs::default(),
+            guard: ConnectionGuard {
+                _active: self.active.clone(),
+                draining: self.draining.clone(),
+                aborting: self.aborting.clone(),
+            },
+        }
+    }
+}
+
+/// Caps how many connections are served at once.
+struct ConnectionLimit {
+    semaphore: Arc<Semaphore>,
+    max: usize,
+    warned: AtomicBool,
+}
+
+impl ConnectionLimit {
+    fn new(max: usize) -> Self {
+        Self {
+            semaphore: Arc::new(Semaphore::new(max)),
+            max,
+            warned: AtomicBool::new(false),
+        }
+    }
+
+    /// Wait for a free slot. The slot is released when the returned guard is dropped.
+    async fn acquire(&self) -> SemaphoreGuardArc {
+        if let Some(permit) = self.semaphore.try_acquire_arc() {
+            return permit;
+        }
+        if !self.warned.swap(true, Ordering::Relaxed) {
+            warn!(
+                "Connection limit of {} reached; pausing accepts until a connection closes",
+                self.max
+            );
+        }
+        self.semaphore.acquire_arc().await
+    }
+}
+
+/// Everything needed to serve one accepted connection with Hyper.
+struct HyperConnection<Exec, E> {
+    endpoint: E,
+    hyper_executor: HyperExecutor<Exec>,
+    shared_executor: Arc<AnyExecutor>,
+    http: HttpConfig,
+    addrs: ConnectionAddrs,
+    guard: ConnectionGuard,
+}
+
+/// Remote addresses of a connection, copied into the extensions of every request it carries.
+#[derive(Debug, Clone, Default)]
+struct ConnectionAddrs {
+    peer: Option<PeerAddr>,
+    proxied: Option<ProxiedAddr>,
+}
+
+impl<Exec, E> HyperConnection<Exec, E>
+where
+    Exec: CoreExecutor + 'static,
+    E: Endpoint + Clone + Send + Sync + 'static,
+{
+    async fn serve<S>(self, stream: S, is_h2: bool)
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        let service = IntoService::new(self.endpoint, self.shared_executor, self.addrs);
+        if is_h2 {
+            let mut builder = http2::Builder::new(self.hyper_executor);
+            configure_http2(&mut builder, &self.http);
+            let connection = pin!(builder.serve_connection(ConnectionWrapper(stream), service));
+            if let Err(error) =
+                drive_connection(connection, self.guard, http2::Connection::graceful_shutdown).await
+            {
+                error!("Hyper h2 connection error: {error}");
+            }
+        } else {
+            let mut builder = http1::Builder::new();
+            configure_http1(&mut builder, &self.http);
+            let connection = pin!(builder
+                .serve_connection(ConnectionWrapper(stream), service)
+                .with_upgrades());
+            if let Err(error) = drive_connection(
+                connection,
+                self.guard,
+                http1::UpgradeableConnection::graceful_shutdown,
+            )
+            .await
+            {
+                error!("Hyper h1 connection error: {error}");
             }
         }
     }
+}
 
-    Ok(())
+/// Wait for tracked connections to finish, giving up after `timeout` or another shutdown signal.
+async fn drain_connections(
+    active_tx: Sender<()>,
+    active_rx: &Receiver<()>,
+    shutdown: &Receiver<()>,
+    timeout: Duration,
+) {
+    let in_flight = active_tx.sender_count() - 1;
+    drop(active_tx);
+    if in_flight == 0 {
+        return;
+    }
+
+    info!("Waiting up to {timeout:?} for {in_flight} connection(s) to finish");
+    let drained = active_rx.recv().fuse();
+    let timed_out = FutureExt::fuse(async_io::Timer::after(timeout));
+    // A closed channel means the trigger is gone rather than a second signal.
+    let forced = async {
+        if shutdown.recv().await.is_err() {
+            std::future::pending::<()>().await;
+        }
+    }
+    .fuse();
+    futures_util::pin_mut!(drained, timed_out, forced);
+    futures_util::select! {
+        _ = drained => {}
+        _ = timed_out => {}
+        () = forced => info!("Second shutdown signal received, aborting remaining connections"),
+    }
+
+    let aborted = active_rx.sender_count();
+    let drained = in_flight.saturating_sub(aborted);
+    if aborted == 0 {
+        info!("Drained {drained} connection(s)");
+    } else {
+        warn!("Drained {drained} connection(s), aborting {aborted} still in flight");
+    }
+}
+
+/// Shutdown bookkeeping held by every connection task.
+///
+/// The server counts live guards through `_active` to know how many connections are in flight.
+/// Closing `draining` asks the connection to finish its current requests; closing `aborting`
+/// drops it outright.
+struct ConnectionGuard {
+    _active: Sender<()>,
+    draining: Receiver<()>,
+    aborting: Receiver<()>,
 }
 
-fn server_addr() -> SocketAddr {
-    std::env::var("SKYZEN_ADDRESS").map_or_else(
-        |_| SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0),
-        |addr| {
-            // Use the provided address by default
-            addr.parse()
-                .unwrap_or_else(|error| panic!("Invalid SKYZEN_ADDRESS value: {error}"))
-        },
+/// Drive a Hyper connection, switching it to graceful shutdown once the server starts draining.
+async fn drive_connection<C>(
+    mut connection: Pin<&mut C>,
+    guard: ConnectionGuard,
+    graceful_shutdown: impl FnOnce(Pin<&mut C>),
+) -> hyper::Result<()>
+where
+    C: Future<Output = hyper::Result<()>>,
+{
+    let finished = match futures_util::future::select(
+        connection.as_mut(),
+        pin!(guard.draining.recv()),
     )
+    .await
+    {
+        Either::Left((result, _)) => Some(result),
+        Either::Right(_) => None,
+    };
+    if let Some(result) = finished {
+        return result;
+    }
+
+    graceful_shutdown(connection.as_mut());
+    match futures_util::future::select(connection, pin!(guard.aborting.recv())).await {
+        Either::Left((result, _)) => result,
+        Either::Right(_) => Ok(()),
+    }
+}
+
+/// Parse a connection limit, where `0` means unlimited.
+pub(super) fn parse_max_connections(value: &str) -> Option<usize> {
+    value.trim().parse().ok()
+}
+
+/// Parse a timeout given in seconds, e.g. `30` or `2.5`.
+pub(super) fn parse_shutdown_timeout(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    let seconds = value
+        .strip_suffix('s')
+        .unwrap_or(value)
+        .parse::<f64>()
+        .ok()?;
+    Duration::try_from_secs_f64(seconds).ok()
+}
+
+pub(super) fn parse_addr_list(value: &str) -> Result<Vec<SocketAddr>, std::net::AddrParseError> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|addr| !addr.is_empty())
+        .map(str::parse)
+        .collect()
+}
+
+/// Bind every address, failing with a single error that names each address that could not be bound.
+async fn bind_all(addrs: &[SocketAddr]) -> std::io::Result<Vec<TcpListener>> {
+    let mut listeners = Vec::with_capacity(addrs.len());
+    let mut failures = Vec::new();
+    let mut kind = std::io::ErrorKind::AddrInUse;
+    for addr in addrs {
+        match TcpListener::bind(addr).await {
+            Ok(listener) => listeners.push(listener),
+            Err(error) => {
+                kind = error.kind();
+                failures.push(format!("{addr} ({error})"));
+            }
+        }
+    }
+
+    if failures.is_empty() {
+        Ok(listeners)
+    } else {
+        Err(std::io::Error::new(
+            kind,
+            format!("failed to bind {}", failures.join(", ")),
+        ))
+    }
+}
+
+/// Consume the PROXY protocol header at the start of `stream`, returning the client address it
+/// reports and a stream that replays any bytes read past the header.
+async fn read_proxy_header<C>(mut stream: C) -> std::io::Result<(Prefixed<C>, Option<SocketAddr>)>
+where
+    C: AsyncRead + Unpin,
+{
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 256];
+    loop {
+        if let Some(header) = proxy_protocol::parse(&buf)? {
+            buf.drain(..header.len);
+            return Ok((Prefixed::new(stream, buf), header.source));
+        }
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                "connection closed before the PROXY protocol header",
+            ));
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+}
+
+/// Run `future`, failing with [`TimedOut`](std::io::ErrorKind::TimedOut) once `timeout` passes.
+async fn within<T>(
+    timeout: Duration,
+    future: impl Future<Output = std::io::Result<T>>,
+) -> std::io::Result<T> {
+    match futures_util::future::select(pin!(future), async_io::Timer::after(timeout)).await {
+        Either::Left((result, _)) => result,
+        Either::Right(_) => Err(std::io::Error::new(
+            std::io::ErrorKind::TimedOut,
+            format!("no progress within {timeout:?}"),
+        )),
+    }
 }
 
 async fn sniff_protocol<C>(mut stream: C, preface: &[u8]) -> std::io::Result<(Prefixed<C>, bool)>
@@ -449,12 +1526,22 @@ where
 
 #[cfg(test)]
 mod tests {
-    use super::sniff_protocol;
-    use http_kit::utils::{AsyncRead, AsyncReadExt, AsyncWrite};
+    use super::{
+        bind_all, parse_addr_list, parse_shutdown_timeout, serve, sniff_protocol, BoundListener,
+        CliOverrides, HttpProtocols, RuntimeOptions, ServeOptions, ServerBuilder,
+    };
+    use crate::extract::ClientIp;
+    use crate::routing::{build, CreateRouteNode, Route, Router};
+    use crate::HttpConfig;
+    use async_executor::Executor as AsyncExecutor;
+    use async_net::{TcpListener, TcpStream};
+    use http_kit::utils::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
     use std::collections::VecDeque;
     use std::io;
     use std::pin::Pin;
+    use std::sync::Arc;
     use std::task::{Context, Poll};
+    use std::time::{Duration, Instant};
 
     const PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
 
@@ -532,7 +1619,11 @@ mod tests {
 
     #[tokio::test]
     async fn detects_split_h2_preface() {
-        let chunks = vec![PREFACE[..5].to_vec(), PREFACE[5..12].to_vec(), PREFACE[12..].to_vec()];
+        let chunks = vec![
+            PREFACE[..5].to_vec(),
+            PREFACE[5..12].to_vec(),
+            PREFACE[12..].to_vec(),
+        ];
         let stream = ChunkedStream::new(chunks);
 
         let (_prefixed, is_h2) = sniff_protocol(stream, PREFACE).await.unwrap();
@@ -542,7 +1633,11 @@ mod tests {
     #[tokio::test]
     async fn preserves_bytes_on_mismatch() {
         let payload = b"GET / HTTP/1.1\r\n\r\n".to_vec();
-        let chunks = vec![payload[..3].to_vec(), payload[3..10].to_vec(), payload[10..].to_vec()];
+        let chunks = vec![
+            payload[..3].to_vec(),
+            payload[3..10].to_vec(),
+            payload[10..].to_vec(),
+        ];
         let stream = ChunkedStream::new(chunks);
 
         let (prefixed, is_h2) = sniff_protocol(stream, PREFACE).await.unwrap();
@@ -551,17 +1646,991 @@ mod tests {
         let restored = read_all(prefixed).await;
         assert_eq!(restored, payload);
     }
+
+    #[test]
+    fn parses_shutdown_timeouts() {
+        assert_eq!(parse_shutdown_timeout("30"), Some(Duration::from_secs(30)));
+        assert_eq!(
+            parse_shutdown_timeout(" 2.5s "),
+            Some(Duration::from_millis(2500))
+        );
+        assert_eq!(parse_shutdown_timeout("-1"), None);
+        assert_eq!(parse_shutdown_timeout("soon"), None);
+    }
+
+    #[test]
+    fn parses_cli_flags_in_both_forms() {
+        let args = [
+            "app",
+            "--tls-cert=cert.pem",
+            "--tls-key",
+            "key.pem",
+            "--unknown",
+            "-p",
+            "8080",
+            "--shutdown-timeout=5",
+            "--max-connections",
+            "64",
+        ];
+        let overrides = CliOverrides::parse(args.into_iter().map(str::to_owned));
+        assert_eq!(overrides.tls_cert.as_deref(), Some("cert.pem"));
+        assert_eq!(overrides.tls_key.as_deref(), Some("key.pem"));
+        assert_eq!(overrides.port.as_deref(), Some("8080"));
+        assert_eq!(overrides.shutdown_timeout.as_deref(), Some("5"));
+        assert_eq!(overrides.max_connections.as_deref(), Some("64"));
+        assert!(overrides.listen.is_empty());
+
+        let args = ["app", "--listen", "127.0.0.1:8080", "--addr=[::1]:8080"];
+        let overrides = CliOverrides::parse(args.into_iter().map(str::to_owned));
+        assert_eq!(overrides.listen, ["127.0.0.1:8080", "[::1]:8080"]);
+    }
+
+    #[test]
+    fn parses_comma_separated_addresses() {
+        let addrs = parse_addr_list("127.0.0.1:8080, [::1]:8080,").unwrap();
+        assert_eq!(
+            addrs,
+            [
+                "127.0.0.1:8080".parse().unwrap(),
+                "[::1]:8080".parse::<std::net::SocketAddr>().unwrap()
+            ]
+        );
+        assert!(parse_addr_list("127.0.0.1:8080,nope").is_err());
+    }
+
+    #[test]
+    fn bind_failures_name_the_address() {
+        smol::block_on(async {
+            let taken = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let taken_addr = taken.local_addr().unwrap();
+            let free_addr = "127.0.0.1:0".parse().unwrap();
+
+            let error = bind_all(&[free_addr, taken_addr]).await.unwrap_err();
+            assert!(
+                error.to_string().contains(&taken_addr.to_string()),
+                "{error}"
+            );
+        });
+    }
+
+    #[test]
+    fn serves_every_listener_until_shutdown() {
+        let executor = Arc::new(AsyncExecutor::new());
+        smol::block_on(executor.run(async {
+            let first = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let second = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addrs = [first.local_addr().unwrap(), second.local_addr().unwrap()];
+            let (shutdown_tx, shutdown_rx) = async_channel::bounded(1);
+            let server = executor.spawn(serve(
+                Arc::clone(&executor),
+                vec![BoundListener::plain(first), BoundListener::plain(second)],
+                sleepy_router(Duration::ZERO),
+                shutdown_rx,
+                options(Duration::from_secs(5)),
+            ));
+
+            for addr in addrs {
+                let mut stream = TcpStream::connect(addr).await.unwrap();
+                stream
+                    .write_all(
+                        b"GET /slow HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+                    )
+                    .await
+                    .unwrap();
+                let mut response = Vec::new();
+                let _ = stream.read_to_end(&mut response).await;
+                assert!(response.starts_with(b"HTTP/1.1 200 OK"));
+            }
+
+            shutdown_tx.send(()).await.unwrap();
+            server.await.unwrap();
+            for addr in addrs {
+                assert!(TcpStream::connect(addr).await.is_err());
+            }
+        }));
+    }
+
+    #[test]
+    fn rejects_requests_with_too_many_headers() {
+        let config = HttpConfig {
+            http1_max_headers: Some(4),
+            ..HttpConfig::new()
+        };
+        let filler = "X-Filler: yes\r\n".repeat(8);
+        let request =
+            format!("GET /slow HTTP/1.1\r\nHost: localhost\r\n{filler}Connection: close\r\n\r\n");
+
+        let (response, _) =
+            exchange_with_config(config, request.as_bytes(), Duration::from_secs(5));
+        assert!(
+            response.starts_with(b"HTTP/1.1 431"),
+            "{}",
+            String::from_utf8_lossy(&response)
+        );
+    }
+
+    #[test]
+    fn closes_connections_that_send_headers_too_slowly() {
+        let config = HttpConfig {
+            http1_header_read_timeout: Some(Duration::from_millis(100)),
+            ..HttpConfig::new()
+        };
+        let (response, elapsed) = exchange_with_config(
+            config,
+            b"GET /slow HTTP/1.1\r\nHost: localhost\r\n",
+            Duration::from_secs(5),
+        );
+        assert!(!response.starts_with(b"HTTP/1.1 200"));
+        assert!(elapsed < Duration::from_secs(2), "took {elapsed:?}");
+    }
+
+    #[test]
+    fn pauses_accepting_at_the_connection_limit() {
+        let executor = Arc::new(AsyncExecutor::new());
+        smol::block_on(executor.run(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            let (shutdown_tx, shutdown_rx) = async_channel::bounded(1);
+            let server = executor.spawn(serve(
+                Arc::clone(&executor),
+                vec![BoundListener::plain(listener)],
+                sleepy_router(Duration::ZERO),
+                shutdown_rx,
+                ServeOptions {
+                    max_connections: 1,
+                    ..options(Duration::from_secs(5))
+                },
+            ));
+
+            // Hold the only slot with a kept-alive connection.
+            let mut first = TcpStream::connect(addr).await.unwrap();
+            first
+                .write_all(b"GET /slow HTTP/1.1\r\nHost: localhost\r\n\r\n")
+                .await
+                .unwrap();
+            let mut buf = [0u8; 1024];
+            let n = first.read(&mut buf).await.unwrap();
+            assert!(buf[..n].starts_with(b"HTTP/1.1 200 OK"));
+
+            // The second client is queued rather than refused.
+            let mut second = TcpStream::connect(addr).await.unwrap();
+            second
+                .write_all(b"GET /slow HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+                .await
+                .unwrap();
+            let queued = futures_util::future::select(
+                Box::pin(second.read(&mut buf)),
+                Box::pin(async_io::Timer::after(Duration::from_millis(200))),
+            )
+            .await;
+            assert!(
+                matches!(queued, futures_util::future::Either::Right(_)),
+                "second connection was served while the limit was reached"
+            );
+
+            drop(first);
+            let mut response = Vec::new();
+            let _ = second.read_to_end(&mut response).await;
+            assert!(response.starts_with(b"HTTP/1.1 200 OK"));
+
+            shutdown_tx.send(()).await.unwrap();
+            server.await.unwrap();
+        }));
+    }
+
+    #[test]
+    fn closes_silent_connections_to_release_their_slot() {
+        let executor = Arc::new(AsyncExecutor::new());
+        smol::block_on(executor.run(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            let (shutdown_tx, shutdown_rx) = async_channel::bounded(1);
+            let server = executor.spawn(serve(
+                Arc::clone(&executor),
+                vec![BoundListener::plain(listener)],
+                sleepy_router(Duration::ZERO),
+                shutdown_rx,
+                ServeOptions {
+                    max_connections: 1,
+                    handshake_timeout: Duration::from_millis(100),
+                    ..options(Duration::from_secs(5))
+                },
+            ));
+
+            // An idle socket takes the only slot but never sends a byte.
+            let mut idle = TcpStream::connect(addr).await.unwrap();
+            let mut second = TcpStream::connect(addr).await.unwrap();
+            second
+                .write_all(b"GET /slow HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+                .await
+                .unwrap();
+            let started = Instant::now();
+            let mut response = Vec::new();
+            let _ = second.read_to_end(&mut response).await;
+            assert!(response.starts_with(b"HTTP/1.1 200 OK"));
+            assert!(started.elapsed() < Duration::from_secs(2));
+
+            let mut buf = [0u8; 16];
+            assert_eq!(idle.read(&mut buf).await.unwrap(), 0);
+
+            shutdown_tx.send(()).await.unwrap();
+            server.await.unwrap();
+        }));
+    }
+
+    #[test]
+    fn reports_proxy_protocol_client_address() {
+        let executor = Arc::new(AsyncExecutor::new());
+        smol::block_on(executor.run(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            let (shutdown_tx, shutdown_rx) = async_channel::bounded(1);
+            let router = build(Route::new(("/ip".at(|ClientIp(ip): ClientIp| async move {
+                crate::Result::Ok(ip.to_string())
+            }),)))
+            .unwrap();
+            let server = executor.spawn(serve(
+                Arc::clone(&executor),
+                vec![BoundListener::plain(listener)],
+                router,
+                shutdown_rx,
+                ServeOptions {
+                    proxy_protocol: true,
+                    handshake_timeout: Duration::from_millis(100),
+                    ..options(Duration::from_secs(5))
+                },
+            ));
+
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            stream
+                .write_all(
+                    b"PROXY TCP4 203.0.113.9 127.0.0.1 40000 80\r\nGET /ip HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+                )
+                .await
+                .unwrap();
+            let mut response = Vec::new();
+            let _ = stream.read_to_end(&mut response).await;
+            let response = String::from_utf8_lossy(&response);
+            assert!(response.starts_with("HTTP/1.1 200 OK"), "{response}");
+            assert!(response.contains("203.0.113.9"), "{response}");
+
+            // Without a header the connection is closed before reaching the router.
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            stream
+                .write_all(b"GET /ip HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+                .await
+                .unwrap();
+            let mut response = Vec::new();
+            let _ = stream.read_to_end(&mut response).await;
+            assert!(response.is_empty());
+
+            // Nor is a connection that never sends the header kept open.
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            let started = Instant::now();
+            let mut buf = [0u8; 16];
+            assert_eq!(stream.read(&mut buf).await.unwrap(), 0);
+            assert!(started.elapsed() < Duration::from_secs(2));
+
+            shutdown_tx.send(()).await.unwrap();
+            server.await.unwrap();
+        }));
+    }
+
+    #[test]
+    fn resolves_worker_threads() {
+        assert_eq!(RuntimeOptions::new().resolved_worker_threads(), None);
+        assert_eq!(
+            RuntimeOptions::new()
+                .worker_threads(3)
+                .resolved_worker_threads(),
+            Some(3)
+        );
+        let cpus = std::thread::available_parallelism().map_or(1, std::num::NonZeroUsize::get);
+        assert_eq!(
+            RuntimeOptions::new()
+                .worker_threads(0)
+                .resolved_worker_threads(),
+            Some(cpus)
+        );
+    }
+
+    #[cfg(not(feature = "tokio-runtime"))]
+    #[test]
+    fn runs_the_executor_on_worker_threads() {
+        let executor = Arc::new(AsyncExecutor::new());
+        let threads = super::block_on_workers(&executor, 4, async {
+            // Tasks that block their thread for a while get picked up by idle workers.
+            let tasks = (0..16)
+                .map(|_| {
+                    executor.spawn(async {
+                        std::thread::sleep(Duration::from_millis(20));
+                        std::thread::current().id()
+                    })
+                })
+                .collect::<Vec<_>>();
+            futures_util::future::join_all(tasks)
+                .await
+                .into_iter()
+                .collect::<std::collections::HashSet<_>>()
+        });
+        assert!(threads.len() > 1, "ran on {} thread(s)", threads.len());
+    }
+
+    #[test]
+    fn builder_requires_an_address() {
+        let error =
+            smol::block_on(ServerBuilder::new().serve(sleepy_router(Duration::ZERO))).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn builder_serves_until_graceful_shutdown_signal() {
+        let executor = Arc::new(AsyncExecutor::new());
+        smol::block_on(executor.run(async {
+            let (stop_tx, stop_rx) = async_channel::bounded::<()>(1);
+            let (bound_tx, bound_rx) = async_channel::bounded(1);
+            let server = executor.spawn(
+                ServerBuilder::new()
+                    .default_addr("127.0.0.1:0".parse().unwrap())
+                    .on_bound(move |addr| {
+                        let _ = bound_tx.try_send(addr);
+                    })
+                    .executor(Arc::clone(&executor))
+                    .http1_only()
+                    .graceful_shutdown(async move {
+                        let _ = stop_rx.recv().await;
+                    })
+                    .serve(sleepy_router(Duration::ZERO)),
+            );
+
+            let addr: std::net::SocketAddr = bound_rx.recv().await.unwrap();
+            assert_ne!(addr.port(), 0);
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            stream
+                .write_all(b"GET /slow HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+                .await
+                .unwrap();
+            let mut response = Vec::new();
+            let _ = stream.read_to_end(&mut response).await;
+            assert!(response.starts_with(b"HTTP/1.1 200 OK"));
+
+            stop_tx.send(()).await.unwrap();
+            server.await.unwrap();
+        }));
+    }
+
+    #[cfg(feature = "tokio-runtime")]
+    #[test]
+    fn runs_tokio_handlers_on_the_tokio_runtime() {
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let router = build(Route::new(("/sleep".at(|| async {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            crate::Result::Ok("rested")
+        }),)))
+        .unwrap();
+
+        super::tokio_runtime(super::RuntimeOptions::new())
+            .unwrap()
+            .block_on(async {
+                let (stop_tx, stop_rx) = async_channel::bounded::<()>(1);
+                let server = tokio::spawn(
+                    ServerBuilder::new()
+                        .bind(addr)
+                        .http1_only()
+                        .graceful_shutdown(async move {
+                            let _ = stop_rx.recv().await;
+                        })
+                        .serve(router),
+                );
+
+                let mut stream = loop {
+                    if let Ok(stream) = TcpStream::connect(addr).await {
+                        break stream;
+                    }
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                };
+                stream
+                    .write_all(
+                        b"GET /sleep HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+                    )
+                    .await
+                    .unwrap();
+                let mut response = Vec::new();
+                let _ = stream.read_to_end(&mut response).await;
+                let response = String::from_utf8_lossy(&response);
+                assert!(response.starts_with("HTTP/1.1 200 OK"), "{response}");
+                assert!(response.contains("rested"), "{response}");
+
+                stop_tx.send(()).await.unwrap();
+                server.await.unwrap().unwrap();
+            });
+    }
+
+    #[test]
+    fn answers_expect_continue_before_the_body_is_sent() {
+        use crate::{
+            extract::BodyReadError,
+            header::CONTENT_LENGTH,
+            middleware::{ExpectContinueMiddleware, WithMiddleware},
+        };
+
+        async fn read_head(stream: &mut TcpStream) -> String {
+            let mut head = Vec::new();
+            let mut byte = [0; 1];
+            while !head.ends_with(b"\r\n\r\n") {
+                let read = stream.read(&mut byte).await.unwrap();
+                assert_ne!(read, 0, "connection closed before a response");
+                head.push(byte[0]);
+            }
+            String::from_utf8(head).unwrap()
+        }
+
+        let router = build(Route::new(("/upload".post(|body: String| async move {
+            crate::Result::Ok(format!("stored {} bytes", body.len()))
+        }),)))
+        .unwrap();
+        let app = WithMiddleware::new(
+            router,
+            ExpectContinueMiddleware::new(|request: &crate::Request| {
+                let length = request
+                    .headers()
+                    .get(CONTENT_LENGTH)
+                    .and_then(|value| value.to_str().ok()?.parse::<usize>().ok());
+                match length {
+                    Some(length) if length <= 16 => Ok(()),
+                    _ => Err(BodyReadError::TooLarge(16)),
+                }
+            }),
+        );
+
+        let executor = Arc::new(AsyncExecutor::new());
+        smol::block_on(executor.run(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            let (shutdown_tx, shutdown_rx) = async_channel::bounded(1);
+            let server = executor.spawn(serve(
+                Arc::clone(&executor),
+                vec![BoundListener::plain(listener)],
+                app,
+                shutdown_rx,
+                options(Duration::from_secs(5)),
+            ));
+
+            // The client waits for the interim response before uploading, as curl does.
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            stream
+                .write_all(
+                    b"POST /upload HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\
+                      Content-Length: 5\r\nExpect: 100-continue\r\n\r\n",
+                )
+                .await
+                .unwrap();
+            let head = read_head(&mut stream).await;
+            assert!(head.starts_with("HTTP/1.1 100 Continue"), "{head}");
+            stream.write_all(b"hello").await.unwrap();
+            let mut response = Vec::new();
+            stream.read_to_end(&mut response).await.unwrap();
+            let response = String::from_utf8_lossy(&response);
+            assert!(response.starts_with("HTTP/1.1 200 OK"), "{response}");
+            assert!(response.contains("stored 5 bytes"), "{response}");
+
+            // A rejected upload gets its final status without any interim response.
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            stream
+                .write_all(
+                    b"POST /upload HTTP/1.1\r\nHost: localhost\r\nContent-Length: 1048576\r\n\
+                      Expect: 100-continue\r\n\r\n",
+                )
+                .await
+                .unwrap();
+            let head = read_head(&mut stream).await;
+            assert!(head.starts_with("HTTP/1.1 413 Payload Too Large"), "{head}");
+
+            shutdown_tx.send(()).await.unwrap();
+            server.await.unwrap();
+        }));
+    }
+
+    /// Describes the request the upstream received.
+    #[cfg(feature = "proxy")]
+    #[derive(Clone)]
+    struct Echo;
+
+    #[cfg(feature = "proxy")]
+    impl crate::Endpoint for Echo {
+        type Error = std::convert::Infallible;
+        async fn respond(
+            &mut self,
+            request: &mut crate::Request,
+        ) -> Result<crate::Response, Self::Error> {
+            let header = |name: &str| {
+                request
+                    .headers()
+                    .get(name)
+                    .map_or("-", |value| value.to_str().unwrap())
+                    .to_owned()
+            };
+            let description = format!(
+                "{} {} host={} xfh={} xfp={} xff={} secret={}",
+                request.method(),
+                request.uri(),
+                header("host"),
+                header("x-forwarded-host"),
+                header("x-forwarded-proto"),
+                header("x-forwarded-for"),
+                header("x-secret"),
+            );
+            let body = std::mem::replace(request.body_mut(), crate::Body::empty());
+            let body = body.into_bytes().await.unwrap();
+            let mut response = crate::Response::new(crate::Body::from(format!(
+                "{description} body={}",
+                body.len()
+            )));
+            for (name, value) in [
+                ("connection", "x-internal"),
+                ("x-internal", "1"),
+                ("x-upstream", "1"),
+            ] {
+                response
+                    .headers_mut()
+                    .insert(name, http_kit::header::HeaderValue::from_static(value));
+            }
+            Ok(response)
+        }
+    }
+
+    #[cfg(feature = "proxy")]
+    async fn send_and_read(addr: std::net::SocketAddr, request: &str) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).await.unwrap();
+        String::from_utf8(response).unwrap()
+    }
+
+    #[cfg(feature = "proxy")]
+    #[test]
+    fn proxies_requests_to_an_upstream_router() {
+        use crate::{utils::Proxy, Method};
+
+        let executor = Arc::new(AsyncExecutor::new());
+        smol::block_on(executor.run(async {
+            let upstream_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let upstream_addr = upstream_listener.local_addr().unwrap();
+            let upstream = build(Route::new((
+                "/v1/{*rest}".endpoint(Method::GET, Echo),
+                "/v1/{*rest}".endpoint(Method::POST, Echo),
+            )))
+            .unwrap();
+            let closed_addr = TcpListener::bind("127.0.0.1:0")
+                .await
+                .unwrap()
+                .local_addr()
+                .unwrap();
+            let front = build(Route::new((
+                Proxy::new(&format!("http://{upstream_addr}/v1")).mount("/legacy"),
+                Proxy::new(&format!("http://{closed_addr}")).mount("/down"),
+            )))
+            .unwrap();
+
+            let front_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let front_addr = front_listener.local_addr().unwrap();
+            let (upstream_tx, upstream_rx) = async_channel::bounded(1);
+            let (front_tx, front_rx) = async_channel::bounded(1);
+            let upstream_server = executor.spawn(serve(
+                Arc::clone(&executor),
+                vec![BoundListener::plain(upstream_listener)],
+                upstream,
+                upstream_rx,
+                options(Duration::from_secs(5)),
+            ));
+            let front_server = executor.spawn(serve(
+                Arc::clone(&executor),
+                vec![BoundListener::plain(front_listener)],
+                front,
+                front_rx,
+                options(Duration::from_secs(5)),
+            ));
+
+            for _ in 0..2 {
+                let response = send_and_read(
+                    front_addr,
+                    "GET /legacy/users?page=2 HTTP/1.1\r\nHost: app.test\r\n\
+                     X-Forwarded-For: 203.0.113.7\r\nConnection: close, X-Secret\r\n\
+                     X-Secret: 1\r\n\r\n",
+                )
+                .await;
+                assert!(response.starts_with("HTTP/1.1 200 OK"), "{response}");
+                assert!(response.contains("x-upstream: 1"), "{response}");
+                assert!(!response.contains("x-internal"), "{response}");
+                assert!(
+                    response.contains(&format!(
+                        "GET /v1/users?page=2 host={upstream_addr} xfh=app.test xfp=http \
+                         xff=203.0.113.7, 127.0.0.1 secret=- body=0"
+                    )),
+                    "{response}"
+                );
+            }
+
+            let response = send_and_read(
+                front_addr,
+                "POST /legacy/upload HTTP/1.1\r\nHost: app.test\r\nConnection: close\r\n\
+                 Transfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n6\r\n world\r\n0\r\n\r\n",
+            )
+            .await;
+            assert!(response.contains("POST /v1/upload"), "{response}");
+            assert!(response.contains("body=11"), "{response}");
+
+            let response = send_and_read(
+                front_addr,
+                "GET /down/status HTTP/1.1\r\nHost: app.test\r\nConnection: close\r\n\r\n",
+            )
+            .await;
+            assert!(
+                response.starts_with("HTTP/1.1 502 Bad Gateway"),
+                "{response}"
+            );
+
+            front_tx.send(()).await.unwrap();
+            front_server.await.unwrap();
+            upstream_tx.send(()).await.unwrap();
+            upstream_server.await.unwrap();
+        }));
+    }
+
+    #[cfg(feature = "csv")]
+    #[test]
+    fn streams_csv_exports_while_rows_are_produced() {
+        use crate::responder::Csv;
+        use futures_util::StreamExt;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        #[derive(serde::Serialize)]
+        struct Row {
+            id: u32,
+            name: String,
+        }
+
+        const ROWS: u32 = 10_000;
+        let produced = Arc::new(AtomicUsize::new(0));
+        // The source stalls halfway until the client has seen the first chunk.
+        let (release_tx, release_rx) = async_channel::bounded::<()>(1);
+        let router = {
+            let produced = Arc::clone(&produced);
+            build(Route::new(("/export".at(move || {
+                let produced = Arc::clone(&produced);
+                let release = release_rx.clone();
+                async move {
+                    let rows = futures_util::stream::iter(0..ROWS).then(move |id| {
+                        let produced = Arc::clone(&produced);
+                        let release = release.clone();
+                        async move {
+                            if id == ROWS / 2 {
+                                let _ = release.recv().await;
+                            }
+                            produced.fetch_add(1, Ordering::SeqCst);
+                            Row {
+                                id,
+                                name: format!("row-{id}"),
+                            }
+                        }
+                    });
+                    crate::Result::Ok(Csv::from_stream(rows).file_name("export.csv"))
+                }
+            }),)))
+            .unwrap()
+        };
+
+        let executor = Arc::new(AsyncExecutor::new());
+        smol::block_on(executor.run(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            let (shutdown_tx, shutdown_rx) = async_channel::bounded(1);
+            let server = executor.spawn(serve(
+                Arc::clone(&executor),
+                vec![BoundListener::plain(listener)],
+                router,
+                shutdown_rx,
+                options(Duration::from_secs(5)),
+            ));
+
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            stream
+                .write_all(b"GET /export HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+                .await
+                .unwrap();
+            let mut response = Vec::new();
+            let mut buf = [0; 4096];
+            while !String::from_utf8_lossy(&response).contains("row-1\n") {
+                let read = stream.read(&mut buf).await.unwrap();
+                assert_ne!(read, 0, "connection closed before the first rows");
+                response.extend_from_slice(&buf[..read]);
+            }
+            assert!(produced.load(Ordering::SeqCst) < ROWS as usize);
+            let head = String::from_utf8_lossy(&response).into_owned();
+            assert!(head.starts_with("HTTP/1.1 200 OK"), "{head}");
+            assert!(
+                head.contains("content-type: text/csv; charset=utf-8"),
+                "{head}"
+            );
+            assert!(
+                head.contains("content-disposition: attachment; filename=\"export.csv\""),
+                "{head}"
+            );
+            assert!(head.contains("\r\n\r\n"), "{head}");
+
+            release_tx.send(()).await.unwrap();
+            stream.read_to_end(&mut response).await.unwrap();
+            assert_eq!(produced.load(Ordering::SeqCst), ROWS as usize);
+            let response = String::from_utf8(response).unwrap();
+            let (_, mut chunked) = response.split_once("\r\n\r\n").unwrap();
+            let mut body = String::new();
+            loop {
+                let (size, rest) = chunked.split_once("\r\n").unwrap();
+                let size = usize::from_str_radix(size, 16).unwrap();
+                if size == 0 {
+                    break;
+                }
+                body.push_str(&rest[..size]);
+                chunked = &rest[size + 2..];
+            }
+            let lines = body.lines().collect::<Vec<_>>();
+            assert_eq!(lines.len(), ROWS as usize + 1);
+            assert_eq!(lines[0], "id,name");
+            assert_eq!(lines[ROWS as usize], "9999,row-9999");
+
+            shutdown_tx.send(()).await.unwrap();
+            server.await.unwrap();
+        }));
+    }
+
+    fn options(drain_timeout: Duration) -> ServeOptions {
+        ServeOptions {
+            protocols: HttpProtocols::Auto,
+            http: HttpConfig::new(),
+            drain_timeout,
+            handshake_timeout: Duration::from_secs(5),
+            max_connections: 0,
+            proxy_protocol: false,
+        }
+    }
+
+    /// Send `request`, wait up to `wait` for the server to close the connection, and return what
+    /// it wrote back along with how long that took.
+    fn exchange_with_config(
+        config: HttpConfig,
+        request: &[u8],
+        wait: Duration,
+    ) -> (Vec<u8>, Duration) {
+        let executor = Arc::new(AsyncExecutor::new());
+        smol::block_on(executor.run(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            let (shutdown_tx, shutdown_rx) = async_channel::bounded(1);
+            let server = executor.spawn(serve(
+                Arc::clone(&executor),
+                vec![BoundListener::plain(listener)],
+                sleepy_router(Duration::ZERO),
+                shutdown_rx,
+                ServeOptions {
+                    http: config,
+                    ..options(Duration::from_secs(5))
+                },
+            ));
+
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            stream.write_all(request).await.unwrap();
+            let started = Instant::now();
+            let mut response = Vec::new();
+            let read = async {
+                let _ = stream.read_to_end(&mut response).await;
+            };
+            futures_util::future::select(Box::pin(read), Box::pin(async_io::Timer::after(wait)))
+                .await;
+            let elapsed = started.elapsed();
+
+            shutdown_tx.send(()).await.unwrap();
+            server.await.unwrap();
+            (response, elapsed)
+        }))
+    }
+
+    fn sleepy_router(delay: Duration) -> Router {
+        build(Route::new(("/slow".at(move || async move {
+            async_io::Timer::after(delay).await;
+            crate::Result::Ok("done")
+        }),)))
+        .unwrap()
+    }
+
+    /// Start a request, trigger shutdown while it is in flight and return the raw response
+    /// together with how long `serve` took to return.
+    async fn shutdown_during_request(
+        executor: &Arc<AsyncExecutor<'static>>,
+        handler_delay: Duration,
+        drain_timeout: Duration,
+    ) -> (String, Duration) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (shutdown_tx, shutdown_rx) = async_channel::bounded(1);
+        let server = executor.spawn(serve(
+            Arc::clone(executor),
+            vec![BoundListener::plain(listener)],
+            sleepy_router(handler_delay),
+            shutdown_rx,
+            options(drain_timeout),
+        ));
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"GET /slow HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .await
+            .unwrap();
+        async_io::Timer::after(Duration::from_millis(50)).await;
+
+        let started = Instant::now();
+        shutdown_tx.send(()).await.unwrap();
+        server.await.unwrap();
+        let elapsed = started.elapsed();
+
+        let mut response = Vec::new();
+        let _ = stream.read_to_end(&mut response).await;
+        (String::from_utf8_lossy(&response).into_owned(), elapsed)
+    }
+
+    #[test]
+    fn drains_in_flight_requests_before_exiting() {
+        let executor = Arc::new(AsyncExecutor::new());
+        let (response, _) = smol::block_on(executor.run(shutdown_during_request(
+            &executor,
+            Duration::from_millis(200),
+            Duration::from_secs(10),
+        )));
+
+        assert!(response.starts_with("HTTP/1.1 200 OK"), "{response}");
+        assert!(response.contains("done"), "{response}");
+    }
+
+    #[test]
+    fn aborts_connections_after_drain_timeout() {
+        let executor = Arc::new(AsyncExecutor::new());
+        let (response, elapsed) = smol::block_on(executor.run(shutdown_during_request(
+            &executor,
+            Duration::from_secs(30),
+            Duration::from_millis(100),
+        )));
+
+        assert!(elapsed < Duration::from_secs(5), "took {elapsed:?}");
+        assert!(!response.contains("done"), "{response}");
+    }
+
+    #[cfg(feature = "tls")]
+    #[test]
+    fn serves_https_with_alpn_negotiation() {
+        use futures_rustls::{
+            rustls::{self, pki_types::ServerName, RootCertStore},
+            TlsConnector,
+        };
+
+        let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_owned()]).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let cert_path = dir.path().join("cert.pem");
+        let key_path = dir.path().join("key.pem");
+        std::fs::write(&cert_path, certified.cert.pem()).unwrap();
+        std::fs::write(&key_path, certified.key_pair.serialize_pem()).unwrap();
+
+        let mut roots = RootCertStore::empty();
+        roots.add(certified.cert.der().clone()).unwrap();
+        let client_config = |alpn: &[&[u8]]| {
+            let mut config = rustls::ClientConfig::builder_with_provider(Arc::new(
+                rustls::crypto::ring::default_provider(),
+            ))
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_root_certificates(roots.clone())
+            .with_no_client_auth();
+            config.alpn_protocols = alpn.iter().map(|proto| proto.to_vec()).collect();
+            TlsConnector::from(Arc::new(config))
+        };
+
+        let executor = Arc::new(AsyncExecutor::new());
+        smol::block_on(executor.run(async {
+            let listener = BoundListener {
+                tcp: TcpListener::bind("127.0.0.1:0").await.unwrap(),
+                tls: Some(
+                    super::super::tls::load_acceptor(
+                        &cert_path,
+                        &key_path,
+                        HttpProtocols::Auto.alpn(),
+                    )
+                    .unwrap(),
+                ),
+            };
+            let addr = listener.tcp.local_addr().unwrap();
+            let (shutdown_tx, shutdown_rx) = async_channel::bounded(1);
+            let server = executor.spawn(serve(
+                Arc::clone(&executor),
+                vec![listener],
+                sleepy_router(Duration::ZERO),
+                shutdown_rx,
+                options(Duration::from_secs(5)),
+            ));
+            let server_name = ServerName::try_from("localhost").unwrap();
+
+            // A client speaking plain HTTP fails the handshake without stopping the server.
+            let mut plain = TcpStream::connect(addr).await.unwrap();
+            plain
+                .write_all(b"GET /slow HTTP/1.1\r\nHost: localhost\r\n\r\n")
+                .await
+                .unwrap();
+            let mut ignored = Vec::new();
+            let _ = plain.read_to_end(&mut ignored).await;
+
+            let tcp = TcpStream::connect(addr).await.unwrap();
+            let tls = client_config(&[b"h2", b"http/1.1"])
+                .connect(server_name.clone(), tcp)
+                .await
+                .unwrap();
+            assert_eq!(tls.get_ref().1.alpn_protocol(), Some(&b"h2"[..]));
+            drop(tls);
+
+            let tcp = TcpStream::connect(addr).await.unwrap();
+            let mut tls = client_config(&[b"http/1.1"])
+                .connect(server_name, tcp)
+                .await
+                .unwrap();
+            tls.write_all(b"GET /slow HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+                .await
+                .unwrap();
+            let mut response = Vec::new();
+            let _ = tls.read_to_end(&mut response).await;
+            let response = String::from_utf8_lossy(&response);
+            assert!(response.starts_with("HTTP/1.1 200 OK"), "{response}");
+            assert!(response.contains("done"), "{response}");
+
+            shutdown_tx.send(()).await.unwrap();
+            server.await.unwrap();
+        }));
+    }
 }
 
 #[derive(Debug)]
 struct IntoService<E> {
     endpoint: E,
     executor: Arc<AnyExecutor>,
+    addrs: ConnectionAddrs,
 }
 
 impl<E: Endpoint + Clone> IntoService<E> {
-    const fn new(endpoint: E, executor: Arc<AnyExecutor>) -> Self {
-        Self { endpoint, executor }
+    const fn new(endpoint: E, executor: Arc<AnyExecutor>, addrs: ConnectionAddrs) -> Self {
+        Self {
+            endpoint,
+            executor,
+            addrs,
+        }
     }
 }
 
@@ -577,10 +2646,14 @@ impl<E: Endpoint + Send + Sync + Clone + 'static> Service<hyper::Request<Incomin
     fn call(&self, mut req: hyper::Request<Incoming>) -> Self::Future {
         let mut endpoint = self.endpoint.clone();
         let executor = self.executor.clone();
+        let addrs = self.addrs.clone();
         let fut = async move {
             let on_upgrade = hyper::upgrade::on(&mut req);
             let method = req.method().clone();
-            let path = req.uri().path().to_owned();
+            // Cloning the `Uri` shares its buffer instead of copying the path.
+            let uri = req.uri().clone();
+            // The incoming body is only polled when read, which is when hyper answers
+            // `Expect: 100-continue`; requests rejected before that never see their body sent.
             let mut request: crate::Request =
                 crate::Request::from(req.map(BodyDataStream::new).map(|body| {
                     crate::Body::from_stream(
@@ -589,15 +2662,22 @@ impl<E: Endpoint + Send + Sync + Clone + 'static> Service<hyper::Request<Incomin
                 }));
             request.extensions_mut().insert(on_upgrade);
             request.extensions_mut().insert(executor);
+            if let Some(peer) = addrs.peer {
+                request.extensions_mut().insert(peer);
+            }
+            if let Some(proxied) = addrs.proxied {
+                request.extensions_mut().insert(proxied);
+            }
             let response = endpoint.respond(&mut request).await;
             let response: Result<hyper::Response<crate::Body>, Self::Error> =
                 response.map_err(|error| Box::new(error) as BoxHttpError);
 
             match &response {
+                Ok(_) if AccessLogged::is_set(&request) => {}
                 Ok(ok) => {
                     info!(
                         method = method.as_str(),
-                        path = path.as_str(),
+                        path = uri.path(),
                         status = ok.status().as_u16(),
                         "request completed"
                     );
@@ -606,7 +2686,7 @@ impl<E: Endpoint + Send + Sync + Clone + 'static> Service<hyper::Request<Incomin
                     let status = err.status().as_u16();
                     error!(
                         method = method.as_str(),
-                        path = path.as_str(),
+                        path = uri.path(),
                         status = status,
                         "request failed: {err}"
                     );
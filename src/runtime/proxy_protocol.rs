@@ -0,0 +1,235 @@
+//! Parser for the PROXY protocol (v1 text and v2 binary headers) sent by L4 load balancers.
+//!
+//! See <https://www.haproxy.org/download/2.9/doc/proxy-protocol.txt>.
+
+use std::{
+    io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+};
+
+const V1_PREFIX: &[u8] = b"PROXY ";
+/// A v1 header, including the trailing CRLF, is at most 107 bytes long.
+const V1_MAX_LEN: usize = 107;
+const V2_SIGNATURE: &[u8] = b"\r\n\r\n\0\r\nQUIT\n";
+const V2_FIXED_LEN: usize = 16;
+
+/// A complete PROXY protocol header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProxyHeader {
+    /// The original client address, or `None` for `UNKNOWN`/`LOCAL` headers and
+    /// non-IP address families.
+    pub source: Option<SocketAddr>,
+    /// Number of bytes the header occupies at the start of the stream.
+    pub len: usize,
+}
+
+/// Parse a PROXY protocol header at the start of `buf`.
+///
+/// Returns `Ok(None)` when more bytes are needed.
+///
+/// # Errors
+///
+/// Returns an [`io::ErrorKind::InvalidData`] error when the bytes cannot be a valid header.
+pub fn parse(buf: &[u8]) -> io::Result<Option<ProxyHeader>> {
+    if is_prefix_of(buf, V2_SIGNATURE) {
+        return parse_v2(buf);
+    }
+    if is_prefix_of(buf, V1_PREFIX) {
+        return parse_v1(buf);
+    }
+    Err(invalid("missing PROXY protocol header"))
+}
+
+/// Whether `buf` and `signature` agree on their common prefix.
+fn is_prefix_of(buf: &[u8], signature: &[u8]) -> bool {
+    let len = buf.len().min(signature.len());
+    buf[..len] == signature[..len]
+}
+
+fn parse_v1(buf: &[u8]) -> io::Result<Option<ProxyHeader>> {
+    let window = &buf[..buf.len().min(V1_MAX_LEN)];
+    let Some(end) = window.windows(2).position(|pair| pair == b"\r\n") else {
+        if buf.len() >= V1_MAX_LEN {
+            return Err(invalid("PROXY v1 header is too long"));
+        }
+        return Ok(None);
+    };
+
+    let line = std::str::from_utf8(&buf[V1_PREFIX.len()..end])
+        .map_err(|_| invalid("PROXY v1 header is not ASCII"))?;
+    let mut fields = line.split(' ');
+    let source = match fields.next() {
+        Some("UNKNOWN") => None,
+        Some(family @ ("TCP4" | "TCP6")) => {
+            let (Some(src), Some(_dst), Some(src_port), Some(_dst_port), None) = (
+                fields.next(),
+                fields.next(),
+                fields.next(),
+                fields.next(),
+                fields.next(),
+            ) else {
+                return Err(invalid("PROXY v1 header has the wrong number of fields"));
+            };
+            let ip: IpAddr = src
+                .parse()
+                .map_err(|_| invalid("invalid PROXY v1 source address"))?;
+            if ip.is_ipv4() != (family == "TCP4") {
+                return Err(invalid("PROXY v1 source address does not match its family"));
+            }
+            let port = src_port
+                .parse()
+                .map_err(|_| invalid("invalid PROXY v1 source port"))?;
+            Some(SocketAddr::new(ip, port))
+        }
+        _ => return Err(invalid("unsupported PROXY v1 protocol family")),
+    };
+
+    Ok(Some(ProxyHeader {
+        source,
+        len: end + 2,
+    }))
+}
+
+fn parse_v2(buf: &[u8]) -> io::Result<Option<ProxyHeader>> {
+    if buf.len() < V2_FIXED_LEN {
+        return Ok(None);
+    }
+    let version_command = buf[12];
+    if version_command >> 4 != 2 {
+        return Err(invalid("unsupported PROXY protocol version"));
+    }
+    let family = buf[13];
+    let address_len = usize::from(u16::from_be_bytes([buf[14], buf[15]]));
+    let len = V2_FIXED_LEN + address_len;
+    if buf.len() < len {
+        return Ok(None);
+    }
+    let addresses = &buf[V2_FIXED_LEN..len];
+
+    let source = match version_command & 0x0F {
+        // LOCAL: health checks from the proxy itself, keep the real peer address.
+        0x0 => None,
+        0x1 => match family >> 4 {
+            0x1 => {
+                let block: &[u8; 12] = addresses
+                    .get(..12)
+                    .and_then(|block| block.try_into().ok())
+                    .ok_or_else(|| invalid("truncated PROXY v2 IPv4 addresses"))?;
+                let ip = Ipv4Addr::new(block[0], block[1], block[2], block[3]);
+                let port = u16::from_be_bytes([block[8], block[9]]);
+                Some(SocketAddr::new(ip.into(), port))
+            }
+            0x2 => {
+                let block: &[u8; 36] = addresses
+                    .get(..36)
+                    .and_then(|block| block.try_into().ok())
+                    .ok_or_else(|| invalid("truncated PROXY v2 IPv6 addresses"))?;
+                let mut octets = [0u8; 16];
+                octets.copy_from_slice(&block[..16]);
+                let port = u16::from_be_bytes([block[32], block[33]]);
+                Some(SocketAddr::new(Ipv6Addr::from(octets).into(), port))
+            }
+            // AF_UNSPEC and AF_UNIX carry no IP address.
+            _ => None,
+        },
+        _ => return Err(invalid("unsupported PROXY v2 command")),
+    };
+
+    Ok(Some(ProxyHeader { source, len }))
+}
+
+fn invalid(message: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, ProxyHeader, V2_SIGNATURE};
+    use std::net::SocketAddr;
+
+    fn v2(command: u8, family: u8, addresses: &[u8]) -> Vec<u8> {
+        let mut header = V2_SIGNATURE.to_vec();
+        header.push(0x20 | command);
+        header.push(family);
+        header.extend_from_slice(&u16::try_from(addresses.len()).unwrap().to_be_bytes());
+        header.extend_from_slice(addresses);
+        header
+    }
+
+    #[test]
+    fn parses_v1_headers() {
+        let buf = b"PROXY TCP4 192.0.2.1 198.51.100.1 56324 443\r\nGET / HTTP/1.1\r\n";
+        let header = parse(buf).unwrap().unwrap();
+        assert_eq!(
+            header.source,
+            Some("192.0.2.1:56324".parse::<SocketAddr>().unwrap())
+        );
+        assert_eq!(&buf[header.len..], b"GET / HTTP/1.1\r\n");
+
+        let header = parse(b"PROXY TCP6 2001:db8::1 2001:db8::2 4711 80\r\n")
+            .unwrap()
+            .unwrap();
+        assert_eq!(header.source, Some("[2001:db8::1]:4711".parse().unwrap()));
+
+        let header = parse(b"PROXY UNKNOWN\r\n").unwrap().unwrap();
+        assert_eq!(
+            header,
+            ProxyHeader {
+                source: None,
+                len: 15
+            }
+        );
+    }
+
+    #[test]
+    fn waits_for_complete_headers() {
+        assert_eq!(parse(b"").unwrap(), None);
+        assert_eq!(parse(b"PRO").unwrap(), None);
+        assert_eq!(parse(b"PROXY TCP4 192.0.2.1").unwrap(), None);
+        assert_eq!(parse(&V2_SIGNATURE[..5]).unwrap(), None);
+
+        let full = v2(
+            0x1,
+            0x11,
+            &[192, 0, 2, 1, 198, 51, 100, 1, 0x1F, 0x90, 0, 80],
+        );
+        assert_eq!(parse(&full[..20]).unwrap(), None);
+    }
+
+    #[test]
+    fn parses_v2_headers() {
+        let mut buf = v2(
+            0x1,
+            0x11,
+            &[192, 0, 2, 1, 198, 51, 100, 1, 0x1F, 0x90, 0, 80],
+        );
+        let header_len = buf.len();
+        buf.extend_from_slice(b"GET /");
+        let header = parse(&buf).unwrap().unwrap();
+        assert_eq!(header.source, Some("192.0.2.1:8080".parse().unwrap()));
+        assert_eq!(header.len, header_len);
+
+        let mut addresses = [0u8; 36];
+        addresses[0] = 0x20;
+        addresses[1] = 0x01;
+        addresses[15] = 0x01;
+        addresses[32..34].copy_from_slice(&4711u16.to_be_bytes());
+        let header = parse(&v2(0x1, 0x21, &addresses)).unwrap().unwrap();
+        assert_eq!(header.source, Some("[2001::1]:4711".parse().unwrap()));
+
+        let local = parse(&v2(0x0, 0x00, &[])).unwrap().unwrap();
+        assert_eq!(local.source, None);
+    }
+
+    #[test]
+    fn rejects_malformed_headers() {
+        assert!(parse(b"GET / HTTP/1.1\r\n").is_err());
+        assert!(parse(b"PROXY TCP4 192.0.2.1 198.51.100.1 56324\r\n").is_err());
+        assert!(parse(b"PROXY TCP4 2001:db8::1 198.51.100.1 1 2\r\n").is_err());
+        assert!(parse(b"PROXY TCP4 192.0.2.1 198.51.100.1 99999 443\r\n").is_err());
+        assert!(parse(b"PROXY SCTP a b c d\r\n").is_err());
+        assert!(parse(&[b"PROXY TCP4 ".as_slice(), &[b'1'; 120]].concat()).is_err());
+        assert!(parse(&v2(0x1, 0x11, &[192, 0, 2, 1])).is_err());
+        assert!(parse(&v2(0x7, 0x11, &[])).is_err());
+    }
+}
@@ -0,0 +1,56 @@
+//! rustls-based TLS termination for the native runtime.
+
+use std::{io, path::Path, sync::Arc};
+
+use futures_rustls::{
+    rustls::{
+        crypto::ring,
+        pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer},
+        ServerConfig,
+    },
+    TlsAcceptor,
+};
+
+/// ALPN protocol id for HTTP/2.
+pub const ALPN_H2: &[u8] = b"h2";
+/// ALPN protocol id for HTTP/1.1.
+pub const ALPN_HTTP11: &[u8] = b"http/1.1";
+
+/// Load a PEM certificate chain and private key, advertising `alpn` during the handshake.
+pub fn load_acceptor(cert_path: &Path, key_path: &Path, alpn: &[&[u8]]) -> io::Result<TlsAcceptor> {
+    let certs = CertificateDer::pem_file_iter(cert_path)
+        .and_then(Iterator::collect::<Result<Vec<_>, _>>)
+        .map_err(|error| {
+            invalid_config(format!(
+                "failed to read TLS certificate `{}`: {error}",
+                cert_path.display()
+            ))
+        })?;
+    if certs.is_empty() {
+        return Err(invalid_config(format!(
+            "no certificates found in `{}`",
+            cert_path.display()
+        )));
+    }
+
+    let key = PrivateKeyDer::from_pem_file(key_path).map_err(|error| {
+        invalid_config(format!(
+            "failed to read TLS private key `{}`: {error}",
+            key_path.display()
+        ))
+    })?;
+
+    let mut config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .map_err(invalid_config)?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(invalid_config)?;
+    config.alpn_protocols = alpn.iter().map(|protocol| protocol.to_vec()).collect();
+
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+fn invalid_config(error: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, error)
+}
@@ -1,4 +1,7 @@
-use std::future::Future;
+use std::{
+    future::Future,
+    sync::atomic::{AtomicBool, Ordering},
+};
 
 use crate::{Body, Endpoint, StatusCode};
 use wasm_bindgen::prelude::*;
@@ -13,7 +16,65 @@ pub type Env = JsValue;
 /// Alias for the execution context value.
 pub type ExecutionContext = JsValue;
 
+/// A cron trigger delivered to the Worker's `scheduled` handler.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScheduledEvent {
+    /// The cron expression that fired, as configured in `wrangler.toml`.
+    pub cron: String,
+    /// When the trigger was scheduled to run, in milliseconds since the Unix epoch.
+    pub scheduled_time: u64,
+}
+
+/// Bridge a `#[skyzen::scheduled]` handler into the Workers `scheduled` contract.
+///
+/// # Errors
+///
+/// Returns the handler's error, after logging it, so that the Workers runtime records the
+/// invocation as failed.
+pub async fn scheduled<F, Fut, E>(
+    handler: F,
+    event: JsValue,
+    env: Env,
+    _ctx: ExecutionContext,
+) -> Result<(), JsValue>
+where
+    F: FnOnce(ScheduledEvent, crate::extract::WorkerEnv) -> Fut,
+    Fut: Future<Output = Result<(), E>>,
+    E: std::fmt::Display,
+{
+    let cron = js_sys::Reflect::get(&event, &JsValue::from_str("cron"))?
+        .as_string()
+        .unwrap_or_default();
+    let scheduled_time = js_sys::Reflect::get(&event, &JsValue::from_str("scheduledTime"))?
+        .as_f64()
+        .unwrap_or_default();
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let event = ScheduledEvent {
+        cron,
+        scheduled_time: scheduled_time as u64,
+    };
+
+    let cron = event.cron.clone();
+    handler(event, crate::extract::WorkerEnv::new(env))
+        .await
+        .map_err(|error| {
+            tracing::error!(cron = cron.as_str(), "scheduled handler failed: {error}");
+            JsValue::from_str(&error.to_string())
+        })
+}
+
+/// Whether the startup hooks have run, or are running, in this isolate.
+static STARTED: AtomicBool = AtomicBool::new(false);
+
 /// Bridge the annotated endpoint into the WinterCG `fetch` contract.
+///
+/// `env` and `ctx` are stored in the request extensions, so handlers can reach bindings through
+/// [`WorkerEnv`](crate::extract::WorkerEnv) and extend the invocation with
+/// [`BackgroundTasks`](crate::extract::BackgroundTasks).
+///
+/// Hooks registered with [`on_startup`](super::lifecycle::on_startup) run before the first request is
+/// served; if one fails, that request fails and the next one retries. Shutdown hooks are ignored,
+/// as Workers have no shutdown event.
 pub async fn launch<Fut, E>(
     factory: impl FnOnce() -> Fut,
     request: Request,
@@ -25,19 +86,35 @@ where
     E: Endpoint + Clone + 'static,
 {
     let endpoint = factory().await;
+    let mut hooks = super::lifecycle::take_registered();
+    if !STARTED.swap(true, Ordering::AcqRel) {
+        if let Err(error) = hooks
+            .startup(&super::lifecycle::LifecycleContext::default())
+            .await
+        {
+            STARTED.store(false, Ordering::Release);
+            return Err(JsValue::from_str(&format!("startup hook failed: {error}")));
+        }
+    }
     serve(endpoint, request, env, ctx).await
 }
 
 async fn serve<E>(
     mut endpoint: E,
     request: Request,
-    _env: Env,
-    _ctx: ExecutionContext,
+    env: Env,
+    ctx: ExecutionContext,
 ) -> Result<Response, JsValue>
 where
     E: Endpoint + Clone + 'static,
 {
     let mut sky_request = convert_request(request).await?;
+    sky_request
+        .extensions_mut()
+        .insert(crate::extract::WorkerEnv::new(env));
+    sky_request
+        .extensions_mut()
+        .insert(crate::extract::BackgroundTasks::from_context(ctx));
     let response = endpoint
         .respond(&mut sky_request)
         .await
@@ -1,4 +1,5 @@
 use std::{
+    fmt::Write as _,
     io,
     path::{Component, Path, PathBuf},
     sync::Arc,
@@ -7,8 +8,10 @@ use std::{
 use crate::{
     header::{self, HeaderValue},
     routing::{IntoRouteNode, Params, Route, RouteNode},
+    utils::{header_util, negotiation},
     Endpoint, Method, Request, Response, StatusCode,
 };
+use futures_util::TryStreamExt;
 use skyzen_core::Extractor;
 
 /// Mount a directory tree into the router.
@@ -17,12 +20,23 @@ use skyzen_core::Extractor;
 /// Files are looked up relative to the provided directory, `..` segments are rejected,
 /// and directories fall back to `index.html` by default.
 ///
+/// Call [`StaticDir::precompressed`] to serve `.br` / `.gz` siblings produced by a build pipeline.
+///
 /// Note: `StaticDir` does not support `OpenAPI` documentation generation for its routes.
 #[derive(Debug, Clone)]
 pub struct StaticDir {
     mount_path: String,
     directory: Arc<PathBuf>,
     index_file: String,
+    precompressed: bool,
+    spa_fallback: bool,
+    listing: ListingOptions,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct ListingOptions {
+    enabled: bool,
+    show_hidden: bool,
 }
 
 impl StaticDir {
@@ -36,6 +50,9 @@ impl StaticDir {
             mount_path: normalize_mount_path(&mount_path_string),
             directory: Arc::new(directory.into()),
             index_file: "index.html".to_owned(),
+            precompressed: false,
+            spa_fallback: false,
+            listing: ListingOptions::default(),
         }
     }
 
@@ -45,6 +62,47 @@ impl StaticDir {
         self.index_file = index_file.into();
         self
     }
+
+    /// Serve precompressed siblings (`<file>.br`, `<file>.gz`) when the client accepts them.
+    ///
+    /// Brotli is preferred over gzip when both are acceptable. The response keeps the
+    /// `Content-Type` of the original file, carries the matching `Content-Encoding`, and
+    /// always includes `Vary: Accept-Encoding`. Requests fall back to the uncompressed file
+    /// when no sibling exists.
+    #[must_use]
+    pub const fn precompressed(mut self) -> Self {
+        self.precompressed = true;
+        self
+    }
+
+    /// Serve the index file for any path that does not resolve to an existing file.
+    ///
+    /// This lets client-side routers of single-page apps handle deep links after a refresh.
+    /// Paths whose last segment has a file extension (such as `/missing.js`) still return
+    /// `404 Not Found` so missing assets stay visible.
+    #[must_use]
+    pub const fn spa_fallback(mut self, enabled: bool) -> Self {
+        self.spa_fallback = enabled;
+        self
+    }
+
+    /// Render an HTML listing when a directory without an index file is requested.
+    ///
+    /// Entries are sorted directories-first and show their size and modification time.
+    /// Dotfiles are omitted unless [`StaticDir::show_hidden`] is enabled, and symlinks that
+    /// point outside the served directory are never listed or followed.
+    #[must_use]
+    pub const fn directory_listing(mut self, enabled: bool) -> Self {
+        self.listing.enabled = enabled;
+        self
+    }
+
+    /// Include hidden files (names starting with `.`) in directory listings.
+    #[must_use]
+    pub const fn show_hidden(mut self, enabled: bool) -> Self {
+        self.listing.show_hidden = enabled;
+        self
+    }
 }
 
 impl IntoRouteNode for Route {
@@ -55,11 +113,11 @@ impl IntoRouteNode for Route {
 
 impl IntoRouteNode for StaticDir {
     fn into_route_node(self) -> RouteNode {
+        let mount_path = self.mount_path.clone();
         let endpoint = StaticDirEndpoint {
-            directory: self.directory.clone(),
-            index_file: Arc::new(self.index_file.clone()),
+            config: Arc::new(self),
         };
-        let wildcard_suffix = if self.mount_path == "/" {
+        let wildcard_suffix = if mount_path == "/" {
             "{*path}"
         } else {
             "/{*path}"
@@ -69,30 +127,249 @@ impl IntoRouteNode for StaticDir {
             RouteNode::new_endpoint(wildcard_suffix, Method::GET, endpoint, None),
         ));
 
-        RouteNode::new_route(self.mount_path, route)
+        RouteNode::new_route(mount_path, route)
     }
 }
 
 async fn serve_static(
-    directory: &Path,
-    index_file: &str,
+    config: &StaticDir,
     params: &Params,
+    request: &Request,
 ) -> Result<Response, StaticDirError> {
     let requested_path = params.get("path").unwrap_or("");
     let sanitized = sanitize_relative_path(requested_path).ok_or(StaticDirError::InvalidPath)?;
-    let file_path = resolve_target_path(directory, &sanitized, index_file)
-        .ok_or(StaticDirError::FileNotFound)?;
+    let root = async_fs::canonicalize(config.directory.as_ref())
+        .await
+        .map_err(|_| StaticDirError::FileNotFound)?;
+    let resolved = resolve_target_path(&root, &sanitized, &config.index_file).await;
+
+    if resolved.is_none() && config.listing.enabled {
+        if let Some(listing) =
+            render_listing(config, &root, &sanitized, request.uri().path()).await?
+        {
+            let mut response = Response::new(http_kit::Body::from(listing));
+            response.headers_mut().insert(
+                header::CONTENT_TYPE,
+                HeaderValue::from_static("text/html; charset=utf-8"),
+            );
+            return Ok(response);
+        }
+    }
+
+    let file_path = match resolved {
+        Some(path) => Some(path),
+        None if config.spa_fallback && sanitized.extension().is_none() => {
+            resolve_target_path(&root, Path::new(""), &config.index_file).await
+        }
+        None => None,
+    }
+    .ok_or(StaticDirError::FileNotFound)?;
+
+    let precompressed = if config.precompressed {
+        find_precompressed(
+            &root,
+            &file_path,
+            request.headers().get(header::ACCEPT_ENCODING),
+        )
+        .await
+    } else {
+        None
+    };
 
-    let data = read_file(&file_path).await?;
-    let mut response = Response::new(http_kit::Body::from(data));
+    let mut response = if let Some((encoding, sibling)) = precompressed {
+        let data = read_file(&sibling).await?;
+        let mut response = Response::new(http_kit::Body::from(data));
+        response.headers_mut().insert(
+            header::CONTENT_ENCODING,
+            HeaderValue::from_static(encoding.as_str()),
+        );
+        response
+    } else {
+        Response::new(http_kit::Body::from(read_file(&file_path).await?))
+    };
 
     if let Some(value) = guess_content_type(&file_path) {
         response.headers_mut().insert(header::CONTENT_TYPE, value);
     }
+    if config.precompressed {
+        header_util::append_vary(response.headers_mut(), "Accept-Encoding");
+    }
 
     Ok(response)
 }
 
+/// Content codings that may be served from precompressed siblings, in order of preference.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Precompressed {
+    Brotli,
+    Gzip,
+}
+
+impl Precompressed {
+    const PREFERENCE: [Self; 2] = [Self::Brotli, Self::Gzip];
+
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::Brotli => "br",
+            Self::Gzip => "gzip",
+        }
+    }
+
+    const fn extension(self) -> &'static str {
+        match self {
+            Self::Brotli => "br",
+            Self::Gzip => "gz",
+        }
+    }
+}
+
+async fn find_precompressed(
+    root: &Path,
+    file_path: &Path,
+    accept_encoding: Option<&HeaderValue>,
+) -> Option<(Precompressed, PathBuf)> {
+    let accept_encoding = accept_encoding?.to_str().ok()?;
+    for encoding in Precompressed::PREFERENCE {
+        if !accepts_encoding(accept_encoding, encoding.as_str()) {
+            continue;
+        }
+        let mut sibling = file_path.as_os_str().to_owned();
+        sibling.push(".");
+        sibling.push(encoding.extension());
+        let sibling = PathBuf::from(sibling);
+        if metadata_within(root, &sibling)
+            .await
+            .is_some_and(|meta| meta.is_file())
+        {
+            return Some((encoding, sibling));
+        }
+    }
+    None
+}
+
+/// Whether an `Accept-Encoding` header value allows `coding` (explicitly or via `*`).
+fn accepts_encoding(accept_encoding: &str, coding: &str) -> bool {
+    let mut wildcard = None;
+    for (name, quality) in negotiation::entries(accept_encoding) {
+        if name.eq_ignore_ascii_case(coding) {
+            return quality > 0.0;
+        }
+        if name == "*" {
+            wildcard = Some(quality > 0.0);
+        }
+    }
+    wildcard.unwrap_or(false)
+}
+
+struct ListingEntry {
+    name: String,
+    is_dir: bool,
+    size: u64,
+    modified: Option<std::time::SystemTime>,
+}
+
+/// Render the listing for `relative`, or `None` if it is not a directory inside `root`.
+async fn render_listing(
+    config: &StaticDir,
+    root: &Path,
+    relative: &Path,
+    request_path: &str,
+) -> Result<Option<String>, StaticDirError> {
+    let target = root.join(relative);
+    if !metadata_within(root, &target)
+        .await
+        .is_some_and(|meta| meta.is_dir())
+    {
+        return Ok(None);
+    }
+
+    let mut entries = Vec::new();
+    let mut read_dir = async_fs::read_dir(&target).await?;
+    while let Some(entry) = read_dir.try_next().await? {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if !config.listing.show_hidden && name.starts_with('.') {
+            continue;
+        }
+        let Some(metadata) = metadata_within(root, &entry.path()).await else {
+            continue;
+        };
+        entries.push(ListingEntry {
+            name,
+            is_dir: metadata.is_dir(),
+            size: metadata.len(),
+            modified: metadata.modified().ok(),
+        });
+    }
+    entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+
+    let base = request_path.trim_end_matches('/');
+    let title = escape_html(if request_path.is_empty() {
+        "/"
+    } else {
+        request_path
+    });
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>Index of {title}</title></head>\n\
+         <body>\n<h1>Index of {title}</h1>\n<table>\n\
+         <tr><th>Name</th><th>Size</th><th>Modified</th></tr>\n"
+    );
+    if !relative.as_os_str().is_empty() {
+        let parent = base.rsplit_once('/').map_or("", |(parent, _)| parent);
+        let _ = writeln!(
+            html,
+            "<tr><td><a href=\"{}/\">../</a></td><td>-</td><td>-</td></tr>",
+            escape_html(parent)
+        );
+    }
+    for entry in &entries {
+        let suffix = if entry.is_dir { "/" } else { "" };
+        let size = if entry.is_dir {
+            "-".to_owned()
+        } else {
+            entry.size.to_string()
+        };
+        let modified = entry
+            .modified
+            .map_or_else(|| "-".to_owned(), httpdate::fmt_http_date);
+        let _ = writeln!(
+            html,
+            "<tr><td><a href=\"{}/{}{suffix}\">{}{suffix}</a></td><td>{size}</td><td>{modified}</td></tr>",
+            escape_html(base),
+            escape_html(&percent_encode_segment(&entry.name)),
+            escape_html(&entry.name),
+        );
+    }
+    html.push_str("</table>\n</body>\n</html>\n");
+    Ok(Some(html))
+}
+
+fn escape_html(input: &str) -> String {
+    let mut escaped = String::with_capacity(input.len());
+    for ch in input.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+fn percent_encode_segment(segment: &str) -> String {
+    let mut encoded = String::with_capacity(segment.len());
+    for byte in segment.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            encoded.push(char::from(byte));
+        } else {
+            let _ = write!(encoded, "%{byte:02X}");
+        }
+    }
+    encoded
+}
+
 async fn read_file(path: &Path) -> Result<Vec<u8>, StaticDirError> {
     async_fs::read(path).await.map_err(StaticDirError::IoError)
 }
@@ -103,23 +380,35 @@ fn guess_content_type(path: &Path) -> Option<HeaderValue> {
         .and_then(|mime| HeaderValue::from_str(mime).ok())
 }
 
-fn resolve_target_path(base: &Path, relative: &Path, index_file: &str) -> Option<PathBuf> {
+/// Metadata of `path` if it exists and, once symlinks are resolved, stays inside `root`.
+///
+/// `root` must already be canonical.
+async fn metadata_within(root: &Path, path: &Path) -> Option<std::fs::Metadata> {
+    let canonical = async_fs::canonicalize(path).await.ok()?;
+    if !canonical.starts_with(root) {
+        return None;
+    }
+    async_fs::metadata(canonical).await.ok()
+}
+
+async fn resolve_target_path(root: &Path, relative: &Path, index_file: &str) -> Option<PathBuf> {
     let target = if relative.as_os_str().is_empty() {
-        base.to_path_buf()
+        root.to_path_buf()
     } else {
-        base.join(relative)
+        root.join(relative)
     };
 
-    let metadata = std::fs::metadata(&target).ok()?;
+    let metadata = metadata_within(root, &target).await?;
     let resolved = if metadata.is_dir() {
         target.join(index_file)
     } else {
         target
     };
 
-    std::fs::metadata(&resolved)
-        .ok()
-        .and_then(|meta| if meta.is_file() { Some(resolved) } else { None })
+    metadata_within(root, &resolved)
+        .await
+        .is_some_and(|meta| meta.is_file())
+        .then_some(resolved)
 }
 
 fn sanitize_relative_path(path: &str) -> Option<PathBuf> {
@@ -134,7 +423,7 @@ fn sanitize_relative_path(path: &str) -> Option<PathBuf> {
     Some(buf)
 }
 
-fn normalize_mount_path(mount_path: &str) -> String {
+pub(crate) fn normalize_mount_path(mount_path: &str) -> String {
     let mut normalized = mount_path.trim().to_owned();
     if normalized.is_empty() {
         return "/".to_owned();
@@ -152,8 +441,7 @@ fn normalize_mount_path(mount_path: &str) -> String {
 
 #[derive(Clone)]
 struct StaticDirEndpoint {
-    directory: Arc<PathBuf>,
-    index_file: Arc<String>,
+    config: Arc<StaticDir>,
 }
 
 /// Errors that can occur when serving static files.
@@ -174,13 +462,13 @@ impl Endpoint for StaticDirEndpoint {
     type Error = StaticDirError;
     async fn respond(&mut self, request: &mut Request) -> Result<Response, Self::Error> {
         let params = Params::extract(request).await.unwrap(); // Params extractor never fails, so unwrap is safe
-        serve_static(self.directory.as_ref(), self.index_file.as_ref(), &params).await
+        serve_static(&self.config, &params, request).await
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{normalize_mount_path, sanitize_relative_path};
+    use super::{accepts_encoding, escape_html, normalize_mount_path, sanitize_relative_path};
     use crate::{
         header,
         routing::{build, Route},
@@ -287,4 +575,337 @@ mod tests {
         let body = response.into_body().into_string().await.unwrap();
         assert_eq!(body, "custom");
     }
+
+    #[test]
+    fn parses_accept_encoding_quality_values() {
+        assert!(accepts_encoding("gzip, deflate, br", "br"));
+        assert!(accepts_encoding("br;q=0.5", "br"));
+        assert!(!accepts_encoding("br;q=0, gzip", "br"));
+        assert!(accepts_encoding("*", "gzip"));
+        assert!(!accepts_encoding("*;q=0", "gzip"));
+        assert!(!accepts_encoding("identity", "gzip"));
+    }
+
+    fn write_precompressed_fixture() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("app.js"), b"plain").unwrap();
+        std::fs::write(dir.path().join("app.js.br"), b"brotli").unwrap();
+        std::fs::write(dir.path().join("app.js.gz"), b"gzip").unwrap();
+        std::fs::write(dir.path().join("only-plain.css"), b"plain css").unwrap();
+        dir
+    }
+
+    fn request_with_encoding(path: &str, accept_encoding: &str) -> http_kit::Request {
+        let mut request = get_request(path);
+        request.headers_mut().insert(
+            header::ACCEPT_ENCODING,
+            accept_encoding.parse().expect("invalid header"),
+        );
+        request
+    }
+
+    #[tokio::test]
+    async fn prefers_brotli_sibling_when_accepted() {
+        let dir = write_precompressed_fixture();
+        let router = build(Route::new((
+            StaticDir::new("/assets", dir.path()).precompressed(),
+        )))
+        .unwrap();
+
+        let request = request_with_encoding("/assets/app.js", "gzip, br");
+        let response = router.clone().go(request).await.unwrap();
+        let headers = response.headers();
+        assert_eq!(headers.get(header::CONTENT_ENCODING).unwrap(), "br");
+        assert_eq!(
+            headers.get(header::CONTENT_TYPE).unwrap(),
+            "text/javascript"
+        );
+        assert_eq!(headers.get(header::VARY).unwrap(), "Accept-Encoding");
+        let body = response.into_body().into_bytes().await.unwrap();
+        assert_eq!(body.as_ref(), b"brotli");
+    }
+
+    #[tokio::test]
+    async fn serves_gzip_sibling_when_brotli_is_refused() {
+        let dir = write_precompressed_fixture();
+        let router = build(Route::new((
+            StaticDir::new("/assets", dir.path()).precompressed(),
+        )))
+        .unwrap();
+
+        let request = request_with_encoding("/assets/app.js", "br;q=0, gzip");
+        let response = router.clone().go(request).await.unwrap();
+        assert_eq!(
+            response.headers().get(header::CONTENT_ENCODING).unwrap(),
+            "gzip"
+        );
+        let body = response.into_body().into_bytes().await.unwrap();
+        assert_eq!(body.as_ref(), b"gzip");
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_uncompressed_file() {
+        let dir = write_precompressed_fixture();
+        let router = build(Route::new((
+            StaticDir::new("/assets", dir.path()).precompressed(),
+        )))
+        .unwrap();
+
+        let request = request_with_encoding("/assets/only-plain.css", "br, gzip");
+        let response = router.clone().go(request).await.unwrap();
+        assert!(response.headers().get(header::CONTENT_ENCODING).is_none());
+        assert_eq!(
+            response.headers().get(header::VARY).unwrap(),
+            "Accept-Encoding"
+        );
+        let body = response.into_body().into_bytes().await.unwrap();
+        assert_eq!(body.as_ref(), b"plain css");
+
+        let request = get_request("/assets/app.js");
+        let response = router.clone().go(request).await.unwrap();
+        assert!(response.headers().get(header::CONTENT_ENCODING).is_none());
+        let body = response.into_body().into_bytes().await.unwrap();
+        assert_eq!(body.as_ref(), b"plain");
+    }
+
+    #[tokio::test]
+    async fn ignores_siblings_unless_enabled() {
+        let dir = write_precompressed_fixture();
+        let router = build(Route::new((StaticDir::new("/assets", dir.path()),))).unwrap();
+
+        let request = request_with_encoding("/assets/app.js", "br, gzip");
+        let response = router.clone().go(request).await.unwrap();
+        assert!(response.headers().get(header::CONTENT_ENCODING).is_none());
+        assert!(response.headers().get(header::VARY).is_none());
+        let body = response.into_body().into_bytes().await.unwrap();
+        assert_eq!(body.as_ref(), b"plain");
+    }
+
+    fn write_spa_fixture() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("index.html"), b"<div id=app></div>").unwrap();
+        std::fs::write(dir.path().join("app.js"), b"boot()").unwrap();
+        dir
+    }
+
+    #[tokio::test]
+    async fn spa_fallback_serves_index_for_root() {
+        let dir = write_spa_fixture();
+        let router = build(Route::new((
+            StaticDir::new("/", dir.path()).spa_fallback(true),
+        )))
+        .unwrap();
+
+        let response = router.clone().go(get_request("/")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().into_string().await.unwrap();
+        assert_eq!(body, "<div id=app></div>");
+    }
+
+    #[tokio::test]
+    async fn spa_fallback_serves_index_for_client_routes() {
+        let dir = write_spa_fixture();
+        let router = build(Route::new((
+            StaticDir::new("/", dir.path()).spa_fallback(true),
+        )))
+        .unwrap();
+
+        let response = router
+            .clone()
+            .go(get_request("/deep/client/route"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE).unwrap(),
+            "text/html"
+        );
+        let body = response.into_body().into_string().await.unwrap();
+        assert_eq!(body, "<div id=app></div>");
+
+        let response = router.clone().go(get_request("/app.js")).await.unwrap();
+        let body = response.into_body().into_string().await.unwrap();
+        assert_eq!(body, "boot()");
+    }
+
+    #[tokio::test]
+    async fn spa_fallback_keeps_missing_assets_visible() {
+        let dir = write_spa_fixture();
+        let router = build(Route::new((
+            StaticDir::new("/", dir.path()).spa_fallback(true),
+        )))
+        .unwrap();
+
+        let error = router
+            .clone()
+            .go(get_request("/missing.js"))
+            .await
+            .unwrap_err();
+        assert_eq!(error.status(), StatusCode::NOT_FOUND);
+
+        let error = router
+            .clone()
+            .go(get_request("/deep/../../route"))
+            .await
+            .unwrap_err();
+        assert_eq!(error.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn unknown_paths_still_404_without_spa_fallback() {
+        let dir = write_spa_fixture();
+        let router = build(Route::new((StaticDir::new("/", dir.path()),))).unwrap();
+
+        let error = router
+            .clone()
+            .go(get_request("/deep/client/route"))
+            .await
+            .unwrap_err();
+        assert_eq!(error.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn escapes_html_metacharacters() {
+        assert_eq!(
+            escape_html(r#"<a href="x">'&'</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;&#39;&amp;&#39;&lt;/a&gt;"
+        );
+    }
+
+    #[tokio::test]
+    async fn lists_directories_with_escaped_names() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("zeta")).unwrap();
+        std::fs::write(dir.path().join("<script>alert(1).txt"), b"12345").unwrap();
+        std::fs::write(dir.path().join("alpha.txt"), b"a").unwrap();
+        std::fs::write(dir.path().join(".secret"), b"hidden").unwrap();
+
+        let router = build(Route::new((
+            StaticDir::new("/files", dir.path()).directory_listing(true),
+        )))
+        .unwrap();
+
+        let response = router.clone().go(get_request("/files")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE).unwrap(),
+            "text/html; charset=utf-8"
+        );
+        let body = response.into_body().into_string().await.unwrap();
+        assert!(!body.contains("<script>"));
+        assert!(body.contains("&lt;script&gt;alert(1).txt"));
+        assert!(body.contains("%3Cscript%3Ealert%281%29.txt"));
+        assert!(!body.contains(".secret"));
+        assert!(body.contains("<td>5</td>"));
+
+        let zeta = body.find("zeta/").unwrap();
+        let alpha = body.find("alpha.txt").unwrap();
+        assert!(zeta < alpha, "directories should be listed first");
+    }
+
+    #[tokio::test]
+    async fn listing_shows_hidden_files_when_enabled() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join(".env"), b"x").unwrap();
+
+        let router = build(Route::new((StaticDir::new("/files", dir.path())
+            .directory_listing(true)
+            .show_hidden(true),)))
+        .unwrap();
+
+        let response = router.clone().go(get_request("/files")).await.unwrap();
+        let body = response.into_body().into_string().await.unwrap();
+        assert!(body.contains(".env"));
+    }
+
+    #[tokio::test]
+    async fn listing_prefers_index_file() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("index.html"), b"home").unwrap();
+
+        let router = build(Route::new((
+            StaticDir::new("/files", dir.path()).directory_listing(true),
+        )))
+        .unwrap();
+
+        let response = router.clone().go(get_request("/files")).await.unwrap();
+        let body = response.into_body().into_string().await.unwrap();
+        assert_eq!(body, "home");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn listing_skips_symlinks_escaping_root() {
+        let outside = tempfile::tempdir().unwrap();
+        std::fs::write(outside.path().join("passwd"), b"root:x").unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("inner")).unwrap();
+        std::os::unix::fs::symlink(outside.path(), dir.path().join("escape")).unwrap();
+        std::os::unix::fs::symlink(dir.path().join("inner"), dir.path().join("alias")).unwrap();
+
+        let router = build(Route::new((
+            StaticDir::new("/files", dir.path()).directory_listing(true),
+        )))
+        .unwrap();
+
+        let response = router.clone().go(get_request("/files")).await.unwrap();
+        let body = response.into_body().into_string().await.unwrap();
+        assert!(!body.contains("escape"));
+        assert!(body.contains("alias/"));
+
+        let error = router
+            .clone()
+            .go(get_request("/files/escape"))
+            .await
+            .unwrap_err();
+        assert_eq!(error.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn refuses_files_behind_symlinks_escaping_root() {
+        let outside = tempfile::tempdir().unwrap();
+        std::fs::write(outside.path().join("passwd"), b"root:x").unwrap();
+        std::fs::write(outside.path().join("app.js.br"), b"leaked").unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("app.js"), b"console.log(1)").unwrap();
+        std::fs::create_dir(dir.path().join("inner")).unwrap();
+        std::fs::write(dir.path().join("inner/notes.txt"), b"notes").unwrap();
+        std::os::unix::fs::symlink(outside.path(), dir.path().join("escape")).unwrap();
+        std::os::unix::fs::symlink(outside.path().join("passwd"), dir.path().join("passwd"))
+            .unwrap();
+        std::os::unix::fs::symlink(
+            outside.path().join("app.js.br"),
+            dir.path().join("app.js.br"),
+        )
+        .unwrap();
+        std::os::unix::fs::symlink(dir.path().join("inner"), dir.path().join("alias")).unwrap();
+
+        let router = build(Route::new((
+            StaticDir::new("/files", dir.path()).precompressed(),
+        )))
+        .unwrap();
+
+        for path in ["/files/escape/passwd", "/files/passwd"] {
+            let error = router.clone().go(get_request(path)).await.unwrap_err();
+            assert_eq!(error.status(), StatusCode::NOT_FOUND, "{path}");
+        }
+
+        let response = router
+            .clone()
+            .go(request_with_encoding("/files/app.js", "br"))
+            .await
+            .unwrap();
+        assert!(response.headers().get(header::CONTENT_ENCODING).is_none());
+        let body = response.into_body().into_string().await.unwrap();
+        assert_eq!(body, "console.log(1)");
+
+        let response = router
+            .clone()
+            .go(get_request("/files/alias/notes.txt"))
+            .await
+            .unwrap();
+        let body = response.into_body().into_string().await.unwrap();
+        assert_eq!(body, "notes");
+    }
 }
@@ -0,0 +1,365 @@
+//! In-process testing of routers.
+//!
+//! [`TestClient`] drives a [`Router`] the way the server would, without opening sockets:
+//!
+//! ```
+//! use skyzen::{routing::{CreateRouteNode, Route}, test::TestClient, Result, StatusCode};
+//!
+//! # futures_lite::future::block_on(async {
+//! let router = Route::new(("/ping".at(|| async { Result::Ok("pong") }),)).build();
+//! let client = TestClient::new(router);
+//!
+//! let response = client.get("/ping").send().await;
+//! assert_eq!(response.status(), StatusCode::OK);
+//! assert_eq!(response.text().await, "pong");
+//! # });
+//! ```
+
+use cookie::Cookie;
+use http_kit::{
+    header::{self, HeaderMap, HeaderName, HeaderValue},
+    Body, Endpoint, Method, Request, Response, StatusCode,
+};
+
+use crate::routing::Router;
+
+/// Sends requests to a [`Router`] in-process.
+///
+/// Responses are produced by the router's [`Endpoint`] implementation, so errors are rendered
+/// exactly as a client would see them, including [`Router::on_error`].
+#[derive(Debug, Clone)]
+pub struct TestClient {
+    router: Router,
+}
+
+impl TestClient {
+    /// Create a client for `router`.
+    #[must_use]
+    pub const fn new(router: Router) -> Self {
+        Self { router }
+    }
+
+    /// Start a `GET` request.
+    #[must_use]
+    pub fn get(&self, uri: &str) -> TestRequest {
+        self.request(Method::GET, uri)
+    }
+
+    /// Start a `POST` request.
+    #[must_use]
+    pub fn post(&self, uri: &str) -> TestRequest {
+        self.request(Method::POST, uri)
+    }
+
+    /// Start a `PUT` request.
+    #[must_use]
+    pub fn put(&self, uri: &str) -> TestRequest {
+        self.request(Method::PUT, uri)
+    }
+
+    /// Start a `PATCH` request.
+    #[must_use]
+    pub fn patch(&self, uri: &str) -> TestRequest {
+        self.request(Method::PATCH, uri)
+    }
+
+    /// Start a `DELETE` request.
+    #[must_use]
+    pub fn delete(&self, uri: &str) -> TestRequest {
+        self.request(Method::DELETE, uri)
+    }
+
+    /// Open a WebSocket connection to `uri`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the response if the route did not switch protocols.
+    #[cfg(all(feature = "ws", not(target_arch = "wasm32")))]
+    pub async fn ws(&self, uri: &str) -> Result<crate::websocket::WebSocket, TestResponse> {
+        self.get(uri).ws().await
+    }
+
+    /// Start a request with an arbitrary method.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `uri` is not a valid URI.
+    #[must_use]
+    pub fn request(&self, method: Method, uri: &str) -> TestRequest {
+        let mut request = Request::new(Body::empty());
+        *request.method_mut() = method;
+        *request.uri_mut() = uri.parse().expect("invalid request URI");
+        TestRequest {
+            router: self.router.clone(),
+            request,
+        }
+    }
+}
+
+/// A request being built by [`TestClient`].
+#[derive(Debug)]
+pub struct TestRequest {
+    router: Router,
+    request: Request,
+}
+
+impl TestRequest {
+    /// Append a header.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the name or value is not a valid header.
+    #[must_use]
+    pub fn header<K, V>(mut self, name: K, value: V) -> Self
+    where
+        K: TryInto<HeaderName>,
+        V: TryInto<HeaderValue>,
+    {
+        let name = name.try_into().ok().expect("invalid header name");
+        let value = value.try_into().ok().expect("invalid header value");
+        self.request.headers_mut().append(name, value);
+        self
+    }
+
+    /// Send the cookie `name=value`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the cookie cannot be encoded as a header value.
+    #[must_use]
+    pub fn cookie(self, name: &str, value: &str) -> Self {
+        let cookie = Cookie::new(name, value).encoded().to_string();
+        self.header(header::COOKIE, cookie)
+    }
+
+    /// Use `body` as the request body.
+    #[must_use]
+    pub fn body(mut self, body: impl Into<Body>) -> Self {
+        *self.request.body_mut() = body.into();
+        self
+    }
+
+    /// Send `value` as a JSON body.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `value` cannot be serialized.
+    #[cfg(feature = "json")]
+    #[must_use]
+    pub fn json<T: serde::Serialize>(self, value: &T) -> Self {
+        let body = serde_json::to_vec(value).expect("failed to serialize JSON body");
+        self.header(header::CONTENT_TYPE, "application/json")
+            .body(body)
+    }
+
+    /// Send `value` as a URL-encoded form body.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `value` cannot be serialized.
+    #[cfg(feature = "form")]
+    #[must_use]
+    pub fn form<T: serde::Serialize>(self, value: &T) -> Self {
+        let body = serde_urlencoded::to_string(value).expect("failed to serialize form body");
+        self.header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(body)
+    }
+
+    /// Dispatch the request through the router.
+    pub async fn send(self) -> TestResponse {
+        let Self {
+            mut router,
+            mut request,
+        } = self;
+        let response = router.respond(&mut request).await.unwrap_or_else(|error| {
+            let mut response = Response::new(Body::empty());
+            *response.status_mut() = error.status();
+            response
+        });
+        TestResponse { response }
+    }
+
+    /// Perform a WebSocket handshake with this request and return the client side of the
+    /// connection.
+    ///
+    /// See [`crate::websocket::testing`] for how the connection is driven.
+    ///
+    /// # Errors
+    ///
+    /// Returns the response if the route did not switch protocols.
+    #[cfg(all(feature = "ws", not(target_arch = "wasm32")))]
+    pub async fn ws(self) -> Result<crate::websocket::WebSocket, TestResponse> {
+        crate::websocket::testing::connect_with(&self.router, self.request)
+            .await
+            .map_err(|response| TestResponse { response })
+    }
+}
+
+/// A response received by [`TestClient`].
+#[derive(Debug)]
+pub struct TestResponse {
+    response: Response,
+}
+
+impl TestResponse {
+    /// The response status.
+    #[must_use]
+    pub fn status(&self) -> StatusCode {
+        self.response.status()
+    }
+
+    /// The response headers.
+    #[must_use]
+    pub fn headers(&self) -> &HeaderMap {
+        self.response.headers()
+    }
+
+    /// The first value of header `name`, if it is valid UTF-8.
+    #[must_use]
+    pub fn header(&self, name: impl header::AsHeaderName) -> Option<&str> {
+        self.response
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+    }
+
+    /// The cookie `name` set by the response through `Set-Cookie`.
+    #[must_use]
+    pub fn cookie(&self, name: &str) -> Option<Cookie<'static>> {
+        self.response
+            .headers()
+            .get_all(header::SET_COOKIE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .filter_map(|value| Cookie::parse_encoded(value.to_owned()).ok())
+            .find(|cookie| cookie.name() == name)
+    }
+
+    /// Read the body as bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the body cannot be read.
+    pub async fn bytes(self) -> bytes::Bytes {
+        self.response
+            .into_body()
+            .into_bytes()
+            .await
+            .expect("failed to read response body")
+    }
+
+    /// Read the body as UTF-8 text.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the body cannot be read or is not UTF-8.
+    pub async fn text(self) -> String {
+        String::from_utf8(self.bytes().await.to_vec()).expect("response body is not UTF-8")
+    }
+
+    /// Deserialize the body as JSON.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the body cannot be read or deserialized into `T`.
+    #[cfg(feature = "json")]
+    pub async fn json<T: serde::de::DeserializeOwned>(self) -> T {
+        serde_json::from_slice(&self.bytes().await).expect("failed to deserialize JSON body")
+    }
+
+    /// The underlying response.
+    #[must_use]
+    pub fn into_inner(self) -> Response {
+        self.response
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::TestClient;
+    use crate::{
+        extract::Host,
+        header,
+        routing::{CreateRouteNode, Params, Route},
+        utils::{
+            cookie::{Cookie, CookieJar},
+            Json,
+        },
+        Result, StatusCode,
+    };
+
+    #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+    struct Item {
+        name: String,
+    }
+
+    fn client() -> TestClient {
+        TestClient::new(
+            Route::new((
+                "/items/{id}".at(|params: Params| async move {
+                    Result::Ok(format!("item {}", params.get("id")?))
+                }),
+                "/items".post(|Json(item): Json<Item>| async move { Result::Ok(Json(item)) }),
+                "/host".at(|host: Host| async move { Result::Ok(host.0) }),
+                "/login".post(|mut jar: CookieJar| async move {
+                    jar.add(Cookie::new("session", "abc"));
+                    Result::Ok(jar)
+                }),
+                "/whoami".at(|jar: CookieJar| async move {
+                    Result::Ok(
+                        jar.get("session")
+                            .map_or("anonymous", |c| c.value())
+                            .to_owned(),
+                    )
+                }),
+            ))
+            .build(),
+        )
+    }
+
+    #[tokio::test]
+    async fn sends_requests_and_reads_text() {
+        let response = client().get("/items/7").send().await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.text().await, "item 7");
+
+        let response = client()
+            .get("/host")
+            .header(header::HOST, "example.com")
+            .send()
+            .await;
+        assert_eq!(response.text().await, "example.com");
+
+        let response = client().get("/missing").send().await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn round_trips_json() {
+        let item = Item {
+            name: "widget".to_owned(),
+        };
+        let response = client().post("/items").json(&item).send().await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.header(header::CONTENT_TYPE),
+            Some("application/json")
+        );
+        assert_eq!(response.json::<Item>().await, item);
+    }
+
+    #[tokio::test]
+    async fn sends_and_reads_cookies() {
+        let client = client();
+        let response = client.post("/login").send().await;
+        let session = response.cookie("session").expect("session cookie");
+        assert_eq!(session.value(), "abc");
+
+        let response = client
+            .get("/whoami")
+            .cookie("session", session.value())
+            .send()
+            .await;
+        assert_eq!(response.text().await, "abc");
+    }
+}
@@ -0,0 +1,319 @@
+//! Interoperability with the [tower](https://docs.rs/tower) ecosystem.
+//!
+//! [`Router`] implements `tower::Service`, so it can be wrapped in tower layers or mounted inside
+//! axum and tonic servers, and [`TowerLayer`] runs a tower layer as skyzen middleware.
+//!
+//! Bodies are bridged by streaming: incoming `http_body::Body` types are read through a skyzen
+//! [`Body`], and skyzen bodies implement `http_body::Body` on the way out. Incoming bodies must be
+//! `Unpin`, which holds for hyper's `Incoming` and axum's `Body`.
+
+use std::{
+    convert::Infallible,
+    future::{poll_fn, Future},
+    mem,
+    pin::{pin, Pin},
+    task::{Context, Poll},
+};
+
+use bytes::Bytes;
+use futures_util::future::{select, Either};
+use http_body::Frame;
+use http_kit::{
+    error::BoxHttpError, http_error, middleware::MiddlewareError, Body, BodyError, Endpoint,
+    Middleware, Request, Response, StatusCode,
+};
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::routing::Router;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send>>;
+
+/// Serves the router to tower-based servers and layers.
+///
+/// The service never fails: errors are rendered into responses exactly as the router's
+/// [`Endpoint`] implementation renders them, including [`Router::on_error`]. It is also always
+/// ready, since the router has no capacity of its own to wait for; `poll_ready` returns
+/// `Poll::Ready(Ok(()))` immediately. Put a tower load-shedding or concurrency-limit layer in
+/// front of it to apply backpressure.
+impl<B> Service<http::Request<B>> for Router
+where
+    B: http_body::Body + Unpin + Send + 'static,
+    B::Data: Into<Bytes>,
+    B::Error: Into<BoxError>,
+{
+    type Response = Response;
+    type Error = Infallible;
+    type Future = BoxFuture<Result<Response, Infallible>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: http::Request<B>) -> Self::Future {
+        let mut router = self.clone();
+        let mut request = request.map(into_body);
+        Box::pin(async move {
+            let response = router.respond(&mut request).await.unwrap_or_else(|error| {
+                let mut response = Response::new(Body::empty());
+                *response.status_mut() = error.status();
+                response
+            });
+            Ok(response)
+        })
+    }
+}
+
+/// Runs a tower [`Layer`] as skyzen [`Middleware`].
+///
+/// The layer wraps a [`TowerNext`] service that forwards requests to the rest of the skyzen
+/// chain. Because tower services take requests by value, the request is moved into the layered
+/// service; changes made to it further down the chain are not visible to outer middleware.
+///
+/// ```
+/// use std::time::Duration;
+/// use skyzen::{middleware::TowerLayer, routing::{CreateRouteNode, Route}, Result, StatusCode};
+/// use tower_http::timeout::TimeoutLayer;
+///
+/// let router = Route::new(("/slow".at(|| async { Result::Ok("done") }),))
+///     .middleware(TowerLayer::new(TimeoutLayer::with_status_code(
+///         StatusCode::REQUEST_TIMEOUT,
+///         Duration::from_secs(5),
+///     )))
+///     .build();
+/// ```
+#[derive(Debug, Clone)]
+pub struct TowerLayer<L>(L);
+
+impl<L> TowerLayer<L> {
+    /// Wrap `layer`.
+    #[must_use]
+    pub const fn new(layer: L) -> Self {
+        Self(layer)
+    }
+}
+
+impl<L> Middleware for TowerLayer<L>
+where
+    L: Layer<TowerNext> + Send,
+    L::Service: Service<Request> + Send,
+    <L::Service as Service<Request>>::Response: TowerResponse,
+    <L::Service as Service<Request>>::Error: Into<BoxHttpError>,
+    <L::Service as Service<Request>>::Future: Send,
+{
+    type Error = BoxHttpError;
+
+    async fn handle<N: Endpoint>(
+        &mut self,
+        request: &mut Request,
+        mut next: N,
+    ) -> Result<Response, MiddlewareError<N::Error, Self::Error>> {
+        let (sender, calls) = async_channel::unbounded::<Call>();
+        let mut service = self.0.layer(TowerNext { sender });
+        let request = mem::replace(request, Request::new(Body::empty()));
+
+        let response = pin!(async move {
+            poll_fn(|cx| service.poll_ready(cx))
+                .await
+                .map_err(Into::into)?;
+            service
+                .call(request)
+                .await
+                .map(TowerResponse::into_response)
+                .map_err(Into::into)
+        });
+        let forward = pin!(async {
+            while let Ok((mut request, reply)) = calls.recv().await {
+                let response = next
+                    .respond(&mut request)
+                    .await
+                    .map_err(|error| Box::new(error) as BoxHttpError);
+                let _ = reply.send(response).await;
+            }
+        });
+
+        let response = match select(response, forward).await {
+            Either::Left((response, _)) => response,
+            Either::Right(((), response)) => response.await,
+        };
+        response.map_err(MiddlewareError::Middleware)
+    }
+}
+
+type Call = (
+    Request,
+    async_channel::Sender<Result<Response, BoxHttpError>>,
+);
+
+/// The service a [`TowerLayer`] wraps: it forwards each request to the next skyzen middleware or
+/// endpoint. It is cheap to clone, so layers that retry or buffer work as expected.
+#[derive(Debug, Clone)]
+pub struct TowerNext {
+    sender: async_channel::Sender<Call>,
+}
+
+http_error!(
+    /// A [`TowerNext`] was called after its middleware had returned.
+    pub NextUnavailable,
+    StatusCode::INTERNAL_SERVER_ERROR,
+    "The next endpoint is no longer available to the tower layer"
+);
+
+impl Service<Request> for TowerNext {
+    type Response = Response;
+    type Error = BoxHttpError;
+    type Future = BoxFuture<Result<Response, BoxHttpError>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), BoxHttpError>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        let sender = self.sender.clone();
+        Box::pin(async move {
+            let (reply, response) = async_channel::bounded(1);
+            sender.send((request, reply)).await.map_err(unavailable)?;
+            response.recv().await.map_err(unavailable)?
+        })
+    }
+}
+
+fn unavailable<E>(_: E) -> BoxHttpError {
+    Box::new(NextUnavailable::new())
+}
+
+/// Responses a layered service may produce, converted back into a skyzen [`Response`].
+pub trait TowerResponse {
+    /// Convert into a skyzen response, streaming the body.
+    fn into_response(self) -> Response;
+}
+
+impl<B> TowerResponse for http::Response<B>
+where
+    B: http_body::Body + Unpin + Send + 'static,
+    B::Data: Into<Bytes>,
+    B::Error: Into<BoxError>,
+{
+    fn into_response(self) -> Response {
+        self.map(into_body)
+    }
+}
+
+fn into_body<B>(body: B) -> Body
+where
+    B: http_body::Body + Unpin + Send + 'static,
+    B::Data: Into<Bytes>,
+    B::Error: Into<BoxError>,
+{
+    Body::new(SyncBody(body))
+}
+
+/// Lets a body that is not `Sync`, such as axum's, be stored in a skyzen [`Body`].
+struct SyncBody<B>(B);
+
+// SAFETY: the inner body is only reachable through `poll_frame`, which takes `Pin<&mut Self>`;
+// no method reads it through a shared reference.
+unsafe impl<B: Send> Sync for SyncBody<B> {}
+
+impl<B> http_body::Body for SyncBody<B>
+where
+    B: http_body::Body + Unpin,
+    B::Error: Into<BoxError>,
+{
+    type Data = B::Data;
+    type Error = BodyError;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        Pin::new(&mut self.get_mut().0)
+            .poll_frame(cx)
+            .map_err(|error| BodyError::Other(error.into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use http_kit::StatusCode;
+    use tower::{Layer, ServiceExt};
+    use tower_http::timeout::TimeoutLayer;
+
+    use super::TowerLayer;
+    use crate::{
+        routing::{CreateRouteNode, Route, Router},
+        Result,
+    };
+
+    fn router() -> Router {
+        Route::new((
+            "/fast".post(|body: String| async move { Result::Ok(format!("got {body}")) }),
+            "/slow".at(|| async {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                Result::Ok("done")
+            }),
+        ))
+        .middleware(TowerLayer::new(TimeoutLayer::with_status_code(
+            StatusCode::REQUEST_TIMEOUT,
+            Duration::from_millis(20),
+        )))
+        .build()
+    }
+
+    fn request(method: http::Method, uri: &str, body: &str) -> http::Request<String> {
+        http::Request::builder()
+            .method(method)
+            .uri(uri)
+            .body(body.to_owned())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn serves_requests_as_a_tower_service() {
+        let response = router()
+            .oneshot(request(http::Method::POST, "/fast", "ping"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().into_string().await.unwrap();
+        assert_eq!(body, "got ping");
+
+        let response = router()
+            .oneshot(request(http::Method::GET, "/missing", ""))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn applies_tower_layers_as_middleware() {
+        let response = router()
+            .oneshot(request(http::Method::GET, "/slow", ""))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::REQUEST_TIMEOUT);
+    }
+
+    #[tokio::test]
+    async fn router_can_be_wrapped_in_tower_layers() {
+        let service = TimeoutLayer::with_status_code(
+            StatusCode::SERVICE_UNAVAILABLE,
+            Duration::from_millis(20),
+        )
+        .layer(
+            Route::new(("/slow".at(|| async {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                Result::Ok("done")
+            }),))
+            .build(),
+        );
+        let response = service
+            .oneshot(request(http::Method::GET, "/slow", ""))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+}
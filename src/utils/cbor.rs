@@ -0,0 +1,304 @@
+//! CBOR utilities module.
+//! It provides a CBOR extractor and responder.
+
+use crate::{
+    extract::{BodyReadError, Extractor},
+    header::CONTENT_TYPE,
+    responder::Responder,
+    utils::Bytes,
+    Request, Response, StatusCode,
+};
+use http_kit::header::HeaderValue;
+use http_kit::http_error;
+
+use serde::{de::DeserializeOwned, Serialize};
+
+#[allow(clippy::declare_interior_mutable_const)]
+const APPLICATION_CBOR: HeaderValue = HeaderValue::from_static("application/cbor");
+
+/// CBOR extractor/responder.
+///
+/// Payloads use the same serde representation as [`Json`](super::Json), so a type can be
+/// exchanged in either format.
+#[derive(Debug, Clone)]
+pub struct Cbor<T: Send + Sync + 'static>(pub T);
+
+http_error!(
+    /// An error occurred when encoding the CBOR response.
+    pub CborEncodingError, StatusCode::INTERNAL_SERVER_ERROR, "Failed to encode CBOR response");
+
+impl<T: Send + Sync + Serialize + 'static> Responder for Cbor<T> {
+    type Error = CborEncodingError;
+    fn respond_to(self, _request: &Request, response: &mut Response) -> Result<(), Self::Error> {
+        let mut payload = Vec::new();
+        ciborium::into_writer(&self.0, &mut payload).map_err(|_| CborEncodingError::new())?;
+        response
+            .headers_mut()
+            .insert(CONTENT_TYPE, APPLICATION_CBOR);
+        *response.body_mut() = http_kit::Body::from_bytes(payload);
+        Ok(())
+    }
+
+    #[cfg(feature = "openapi")]
+    fn openapi() -> Option<Vec<crate::openapi::ResponseSchema>> {
+        Some(vec![crate::openapi::ResponseSchema {
+            status: None,
+            description: None,
+            schema: None,
+            content_type: Some("application/cbor"),
+            example: None,
+        }])
+    }
+
+    #[cfg(feature = "openapi")]
+    fn register_openapi_schemas(
+        _defs: &mut std::collections::BTreeMap<String, crate::openapi::SchemaRef>,
+    ) {
+    }
+}
+
+/// Error raised when a CBOR request body cannot be extracted.
+#[skyzen::error]
+pub enum CborContentTypeError {
+    /// The content type header is missing.
+    #[error("Expected content type `application/cbor`", status = StatusCode::BAD_REQUEST)]
+    Missing,
+    /// The content type does not match `application/cbor`.
+    #[error(
+        "Expected content type `application/cbor`",
+        status = StatusCode::UNSUPPORTED_MEDIA_TYPE
+    )]
+    Unsupported,
+    /// The request body could not be read.
+    #[error("Failed to read CBOR payload", status = StatusCode::BAD_REQUEST)]
+    InvalidPayload,
+    /// The request body is larger than the configured [`BodyLimit`](crate::extract::BodyLimit).
+    #[error(
+        "Request body exceeds the limit of {0} bytes",
+        status = StatusCode::PAYLOAD_TOO_LARGE
+    )]
+    TooLarge(usize),
+    /// The payload is not well-formed CBOR.
+    #[error("Cbor deserialize error: {0}", status = StatusCode::BAD_REQUEST)]
+    Syntax(String),
+    /// The payload is well-formed CBOR but does not match the expected type, such as when a
+    /// field is missing or has the wrong type.
+    #[error("Cbor deserialize error: {0}", status = StatusCode::UNPROCESSABLE_ENTITY)]
+    Data(String),
+}
+
+impl<T: Send + Sync + DeserializeOwned + 'static> Extractor for Cbor<T> {
+    type Error = CborContentTypeError;
+    async fn extract(request: &mut Request) -> Result<Self, Self::Error> {
+        if let Some(content_type) = request.headers().get(CONTENT_TYPE) {
+            if !is_cbor_content_type(content_type) {
+                return Err(CborContentTypeError::Unsupported);
+            }
+        } else {
+            return Err(CborContentTypeError::Missing);
+        }
+
+        let bytes = Bytes::extract(request).await.map_err(|error| match error {
+            BodyReadError::TooLarge(limit) => CborContentTypeError::TooLarge(limit),
+            _ => CborContentTypeError::InvalidPayload,
+        })?;
+        from_cbor(&bytes).map(Self)
+    }
+
+    #[cfg(feature = "openapi")]
+    fn openapi() -> Option<crate::openapi::ExtractorSchema> {
+        Some(crate::openapi::ExtractorSchema {
+            content_type: Some("application/cbor"),
+            schema: None,
+            location: crate::openapi::ParameterLocation::Body,
+            required: true,
+            extra_content_types: &[],
+        })
+    }
+
+    #[cfg(feature = "openapi")]
+    fn register_openapi_schemas(
+        _defs: &mut std::collections::BTreeMap<String, crate::openapi::SchemaRef>,
+    ) {
+    }
+}
+
+/// Deserialize `bytes`, naming the offset of malformed input.
+fn from_cbor<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, CborContentTypeError> {
+    use ciborium::de::Error;
+
+    ciborium::from_reader(bytes).map_err(|error| match error {
+        Error::Semantic(Some(offset), message) => {
+            CborContentTypeError::Data(format!("{message} at offset {offset}"))
+        }
+        Error::Semantic(None, message) => CborContentTypeError::Data(message),
+        Error::Syntax(offset) => {
+            CborContentTypeError::Syntax(format!("invalid CBOR at offset {offset}"))
+        }
+        Error::Io(_) => CborContentTypeError::Syntax("unexpected end of input".to_owned()),
+        Error::RecursionLimitExceeded => {
+            CborContentTypeError::Syntax("recursion limit exceeded".to_owned())
+        }
+    })
+}
+
+fn is_cbor_content_type(value: &HeaderValue) -> bool {
+    value
+        .to_str()
+        .ok()
+        .and_then(|raw| raw.split(';').next())
+        .is_some_and(|mime| mime.trim().eq_ignore_ascii_case("application/cbor"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::Cbor;
+    use crate::{
+        extract::BodyLimit,
+        header::{HeaderValue, CONTENT_TYPE},
+        routing::{CreateRouteNode, Route, Router},
+        Body, Endpoint, Method, Request, Result, StatusCode,
+    };
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Reading {
+        sensor: String,
+        values: Vec<u16>,
+    }
+
+    fn router() -> Router {
+        Route::new((
+            "/readings".post(|Cbor(reading): Cbor<Reading>| async move {
+                Result::Ok(format!("{}:{}", reading.sensor, reading.values.len()))
+            }),
+            "/latest".at(|| async {
+                Result::Ok(Cbor(Reading {
+                    sensor: "t1".to_owned(),
+                    values: vec![1, 2, 3],
+                }))
+            }),
+        ))
+        .build()
+    }
+
+    fn request(method: Method, path: &str, content_type: &'static str, body: Vec<u8>) -> Request {
+        let mut request = Request::new(Body::from_bytes(body));
+        *request.method_mut() = method;
+        *request.uri_mut() = path.parse().unwrap();
+        request
+            .headers_mut()
+            .insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+        request
+    }
+
+    fn encode(value: &impl Serialize) -> Vec<u8> {
+        let mut payload = Vec::new();
+        ciborium::into_writer(value, &mut payload).unwrap();
+        payload
+    }
+
+    #[tokio::test]
+    async fn extracts_cbor_bodies() {
+        let body = encode(&Reading {
+            sensor: "t1".to_owned(),
+            values: vec![7, 8],
+        });
+        let response = router()
+            .respond(&mut request(
+                Method::POST,
+                "/readings",
+                "application/cbor",
+                body,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().into_string().await.unwrap();
+        assert_eq!(body.as_str(), "t1:2");
+    }
+
+    #[tokio::test]
+    async fn responds_with_cbor() {
+        let response = router()
+            .respond(&mut request(
+                Method::GET,
+                "/latest",
+                "text/plain",
+                Vec::new(),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.headers()[CONTENT_TYPE], "application/cbor");
+        let body = response.into_body().into_bytes().await.unwrap();
+        let reading: Reading = ciborium::from_reader(&body[..]).unwrap();
+        assert_eq!(
+            reading,
+            Reading {
+                sensor: "t1".to_owned(),
+                values: vec![1, 2, 3],
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_other_content_types() {
+        let body = encode(&Reading {
+            sensor: "t1".to_owned(),
+            values: Vec::new(),
+        });
+        let response = router()
+            .respond(&mut request(
+                Method::POST,
+                "/readings",
+                "application/json",
+                body,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+
+    #[tokio::test]
+    async fn rejects_bodies_over_the_body_limit() {
+        let body = encode(&Reading {
+            sensor: "t1".to_owned(),
+            values: vec![7, 8],
+        });
+        let mut request = request(Method::POST, "/readings", "application/cbor", body);
+        request.extensions_mut().insert(BodyLimit(4));
+        let response = router().respond(&mut request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn reports_mismatched_data_as_unprocessable() {
+        #[derive(Serialize)]
+        struct Partial {
+            sensor: &'static str,
+        }
+
+        let body = encode(&Partial { sensor: "t1" });
+        let response = router()
+            .respond(&mut request(
+                Method::POST,
+                "/readings",
+                "application/cbor",
+                body,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let response = router()
+            .respond(&mut request(
+                Method::POST,
+                "/readings",
+                "application/cbor",
+                vec![0xa2, 0x66],
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -1,5 +1,5 @@
 //! HTTP cookies
-pub use cookie::Cookie;
+pub use cookie::{Cookie, Key};
 use http::StatusCode;
 
 use std::{
@@ -13,6 +13,8 @@ use http_kit::{
 };
 use skyzen_core::{Extractor, Responder};
 
+use crate::utils::{state::StateNotExist, State};
+
 /// A collection of cookies that tracks its modifications.
 #[derive(Debug)]
 pub struct CookieJar(cookie::CookieJar);
@@ -63,6 +65,87 @@ impl Extractor for CookieJar {
     }
 }
 
+/// Errors raised when extracting a [`SignedCookieJar`] or [`PrivateCookieJar`].
+#[skyzen::error]
+pub enum KeyedCookieJarError {
+    /// The `Cookie` header could not be parsed.
+    #[error("Failed to parse cookies", status = StatusCode::BAD_REQUEST)]
+    Parse(#[from] CookieParseError),
+    /// No `State<Key>` was installed.
+    #[error("Missing cookie key state", status = StatusCode::INTERNAL_SERVER_ERROR)]
+    MissingKey(#[from] StateNotExist),
+}
+
+macro_rules! keyed_cookie_jar {
+    ($(#[$meta:meta])* $name:ident, $view:ident, $view_mut:ident) => {
+        $(#[$meta])*
+        #[derive(Debug)]
+        pub struct $name {
+            jar: cookie::CookieJar,
+            key: Key,
+        }
+
+        impl $name {
+            /// Get the verified cookie named `name`.
+            #[must_use]
+            pub fn get(&self, name: &str) -> Option<Cookie<'static>> {
+                self.jar.$view(&self.key).get(name)
+            }
+
+            /// Add a cookie, protecting its value with the key.
+            pub fn add(&mut self, cookie: impl Into<Cookie<'static>>) {
+                self.jar.$view_mut(&self.key).add(cookie);
+            }
+
+            /// Remove a cookie, sending a removal cookie to the client.
+            pub fn remove(&mut self, cookie: impl Into<Cookie<'static>>) {
+                self.jar.$view_mut(&self.key).remove(cookie);
+            }
+        }
+
+        impl Extractor for $name {
+            type Error = KeyedCookieJarError;
+            async fn extract(request: &mut Request) -> Result<Self, Self::Error> {
+                let State(key) = State::<Key>::extract(request).await?;
+                let CookieJar(raw) = CookieJar::extract(request).await?;
+                // Keep only the cookies the key can verify, so tampered ones silently disappear.
+                let mut jar = cookie::CookieJar::new();
+                for cookie in raw.iter() {
+                    if raw.$view(&key).get(cookie.name()).is_some() {
+                        jar.add_original(cookie.clone());
+                    }
+                }
+                Ok(Self { jar, key })
+            }
+        }
+
+        impl Responder for $name {
+            type Error = CookieSetError;
+            fn respond_to(self, request: &Request, response: &mut Response) -> Result<(), Self::Error> {
+                CookieJar(self.jar).respond_to(request, response)
+            }
+        }
+    };
+}
+
+keyed_cookie_jar!(
+    /// A cookie jar whose cookies are signed with the [`Key`] from `State<Key>`.
+    ///
+    /// Clients can read but not modify the values; cookies with an invalid signature are dropped.
+    SignedCookieJar,
+    signed,
+    signed_mut
+);
+
+keyed_cookie_jar!(
+    /// A cookie jar whose cookies are encrypted with the [`Key`] from `State<Key>`.
+    ///
+    /// Clients can neither read nor modify the values; cookies that fail to decrypt are dropped.
+    PrivateCookieJar,
+    private,
+    private_mut
+);
+
 http_error!(
     /// Error occurs when setting cookies to response headers.
     pub CookieSetError, StatusCode::SERVICE_UNAVAILABLE, "Failed to set cookies"
@@ -81,3 +164,119 @@ impl Responder for CookieJar {
         Ok(())
     }
 }
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::{Cookie, Key, PrivateCookieJar, SignedCookieJar};
+    use crate::{
+        header,
+        routing::{CreateRouteNode, Route, Router},
+        utils::State,
+        Body, Method, Request, Result,
+    };
+
+    fn router(key: Key) -> Router {
+        Route::new((
+            "/login".at(|mut jar: PrivateCookieJar| async move {
+                jar.add(Cookie::new("session", "user-42"));
+                Result::Ok(jar)
+            }),
+            "/whoami".at(|jar: PrivateCookieJar| async move {
+                Result::Ok(jar.get("session").map_or_else(
+                    || "anonymous".to_owned(),
+                    |cookie| cookie.value().to_owned(),
+                ))
+            }),
+            "/theme".at(|jar: SignedCookieJar| async move {
+                Result::Ok(
+                    jar.get("theme")
+                        .map_or_else(|| "default".to_owned(), |cookie| cookie.value().to_owned()),
+                )
+            }),
+            "/reset-theme".at(|mut jar: SignedCookieJar| async move {
+                jar.remove(Cookie::from("theme"));
+                Result::Ok(jar)
+            }),
+        ))
+        .middleware(State(key))
+        .build()
+    }
+
+    fn request(path: &str, cookie: Option<&str>) -> Request {
+        let mut request = Request::new(Body::empty());
+        *request.method_mut() = Method::GET;
+        *request.uri_mut() = path.parse().unwrap();
+        if let Some(cookie) = cookie {
+            request
+                .headers_mut()
+                .insert(header::COOKIE, cookie.parse().unwrap());
+        }
+        request
+    }
+
+    async fn body(router: &Router, request: Request) -> String {
+        let response = router.clone().go(request).await.unwrap();
+        response
+            .into_body()
+            .into_string()
+            .await
+            .unwrap()
+            .to_string()
+    }
+
+    fn signed_theme(key: &Key) -> String {
+        let mut jar = cookie::CookieJar::new();
+        jar.signed_mut(key).add(Cookie::new("theme", "dark"));
+        jar.get("theme").unwrap().encoded().to_string()
+    }
+
+    #[tokio::test]
+    async fn private_cookies_round_trip() {
+        let router = router(Key::generate());
+        let response = router.clone().go(request("/login", None)).await.unwrap();
+        let set_cookie = response.headers()[header::SET_COOKIE].to_str().unwrap();
+        let cookie = set_cookie.split(';').next().unwrap().to_owned();
+        assert!(!cookie.contains("user-42"));
+
+        assert_eq!(
+            body(&router, request("/whoami", Some(&cookie))).await,
+            "user-42"
+        );
+        assert_eq!(
+            body(&router, request("/whoami", Some("session=forged"))).await,
+            "anonymous"
+        );
+    }
+
+    #[tokio::test]
+    async fn tampered_signed_cookies_are_dropped() {
+        let key = Key::generate();
+        let signed = signed_theme(&key);
+        let router = router(key);
+        assert_eq!(
+            body(&router, request("/theme", Some(&signed))).await,
+            "dark"
+        );
+
+        let tampered = signed.replace("dark", "lite");
+        assert_eq!(
+            body(&router, request("/theme", Some(&tampered))).await,
+            "default"
+        );
+    }
+
+    #[tokio::test]
+    async fn removals_emit_set_cookie_headers() {
+        let key = Key::generate();
+        let signed = signed_theme(&key);
+        let router = router(key);
+        let response = router
+            .clone()
+            .go(request("/reset-theme", Some(&signed)))
+            .await
+            .unwrap();
+        let set_cookie = response.headers()[header::SET_COOKIE].to_str().unwrap();
+        assert!(set_cookie.starts_with("theme=;"));
+        assert!(set_cookie.contains("Max-Age=0"));
+    }
+}
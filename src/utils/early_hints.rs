@@ -0,0 +1,173 @@
+//! `103 Early Hints` interim responses.
+//!
+//! A handler rendering a page can tell the client which resources it will need before the final
+//! response is ready, so that the browser starts fetching them in the meantime:
+//!
+//! ```
+//! use skyzen::{utils::EarlyHints, Result};
+//!
+//! async fn page(hints: EarlyHints) -> Result<&'static str> {
+//!     hints.links(["</app.css>; rel=preload; as=style"]);
+//!     // Render the page...
+//!     Ok("<!doctype html>")
+//! }
+//! ```
+//!
+//! Interim responses have to be written on the connection by the server itself, which installs
+//! an [`EarlyHints`] handle with [`EarlyHints::new`] in the request extensions. Without one,
+//! sending hints does nothing. Neither the native runtime nor the wasm runtime installs one yet:
+//! hyper has no way for a service to send informational responses, and a wasm `fetch`
+//! handler only returns a final response. Hints are an optimization, so handlers can send them
+//! unconditionally.
+
+use std::{convert::Infallible, fmt, sync::Arc};
+
+use http_kit::header::{HeaderMap, HeaderValue, LINK};
+
+use crate::{extract::Extractor, Request};
+
+type Sender = Arc<dyn Fn(HeaderMap) + Send + Sync>;
+
+/// Sends `103 Early Hints` responses ahead of the final response, see the
+/// [module docs](self).
+///
+/// As an extractor it never fails, and hands out a no-op handle when the server cannot send
+/// interim responses.
+#[derive(Clone, Default)]
+pub struct EarlyHints {
+    sender: Option<Sender>,
+}
+
+impl EarlyHints {
+    /// A handle writing each set of headers as a `103 Early Hints` response, for servers that
+    /// support interim responses.
+    ///
+    /// Insert it into the request extensions before the request reaches the router.
+    pub fn new(send: impl Fn(HeaderMap) + Send + Sync + 'static) -> Self {
+        Self {
+            sender: Some(Arc::new(send)),
+        }
+    }
+
+    /// Whether hints sent through this handle reach the client.
+    #[must_use]
+    pub const fn is_supported(&self) -> bool {
+        self.sender.is_some()
+    }
+
+    /// Send one `103 Early Hints` response with a `Link` header for each of `links`, such as
+    /// `</app.css>; rel=preload; as=style`.
+    ///
+    /// Links that are not valid header values are skipped.
+    pub fn links<'a>(&self, links: impl IntoIterator<Item = &'a str>) {
+        let mut headers = HeaderMap::new();
+        for link in links {
+            if let Ok(value) = HeaderValue::from_str(link) {
+                headers.append(LINK, value);
+            } else {
+                tracing::debug!(link, "ignoring invalid early hint link");
+            }
+        }
+        self.send(headers);
+    }
+
+    /// Send one `103 Early Hints` response with `headers`.
+    ///
+    /// Empty header maps are not sent.
+    pub fn send(&self, headers: HeaderMap) {
+        if let Some(sender) = &self.sender {
+            if !headers.is_empty() {
+                sender(headers);
+            }
+        }
+    }
+}
+
+impl fmt::Debug for EarlyHints {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EarlyHints")
+            .field("supported", &self.is_supported())
+            .finish()
+    }
+}
+
+impl Extractor for EarlyHints {
+    type Error = Infallible;
+    async fn extract(request: &mut Request) -> Result<Self, Self::Error> {
+        Ok(request
+            .extensions()
+            .get::<Self>()
+            .cloned()
+            .unwrap_or_default())
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::EarlyHints;
+    use crate::{
+        header::{HeaderMap, LINK},
+        routing::{CreateRouteNode, Route, Router},
+        Body, Endpoint, Request, Result, StatusCode,
+    };
+
+    fn router() -> Router {
+        Route::new(("/".at(|hints: EarlyHints| async move {
+            hints.links(["</app.css>; rel=preload; as=style", "bad\nlink"]);
+            hints.links(["</app.js>; rel=preload; as=script"]);
+            hints.links([]);
+            Result::Ok("<p>page</p>")
+        }),))
+        .build()
+    }
+
+    async fn get(hints: Option<EarlyHints>) -> (StatusCode, HeaderMap, String) {
+        let mut request = Request::new(Body::empty());
+        *request.uri_mut() = "/".parse().unwrap();
+        if let Some(hints) = hints {
+            request.extensions_mut().insert(hints);
+        }
+        let response = router().respond(&mut request).await.unwrap();
+        let (parts, body) = response.into_parts();
+        let body = body.into_string().await.unwrap().as_str().to_owned();
+        (parts.status, parts.headers, body)
+    }
+
+    #[tokio::test]
+    async fn sends_hints_without_touching_the_final_response() {
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let hints = {
+            let sent = Arc::clone(&sent);
+            EarlyHints::new(move |headers| sent.lock().unwrap().push(headers))
+        };
+
+        let (status, headers, body) = get(Some(hints)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(!headers.contains_key(LINK));
+        assert_eq!(body, "<p>page</p>");
+
+        let sent = std::mem::take(&mut *sent.lock().unwrap());
+        let links = sent
+            .iter()
+            .map(|headers| headers.get_all(LINK).iter().collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            links,
+            [
+                vec!["</app.css>; rel=preload; as=style"],
+                vec!["</app.js>; rel=preload; as=script"],
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn is_a_no_op_without_server_support() {
+        assert!(!EarlyHints::default().is_supported());
+        let (status, headers, body) = get(None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(!headers.contains_key(LINK));
+        assert_eq!(body, "<p>page</p>");
+    }
+}
@@ -42,6 +42,7 @@ impl<T: Send + Sync + Serialize + DeserializeOwned + 'static> Responder for Form
             description: None,
             schema: None,
             content_type: Some("application/x-www-form-urlencoded"),
+            example: None,
         }])
     }
 
@@ -70,6 +71,21 @@ pub enum FormContentTypeError {
     /// The payload could not be parsed as form data.
     #[error("Failed to parse form data", status = StatusCode::BAD_REQUEST)]
     InvalidPayload,
+    /// A `multipart/form-data` payload contains a file, which [`Form`] cannot hold.
+    ///
+    /// This and the other multipart errors are only raised with the `form-multipart` feature.
+    #[error(
+        "Field `{0}` is a file upload; extract the request with `Multipart` to read files",
+        status = StatusCode::BAD_REQUEST
+    )]
+    FileField(String),
+    /// A `multipart/form-data` payload is malformed.
+    #[error("{0}", status = StatusCode::BAD_REQUEST)]
+    Multipart(String),
+    /// A `multipart/form-data` payload exceeds the configured
+    /// `MultipartLimits`.
+    #[error("{0}", status = StatusCode::PAYLOAD_TOO_LARGE)]
+    MultipartTooLarge(String),
 }
 
 impl<T: Send + Sync + DeserializeOwned + 'static> Extractor for Form<T> {
@@ -79,12 +95,16 @@ impl<T: Send + Sync + DeserializeOwned + 'static> Extractor for Form<T> {
             let data = request.uri().query().unwrap_or_default();
             extract(data)
         } else {
-            if let Some(content_type) = request.headers().get(CONTENT_TYPE) {
-                if !is_form_content_type(content_type) {
-                    return Err(FormContentTypeError::Unsupported);
-                }
-            } else {
-                return Err(FormContentTypeError::Missing);
+            let content_type = request
+                .headers()
+                .get(CONTENT_TYPE)
+                .ok_or(FormContentTypeError::Missing)?;
+            #[cfg(feature = "form-multipart")]
+            if has_mime(content_type, "multipart/form-data") {
+                return from_multipart(request).await;
+            }
+            if !has_mime(content_type, "application/x-www-form-urlencoded") {
+                return Err(FormContentTypeError::Unsupported);
             }
 
             let body = core::mem::replace(request.body_mut(), http_kit::Body::empty());
@@ -101,6 +121,13 @@ impl<T: Send + Sync + DeserializeOwned + 'static> Extractor for Form<T> {
         Some(crate::openapi::ExtractorSchema {
             content_type: Some("application/x-www-form-urlencoded"),
             schema: None,
+            location: crate::openapi::ParameterLocation::Body,
+            required: true,
+            extra_content_types: if cfg!(feature = "form-multipart") {
+                &["multipart/form-data"]
+            } else {
+                &[]
+            },
         })
     }
 
@@ -117,21 +144,58 @@ fn extract<T: Send + Sync + DeserializeOwned>(data: &str) -> Result<Form<T>, For
         .map_err(|_| FormContentTypeError::InvalidPayload)
 }
 
+/// Deserialize the text fields of a `multipart/form-data` body as if they were url-encoded.
+#[cfg(feature = "form-multipart")]
+async fn from_multipart<T: Send + Sync + DeserializeOwned>(
+    request: &mut Request,
+) -> Result<Form<T>, FormContentTypeError> {
+    use super::{Multipart, MultipartError};
+
+    fn multipart_error(error: &MultipartError) -> FormContentTypeError {
+        if error.status() == StatusCode::PAYLOAD_TOO_LARGE {
+            FormContentTypeError::MultipartTooLarge(error.to_string())
+        } else {
+            FormContentTypeError::Multipart(error.to_string())
+        }
+    }
+
+    let mut multipart = Multipart::extract(request)
+        .await
+        .map_err(|_| FormContentTypeError::Unsupported)?;
+    let mut pairs = Vec::new();
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|error| multipart_error(&error))?
+    {
+        let name = field.name().unwrap_or_default().to_owned();
+        if field.file_name().is_some() {
+            return Err(FormContentTypeError::FileField(name));
+        }
+        let value = field
+            .text(usize::MAX)
+            .await
+            .map_err(|error| multipart_error(&error))?;
+        pairs.push((name, value));
+    }
+
+    let data =
+        serde_urlencoded::to_string(&pairs).map_err(|_| FormContentTypeError::InvalidPayload)?;
+    extract(&data)
+}
+
 impl_deref!(Form);
 
-fn is_form_content_type(value: &HeaderValue) -> bool {
+/// Whether the content type is `mime`, ignoring parameters such as `charset`.
+fn has_mime(value: &HeaderValue, mime: &str) -> bool {
     value
         .to_str()
         .ok()
         .and_then(|raw| raw.split(';').next())
-        .map(|mime| {
-            mime.trim()
-                .eq_ignore_ascii_case("application/x-www-form-urlencoded")
-        })
-        .unwrap_or(false)
+        .is_some_and(|essence| essence.trim().eq_ignore_ascii_case(mime))
 }
 
-#[cfg(test)]
+#[cfg(all(test, not(target_arch = "wasm32")))]
 mod tests {
     use super::{Form, FormContentTypeError};
     use crate::{Body, Method};
@@ -222,4 +286,78 @@ mod tests {
             }
         );
     }
+
+    #[cfg(feature = "form-multipart")]
+    const BOUNDARY: &str = "----WebKitFormBoundaryd2rQ8Zs1jDgfVxLq";
+
+    /// A form as Chrome submits it: quoted boundary and names, and a CRLF after the terminator.
+    #[cfg(feature = "form-multipart")]
+    fn browser_multipart(extra_part: &str) -> Request {
+        let boundary = BOUNDARY;
+        let body = format!(
+            "--{boundary}\r\n\
+             Content-Disposition: form-data; name=\"name\"\r\n\r\n\
+             Lexo\r\n\
+             --{boundary}\r\n\
+             Content-Disposition: form-data; name=\"age\"\r\n\r\n\
+             17\r\n\
+             --{boundary}\r\n\
+             Content-Disposition: form-data; name=\"display name\"\r\n\r\n\
+             L & \"x\"=1\r\n\
+             {extra_part}\
+             --{boundary}--\r\n"
+        );
+        let mut request = Request::new(Body::from_bytes(body));
+        *request.method_mut() = Method::POST;
+        request.headers_mut().insert(
+            CONTENT_TYPE,
+            http_kit::header::HeaderValue::from_str(&format!(
+                "multipart/form-data; boundary=\"{boundary}\""
+            ))
+            .unwrap(),
+        );
+        request
+    }
+
+    #[cfg(feature = "form-multipart")]
+    #[tokio::test]
+    async fn reads_text_fields_of_multipart_forms() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Profile {
+            name: String,
+            age: u8,
+            #[serde(rename = "display name")]
+            display_name: String,
+        }
+
+        let mut request = browser_multipart("");
+        let Form(profile) = Form::<Profile>::extract(&mut request)
+            .await
+            .expect("multipart form should parse");
+        assert_eq!(
+            profile,
+            Profile {
+                name: "Lexo".to_string(),
+                age: 17,
+                display_name: "L & \"x\"=1".to_string(),
+            }
+        );
+    }
+
+    #[cfg(feature = "form-multipart")]
+    #[tokio::test]
+    async fn rejects_file_fields_in_multipart_forms() {
+        use crate::{HttpError, StatusCode};
+
+        let mut request = browser_multipart(&format!(
+            "--{BOUNDARY}\r\n\
+             Content-Disposition: form-data; name=\"avatar\"; filename=\"me.png\"\r\n\
+             Content-Type: image/png\r\n\r\n\
+             \u{89}PNG\r\n"
+        ));
+        let error = Form::<Payload>::extract(&mut request).await.unwrap_err();
+        assert!(matches!(&error, FormContentTypeError::FileField(name) if name == "avatar"));
+        assert_eq!(error.status(), StatusCode::BAD_REQUEST);
+        assert!(error.to_string().contains("`Multipart`"), "{error}");
+    }
 }
@@ -0,0 +1,55 @@
+//! Editing of list-valued response headers, shared by middleware and responders.
+
+use crate::header::{HeaderMap, HeaderValue, VARY};
+
+/// Add `name` to the `Vary` header unless it is already listed.
+///
+/// Names are compared case-insensitively, and `name` is dropped if it is not a valid header value.
+pub fn append_vary(headers: &mut HeaderMap, name: &str) {
+    let Ok(name_value) = HeaderValue::from_str(name) else {
+        return;
+    };
+    let Some(value) = headers.get_mut(VARY) else {
+        headers.insert(VARY, name_value);
+        return;
+    };
+    let Ok(existing) = value.to_str() else {
+        *value = name_value;
+        return;
+    };
+    if existing
+        .split(',')
+        .any(|segment| segment.trim().eq_ignore_ascii_case(name))
+    {
+        return;
+    }
+
+    let existing = existing.trim();
+    let combined = if existing.is_empty() {
+        name.to_owned()
+    } else {
+        format!("{existing}, {name}")
+    };
+    if let Ok(updated) = HeaderValue::from_str(&combined) {
+        *value = updated;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::append_vary;
+    use crate::header::{HeaderMap, HeaderValue, VARY};
+
+    #[test]
+    fn appends_to_vary_once() {
+        let mut headers = HeaderMap::new();
+        append_vary(&mut headers, "Accept");
+        append_vary(&mut headers, "Accept");
+        assert_eq!(headers[VARY], "Accept");
+
+        headers.insert(VARY, HeaderValue::from_static("Accept-Language"));
+        append_vary(&mut headers, "Accept");
+        append_vary(&mut headers, "accept-language");
+        assert_eq!(headers[VARY], "Accept-Language, Accept");
+    }
+}
@@ -1,19 +1,22 @@
 //! JSON utilities module.
 //! It provides JSON extractor and responder.
 
+use std::{borrow::Cow, convert::Infallible, fmt::Write};
+
 use crate::{
-    extract::Extractor, header::CONTENT_TYPE, responder::Responder, Request, Response, StatusCode,
+    extract::{BodyReadError, Extractor},
+    header::CONTENT_TYPE,
+    responder::Responder,
+    utils::Bytes,
+    Request, Response, StatusCode,
 };
 use http_kit::header::HeaderValue;
-use http_kit::http_error;
+use http_kit::{http_error, middleware::MiddlewareError, Endpoint, Middleware};
 pub use serde_json::json;
 pub use serde_json::Value as JsonValue;
 
 use serde::{de::DeserializeOwned, Serialize};
 
-#[allow(clippy::declare_interior_mutable_const)]
-const APPLICATION_JSON: HeaderValue = HeaderValue::from_static("application/json");
-
 /// JSON extractor/responder.
 #[derive(Debug, Clone)]
 pub struct Json<T: Send + Sync + 'static = JsonValue>(pub T);
@@ -24,13 +27,8 @@ http_error!(
 
 impl<T: Send + Sync + Serialize + 'static> Responder for Json<T> {
     type Error = JsonEncodingError;
-    fn respond_to(self, _request: &Request, response: &mut Response) -> Result<(), Self::Error> {
-        response
-            .headers_mut()
-            .insert(CONTENT_TYPE, APPLICATION_JSON);
-        *response.body_mut() =
-            http_kit::Body::from_json(&self.0).map_err(|_| JsonEncodingError::new())?;
-        Ok(())
+    fn respond_to(self, request: &Request, response: &mut Response) -> Result<(), Self::Error> {
+        write_json(request, response, &self.0, false).map_err(|_| JsonEncodingError::new())
     }
 
     #[cfg(feature = "openapi")]
@@ -40,6 +38,7 @@ impl<T: Send + Sync + Serialize + 'static> Responder for Json<T> {
             description: None,
             schema: None,
             content_type: Some("application/json"),
+            example: None,
         }])
     }
 
@@ -50,6 +49,144 @@ impl<T: Send + Sync + Serialize + 'static> Responder for Json<T> {
     }
 }
 
+/// Configure how [`Json`] and [`PrettyJson`](crate::responder::PrettyJson) write responses.
+///
+/// Add it as a middleware to apply it to every route below. Without it, `Json` writes compact
+/// JSON and `PrettyJson` indented JSON, both as plain `application/json`.
+///
+/// ```
+/// use skyzen::{routing::{CreateRouteNode, Route}, utils::{json, Json, JsonConfig, JsonValue}};
+///
+/// async fn user() -> Json<JsonValue> {
+///     Json(json!({ "name": "Zoë" }))
+/// }
+///
+/// // `GET /user?pretty` answers with indented JSON.
+/// let route = Route::new(("/user".at(user),)).middleware(JsonConfig::new().pretty_query_param("pretty"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct JsonConfig {
+    pretty_query_param: Option<Cow<'static, str>>,
+    escape_non_ascii: bool,
+    charset: bool,
+}
+
+impl JsonConfig {
+    /// Create the default configuration.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            pretty_query_param: None,
+            escape_non_ascii: false,
+            charset: false,
+        }
+    }
+
+    /// Indent JSON responses whose request has the query parameter `name`, such as `?pretty` or
+    /// `?pretty=1`.
+    ///
+    /// `?pretty=0` and `?pretty=false` leave the response compact.
+    #[must_use]
+    pub fn pretty_query_param(mut self, name: impl Into<Cow<'static, str>>) -> Self {
+        self.pretty_query_param = Some(name.into());
+        self
+    }
+
+    /// Write non-ASCII characters in strings as `\uXXXX` escapes, for clients that do not decode
+    /// UTF-8.
+    #[must_use]
+    pub const fn escape_non_ascii(mut self, enabled: bool) -> Self {
+        self.escape_non_ascii = enabled;
+        self
+    }
+
+    /// Send `Content-Type: application/json; charset=utf-8` instead of `application/json`.
+    #[must_use]
+    pub const fn charset(mut self, enabled: bool) -> Self {
+        self.charset = enabled;
+        self
+    }
+
+    /// Whether the query of `request` asks for indented JSON.
+    fn pretty_requested(&self, request: &Request) -> bool {
+        let (Some(name), Some(query)) = (&self.pretty_query_param, request.uri().query()) else {
+            return false;
+        };
+        query
+            .split('&')
+            .map(|pair| pair.split_once('=').unwrap_or((pair, "")))
+            .rfind(|(key, _)| key == name)
+            .is_some_and(|(_, value)| !matches!(value, "0" | "false"))
+    }
+}
+
+impl Middleware for JsonConfig {
+    type Error = Infallible;
+    async fn handle<N: Endpoint>(
+        &mut self,
+        request: &mut Request,
+        mut next: N,
+    ) -> Result<Response, MiddlewareError<N::Error, Self::Error>> {
+        request.extensions_mut().insert(self.clone());
+        next.respond(request)
+            .await
+            .map_err(MiddlewareError::Endpoint)
+    }
+}
+
+/// Serialize `value` into `response` as configured by the [`JsonConfig`] of `request`, indented
+/// if `pretty` or if the query asks for it.
+pub(crate) fn write_json<T: Serialize>(
+    request: &Request,
+    response: &mut Response,
+    value: &T,
+    pretty: bool,
+) -> serde_json::Result<()> {
+    const DEFAULT: &JsonConfig = &JsonConfig::new();
+    let config = request.extensions().get::<JsonConfig>().unwrap_or(DEFAULT);
+    let mut json = if pretty || config.pretty_requested(request) {
+        serde_json::to_vec_pretty(value)?
+    } else {
+        serde_json::to_vec(value)?
+    };
+    if config.escape_non_ascii {
+        json = escape_non_ascii(json);
+    }
+    let content_type = if config.charset {
+        "application/json; charset=utf-8"
+    } else {
+        "application/json"
+    };
+    response
+        .headers_mut()
+        .insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+    *response.body_mut() = http_kit::Body::from_bytes(json);
+    Ok(())
+}
+
+/// Replace the non-ASCII characters of serialized JSON with `\uXXXX` escapes.
+///
+/// Outside of strings JSON is pure ASCII, so every such character is inside a string.
+fn escape_non_ascii(json: Vec<u8>) -> Vec<u8> {
+    let json = match String::from_utf8(json) {
+        Ok(json) if !json.is_ascii() => json,
+        Ok(json) => return json.into_bytes(),
+        Err(error) => return error.into_bytes(),
+    };
+    let mut escaped = String::with_capacity(json.len() + json.len() / 2);
+    for c in json.chars() {
+        if c.is_ascii() {
+            escaped.push(c);
+        } else {
+            for unit in c.encode_utf16(&mut [0; 2]) {
+                // Writing into a `String` cannot fail.
+                let _ = write!(escaped, "\\u{unit:04x}");
+            }
+        }
+    }
+    escaped.into_bytes()
+}
+
 /// Error raised when the content-type header is not `application/json`.
 #[skyzen::error]
 pub enum JsonContentTypeError {
@@ -62,9 +199,22 @@ pub enum JsonContentTypeError {
         status = StatusCode::UNSUPPORTED_MEDIA_TYPE
     )]
     Unsupported,
-    /// The payload could not be parsed as JSON.
-    #[error("Failed to parse JSON payload", status = StatusCode::BAD_REQUEST)]
+    /// The request body could not be read.
+    #[error("Failed to read JSON payload", status = StatusCode::BAD_REQUEST)]
     InvalidPayload,
+    /// The request body is larger than the configured [`BodyLimit`](crate::extract::BodyLimit).
+    #[error(
+        "Request body exceeds the limit of {0} bytes",
+        status = StatusCode::PAYLOAD_TOO_LARGE
+    )]
+    TooLarge(usize),
+    /// The payload is not well-formed JSON.
+    #[error("Json deserialize error: {0}", status = StatusCode::BAD_REQUEST)]
+    Syntax(String),
+    /// The payload is well-formed JSON but does not match the expected type, such as when a
+    /// field is missing or has the wrong type.
+    #[error("Json deserialize error: {0}", status = StatusCode::UNPROCESSABLE_ENTITY)]
+    Data(String),
 }
 
 impl<T: Send + Sync + DeserializeOwned + 'static> Extractor for Json<T> {
@@ -78,12 +228,11 @@ impl<T: Send + Sync + DeserializeOwned + 'static> Extractor for Json<T> {
             return Err(JsonContentTypeError::Missing);
         }
 
-        let value = request
-            .body_mut()
-            .into_json()
-            .await
-            .map_err(|_| JsonContentTypeError::InvalidPayload)?;
-        Ok(Self(value))
+        let bytes = Bytes::extract(request).await.map_err(|error| match error {
+            BodyReadError::TooLarge(limit) => JsonContentTypeError::TooLarge(limit),
+            _ => JsonContentTypeError::InvalidPayload,
+        })?;
+        from_json(&bytes).map(Self)
     }
 
     #[cfg(feature = "openapi")]
@@ -91,6 +240,9 @@ impl<T: Send + Sync + DeserializeOwned + 'static> Extractor for Json<T> {
         Some(crate::openapi::ExtractorSchema {
             content_type: Some("application/json"),
             schema: None,
+            location: crate::openapi::ParameterLocation::Body,
+            required: true,
+            extra_content_types: &[],
         })
     }
 
@@ -101,19 +253,97 @@ impl<T: Send + Sync + DeserializeOwned + 'static> Extractor for Json<T> {
     }
 }
 
+/// Deserialize `bytes`, naming the line, column and path of the first problem.
+#[cfg(not(feature = "simd-json"))]
+fn from_json<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, JsonContentTypeError> {
+    serde_json::from_slice(bytes).map_err(|error| {
+        let path = failing_path::<T, _>(&mut serde_json::Deserializer::from_slice(bytes));
+        let message = with_path(error.to_string(), path);
+        if error.classify() == serde_json::error::Category::Data {
+            JsonContentTypeError::Data(message)
+        } else {
+            JsonContentTypeError::Syntax(message)
+        }
+    })
+}
+
+/// Deserialize `bytes` with simd-json, which parses a mutable copy of the buffered body in place.
+#[cfg(feature = "simd-json")]
+fn from_json<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, JsonContentTypeError> {
+    // Building the tape validates the syntax, so later errors are about the shape of the data.
+    let mut buffer = bytes.to_vec();
+    let mut deserializer = simd_json::Deserializer::from_slice(&mut buffer)
+        .map_err(|error| JsonContentTypeError::Syntax(simd_message(&error, Some(bytes))))?;
+    T::deserialize(&mut deserializer).map_err(|error| {
+        let mut buffer = bytes.to_vec();
+        let path = simd_json::Deserializer::from_slice(&mut buffer)
+            .ok()
+            .and_then(|mut deserializer| failing_path::<T, _>(&mut deserializer));
+        JsonContentTypeError::Data(with_path(simd_message(&error, None), path))
+    })
+}
+
+/// Describe a simd-json error like `serde_json` does, locating it by line and column in `input`.
+///
+/// Errors raised while walking the tape carry no meaningful offset, so they are not located.
+#[cfg(feature = "simd-json")]
+fn simd_message(error: &simd_json::Error, input: Option<&[u8]>) -> String {
+    if let simd_json::ErrorType::Serde(message) = error.error() {
+        return message.clone();
+    }
+    let Some(input) = input else {
+        return format!("{:?}", error.error());
+    };
+    let offset = error.index().min(input.len());
+    let before = &input[..offset];
+    let line = before.split(|byte| *byte == b'\n').count();
+    let column = offset
+        - before
+            .iter()
+            .rposition(|byte| *byte == b'\n')
+            .map_or(0, |i| i + 1)
+        + 1;
+    format!("{:?} at line {line} column {column}", error.error())
+}
+
+/// Deserialize again while tracking the path, to find the field that failed.
+///
+/// Tracking slows down every field, so it only runs once a payload is known to be invalid.
+fn failing_path<'de, T, D>(deserializer: D) -> Option<String>
+where
+    T: serde::Deserialize<'de>,
+    D: serde::Deserializer<'de>,
+{
+    let error = serde_path_to_error::deserialize::<_, T>(deserializer).err()?;
+    let path = error.path().to_string();
+    (path != ".").then_some(path)
+}
+
+fn with_path(message: String, path: Option<String>) -> String {
+    match path {
+        Some(path) => format!("{message} in `{path}`"),
+        None => message,
+    }
+}
+
 fn is_json_content_type(value: &HeaderValue) -> bool {
     value
         .to_str()
         .ok()
         .and_then(|raw| raw.split(';').next())
-        .map(|mime| mime.trim().eq_ignore_ascii_case("application/json"))
-        .unwrap_or(false)
+        .is_some_and(|mime| mime.trim().eq_ignore_ascii_case("application/json"))
 }
 
-#[cfg(test)]
+#[cfg(all(test, not(target_arch = "wasm32")))]
 mod test {
-    use super::Json;
-    use crate::{Body, Method, StatusCode};
+    use super::{escape_non_ascii, json, Json, JsonConfig, JsonValue};
+    use crate::{
+        extract::BodyLimit,
+        responder::PrettyJson,
+        routing::{CreateRouteNode, Route},
+        test::TestClient,
+        Body, Method, StatusCode,
+    };
     use http_kit::{header::CONTENT_TYPE, HttpError, Request};
     use serde::Deserialize;
     use skyzen_core::Extractor;
@@ -144,6 +374,19 @@ mod test {
         assert!(payload.ok);
     }
 
+    #[tokio::test]
+    async fn rejects_bodies_over_the_body_limit() {
+        let mut request = request_with_body(br#"{"ok":true}"#);
+        request.headers_mut().insert(
+            CONTENT_TYPE,
+            http_kit::header::HeaderValue::from_static("application/json"),
+        );
+        request.extensions_mut().insert(BodyLimit(4));
+
+        let error = Json::<Payload>::extract(&mut request).await.unwrap_err();
+        assert_eq!(error.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
     #[tokio::test]
     async fn rejects_non_json_content_type() {
         let mut request = request_with_body(br#"{"ok":true}"#);
@@ -156,6 +399,67 @@ mod test {
         assert_eq!(error.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
     }
 
+    fn json_request(body: &'static [u8]) -> Request {
+        let mut request = request_with_body(body);
+        request.headers_mut().insert(
+            CONTENT_TYPE,
+            http_kit::header::HeaderValue::from_static("application/json"),
+        );
+        request
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Event {
+        #[allow(dead_code)]
+        user: User,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct User {
+        #[allow(dead_code)]
+        user_id: u64,
+    }
+
+    #[tokio::test]
+    async fn reports_missing_fields_as_unprocessable() {
+        let mut request = json_request(b"{\n  \"user\": {\n    \"name\": \"ada\"\n  }\n}");
+        let error = Json::<Event>::extract(&mut request).await.unwrap_err();
+        assert_eq!(error.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let message = error.to_string();
+        assert!(
+            message.starts_with("Json deserialize error: missing field `user_id`"),
+            "{message}"
+        );
+        assert!(message.ends_with(" in `user`"), "{message}");
+        #[cfg(not(feature = "simd-json"))]
+        assert!(message.contains("at line 4 column 3"), "{message}");
+    }
+
+    #[tokio::test]
+    async fn reports_wrong_types_with_their_path() {
+        let mut request = json_request(br#"{"user": {"user_id": "seven"}}"#);
+        let error = Json::<Event>::extract(&mut request).await.unwrap_err();
+        assert_eq!(error.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(error.to_string().contains("`user.user_id`"), "{error}");
+    }
+
+    #[tokio::test]
+    async fn reports_syntax_errors_as_bad_requests() {
+        for body in [
+            &b"{\"ok\": tru}"[..],
+            b"{\"ok\": true",
+            b"{\"ok\": true} {}",
+        ] {
+            let mut request = json_request(body);
+            let error = Json::<Payload>::extract(&mut request).await.unwrap_err();
+            assert_eq!(error.status(), StatusCode::BAD_REQUEST, "{error}");
+            assert!(
+                error.to_string().starts_with("Json deserialize error: "),
+                "{error}"
+            );
+        }
+    }
+
     #[tokio::test]
     async fn rejects_missing_content_type() {
         let mut request = request_with_body(br#"{"ok":true}"#);
@@ -163,6 +467,74 @@ mod test {
         assert_eq!(error.status(), StatusCode::BAD_REQUEST);
     }
 
+    fn configured_client(config: Option<JsonConfig>) -> TestClient {
+        async fn user() -> Json<JsonValue> {
+            Json(json!({ "name": "Zoë" }))
+        }
+
+        async fn pretty_user() -> PrettyJson<JsonValue> {
+            PrettyJson(json!({ "name": "Zoë" }))
+        }
+
+        let route = Route::new(("/user".at(user), "/pretty".at(pretty_user)));
+        let route = match config {
+            Some(config) => route.middleware(config),
+            None => route,
+        };
+        TestClient::new(route.build())
+    }
+
+    #[tokio::test]
+    async fn keeps_default_output_without_config() {
+        let client = configured_client(None);
+        let response = client.get("/user?pretty").send().await;
+        assert_eq!(response.header(CONTENT_TYPE), Some("application/json"));
+        assert_eq!(response.text().await, r#"{"name":"Zoë"}"#);
+
+        let response = client.get("/pretty").send().await;
+        assert_eq!(response.header(CONTENT_TYPE), Some("application/json"));
+        assert_eq!(response.text().await, "{\n  \"name\": \"Zoë\"\n}");
+    }
+
+    #[tokio::test]
+    async fn indents_when_the_query_parameter_is_present() {
+        let client = configured_client(Some(JsonConfig::new().pretty_query_param("pretty")));
+        for uri in ["/user?pretty", "/user?pretty=1", "/user?page=2&pretty=true"] {
+            let response = client.get(uri).send().await;
+            assert_eq!(response.text().await, "{\n  \"name\": \"Zoë\"\n}", "{uri}");
+        }
+        for uri in [
+            "/user",
+            "/user?pretty=0",
+            "/user?pretty=false",
+            "/user?prettyish",
+        ] {
+            let response = client.get(uri).send().await;
+            assert_eq!(response.text().await, r#"{"name":"Zoë"}"#, "{uri}");
+        }
+    }
+
+    #[tokio::test]
+    async fn escapes_non_ascii_and_sets_charset() {
+        let config = JsonConfig::new().escape_non_ascii(true).charset(true);
+        let client = configured_client(Some(config));
+        let response = client.get("/user").send().await;
+        assert_eq!(
+            response.header(CONTENT_TYPE),
+            Some("application/json; charset=utf-8")
+        );
+        assert_eq!(response.text().await, r#"{"name":"Zo\u00eb"}"#);
+
+        let response = client.get("/pretty").send().await;
+        assert_eq!(response.text().await, "{\n  \"name\": \"Zo\\u00eb\"\n}");
+    }
+
+    #[test]
+    fn escapes_characters_outside_the_basic_plane_as_surrogate_pairs() {
+        let json = serde_json::to_vec(&"a🦀").unwrap();
+        assert_eq!(escape_non_ascii(json), br#""a\ud83e\udd80""#);
+    }
+
     /* use super::Json;
     use http_kit::Request;
     use serde::{Deserialize, Serialize};
@@ -1,31 +1,71 @@
 #[cfg(feature = "json")]
 pub mod json;
 #[cfg(feature = "json")]
-pub use json::{json, Json, JsonValue};
+pub use json::{json, Json, JsonConfig, JsonValue};
+
+#[cfg(feature = "cbor")]
+pub mod cbor;
+#[cfg(feature = "cbor")]
+pub use cbor::Cbor;
+
+#[cfg(feature = "msgpack")]
+pub mod msgpack;
+#[cfg(feature = "msgpack")]
+pub use msgpack::MsgPack;
+
+#[cfg(any(feature = "json", feature = "compression", not(target_arch = "wasm32")))]
+pub(crate) mod negotiation;
+
+pub(crate) mod header_util;
+
+pub(crate) mod percent;
 
 #[cfg(feature = "form")]
 pub mod form;
 #[cfg(feature = "form")]
 pub use form::Form;
 
+#[cfg(all(feature = "json", feature = "form"))]
+pub mod payload;
+#[cfg(all(feature = "json", feature = "form"))]
+pub use payload::Payload;
+
 #[cfg(feature = "multipart")]
 pub mod multipart;
 #[cfg(feature = "multipart")]
-pub use multipart::{Field, Multipart, MultipartBoundaryError, MultipartError};
+pub use multipart::{Field, Multipart, MultipartBoundaryError, MultipartError, MultipartLimits};
+#[cfg(all(feature = "multipart", not(target_arch = "wasm32")))]
+pub use multipart::{MultipartForm, TempFileField};
 
 pub mod state;
 pub use state::State;
 
 pub mod cookie;
 
+pub mod early_hints;
+pub use early_hints::EarlyHints;
+
+#[cfg(all(feature = "proxy", not(target_arch = "wasm32")))]
+pub mod proxy;
+#[cfg(all(feature = "proxy", not(target_arch = "wasm32")))]
+pub use proxy::Proxy;
+
 /// Error types
 pub mod error {
+    #[cfg(feature = "cbor")]
+    pub use super::cbor::CborContentTypeError;
     #[cfg(feature = "form")]
     pub use super::form::FormContentTypeError;
     #[cfg(feature = "json")]
     pub use super::json::JsonContentTypeError;
+    #[cfg(feature = "msgpack")]
+    pub use super::msgpack::MsgPackContentTypeError;
     #[cfg(feature = "multipart")]
     pub use super::multipart::MultipartBoundaryError;
+    #[cfg(all(feature = "json", feature = "form"))]
+    pub use super::payload::PayloadError;
+    #[cfg(all(feature = "proxy", not(target_arch = "wasm32")))]
+    pub use super::proxy::ProxyError;
     pub use super::state::StateNotExist;
 }
 
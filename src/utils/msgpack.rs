@@ -0,0 +1,301 @@
+//! Msgpack utilities module.
+//! It provides a msgpack extractor and responder.
+
+use crate::{
+    extract::{BodyReadError, Extractor},
+    header::CONTENT_TYPE,
+    responder::Responder,
+    utils::Bytes,
+    Request, Response, StatusCode,
+};
+use http_kit::header::HeaderValue;
+use http_kit::http_error;
+
+use serde::{de::DeserializeOwned, Serialize};
+
+#[allow(clippy::declare_interior_mutable_const)]
+const APPLICATION_MSGPACK: HeaderValue = HeaderValue::from_static("application/msgpack");
+
+/// Media types accepted for msgpack request bodies, besides the registered one.
+const LEGACY_MSGPACK: [&str; 2] = ["application/x-msgpack", "application/vnd.msgpack"];
+
+/// msgpack extractor/responder.
+///
+/// Structs are encoded as maps keyed by field name, the same serde representation as
+/// [`Json`](super::Json), so a type can be exchanged in either format.
+#[derive(Debug, Clone)]
+pub struct MsgPack<T: Send + Sync + 'static>(pub T);
+
+http_error!(
+    /// An error occurred when encoding the msgpack response.
+    pub MsgPackEncodingError, StatusCode::INTERNAL_SERVER_ERROR, "Failed to encode MessagePack response");
+
+impl<T: Send + Sync + Serialize + 'static> Responder for MsgPack<T> {
+    type Error = MsgPackEncodingError;
+    fn respond_to(self, _request: &Request, response: &mut Response) -> Result<(), Self::Error> {
+        let payload = rmp_serde::to_vec_named(&self.0).map_err(|_| MsgPackEncodingError::new())?;
+        response
+            .headers_mut()
+            .insert(CONTENT_TYPE, APPLICATION_MSGPACK);
+        *response.body_mut() = http_kit::Body::from_bytes(payload);
+        Ok(())
+    }
+
+    #[cfg(feature = "openapi")]
+    fn openapi() -> Option<Vec<crate::openapi::ResponseSchema>> {
+        Some(vec![crate::openapi::ResponseSchema {
+            status: None,
+            description: None,
+            schema: None,
+            content_type: Some("application/msgpack"),
+            example: None,
+        }])
+    }
+
+    #[cfg(feature = "openapi")]
+    fn register_openapi_schemas(
+        _defs: &mut std::collections::BTreeMap<String, crate::openapi::SchemaRef>,
+    ) {
+    }
+}
+
+/// Error raised when a msgpack request body cannot be extracted.
+#[skyzen::error]
+pub enum MsgPackContentTypeError {
+    /// The content type header is missing.
+    #[error("Expected content type `application/msgpack`", status = StatusCode::BAD_REQUEST)]
+    Missing,
+    /// The content type does not match `application/msgpack`.
+    #[error(
+        "Expected content type `application/msgpack`",
+        status = StatusCode::UNSUPPORTED_MEDIA_TYPE
+    )]
+    Unsupported,
+    /// The request body could not be read.
+    #[error("Failed to read MessagePack payload", status = StatusCode::BAD_REQUEST)]
+    InvalidPayload,
+    /// The request body is larger than the configured [`BodyLimit`](crate::extract::BodyLimit).
+    #[error(
+        "Request body exceeds the limit of {0} bytes",
+        status = StatusCode::PAYLOAD_TOO_LARGE
+    )]
+    TooLarge(usize),
+    /// The payload is not well-formed msgpack.
+    #[error("MsgPack deserialize error: {0}", status = StatusCode::BAD_REQUEST)]
+    Syntax(String),
+    /// The payload is well-formed msgpack but does not match the expected type, such as when a
+    /// field is missing or has the wrong type.
+    #[error("MsgPack deserialize error: {0}", status = StatusCode::UNPROCESSABLE_ENTITY)]
+    Data(String),
+}
+
+impl<T: Send + Sync + DeserializeOwned + 'static> Extractor for MsgPack<T> {
+    type Error = MsgPackContentTypeError;
+    async fn extract(request: &mut Request) -> Result<Self, Self::Error> {
+        if let Some(content_type) = request.headers().get(CONTENT_TYPE) {
+            if !is_msgpack_content_type(content_type) {
+                return Err(MsgPackContentTypeError::Unsupported);
+            }
+        } else {
+            return Err(MsgPackContentTypeError::Missing);
+        }
+
+        let bytes = Bytes::extract(request).await.map_err(|error| match error {
+            BodyReadError::TooLarge(limit) => MsgPackContentTypeError::TooLarge(limit),
+            _ => MsgPackContentTypeError::InvalidPayload,
+        })?;
+        from_msgpack(&bytes).map(Self)
+    }
+
+    #[cfg(feature = "openapi")]
+    fn openapi() -> Option<crate::openapi::ExtractorSchema> {
+        Some(crate::openapi::ExtractorSchema {
+            content_type: Some("application/msgpack"),
+            schema: None,
+            location: crate::openapi::ParameterLocation::Body,
+            required: true,
+            extra_content_types: &[],
+        })
+    }
+
+    #[cfg(feature = "openapi")]
+    fn register_openapi_schemas(
+        _defs: &mut std::collections::BTreeMap<String, crate::openapi::SchemaRef>,
+    ) {
+    }
+}
+
+/// Deserialize `bytes`, telling malformed input apart from data of the wrong shape.
+fn from_msgpack<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, MsgPackContentTypeError> {
+    use rmp_serde::decode::Error;
+
+    rmp_serde::from_slice(bytes).map_err(|error| match error {
+        Error::InvalidMarkerRead(_)
+        | Error::InvalidDataRead(_)
+        | Error::Utf8Error(_)
+        | Error::DepthLimitExceeded => MsgPackContentTypeError::Syntax(error.to_string()),
+        _ => MsgPackContentTypeError::Data(error.to_string()),
+    })
+}
+
+fn is_msgpack_content_type(value: &HeaderValue) -> bool {
+    value
+        .to_str()
+        .ok()
+        .and_then(|raw| raw.split(';').next())
+        .map(str::trim)
+        .is_some_and(|mime| {
+            mime.eq_ignore_ascii_case("application/msgpack")
+                || LEGACY_MSGPACK
+                    .iter()
+                    .any(|legacy| mime.eq_ignore_ascii_case(legacy))
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::MsgPack;
+    use crate::{
+        extract::BodyLimit,
+        header::{HeaderValue, CONTENT_TYPE},
+        routing::{CreateRouteNode, Route, Router},
+        Body, Endpoint, Method, Request, Result, StatusCode,
+    };
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Reading {
+        sensor: String,
+        values: Vec<u16>,
+    }
+
+    fn router() -> Router {
+        Route::new((
+            "/readings".post(|MsgPack(reading): MsgPack<Reading>| async move {
+                Result::Ok(format!("{}:{}", reading.sensor, reading.values.len()))
+            }),
+            "/latest".at(|| async {
+                Result::Ok(MsgPack(Reading {
+                    sensor: "t1".to_owned(),
+                    values: vec![1, 2, 3],
+                }))
+            }),
+        ))
+        .build()
+    }
+
+    fn request(method: Method, path: &str, content_type: &'static str, body: Vec<u8>) -> Request {
+        let mut request = Request::new(Body::from_bytes(body));
+        *request.method_mut() = method;
+        *request.uri_mut() = path.parse().unwrap();
+        request
+            .headers_mut()
+            .insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+        request
+    }
+
+    fn encode(value: &impl Serialize) -> Vec<u8> {
+        rmp_serde::to_vec_named(value).unwrap()
+    }
+
+    #[tokio::test]
+    async fn extracts_msgpack_bodies() {
+        for content_type in ["application/msgpack", "application/x-msgpack"] {
+            let body = encode(&Reading {
+                sensor: "t1".to_owned(),
+                values: vec![7, 8],
+            });
+            let response = router()
+                .respond(&mut request(Method::POST, "/readings", content_type, body))
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = response.into_body().into_string().await.unwrap();
+            assert_eq!(body.as_str(), "t1:2");
+        }
+    }
+
+    #[tokio::test]
+    async fn responds_with_msgpack() {
+        let response = router()
+            .respond(&mut request(
+                Method::GET,
+                "/latest",
+                "text/plain",
+                Vec::new(),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.headers()[CONTENT_TYPE], "application/msgpack");
+        let body = response.into_body().into_bytes().await.unwrap();
+        let reading: Reading = rmp_serde::from_slice(&body).unwrap();
+        assert_eq!(
+            reading,
+            Reading {
+                sensor: "t1".to_owned(),
+                values: vec![1, 2, 3],
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_other_content_types() {
+        let body = encode(&Reading {
+            sensor: "t1".to_owned(),
+            values: Vec::new(),
+        });
+        let response = router()
+            .respond(&mut request(
+                Method::POST,
+                "/readings",
+                "application/json",
+                body,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+
+    #[tokio::test]
+    async fn rejects_bodies_over_the_body_limit() {
+        let body = encode(&Reading {
+            sensor: "t1".to_owned(),
+            values: vec![7, 8],
+        });
+        let mut request = request(Method::POST, "/readings", "application/msgpack", body);
+        request.extensions_mut().insert(BodyLimit(4));
+        let response = router().respond(&mut request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn reports_mismatched_data_as_unprocessable() {
+        #[derive(Serialize)]
+        struct Partial {
+            sensor: &'static str,
+        }
+
+        let body = encode(&Partial { sensor: "t1" });
+        let response = router()
+            .respond(&mut request(
+                Method::POST,
+                "/readings",
+                "application/msgpack",
+                body,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let response = router()
+            .respond(&mut request(
+                Method::POST,
+                "/readings",
+                "application/msgpack",
+                vec![0x82, 0xa6],
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
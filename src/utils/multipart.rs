@@ -3,51 +3,231 @@
 
 use core::mem;
 use core::pin::Pin;
-use core::task::{Context, Poll};
+use core::task::{ready, Context, Poll};
+#[cfg(not(target_arch = "wasm32"))]
+use std::collections::HashMap;
+use std::convert::Infallible;
 
 use crate::{
     extract::Extractor,
     header::{HeaderMap, CONTENT_TYPE},
-    Body, Request, StatusCode,
+    middleware::Middleware,
+    Body, Endpoint, Request, Response, StatusCode,
 };
 use futures_core::Stream;
+use http_kit::middleware::MiddlewareError;
 use http_kit::utils::{Bytes, Stream as LiteStream};
 use http_kit::{http_error, BodyError};
 use multer::Field as MulterField;
 use pin_project_lite::pin_project;
 
+/// Guard rails for [`Multipart`] uploads, enforced while the body is streamed.
+///
+/// Insert it into the request extensions (it works as middleware) or pass it to
+/// [`Multipart::with_limits`]. Every limit is off by default.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MultipartLimits {
+    max_total_size: Option<u64>,
+    max_file_size: Option<u64>,
+    max_text_size: Option<u64>,
+    max_fields: Option<usize>,
+    allowed_content_types: Option<Vec<String>>,
+}
+
+impl MultipartLimits {
+    /// Create limits that accept everything.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            max_total_size: None,
+            max_file_size: None,
+            max_text_size: None,
+            max_fields: None,
+            allowed_content_types: None,
+        }
+    }
+
+    /// Maximum number of bytes across all fields.
+    #[must_use]
+    pub const fn max_total_size(mut self, bytes: u64) -> Self {
+        self.max_total_size = Some(bytes);
+        self
+    }
+
+    /// Maximum number of bytes in a single file field.
+    #[must_use]
+    pub const fn max_file_size(mut self, bytes: u64) -> Self {
+        self.max_file_size = Some(bytes);
+        self
+    }
+
+    /// Maximum number of bytes in a single text field, one without a filename.
+    #[must_use]
+    pub const fn max_text_size(mut self, bytes: u64) -> Self {
+        self.max_text_size = Some(bytes);
+        self
+    }
+
+    /// Maximum number of fields in the form.
+    #[must_use]
+    pub const fn max_fields(mut self, count: usize) -> Self {
+        self.max_fields = Some(count);
+        self
+    }
+
+    /// Content types accepted for file fields, such as `image/png` or `image/*`.
+    #[must_use]
+    pub fn allowed_content_types<I, S>(mut self, content_types: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.allowed_content_types = Some(content_types.into_iter().map(Into::into).collect());
+        self
+    }
+
+    fn allows(&self, content_type: Option<&str>) -> bool {
+        let Some(allowed) = &self.allowed_content_types else {
+            return true;
+        };
+        let Some(content_type) = content_type else {
+            return false;
+        };
+        let essence = content_type.split(';').next().unwrap_or_default().trim();
+        allowed.iter().any(|allowed| {
+            allowed.strip_suffix("/*").map_or_else(
+                || allowed.eq_ignore_ascii_case(essence),
+                |prefix| {
+                    essence
+                        .split_once('/')
+                        .is_some_and(|(ty, _)| ty.eq_ignore_ascii_case(prefix))
+                },
+            )
+        })
+    }
+}
+
+impl Middleware for MultipartLimits {
+    type Error = Infallible;
+    async fn handle<N: Endpoint>(
+        &mut self,
+        request: &mut Request,
+        mut next: N,
+    ) -> Result<Response, MiddlewareError<N::Error, Self::Error>> {
+        request.extensions_mut().insert(self.clone());
+        next.respond(request)
+            .await
+            .map_err(MiddlewareError::Endpoint)
+    }
+}
+
 /// Extractor that parses `multipart/form-data` bodies.
 #[derive(Debug)]
 pub struct Multipart {
     inner: multer::Multipart<'static>,
+    limits: MultipartLimits,
+    fields: usize,
+    total_size: u64,
 }
 
 impl Multipart {
-    fn from_parts(boundary: String, body: Body) -> Self {
+    fn from_parts(boundary: String, body: Body, limits: MultipartLimits) -> Self {
         Self {
             inner: multer::Multipart::new(RequestBodyStream::new(body), boundary),
+            limits,
+            fields: 0,
+            total_size: 0,
         }
     }
 
+    /// Replace the limits taken from the request extensions.
+    #[must_use]
+    pub fn with_limits(mut self, limits: MultipartLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
     /// Yields the next [`Field`] if available.
     ///
     /// # Errors
     ///
-    /// Returns [`MultipartError`] if parsing the field fails.
+    /// Returns [`MultipartError`] if parsing the field fails, the form has too many fields, or
+    /// a file field has a content type that is not allowed.
     pub async fn next_field(&mut self) -> Result<Option<Field<'_>>, MultipartError> {
-        let field = self
+        let Some(inner) = self
             .inner
             .next_field()
             .await
-            .map_err(MultipartError::from_multer)?;
+            .map_err(MultipartError::from_multer)?
+        else {
+            return Ok(None);
+        };
+
+        let field_name = inner.name().map(ToOwned::to_owned);
+        self.fields += 1;
+        if let Some(limit) = self.limits.max_fields {
+            if self.fields > limit {
+                return Err(MultipartError::new(
+                    field_name,
+                    ErrorKind::TooManyFields { limit },
+                ));
+            }
+        }
+        if inner.file_name().is_some() {
+            let content_type = inner.content_type().map(AsRef::as_ref);
+            if !self.limits.allows(content_type) {
+                return Err(MultipartError::new(
+                    field_name,
+                    ErrorKind::ContentTypeNotAllowed(content_type.map(ToOwned::to_owned)),
+                ));
+            }
+        }
 
-        Ok(field.map(|inner| Field {
+        Ok(Some(Field {
             inner,
-            _multipart: self,
+            size: 0,
+            multipart: self,
         }))
     }
+
+    /// Reads the whole form with `limits`, streaming file fields into temporary files and
+    /// buffering text fields.
+    ///
+    /// Text fields are held in memory, so they are capped at
+    /// [`max_text_size`](MultipartLimits::max_text_size), or 64 KiB when it is unset. Unnamed text
+    /// fields are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MultipartError`] if any field fails to parse, breaks `limits`, or cannot be
+    /// written. The files collected up to that point are deleted.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn collect_files(
+        mut self,
+        limits: MultipartLimits,
+    ) -> Result<MultipartForm, MultipartError> {
+        self.limits = MultipartLimits {
+            max_text_size: Some(limits.max_text_size.unwrap_or(DEFAULT_MAX_TEXT_SIZE)),
+            ..limits
+        };
+        let mut form = MultipartForm::default();
+        while let Some(field) = self.next_field().await? {
+            if field.file_name().is_some() {
+                form.files.push(field.persist_temp().await?);
+            } else if let Some(name) = field.name().map(ToOwned::to_owned) {
+                let text = field.text(usize::MAX).await?;
+                form.text.insert(name, text);
+            }
+        }
+        Ok(form)
+    }
 }
 
+/// Text field cap of [`Multipart::collect_files`] when [`MultipartLimits::max_text_size`] is
+/// unset.
+#[cfg(not(target_arch = "wasm32"))]
+const DEFAULT_MAX_TEXT_SIZE: u64 = 64 * 1024;
+
 http_error!(
     /// Error indicating that the multipart boundary is missing or invalid.
     pub MultipartBoundaryError, StatusCode::UNSUPPORTED_MEDIA_TYPE, "Expected content type `multipart/form-data` with a boundary");
@@ -58,8 +238,13 @@ impl Extractor for Multipart {
         let boundary =
             boundary_from_headers(request.headers()).ok_or(MultipartBoundaryError::new())?;
 
+        let limits = request
+            .extensions()
+            .get::<MultipartLimits>()
+            .cloned()
+            .unwrap_or_default();
         let body = mem::replace(request.body_mut(), Body::empty());
-        Ok(Self::from_parts(boundary, body))
+        Ok(Self::from_parts(boundary, body, limits))
     }
 
     #[cfg(feature = "openapi")]
@@ -67,6 +252,9 @@ impl Extractor for Multipart {
         Some(crate::openapi::ExtractorSchema {
             content_type: Some("multipart/form-data"),
             schema: None,
+            location: crate::openapi::ParameterLocation::Body,
+            required: true,
+            extra_content_types: &[],
         })
     }
 }
@@ -75,16 +263,20 @@ impl Extractor for Multipart {
 #[derive(Debug)]
 pub struct Field<'a> {
     inner: MulterField<'static>,
-    _multipart: &'a mut Multipart,
+    size: u64,
+    multipart: &'a mut Multipart,
 }
 
 impl Stream for Field<'_> {
     type Item = Result<Bytes, MultipartError>;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        Pin::new(&mut self.inner)
-            .poll_next(cx)
-            .map(|item| item.map(|res| res.map_err(MultipartError::from_multer)))
+        let item = ready!(Pin::new(&mut self.inner).poll_next(cx));
+        Poll::Ready(item.map(|res| {
+            let chunk = res.map_err(MultipartError::from_multer)?;
+            self.account(&chunk)?;
+            Ok(chunk)
+        }))
     }
 }
 
@@ -113,82 +305,333 @@ impl Field<'_> {
         self.inner.headers()
     }
 
-    /// Reads the entire field contents into memory as bytes.
+    /// Reads the entire field contents into memory, accepting at most `limit` bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MultipartError`] if the payload cannot be read or is larger than `limit` or the
+    /// configured [`MultipartLimits`].
+    pub async fn bytes(mut self, limit: usize) -> Result<Bytes, MultipartError> {
+        let mut buf = Vec::new();
+        while let Some(chunk) = self.chunk().await? {
+            if buf.len() + chunk.len() > limit {
+                return Err(self.error(ErrorKind::FieldTooLarge {
+                    limit: limit as u64,
+                }));
+            }
+            buf.extend_from_slice(&chunk);
+        }
+        Ok(buf.into())
+    }
+
+    /// Reads the entire field contents into a UTF-8 string, accepting at most `limit` bytes.
     ///
     /// # Errors
     ///
-    /// Returns [`MultipartError`] if the payload cannot be read.
-    pub async fn bytes(self) -> Result<Bytes, MultipartError> {
-        self.inner
-            .bytes()
+    /// Returns [`MultipartError`] if the payload cannot be read, is too large, or is not UTF-8.
+    pub async fn text(self, limit: usize) -> Result<String, MultipartError> {
+        let name = self.name().map(ToOwned::to_owned);
+        let bytes = self.bytes(limit).await?;
+        String::from_utf8(bytes.into()).map_err(|_| MultipartError::new(name, ErrorKind::Utf8))
+    }
+
+    /// Streams the field contents into a file at `path`, returning the number of bytes written.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MultipartError`] if the payload cannot be read, breaks the configured
+    /// [`MultipartLimits`], or the file cannot be written.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn save_to(
+        mut self,
+        path: impl AsRef<std::path::Path>,
+    ) -> Result<u64, MultipartError> {
+        let file = async_fs::File::create(path)
             .await
-            .map_err(MultipartError::from_multer)
+            .map_err(|error| self.error(ErrorKind::Io(error)))?;
+        self.write_into(file).await?;
+        Ok(self.size)
     }
 
-    /// Reads the entire field contents into a UTF-8 string.
+    /// Streams the field contents into a temporary file, which is deleted when the returned
+    /// [`TempFileField`] is dropped unless it is [kept](TempFileField::keep).
     ///
     /// # Errors
     ///
-    /// Returns [`MultipartError`] if the payload cannot be read or decoded.
-    pub async fn text(self) -> Result<String, MultipartError> {
-        self.inner.text().await.map_err(MultipartError::from_multer)
+    /// Returns [`MultipartError`] if the payload cannot be read, breaks the configured
+    /// [`MultipartLimits`], or the temporary file cannot be written. The partial file is deleted.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn persist_temp(mut self) -> Result<TempFileField, MultipartError> {
+        let temp =
+            tempfile::NamedTempFile::new().map_err(|error| self.error(ErrorKind::Io(error)))?;
+        let file = temp
+            .reopen()
+            .map_err(|error| self.error(ErrorKind::Io(error)))?;
+        self.write_into(file.into()).await?;
+        Ok(TempFileField {
+            name: self.name().map(ToOwned::to_owned),
+            file_name: self.file_name().map(ToOwned::to_owned),
+            content_type: self.content_type().map(ToOwned::to_owned),
+            size: self.size,
+            path: temp.into_temp_path(),
+        })
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn write_into(&mut self, mut file: async_fs::File) -> Result<(), MultipartError> {
+        use http_kit::utils::AsyncWriteExt;
+
+        while let Some(chunk) = self.chunk().await? {
+            file.write_all(&chunk)
+                .await
+                .map_err(|error| self.error(ErrorKind::Io(error)))?;
+        }
+        file.flush()
+            .await
+            .map_err(|error| self.error(ErrorKind::Io(error)))
     }
 
     /// Reads the next chunk from the field stream.
     ///
     /// # Errors
     ///
-    /// Returns [`MultipartError`] if streaming the payload fails.
+    /// Returns [`MultipartError`] if streaming the payload fails or breaks the configured
+    /// [`MultipartLimits`].
     pub async fn chunk(&mut self) -> Result<Option<Bytes>, MultipartError> {
-        self.inner
+        let Some(chunk) = self
+            .inner
             .chunk()
             .await
-            .map_err(MultipartError::from_multer)
+            .map_err(MultipartError::from_multer)?
+        else {
+            return Ok(None);
+        };
+        self.account(&chunk)?;
+        Ok(Some(chunk))
+    }
+
+    fn account(&mut self, chunk: &Bytes) -> Result<(), MultipartError> {
+        let len = chunk.len() as u64;
+        self.size += len;
+        self.multipart.total_size += len;
+
+        let limits = &self.multipart.limits;
+        let field_limit = if self.file_name().is_some() {
+            limits.max_file_size
+        } else {
+            limits.max_text_size
+        };
+        if let Some(limit) = field_limit {
+            if self.size > limit {
+                return Err(self.error(ErrorKind::FieldTooLarge { limit }));
+            }
+        }
+        if let Some(limit) = limits.max_total_size {
+            if self.multipart.total_size > limit {
+                return Err(self.error(ErrorKind::TotalTooLarge { limit }));
+            }
+        }
+        Ok(())
+    }
+
+    fn error(&self, kind: ErrorKind) -> MultipartError {
+        MultipartError::new(self.name().map(ToOwned::to_owned), kind)
+    }
+}
+
+/// A file field streamed into a temporary file by [`Field::persist_temp`].
+///
+/// The file is deleted when this value is dropped, so uploads do not pile up when a handler
+/// returns early, for example because a later field fails validation. Move it into place with
+/// [`keep`](Self::keep) to hold on to it.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug)]
+pub struct TempFileField {
+    name: Option<String>,
+    file_name: Option<String>,
+    content_type: Option<String>,
+    size: u64,
+    path: tempfile::TempPath,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl TempFileField {
+    /// Name of the form field.
+    #[must_use]
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Filename reported by the client. Do not use it as a path without sanitizing it.
+    #[must_use]
+    pub fn file_name(&self) -> Option<&str> {
+        self.file_name.as_deref()
+    }
+
+    /// Content type reported by the client.
+    #[must_use]
+    pub fn content_type(&self) -> Option<&str> {
+        self.content_type.as_deref()
+    }
+
+    /// Number of bytes written to the file.
+    #[must_use]
+    pub const fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Location of the temporary file.
+    #[must_use]
+    pub fn path(&self) -> &std::path::Path {
+        &self.path
+    }
+
+    /// Move the file to `path`, where it is no longer deleted.
+    ///
+    /// The file is renamed, or copied when `path` is on another file system than the temporary
+    /// directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can be neither renamed nor copied to `path`. The temporary file
+    /// is deleted either way.
+    pub async fn keep(self, path: impl AsRef<std::path::Path>) -> std::io::Result<()> {
+        let path = path.as_ref();
+        if async_fs::rename(&self.path, path).await.is_ok() {
+            // Nothing is left at the temporary path to delete.
+            let _ = self.path.keep();
+            return Ok(());
+        }
+        async_fs::copy(&self.path, path).await.map(drop)
+    }
+}
+
+/// The fields of a form read by [`Multipart::collect_files`].
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Default)]
+pub struct MultipartForm {
+    /// File fields, in the order they were sent.
+    pub files: Vec<TempFileField>,
+    /// Text fields by name. A repeated name keeps its last value.
+    pub text: HashMap<String, String>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl MultipartForm {
+    /// The first file field named `name`.
+    #[must_use]
+    pub fn file(&self, name: &str) -> Option<&TempFileField> {
+        self.files.iter().find(|file| file.name() == Some(name))
     }
 }
 
 /// Errors that can occur when processing multipart data.
 #[derive(Debug)]
 pub struct MultipartError {
-    source: multer::Error,
+    field: Option<String>,
+    kind: ErrorKind,
+}
+
+#[derive(Debug)]
+enum ErrorKind {
+    Multer(multer::Error),
+    FieldTooLarge {
+        limit: u64,
+    },
+    TotalTooLarge {
+        limit: u64,
+    },
+    TooManyFields {
+        limit: usize,
+    },
+    ContentTypeNotAllowed(Option<String>),
+    Utf8,
+    #[cfg(not(target_arch = "wasm32"))]
+    Io(std::io::Error),
 }
 
 impl MultipartError {
+    const fn new(field: Option<String>, kind: ErrorKind) -> Self {
+        Self { field, kind }
+    }
+
     const fn from_multer(source: multer::Error) -> Self {
-        Self { source }
+        Self::new(None, ErrorKind::Multer(source))
+    }
+
+    /// Name of the field that caused the error, when known.
+    #[must_use]
+    pub fn field_name(&self) -> Option<&str> {
+        self.field.as_deref()
     }
 
     /// HTTP status associated with this error.
     #[must_use]
     pub const fn status(&self) -> StatusCode {
-        match &self.source {
-            multer::Error::UnknownField { .. }
-            | multer::Error::IncompleteFieldData { .. }
-            | multer::Error::IncompleteHeaders
-            | multer::Error::ReadHeaderFailed(..)
-            | multer::Error::DecodeHeaderName { .. }
-            | multer::Error::DecodeContentType(..)
-            | multer::Error::NoBoundary
-            | multer::Error::DecodeHeaderValue { .. }
-            | multer::Error::NoMultipart
-            | multer::Error::IncompleteStream => StatusCode::BAD_REQUEST,
-            multer::Error::FieldSizeExceeded { .. } | multer::Error::StreamSizeExceeded { .. } => {
-                StatusCode::PAYLOAD_TOO_LARGE
-            }
-            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        match &self.kind {
+            ErrorKind::Multer(
+                multer::Error::UnknownField { .. }
+                | multer::Error::IncompleteFieldData { .. }
+                | multer::Error::IncompleteHeaders
+                | multer::Error::ReadHeaderFailed(..)
+                | multer::Error::DecodeHeaderName { .. }
+                | multer::Error::DecodeContentType(..)
+                | multer::Error::NoBoundary
+                | multer::Error::DecodeHeaderValue { .. }
+                | multer::Error::NoMultipart
+                | multer::Error::IncompleteStream,
+            )
+            | ErrorKind::Utf8 => StatusCode::BAD_REQUEST,
+            ErrorKind::Multer(
+                multer::Error::FieldSizeExceeded { .. } | multer::Error::StreamSizeExceeded { .. },
+            )
+            | ErrorKind::FieldTooLarge { .. }
+            | ErrorKind::TotalTooLarge { .. }
+            | ErrorKind::TooManyFields { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+            ErrorKind::ContentTypeNotAllowed(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            #[cfg(not(target_arch = "wasm32"))]
+            ErrorKind::Io(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            ErrorKind::Multer(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
 }
 
 impl core::fmt::Display for MultipartError {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        write!(f, "error parsing multipart request: {}", self.source)
+        f.write_str("error parsing multipart request: ")?;
+        let field = self.field.as_deref().unwrap_or("<unnamed>");
+        match &self.kind {
+            ErrorKind::Multer(source) => write!(f, "{source}"),
+            ErrorKind::FieldTooLarge { limit } => {
+                write!(f, "field `{field}` exceeds the limit of {limit} bytes")
+            }
+            ErrorKind::TotalTooLarge { limit } => write!(
+                f,
+                "form data exceeds the limit of {limit} bytes in field `{field}`"
+            ),
+            ErrorKind::TooManyFields { limit } => {
+                write!(f, "field `{field}` exceeds the limit of {limit} fields")
+            }
+            ErrorKind::ContentTypeNotAllowed(content_type) => write!(
+                f,
+                "content type `{}` is not allowed for field `{field}`",
+                content_type.as_deref().unwrap_or("<none>")
+            ),
+            ErrorKind::Utf8 => write!(f, "field `{field}` is not valid UTF-8"),
+            #[cfg(not(target_arch = "wasm32"))]
+            ErrorKind::Io(error) => write!(f, "failed to save field `{field}`: {error}"),
+        }
     }
 }
 
 impl std::error::Error for MultipartError {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
-        Some(&self.source)
+        match &self.kind {
+            ErrorKind::Multer(source) => Some(source),
+            #[cfg(not(target_arch = "wasm32"))]
+            ErrorKind::Io(source) => Some(source),
+            _ => None,
+        }
     }
 }
 
@@ -219,12 +662,206 @@ impl Stream for RequestBodyStream {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, not(target_arch = "wasm32")))]
 mod tests {
-    use super::Multipart;
-    use crate::{header::HeaderValue, Body, Request};
+    use super::{Multipart, MultipartLimits};
+    use crate::{header::HeaderValue, Body, Request, StatusCode};
     use http_kit::HttpError;
     use skyzen_core::Extractor;
+    use std::fmt::Write;
+
+    /// Build a request with one file field per `(name, content type, contents)` entry.
+    fn upload(files: &[(&str, &str, &str)], limits: Option<MultipartLimits>) -> Request {
+        let boundary = "boundary";
+        let mut payload = String::new();
+        for (name, content_type, contents) in files {
+            write!(
+                payload,
+                "--{boundary}\r\nContent-Disposition: form-data; name=\"{name}\"; filename=\"{name}.bin\"\r\nContent-Type: {content_type}\r\n\r\n{contents}\r\n"
+            )
+            .unwrap();
+        }
+        write!(payload, "--{boundary}--\r\n").unwrap();
+
+        let mut request = Request::new(Body::from_bytes(payload));
+        request.headers_mut().insert(
+            crate::header::CONTENT_TYPE,
+            HeaderValue::from_str(&format!("multipart/form-data; boundary={boundary}")).unwrap(),
+        );
+        if let Some(limits) = limits {
+            request.extensions_mut().insert(limits);
+        }
+        request
+    }
+
+    #[tokio::test]
+    async fn enforces_file_size_at_the_boundary() {
+        let limits = MultipartLimits::new().max_file_size(5);
+        let mut request = upload(&[("exact", "text/plain", "12345")], Some(limits.clone()));
+        let mut multipart = Multipart::extract(&mut request).await.unwrap();
+        let field = multipart.next_field().await.unwrap().unwrap();
+        assert_eq!(field.bytes(usize::MAX).await.unwrap(), "12345");
+
+        let mut request = upload(&[("over", "text/plain", "123456")], Some(limits));
+        let mut multipart = Multipart::extract(&mut request).await.unwrap();
+        let field = multipart.next_field().await.unwrap().unwrap();
+        let error = field.bytes(usize::MAX).await.unwrap_err();
+        assert_eq!(error.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(error.field_name(), Some("over"));
+
+        let mut request = upload(&[("inline", "text/plain", "123456")], None);
+        let mut multipart = Multipart::extract(&mut request).await.unwrap();
+        let field = multipart.next_field().await.unwrap().unwrap();
+        assert!(field.text(6).await.is_ok());
+        let mut request = upload(&[("inline", "text/plain", "123456")], None);
+        let mut multipart = Multipart::extract(&mut request).await.unwrap();
+        let field = multipart.next_field().await.unwrap().unwrap();
+        let error = field.text(5).await.unwrap_err();
+        assert_eq!(error.field_name(), Some("inline"));
+    }
+
+    #[tokio::test]
+    async fn enforces_total_size_and_field_count() {
+        let files = [("a", "text/plain", "123"), ("b", "text/plain", "456")];
+
+        let mut request = upload(&files, None);
+        let limits = MultipartLimits::new().max_total_size(5);
+        let mut multipart = Multipart::extract(&mut request)
+            .await
+            .unwrap()
+            .with_limits(limits);
+        let field = multipart.next_field().await.unwrap().unwrap();
+        field.bytes(usize::MAX).await.unwrap();
+        let field = multipart.next_field().await.unwrap().unwrap();
+        let error = field.bytes(usize::MAX).await.unwrap_err();
+        assert_eq!(error.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(error.field_name(), Some("b"));
+
+        let mut request = upload(&files, Some(MultipartLimits::new().max_fields(1)));
+        let mut multipart = Multipart::extract(&mut request).await.unwrap();
+        multipart.next_field().await.unwrap().unwrap();
+        let error = multipart.next_field().await.unwrap_err();
+        assert_eq!(error.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(error.field_name(), Some("b"));
+    }
+
+    #[tokio::test]
+    async fn rejects_disallowed_content_types() {
+        let limits = MultipartLimits::new().allowed_content_types(["image/*"]);
+        let files = [
+            ("avatar", "image/png", "png"),
+            ("script", "text/html", "<p>"),
+        ];
+        let mut request = upload(&files, Some(limits));
+        let mut multipart = Multipart::extract(&mut request).await.unwrap();
+        multipart.next_field().await.unwrap().unwrap();
+        let error = multipart.next_field().await.unwrap_err();
+        assert_eq!(error.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(error.field_name(), Some("script"));
+    }
+
+    #[tokio::test]
+    async fn saves_fields_to_disk() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("upload.bin");
+        let mut request = upload(&[("file", "application/octet-stream", "contents")], None);
+        let mut multipart = Multipart::extract(&mut request).await.unwrap();
+        let field = multipart.next_field().await.unwrap().unwrap();
+        assert_eq!(field.save_to(&path).await.unwrap(), 8);
+        assert_eq!(std::fs::read(&path).unwrap(), b"contents");
+    }
+
+    /// Build a request with a `title` text field and a `photo` file field.
+    fn photo_upload(title: &str) -> Request {
+        let boundary = "boundary";
+        let payload = format!(
+            "--{boundary}\r\nContent-Disposition: form-data; name=\"title\"\r\n\r\n{title}\r\n\
+             --{boundary}\r\nContent-Disposition: form-data; name=\"photo\"; filename=\"cat.png\"\r\nContent-Type: image/png\r\n\r\npixels\r\n\
+             --{boundary}--\r\n"
+        );
+        let mut request = Request::new(Body::from_bytes(payload));
+        request.headers_mut().insert(
+            crate::header::CONTENT_TYPE,
+            HeaderValue::from_str(&format!("multipart/form-data; boundary={boundary}")).unwrap(),
+        );
+        request
+    }
+
+    #[tokio::test]
+    async fn collects_files_into_temp_files() {
+        let mut request = photo_upload("Whiskers");
+        let multipart = Multipart::extract(&mut request).await.unwrap();
+        let form = multipart
+            .collect_files(MultipartLimits::new())
+            .await
+            .unwrap();
+        assert_eq!(form.text["title"], "Whiskers");
+        let photo = form.file("photo").unwrap();
+        assert_eq!(photo.file_name(), Some("cat.png"));
+        assert_eq!(photo.content_type(), Some("image/png"));
+        assert_eq!(photo.size(), 6);
+        assert_eq!(std::fs::read(photo.path()).unwrap(), b"pixels");
+
+        let dir = tempfile::tempdir().unwrap();
+        let kept = dir.path().join("cat.png");
+        let temp = photo.path().to_owned();
+        let mut form = form;
+        form.files.pop().unwrap().keep(&kept).await.unwrap();
+        assert!(!temp.exists());
+        assert_eq!(std::fs::read(&kept).unwrap(), b"pixels");
+
+        let mut request = photo_upload("Whiskers");
+        let multipart = Multipart::extract(&mut request).await.unwrap();
+        let error = multipart
+            .collect_files(MultipartLimits::new().max_text_size(4))
+            .await
+            .unwrap_err();
+        assert_eq!(error.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(error.field_name(), Some("title"));
+    }
+
+    #[tokio::test]
+    async fn deletes_temp_files_when_the_handler_fails() {
+        use crate::{
+            routing::{CreateRouteNode, Route},
+            Endpoint, Error,
+        };
+        use std::{
+            path::PathBuf,
+            sync::{Arc, Mutex},
+        };
+
+        let seen = Arc::new(Mutex::new(Vec::<PathBuf>::new()));
+        let handler = {
+            let seen = Arc::clone(&seen);
+            move |multipart: Multipart| {
+                let seen = Arc::clone(&seen);
+                async move {
+                    let form = multipart.collect_files(MultipartLimits::new()).await?;
+                    seen.lock()
+                        .unwrap()
+                        .extend(form.files.iter().map(|file| file.path().to_owned()));
+                    if form.text["title"].is_empty() {
+                        return Err(
+                            Error::msg("title is required").set_status(StatusCode::BAD_REQUEST)
+                        );
+                    }
+                    crate::Result::Ok("uploaded")
+                }
+            }
+        };
+        let mut router = Route::new(("/photos".post(handler),)).build();
+
+        let mut request = photo_upload("");
+        *request.method_mut() = crate::Method::POST;
+        *request.uri_mut() = "/photos".parse().unwrap();
+        let response = router.respond(&mut request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let seen = std::mem::take(&mut *seen.lock().unwrap());
+        assert_eq!(seen.len(), 1);
+        assert!(!seen[0].exists());
+    }
 
     #[tokio::test]
     async fn parses_text_field() {
@@ -242,7 +879,7 @@ mod tests {
         let mut multipart = Multipart::extract(&mut request).await.unwrap();
         let field = multipart.next_field().await.unwrap().unwrap();
         assert_eq!(field.name(), Some("greeting"));
-        assert_eq!(field.text().await.unwrap(), "Hello Skyzen!");
+        assert_eq!(field.text(1024).await.unwrap(), "Hello Skyzen!");
         assert!(multipart.next_field().await.unwrap().is_none());
     }
 
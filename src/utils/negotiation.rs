@@ -0,0 +1,48 @@
+//! Parsing shared by content negotiation on `Accept`-style headers.
+
+/// The entries of an `Accept`-style header value as `(token, quality)` pairs, in order.
+///
+/// Parameters other than `q` are dropped. A malformed quality counts as `0`, which means the
+/// entry is not acceptable.
+pub fn entries(value: &str) -> impl Iterator<Item = (&str, f32)> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(parse_entry)
+}
+
+fn parse_entry(entry: &str) -> (&str, f32) {
+    let mut sections = entry.split(';');
+    let token = sections.next().unwrap_or_default().trim();
+    let quality = sections
+        .filter_map(|parameter| parameter.split_once('='))
+        .find(|(key, _)| key.trim().eq_ignore_ascii_case("q"))
+        .map_or(1.0, |(_, value)| parse_quality(value).unwrap_or(0.0));
+    (token, quality)
+}
+
+fn parse_quality(raw: &str) -> Option<f32> {
+    let value = raw.trim().parse::<f32>().ok()?;
+    (0.0..=1.0).contains(&value).then_some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::entries;
+
+    #[test]
+    fn parses_qualities_in_order() {
+        let parsed = entries("text/html;level=1, application/json ;q=0.5,, */*;q=oops, x;q=2")
+            .collect::<Vec<_>>();
+        assert_eq!(
+            parsed,
+            [
+                ("text/html", 1.0),
+                ("application/json", 0.5),
+                ("*/*", 0.0),
+                ("x", 0.0),
+            ]
+        );
+    }
+}
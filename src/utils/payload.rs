@@ -0,0 +1,179 @@
+//! Content-negotiating body extractor.
+
+use core::fmt;
+
+use serde::de::DeserializeOwned;
+
+use crate::{
+    extract::Extractor,
+    header::CONTENT_TYPE,
+    utils::{form::FormContentTypeError, json::JsonContentTypeError, Form, Json},
+    HttpError, Request, StatusCode,
+};
+
+/// Extract a payload sent either as `application/json` or `application/x-www-form-urlencoded`.
+///
+/// The `Content-Type` header decides whether the body is parsed like [`Json`] or [`Form`];
+/// any other content type is rejected with `415 Unsupported Media Type`.
+#[derive(Debug, Clone)]
+pub struct Payload<T>(pub T);
+
+impl_deref!(Payload);
+
+impl<T: Send + Sync + DeserializeOwned + 'static> Extractor for Payload<T> {
+    type Error = PayloadError;
+    async fn extract(request: &mut Request) -> Result<Self, Self::Error> {
+        let content_type = request
+            .headers()
+            .get(CONTENT_TYPE)
+            .ok_or(PayloadError::Missing)?;
+        let mime = content_type
+            .to_str()
+            .ok()
+            .and_then(|raw| raw.split(';').next())
+            .map(str::trim)
+            .unwrap_or_default();
+
+        if mime.eq_ignore_ascii_case("application/json") {
+            let Json(value) = Json::extract(request).await?;
+            Ok(Self(value))
+        } else if mime.eq_ignore_ascii_case("application/x-www-form-urlencoded") {
+            let Form(value) = Form::extract(request).await?;
+            Ok(Self(value))
+        } else {
+            Err(PayloadError::Unsupported(
+                String::from_utf8_lossy(content_type.as_bytes()).into_owned(),
+            ))
+        }
+    }
+
+    #[cfg(feature = "openapi")]
+    fn openapi() -> Option<crate::openapi::ExtractorSchema> {
+        Some(crate::openapi::ExtractorSchema {
+            content_type: Some("application/json"),
+            schema: None,
+            location: crate::openapi::ParameterLocation::Body,
+            required: true,
+            extra_content_types: &["application/x-www-form-urlencoded"],
+        })
+    }
+
+    #[cfg(feature = "openapi")]
+    fn register_openapi_schemas(
+        _defs: &mut std::collections::BTreeMap<String, crate::openapi::SchemaRef>,
+    ) {
+    }
+}
+
+/// An error occurred while extracting a [`Payload`].
+#[derive(Debug)]
+pub enum PayloadError {
+    /// The content type header is missing.
+    Missing,
+    /// The content type is neither JSON nor form data.
+    Unsupported(String),
+    /// The JSON payload could not be parsed.
+    Json(JsonContentTypeError),
+    /// The form payload could not be parsed.
+    Form(FormContentTypeError),
+}
+
+impl From<JsonContentTypeError> for PayloadError {
+    fn from(error: JsonContentTypeError) -> Self {
+        Self::Json(error)
+    }
+}
+
+impl From<FormContentTypeError> for PayloadError {
+    fn from(error: FormContentTypeError) -> Self {
+        Self::Form(error)
+    }
+}
+
+impl fmt::Display for PayloadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Missing => f.write_str(
+                "Expected content type `application/json` or `application/x-www-form-urlencoded`",
+            ),
+            Self::Unsupported(content_type) => write!(
+                f,
+                "Unsupported content type `{content_type}`, expected `application/json` or `application/x-www-form-urlencoded`"
+            ),
+            Self::Json(error) => fmt::Display::fmt(error, f),
+            Self::Form(error) => fmt::Display::fmt(error, f),
+        }
+    }
+}
+
+impl core::error::Error for PayloadError {}
+
+impl HttpError for PayloadError {
+    fn status(&self) -> StatusCode {
+        match self {
+            Self::Missing => StatusCode::BAD_REQUEST,
+            Self::Unsupported(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            Self::Json(error) => error.status(),
+            Self::Form(error) => error.status(),
+        }
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::{Payload, PayloadError};
+    use crate::{Body, HttpError, Method, StatusCode};
+    use http_kit::{
+        header::{HeaderValue, CONTENT_TYPE},
+        Request,
+    };
+    use serde::Deserialize;
+    use skyzen_core::Extractor;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Signup {
+        name: String,
+        age: u8,
+    }
+
+    fn post(content_type: &'static str, body: &'static str) -> Request {
+        let mut request = Request::new(Body::from_bytes(body));
+        *request.method_mut() = Method::POST;
+        request
+            .headers_mut()
+            .insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+        request
+    }
+
+    #[tokio::test]
+    async fn accepts_json_and_form_bodies() {
+        let expected = Signup {
+            name: "Lexo".to_owned(),
+            age: 17,
+        };
+
+        let mut request = post("application/json", r#"{"name":"Lexo","age":17}"#);
+        let Payload(signup) = Payload::<Signup>::extract(&mut request).await.unwrap();
+        assert_eq!(signup, expected);
+
+        let mut request = post(
+            "application/x-www-form-urlencoded; charset=utf-8",
+            "name=Lexo&age=17",
+        );
+        let Payload(signup) = Payload::<Signup>::extract(&mut request).await.unwrap();
+        assert_eq!(signup, expected);
+    }
+
+    #[tokio::test]
+    async fn rejects_other_content_types() {
+        let mut request = post("text/plain", "name=Lexo&age=17");
+        let error = Payload::<Signup>::extract(&mut request).await.unwrap_err();
+        assert_eq!(error.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        assert!(matches!(&error, PayloadError::Unsupported(ty) if ty == "text/plain"));
+        assert!(error.to_string().contains("`text/plain`"));
+
+        let mut request = post("application/json", "name=Lexo");
+        let error = Payload::<Signup>::extract(&mut request).await.unwrap_err();
+        assert_eq!(error.status(), StatusCode::BAD_REQUEST);
+    }
+}
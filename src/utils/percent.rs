@@ -0,0 +1,55 @@
+//! Percent-decoding of URL components, shared by routing, extractors and middleware.
+
+/// Decode `%XX` escapes in `value`.
+///
+/// Returns `None` if an escape is truncated or not two hex digits, or if the decoded bytes are
+/// not valid UTF-8. `+` is left as is.
+pub fn percent_decode(value: &str) -> Option<String> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let &[high, low] = bytes.get(i + 1..i + 3)? else {
+                return None;
+            };
+            decoded.push(hex_digit(high)? << 4 | hex_digit(low)?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+// Digits are matched by hand: `u8::from_str_radix` would accept a leading `+` such as `%+4`.
+const fn hex_digit(byte: u8) -> Option<u8> {
+    match byte {
+        b'0'..=b'9' => Some(byte - b'0'),
+        b'a'..=b'f' => Some(byte - b'a' + 10),
+        b'A'..=b'F' => Some(byte - b'A' + 10),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::percent_decode;
+
+    #[test]
+    fn decodes_escapes() {
+        assert_eq!(percent_decode("a%20b%2Fc").as_deref(), Some("a b/c"));
+        assert_eq!(percent_decode("caf%C3%A9").as_deref(), Some("café"));
+        assert_eq!(percent_decode("a+b").as_deref(), Some("a+b"));
+    }
+
+    #[test]
+    fn rejects_malformed_escapes() {
+        assert_eq!(percent_decode("%+4"), None);
+        assert_eq!(percent_decode("%-1"), None);
+        assert_eq!(percent_decode("%zz"), None);
+        assert_eq!(percent_decode("%4"), None);
+        assert_eq!(percent_decode("%FF"), None);
+    }
+}
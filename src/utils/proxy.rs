@@ -0,0 +1,486 @@
+//! Forward requests to another HTTP service.
+//!
+//! [`Proxy`] is an endpoint relaying requests to an upstream server, such as an auth service or a
+//! legacy backend, and streaming its responses back:
+//!
+//! ```
+//! use skyzen::{routing::Route, utils::Proxy};
+//!
+//! // `/legacy/users?page=2` is forwarded to `http://127.0.0.1:9000/v1/users?page=2`.
+//! let router = Route::new((Proxy::new("http://127.0.0.1:9000/v1").mount("/legacy"),)).build();
+//! ```
+//!
+//! Request and response bodies are streamed in both directions. Hop-by-hop headers, such as
+//! `Connection` and `Transfer-Encoding` and any header named in `Connection`, are removed in both
+//! directions. The upstream sees its own authority as `Host`, and the original host, scheme and
+//! client address in `X-Forwarded-Host`, `X-Forwarded-Proto` and `X-Forwarded-For`; the client
+//! address is appended to an existing `X-Forwarded-For` list.
+//!
+//! Connections to the upstream use HTTP/1.1 and are kept alive and reused once a response body
+//! has been read to the end. Only `http` upstreams are supported, and protocol upgrades such as
+//! WebSocket connections are not forwarded. A failure to reach the upstream is logged and
+//! answered with `502 Bad Gateway`. Requests whose forwarded path contains `.` or `..` segments,
+//! including percent-encoded ones like `%2e%2e`, are answered with `400 Bad Request` rather than
+//! reaching upstream paths outside the base path.
+
+use std::{
+    fmt, io, mem,
+    pin::Pin,
+    sync::{Arc, Mutex, PoisonError},
+    task::{Context, Poll},
+};
+
+use async_net::TcpStream;
+use executor_core::{AnyExecutor, DefaultExecutor, Executor};
+use futures_core::Stream;
+use futures_util::{stream::MapOk, TryStreamExt};
+use http::{
+    header::{self, HeaderMap, HeaderName, HeaderValue},
+    uri::{Authority, Scheme},
+    Method, StatusCode, Uri, Version,
+};
+use http_body_util::{BodyDataStream, StreamBody};
+use http_kit::{utils::Bytes, Body, BodyError, Endpoint, Request, Response};
+use hyper::{
+    body::{Frame, Incoming},
+    client::conn::http1::{self, SendRequest},
+};
+
+use crate::{
+    extract::{PeerAddr, ProxiedAddr, RequestUriExt},
+    routing::{MatchedPath, Params, Route, RouteNode},
+    runtime::native::ConnectionWrapper,
+    static_files::normalize_mount_path,
+};
+
+const X_FORWARDED_FOR: HeaderName = HeaderName::from_static("x-forwarded-for");
+const X_FORWARDED_HOST: HeaderName = HeaderName::from_static("x-forwarded-host");
+const X_FORWARDED_PROTO: HeaderName = HeaderName::from_static("x-forwarded-proto");
+const KEEP_ALIVE: HeaderName = HeaderName::from_static("keep-alive");
+
+/// Methods routed to the proxy by [`Proxy::mount`].
+const METHODS: [Method; 7] = [
+    Method::GET,
+    Method::HEAD,
+    Method::POST,
+    Method::PUT,
+    Method::PATCH,
+    Method::DELETE,
+    Method::OPTIONS,
+];
+
+/// Idle upstream connections kept for reuse.
+const MAX_IDLE: usize = 32;
+
+type OutgoingBody = StreamBody<MapOk<Body, fn(Bytes) -> Frame<Bytes>>>;
+type Connection = SendRequest<OutgoingBody>;
+
+/// An endpoint forwarding requests to an upstream HTTP service, see the [module docs](self).
+///
+/// Clones share the same pool of upstream connections.
+#[derive(Clone)]
+pub struct Proxy {
+    upstream: Arc<Upstream>,
+}
+
+struct Upstream {
+    authority: Authority,
+    /// The path of the upstream base URI, without a trailing slash.
+    base_path: String,
+    idle: Mutex<Vec<Connection>>,
+}
+
+impl Proxy {
+    /// Forward requests to `upstream`, an absolute `http` URI whose path is prepended to the
+    /// forwarded paths.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `upstream` is not an absolute `http` URI.
+    #[must_use]
+    pub fn new(upstream: &str) -> Self {
+        let uri = upstream
+            .parse::<Uri>()
+            .unwrap_or_else(|error| panic!("invalid upstream URI `{upstream}`: {error}"));
+        assert!(
+            uri.scheme() == Some(&Scheme::HTTP),
+            "upstream URI `{upstream}` must use the `http` scheme"
+        );
+        let authority = uri
+            .authority()
+            .cloned()
+            .unwrap_or_else(|| panic!("upstream URI `{upstream}` has no host"));
+        Self {
+            upstream: Arc::new(Upstream {
+                authority,
+                base_path: uri.path().trim_end_matches('/').to_owned(),
+                idle: Mutex::new(Vec::new()),
+            }),
+        }
+    }
+
+    /// Route every request under `path` to the upstream, with the rest of the path appended to
+    /// the upstream base path.
+    ///
+    /// The proxy answers the common methods: `GET`, `HEAD`, `POST`, `PUT`, `PATCH`, `DELETE`
+    /// and `OPTIONS`. To mount it differently, register it as an endpoint on a route ending in a
+    /// catch-all parameter such as `/legacy/{*rest}`; the parameter is then appended instead.
+    #[must_use]
+    pub fn mount(self, path: &str) -> RouteNode {
+        let path = normalize_mount_path(path);
+        let wildcard = if path == "/" { "{*path}" } else { "/{*path}" };
+        let nodes = METHODS
+            .into_iter()
+            .flat_map(|method| {
+                [
+                    RouteNode::new_endpoint("", method.clone(), self.clone(), None),
+                    RouteNode::new_endpoint(wildcard, method, self.clone(), None),
+                ]
+            })
+            .collect::<Vec<_>>();
+        RouteNode::new_route(path, Route::new(nodes))
+    }
+}
+
+impl fmt::Debug for Proxy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Proxy")
+            .field("authority", &self.upstream.authority)
+            .field("base_path", &self.upstream.base_path)
+            .finish_non_exhaustive()
+    }
+}
+
+/// An error occurred while forwarding a request to the upstream.
+#[skyzen::error(status = StatusCode::BAD_GATEWAY)]
+pub enum ProxyError {
+    /// The upstream could not be reached.
+    #[error("Failed to connect to the upstream: {0}")]
+    Connect(io::Error),
+    /// The upstream connection failed before a response was received.
+    #[error("Upstream request failed: {0}")]
+    Upstream(hyper::Error),
+    /// The request could not be turned into an upstream request.
+    #[error("Invalid upstream request: {0}")]
+    InvalidRequest(http::Error),
+    /// The forwarded path contains `.` or `..` segments.
+    #[error("Request path contains dot segments", status = StatusCode::BAD_REQUEST)]
+    DotSegments,
+}
+
+impl Endpoint for Proxy {
+    type Error = ProxyError;
+
+    async fn respond(&mut self, request: &mut Request) -> Result<Response, Self::Error> {
+        let upstream = &self.upstream;
+        let result = upstream.forward(request).await;
+        if let Err(error) = &result {
+            tracing::warn!(
+                upstream = %upstream.authority,
+                method = request.method().as_str(),
+                path = request.uri().path(),
+                "proxy request failed: {error}"
+            );
+        }
+        result
+    }
+}
+
+impl Upstream {
+    async fn forward(self: &Arc<Self>, request: &mut Request) -> Result<Response, ProxyError> {
+        let uri = self.uri_for(request)?;
+        let headers = self.forwarded_headers(request);
+        let body = mem::replace(request.body_mut(), Body::empty());
+        let mut outgoing = hyper::Request::new(StreamBody::new(
+            body.map_ok(Frame::data as fn(Bytes) -> Frame<Bytes>),
+        ));
+        *outgoing.method_mut() = request.method().clone();
+        *outgoing.uri_mut() = uri;
+        *outgoing.version_mut() = Version::HTTP_11;
+        *outgoing.headers_mut() = headers;
+
+        let executor = request
+            .extensions()
+            .get::<Arc<AnyExecutor>>()
+            .cloned()
+            .unwrap_or_else(|| Arc::new(AnyExecutor::new(DefaultExecutor)));
+        let mut connection = self.connect(&executor).await?;
+        let response = connection
+            .send_request(outgoing)
+            .await
+            .map_err(ProxyError::Upstream)?;
+
+        let (mut parts, incoming) = response.into_parts();
+        strip_hop_by_hop(&mut parts.headers);
+        let body = Body::from_stream(PooledBody {
+            body: BodyDataStream::new(incoming),
+            connection: Some(connection),
+            upstream: Arc::clone(self),
+        });
+        Ok(Response::from_parts(parts, body))
+    }
+
+    /// The origin-form target of the upstream request: the base path, the part of the request
+    /// path below the mount point and the request query.
+    fn uri_for(&self, request: &Request) -> Result<Uri, ProxyError> {
+        let mut path_and_query = self.base_path.clone();
+        match remainder(request).filter(|rest| !rest.is_empty()) {
+            Some(rest) if has_dot_segment(rest) => return Err(ProxyError::DotSegments),
+            Some(rest) => {
+                path_and_query.push('/');
+                path_and_query.push_str(rest.trim_start_matches('/'));
+            }
+            None if path_and_query.is_empty() => path_and_query.push('/'),
+            None => {}
+        }
+        if let Some(query) = request.uri().query() {
+            path_and_query.push('?');
+            path_and_query.push_str(query);
+        }
+        Uri::builder()
+            .path_and_query(path_and_query)
+            .build()
+            .map_err(ProxyError::InvalidRequest)
+    }
+
+    fn forwarded_headers(&self, request: &Request) -> HeaderMap {
+        let mut headers = request.headers().clone();
+        strip_hop_by_hop(&mut headers);
+
+        let original_host = request.headers().get(header::HOST).cloned().or_else(|| {
+            let authority = request.uri().authority()?;
+            HeaderValue::from_str(authority.as_str()).ok()
+        });
+        if let Some(host) = original_host {
+            headers.insert(X_FORWARDED_HOST, host);
+        }
+        if let Ok(proto) = HeaderValue::from_str(request.external_scheme().as_str()) {
+            headers.insert(X_FORWARDED_PROTO, proto);
+        }
+        let client = request
+            .extensions()
+            .get::<ProxiedAddr>()
+            .map(|addr| addr.0)
+            .or_else(|| request.extensions().get::<PeerAddr>().map(|addr| addr.0));
+        if let Some(client) = client {
+            let forwarded_for = request
+                .headers()
+                .get_all(X_FORWARDED_FOR)
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .chain([client.ip().to_string().as_str()])
+                .collect::<Vec<_>>()
+                .join(", ");
+            if let Ok(value) = HeaderValue::from_str(&forwarded_for) {
+                headers.insert(X_FORWARDED_FOR, value);
+            }
+        }
+        if let Ok(host) = HeaderValue::from_str(self.authority.as_str()) {
+            headers.insert(header::HOST, host);
+        }
+        headers
+    }
+
+    /// An idle pooled connection, or a new one.
+    async fn connect(&self, executor: &Arc<AnyExecutor>) -> Result<Connection, ProxyError> {
+        loop {
+            let idle = self
+                .idle
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .pop();
+            let Some(mut connection) = idle else {
+                break;
+            };
+            if connection.ready().await.is_ok() {
+                return Ok(connection);
+            }
+        }
+
+        let port = self.authority.port_u16().unwrap_or(80);
+        let stream = TcpStream::connect((self.authority.host().trim_matches(['[', ']']), port))
+            .await
+            .map_err(ProxyError::Connect)?;
+        let _ = stream.set_nodelay(true);
+        let (connection, driver) = http1::handshake(ConnectionWrapper(stream))
+            .await
+            .map_err(ProxyError::Upstream)?;
+        executor
+            .spawn(async move {
+                if let Err(error) = driver.await {
+                    tracing::debug!("upstream connection closed: {error}");
+                }
+            })
+            .detach();
+        Ok(connection)
+    }
+
+    fn release(&self, connection: Connection) {
+        if connection.is_closed() {
+            return;
+        }
+        let mut idle = self.idle.lock().unwrap_or_else(PoisonError::into_inner);
+        if idle.len() < MAX_IDLE {
+            idle.push(connection);
+        }
+    }
+}
+
+/// The part of the request path matched by the route's trailing catch-all parameter.
+fn remainder(request: &Request) -> Option<&str> {
+    let pattern = request.extensions().get::<MatchedPath>()?;
+    let name = pattern
+        .as_str()
+        .rsplit('/')
+        .next()?
+        .strip_prefix("{*")?
+        .strip_suffix('}')?;
+    request.extensions().get::<Params>()?.get_raw(name).ok()
+}
+
+/// Whether `path` has a `.` or `..` segment, either literal or percent-encoded.
+fn has_dot_segment(path: &str) -> bool {
+    path.split('/').any(|segment| {
+        let decoded = segment.replace("%2e", ".").replace("%2E", ".");
+        decoded == "." || decoded == ".."
+    })
+}
+
+/// Remove the headers that only apply to one connection (RFC 9110, section 7.6.1).
+fn strip_hop_by_hop(headers: &mut HeaderMap) {
+    let listed = headers
+        .get_all(header::CONNECTION)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|name| HeaderName::from_bytes(name.trim().as_bytes()).ok())
+        .collect::<Vec<_>>();
+    for name in listed {
+        headers.remove(name);
+    }
+    for name in [
+        header::CONNECTION,
+        KEEP_ALIVE,
+        header::PROXY_AUTHENTICATE,
+        header::PROXY_AUTHORIZATION,
+        header::TE,
+        header::TRAILER,
+        header::TRANSFER_ENCODING,
+        header::UPGRADE,
+    ] {
+        headers.remove(name);
+    }
+}
+
+/// A response body returning its connection to the pool once read to the end.
+struct PooledBody {
+    body: BodyDataStream<Incoming>,
+    connection: Option<Connection>,
+    upstream: Arc<Upstream>,
+}
+
+impl Stream for PooledBody {
+    type Item = Result<Bytes, BodyError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let poll = Pin::new(&mut self.body)
+            .poll_next(cx)
+            .map_err(|error| BodyError::Other(Box::new(error)));
+        if matches!(poll, Poll::Ready(None)) {
+            if let Some(connection) = self.connection.take() {
+                self.upstream.release(connection);
+            }
+        }
+        poll
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{has_dot_segment, strip_hop_by_hop, Proxy};
+    use crate::{
+        header::{HeaderMap, HeaderValue},
+        routing::Route,
+        test::TestClient,
+        Body, Request, StatusCode,
+    };
+
+    #[test]
+    fn strips_hop_by_hop_headers() {
+        let mut headers = HeaderMap::new();
+        for (name, value) in [
+            ("connection", "keep-alive, X-Internal"),
+            ("keep-alive", "timeout=5"),
+            ("x-internal", "1"),
+            ("transfer-encoding", "chunked"),
+            ("upgrade", "websocket"),
+            ("te", "trailers"),
+            ("accept", "text/html"),
+        ] {
+            headers.append(name, HeaderValue::from_static(value));
+        }
+        strip_hop_by_hop(&mut headers);
+        assert_eq!(headers.len(), 1);
+        assert_eq!(headers["accept"], "text/html");
+    }
+
+    #[test]
+    fn appends_the_remainder_and_query() {
+        let proxy = Proxy::new("http://127.0.0.1:9000/v1/");
+        let mut request = Request::new(Body::empty());
+        *request.uri_mut() = "/legacy?page=2".parse().unwrap();
+        let uri = proxy.upstream.uri_for(&request).unwrap();
+        assert_eq!(uri, "/v1?page=2");
+
+        let proxy = Proxy::new("http://127.0.0.1:9000");
+        *request.uri_mut() = "/legacy".parse().unwrap();
+        let uri = proxy.upstream.uri_for(&request).unwrap();
+        assert_eq!(uri, "/");
+    }
+
+    #[test]
+    fn detects_dot_segments() {
+        for path in [
+            "..",
+            "a/../b",
+            "./a",
+            "a/.",
+            "%2e%2e/admin",
+            "a/.%2E",
+            "%2E/a",
+        ] {
+            assert!(has_dot_segment(path), "{path}");
+        }
+        for path in [
+            "a/b",
+            "...",
+            "a..b/c",
+            ".well-known",
+            "%2e%2e%2e",
+            "%252e%252e",
+        ] {
+            assert!(!has_dot_segment(path), "{path}");
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_dot_segments_before_forwarding() {
+        // Nothing listens on the upstream: the request must be refused before connecting.
+        let proxy = Proxy::new("http://127.0.0.1:9/v1").mount("/legacy");
+        let client = TestClient::new(Route::new((proxy,)).build());
+        for path in [
+            "/legacy/../admin",
+            "/legacy/a/%2e%2e/%2E%2e/admin",
+            "/legacy/./users",
+        ] {
+            let response = client.get(path).send().await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{path}");
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "must use the `http` scheme")]
+    fn rejects_https_upstreams() {
+        let _ = Proxy::new("https://example.com");
+    }
+}
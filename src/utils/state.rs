@@ -1,13 +1,52 @@
 //! State utilities module.
 //! It provides a middleware and extractor for application state sharing.
+//!
+//! A single composite state can provide every component a handler needs. Derive
+//! [`FromRef`](macro@crate::FromRef) on it, install it with [`CompositeState`], and extract
+//! each field on its own:
+//!
+//! ```no_run
+//! use skyzen::{
+//!     routing::{CreateRouteNode, Route},
+//!     utils::{state::CompositeState, State},
+//!     FromRef, Result,
+//! };
+//!
+//! #[derive(Clone)]
+//! struct DbPool;
+//!
+//! #[derive(Clone)]
+//! struct Config {
+//!     greeting: &'static str,
+//! }
+//!
+//! #[derive(Clone, FromRef)]
+//! struct AppState {
+//!     db: DbPool,
+//!     config: Config,
+//! }
+//!
+//! async fn hello(State(config): State<Config>) -> Result<&'static str> {
+//!     Ok(config.greeting)
+//! }
+//!
+//! let app = AppState {
+//!     db: DbPool,
+//!     config: Config { greeting: "hello" },
+//! };
+//! let router = Route::new(("/hello".at(hello),))
+//!     .middleware(CompositeState(app))
+//!     .build();
+//! ```
 
 use std::{
     convert::Infallible,
+    fmt,
     ops::{Deref, DerefMut},
 };
 
 use http::StatusCode;
-use http_kit::{http_error, middleware::MiddlewareError, Middleware, Request, Response};
+use http_kit::{middleware::MiddlewareError, Extensions, HttpError, Middleware, Request, Response};
 use skyzen_core::Extractor;
 
 /// Share the state of application.
@@ -27,10 +66,41 @@ impl<T: Send + Sync + Clone + 'static> DerefMut for State<T> {
     }
 }
 
-http_error!(
-    /// An error occurred when extracting a missing state from the request extensions.
-    pub StateNotExist, StatusCode::INTERNAL_SERVER_ERROR, "This state does not exist"
-);
+/// An error occurred when extracting a missing state from the request extensions.
+#[derive(Debug)]
+pub struct StateNotExist {
+    type_name: &'static str,
+}
+
+impl StateNotExist {
+    /// Create the error for a missing `State<T>`.
+    #[must_use]
+    pub fn of<T: 'static>() -> Self {
+        Self {
+            type_name: core::any::type_name::<T>(),
+        }
+    }
+
+    /// Name of the requested state type.
+    #[must_use]
+    pub const fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+}
+
+impl fmt::Display for StateNotExist {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "State `{}` does not exist", self.type_name)
+    }
+}
+
+impl core::error::Error for StateNotExist {}
+
+impl HttpError for StateNotExist {
+    fn status(&self) -> StatusCode {
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+}
 
 impl<T: Send + Sync + Clone + 'static> Extractor for State<T> {
     type Error = StateNotExist;
@@ -39,7 +109,7 @@ impl<T: Send + Sync + Clone + 'static> Extractor for State<T> {
             .extensions()
             .get::<Self>()
             .cloned()
-            .ok_or(StateNotExist::new())
+            .ok_or_else(StateNotExist::of::<T>)
     }
 }
 
@@ -56,3 +126,146 @@ impl<T: Send + Sync + Clone + 'static> Middleware for State<T> {
             .map_err(MiddlewareError::Endpoint)
     }
 }
+
+/// Build a piece of state out of a composite application state.
+///
+/// Derive it with [`FromRef`](macro@crate::FromRef) to get an impl for every field.
+pub trait FromRef<S> {
+    /// Produce the value from a reference to the composite state.
+    fn from_ref(input: &S) -> Self;
+}
+
+impl<T: Clone> FromRef<T> for T {
+    fn from_ref(input: &T) -> Self {
+        input.clone()
+    }
+}
+
+/// A composite state that can install its components as individual [`State`]s.
+///
+/// Implemented by [`#[derive(FromRef)]`](macro@crate::FromRef).
+pub trait Substates: Send + Sync + Clone + 'static {
+    /// Insert `State<T>` for every component `T: FromRef<Self>`.
+    fn insert_substates(&self, extensions: &mut Extensions);
+}
+
+/// Middleware installing a composite state as `State<S>` along with each of its substates.
+#[derive(Debug, Clone)]
+pub struct CompositeState<S: Substates>(pub S);
+
+impl<S: Substates> Middleware for CompositeState<S> {
+    type Error = Infallible;
+    async fn handle<N: http_kit::Endpoint>(
+        &mut self,
+        request: &mut Request,
+        mut next: N,
+    ) -> Result<Response, MiddlewareError<N::Error, Self::Error>> {
+        let extensions = request.extensions_mut();
+        self.0.insert_substates(extensions);
+        extensions.insert(State(self.0.clone()));
+        next.respond(request)
+            .await
+            .map_err(MiddlewareError::Endpoint)
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::{CompositeState, State, StateNotExist};
+    use crate::{
+        routing::{CreateRouteNode, Route},
+        Body, FromRef, HttpError, Method, Request, Result, StatusCode,
+    };
+    use skyzen_core::Extractor;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct DbPool(&'static str);
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Config {
+        name: &'static str,
+    }
+
+    #[derive(Clone, FromRef)]
+    struct AppState {
+        db: DbPool,
+        config: Config,
+        #[from_ref(skip)]
+        #[allow(dead_code)]
+        secret: String,
+    }
+
+    fn get(path: &str) -> Request {
+        let mut request = Request::new(Body::empty());
+        *request.method_mut() = Method::GET;
+        *request.uri_mut() = path.parse().unwrap();
+        request
+    }
+
+    #[tokio::test]
+    async fn extracts_substates_through_the_router() {
+        let app = AppState {
+            db: DbPool("postgres://"),
+            config: Config { name: "skyzen" },
+            secret: "hunter2".to_owned(),
+        };
+        let router = Route::new((
+            "/db".at(|State(db): State<DbPool>| async move { Result::Ok(db.0) }),
+            "/config".at(
+                |State(config): State<Config>, State(app): State<AppState>| async move {
+                    Result::Ok(format!("{} {}", config.name, app.db.0))
+                },
+            ),
+        ))
+        .middleware(CompositeState(app))
+        .build();
+
+        for (path, expected) in [("/db", "postgres://"), ("/config", "skyzen postgres://")] {
+            let response = router.clone().go(get(path)).await.unwrap();
+            let body = response.into_body().into_string().await.unwrap();
+            assert_eq!(body, expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn nested_states_shadow_outer_ones() {
+        let nearest = || |State(n): State<usize>| async move { Result::Ok(n.to_string()) };
+        let router = Route::new((
+            "/outer".at(nearest()),
+            "/v1".route((Route::new(("/inner".at(nearest()),)).middleware(State(2usize)),)),
+            Route::new((
+                "/v2/inner".at(nearest()),
+                Route::new(("/v2/deep".at(nearest()),)).middleware(State(4usize)),
+            ))
+            .middleware(State(3usize)),
+            // The first middleware attached to a route is the closest to its endpoints.
+            Route::new(("/stacked".at(nearest()),))
+                .middleware(State(5usize))
+                .middleware(State(6usize)),
+        ))
+        .middleware(State(1usize))
+        .build();
+
+        for (path, expected) in [
+            ("/outer", "1"),
+            ("/v1/inner", "2"),
+            ("/v2/inner", "3"),
+            ("/v2/deep", "4"),
+            ("/stacked", "5"),
+        ] {
+            let response = router.clone().go(get(path)).await.unwrap();
+            let body = response.into_body().into_string().await.unwrap();
+            assert_eq!(body, expected, "{path}");
+        }
+    }
+
+    #[tokio::test]
+    async fn missing_state_names_the_type() {
+        let mut request = get("/");
+        let error = State::<String>::extract(&mut request).await.unwrap_err();
+        assert_eq!(error.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(error.type_name(), "alloc::string::String");
+        assert_eq!(error.to_string(), StateNotExist::of::<String>().to_string());
+        assert!(error.to_string().contains("String"));
+    }
+}
@@ -0,0 +1,173 @@
+//! Fan-out hub relaying messages between connected websockets.
+
+use std::{
+    pin::Pin,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    task::{Context, Poll},
+};
+
+use async_channel::{Receiver, Sender, TrySendError};
+use futures_core::Stream;
+use futures_util::{
+    future::{select, Either},
+    StreamExt,
+};
+use http_kit::ws::WebSocketMessage;
+
+use super::WebSocket;
+
+/// A cloneable hub that relays every message to all of its subscribers.
+///
+/// Each subscriber buffers up to `capacity` messages. A subscriber that falls further behind
+/// is dropped, so one slow peer cannot stall the others. The hub spawns no tasks and works on
+/// any runtime.
+///
+/// ```no_run
+/// use skyzen::{
+///     routing::{CreateRouteNode, Route},
+///     websocket::Broadcast,
+/// };
+///
+/// let hub = Broadcast::new(64);
+/// let router = Route::new(("/chat".ws(move |socket| hub.clone().handle(socket)),)).build();
+/// ```
+#[derive(Debug, Clone)]
+pub struct Broadcast {
+    subscribers: Arc<Mutex<Vec<Sender<WebSocketMessage>>>>,
+    capacity: usize,
+}
+
+impl Broadcast {
+    /// Create a hub buffering up to `capacity` messages per subscriber.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    #[must_use]
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "Broadcast capacity must be non-zero");
+        Self {
+            subscribers: Arc::default(),
+            capacity,
+        }
+    }
+
+    fn subscribers(&self) -> MutexGuard<'_, Vec<Sender<WebSocketMessage>>> {
+        self.subscribers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Receive every message sent to the hub from now on.
+    ///
+    /// The stream ends if the subscriber lags more than `capacity` messages behind.
+    #[must_use]
+    pub fn subscribe(&self) -> Subscription {
+        let (sender, receiver) = async_channel::bounded(self.capacity);
+        self.subscribers().push(sender);
+        Subscription {
+            receiver: Box::pin(receiver),
+        }
+    }
+
+    /// Send `message` to every subscriber, returning how many received it.
+    pub fn send(&self, message: impl Into<WebSocketMessage>) -> usize {
+        let message = message.into();
+        let mut subscribers = self.subscribers();
+        subscribers.retain(|subscriber| match subscriber.try_send(message.clone()) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) => {
+                subscriber.close();
+                false
+            }
+            Err(TrySendError::Closed(_)) => false,
+        });
+        subscribers.len()
+    }
+
+    /// Number of live subscribers.
+    #[must_use]
+    pub fn receiver_count(&self) -> usize {
+        let mut subscribers = self.subscribers();
+        subscribers.retain(|subscriber| !subscriber.is_closed());
+        subscribers.len()
+    }
+
+    /// Relay text and binary messages from `socket` to the hub and from the hub to `socket`,
+    /// until either side closes or the socket lags behind.
+    ///
+    /// Messages are also delivered back to the socket that sent them.
+    #[cfg_attr(target_arch = "wasm32", allow(clippy::future_not_send))]
+    pub async fn handle(self, socket: WebSocket) {
+        let mut subscription = self.subscribe();
+        let (mut sender, mut receiver) = socket.split();
+        loop {
+            match select(receiver.next(), subscription.next()).await {
+                Either::Left((Some(Ok(message)), _)) => match message {
+                    WebSocketMessage::Text(_) | WebSocketMessage::Binary(_) => {
+                        self.send(message);
+                    }
+                    WebSocketMessage::Close => break,
+                    _ => {}
+                },
+                Either::Left(_) | Either::Right((None, _)) => break,
+                Either::Right((Some(message), _)) => {
+                    if sender.send_message(message).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+        let _ = sender.close(None).await;
+    }
+}
+
+/// Stream of messages returned from [`Broadcast::subscribe`].
+#[derive(Debug)]
+pub struct Subscription {
+    receiver: Pin<Box<Receiver<WebSocketMessage>>>,
+}
+
+impl Stream for Subscription {
+    type Item = WebSocketMessage;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.as_mut().poll_next(cx)
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::Broadcast;
+    use futures_util::StreamExt;
+
+    #[tokio::test]
+    async fn fans_out_to_every_subscriber() {
+        let hub = Broadcast::new(4);
+        let mut first = hub.subscribe();
+        let mut second = hub.clone().subscribe();
+        assert_eq!(hub.send("hello"), 2);
+        assert_eq!(first.next().await.unwrap().into_text().unwrap(), "hello");
+        assert_eq!(second.next().await.unwrap().into_text().unwrap(), "hello");
+
+        drop(second);
+        assert_eq!(hub.receiver_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn drops_lagging_subscribers() {
+        let hub = Broadcast::new(2);
+        let mut slow = hub.subscribe();
+        let mut fast = hub.subscribe();
+        for i in 0..3 {
+            let expected = if i < 2 { 2 } else { 1 };
+            assert_eq!(hub.send(i.to_string()), expected);
+            assert!(fast.next().await.is_some());
+        }
+
+        // Buffered messages are still delivered before the stream ends.
+        assert_eq!(slow.next().await.unwrap().into_text().unwrap(), "0");
+        assert_eq!(slow.next().await.unwrap().into_text().unwrap(), "1");
+        assert!(slow.next().await.is_none());
+    }
+}
@@ -6,7 +6,7 @@
 //!
 //! **Platform Differences:**
 //! - WASM: 1 MiB message size limit (platform imposed)
-//! - WASM: No custom ping/pong frame control
+//! - WASM: No custom ping/pong frame control; `ping_interval`/`idle_timeout` are no-ops
 //! - WASM: Event-driven model vs native stream model
 //!
 //! # Quick Start
@@ -79,6 +79,17 @@
 //! - **Binary**: `send_binary(bytes)` for binary data
 //! - **Ping/Pong**: `send_ping(data)` and `send_pong(data)` (native only)
 //!
+//! # Broadcasting
+//!
+//! [`Broadcast`] relays every message to all connected sockets:
+//!
+//! ```no_run
+//! # use skyzen::routing::{CreateRouteNode, Route};
+//! # use skyzen::websocket::Broadcast;
+//! let hub = Broadcast::new(64);
+//! let chat = "/chat".ws(move |socket| hub.clone().handle(socket));
+//! ```
+//!
 //! # Protocol Negotiation
 //!
 //! ```no_run
@@ -109,15 +120,19 @@
 //! }
 //! ```
 
+mod broadcast;
 mod types;
 
 #[cfg(target_arch = "wasm32")]
 mod ffi;
 #[cfg(not(target_arch = "wasm32"))]
 mod native;
+#[cfg(all(not(target_arch = "wasm32"), any(test, feature = "test-utils")))]
+pub mod testing;
 #[cfg(target_arch = "wasm32")]
 mod wasm;
 
+pub use broadcast::{Broadcast, Subscription};
 pub use http_kit::ws::*;
 #[cfg(not(target_arch = "wasm32"))]
 pub use native::*;
@@ -16,12 +16,19 @@
 //!     })
 //! }
 //! ```
+//!
+//! The built-in runtime provides what the upgrade needs. A custom [`Server`](crate::Server)
+//! backend has to insert hyper's `OnUpgrade` and an `Arc<AnyExecutor>` running the upgraded
+//! connection into the request extensions; without the executor, the upgrade falls back to the
+//! one registered with `runtime::native::set_global_executor` and otherwise fails with
+//! [`WebSocketUpgradeError::MissingExecutor`].
 
 use crate::{
     header,
     websocket::types::{WebSocketCloseFrame, WebSocketError, WebSocketResult},
     Method, Request, Response, StatusCode,
 };
+use async_io::Timer;
 use async_tungstenite::{
     tungstenite::{
         protocol::{
@@ -35,7 +42,7 @@ use async_tungstenite::{
 };
 use executor_core::{AnyExecutor, Executor};
 use futures_core::Stream;
-use futures_util::Sink;
+use futures_util::{Sink, SinkExt};
 use http_kit::utils::{AsyncRead, AsyncWrite};
 use http_kit::{
     utils::{ByteStr, Bytes},
@@ -47,13 +54,26 @@ use hyper::{
 };
 use serde::Serialize;
 use skyzen_core::{Extractor, Responder};
-use std::sync::Arc;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, Mutex, MutexGuard, PoisonError, Weak,
+};
 use std::{
     pin::Pin,
     task::{Context, Poll},
+    time::{Duration, Instant},
 };
 use tracing::error;
 
+#[cfg(feature = "rt")]
+use crate::runtime::native::global_executor;
+
+/// Without the built-in runtime there is no global executor to fall back to.
+#[cfg(not(feature = "rt"))]
+const fn global_executor() -> Option<Arc<AnyExecutor>> {
+    None
+}
+
 const GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
 
 /// Errors that can occur during WebSocket upgrade.
@@ -89,17 +109,21 @@ pub enum WebSocketUpgradeError {
     /// The `OnUpgrade` extension is missing.
     #[error("Missing OnUpgrade extension", status = StatusCode::UPGRADE_REQUIRED)]
     MissingOnUpgrade,
+    /// Neither the request extensions nor the global executor provide an executor to run the
+    /// upgraded connection on.
+    #[error(
+        "Missing executor for the WebSocket connection",
+        status = StatusCode::INTERNAL_SERVER_ERROR
+    )]
+    MissingExecutor,
 }
 
 fn header_has_token(value: &header::HeaderValue, token: &str) -> bool {
-    value
-        .to_str()
-        .map(|value| {
-            value
-                .split(',')
-                .any(|part| part.trim().eq_ignore_ascii_case(token))
-        })
-        .unwrap_or(false)
+    value.to_str().is_ok_and(|value| {
+        value
+            .split(',')
+            .any(|part| part.trim().eq_ignore_ascii_case(token))
+    })
 }
 
 fn parse_protocols(value: Option<&header::HeaderValue>) -> Vec<String> {
@@ -115,6 +139,19 @@ fn parse_protocols(value: Option<&header::HeaderValue>) -> Vec<String> {
         .unwrap_or_default()
 }
 
+/// Names of the extensions offered in `Sec-WebSocket-Extensions`, without their parameters.
+fn parse_extensions(headers: &header::HeaderMap) -> Vec<String> {
+    headers
+        .get_all(header::SEC_WEBSOCKET_EXTENSIONS)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|offer| offer.split(';').next())
+        .map(|name| name.trim().to_ascii_lowercase())
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
 fn compute_accept_header(key: &header::HeaderValue) -> header::HeaderValue {
     use base64::engine::general_purpose::STANDARD;
     use base64::Engine as _;
@@ -130,7 +167,28 @@ fn compute_accept_header(key: &header::HeaderValue) -> header::HeaderValue {
 
 /// Upgraded connection wrapper that implements `futures_io` traits.
 #[derive(Debug)]
-pub struct UpgradedIo(Upgraded);
+pub struct UpgradedIo {
+    inner: UpgradedStream,
+    activity: Option<Arc<Activity>>,
+}
+
+/// Transport underneath an [`UpgradedIo`].
+#[derive(Debug)]
+enum UpgradedStream {
+    Hyper(Upgraded),
+    #[cfg(any(test, feature = "test-utils"))]
+    Memory(super::testing::MemoryIo),
+}
+
+#[cfg(any(test, feature = "test-utils"))]
+impl UpgradedIo {
+    pub(crate) const fn memory(io: super::testing::MemoryIo) -> Self {
+        Self {
+            inner: UpgradedStream::Memory(io),
+            activity: None,
+        }
+    }
+}
 
 impl AsyncRead for UpgradedIo {
     fn poll_read(
@@ -139,17 +197,25 @@ impl AsyncRead for UpgradedIo {
         buf: &mut [u8],
     ) -> Poll<Result<usize, std::io::Error>> {
         let this = self.get_mut();
-        let mut hyper_buf = ReadBuf::uninit(unsafe {
-            // SAFETY: We're converting &mut [u8] to &mut [MaybeUninit<u8>]
-            // This is safe because MaybeUninit<u8> has the same layout as u8
-            std::slice::from_raw_parts_mut(buf.as_mut_ptr().cast(), buf.len())
-        });
-        let cursor = hyper_buf.unfilled();
-        match Pin::new(&mut this.0).poll_read(cx, cursor) {
-            Poll::Ready(Ok(())) => Poll::Ready(Ok(hyper_buf.filled().len())),
-            Poll::Ready(Err(err)) => Poll::Ready(Err(err)),
-            Poll::Pending => Poll::Pending,
+        let result = match &mut this.inner {
+            UpgradedStream::Hyper(inner) => {
+                let mut hyper_buf = ReadBuf::uninit(unsafe {
+                    // SAFETY: We're converting &mut [u8] to &mut [MaybeUninit<u8>]
+                    // This is safe because MaybeUninit<u8> has the same layout as u8
+                    std::slice::from_raw_parts_mut(buf.as_mut_ptr().cast(), buf.len())
+                });
+                let cursor = hyper_buf.unfilled();
+                Pin::new(inner)
+                    .poll_read(cx, cursor)
+                    .map_ok(|()| hyper_buf.filled().len())
+            }
+            #[cfg(any(test, feature = "test-utils"))]
+            UpgradedStream::Memory(inner) => Pin::new(inner).poll_read(cx, buf),
+        };
+        if let (Poll::Ready(Ok(_)), Some(activity)) = (&result, &this.activity) {
+            activity.touch(&activity.last_read);
         }
+        result
     }
 }
 
@@ -159,24 +225,176 @@ impl AsyncWrite for UpgradedIo {
         cx: &mut Context<'_>,
         buf: &[u8],
     ) -> Poll<Result<usize, std::io::Error>> {
-        Pin::new(&mut self.get_mut().0).poll_write(cx, buf)
+        let this = self.get_mut();
+        let result = match &mut this.inner {
+            UpgradedStream::Hyper(inner) => Pin::new(inner).poll_write(cx, buf),
+            #[cfg(any(test, feature = "test-utils"))]
+            UpgradedStream::Memory(inner) => Pin::new(inner).poll_write(cx, buf),
+        };
+        if let (Poll::Ready(Ok(_)), Some(activity)) = (&result, &this.activity) {
+            activity.touch(&activity.last_write);
+        }
+        result
     }
 
     fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), std::io::Error>> {
-        Pin::new(&mut self.get_mut().0).poll_flush(cx)
+        match &mut self.get_mut().inner {
+            UpgradedStream::Hyper(inner) => Pin::new(inner).poll_flush(cx),
+            #[cfg(any(test, feature = "test-utils"))]
+            UpgradedStream::Memory(inner) => Pin::new(inner).poll_flush(cx),
+        }
     }
 
     fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), std::io::Error>> {
-        Pin::new(&mut self.get_mut().0).poll_shutdown(cx)
+        match &mut self.get_mut().inner {
+            UpgradedStream::Hyper(inner) => Pin::new(inner).poll_shutdown(cx),
+            #[cfg(any(test, feature = "test-utils"))]
+            UpgradedStream::Memory(inner) => Pin::new(inner).poll_close(cx),
+        }
     }
 }
 
 type NativeIo = UpgradedIo;
 
+/// Time of the last bytes read from and written to an upgraded connection.
+#[derive(Debug)]
+struct Activity {
+    started: Instant,
+    last_read: AtomicU64,
+    last_write: AtomicU64,
+}
+
+impl Activity {
+    fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            last_read: AtomicU64::new(0),
+            last_write: AtomicU64::new(0),
+        }
+    }
+
+    fn now(&self) -> u64 {
+        u64::try_from(self.started.elapsed().as_millis()).unwrap_or(u64::MAX)
+    }
+
+    fn touch(&self, slot: &AtomicU64) {
+        slot.store(self.now(), Ordering::Relaxed);
+    }
+
+    fn since(&self, slot: &AtomicU64) -> Duration {
+        Duration::from_millis(self.now().saturating_sub(slot.load(Ordering::Relaxed)))
+    }
+}
+
+/// Keep-alive options applied to an upgraded connection.
+#[derive(Debug, Clone, Copy, Default)]
+struct KeepAlive {
+    ping_interval: Option<Duration>,
+    idle_timeout: Option<Duration>,
+}
+
+impl KeepAlive {
+    const fn is_enabled(&self) -> bool {
+        self.ping_interval.is_some() || self.idle_timeout.is_some()
+    }
+}
+
+type SharedSink = Mutex<AsyncWebSocketSender<NativeIo>>;
+
+/// Write half shared between the user's socket and the keep-alive task.
+#[derive(Clone)]
+struct SharedSender(Arc<SharedSink>);
+
+impl SharedSender {
+    fn lock(&self) -> MutexGuard<'_, AsyncWebSocketSender<NativeIo>> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    async fn close(
+        &mut self,
+        frame: Option<TungsteniteCloseFrame>,
+    ) -> Result<(), TungsteniteError> {
+        self.send(TungsteniteMessage::Close(frame)).await
+    }
+}
+
+impl Sink<TungsteniteMessage> for SharedSender {
+    type Error = TungsteniteError;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut *self.lock()).poll_ready(cx)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: TungsteniteMessage) -> Result<(), Self::Error> {
+        Pin::new(&mut *self.lock()).start_send(item)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut *self.lock()).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut *self.lock()).poll_close(cx)
+    }
+}
+
+/// Ping the peer when the connection has been quiet and close it once it stops responding.
+///
+/// Exits when every handle to the write half is dropped or the connection fails.
+async fn drive_keep_alive(sender: Weak<SharedSink>, activity: Arc<Activity>, options: KeepAlive) {
+    loop {
+        let since_read = activity.since(&activity.last_read);
+        let mut since_write = activity.since(&activity.last_write);
+        let Some(sender) = sender.upgrade() else {
+            return;
+        };
+        let mut sender = SharedSender(sender);
+
+        if options
+            .idle_timeout
+            .is_some_and(|timeout| since_read >= timeout)
+        {
+            let frame = TungsteniteCloseFrame {
+                code: CloseCode::Away,
+                reason: Utf8Bytes::from_static("idle timeout"),
+            };
+            let _ = sender.close(Some(frame)).await;
+            return;
+        }
+        if options
+            .ping_interval
+            .is_some_and(|interval| since_write >= interval)
+        {
+            if sender
+                .send(TungsteniteMessage::Ping(Bytes::new()))
+                .await
+                .is_err()
+            {
+                return;
+            }
+            since_write = Duration::ZERO;
+        }
+        drop(sender);
+
+        let next_ping = options
+            .ping_interval
+            .map(|interval| interval.saturating_sub(since_write));
+        let next_timeout = options
+            .idle_timeout
+            .map(|timeout| timeout.saturating_sub(since_read));
+        let Some(wait) = next_ping.into_iter().chain(next_timeout).min() else {
+            return;
+        };
+        Timer::after(wait.max(Duration::from_millis(1))).await;
+    }
+}
+
 /// Stream representing a WebSocket connection handled by `async-tungstenite`.
 pub struct WebSocket {
-    inner: WebSocketStream<NativeIo>,
+    sender: SharedSender,
+    receiver: AsyncWebSocketReceiver<NativeIo>,
     config: WebSocketConfig,
+    close_frame: Option<WebSocketCloseFrame>,
 }
 
 impl WebSocket {
@@ -185,10 +403,16 @@ impl WebSocket {
         role: Role,
         config: WebSocketConfig,
     ) -> Self {
-        let inner =
+        let (sender, receiver) =
             WebSocketStream::from_raw_socket(stream, role, Some(to_tungstenite_config(&config)))
-                .await;
-        Self { inner, config }
+                .await
+                .split();
+        Self {
+            sender: SharedSender(Arc::new(Mutex::new(sender))),
+            receiver,
+            config,
+            close_frame: None,
+        }
     }
 
     /// Serialize a value to JSON text and send it over the websocket connection.
@@ -251,7 +475,7 @@ impl WebSocket {
     ///
     /// Returns [`WebSocketError::Transport`] if the connection fails to send the message.
     pub async fn send_message(&mut self, message: WebSocketMessage) -> WebSocketResult<()> {
-        self.inner
+        self.sender
             .send(to_tungstenite_msg(message))
             .await
             .map_err(WebSocketError::from)
@@ -259,7 +483,8 @@ impl WebSocket {
 
     /// Receive and deserialize the next JSON message.
     ///
-    /// Skips non-text messages and returns None when connection closes.
+    /// Skips non-text messages and returns None when connection closes; the peer's close code
+    /// is then available from [`close_reason`](Self::close_reason).
     ///
     /// # Example
     ///
@@ -272,6 +497,9 @@ impl WebSocket {
     /// while let Some(Ok(data)) = socket.recv_json::<MyData>().await {
     ///     println!("Received: {}", data.value);
     /// }
+    /// if let Some(frame) = socket.close_reason() {
+    ///     println!("Closed with {}: {}", frame.code, frame.reason);
+    /// }
     /// # }
     /// ```
     #[cfg(feature = "json")]
@@ -295,35 +523,46 @@ impl WebSocket {
     }
 
     /// Access the underlying websocket configuration.
+    #[must_use]
     pub const fn get_config(&self) -> &WebSocketConfig {
         &self.config
     }
 
+    /// The close code and reason sent by the peer.
+    ///
+    /// Populated once a [`WebSocketMessage::Close`] has been received, so check it after the
+    /// stream ends. Returns `None` if the peer closed without a code.
+    #[must_use]
+    pub fn close_reason(&self) -> Option<WebSocketCloseFrame> {
+        self.close_frame.clone()
+    }
+
     /// Close the websocket connection gracefully.
     ///
     /// # Errors
     ///
     /// Returns [`WebSocketError::Transport`] if the connection fails to close.
     pub async fn close(&mut self, close_frame: Option<WebSocketCloseFrame>) -> WebSocketResult<()> {
-        self.inner
+        self.sender
             .close(close_frame.map(Into::into))
             .await
             .map_err(WebSocketError::from)
     }
 
     /// Split the websocket into independent sender and receiver halves.
+    #[must_use]
     pub fn split(self) -> (WebSocketSender, WebSocketReceiver) {
         let config = self.config.clone();
-        let (inner_sink, inner_stream) = self.inner.split();
 
         (
             WebSocketSender {
-                inner: inner_sink,
+                inner: self.sender,
                 config: config.clone(),
             },
             WebSocketReceiver {
-                inner: inner_stream,
+                inner: self.receiver,
                 config,
+                close_frame: self.close_frame,
             },
         )
     }
@@ -338,9 +577,12 @@ impl std::fmt::Debug for WebSocket {
 impl Stream for WebSocket {
     type Item = WebSocketResult<WebSocketMessage>;
 
-    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        match Pin::new(&mut self.inner).poll_next(cx) {
-            Poll::Ready(Some(Ok(message))) => Poll::Ready(Some(Ok(to_websocket_msg(message)))),
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        match Pin::new(&mut this.receiver).poll_next(cx) {
+            Poll::Ready(Some(Ok(message))) => {
+                Poll::Ready(Some(Ok(to_websocket_msg(message, &mut this.close_frame))))
+            }
             Poll::Ready(Some(Err(error))) => Poll::Ready(Some(Err(error.into()))),
             Poll::Ready(None) => Poll::Ready(None),
             Poll::Pending => Poll::Pending,
@@ -355,7 +597,7 @@ impl Sink<WebSocketMessage> for WebSocket {
         mut self: Pin<&mut Self>,
         cx: &mut Context<'_>,
     ) -> Poll<std::result::Result<(), Self::Error>> {
-        Pin::new(&mut self.inner)
+        Pin::new(&mut self.sender)
             .poll_ready(cx)
             .map_err(WebSocketError::from)
     }
@@ -364,7 +606,7 @@ impl Sink<WebSocketMessage> for WebSocket {
         mut self: Pin<&mut Self>,
         item: WebSocketMessage,
     ) -> std::result::Result<(), Self::Error> {
-        Pin::new(&mut self.inner)
+        Pin::new(&mut self.sender)
             .start_send(to_tungstenite_msg(item))
             .map_err(WebSocketError::from)
     }
@@ -373,7 +615,7 @@ impl Sink<WebSocketMessage> for WebSocket {
         mut self: Pin<&mut Self>,
         cx: &mut Context<'_>,
     ) -> Poll<std::result::Result<(), Self::Error>> {
-        Pin::new(&mut self.inner)
+        Pin::new(&mut self.sender)
             .poll_flush(cx)
             .map_err(WebSocketError::from)
     }
@@ -382,7 +624,7 @@ impl Sink<WebSocketMessage> for WebSocket {
         mut self: Pin<&mut Self>,
         cx: &mut Context<'_>,
     ) -> Poll<std::result::Result<(), Self::Error>> {
-        Pin::new(&mut self.inner)
+        Pin::new(&mut self.sender)
             .poll_close(cx)
             .map_err(WebSocketError::from)
     }
@@ -390,7 +632,7 @@ impl Sink<WebSocketMessage> for WebSocket {
 
 /// Sender half returned from [`WebSocket::split`].
 pub struct WebSocketSender {
-    inner: AsyncWebSocketSender<NativeIo>,
+    inner: SharedSender,
     config: WebSocketConfig,
 }
 
@@ -530,6 +772,7 @@ impl Sink<WebSocketMessage> for WebSocketSender {
 pub struct WebSocketReceiver {
     inner: AsyncWebSocketReceiver<NativeIo>,
     config: WebSocketConfig,
+    close_frame: Option<WebSocketCloseFrame>,
 }
 
 impl WebSocketReceiver {
@@ -561,6 +804,12 @@ impl WebSocketReceiver {
     pub const fn get_config(&self) -> &WebSocketConfig {
         &self.config
     }
+
+    /// The close code and reason sent by the peer, see [`WebSocket::close_reason`].
+    #[must_use]
+    pub fn close_reason(&self) -> Option<WebSocketCloseFrame> {
+        self.close_frame.clone()
+    }
 }
 
 impl std::fmt::Debug for WebSocketReceiver {
@@ -572,9 +821,12 @@ impl std::fmt::Debug for WebSocketReceiver {
 impl Stream for WebSocketReceiver {
     type Item = WebSocketResult<WebSocketMessage>;
 
-    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        match Pin::new(&mut self.inner).poll_next(cx) {
-            Poll::Ready(Some(Ok(message))) => Poll::Ready(Some(Ok(to_websocket_msg(message)))),
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        match Pin::new(&mut this.inner).poll_next(cx) {
+            Poll::Ready(Some(Ok(message))) => {
+                Poll::Ready(Some(Ok(to_websocket_msg(message, &mut this.close_frame))))
+            }
             Poll::Ready(Some(Err(error))) => Poll::Ready(Some(Err(error.into()))),
             Poll::Ready(None) => Poll::Ready(None),
             Poll::Pending => Poll::Pending,
@@ -585,10 +837,12 @@ impl Stream for WebSocketReceiver {
 /// Helper that contains the state required to accept a WebSocket connection.
 pub struct WebSocketUpgrade {
     key: header::HeaderValue,
-    on_upgrade: OnUpgrade,
+    source: UpgradeSource,
     requested_protocols: Vec<String>,
     response_protocol: Option<String>,
+    offered_extensions: Vec<String>,
     config: WebSocketConfig,
+    keep_alive: KeepAlive,
     executor: Option<Arc<AnyExecutor>>,
 }
 
@@ -597,7 +851,9 @@ impl std::fmt::Debug for WebSocketUpgrade {
         f.debug_struct("WebSocketUpgrade")
             .field("requested_protocols", &self.requested_protocols)
             .field("response_protocol", &self.response_protocol)
+            .field("offered_extensions", &self.offered_extensions)
             .field("config", &self.config)
+            .field("keep_alive", &self.keep_alive)
             .finish_non_exhaustive()
     }
 }
@@ -632,6 +888,15 @@ impl WebSocketUpgrade {
         self
     }
 
+    /// Extensions offered by the client in `Sec-WebSocket-Extensions`, lowercased and without
+    /// their parameters, such as `permessage-deflate`.
+    ///
+    /// None of them are accepted in the handshake yet, so clients fall back to plain frames.
+    #[must_use]
+    pub fn offered_extensions(&self) -> &[String] {
+        &self.offered_extensions
+    }
+
     /// Set the maximum incoming message size accepted by the websocket.
     ///
     /// Pass `None` to disable the limit enforced by the backend implementation.
@@ -641,6 +906,28 @@ impl WebSocketUpgrade {
         self
     }
 
+    /// Send a ping whenever nothing has been written for `interval`.
+    ///
+    /// Pings are sent by a background task, so they keep flowing while the handler is busy.
+    /// Load balancers commonly drop connections after about a minute of silence.
+    #[must_use]
+    pub const fn ping_interval(mut self, interval: Duration) -> Self {
+        self.keep_alive.ping_interval = Some(interval);
+        self
+    }
+
+    /// Close the connection with code 1001 (going away) when nothing has been received for
+    /// `timeout`.
+    ///
+    /// Incoming frames, including pongs, are only observed while the socket is being read, so
+    /// keep polling it. Combine with [`ping_interval`](Self::ping_interval) to detect dead
+    /// peers on otherwise quiet connections.
+    #[must_use]
+    pub const fn idle_timeout(mut self, timeout: Duration) -> Self {
+        self.keep_alive.idle_timeout = Some(timeout);
+        self
+    }
+
     /// Finalize the handshake and start handling the upgraded socket with `callback`.
     pub fn on_upgrade<F, Fut>(self, callback: F) -> WebSocketUpgradeResponder
     where
@@ -663,7 +950,7 @@ fn upgrade(request: &mut Request) -> Result<WebSocketUpgrade, WebSocketUpgradeEr
     if request.method() != Method::GET {
         return Err(WebSocketUpgradeError::MethodNotAllowed);
     }
-    let (key, requested_protocols) = {
+    let (key, requested_protocols, offered_extensions) = {
         let headers = request.headers();
 
         let key = headers
@@ -685,8 +972,7 @@ fn upgrade(request: &mut Request) -> Result<WebSocketUpgrade, WebSocketUpgradeEr
 
         if !upgrade_header
             .to_str()
-            .map(|value| value.eq_ignore_ascii_case("websocket"))
-            .unwrap_or(false)
+            .is_ok_and(|value| value.eq_ignore_ascii_case("websocket"))
         {
             return Err(WebSocketUpgradeError::InvalidUpgradeHeader);
         }
@@ -700,27 +986,49 @@ fn upgrade(request: &mut Request) -> Result<WebSocketUpgrade, WebSocketUpgradeEr
 
         let requested_protocols = parse_protocols(headers.get(header::SEC_WEBSOCKET_PROTOCOL));
 
-        (key, requested_protocols)
+        (key, requested_protocols, parse_extensions(headers))
     };
 
-    let on_upgrade = request
-        .extensions_mut()
-        .remove::<OnUpgrade>()
-        .ok_or(WebSocketUpgradeError::MissingOnUpgrade)?;
+    let source = take_upgrade_source(request)?;
 
     // Extract executor from request extensions (injected by the runtime)
     let executor = request.extensions_mut().remove::<Arc<AnyExecutor>>();
 
     Ok(WebSocketUpgrade {
         key,
-        on_upgrade,
+        source,
         requested_protocols,
         response_protocol: None,
+        offered_extensions,
         config: WebSocketConfig::default(),
+        keep_alive: KeepAlive::default(),
         executor,
     })
 }
 
+/// Where the upgraded connection comes from once the handshake response is sent.
+enum UpgradeSource {
+    Hyper(OnUpgrade),
+    #[cfg(any(test, feature = "test-utils"))]
+    Memory(super::testing::MemoryUpgrade),
+}
+
+fn take_upgrade_source(request: &mut Request) -> Result<UpgradeSource, WebSocketUpgradeError> {
+    #[cfg(any(test, feature = "test-utils"))]
+    if let Some(memory) = request
+        .extensions_mut()
+        .remove::<super::testing::MemoryUpgrade>()
+    {
+        return Ok(UpgradeSource::Memory(memory));
+    }
+
+    request
+        .extensions_mut()
+        .remove::<OnUpgrade>()
+        .map(UpgradeSource::Hyper)
+        .ok_or(WebSocketUpgradeError::MissingOnUpgrade)
+}
+
 impl Extractor for WebSocketUpgrade {
     type Error = WebSocketUpgradeError;
     async fn extract(request: &mut Request) -> Result<Self, Self::Error> {
@@ -778,29 +1086,63 @@ impl Responder for WebSocketUpgradeResponder {
             }
         }
 
-        if let Some(callback) = self.callback.take() {
-            let on_upgrade = self.upgrade.on_upgrade.clone();
-            let config = self.upgrade.config.clone();
-            let executor = self
-                .upgrade
-                .executor
-                .take()
-                .expect("Executor must be set by the HTTP backend");
-
-            executor
-                .spawn(async move {
-                    match on_upgrade.await {
-                        Ok(upgraded) => {
-                            let io = UpgradedIo(upgraded);
-                            let stream = WebSocket::from_raw_socket(io, Role::Server, config).await;
-                            callback(stream).await;
+        let Some(callback) = self.callback.take() else {
+            return Ok(());
+        };
+        let config = self.upgrade.config.clone();
+        let keep_alive = self.upgrade.keep_alive;
+
+        match &self.upgrade.source {
+            UpgradeSource::Hyper(on_upgrade) => {
+                let on_upgrade = on_upgrade.clone();
+                let executor = self
+                    .upgrade
+                    .executor
+                    .take()
+                    .or_else(global_executor)
+                    .ok_or(WebSocketUpgradeError::MissingExecutor)?;
+
+                let driver_executor = executor.clone();
+
+                executor
+                    .spawn(async move {
+                        match on_upgrade.await {
+                            Ok(upgraded) => {
+                                let activity =
+                                    keep_alive.is_enabled().then(|| Arc::new(Activity::new()));
+                                let io = UpgradedIo {
+                                    inner: UpgradedStream::Hyper(upgraded),
+                                    activity: activity.clone(),
+                                };
+                                let stream =
+                                    WebSocket::from_raw_socket(io, Role::Server, config).await;
+                                if let Some(activity) = activity {
+                                    let sender = Arc::downgrade(&stream.sender.0);
+                                    driver_executor
+                                        .spawn(drive_keep_alive(sender, activity, keep_alive))
+                                        .detach();
+                                }
+                                callback(stream).await;
+                            }
+                            Err(error) => {
+                                error!("WebSocket upgrade failed: {error}");
+                            }
                         }
-                        Err(error) => {
-                            error!("WebSocket upgrade failed: {error}");
-                        }
-                    }
-                })
-                .detach();
+                    })
+                    .detach();
+            }
+            #[cfg(any(test, feature = "test-utils"))]
+            UpgradeSource::Memory(memory) => {
+                if let Some(io) = memory.take_io() {
+                    let io = UpgradedIo {
+                        inner: UpgradedStream::Memory(io),
+                        activity: None,
+                    };
+                    memory.set_session(Box::pin(async move {
+                        callback(WebSocket::from_raw_socket(io, Role::Server, config).await).await;
+                    }));
+                }
+            }
         }
 
         Ok(())
@@ -813,6 +1155,7 @@ impl Responder for WebSocketUpgradeResponder {
             description: None,
             schema: None,
             content_type: None,
+            example: None,
         }])
     }
 }
@@ -838,7 +1181,11 @@ fn to_tungstenite_msg(message: WebSocketMessage) -> TungsteniteMessage {
     }
 }
 
-fn to_websocket_msg(message: TungsteniteMessage) -> WebSocketMessage {
+/// Convert an incoming message, recording the peer's close frame in `close_frame`.
+fn to_websocket_msg(
+    message: TungsteniteMessage,
+    close_frame: &mut Option<WebSocketCloseFrame>,
+) -> WebSocketMessage {
     match message {
         TungsteniteMessage::Text(text) => {
             WebSocketMessage::Text(unsafe { ByteStr::from_utf8_unchecked(Bytes::from(text)) })
@@ -846,7 +1193,10 @@ fn to_websocket_msg(message: TungsteniteMessage) -> WebSocketMessage {
         TungsteniteMessage::Binary(bytes) => WebSocketMessage::Binary(bytes),
         TungsteniteMessage::Ping(bytes) => WebSocketMessage::Ping(bytes),
         TungsteniteMessage::Pong(bytes) => WebSocketMessage::Pong(bytes),
-        TungsteniteMessage::Close(_) => WebSocketMessage::Close,
+        TungsteniteMessage::Close(frame) => {
+            *close_frame = frame.map(Into::into);
+            WebSocketMessage::Close
+        }
         TungsteniteMessage::Frame(_) => unimplemented!(),
     }
 }
@@ -1034,6 +1384,37 @@ mod tests {
         assert_eq!(upgraded_again.config.max_message_size, Some(512));
     }
 
+    #[tokio::test]
+    async fn never_accepts_unsupported_compression() {
+        let (upgrade, mut request) = build_valid_upgrade().await;
+        assert!(upgrade.offered_extensions().is_empty());
+        drop(upgrade);
+
+        request.headers_mut().insert(
+            header::SEC_WEBSOCKET_EXTENSIONS,
+            hyper::header::HeaderValue::from_static(
+                "permessage-deflate; client_max_window_bits, x-webkit-deflate-frame",
+            ),
+        );
+        let on_upgrade = hyper::upgrade::on(&mut request);
+        request.extensions_mut().insert(on_upgrade);
+        request.extensions_mut().insert(create_executor());
+        let upgrade = WebSocketUpgrade::extract(&mut request).await.unwrap();
+        assert_eq!(
+            upgrade.offered_extensions(),
+            ["permessage-deflate", "x-webkit-deflate-frame"]
+        );
+        let mut response = Response::new(Body::empty());
+        upgrade
+            .on_upgrade(|_socket| async move {})
+            .respond_to(&request, &mut response)
+            .unwrap();
+        assert!(response
+            .headers()
+            .get(header::SEC_WEBSOCKET_EXTENSIONS)
+            .is_none());
+    }
+
     // NOTE: Direct WebSocket tests have been moved to hyper/tests/websocket.rs
     // where they can properly test through the full hyper upgrade flow.
 }
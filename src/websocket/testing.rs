@@ -0,0 +1,250 @@
+//! In-process WebSocket testing.
+//!
+//! [`connect`] performs the upgrade handshake against a [`Router`] without opening a socket and
+//! returns the client side of the connection. The server callback runs on a background thread
+//! over an in-memory duplex pipe, so the test can talk to it like a real client would:
+//!
+//! ```
+//! use futures_util::StreamExt;
+//! use skyzen::{routing::{CreateRouteNode, Route}, websocket::testing};
+//!
+//! # futures_lite::future::block_on(async {
+//! let router = Route::new(("/echo".ws(|mut socket| async move {
+//!     while let Some(Ok(message)) = socket.next().await {
+//!         if let Some(text) = message.into_text() {
+//!             let _ = socket.send_text(text).await;
+//!         }
+//!     }
+//! }),))
+//! .build();
+//!
+//! let mut client = testing::connect(&router, "/echo").await.unwrap();
+//! client.send_text("hello").await.unwrap();
+//! let reply = client.next().await.unwrap().unwrap();
+//! assert_eq!(reply.into_text().as_deref(), Some("hello"));
+//! # });
+//! ```
+//!
+//! Keep-alive pings are not sent on in-memory connections.
+
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex, PoisonError},
+    task::{Context, Poll},
+};
+
+use async_tungstenite::tungstenite::protocol::Role;
+use http_kit::{
+    header::{self, HeaderValue},
+    utils::{AsyncRead, AsyncWrite},
+    ws::WebSocketConfig,
+    Body, Endpoint, Method, Request, Response, StatusCode,
+};
+
+use super::{UpgradedIo, WebSocket};
+use crate::routing::Router;
+
+/// Capacity of each direction of the in-memory pipe.
+const PIPE_CAPACITY: usize = 64 * 1024;
+
+type Session = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// Perform a WebSocket handshake for `uri` against `router`.
+///
+/// # Errors
+///
+/// Returns the router's response if it did not switch protocols.
+///
+/// # Panics
+///
+/// Panics if `uri` is not a valid URI.
+pub async fn connect(router: &Router, uri: &str) -> Result<WebSocket, Response> {
+    let mut request = Request::new(Body::empty());
+    *request.uri_mut() = uri.parse().expect("invalid request URI");
+    connect_with(router, request).await
+}
+
+/// Perform a WebSocket handshake for `request` against `router`.
+///
+/// The handshake headers are filled in when `request` does not set them, so callers only need
+/// to add what their route inspects, such as `Sec-WebSocket-Protocol` or credentials.
+///
+/// # Errors
+///
+/// Returns the router's response if it did not switch protocols.
+pub async fn connect_with(router: &Router, mut request: Request) -> Result<WebSocket, Response> {
+    *request.method_mut() = Method::GET;
+    let headers = request.headers_mut();
+    for (name, value) in [
+        (header::CONNECTION, "upgrade"),
+        (header::UPGRADE, "websocket"),
+        (header::SEC_WEBSOCKET_VERSION, "13"),
+        (header::SEC_WEBSOCKET_KEY, "dGhlIHNhbXBsZSBub25jZQ=="),
+    ] {
+        headers
+            .entry(name)
+            .or_insert_with(|| HeaderValue::from_static(value));
+    }
+
+    let (client_reader, server_writer) = piper::pipe(PIPE_CAPACITY);
+    let (server_reader, client_writer) = piper::pipe(PIPE_CAPACITY);
+    let upgrade = MemoryUpgrade::new(MemoryIo {
+        reader: server_reader,
+        writer: server_writer,
+    });
+    request.extensions_mut().insert(upgrade.clone());
+
+    let response = match router.clone().respond(&mut request).await {
+        Ok(response) => response,
+        Err(error) => {
+            let mut response = Response::new(Body::empty());
+            *response.status_mut() = error.status();
+            return Err(response);
+        }
+    };
+    if response.status() != StatusCode::SWITCHING_PROTOCOLS {
+        return Err(response);
+    }
+
+    if let Some(session) = upgrade.take_session() {
+        std::thread::spawn(move || async_io::block_on(session));
+    }
+
+    let io = UpgradedIo::memory(MemoryIo {
+        reader: client_reader,
+        writer: client_writer,
+    });
+    Ok(WebSocket::from_raw_socket(io, Role::Client, WebSocketConfig::default()).await)
+}
+
+/// One end of an in-memory duplex connection.
+pub(crate) struct MemoryIo {
+    reader: piper::Reader,
+    writer: piper::Writer,
+}
+
+impl std::fmt::Debug for MemoryIo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MemoryIo").finish_non_exhaustive()
+    }
+}
+
+impl AsyncRead for MemoryIo {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.get_mut().reader).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for MemoryIo {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.get_mut().writer).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().writer).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().writer).poll_close(cx)
+    }
+}
+
+/// Request extension that replaces hyper's `OnUpgrade` for in-memory connections.
+///
+/// The upgrade responder takes the server end of the pipe and hands the session future back,
+/// so no executor is needed to accept the connection.
+#[derive(Clone)]
+pub(crate) struct MemoryUpgrade(Arc<Mutex<MemorySlot>>);
+
+struct MemorySlot {
+    io: Option<MemoryIo>,
+    session: Option<Session>,
+}
+
+impl std::fmt::Debug for MemoryUpgrade {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MemoryUpgrade").finish_non_exhaustive()
+    }
+}
+
+impl MemoryUpgrade {
+    fn new(io: MemoryIo) -> Self {
+        Self(Arc::new(Mutex::new(MemorySlot {
+            io: Some(io),
+            session: None,
+        })))
+    }
+
+    fn slot(&self) -> std::sync::MutexGuard<'_, MemorySlot> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Take the server end of the pipe, if it has not been accepted yet.
+    pub(crate) fn take_io(&self) -> Option<MemoryIo> {
+        self.slot().io.take()
+    }
+
+    /// Hand the server session back to [`connect_with`].
+    pub(crate) fn set_session(&self, session: Session) {
+        self.slot().session = Some(session);
+    }
+
+    fn take_session(&self) -> Option<Session> {
+        self.slot().session.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::StreamExt;
+
+    use crate::{
+        routing::{CreateRouteNode, Route},
+        test::TestClient,
+        Result, StatusCode,
+    };
+
+    fn client() -> TestClient {
+        TestClient::new(
+            Route::new((
+                "/echo".ws(|mut socket| async move {
+                    while let Some(Ok(message)) = socket.next().await {
+                        if let Some(text) = message.into_text() {
+                            let _ = socket.send_text(text).await;
+                        }
+                    }
+                }),
+                "/plain".at(|| async { Result::Ok("not a socket") }),
+            ))
+            .build(),
+        )
+    }
+
+    #[tokio::test]
+    async fn echoes_messages() {
+        let mut socket = client().ws("/echo").await.expect("upgrade");
+        for text in ["hello", "world"] {
+            socket.send_text(text).await.unwrap();
+            let reply = socket.next().await.unwrap().unwrap();
+            assert_eq!(reply.into_text().as_deref(), Some(text));
+        }
+    }
+
+    #[tokio::test]
+    async fn returns_response_when_not_upgraded() {
+        let response = client().ws("/plain").await.expect_err("no upgrade");
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = client().ws("/missing").await.expect_err("no upgrade");
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -30,7 +30,7 @@ use wasm_bindgen::{prelude::*, JsCast};
 /// WebSocket connection for WASM targets.
 ///
 /// # Platform Notes
-/// - Maximum message size: 1 MiB (platform imposed)
+/// - Maximum message size: 1 MiB (platform imposed), or less with `max_message_size`
 /// - No ping/pong frame control (use `send_ping`/`send_pong` returns error)
 /// - Event-driven model converted to Stream
 pub struct WebSocket {
@@ -38,6 +38,7 @@ pub struct WebSocket {
     rx: UnboundedReceiver<WebSocketResult<WebSocketMessage>>,
     _closures: Rc<RefCell<EventClosures>>,
     config: WebSocketConfig,
+    close_frame: Rc<RefCell<Option<WebSocketCloseFrame>>>,
 }
 
 /// Holds the event handler closures to prevent them from being dropped.
@@ -50,31 +51,50 @@ struct EventClosures {
 impl WebSocket {
     pub(crate) fn from_ffi_socket(socket: ffi::WebSocket, config: WebSocketConfig) -> Self {
         let (tx, rx) = mpsc::unbounded();
+        let close_frame = Rc::new(RefCell::new(None));
 
         // Create event handlers
-        let closures = Self::setup_event_handlers(&socket, tx);
+        let closures =
+            Self::setup_event_handlers(&socket, tx, close_frame.clone(), config.max_message_size);
 
         Self {
             inner: socket,
             rx,
             _closures: Rc::new(RefCell::new(closures)),
             config,
+            close_frame,
         }
     }
 
     fn setup_event_handlers(
         socket: &ffi::WebSocket,
         tx: UnboundedSender<WebSocketResult<WebSocketMessage>>,
+        close_frame: Rc<RefCell<Option<WebSocketCloseFrame>>>,
+        max_message_size: Option<usize>,
     ) -> EventClosures {
         // Message handler
         let tx_message = tx.clone();
+        let message_socket = socket.clone();
         let on_message = Closure::wrap(Box::new(move |event: ffi::MessageEvent| {
             let data = event.data();
+            // The runtime only caps messages at 1 MiB, so smaller limits are enforced here.
+            let reject = |error: WebSocketError| {
+                message_socket.close(Some(MESSAGE_TOO_BIG), Some(MESSAGE_TOO_BIG_REASON));
+                let _ = tx_message.unbounded_send(Err(error));
+            };
 
             let message = if let Some(text) = data.as_string() {
+                if let Err(error) = check_message_size(text.len(), max_message_size) {
+                    reject(error);
+                    return;
+                }
                 WebSocketMessage::Text(text.into())
             } else if js_sys::Uint8Array::instanceof(&data) {
                 let array = js_sys::Uint8Array::from(data);
+                if let Err(error) = check_message_size(array.length() as usize, max_message_size) {
+                    reject(error);
+                    return;
+                }
                 let mut bytes = vec![0u8; array.length() as usize];
                 array.copy_to(&mut bytes);
                 WebSocketMessage::Binary(bytes.into())
@@ -88,7 +108,12 @@ impl WebSocket {
 
         // Close handler
         let tx_close = tx.clone();
-        let on_close = Closure::wrap(Box::new(move |_event: ffi::CloseEvent| {
+        let on_close = Closure::wrap(Box::new(move |event: ffi::CloseEvent| {
+            // 1005 means the peer sent no status code.
+            if event.code() != 1005 {
+                *close_frame.borrow_mut() =
+                    Some(WebSocketCloseFrame::new(event.code(), event.reason()));
+            }
             let _ = tx_close.unbounded_send(Ok(WebSocketMessage::Close));
         }) as Box<dyn FnMut(ffi::CloseEvent)>);
 
@@ -119,6 +144,7 @@ impl WebSocket {
     /// Send a raw text frame without JSON serialization.
     pub async fn send_text(&mut self, text: impl Into<ByteStr>) -> WebSocketResult<()> {
         let text = text.into();
+        check_message_size(text.len(), self.config.max_message_size)?;
         self.inner
             .send(&JsValue::from_str(&text))
             .map_err(|e| WebSocketError::Protocol(format!("{:?}", e)))
@@ -127,6 +153,7 @@ impl WebSocket {
     /// Send raw binary data without JSON serialization.
     pub async fn send_binary(&mut self, data: impl Into<Vec<u8>>) -> WebSocketResult<()> {
         let bytes = data.into();
+        check_message_size(bytes.len(), self.config.max_message_size)?;
         let array = js_sys::Uint8Array::from(&bytes[..]);
         self.inner
             .send(&array.into())
@@ -194,6 +221,14 @@ impl WebSocket {
         &self.config
     }
 
+    /// The close code and reason sent by the peer.
+    ///
+    /// Populated from the `CloseEvent` once [`WebSocketMessage::Close`] has been received.
+    #[must_use]
+    pub fn close_reason(&self) -> Option<WebSocketCloseFrame> {
+        self.close_frame.borrow().clone()
+    }
+
     /// Close the websocket connection gracefully.
     pub async fn close(&mut self, close_frame: Option<WebSocketCloseFrame>) -> WebSocketResult<()> {
         if let Some(frame) = close_frame {
@@ -224,11 +259,23 @@ impl WebSocket {
                 rx: self.rx,
                 config,
                 _closures: closures,
+                close_frame: self.close_frame,
             },
         )
     }
 }
 
+/// Close code for messages larger than the configured limit.
+const MESSAGE_TOO_BIG: u16 = 1009;
+const MESSAGE_TOO_BIG_REASON: &str = "message exceeds limit";
+
+fn check_message_size(len: usize, max_message_size: Option<usize>) -> WebSocketResult<()> {
+    match max_message_size {
+        Some(max) if len > max => Err(WebSocketError::Protocol(MESSAGE_TOO_BIG_REASON.into())),
+        _ => Ok(()),
+    }
+}
+
 impl std::fmt::Debug for WebSocket {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("WebSocket").finish_non_exhaustive()
@@ -264,6 +311,7 @@ impl WebSocketSender {
     /// Send a raw text frame without JSON serialization.
     pub async fn send_text(&mut self, text: impl Into<ByteStr>) -> WebSocketResult<()> {
         let text = text.into();
+        check_message_size(text.len(), self.config.max_message_size)?;
         self.inner
             .send(&JsValue::from_str(&text))
             .map_err(|e| WebSocketError::Protocol(format!("{:?}", e)))
@@ -272,6 +320,7 @@ impl WebSocketSender {
     /// Send raw binary data without JSON serialization.
     pub async fn send_binary(&mut self, data: impl Into<Vec<u8>>) -> WebSocketResult<()> {
         let bytes = data.into();
+        check_message_size(bytes.len(), self.config.max_message_size)?;
         let array = js_sys::Uint8Array::from(&bytes[..]);
         self.inner
             .send(&array.into())
@@ -339,6 +388,7 @@ pub struct WebSocketReceiver {
     rx: UnboundedReceiver<WebSocketResult<WebSocketMessage>>,
     config: WebSocketConfig,
     _closures: Rc<RefCell<EventClosures>>,
+    close_frame: Rc<RefCell<Option<WebSocketCloseFrame>>>,
 }
 
 impl WebSocketReceiver {
@@ -369,6 +419,12 @@ impl WebSocketReceiver {
     pub fn get_config(&self) -> &WebSocketConfig {
         &self.config
     }
+
+    /// The close code and reason sent by the peer, see [`WebSocket::close_reason`].
+    #[must_use]
+    pub fn close_reason(&self) -> Option<WebSocketCloseFrame> {
+        self.close_frame.borrow().clone()
+    }
 }
 
 impl std::fmt::Debug for WebSocketReceiver {
@@ -468,13 +524,35 @@ impl WebSocketUpgrade {
     ///
     /// # Platform Notes
     /// - **Native**: Enforced by async-tungstenite
-    /// - **WASM**: 1 MiB limit enforced by runtime (this setting is advisory only)
+    /// - **WASM**: Enforced for incoming and outgoing messages; an oversized incoming message
+    ///   yields an error and closes the connection with code 1009. The runtime caps messages at
+    ///   1 MiB regardless.
     #[must_use]
     pub fn max_message_size(mut self, max_size: Option<usize>) -> Self {
         self.config.max_message_size = max_size;
         self
     }
 
+    /// Send a ping when nothing has been written for `interval`.
+    ///
+    /// # Platform Notes
+    /// - **Native**: Pings are sent by a background task
+    /// - **WASM**: No-op, the runtime manages keep-alive itself
+    #[must_use]
+    pub const fn ping_interval(self, _interval: std::time::Duration) -> Self {
+        self
+    }
+
+    /// Close the connection when nothing has been received for `timeout`.
+    ///
+    /// # Platform Notes
+    /// - **Native**: Closes with code 1001 (going away)
+    /// - **WASM**: No-op, the runtime manages keep-alive itself
+    #[must_use]
+    pub const fn idle_timeout(self, _timeout: std::time::Duration) -> Self {
+        self
+    }
+
     /// Finalize the handshake and start handling the upgraded socket with `callback`.
     pub fn on_upgrade<F, Fut>(mut self, callback: F) -> WebSocketUpgradeResponder
     where
@@ -619,3 +697,98 @@ impl Responder for WebSocketUpgradeResponder {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use futures_util::StreamExt;
+    use http_kit::ws::{WebSocketConfig, WebSocketMessage};
+    use wasm_bindgen::{JsCast, JsValue};
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::{ffi, WebSocket, MESSAGE_TOO_BIG};
+    use crate::websocket::types::WebSocketError;
+
+    /// A stand-in for the runtime's server socket that records what the backend does with it.
+    fn fake_socket() -> JsValue {
+        js_sys::Function::new_no_args(
+            "return {
+                listeners: {},
+                sent: [],
+                closed: null,
+                accept() {},
+                send(data) { this.sent.push(data); },
+                close(code, reason) { this.closed = [code, reason]; },
+                addEventListener(type, handler) { this.listeners[type] = handler; },
+                removeEventListener() {},
+                deliver(data) { this.listeners.message({ data }); },
+            };",
+        )
+        .call0(&JsValue::NULL)
+        .unwrap()
+    }
+
+    fn deliver(fake: &JsValue, data: &JsValue) {
+        let deliver: js_sys::Function = js_sys::Reflect::get(fake, &"deliver".into())
+            .unwrap()
+            .unchecked_into();
+        deliver.call1(fake, data).unwrap();
+    }
+
+    fn get(fake: &JsValue, key: &str) -> JsValue {
+        js_sys::Reflect::get(fake, &key.into()).unwrap()
+    }
+
+    fn limited_socket(fake: &JsValue) -> WebSocket {
+        WebSocket::from_ffi_socket(
+            fake.clone().unchecked_into::<ffi::WebSocket>(),
+            WebSocketConfig::default().with_max_message_size(Some(4)),
+        )
+    }
+
+    #[wasm_bindgen_test]
+    async fn closes_on_incoming_messages_over_the_limit() {
+        let fake = fake_socket();
+        let mut socket = limited_socket(&fake);
+
+        deliver(&fake, &js_sys::Uint8Array::from(&[1u8, 2, 3, 4][..]).into());
+        assert!(matches!(
+            socket.next().await,
+            Some(Ok(WebSocketMessage::Binary(bytes))) if bytes.len() == 4
+        ));
+        assert!(get(&fake, "closed").is_null());
+
+        deliver(
+            &fake,
+            &js_sys::Uint8Array::from(&[1u8, 2, 3, 4, 5][..]).into(),
+        );
+        assert!(matches!(
+            socket.next().await,
+            Some(Err(WebSocketError::Protocol(message))) if message == "message exceeds limit"
+        ));
+        let closed: js_sys::Array = get(&fake, "closed").unchecked_into();
+        assert_eq!(closed.get(0), JsValue::from(MESSAGE_TOO_BIG));
+        assert_eq!(closed.get(1), JsValue::from("message exceeds limit"));
+
+        let fake = fake_socket();
+        let mut socket = limited_socket(&fake);
+        deliver(&fake, &"hello".into());
+        assert!(matches!(socket.next().await, Some(Err(_))));
+        assert!(!get(&fake, "closed").is_null());
+    }
+
+    #[wasm_bindgen_test]
+    async fn refuses_to_send_messages_over_the_limit() {
+        let fake = fake_socket();
+        let (mut sender, _receiver) = limited_socket(&fake).split();
+
+        assert!(sender.send_binary(vec![0; 5]).await.is_err());
+        assert!(sender.send_text("hello").await.is_err());
+        let sent: js_sys::Array = get(&fake, "sent").unchecked_into();
+        assert_eq!(sent.length(), 0);
+
+        sender.send_binary(vec![0; 4]).await.unwrap();
+        sender.send_text("hi").await.unwrap();
+        assert_eq!(sent.length(), 2);
+        assert!(get(&fake, "closed").is_null());
+    }
+}
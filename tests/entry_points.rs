@@ -0,0 +1,32 @@
+//! `#[skyzen::main]` and `#[skyzen::scheduled]` can share a crate root: the handlers keep their
+//! names, and on wasm the macros emit the `fetch` and `scheduled` exports side by side.
+
+#![allow(dead_code)]
+
+use skyzen::{
+    routing::{CreateRouteNode, Route, Router},
+    Result,
+};
+
+#[skyzen::main(default_logger = false)]
+fn app() -> Router {
+    Route::new(("/".at(|| async { Result::Ok("ok") }),)).build()
+}
+
+#[cfg(target_arch = "wasm32")]
+use skyzen::{extract::WorkerEnv, runtime::wasm::ScheduledEvent};
+
+#[skyzen::scheduled]
+async fn scheduled(event: ScheduledEvent, env: WorkerEnv) -> std::result::Result<(), String> {
+    let _ = env.secret("API_TOKEN");
+    if event.cron.is_empty() {
+        return Err("missing cron expression".to_owned());
+    }
+    Ok(())
+}
+
+#[test]
+fn handlers_keep_their_names() {
+    let router: fn() -> Router = app;
+    let _ = router();
+}
@@ -0,0 +1,161 @@
+//! Runtime behaviour of `#[skyzen::error]`.
+
+use std::{error::Error, fmt, io};
+
+use skyzen::{header, openapi::ErrorResponses, BoxHttpError, ErrorHeaders, HttpError, StatusCode};
+
+#[skyzen::error(status = NOT_FOUND)]
+enum UserError {
+    #[error("user {0} not found")]
+    NotFound(u64),
+    #[error("user {name:?} is {state}")]
+    Blocked { name: String, state: &'static str },
+    #[error("invalid range {1}..{0}", status = BAD_REQUEST)]
+    Range(u32, u32),
+    #[error("literal {{braces}}")]
+    Literal,
+}
+
+#[skyzen::error(status = CONFLICT, message = "name {0} is taken")]
+struct Taken(&'static str);
+
+#[derive(Debug)]
+struct ParseFailure;
+
+impl fmt::Display for ParseFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("parse failure")
+    }
+}
+
+impl Error for ParseFailure {}
+
+#[skyzen::error]
+enum StorageError {
+    #[error("io failed")]
+    Io(#[from] io::Error),
+    #[error("decode failed at byte {offset}")]
+    Decode {
+        #[source]
+        cause: ParseFailure,
+        offset: usize,
+    },
+    #[error("row {0} is corrupt")]
+    Corrupt(usize, #[source] ParseFailure),
+    #[error("unavailable")]
+    Unavailable,
+}
+
+#[skyzen::error(status = BAD_GATEWAY, message = "upstream failed")]
+struct UpstreamError {
+    #[source]
+    cause: io::Error,
+}
+
+#[skyzen::error(status = TOO_MANY_REQUESTS, header("Cache-Control" = "no-store"))]
+enum LimitError {
+    #[error("slow down", header("retry-after" = "30"))]
+    Throttled,
+    #[error("quota exceeded", status = FORBIDDEN)]
+    Quota,
+}
+
+#[skyzen::error(
+    status = UNAUTHORIZED,
+    message = "login required",
+    header("www-authenticate" = "Basic"),
+    header("www-authenticate" = "Bearer")
+)]
+struct LoginRequired;
+
+fn read() -> Result<(), StorageError> {
+    Err(io::Error::other("disk gone"))?;
+    Ok(())
+}
+
+#[test]
+fn tuple_variant_interpolates_positional_fields() {
+    assert_eq!(UserError::NotFound(7).to_string(), "user 7 not found");
+    assert_eq!(UserError::Range(1, 9).to_string(), "invalid range 9..1");
+    assert_eq!(UserError::Range(1, 9).status(), StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn struct_variant_interpolates_named_fields() {
+    let error = UserError::Blocked {
+        name: "ferris".to_owned(),
+        state: "suspended",
+    };
+    assert_eq!(error.to_string(), "user \"ferris\" is suspended");
+}
+
+#[test]
+fn escaped_braces_render_literally() {
+    assert_eq!(UserError::Literal.to_string(), "literal {braces}");
+}
+
+#[test]
+fn struct_message_interpolates_fields() {
+    assert_eq!(Taken("admin").to_string(), "name admin is taken");
+}
+
+#[test]
+fn openapi_description_keeps_template() {
+    let descriptions: Vec<_> = UserError::openapi_responses()
+        .into_iter()
+        .map(|response| response.description)
+        .collect();
+    assert_eq!(descriptions[0], Some("user {0} not found"));
+    assert_eq!(
+        Taken::openapi_responses()[0].description,
+        Some("name {0} is taken")
+    );
+}
+
+#[test]
+fn from_variant_exposes_source_after_question_mark() {
+    let error = read().unwrap_err();
+    let source = error.source().expect("io source");
+    let io = source.downcast_ref::<io::Error>().expect("io::Error");
+    assert_eq!(io.to_string(), "disk gone");
+}
+
+#[test]
+fn source_fields_are_returned() {
+    let decode = StorageError::Decode {
+        cause: ParseFailure,
+        offset: 3,
+    };
+    assert!(decode.source().unwrap().is::<ParseFailure>());
+    let corrupt = StorageError::Corrupt(4, ParseFailure);
+    assert!(corrupt.source().unwrap().is::<ParseFailure>());
+    assert!(StorageError::Unavailable.source().is_none());
+}
+
+#[test]
+fn struct_source_field_is_returned() {
+    let error = UpstreamError {
+        cause: io::Error::other("reset"),
+    };
+    assert!(error.source().unwrap().is::<io::Error>());
+    assert!(UserError::NotFound(1).source().is_none());
+}
+
+#[test]
+fn header_arguments_are_exposed_and_kept_when_boxed() {
+    let throttled = LimitError::Throttled.headers().unwrap();
+    assert_eq!(throttled[header::RETRY_AFTER], "30");
+    assert_eq!(throttled[header::CACHE_CONTROL], "no-store");
+    let quota = LimitError::Quota.headers().unwrap();
+    assert_eq!(quota.len(), 1);
+
+    let boxed: BoxHttpError = LoginRequired.into();
+    assert_eq!(boxed.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(boxed.to_string(), "login required");
+    let challenges = (*boxed).headers().unwrap();
+    let challenges: Vec<_> = challenges
+        .get_all(header::WWW_AUTHENTICATE)
+        .iter()
+        .collect();
+    assert_eq!(challenges, ["Basic", "Bearer"]);
+}
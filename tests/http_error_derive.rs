@@ -0,0 +1,57 @@
+//! Runtime behaviour of `#[derive(HttpError)]`.
+
+use skyzen::{HttpError, StatusCode};
+
+#[skyzen::error(status = NOT_FOUND, message = "missing")]
+struct Missing;
+
+#[derive(Debug, HttpError)]
+#[status = 410]
+struct Gone;
+
+#[derive(Debug, HttpError)]
+#[status(transparent)]
+struct Wrapped(Missing);
+
+#[derive(Debug, HttpError)]
+enum AppError {
+    #[status = 400]
+    BadRequest,
+    #[status(transparent)]
+    Missing(Missing),
+    Internal,
+}
+
+macro_rules! impl_error {
+    ($($ty:ty),*) => {$(
+        impl std::fmt::Display for $ty {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                std::fmt::Debug::fmt(self, f)
+            }
+        }
+
+        impl std::error::Error for $ty {}
+    )*};
+}
+
+impl_error!(Gone, Wrapped, AppError);
+
+#[test]
+fn struct_uses_declared_status() {
+    assert_eq!(Gone.status(), StatusCode::GONE);
+}
+
+#[test]
+fn transparent_struct_forwards_inner_status() {
+    assert_eq!(Wrapped(Missing).status(), StatusCode::NOT_FOUND);
+}
+
+#[test]
+fn enum_variants_map_to_statuses() {
+    assert_eq!(AppError::BadRequest.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(AppError::Missing(Missing).status(), StatusCode::NOT_FOUND);
+    assert_eq!(
+        AppError::Internal.status(),
+        StatusCode::INTERNAL_SERVER_ERROR
+    );
+}
@@ -0,0 +1,114 @@
+//! Startup and shutdown hooks run around the native server's accept loop, in registration order.
+
+#![cfg(all(not(target_arch = "wasm32"), feature = "rt"))]
+
+use std::{
+    io,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+use skyzen::{
+    routing::{CreateRouteNode, Route, Router},
+    runtime::{lifecycle::LifecycleContext, native::ServerBuilder},
+    Result,
+};
+use smol::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+    Executor,
+};
+
+type Events = Arc<Mutex<Vec<String>>>;
+
+fn router(events: &Events) -> Router {
+    let events = Arc::clone(events);
+    Route::new(("/".at(move || {
+        let events = Arc::clone(&events);
+        async move {
+            events.lock().unwrap().push("request".to_owned());
+            Result::Ok("ok")
+        }
+    }),))
+    .build()
+}
+
+fn record(
+    events: &Events,
+    name: &'static str,
+) -> impl FnOnce(LifecycleContext) -> std::future::Ready<std::result::Result<(), io::Error>> {
+    let events = Arc::clone(events);
+    move |context| {
+        let port = context.addr().map_or(0, |addr| addr.port());
+        events.lock().unwrap().push(format!("{name} {port}"));
+        std::future::ready(Ok(()))
+    }
+}
+
+#[test]
+fn hooks_run_in_order_around_serving() {
+    let executor = Arc::new(Executor::new());
+    let events = Events::default();
+    smol::block_on(executor.run(async {
+        let (stop_tx, stop_rx) = async_channel::bounded::<()>(1);
+        let (bound_tx, bound_rx) = async_channel::bounded(1);
+        let server = executor.spawn(
+            ServerBuilder::new()
+                .default_addr("127.0.0.1:0".parse().unwrap())
+                .executor(Arc::clone(&executor))
+                .on_bound(move |addr| {
+                    let _ = bound_tx.try_send(addr);
+                })
+                .on_startup(record(&events, "first startup"))
+                .on_startup(record(&events, "second startup"))
+                .on_shutdown(record(&events, "first shutdown"))
+                .on_shutdown(|_context| async { Err("pool already closed") })
+                .on_shutdown(record(&events, "second shutdown"))
+                .graceful_shutdown(async move {
+                    let _ = stop_rx.recv().await;
+                })
+                .serve(router(&events)),
+        );
+
+        let addr: SocketAddr = bound_rx.recv().await.unwrap();
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = Vec::new();
+        let _ = stream.read_to_end(&mut response).await;
+        assert!(response.starts_with(b"HTTP/1.1 200 OK"));
+
+        stop_tx.send(()).await.unwrap();
+        server.await.unwrap();
+
+        let port = addr.port();
+        assert_eq!(
+            *events.lock().unwrap(),
+            [
+                format!("first startup {port}"),
+                format!("second startup {port}"),
+                "request".to_owned(),
+                format!("first shutdown {port}"),
+                format!("second shutdown {port}"),
+            ]
+        );
+    }));
+}
+
+#[test]
+fn failing_startup_hooks_abort_the_launch() {
+    let events = Events::default();
+    let error = smol::block_on(
+        ServerBuilder::new()
+            .default_addr("127.0.0.1:0".parse().unwrap())
+            .on_startup(|_context| async { Err("migrations failed") })
+            .on_startup(record(&events, "second startup"))
+            .on_shutdown(record(&events, "shutdown"))
+            .serve(router(&events)),
+    )
+    .unwrap_err();
+    assert!(error.to_string().contains("migrations failed"), "{error}");
+    assert!(events.lock().unwrap().is_empty());
+}
@@ -0,0 +1,26 @@
+//! Compile-fail tests for the diagnostics of skyzen's macros and handler traits.
+
+#[cfg(all(feature = "openapi", not(target_arch = "wasm32")))]
+#[test]
+fn macro_errors() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/ui/openapi_*.rs");
+    cases.compile_fail("tests/ui/error_*.rs");
+    cases.compile_fail("tests/ui/http_error_*.rs");
+    cases.compile_fail("tests/ui/main_*.rs");
+    cases.compile_fail("tests/ui/handler_*.rs");
+}
+
+// rustc lists the types implementing `ToSchema` in these diagnostics, and the `cbor` and
+// `msgpack` features add their payload types to that list.
+#[cfg(all(
+    feature = "openapi",
+    not(feature = "cbor"),
+    not(feature = "msgpack"),
+    not(target_arch = "wasm32")
+))]
+#[test]
+fn schema_errors() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/ui/schema_*.rs");
+}
@@ -0,0 +1,20 @@
+//! `#[skyzen::main]` sizes the executor from `worker_threads` (`0` means one per CPU) and
+//! `blocking_threads` without changing the handler it wraps.
+
+#![allow(dead_code)]
+
+use skyzen::{
+    routing::{CreateRouteNode, Route, Router},
+    Result,
+};
+
+#[skyzen::main(default_logger = false, worker_threads = 0, blocking_threads = 16)]
+fn app() -> Router {
+    Route::new(("/".at(|| async { Result::Ok("ok") }),)).build()
+}
+
+#[test]
+fn handler_keeps_its_name() {
+    let router: fn() -> Router = app;
+    let _ = router();
+}
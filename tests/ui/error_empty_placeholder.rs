@@ -0,0 +1,4 @@
+#[skyzen::error(status = NOT_FOUND, message = "missing {}")]
+struct Missing(u64);
+
+fn main() {}
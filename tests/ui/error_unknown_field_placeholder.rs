@@ -0,0 +1,9 @@
+#[skyzen::error(status = NOT_FOUND)]
+enum LookupError {
+    #[error("user {id} not found")]
+    NotFound { user_id: u64 },
+    #[error("row {1} missing")]
+    Missing(u64),
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use skyzen::{
+    routing::{CreateRouteNode, Route},
+    Result,
+};
+
+struct NotAnExtractor;
+
+fn main() {
+    let _ = Route::new(("/".at(|_arg: NotAnExtractor| async { Result::Ok("shown") }),)).build();
+}
@@ -0,0 +1,19 @@
+#[skyzen::error(status = NOT_FOUND, message = "missing")]
+struct Missing;
+
+#[derive(Debug, skyzen::HttpError)]
+enum AppError {
+    #[status = 404]
+    #[status(transparent)]
+    Missing(Missing),
+}
+
+impl core::fmt::Display for AppError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("app error")
+    }
+}
+
+impl core::error::Error for AppError {}
+
+fn main() {}
@@ -0,0 +1,15 @@
+#[derive(Debug, skyzen::HttpError)]
+enum AppError<E: core::fmt::Debug + Send + Sync + 'static> {
+    #[status(transparent)]
+    Inner(E),
+}
+
+impl<E: core::fmt::Debug + Send + Sync + 'static> core::fmt::Display for AppError<E> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("app error")
+    }
+}
+
+impl<E: core::fmt::Debug + Send + Sync + 'static> core::error::Error for AppError<E> {}
+
+fn main() {}
@@ -0,0 +1,6 @@
+#[skyzen::main(worker_threads = 4, blocking_threads = 0)]
+fn app() -> skyzen::routing::Router {
+    skyzen::Route::new(()).build()
+}
+
+fn main() {}
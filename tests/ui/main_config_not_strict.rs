@@ -0,0 +1,6 @@
+#[skyzen::main(config = lenient)]
+fn app() -> skyzen::routing::Router {
+    skyzen::Route::new(()).build()
+}
+
+fn main() {}
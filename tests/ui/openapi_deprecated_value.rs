@@ -0,0 +1,6 @@
+#[skyzen::openapi(deprecated = true)]
+async fn list_users() -> skyzen::Result<&'static str> {
+    Ok("[]")
+}
+
+fn main() {}
@@ -0,0 +1,6 @@
+#[skyzen::openapi(operation_id = "listUsers", operation_id = "allUsers")]
+async fn list_users() -> skyzen::Result<&'static str> {
+    Ok("[]")
+}
+
+fn main() {}
@@ -0,0 +1,6 @@
+#[skyzen::openapi(summary = 42)]
+async fn list_users() -> skyzen::Result<&'static str> {
+    Ok("[]")
+}
+
+fn main() {}
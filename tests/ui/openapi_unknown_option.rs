@@ -0,0 +1,6 @@
+#[skyzen::openapi(tag = "users", description = "List users")]
+async fn list_users() -> skyzen::Result<&'static str> {
+    Ok("[]")
+}
+
+fn main() {}
@@ -0,0 +1,6 @@
+struct Problem;
+
+#[skyzen::error(status = BAD_REQUEST, message = "Bad request", schema = Problem)]
+struct BadRequest;
+
+fn main() {}